@@ -7,10 +7,14 @@ use {
         rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
         rpc_filter::{Memcmp, MemcmpEncodedBytes, MemcmpEncoding, RpcFilterType},
     },
-    solana_program::{borsh::try_from_slice_unchecked, program_pack::Pack, pubkey::Pubkey, stake},
+    solana_program::{borsh::try_from_slice_unchecked, pubkey::Pubkey, stake},
     spl_stake_pool::{
-        find_withdraw_authority_program_address,
-        state::{StakePool, ValidatorList},
+        find_token_metadata_program_address, find_withdraw_authority_program_address,
+        state::{PendingRedelegation, StakePool, TokenMetadata, ValidatorList, WithdrawTicket},
+    },
+    spl_token_2022::{
+        extension::StateWithExtensions,
+        state::{Account, Mint},
     },
     std::collections::HashSet,
 };
@@ -37,14 +41,58 @@ pub fn get_validator_list(
     Ok(validator_list)
 }
 
+pub fn get_withdraw_ticket(
+    rpc_client: &RpcClient,
+    withdraw_ticket_address: &Pubkey,
+) -> Result<WithdrawTicket, Error> {
+    let account_data = rpc_client.get_account_data(withdraw_ticket_address)?;
+    let withdraw_ticket = try_from_slice_unchecked::<WithdrawTicket>(account_data.as_slice())
+        .map_err(|err| {
+            format!(
+                "Invalid withdrawal ticket {}: {}",
+                withdraw_ticket_address, err
+            )
+        })?;
+    if !withdraw_ticket.is_valid() {
+        return Err(format!(
+            "Account {} is not a withdrawal ticket",
+            withdraw_ticket_address
+        )
+        .into());
+    }
+    Ok(withdraw_ticket)
+}
+
+pub fn get_token_metadata(
+    rpc_client: &RpcClient,
+    pool_mint_address: &Pubkey,
+) -> Result<Option<TokenMetadata>, Error> {
+    let (token_metadata_address, _) =
+        find_token_metadata_program_address(&spl_stake_pool::id(), pool_mint_address);
+    let account = rpc_client
+        .get_account_with_commitment(&token_metadata_address, rpc_client.commitment())?
+        .value;
+    match account {
+        Some(account) if !account.data.is_empty() => {
+            let token_metadata = try_from_slice_unchecked::<TokenMetadata>(account.data.as_slice())
+                .map_err(|err| {
+                    format!("Invalid token metadata {}: {}", token_metadata_address, err)
+                })?;
+            Ok(Some(token_metadata))
+        }
+        _ => Ok(None),
+    }
+}
+
 pub fn get_token_account(
     rpc_client: &RpcClient,
     token_account_address: &Pubkey,
     expected_token_mint: &Pubkey,
-) -> Result<spl_token::state::Account, Error> {
+) -> Result<Account, Error> {
     let account_data = rpc_client.get_account_data(token_account_address)?;
-    let token_account = spl_token::state::Account::unpack_from_slice(account_data.as_slice())
-        .map_err(|err| format!("Invalid token account {}: {}", token_account_address, err))?;
+    let token_account = StateWithExtensions::<Account>::unpack(account_data.as_slice())
+        .map_err(|err| format!("Invalid token account {}: {}", token_account_address, err))?
+        .base;
 
     if token_account.mint != *expected_token_mint {
         Err(format!(
@@ -57,13 +105,11 @@ pub fn get_token_account(
     }
 }
 
-pub fn get_token_mint(
-    rpc_client: &RpcClient,
-    token_mint_address: &Pubkey,
-) -> Result<spl_token::state::Mint, Error> {
+pub fn get_token_mint(rpc_client: &RpcClient, token_mint_address: &Pubkey) -> Result<Mint, Error> {
     let account_data = rpc_client.get_account_data(token_mint_address)?;
-    let token_mint = spl_token::state::Mint::unpack_from_slice(account_data.as_slice())
-        .map_err(|err| format!("Invalid token mint {}: {}", token_mint_address, err))?;
+    let token_mint = StateWithExtensions::<Mint>::unpack(account_data.as_slice())
+        .map_err(|err| format!("Invalid token mint {}: {}", token_mint_address, err))?
+        .base;
 
     Ok(token_mint)
 }
@@ -121,6 +167,46 @@ pub(crate) fn get_stake_pools(
         })
 }
 
+pub(crate) fn get_pending_redelegations(
+    rpc_client: &RpcClient,
+    stake_pool_address: &Pubkey,
+) -> Result<Vec<(Pubkey, PendingRedelegation)>, ClientError> {
+    rpc_client
+        .get_program_accounts_with_config(
+            &spl_stake_pool::id(),
+            RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::Memcmp(Memcmp {
+                        offset: 0, // 0 is the account type
+                        bytes: MemcmpEncodedBytes::Base58("6".to_string()),
+                        encoding: None,
+                    }),
+                    RpcFilterType::Memcmp(Memcmp {
+                        offset: 1, // 1 is the stake pool
+                        bytes: MemcmpEncodedBytes::Base58(stake_pool_address.to_string()),
+                        encoding: None,
+                    }),
+                ]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            },
+        )
+        .map(|accounts| {
+            accounts
+                .into_iter()
+                .filter_map(|(address, account)| {
+                    try_from_slice_unchecked::<PendingRedelegation>(account.data.as_slice())
+                        .ok()
+                        .filter(|pending_redelegation| pending_redelegation.is_valid())
+                        .map(|pending_redelegation| (address, pending_redelegation))
+                })
+                .collect()
+        })
+}
+
 pub(crate) fn get_all_stake(
     rpc_client: &RpcClient,
     authorized_staker: &Pubkey,
@@ -4,7 +4,11 @@ mod output;
 use {
     crate::{
         client::*,
-        output::{CliStakePool, CliStakePoolDetails, CliStakePoolStakeAccountInfo, CliStakePools},
+        output::{
+            CliStakePool, CliStakePoolDetails, CliStakePoolEpochYield,
+            CliStakePoolStakeAccountInfo, CliStakePoolStats, CliStakePoolValidatorStats,
+            CliStakePools,
+        },
     },
     clap::{
         crate_description, crate_name, crate_version, value_t, value_t_or_exit, App, AppSettings,
@@ -19,9 +23,14 @@ use {
         keypair::{signer_from_path_with_config, SignerFromPathConfig},
     },
     solana_cli_output::OutputFormat,
-    solana_client::rpc_client::RpcClient,
+    solana_client::{
+        rpc_client::RpcClient,
+        rpc_request::MAX_MULTIPLE_ACCOUNTS,
+        rpc_response::{RpcInflationReward, StakeActivationState},
+    },
     solana_program::{
         borsh::{get_instance_packed_len, get_packed_len},
+        clock::Epoch,
         instruction::Instruction,
         program_pack::Pack,
         pubkey::Pubkey,
@@ -29,6 +38,7 @@ use {
     },
     solana_remote_wallet::remote_wallet::RemoteWalletManager,
     solana_sdk::{
+        clock,
         commitment_config::CommitmentConfig,
         hash::Hash,
         message::Message,
@@ -38,21 +48,28 @@ use {
         system_instruction,
         transaction::Transaction,
     },
-    spl_associated_token_account::get_associated_token_address,
+    spl_associated_token_account::{
+        get_associated_token_address_with_program_id, instruction::create_associated_token_account,
+    },
     spl_stake_pool::state::ValidatorStakeInfo,
     spl_stake_pool::{
         self, find_stake_program_address, find_transient_stake_program_address,
         find_withdraw_authority_program_address,
         instruction::{FundingType, PreferredValidatorType},
-        state::{Fee, FeeType, StakePool, ValidatorList},
+        state::{
+            Fee, FeeType, PendingRedelegation, StakePool, StakeStatus, TokenMetadata,
+            ValidatorList, WithdrawTicket,
+        },
         MINIMUM_ACTIVE_STAKE,
     },
-    std::cmp::Ordering,
-    std::{process::exit, sync::Arc},
+    spl_stake_pool_rebalancer::{
+        JsonFileScores, RebalanceConfig, RebalanceDirection, RebalancePlan, ScoreSource,
+        UniformScores,
+    },
+    spl_token_2022::extension::ExtensionType,
+    std::cmp::{Ordering, Reverse},
+    std::{collections::HashSet, process::exit, sync::Arc, thread, time::Duration},
 };
-// use instruction::create_associated_token_account once ATA 1.0.5 is released
-#[allow(deprecated)]
-use spl_associated_token_account::create_associated_token_account;
 
 pub(crate) struct Config {
     rpc_client: RpcClient,
@@ -233,6 +250,7 @@ fn command_create_pool(
     mint_keypair: Option<Keypair>,
     reserve_keypair: Option<Keypair>,
     unsafe_fees: bool,
+    token_program_id: Pubkey,
 ) -> CommandResult {
     if !unsafe_fees {
         check_stake_pool_fees(&epoch_fee, &withdrawal_fee, &deposit_fee)?;
@@ -256,7 +274,7 @@ fn command_create_pool(
         .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)?;
     let pool_fee_account_balance = config
         .rpc_client
-        .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)?;
+        .get_minimum_balance_for_rent_exemption(associated_token_account_len(&token_program_id))?;
     let stake_pool_account_lamports = config
         .rpc_client
         .get_minimum_balance_for_rent_exemption(get_packed_len::<StakePool>())?;
@@ -306,11 +324,11 @@ fn command_create_pool(
             &mint_keypair.pubkey(),
             mint_account_balance,
             spl_token::state::Mint::LEN as u64,
-            &spl_token::id(),
+            &token_program_id,
         ),
         // Initialize pool token mint account
-        spl_token::instruction::initialize_mint(
-            &spl_token::id(),
+        spl_token_2022::instruction::initialize_mint(
+            &token_program_id,
             &mint_keypair.pubkey(),
             &withdraw_authority,
             None,
@@ -322,6 +340,7 @@ fn command_create_pool(
         config,
         &mint_keypair.pubkey(),
         &config.manager.pubkey(),
+        &token_program_id,
         &mut instructions,
         &mut total_rent_free_balances,
     );
@@ -362,7 +381,7 @@ fn command_create_pool(
                 &reserve_keypair.pubkey(),
                 &mint_keypair.pubkey(),
                 &pool_fee_account,
-                &spl_token::id(),
+                &token_program_id,
                 deposit_authority.as_ref().map(|x| x.pubkey()),
                 epoch_fee,
                 withdrawal_fee,
@@ -596,6 +615,118 @@ fn command_decrease_validator_stake(
     Ok(())
 }
 
+fn command_redelegate(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    source_vote_account: &Pubkey,
+    destination_vote_account: &Pubkey,
+    amount: f64,
+) -> CommandResult {
+    let lamports = native_token::sol_to_lamports(amount);
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    let source_validator_stake_info = validator_list
+        .find(source_vote_account)
+        .ok_or("Source vote account not found in validator list")?;
+    let destination_validator_stake_info = validator_list
+        .find(destination_vote_account)
+        .ok_or("Destination vote account not found in validator list")?;
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.staker.as_ref()];
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::redelegate_with_vote(
+            &spl_stake_pool::id(),
+            &stake_pool,
+            stake_pool_address,
+            source_vote_account,
+            source_validator_stake_info.transient_seed_suffix_start,
+            destination_vote_account,
+            destination_validator_stake_info.transient_seed_suffix_start,
+            lamports,
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_rebalance(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    scores_path: Option<&str>,
+) -> CommandResult {
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    let reserve_lamports = config.rpc_client.get_balance(&stake_pool.reserve_stake)?;
+    let stake_rent = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(STAKE_STATE_LEN)?;
+
+    let score_source: Box<dyn ScoreSource> = match scores_path {
+        Some(path) => Box::new(JsonFileScores::new(path)),
+        None => Box::new(UniformScores),
+    };
+    let reserve_target_lamports = match &stake_pool.liquidity_buffer {
+        Some(liquidity_buffer) => liquidity_buffer
+            .target_lamports(stake_pool.total_lamports)
+            .ok_or("Unable to compute the liquidity buffer target")?,
+        None => 0,
+    }
+    // lamports owed to withdrawal tickets must be undelegated too
+    .saturating_add(stake_pool.withdraw_ticket_lamports);
+    let plan = RebalancePlan::new(
+        &validator_list,
+        score_source.as_ref(),
+        &RebalanceConfig {
+            reserve_target_lamports,
+            ..RebalanceConfig::new(reserve_lamports, stake_rent)
+        },
+    )?;
+
+    for vote_account_address in &plan.busy_validators {
+        println!(
+            "Skipping validator {}, its transient stake account is in use",
+            vote_account_address
+        );
+    }
+    if plan.is_empty() {
+        println!("Stake pool is already balanced");
+        return Ok(());
+    }
+    for action in &plan.actions {
+        let verb = match action.direction {
+            RebalanceDirection::Increase => "Increasing",
+            RebalanceDirection::Decrease => "Decreasing",
+        };
+        println!(
+            "{} stake on {} by {}, from {} towards {}",
+            verb,
+            action.vote_account_address,
+            Sol(action.lamports),
+            Sol(action.current_lamports),
+            Sol(action.target_lamports),
+        );
+    }
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.staker.as_ref()];
+    unique_signers!(signers);
+    for instruction in plan.instructions(&spl_stake_pool::id(), &stake_pool, stake_pool_address) {
+        let transaction = checked_transaction_with_signers(config, &[instruction], &signers)?;
+        send_transaction(config, transaction)?;
+    }
+    Ok(())
+}
+
 fn command_set_preferred_validator(
     config: &Config,
     stake_pool_address: &Pubkey,
@@ -621,28 +752,40 @@ fn command_set_preferred_validator(
     Ok(())
 }
 
+/// Token-2022 associated token accounts always have the immutable owner extension
+fn associated_token_account_len(token_program_id: &Pubkey) -> usize {
+    if *token_program_id == spl_token_2022::id() {
+        ExtensionType::get_account_len::<spl_token_2022::state::Account>(&[
+            ExtensionType::ImmutableOwner,
+        ])
+    } else {
+        spl_token::state::Account::LEN
+    }
+}
+
 fn add_associated_token_account(
     config: &Config,
     mint: &Pubkey,
     owner: &Pubkey,
+    token_program_id: &Pubkey,
     instructions: &mut Vec<Instruction>,
     rent_free_balances: &mut u64,
 ) -> Pubkey {
     // Account for tokens not specified, creating one
-    let account = get_associated_token_address(owner, mint);
+    let account = get_associated_token_address_with_program_id(owner, mint, token_program_id);
     if get_token_account(&config.rpc_client, &account, mint).is_err() {
         println!("Creating associated token account {} to receive stake pool tokens of mint {}, owned by {}", account, mint, owner);
 
         let min_account_balance = config
             .rpc_client
-            .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)
+            .get_minimum_balance_for_rent_exemption(associated_token_account_len(token_program_id))
             .unwrap();
 
-        #[allow(deprecated)]
         instructions.push(create_associated_token_account(
             &config.fee_payer.pubkey(),
             owner,
             mint,
+            token_program_id,
         ));
 
         *rent_free_balances += min_account_balance;
@@ -660,12 +803,22 @@ fn command_deposit_stake(
     withdraw_authority: Box<dyn Signer>,
     pool_token_receiver_account: &Option<Pubkey>,
     referrer_token_account: &Option<Pubkey>,
+    minimum_pool_tokens_out: Option<f64>,
 ) -> CommandResult {
     if !config.no_update {
         command_update(config, stake_pool_address, false, false)?;
     }
 
     let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let minimum_pool_tokens_out = if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+        let pool_mint = get_token_mint(&config.rpc_client, &stake_pool.pool_mint)?;
+        Some(spl_token::ui_amount_to_amount(
+            minimum_pool_tokens_out,
+            pool_mint.decimals,
+        ))
+    } else {
+        None
+    };
     let stake_state = get_stake_state(&config.rpc_client, stake)?;
 
     if config.verbose {
@@ -676,10 +829,22 @@ fn command_deposit_stake(
         _ => Err("Wrong stake account state, must be delegated to validator"),
     }?;
 
-    // Check if this vote account has staking account in the pool
+    // Stake on validators outside of the pool is redelegated by the staker
     let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
     if !validator_list.contains(&vote_account) {
-        return Err("Stake account for this validator does not exist in the pool.".into());
+        println!(
+            "Validator {} is not in the pool, the stake will be redelegated to a pool validator for a fee of {}",
+            vote_account, stake_pool.redelegation_deposit_fee
+        );
+        return deposit_stake_for_redelegation(
+            config,
+            stake_pool_address,
+            &stake_pool,
+            stake,
+            withdraw_authority,
+            pool_token_receiver_account,
+            minimum_pool_tokens_out,
+        );
     }
 
     // Calculate validator stake account address linked to the pool
@@ -706,6 +871,7 @@ fn command_deposit_stake(
             config,
             &stake_pool.pool_mint,
             &config.token_owner.pubkey(),
+            &stake_pool.token_program_id,
             &mut instructions,
             &mut total_rent_free_balances,
         ));
@@ -727,11 +893,47 @@ fn command_deposit_stake(
                 return Err(error.into());
             }
 
-            spl_stake_pool::instruction::deposit_stake_with_authority(
+            if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+                spl_stake_pool::instruction::deposit_stake_with_authority_and_slippage(
+                    &spl_stake_pool::id(),
+                    stake_pool_address,
+                    &stake_pool.validator_list,
+                    &stake_deposit_authority.pubkey(),
+                    &pool_withdraw_authority,
+                    stake,
+                    &withdraw_authority.pubkey(),
+                    &validator_stake_account,
+                    &stake_pool.reserve_stake,
+                    &pool_token_receiver_account,
+                    &stake_pool.manager_fee_account,
+                    &referrer_token_account,
+                    &stake_pool.pool_mint,
+                    &stake_pool.token_program_id,
+                    minimum_pool_tokens_out,
+                )
+            } else {
+                spl_stake_pool::instruction::deposit_stake_with_authority(
+                    &spl_stake_pool::id(),
+                    stake_pool_address,
+                    &stake_pool.validator_list,
+                    &stake_deposit_authority.pubkey(),
+                    &pool_withdraw_authority,
+                    stake,
+                    &withdraw_authority.pubkey(),
+                    &validator_stake_account,
+                    &stake_pool.reserve_stake,
+                    &pool_token_receiver_account,
+                    &stake_pool.manager_fee_account,
+                    &referrer_token_account,
+                    &stake_pool.pool_mint,
+                    &stake_pool.token_program_id,
+                )
+            }
+        } else if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+            spl_stake_pool::instruction::deposit_stake_with_slippage(
                 &spl_stake_pool::id(),
                 stake_pool_address,
                 &stake_pool.validator_list,
-                &stake_deposit_authority.pubkey(),
                 &pool_withdraw_authority,
                 stake,
                 &withdraw_authority.pubkey(),
@@ -741,7 +943,8 @@ fn command_deposit_stake(
                 &stake_pool.manager_fee_account,
                 &referrer_token_account,
                 &stake_pool.pool_mint,
-                &spl_token::id(),
+                &stake_pool.token_program_id,
+                minimum_pool_tokens_out,
             )
         } else {
             spl_stake_pool::instruction::deposit_stake(
@@ -757,7 +960,7 @@ fn command_deposit_stake(
                 &stake_pool.manager_fee_account,
                 &referrer_token_account,
                 &stake_pool.pool_mint,
-                &spl_token::id(),
+                &stake_pool.token_program_id,
             )
         };
 
@@ -779,6 +982,106 @@ fn command_deposit_stake(
     Ok(())
 }
 
+fn deposit_stake_for_redelegation(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    stake_pool: &StakePool,
+    stake: &Pubkey,
+    withdraw_authority: Box<dyn Signer>,
+    pool_token_receiver_account: &Option<Pubkey>,
+    minimum_pool_tokens_out: Option<u64>,
+) -> CommandResult {
+    let mut instructions: Vec<Instruction> = vec![];
+    let pending_redelegation = Keypair::new();
+    let mut signers = vec![
+        config.fee_payer.as_ref(),
+        withdraw_authority.as_ref(),
+        &pending_redelegation,
+    ];
+
+    let mut total_rent_free_balances = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(PendingRedelegation::LEN)?;
+
+    // Create token account if not specified
+    let pool_token_receiver_account =
+        pool_token_receiver_account.unwrap_or(add_associated_token_account(
+            config,
+            &stake_pool.pool_mint,
+            &config.token_owner.pubkey(),
+            &stake_pool.token_program_id,
+            &mut instructions,
+            &mut total_rent_free_balances,
+        ));
+
+    let stake_deposit_authority =
+        if let Some(stake_deposit_authority) = config.funding_authority.as_ref() {
+            signers.push(stake_deposit_authority.as_ref());
+            if stake_deposit_authority.pubkey() != stake_pool.stake_deposit_authority {
+                let error = format!(
+                    "Invalid deposit authority specified, expected {}, received {}",
+                    stake_pool.stake_deposit_authority,
+                    stake_deposit_authority.pubkey()
+                );
+                return Err(error.into());
+            }
+            Some(stake_deposit_authority.pubkey())
+        } else {
+            None
+        };
+
+    let pool_withdraw_authority =
+        find_withdraw_authority_program_address(&spl_stake_pool::id(), stake_pool_address).0;
+
+    instructions.push(system_instruction::create_account(
+        &config.fee_payer.pubkey(),
+        &pending_redelegation.pubkey(),
+        config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(PendingRedelegation::LEN)?,
+        PendingRedelegation::LEN as u64,
+        &spl_stake_pool::id(),
+    ));
+    instructions.append(
+        &mut spl_stake_pool::instruction::deposit_stake_for_redelegation(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &stake_pool.validator_list,
+            stake_deposit_authority.as_ref(),
+            &pool_withdraw_authority,
+            stake,
+            &withdraw_authority.pubkey(),
+            &pending_redelegation.pubkey(),
+            &config.fee_payer.pubkey(),
+            &pool_token_receiver_account,
+            &stake_pool.manager_fee_account,
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+            minimum_pool_tokens_out.unwrap_or(0),
+        ),
+    );
+
+    let recent_blockhash = get_latest_blockhash(&config.rpc_client)?;
+    let message = Message::new_with_blockhash(
+        &instructions,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(
+        config,
+        total_rent_free_balances + config.rpc_client.get_fee_for_message(&message)?,
+    )?;
+    unique_signers!(signers);
+    let transaction = Transaction::new(&signers, message, recent_blockhash);
+    send_transaction(config, transaction)?;
+    println!(
+        "Deposited stake {} pending redelegation, tracked by {}",
+        stake,
+        pending_redelegation.pubkey()
+    );
+    Ok(())
+}
+
 fn command_deposit_all_stake(
     config: &Config,
     stake_pool_address: &Pubkey,
@@ -802,6 +1105,7 @@ fn command_deposit_all_stake(
             config,
             &stake_pool.pool_mint,
             &config.token_owner.pubkey(),
+            &stake_pool.token_program_id,
             &mut create_token_account_instructions,
             &mut total_rent_free_balances,
         ));
@@ -884,7 +1188,7 @@ fn command_deposit_all_stake(
                 &stake_pool.manager_fee_account,
                 &referrer_token_account,
                 &stake_pool.pool_mint,
-                &spl_token::id(),
+                &stake_pool.token_program_id,
             )
         } else {
             spl_stake_pool::instruction::deposit_stake(
@@ -900,7 +1204,7 @@ fn command_deposit_all_stake(
                 &stake_pool.manager_fee_account,
                 &referrer_token_account,
                 &stake_pool.pool_mint,
-                &spl_token::id(),
+                &stake_pool.token_program_id,
             )
         };
 
@@ -924,6 +1228,7 @@ fn command_deposit_sol(
     pool_token_receiver_account: &Option<Pubkey>,
     referrer_token_account: &Option<Pubkey>,
     amount: f64,
+    minimum_pool_tokens_out: Option<f64>,
 ) -> CommandResult {
     if !config.no_update {
         command_update(config, stake_pool_address, false, false)?;
@@ -946,6 +1251,15 @@ fn command_deposit_sol(
     }
 
     let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let minimum_pool_tokens_out = if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+        let pool_mint = get_token_mint(&config.rpc_client, &stake_pool.pool_mint)?;
+        Some(spl_token::ui_amount_to_amount(
+            minimum_pool_tokens_out,
+            pool_mint.decimals,
+        ))
+    } else {
+        None
+    };
 
     let mut instructions: Vec<Instruction> = vec![];
 
@@ -971,6 +1285,7 @@ fn command_deposit_sol(
             config,
             &stake_pool.pool_mint,
             &config.token_owner.pubkey(),
+            &stake_pool.token_program_id,
             &mut instructions,
             &mut total_rent_free_balances,
         ));
@@ -994,10 +1309,42 @@ fn command_deposit_sol(
             return Err(error.into());
         }
 
-        spl_stake_pool::instruction::deposit_sol_with_authority(
+        if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+            spl_stake_pool::instruction::deposit_sol_with_authority_and_slippage(
+                &spl_stake_pool::id(),
+                stake_pool_address,
+                &deposit_authority.pubkey(),
+                &pool_withdraw_authority,
+                &stake_pool.reserve_stake,
+                &user_sol_transfer.pubkey(),
+                &pool_token_receiver_account,
+                &stake_pool.manager_fee_account,
+                &referrer_token_account,
+                &stake_pool.pool_mint,
+                &stake_pool.token_program_id,
+                amount,
+                minimum_pool_tokens_out,
+            )
+        } else {
+            spl_stake_pool::instruction::deposit_sol_with_authority(
+                &spl_stake_pool::id(),
+                stake_pool_address,
+                &deposit_authority.pubkey(),
+                &pool_withdraw_authority,
+                &stake_pool.reserve_stake,
+                &user_sol_transfer.pubkey(),
+                &pool_token_receiver_account,
+                &stake_pool.manager_fee_account,
+                &referrer_token_account,
+                &stake_pool.pool_mint,
+                &stake_pool.token_program_id,
+                amount,
+            )
+        }
+    } else if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+        spl_stake_pool::instruction::deposit_sol_with_slippage(
             &spl_stake_pool::id(),
             stake_pool_address,
-            &deposit_authority.pubkey(),
             &pool_withdraw_authority,
             &stake_pool.reserve_stake,
             &user_sol_transfer.pubkey(),
@@ -1005,8 +1352,9 @@ fn command_deposit_sol(
             &stake_pool.manager_fee_account,
             &referrer_token_account,
             &stake_pool.pool_mint,
-            &spl_token::id(),
+            &stake_pool.token_program_id,
             amount,
+            minimum_pool_tokens_out,
         )
     } else {
         spl_stake_pool::instruction::deposit_sol(
@@ -1019,7 +1367,7 @@ fn command_deposit_sol(
             &stake_pool.manager_fee_account,
             &referrer_token_account,
             &stake_pool.pool_mint,
-            &spl_token::id(),
+            &stake_pool.token_program_id,
             amount,
         )
     };
@@ -1052,6 +1400,7 @@ fn command_list(config: &Config, stake_pool_address: &Pubkey) -> CommandResult {
     let current_number_of_validators = validator_list.validators.len();
     let pool_mint = get_token_mint(&config.rpc_client, &stake_pool.pool_mint)?;
     let epoch_info = config.rpc_client.get_epoch_info()?;
+    let updated_validators = validator_list.updated_validators(epoch_info.epoch) as u32;
     let pool_withdraw_authority =
         find_withdraw_authority_program_address(&spl_stake_pool::id(), stake_pool_address).0;
     let reserve_stake = config.rpc_client.get_account(&stake_pool.reserve_stake)?;
@@ -1084,6 +1433,8 @@ fn command_list(config: &Config, stake_pool_address: &Pubkey) -> CommandResult {
                 validator_transient_stake_account_address: transient_stake_account_address
                     .to_string(),
                 validator_transient_stake_lamports: validator.transient_stake_lamports,
+                validator_max_stake_bps: validator.max_stake_bps,
+                validator_no_deposits: validator.no_deposits,
                 update_required,
             }
         })
@@ -1106,6 +1457,7 @@ fn command_list(config: &Config, stake_pool_address: &Pubkey) -> CommandResult {
         total_pool_tokens,
         current_number_of_validators: current_number_of_validators as u32,
         max_number_of_validators,
+        updated_validators,
         update_required,
     };
     cli_stake_pool.details = Some(cli_stake_pool_details);
@@ -1113,78 +1465,646 @@ fn command_list(config: &Config, stake_pool_address: &Pubkey) -> CommandResult {
     Ok(())
 }
 
-fn command_update(
-    config: &Config,
-    stake_pool_address: &Pubkey,
-    force: bool,
-    no_merge: bool,
-) -> CommandResult {
-    if config.no_update {
-        println!("Update requested, but --no-update flag specified, so doing nothing");
-        return Ok(());
-    }
-    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
-    let epoch_info = config.rpc_client.get_epoch_info()?;
-
-    if stake_pool.last_update_epoch == epoch_info.epoch {
-        if force {
-            println!("Update not required, but --force flag specified, so doing it anyway");
-        } else {
-            println!("Update not required");
-            return Ok(());
-        }
-    }
-
-    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+/// Number of recent performance samples used to estimate the slot time
+const PERFORMANCE_SAMPLES: usize = 60;
 
-    let (mut update_list_instructions, final_instructions) =
-        spl_stake_pool::instruction::update_stake_pool(
-            &spl_stake_pool::id(),
-            &stake_pool,
-            &validator_list,
-            stake_pool_address,
-            no_merge,
-        );
+/// Default number of past epochs included in the exchange rate history
+const DEFAULT_HISTORY_EPOCHS: u64 = 10;
 
-    let update_list_instructions_len = update_list_instructions.len();
-    if update_list_instructions_len > 0 {
-        let last_instruction = update_list_instructions.split_off(update_list_instructions_len - 1);
-        // send the first ones without waiting
-        for instruction in update_list_instructions {
-            let transaction = checked_transaction_with_signers(
-                config,
-                &[instruction],
-                &[config.fee_payer.as_ref()],
-            )?;
-            send_transaction_no_wait(config, transaction)?;
-        }
+fn lamports_per_pool_token(total_lamports: u64, pool_token_supply: u64) -> Option<f64> {
+    if total_lamports == 0 || pool_token_supply == 0 {
+        None
+    } else {
+        Some(total_lamports as f64 / pool_token_supply as f64)
+    }
+}
 
-        // wait on the last one
-        let transaction = checked_transaction_with_signers(
-            config,
-            &last_instruction,
-            &[config.fee_payer.as_ref()],
-        )?;
-        send_transaction(config, transaction)?;
+/// Yield of an epoch given the exchange rates at its start and end
+fn epoch_yield(start_rate: Option<f64>, end_rate: Option<f64>) -> Option<f64> {
+    match (start_rate, end_rate) {
+        (Some(start), Some(end)) => Some(end / start - 1.0),
+        _ => None,
     }
-    let transaction = checked_transaction_with_signers(
-        config,
-        &final_instructions,
-        &[config.fee_payer.as_ref()],
-    )?;
-    send_transaction(config, transaction)?;
+}
 
-    Ok(())
+/// Yield earned by pool token holders from the epoch rewards of the pool's
+/// stake accounts, after the pool's epoch fee
+fn rewards_epoch_yield(rewards: &[RpcInflationReward], epoch_fee: &Fee) -> Option<f64> {
+    let (reward_lamports, staked_lamports) =
+        rewards
+            .iter()
+            .fold((0u64, 0u64), |(amount, staked), reward| {
+                (
+                    amount.saturating_add(reward.amount),
+                    staked.saturating_add(reward.post_balance.saturating_sub(reward.amount)),
+                )
+            });
+    if staked_lamports == 0 {
+        return None;
+    }
+    let fee = if epoch_fee.denominator == 0 {
+        0.0
+    } else {
+        epoch_fee.numerator as f64 / epoch_fee.denominator as f64
+    };
+    Some(reward_lamports as f64 / staked_lamports as f64 * (1.0 - fee))
 }
 
-#[derive(PartialEq, Debug)]
-struct WithdrawAccount {
-    stake_address: Pubkey,
-    vote_address: Option<Pubkey>,
-    pool_amount: u64,
+/// Annual yield from compounding the average of the given epoch yields over
+/// a year of epochs
+fn apy(epoch_yields: &[f64], epochs_per_year: f64) -> Option<f64> {
+    if epoch_yields.is_empty() {
+        return None;
+    }
+    let growth = epoch_yields
+        .iter()
+        .fold(1.0, |growth, epoch_yield| growth * (1.0 + epoch_yield));
+    Some(growth.powf(epochs_per_year / epoch_yields.len() as f64) - 1.0)
 }
 
-fn sorted_accounts<F>(
+fn estimate_epochs_per_year(config: &Config, slots_in_epoch: u64) -> Result<f64, Error> {
+    let samples = config
+        .rpc_client
+        .get_recent_performance_samples(Some(PERFORMANCE_SAMPLES))?;
+    let (num_slots, sample_secs) = samples.iter().fold((0u64, 0u64), |(slots, secs), s| {
+        (slots + s.num_slots, secs + s.sample_period_secs as u64)
+    });
+    let secs_per_slot = if num_slots == 0 {
+        clock::DEFAULT_MS_PER_SLOT as f64 / 1_000.0
+    } else {
+        sample_secs as f64 / num_slots as f64
+    };
+    const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+    Ok(SECONDS_PER_YEAR / (secs_per_slot * slots_in_epoch as f64))
+}
+
+/// Exchange rate history of the pool, most recent epoch first.
+///
+/// The on-chain `last_epoch_*` values only give the yield of the epoch whose
+/// rewards were applied by the last update. Earlier epochs are rebuilt from
+/// the inflation rewards of the validator stake accounts, net of the current
+/// epoch fee, walking the exchange rate back from the rate before the last
+/// update.
+fn get_exchange_rate_history(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    stake_pool: &StakePool,
+    validator_list: &ValidatorList,
+    epochs: u64,
+) -> Result<Vec<CliStakePoolEpochYield>, Error> {
+    let mut history = vec![];
+    let last_rewards_epoch = match stake_pool.last_update_epoch.checked_sub(1) {
+        Some(epoch) if epochs > 0 => epoch,
+        _ => return Ok(history),
+    };
+    let mut end_rate =
+        lamports_per_pool_token(stake_pool.total_lamports, stake_pool.pool_token_supply);
+    let mut start_rate = lamports_per_pool_token(
+        stake_pool.last_epoch_total_lamports,
+        stake_pool.last_epoch_pool_token_supply,
+    );
+    history.push(CliStakePoolEpochYield {
+        epoch: last_rewards_epoch,
+        epoch_yield: epoch_yield(start_rate, end_rate),
+        lamports_per_pool_token: end_rate,
+    });
+
+    let stake_addresses: Vec<Pubkey> = validator_list
+        .validators
+        .iter()
+        .map(|validator| {
+            find_stake_program_address(
+                &spl_stake_pool::id(),
+                &validator.vote_account_address,
+                stake_pool_address,
+            )
+            .0
+        })
+        .collect();
+    let first_epoch = last_rewards_epoch.saturating_sub(epochs - 1);
+    for epoch in (first_epoch..last_rewards_epoch).rev() {
+        let mut rewards = vec![];
+        for addresses in stake_addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            rewards.extend(
+                config
+                    .rpc_client
+                    .get_inflation_reward(addresses, Some(epoch))?
+                    .into_iter()
+                    .flatten(),
+            );
+        }
+        let rewards_yield = rewards_epoch_yield(&rewards, &stake_pool.epoch_fee);
+        end_rate = start_rate;
+        start_rate = match (end_rate, rewards_yield) {
+            (Some(rate), Some(rewards_yield)) => Some(rate / (1.0 + rewards_yield)),
+            _ => None,
+        };
+        history.push(CliStakePoolEpochYield {
+            epoch,
+            epoch_yield: rewards_yield,
+            lamports_per_pool_token: end_rate,
+        });
+    }
+    Ok(history)
+}
+
+fn command_stats(config: &Config, stake_pool_address: &Pubkey, epochs: u64) -> CommandResult {
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    let epoch_info = config.rpc_client.get_epoch_info()?;
+    let reserve_stake = config.rpc_client.get_account(&stake_pool.reserve_stake)?;
+    let delinquent_vote_accounts: HashSet<String> = config
+        .rpc_client
+        .get_vote_accounts()?
+        .delinquent
+        .into_iter()
+        .map(|vote_account| vote_account.vote_pubkey)
+        .collect();
+
+    // `last_epoch_*` hold the pool's values just before the first update of
+    // `last_update_epoch`, so the ratio of the two exchange rates is the
+    // yield earned over the last completed epoch
+    let current_rate =
+        lamports_per_pool_token(stake_pool.total_lamports, stake_pool.pool_token_supply);
+    let last_epoch_rate = lamports_per_pool_token(
+        stake_pool.last_epoch_total_lamports,
+        stake_pool.last_epoch_pool_token_supply,
+    );
+    let history = get_exchange_rate_history(
+        config,
+        stake_pool_address,
+        &stake_pool,
+        &validator_list,
+        epochs,
+    )?;
+    let epoch_yields: Vec<f64> = history.iter().filter_map(|h| h.epoch_yield).collect();
+    let epochs_per_year = estimate_epochs_per_year(config, epoch_info.slots_in_epoch)?;
+    let apy = apy(&epoch_yields, epochs_per_year);
+
+    let validators: Vec<CliStakePoolValidatorStats> = validator_list
+        .validators
+        .iter()
+        .map(|validator| {
+            let stake_lamports = validator.stake_lamports();
+            let stake_share = if stake_pool.total_lamports == 0 {
+                0.0
+            } else {
+                stake_lamports as f64 / stake_pool.total_lamports as f64
+            };
+            CliStakePoolValidatorStats {
+                vote_account_address: validator.vote_account_address.to_string(),
+                stake_lamports,
+                active_stake_lamports: validator.active_stake_lamports,
+                transient_stake_lamports: validator.transient_stake_lamports,
+                stake_share,
+                last_update_epoch: validator.last_update_epoch,
+                delinquent: delinquent_vote_accounts
+                    .contains(&validator.vote_account_address.to_string()),
+                update_required: validator.last_update_epoch != epoch_info.epoch,
+            }
+        })
+        .collect();
+
+    let cli_stake_pool_stats = CliStakePoolStats {
+        address: stake_pool_address.to_string(),
+        epoch: epoch_info.epoch,
+        last_update_epoch: stake_pool.last_update_epoch,
+        update_required: stake_pool.last_update_epoch != epoch_info.epoch,
+        total_lamports: stake_pool.total_lamports,
+        pool_token_supply: stake_pool.pool_token_supply,
+        last_epoch_total_lamports: stake_pool.last_epoch_total_lamports,
+        last_epoch_pool_token_supply: stake_pool.last_epoch_pool_token_supply,
+        lamports_per_pool_token: current_rate,
+        last_epoch_lamports_per_pool_token: last_epoch_rate,
+        epoch_yield: epoch_yield(last_epoch_rate, current_rate),
+        epochs_per_year,
+        apy,
+        apy_epochs: epoch_yields.len() as u64,
+        history,
+        reserve_stake_lamports: reserve_stake.lamports,
+        withdraw_ticket_lamports: stake_pool.withdraw_ticket_lamports,
+        pending_redelegation_lamports: stake_pool.pending_redelegation_lamports,
+        total_transient_stake_lamports: validators.iter().map(|v| v.transient_stake_lamports).sum(),
+        delinquent_validators: validators.iter().filter(|v| v.delinquent).count() as u32,
+        validators_not_updated: validators.iter().filter(|v| v.update_required).count() as u32,
+        validators,
+    };
+    println!(
+        "{}",
+        config.output_format.formatted_string(&cli_stake_pool_stats)
+    );
+    Ok(())
+}
+
+fn command_update(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    force: bool,
+    no_merge: bool,
+) -> CommandResult {
+    if config.no_update {
+        println!("Update requested, but --no-update flag specified, so doing nothing");
+        return Ok(());
+    }
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let epoch_info = config.rpc_client.get_epoch_info()?;
+
+    if stake_pool.last_update_epoch == epoch_info.epoch {
+        if force {
+            println!("Update not required, but --force flag specified, so doing it anyway");
+        } else {
+            println!("Update not required");
+            return Ok(());
+        }
+    }
+
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+
+    let (mut update_list_instructions, final_instructions) =
+        spl_stake_pool::instruction::update_stake_pool(
+            &spl_stake_pool::id(),
+            &stake_pool,
+            &validator_list,
+            stake_pool_address,
+            no_merge,
+        );
+
+    let update_list_instructions_len = update_list_instructions.len();
+    if update_list_instructions_len > 0 {
+        let last_instruction = update_list_instructions.split_off(update_list_instructions_len - 1);
+        // send the first ones without waiting
+        for instruction in update_list_instructions {
+            let transaction = checked_transaction_with_signers(
+                config,
+                &[instruction],
+                &[config.fee_payer.as_ref()],
+            )?;
+            send_transaction_no_wait(config, transaction)?;
+        }
+
+        // wait on the last one
+        let transaction = checked_transaction_with_signers(
+            config,
+            &last_instruction,
+            &[config.fee_payer.as_ref()],
+        )?;
+        send_transaction(config, transaction)?;
+    }
+    let transaction = checked_transaction_with_signers(
+        config,
+        &final_instructions,
+        &[config.fee_payer.as_ref()],
+    )?;
+    send_transaction(config, transaction)?;
+
+    Ok(())
+}
+
+fn crank_once(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    shard_index: usize,
+    shard_count: usize,
+    no_merge: bool,
+) -> CommandResult {
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let epoch_info = config.rpc_client.get_epoch_info()?;
+    if stake_pool.last_update_epoch == epoch_info.epoch {
+        println!("Epoch {} already updated", epoch_info.epoch);
+        // only one cranker moves stake, delegating the pending redelegations
+        // and unbonding also need the staker's signature
+        if shard_index == 0 {
+            let is_staker = stake_pool.staker == config.staker.pubkey();
+            process_pending_redelegations(config, stake_pool_address, &stake_pool, is_staker)?;
+            if is_staker {
+                unbond_withdraw_tickets(config, stake_pool_address, &stake_pool)?;
+            }
+        }
+        return Ok(());
+    }
+
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    println!(
+        "Epoch {}: {} of {} validators updated",
+        epoch_info.epoch,
+        validator_list.updated_validators(epoch_info.epoch),
+        validator_list.validators.len()
+    );
+
+    let stale_chunks = validator_list.stale_update_chunks(epoch_info.epoch);
+    if stale_chunks.is_empty() {
+        // every chunk is done, any cranker can finalize the update
+        let (_, final_instructions) = spl_stake_pool::instruction::update_stake_pool(
+            &spl_stake_pool::id(),
+            &stake_pool,
+            &validator_list,
+            stake_pool_address,
+            no_merge,
+        );
+        let transaction = checked_transaction_with_signers(
+            config,
+            &final_instructions,
+            &[config.fee_payer.as_ref()],
+        )?;
+        send_transaction(config, transaction)?;
+        return Ok(());
+    }
+
+    // shard on the absolute chunk position so that crankers never overlap,
+    // however far along the update is
+    for start_index in stale_chunks.into_iter().filter(|start_index| {
+        (*start_index as usize / spl_stake_pool::MAX_VALIDATORS_TO_UPDATE) % shard_count
+            == shard_index
+    }) {
+        let instruction = spl_stake_pool::instruction::update_validator_list_balance_chunk(
+            &spl_stake_pool::id(),
+            &stake_pool,
+            &validator_list,
+            stake_pool_address,
+            start_index,
+            no_merge,
+        );
+        let result =
+            checked_transaction_with_signers(config, &[instruction], &[config.fee_payer.as_ref()])
+                .and_then(|transaction| Ok(send_transaction_no_wait(config, transaction)?));
+        if let Err(err) = result {
+            eprintln!("Failed to update chunk at index {}: {}", start_index, err);
+        }
+    }
+    Ok(())
+}
+
+/// Deactivate the deposits waiting for redelegation, then once they are
+/// inactive, close them into the reserve and, if `redelegate` is set, delegate
+/// their stake to the pool validators with the least stake
+fn process_pending_redelegations(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    stake_pool: &StakePool,
+    redelegate: bool,
+) -> CommandResult {
+    if stake_pool.pending_redelegation_lamports == 0 {
+        return Ok(());
+    }
+    let pending_redelegations = get_pending_redelegations(&config.rpc_client, stake_pool_address)?;
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    let stake_rent = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(STAKE_STATE_LEN)?;
+    // each validator takes one increase per epoch, in its transient account
+    let mut validators = validator_list
+        .validators
+        .iter()
+        .filter(|validator| {
+            validator.status == StakeStatus::Active && validator.transient_stake_lamports == 0
+        })
+        .collect::<Vec<_>>();
+    validators.sort_by_key(|validator| Reverse(validator.active_stake_lamports));
+
+    for (pending_redelegation_address, pending_redelegation) in pending_redelegations {
+        let activation = match config
+            .rpc_client
+            .get_stake_activation(pending_redelegation.stake_account, None)
+        {
+            Ok(activation) => activation,
+            Err(err) => {
+                eprintln!(
+                    "Pending redelegation {} has no delegated stake account {}: {}",
+                    pending_redelegation_address, pending_redelegation.stake_account, err
+                );
+                continue;
+            }
+        };
+        let mut instructions = vec![];
+        let mut needs_staker = false;
+        match activation.state {
+            StakeActivationState::Inactive => {
+                let stake_lamports = config
+                    .rpc_client
+                    .get_balance(&pending_redelegation.stake_account)?;
+                println!(
+                    "Closing pending redelegation {}, moving {} into the reserve",
+                    pending_redelegation_address,
+                    Sol(stake_lamports)
+                );
+                instructions.push(spl_stake_pool::instruction::close_pending_redelegation(
+                    &spl_stake_pool::id(),
+                    stake_pool_address,
+                    &stake_pool.reserve_stake,
+                    &pending_redelegation_address,
+                    &pending_redelegation.stake_account,
+                    &pending_redelegation.rent_receiver,
+                ));
+                // the new transient stake account's rent comes out of the
+                // closed account's own rent-exemption
+                let lamports = stake_lamports.saturating_sub(stake_rent);
+                if redelegate && lamports >= MINIMUM_ACTIVE_STAKE {
+                    if let Some(validator) = validators.pop() {
+                        println!(
+                            "Delegating {} to validator {}",
+                            Sol(lamports),
+                            validator.vote_account_address
+                        );
+                        instructions.push(
+                            spl_stake_pool::instruction::increase_validator_stake_with_vote(
+                                &spl_stake_pool::id(),
+                                stake_pool,
+                                stake_pool_address,
+                                &validator.vote_account_address,
+                                lamports,
+                                validator.transient_seed_suffix_start,
+                            ),
+                        );
+                        needs_staker = true;
+                    } else {
+                        eprintln!(
+                            "No pool validator available to delegate {} this epoch, it stays in the reserve",
+                            Sol(lamports)
+                        );
+                    }
+                }
+            }
+            StakeActivationState::Active => {
+                println!(
+                    "Deactivating pending stake {} of {}",
+                    pending_redelegation.stake_account,
+                    Sol(pending_redelegation.lamports)
+                );
+                instructions.push(spl_stake_pool::instruction::deactivate_pending_stake(
+                    &spl_stake_pool::id(),
+                    stake_pool_address,
+                    &pending_redelegation_address,
+                    &pending_redelegation.stake_account,
+                ));
+            }
+            _ => continue,
+        }
+        let mut signers = vec![config.fee_payer.as_ref()];
+        if needs_staker {
+            signers.push(config.staker.as_ref());
+        }
+        unique_signers!(signers);
+        let result = checked_transaction_with_signers(config, &instructions, &signers)
+            .and_then(|transaction| Ok(send_transaction(config, transaction)?));
+        if let Err(err) = result {
+            eprintln!(
+                "Failed to process pending redelegation {}: {}",
+                pending_redelegation_address, err
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Deactivate enough validator stake for the reserve to pay out the
+/// withdrawal tickets, counting the transient stake already deactivating
+fn unbond_withdraw_tickets(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    stake_pool: &StakePool,
+) -> CommandResult {
+    if stake_pool.withdraw_ticket_lamports == 0 {
+        return Ok(());
+    }
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    let stake_rent = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(STAKE_STATE_LEN)?;
+    let reserve_lamports = config
+        .rpc_client
+        .get_balance(&stake_pool.reserve_stake)?
+        .saturating_sub(stake_rent.saturating_add(1));
+
+    let mut deactivating_lamports = 0u64;
+    for validator in validator_list
+        .validators
+        .iter()
+        .filter(|validator| validator.transient_stake_lamports > 0)
+    {
+        let (transient_stake_address, _) = find_transient_stake_program_address(
+            &spl_stake_pool::id(),
+            &validator.vote_account_address,
+            stake_pool_address,
+            validator.transient_seed_suffix_start,
+        );
+        if let Ok(stake::state::StakeState::Stake(_, stake)) =
+            get_stake_state(&config.rpc_client, &transient_stake_address)
+        {
+            if stake.delegation.deactivation_epoch != Epoch::MAX {
+                deactivating_lamports =
+                    deactivating_lamports.saturating_add(validator.transient_stake_lamports);
+            }
+        }
+    }
+
+    let mut shortfall = stake_pool
+        .withdraw_ticket_lamports
+        .saturating_sub(reserve_lamports)
+        .saturating_sub(deactivating_lamports);
+    if shortfall == 0 {
+        return Ok(());
+    }
+    println!(
+        "Withdrawal tickets need {} more in the reserve, deactivating stake",
+        Sol(shortfall)
+    );
+
+    // take from the largest validators first, each decrease must leave the
+    // validator its minimum stake and create a rent-exempt transient account
+    let minimum_validator_lamports = stake_rent.saturating_add(MINIMUM_ACTIVE_STAKE);
+    let mut validators = validator_list
+        .validators
+        .iter()
+        .filter(|validator| {
+            validator.status == StakeStatus::Active && validator.transient_stake_lamports == 0
+        })
+        .collect::<Vec<_>>();
+    validators.sort_by_key(|validator| Reverse(validator.active_stake_lamports));
+    for validator in validators {
+        if shortfall == 0 {
+            break;
+        }
+        let lamports = shortfall.max(stake_rent.saturating_add(1)).min(
+            validator
+                .active_stake_lamports
+                .saturating_sub(minimum_validator_lamports),
+        );
+        if lamports <= stake_rent {
+            continue;
+        }
+        println!(
+            "Decreasing stake of validator {} by {}",
+            validator.vote_account_address,
+            Sol(lamports)
+        );
+        let instruction = spl_stake_pool::instruction::decrease_validator_stake_with_vote(
+            &spl_stake_pool::id(),
+            stake_pool,
+            stake_pool_address,
+            &validator.vote_account_address,
+            lamports,
+            validator.transient_seed_suffix_start,
+        );
+        let result = checked_transaction_with_signers(
+            config,
+            &[instruction],
+            &[config.fee_payer.as_ref(), config.staker.as_ref()],
+        )
+        .and_then(|transaction| Ok(send_transaction(config, transaction)?));
+        match result {
+            Ok(()) => shortfall = shortfall.saturating_sub(lamports),
+            Err(err) => eprintln!(
+                "Failed to decrease stake of validator {}: {}",
+                validator.vote_account_address, err
+            ),
+        }
+    }
+    if shortfall > 0 {
+        eprintln!(
+            "Could not deactivate {} for withdrawal tickets, validators are busy or at their minimum stake",
+            Sol(shortfall)
+        );
+    }
+    Ok(())
+}
+
+fn command_crank(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    shard_index: usize,
+    shard_count: usize,
+    interval: Duration,
+    no_merge: bool,
+) -> CommandResult {
+    if shard_index >= shard_count {
+        return Err(format!(
+            "Shard index {} must be lower than the shard count {}",
+            shard_index, shard_count
+        )
+        .into());
+    }
+    loop {
+        if let Err(err) = crank_once(
+            config,
+            stake_pool_address,
+            shard_index,
+            shard_count,
+            no_merge,
+        ) {
+            eprintln!("Crank failed: {}", err);
+        }
+        thread::sleep(interval);
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct WithdrawAccount {
+    stake_address: Pubkey,
+    vote_address: Option<Pubkey>,
+    pool_amount: u64,
+}
+
+fn sorted_accounts<F>(
     validator_list: &ValidatorList,
     stake_pool: &StakePool,
     get_info: F,
@@ -1327,6 +2247,23 @@ fn prepare_withdraw_accounts(
     Ok(withdraw_from)
 }
 
+/// Portion of `minimum` matching the share of `amount` in `total`, rounded up
+/// so that the portions always add up to at least `minimum`
+fn proportional_minimum(minimum: u64, amount: u64, total: u64) -> u64 {
+    if total == 0 {
+        return minimum;
+    }
+    let numerator = (minimum as u128) * (amount as u128);
+    let portion = numerator / (total as u128);
+    let remainder = numerator % (total as u128);
+    if remainder > 0 {
+        (portion + 1) as u64
+    } else {
+        portion as u64
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn command_withdraw_stake(
     config: &Config,
     stake_pool_address: &Pubkey,
@@ -1335,6 +2272,7 @@ fn command_withdraw_stake(
     stake_receiver_param: &Option<Pubkey>,
     pool_token_account: &Option<Pubkey>,
     pool_amount: f64,
+    minimum_sol_out: Option<f64>,
 ) -> CommandResult {
     if !config.no_update {
         command_update(config, stake_pool_address, false, false)?;
@@ -1343,14 +2281,17 @@ fn command_withdraw_stake(
     let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
     let pool_mint = get_token_mint(&config.rpc_client, &stake_pool.pool_mint)?;
     let pool_amount = spl_token::ui_amount_to_amount(pool_amount, pool_mint.decimals);
+    let minimum_lamports_out = minimum_sol_out.map(native_token::sol_to_lamports);
 
     let pool_withdraw_authority =
         find_withdraw_authority_program_address(&spl_stake_pool::id(), stake_pool_address).0;
 
-    let pool_token_account = pool_token_account.unwrap_or(get_associated_token_address(
-        &config.token_owner.pubkey(),
-        &stake_pool.pool_mint,
-    ));
+    let pool_token_account =
+        pool_token_account.unwrap_or(get_associated_token_address_with_program_id(
+            &config.token_owner.pubkey(),
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+        ));
     let token_account = get_token_account(
         &config.rpc_client,
         &pool_token_account,
@@ -1428,8 +2369,8 @@ fn command_withdraw_stake(
 
     instructions.push(
         // Approve spending token
-        spl_token::instruction::approve(
-            &spl_token::id(),
+        spl_token_2022::instruction::approve(
+            &stake_pool.token_program_id,
             &pool_token_account,
             &user_transfer_authority.pubkey(),
             &config.token_owner.pubkey(),
@@ -1440,12 +2381,30 @@ fn command_withdraw_stake(
 
     let mut total_rent_free_balances = 0;
 
+    // The minimum is spread over all withdrawals, in proportion to the
+    // lamports expected from each of them
+    let total_sol_withdraw_amount = withdraw_accounts
+        .iter()
+        .map(|withdraw_account| {
+            stake_pool
+                .calc_lamports_withdraw_amount(withdraw_account.pool_amount)
+                .unwrap()
+        })
+        .sum::<u64>();
+
     // Go through prepared accounts and withdraw/claim them
     for withdraw_account in withdraw_accounts {
         // Convert pool tokens amount to lamports
         let sol_withdraw_amount = stake_pool
             .calc_lamports_withdraw_amount(withdraw_account.pool_amount)
             .unwrap();
+        let minimum_lamports_out = minimum_lamports_out.map(|minimum_lamports_out| {
+            proportional_minimum(
+                minimum_lamports_out,
+                sol_withdraw_amount,
+                total_sol_withdraw_amount,
+            )
+        });
 
         if let Some(vote_address) = withdraw_account.vote_address {
             println!(
@@ -1477,21 +2436,40 @@ fn command_withdraw_stake(
             stake_pubkey
         });
 
-        instructions.push(spl_stake_pool::instruction::withdraw_stake(
-            &spl_stake_pool::id(),
-            stake_pool_address,
-            &stake_pool.validator_list,
-            &pool_withdraw_authority,
-            &withdraw_account.stake_address,
-            &stake_receiver,
-            &config.staker.pubkey(),
-            &user_transfer_authority.pubkey(),
-            &pool_token_account,
-            &stake_pool.manager_fee_account,
-            &stake_pool.pool_mint,
-            &spl_token::id(),
-            withdraw_account.pool_amount,
-        ));
+        if let Some(minimum_lamports_out) = minimum_lamports_out {
+            instructions.push(spl_stake_pool::instruction::withdraw_stake_with_slippage(
+                &spl_stake_pool::id(),
+                stake_pool_address,
+                &stake_pool.validator_list,
+                &pool_withdraw_authority,
+                &withdraw_account.stake_address,
+                &stake_receiver,
+                &config.staker.pubkey(),
+                &user_transfer_authority.pubkey(),
+                &pool_token_account,
+                &stake_pool.manager_fee_account,
+                &stake_pool.pool_mint,
+                &stake_pool.token_program_id,
+                withdraw_account.pool_amount,
+                minimum_lamports_out,
+            ));
+        } else {
+            instructions.push(spl_stake_pool::instruction::withdraw_stake(
+                &spl_stake_pool::id(),
+                stake_pool_address,
+                &stake_pool.validator_list,
+                &pool_withdraw_authority,
+                &withdraw_account.stake_address,
+                &stake_receiver,
+                &config.staker.pubkey(),
+                &user_transfer_authority.pubkey(),
+                &pool_token_account,
+                &stake_pool.manager_fee_account,
+                &stake_pool.pool_mint,
+                &stake_pool.token_program_id,
+                withdraw_account.pool_amount,
+            ));
+        }
     }
 
     let recent_blockhash = get_latest_blockhash(&config.rpc_client)?;
@@ -1519,6 +2497,7 @@ fn command_withdraw_sol(
     pool_token_account: &Option<Pubkey>,
     sol_receiver: &Pubkey,
     pool_amount: f64,
+    minimum_sol_out: Option<f64>,
 ) -> CommandResult {
     if !config.no_update {
         command_update(config, stake_pool_address, false, false)?;
@@ -1527,11 +2506,14 @@ fn command_withdraw_sol(
     let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
     let pool_mint = get_token_mint(&config.rpc_client, &stake_pool.pool_mint)?;
     let pool_amount = spl_token::ui_amount_to_amount(pool_amount, pool_mint.decimals);
+    let minimum_lamports_out = minimum_sol_out.map(native_token::sol_to_lamports);
 
-    let pool_token_account = pool_token_account.unwrap_or(get_associated_token_address(
-        &config.token_owner.pubkey(),
-        &stake_pool.pool_mint,
-    ));
+    let pool_token_account =
+        pool_token_account.unwrap_or(get_associated_token_address_with_program_id(
+            &config.token_owner.pubkey(),
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+        ));
     let token_account = get_token_account(
         &config.rpc_client,
         &pool_token_account,
@@ -1558,8 +2540,8 @@ fn command_withdraw_sol(
 
     let mut instructions = vec![
         // Approve spending token
-        spl_token::instruction::approve(
-            &spl_token::id(),
+        spl_token_2022::instruction::approve(
+            &stake_pool.token_program_id,
             &pool_token_account,
             &user_transfer_authority.pubkey(),
             &config.token_owner.pubkey(),
@@ -1586,48 +2568,234 @@ fn command_withdraw_sol(
             return Err(error.into());
         }
 
-        spl_stake_pool::instruction::withdraw_sol_with_authority(
-            &spl_stake_pool::id(),
-            stake_pool_address,
-            &withdraw_authority.pubkey(),
-            &pool_withdraw_authority,
-            &user_transfer_authority.pubkey(),
+        if let Some(minimum_lamports_out) = minimum_lamports_out {
+            spl_stake_pool::instruction::withdraw_sol_with_authority_and_slippage(
+                &spl_stake_pool::id(),
+                stake_pool_address,
+                &withdraw_authority.pubkey(),
+                &pool_withdraw_authority,
+                &user_transfer_authority.pubkey(),
+                &pool_token_account,
+                &stake_pool.reserve_stake,
+                sol_receiver,
+                &stake_pool.manager_fee_account,
+                &stake_pool.pool_mint,
+                &stake_pool.token_program_id,
+                pool_amount,
+                minimum_lamports_out,
+            )
+        } else {
+            spl_stake_pool::instruction::withdraw_sol_with_authority(
+                &spl_stake_pool::id(),
+                stake_pool_address,
+                &withdraw_authority.pubkey(),
+                &pool_withdraw_authority,
+                &user_transfer_authority.pubkey(),
+                &pool_token_account,
+                &stake_pool.reserve_stake,
+                sol_receiver,
+                &stake_pool.manager_fee_account,
+                &stake_pool.pool_mint,
+                &stake_pool.token_program_id,
+                pool_amount,
+            )
+        }
+    } else if let Some(minimum_lamports_out) = minimum_lamports_out {
+        spl_stake_pool::instruction::withdraw_sol_with_slippage(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &pool_withdraw_authority,
+            &user_transfer_authority.pubkey(),
+            &pool_token_account,
+            &stake_pool.reserve_stake,
+            sol_receiver,
+            &stake_pool.manager_fee_account,
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+            pool_amount,
+            minimum_lamports_out,
+        )
+    } else {
+        spl_stake_pool::instruction::withdraw_sol(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &pool_withdraw_authority,
+            &user_transfer_authority.pubkey(),
+            &pool_token_account,
+            &stake_pool.reserve_stake,
+            sol_receiver,
+            &stake_pool.manager_fee_account,
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+            pool_amount,
+        )
+    };
+
+    instructions.push(withdraw_instruction);
+
+    let recent_blockhash = get_latest_blockhash(&config.rpc_client)?;
+    let message = Message::new_with_blockhash(
+        &instructions,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(config, config.rpc_client.get_fee_for_message(&message)?)?;
+    unique_signers!(signers);
+    let transaction = Transaction::new(&signers, message, recent_blockhash);
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_withdraw_to_ticket(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    pool_token_account: &Option<Pubkey>,
+    pool_amount: f64,
+    minimum_sol_out: Option<f64>,
+) -> CommandResult {
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let pool_mint = get_token_mint(&config.rpc_client, &stake_pool.pool_mint)?;
+    let pool_amount = spl_token::ui_amount_to_amount(pool_amount, pool_mint.decimals);
+    let minimum_lamports_out = minimum_sol_out.map_or(0, native_token::sol_to_lamports);
+
+    let pool_token_account =
+        pool_token_account.unwrap_or(get_associated_token_address_with_program_id(
+            &config.token_owner.pubkey(),
+            &stake_pool.pool_mint,
+            &stake_pool.token_program_id,
+        ));
+    let token_account = get_token_account(
+        &config.rpc_client,
+        &pool_token_account,
+        &stake_pool.pool_mint,
+    )?;
+
+    // Check withdraw_from balance
+    if token_account.amount < pool_amount {
+        return Err(format!(
+            "Not enough token balance to withdraw {} pool tokens.\nMaximum withdraw amount is {} pool tokens.",
+            spl_token::amount_to_ui_amount(pool_amount, pool_mint.decimals),
+            spl_token::amount_to_ui_amount(token_account.amount, pool_mint.decimals)
+        )
+        .into());
+    }
+
+    let user_transfer_authority = Keypair::new(); // ephemeral keypair just to do the transfer
+    let withdraw_ticket = Keypair::new();
+    let ticket_rent = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(WithdrawTicket::LEN)?;
+    let instructions = vec![
+        // Approve spending token
+        spl_token_2022::instruction::approve(
+            &stake_pool.token_program_id,
             &pool_token_account,
-            &stake_pool.reserve_stake,
-            sol_receiver,
-            &stake_pool.manager_fee_account,
-            &stake_pool.pool_mint,
-            &spl_token::id(),
+            &user_transfer_authority.pubkey(),
+            &config.token_owner.pubkey(),
+            &[],
             pool_amount,
-        )
-    } else {
-        spl_stake_pool::instruction::withdraw_sol(
+        )?,
+        system_instruction::create_account(
+            &config.fee_payer.pubkey(),
+            &withdraw_ticket.pubkey(),
+            ticket_rent,
+            WithdrawTicket::LEN as u64,
+            &spl_stake_pool::id(),
+        ),
+        spl_stake_pool::instruction::withdraw_to_ticket(
             &spl_stake_pool::id(),
             stake_pool_address,
-            &pool_withdraw_authority,
             &user_transfer_authority.pubkey(),
             &pool_token_account,
-            &stake_pool.reserve_stake,
-            sol_receiver,
+            &withdraw_ticket.pubkey(),
+            &config.token_owner.pubkey(),
             &stake_pool.manager_fee_account,
             &stake_pool.pool_mint,
-            &spl_token::id(),
+            &stake_pool.token_program_id,
             pool_amount,
-        )
-    };
-
-    instructions.push(withdraw_instruction);
+            minimum_lamports_out,
+        ),
+    ];
 
+    let mut signers = vec![
+        config.fee_payer.as_ref(),
+        config.token_owner.as_ref(),
+        &user_transfer_authority,
+        &withdraw_ticket,
+    ];
     let recent_blockhash = get_latest_blockhash(&config.rpc_client)?;
     let message = Message::new_with_blockhash(
         &instructions,
         Some(&config.fee_payer.pubkey()),
         &recent_blockhash,
     );
-    check_fee_payer_balance(config, config.rpc_client.get_fee_for_message(&message)?)?;
+    check_fee_payer_balance(
+        config,
+        ticket_rent + config.rpc_client.get_fee_for_message(&message)?,
+    )?;
     unique_signers!(signers);
     let transaction = Transaction::new(&signers, message, recent_blockhash);
     send_transaction(config, transaction)?;
+    println!(
+        "Created withdrawal ticket {}, claimable with `claim-ticket` from the next epoch",
+        withdraw_ticket.pubkey()
+    );
+    Ok(())
+}
+
+fn command_claim_ticket(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    withdraw_ticket_address: &Pubkey,
+    sol_receiver: &Option<Pubkey>,
+) -> CommandResult {
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let withdraw_ticket = get_withdraw_ticket(&config.rpc_client, withdraw_ticket_address)?;
+    if withdraw_ticket.stake_pool != *stake_pool_address {
+        return Err(format!(
+            "Withdrawal ticket {} belongs to stake pool {}",
+            withdraw_ticket_address, withdraw_ticket.stake_pool
+        )
+        .into());
+    }
+    if withdraw_ticket.owner != config.token_owner.pubkey() {
+        return Err(format!(
+            "Withdrawal ticket {} is owned by {}, not the token owner {}",
+            withdraw_ticket_address,
+            withdraw_ticket.owner,
+            config.token_owner.pubkey()
+        )
+        .into());
+    }
+    let sol_receiver = sol_receiver.unwrap_or_else(|| config.token_owner.pubkey());
+    println!(
+        "Claiming {} from withdrawal ticket {}",
+        Sol(withdraw_ticket.lamports),
+        withdraw_ticket_address
+    );
+
+    let instruction = spl_stake_pool::instruction::claim_ticket(
+        &spl_stake_pool::id(),
+        stake_pool_address,
+        withdraw_ticket_address,
+        &config.token_owner.pubkey(),
+        &stake_pool.reserve_stake,
+        &sol_receiver,
+    );
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[instruction],
+        &[config.fee_payer.as_ref(), config.token_owner.as_ref()],
+    )?;
+    send_transaction(config, transaction)?;
     Ok(())
 }
 
@@ -1662,19 +2830,62 @@ fn command_set_manager(
         }
     };
 
+    let instruction = match &stake_pool.fee_vesting {
+        Some(fee_vesting) => {
+            if fee_vesting.locked_amount > 0 {
+                println!(
+                    "Changing manager forfeits {} locked fee tokens",
+                    fee_vesting.locked_amount
+                );
+            }
+            spl_stake_pool::instruction::set_manager_with_fee_vesting(
+                &spl_stake_pool::id(),
+                stake_pool_address,
+                &config.manager.pubkey(),
+                &new_manager_pubkey,
+                &new_fee_receiver,
+                &fee_vesting.vesting_account,
+                &stake_pool.pool_mint,
+                &stake_pool.manager_fee_account,
+                &stake_pool.token_program_id,
+            )
+        }
+        None => spl_stake_pool::instruction::set_manager(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            &new_manager_pubkey,
+            &new_fee_receiver,
+        ),
+    };
+
     signers.append(&mut vec![
         config.fee_payer.as_ref(),
         config.manager.as_ref(),
     ]);
     unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(config, &[instruction], &signers)?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_staker(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    new_staker: &Pubkey,
+) -> CommandResult {
+    if !config.no_update {
+        command_update(config, stake_pool_address, false, false)?;
+    }
+    let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
+    unique_signers!(signers);
     let transaction = checked_transaction_with_signers(
         config,
-        &[spl_stake_pool::instruction::set_manager(
+        &[spl_stake_pool::instruction::set_staker(
             &spl_stake_pool::id(),
             stake_pool_address,
             &config.manager.pubkey(),
-            &new_manager_pubkey,
-            &new_fee_receiver,
+            new_staker,
         )],
         &signers,
     )?;
@@ -1682,10 +2893,27 @@ fn command_set_manager(
     Ok(())
 }
 
-fn command_set_staker(
+fn command_realloc_pool(config: &Config, stake_pool_address: &Pubkey) -> CommandResult {
+    // no update first, since pools with the old layout can't store its result
+    let signers = vec![config.fee_payer.as_ref()];
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::realloc_stake_pool(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.fee_payer.pubkey(),
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_funding_authority(
     config: &Config,
     stake_pool_address: &Pubkey,
-    new_staker: &Pubkey,
+    new_authority: Option<Pubkey>,
+    funding_type: FundingType,
 ) -> CommandResult {
     if !config.no_update {
         command_update(config, stake_pool_address, false, false)?;
@@ -1694,11 +2922,12 @@ fn command_set_staker(
     unique_signers!(signers);
     let transaction = checked_transaction_with_signers(
         config,
-        &[spl_stake_pool::instruction::set_staker(
+        &[spl_stake_pool::instruction::set_funding_authority(
             &spl_stake_pool::id(),
             stake_pool_address,
             &config.manager.pubkey(),
-            new_staker,
+            new_authority.as_ref(),
+            funding_type,
         )],
         &signers,
     )?;
@@ -1706,49 +2935,253 @@ fn command_set_staker(
     Ok(())
 }
 
-fn command_set_funding_authority(
+fn command_set_fee(
     config: &Config,
     stake_pool_address: &Pubkey,
-    new_authority: Option<Pubkey>,
-    funding_type: FundingType,
+    new_fee: FeeType,
 ) -> CommandResult {
     if !config.no_update {
         command_update(config, stake_pool_address, false, false)?;
     }
     let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
     unique_signers!(signers);
-    let transaction = checked_transaction_with_signers(
-        config,
-        &[spl_stake_pool::instruction::set_funding_authority(
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::set_fee(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            new_fee,
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_liquidity_buffer(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    target_reserve: Fee,
+    max_sol_withdrawal_fee: Fee,
+) -> CommandResult {
+    let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::set_liquidity_buffer(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            target_reserve,
+            max_sol_withdrawal_fee,
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_fee_vesting(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    vesting_epochs: u64,
+) -> CommandResult {
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let mut instructions: Vec<Instruction> = vec![];
+    let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
+
+    let vesting_account_keypair = Keypair::new();
+    let vesting_account = match &stake_pool.fee_vesting {
+        Some(fee_vesting) => {
+            if vesting_epochs == 0 && fee_vesting.locked_amount > 0 {
+                println!(
+                    "Disabling fee vesting forfeits {} locked fee tokens",
+                    fee_vesting.locked_amount
+                );
+            }
+            fee_vesting.vesting_account
+        }
+        None => {
+            if vesting_epochs == 0 {
+                println!("Fee vesting already disabled");
+                return Ok(());
+            }
+            let (withdraw_authority, _) =
+                find_withdraw_authority_program_address(&spl_stake_pool::id(), stake_pool_address);
+            let vesting_account_balance = config
+                .rpc_client
+                .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)?;
+            println!(
+                "Creating fee vesting account {}",
+                vesting_account_keypair.pubkey()
+            );
+            instructions.push(system_instruction::create_account(
+                &config.fee_payer.pubkey(),
+                &vesting_account_keypair.pubkey(),
+                vesting_account_balance,
+                spl_token::state::Account::LEN as u64,
+                &stake_pool.token_program_id,
+            ));
+            instructions.push(spl_token_2022::instruction::initialize_account(
+                &stake_pool.token_program_id,
+                &vesting_account_keypair.pubkey(),
+                &stake_pool.pool_mint,
+                &withdraw_authority,
+            )?);
+            signers.push(&vesting_account_keypair);
+            vesting_account_keypair.pubkey()
+        }
+    };
+
+    instructions.push(spl_stake_pool::instruction::set_manager_fee_vesting(
+        &spl_stake_pool::id(),
+        stake_pool_address,
+        &config.manager.pubkey(),
+        &vesting_account,
+        &stake_pool.pool_mint,
+        &stake_pool.manager_fee_account,
+        &stake_pool.token_program_id,
+        vesting_epochs,
+    ));
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(config, &instructions, &signers)?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_claim_manager_fee(config: &Config, stake_pool_address: &Pubkey) -> CommandResult {
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let fee_vesting = stake_pool
+        .fee_vesting
+        .ok_or("Fee vesting is not enabled on the stake pool")?;
+
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::claim_manager_fee(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &fee_vesting.vesting_account,
+            &stake_pool.pool_mint,
+            &stake_pool.manager_fee_account,
+            &stake_pool.token_program_id,
+        )],
+        &[config.fee_payer.as_ref()],
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_validator_config(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    vote_account: &Pubkey,
+    max_stake_bps: Option<u16>,
+    no_deposits: Option<bool>,
+) -> CommandResult {
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    let validator_list = get_validator_list(&config.rpc_client, &stake_pool.validator_list)?;
+    let validator_stake_info = validator_list.find(vote_account).ok_or(format!(
+        "Vote account {} not found in stake pool",
+        vote_account
+    ))?;
+    let max_stake_bps = max_stake_bps.unwrap_or(validator_stake_info.max_stake_bps);
+    let no_deposits = no_deposits.unwrap_or(validator_stake_info.no_deposits);
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
+    unique_signers!(signers);
+    let transaction = checked_transaction_with_signers(
+        config,
+        &[spl_stake_pool::instruction::set_validator_config(
+            &spl_stake_pool::id(),
+            stake_pool_address,
+            &config.manager.pubkey(),
+            &stake_pool.validator_list,
+            vote_account,
+            max_stake_bps,
+            no_deposits,
+        )],
+        &signers,
+    )?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_create_token_metadata(
+    config: &Config,
+    stake_pool_address: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> CommandResult {
+    TokenMetadata::check_lengths(&name, &symbol, &uri)?;
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    if get_token_metadata(&config.rpc_client, &stake_pool.pool_mint)?.is_some() {
+        return Err(format!(
+            "Token metadata for pool mint {} already exists, use update-token-metadata instead",
+            stake_pool.pool_mint
+        )
+        .into());
+    }
+    let token_metadata_balance = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(TokenMetadata::LEN)?;
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
+    unique_signers!(signers);
+    let recent_blockhash = get_latest_blockhash(&config.rpc_client)?;
+    let message = Message::new_with_blockhash(
+        &[spl_stake_pool::instruction::create_token_metadata(
             &spl_stake_pool::id(),
             stake_pool_address,
             &config.manager.pubkey(),
-            new_authority.as_ref(),
-            funding_type,
+            &stake_pool.pool_mint,
+            &config.fee_payer.pubkey(),
+            name,
+            symbol,
+            uri,
         )],
-        &signers,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(
+        config,
+        token_metadata_balance + config.rpc_client.get_fee_for_message(&message)?,
     )?;
+    let transaction = Transaction::new(&signers, message, recent_blockhash);
     send_transaction(config, transaction)?;
     Ok(())
 }
 
-fn command_set_fee(
+fn command_update_token_metadata(
     config: &Config,
     stake_pool_address: &Pubkey,
-    new_fee: FeeType,
+    name: String,
+    symbol: String,
+    uri: String,
 ) -> CommandResult {
-    if !config.no_update {
-        command_update(config, stake_pool_address, false, false)?;
+    TokenMetadata::check_lengths(&name, &symbol, &uri)?;
+    let stake_pool = get_stake_pool(&config.rpc_client, stake_pool_address)?;
+    if get_token_metadata(&config.rpc_client, &stake_pool.pool_mint)?.is_none() {
+        return Err(format!(
+            "No token metadata for pool mint {}, use create-token-metadata first",
+            stake_pool.pool_mint
+        )
+        .into());
     }
+
     let mut signers = vec![config.fee_payer.as_ref(), config.manager.as_ref()];
     unique_signers!(signers);
     let transaction = checked_transaction_with_signers(
         config,
-        &[spl_stake_pool::instruction::set_fee(
+        &[spl_stake_pool::instruction::update_token_metadata(
             &spl_stake_pool::id(),
             stake_pool_address,
             &config.manager.pubkey(),
-            new_fee,
+            &stake_pool.pool_mint,
+            name,
+            symbol,
+            uri,
         )],
         &signers,
     )?;
@@ -1992,6 +3425,12 @@ fn main() {
                     .takes_value(false)
                     .help("Bypass fee checks, allowing pool to be created with unsafe fees"),
             )
+            .arg(
+                Arg::with_name("token_2022")
+                    .long("token-2022")
+                    .takes_value(false)
+                    .help("Create the pool token mint with the Token-2022 program"),
+            )
         )
         .subcommand(SubCommand::with_name("add-validator")
             .about("Add validator account to the stake pool. Must be signed by the pool staker.")
@@ -2110,6 +3549,67 @@ fn main() {
                     .help("Amount in SOL to remove from the validator stake account. Must be at least the rent-exempt amount for a stake."),
             )
         )
+        .subcommand(SubCommand::with_name("redelegate")
+            .about("Decrease stake on one validator and increase stake on another by the same amount in one instruction, with the reserve fronting the increase. Must be signed by the pool staker.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address"),
+            )
+            .arg(
+                Arg::with_name("source_vote_account")
+                    .index(2)
+                    .validator(is_pubkey)
+                    .value_name("SOURCE_VOTE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Vote account for the validator to redelegate stake from"),
+            )
+            .arg(
+                Arg::with_name("destination_vote_account")
+                    .index(3)
+                    .validator(is_pubkey)
+                    .value_name("DESTINATION_VOTE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Vote account for the validator to redelegate stake to"),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(4)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Amount in SOL to move from the source validator stake account. The rent-exempt amount for a stake stays behind and goes back to the reserve."),
+            )
+        )
+        .subcommand(SubCommand::with_name("rebalance")
+            .about("Increase and decrease validator stakes to match target scores, within the reserve balance. Must be signed by the pool staker. \
+                    Use the global --dry-run flag to only print and simulate the plan.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address"),
+            )
+            .arg(
+                Arg::with_name("scores")
+                    .long("scores")
+                    .value_name("PATH")
+                    .takes_value(true)
+                    .help("JSON file mapping validator vote account addresses to scores. \
+                          Stake is targeted in proportion to the scores, validators missing from the file are reduced to the minimum. \
+                          Defaults to the same score for every active validator."),
+            )
+        )
         .subcommand(SubCommand::with_name("set-preferred-validator")
             .about("Set the preferred validator for deposits or withdrawals. Must be signed by the pool staker.")
             .arg(
@@ -2150,6 +3650,49 @@ fn main() {
                 .required(true)
             )
         )
+        .subcommand(SubCommand::with_name("set-validator-config")
+            .about("Set the maximum share of the pool a validator may hold, and whether it accepts stake deposits and increases. Must be signed by the manager.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address"),
+            )
+            .arg(
+                Arg::with_name("vote_account")
+                    .index(2)
+                    .validator(is_pubkey)
+                    .value_name("VOTE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Vote account for the validator to configure"),
+            )
+            .arg(
+                Arg::with_name("max_stake_bps")
+                    .long("max-stake-bps")
+                    .validator(is_parsable::<u16>)
+                    .value_name("BASIS_POINTS")
+                    .takes_value(true)
+                    .help("Maximum share of the pool's total stake delegated to the validator, in basis points. Use 0 to remove the cap."),
+            )
+            .arg(
+                Arg::with_name("deposits")
+                    .long("deposits")
+                    .value_name("DEPOSITS")
+                    .possible_values(&["allow", "deny"])
+                    .takes_value(true)
+                    .help("Allow or deny stake deposits and increases to the validator"),
+            )
+            .group(ArgGroup::with_name("validator_config")
+                .arg("max_stake_bps")
+                .arg("deposits")
+                .multiple(true)
+                .required(true)
+            )
+        )
         .subcommand(SubCommand::with_name("deposit-stake")
             .about("Deposit active stake account into the stake pool in exchange for pool tokens")
             .arg(
@@ -2196,6 +3739,15 @@ fn main() {
                     .help("Pool token account to receive the referral fees for deposits. \
                           Defaults to the token receiver."),
             )
+            .arg(
+                Arg::with_name("minimum_pool_tokens_out")
+                    .long("minimum-pool-tokens-out")
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("Fail the deposit if fewer pool tokens than this amount would be received, \
+                          for example after a fee or exchange rate change."),
+            )
         )
         .subcommand(SubCommand::with_name("deposit-all-stake")
             .about("Deposit all active stake accounts into the stake pool in exchange for pool tokens")
@@ -2289,6 +3841,15 @@ fn main() {
                     .help("Account to receive the referral fees for deposits. \
                           Defaults to the token receiver."),
             )
+            .arg(
+                Arg::with_name("minimum_pool_tokens_out")
+                    .long("minimum-pool-tokens-out")
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("Fail the deposit if fewer pool tokens than this amount would be received, \
+                          for example after a fee or exchange rate change."),
+            )
         )
         .subcommand(SubCommand::with_name("list")
             .about("List stake accounts managed by this pool")
@@ -2302,6 +3863,26 @@ fn main() {
                     .help("Stake pool address."),
             )
         )
+        .subcommand(SubCommand::with_name("stats")
+            .about("Show the pool's exchange rate history, estimated APY, and validator health")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("epochs")
+                    .long("epochs")
+                    .validator(is_parsable::<u64>)
+                    .value_name("NUMBER")
+                    .takes_value(true)
+                    .help("Number of past epochs in the exchange rate history and APY [default: 10]"),
+            )
+        )
         .subcommand(SubCommand::with_name("update")
             .about("Updates all balances in the pool after validator stake accounts receive rewards.")
             .arg(
@@ -2320,14 +3901,171 @@ fn main() {
                     .help("Update all balances, even if it has already been performed this epoch."),
             )
             .arg(
-                Arg::with_name("no_merge")
-                    .long("no-merge")
-                    .takes_value(false)
-                    .help("Do not automatically merge transient stakes. Useful if the stake pool is in an expected state, but the balances still need to be updated."),
+                Arg::with_name("no_merge")
+                    .long("no-merge")
+                    .takes_value(false)
+                    .help("Do not automatically merge transient stakes. Useful if the stake pool is in an expected state, but the balances still need to be updated."),
+            )
+        )
+        .subcommand(SubCommand::with_name("crank")
+            .about("Keeps the pool updated, running the epoch update as soon as a new epoch starts. Several crankers can share the work.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("shard_index")
+                    .long("shard-index")
+                    .validator(is_parsable::<usize>)
+                    .value_name("INDEX")
+                    .takes_value(true)
+                    .default_value("0")
+                    .help("Index of the validator list chunks handled by this cranker, lower than the shard count."),
+            )
+            .arg(
+                Arg::with_name("shard_count")
+                    .long("shard-count")
+                    .validator(is_parsable::<usize>)
+                    .value_name("COUNT")
+                    .takes_value(true)
+                    .default_value("1")
+                    .help("Total number of crankers sharing the update."),
+            )
+            .arg(
+                Arg::with_name("interval")
+                    .long("interval")
+                    .validator(is_parsable::<u64>)
+                    .value_name("SECONDS")
+                    .takes_value(true)
+                    .default_value("60")
+                    .help("Seconds to wait between checks."),
+            )
+            .arg(
+                Arg::with_name("no_merge")
+                    .long("no-merge")
+                    .takes_value(false)
+                    .help("Do not automatically merge transient stakes."),
+            )
+        )
+        .subcommand(SubCommand::with_name("withdraw-stake")
+            .about("Withdraw active stake from the stake pool in exchange for pool tokens")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(2)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Amount of pool tokens to withdraw for activated stake."),
+            )
+            .arg(
+                Arg::with_name("pool_account")
+                    .long("pool-account")
+                    .validator(is_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Pool token account to withdraw tokens from. Defaults to the token-owner's associated token account."),
+            )
+            .arg(
+                Arg::with_name("stake_receiver")
+                    .long("stake-receiver")
+                    .validator(is_pubkey)
+                    .value_name("STAKE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .requires("withdraw_from")
+                    .help("Stake account from which to receive a stake from the stake pool. Defaults to a new stake account."),
+            )
+            .arg(
+                Arg::with_name("vote_account")
+                    .long("vote-account")
+                    .validator(is_pubkey)
+                    .value_name("VOTE_ACCOUNT_ADDRESS")
+                    .takes_value(true)
+                    .help("Validator to withdraw from. Defaults to the largest validator stakes in the pool."),
+            )
+            .arg(
+                Arg::with_name("use_reserve")
+                    .long("use-reserve")
+                    .takes_value(false)
+                    .help("Withdraw from the stake pool's reserve. Only possible if all validator stakes are at the minimum possible amount."),
+            )
+            .group(ArgGroup::with_name("withdraw_from")
+                .arg("use_reserve")
+                .arg("vote_account")
+            )
+            .arg(
+                Arg::with_name("minimum_sol_out")
+                    .long("minimum-sol-out")
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("Fail the withdrawal if less SOL than this amount would be received, \
+                          for example after a fee or exchange rate change."),
+            )
+        )
+        .subcommand(SubCommand::with_name("withdraw-sol")
+            .about("Withdraw SOL from the stake pool's reserve in exchange for pool tokens")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("sol_receiver")
+                    .index(2)
+                    .validator(is_valid_pubkey)
+                    .value_name("SYSTEM_ACCOUNT_ADDRESS_OR_KEYPAIR")
+                    .takes_value(true)
+                    .required(true)
+                    .help("System account to receive SOL from the stake pool. Defaults to the payer."),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(3)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Amount of pool tokens to withdraw for SOL."),
+            )
+            .arg(
+                Arg::with_name("pool_account")
+                    .long("pool-account")
+                    .validator(is_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Pool token account to withdraw tokens from. Defaults to the token-owner's associated token account."),
+            )
+            .arg(
+                Arg::with_name("minimum_sol_out")
+                    .long("minimum-sol-out")
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("Fail the withdrawal if less SOL than this amount would be received, \
+                          for example after a fee or exchange rate change."),
             )
         )
-        .subcommand(SubCommand::with_name("withdraw-stake")
-            .about("Withdraw active stake from the stake pool in exchange for pool tokens")
+        .subcommand(SubCommand::with_name("withdraw-to-ticket")
+            .about("Burn pool tokens in exchange for a withdrawal ticket, paid out in SOL once the stake is deactivated")
             .arg(
                 Arg::with_name("pool")
                     .index(1)
@@ -2344,7 +4082,7 @@ fn main() {
                     .value_name("AMOUNT")
                     .takes_value(true)
                     .required(true)
-                    .help("Amount of pool tokens to withdraw for activated stake."),
+                    .help("Amount of pool tokens to withdraw."),
             )
             .arg(
                 Arg::with_name("pool_account")
@@ -2355,35 +4093,17 @@ fn main() {
                     .help("Pool token account to withdraw tokens from. Defaults to the token-owner's associated token account."),
             )
             .arg(
-                Arg::with_name("stake_receiver")
-                    .long("stake-receiver")
-                    .validator(is_pubkey)
-                    .value_name("STAKE_ACCOUNT_ADDRESS")
-                    .takes_value(true)
-                    .requires("withdraw_from")
-                    .help("Stake account from which to receive a stake from the stake pool. Defaults to a new stake account."),
-            )
-            .arg(
-                Arg::with_name("vote_account")
-                    .long("vote-account")
-                    .validator(is_pubkey)
-                    .value_name("VOTE_ACCOUNT_ADDRESS")
+                Arg::with_name("minimum_sol_out")
+                    .long("minimum-sol-out")
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
                     .takes_value(true)
-                    .help("Validator to withdraw from. Defaults to the largest validator stakes in the pool."),
-            )
-            .arg(
-                Arg::with_name("use_reserve")
-                    .long("use-reserve")
-                    .takes_value(false)
-                    .help("Withdraw from the stake pool's reserve. Only possible if all validator stakes are at the minimum possible amount."),
-            )
-            .group(ArgGroup::with_name("withdraw_from")
-                .arg("use_reserve")
-                .arg("vote_account")
+                    .help("Fail the withdrawal if the ticket would be worth less SOL than this amount, \
+                          for example after a fee or exchange rate change."),
             )
         )
-        .subcommand(SubCommand::with_name("withdraw-sol")
-            .about("Withdraw SOL from the stake pool's reserve in exchange for pool tokens")
+        .subcommand(SubCommand::with_name("claim-ticket")
+            .about("Claim the SOL of a withdrawal ticket owned by the token owner, from the epoch after the withdrawal")
             .arg(
                 Arg::with_name("pool")
                     .index(1)
@@ -2394,30 +4114,21 @@ fn main() {
                     .help("Stake pool address."),
             )
             .arg(
-                Arg::with_name("sol_receiver")
+                Arg::with_name("ticket")
                     .index(2)
-                    .validator(is_valid_pubkey)
-                    .value_name("SYSTEM_ACCOUNT_ADDRESS_OR_KEYPAIR")
-                    .takes_value(true)
-                    .required(true)
-                    .help("System account to receive SOL from the stake pool. Defaults to the payer."),
-            )
-            .arg(
-                Arg::with_name("amount")
-                    .index(3)
-                    .validator(is_amount)
-                    .value_name("AMOUNT")
+                    .validator(is_pubkey)
+                    .value_name("TICKET_ADDRESS")
                     .takes_value(true)
                     .required(true)
-                    .help("Amount of pool tokens to withdraw for SOL."),
+                    .help("Withdrawal ticket address."),
             )
             .arg(
-                Arg::with_name("pool_account")
-                    .long("pool-account")
-                    .validator(is_pubkey)
-                    .value_name("ADDRESS")
+                Arg::with_name("sol_receiver")
+                    .long("sol-receiver")
+                    .validator(is_valid_pubkey)
+                    .value_name("SYSTEM_ACCOUNT_ADDRESS")
                     .takes_value(true)
-                    .help("Pool token account to withdraw tokens from. Defaults to the token-owner's associated token account."),
+                    .help("System account to receive the SOL. Defaults to the token owner."),
             )
         )
         .subcommand(SubCommand::with_name("set-manager")
@@ -2474,6 +4185,18 @@ fn main() {
                     .help("Public key for the new stake pool staker."),
             )
         )
+        .subcommand(SubCommand::with_name("realloc-pool")
+            .about("Grow a stake pool account created by an older version of the program to the current layout. Anyone can grow the account, with the fee payer funding it.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+        )
         .subcommand(SubCommand::with_name("set-funding-authority")
             .about("Change one of the funding authorities for the stake pool. Must be signed by the manager.")
             .arg(
@@ -2528,7 +4251,7 @@ fn main() {
             .arg(Arg::with_name("fee_type")
                 .index(2)
                 .value_name("FEE_TYPE")
-                .possible_values(&["epoch", "stake-deposit", "sol-deposit", "stake-withdrawal", "sol-withdrawal"]) // FeeType enum
+                .possible_values(&["epoch", "stake-deposit", "sol-deposit", "stake-withdrawal", "sol-withdrawal", "redelegation-deposit"]) // FeeType enum
                 .takes_value(true)
                 .required(true)
                 .help("Fee type to be updated."),
@@ -2552,6 +4275,89 @@ fn main() {
                     .help("Fee denominator, fee amount is numerator divided by denominator."),
             )
         )
+        .subcommand(SubCommand::with_name("set-liquidity-buffer")
+            .about("Set the share of the pool kept in the reserve for instant SOL withdrawals, and the SOL withdrawal fee charged when the reserve is empty. \
+                    Below the target, the SOL withdrawal fee rises from the normal fee to the maximum fee. Must be signed by the manager.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("target_numerator")
+                    .index(2)
+                    .validator(is_parsable::<u64>)
+                    .value_name("TARGET_NUMERATOR")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Target numerator, the target share of total lamports is numerator divided by denominator. Use 0 to disable the liquidity buffer."),
+            )
+            .arg(
+                Arg::with_name("target_denominator")
+                    .index(3)
+                    .validator(is_parsable::<u64>)
+                    .value_name("TARGET_DENOMINATOR")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Target denominator, the target share of total lamports is numerator divided by denominator."),
+            )
+            .arg(
+                Arg::with_name("max_fee_numerator")
+                    .index(4)
+                    .validator(is_parsable::<u64>)
+                    .value_name("MAX_FEE_NUMERATOR")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Maximum SOL withdrawal fee numerator, fee amount is numerator divided by denominator."),
+            )
+            .arg(
+                Arg::with_name("max_fee_denominator")
+                    .index(5)
+                    .validator(is_parsable::<u64>)
+                    .value_name("MAX_FEE_DENOMINATOR")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Maximum SOL withdrawal fee denominator, fee amount is numerator divided by denominator."),
+            )
+        )
+        .subcommand(SubCommand::with_name("set-fee-vesting")
+            .about("Vest the epoch fee over several epochs instead of minting it to the manager fee account. \
+                    Changing the manager or disabling vesting forfeits the fees still locked. Must be signed by the manager.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("vesting_epochs")
+                    .index(2)
+                    .validator(is_parsable::<u64>)
+                    .value_name("EPOCHS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Number of epochs over which the fees vest. Use 0 to disable fee vesting."),
+            )
+        )
+        .subcommand(SubCommand::with_name("claim-manager-fee")
+            .about("Transfer the vested epoch fees to the manager fee account.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+        )
         .subcommand(SubCommand::with_name("set-referral-fee")
             .about("Change the referral fee assessed by the stake pool for stake deposits. Must be signed by the manager.")
             .arg(
@@ -2584,6 +4390,78 @@ fn main() {
         .subcommand(SubCommand::with_name("list-all")
             .about("List information about all stake pools")
         )
+        .subcommand(SubCommand::with_name("create-token-metadata")
+            .about("Creates the name, symbol and uri of the pool token. Must be signed by the manager.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("name")
+                    .index(2)
+                    .value_name("TOKEN_NAME")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Name of the pool token, maximum 32 bytes."),
+            )
+            .arg(
+                Arg::with_name("symbol")
+                    .index(3)
+                    .value_name("TOKEN_SYMBOL")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Symbol of the pool token, maximum 10 bytes."),
+            )
+            .arg(
+                Arg::with_name("uri")
+                    .index(4)
+                    .value_name("TOKEN_URI")
+                    .takes_value(true)
+                    .required(true)
+                    .help("URI of a JSON document describing the pool token, maximum 200 bytes."),
+            )
+        )
+        .subcommand(SubCommand::with_name("update-token-metadata")
+            .about("Updates the name, symbol and uri of the pool token. Must be signed by the manager.")
+            .arg(
+                Arg::with_name("pool")
+                    .index(1)
+                    .validator(is_pubkey)
+                    .value_name("POOL_ADDRESS")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Stake pool address."),
+            )
+            .arg(
+                Arg::with_name("name")
+                    .index(2)
+                    .value_name("TOKEN_NAME")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Name of the pool token, maximum 32 bytes."),
+            )
+            .arg(
+                Arg::with_name("symbol")
+                    .index(3)
+                    .value_name("TOKEN_SYMBOL")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Symbol of the pool token, maximum 10 bytes."),
+            )
+            .arg(
+                Arg::with_name("uri")
+                    .index(4)
+                    .value_name("TOKEN_URI")
+                    .takes_value(true)
+                    .required(true)
+                    .help("URI of a JSON document describing the pool token, maximum 200 bytes."),
+            )
+        )
         .get_matches();
 
     let mut wallet_manager = None;
@@ -2692,6 +4570,11 @@ fn main() {
             let mint_keypair = keypair_of(arg_matches, "mint_keypair");
             let reserve_keypair = keypair_of(arg_matches, "reserve_keypair");
             let unsafe_fees = arg_matches.is_present("unsafe_fees");
+            let token_program_id = if arg_matches.is_present("token_2022") {
+                spl_token_2022::id()
+            } else {
+                spl_token::id()
+            };
             command_create_pool(
                 &config,
                 deposit_authority,
@@ -2714,6 +4597,7 @@ fn main() {
                 mint_keypair,
                 reserve_keypair,
                 unsafe_fees,
+                token_program_id,
             )
         }
         ("add-validator", Some(arg_matches)) => {
@@ -2746,6 +4630,25 @@ fn main() {
             let amount = value_t_or_exit!(arg_matches, "amount", f64);
             command_decrease_validator_stake(&config, &stake_pool_address, &vote_account, amount)
         }
+        ("rebalance", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let scores_path = arg_matches.value_of("scores");
+            command_rebalance(&config, &stake_pool_address, scores_path)
+        }
+        ("redelegate", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let source_vote_account = pubkey_of(arg_matches, "source_vote_account").unwrap();
+            let destination_vote_account =
+                pubkey_of(arg_matches, "destination_vote_account").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            command_redelegate(
+                &config,
+                &stake_pool_address,
+                &source_vote_account,
+                &destination_vote_account,
+                amount,
+            )
+        }
         ("set-preferred-validator", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             let preferred_type = match arg_matches.value_of("preferred_type").unwrap() {
@@ -2764,9 +4667,26 @@ fn main() {
                 vote_account,
             )
         }
+        ("set-validator-config", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let vote_account = pubkey_of(arg_matches, "vote_account").unwrap();
+            let max_stake_bps = value_t!(arg_matches, "max_stake_bps", u16).ok();
+            let no_deposits = arg_matches
+                .value_of("deposits")
+                .map(|deposits| deposits == "deny");
+            command_set_validator_config(
+                &config,
+                &stake_pool_address,
+                &vote_account,
+                max_stake_bps,
+                no_deposits,
+            )
+        }
         ("deposit-stake", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             let stake_account = pubkey_of(arg_matches, "stake_account").unwrap();
+            let minimum_pool_tokens_out =
+                value_t!(arg_matches, "minimum_pool_tokens_out", f64).ok();
             let token_receiver: Option<Pubkey> = pubkey_of(arg_matches, "token_receiver");
             let referrer: Option<Pubkey> = pubkey_of(arg_matches, "referrer");
             let withdraw_authority = get_signer(
@@ -2785,6 +4705,7 @@ fn main() {
                 withdraw_authority,
                 &token_receiver,
                 &referrer,
+                minimum_pool_tokens_out,
             )
         }
         ("deposit-sol", Some(arg_matches)) => {
@@ -2793,6 +4714,8 @@ fn main() {
             let referrer: Option<Pubkey> = pubkey_of(arg_matches, "referrer");
             let from = keypair_of(arg_matches, "from");
             let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let minimum_pool_tokens_out =
+                value_t!(arg_matches, "minimum_pool_tokens_out", f64).ok();
             command_deposit_sol(
                 &config,
                 &stake_pool_address,
@@ -2800,18 +4723,39 @@ fn main() {
                 &token_receiver,
                 &referrer,
                 amount,
+                minimum_pool_tokens_out,
             )
         }
         ("list", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             command_list(&config, &stake_pool_address)
         }
+        ("stats", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let epochs = value_t!(arg_matches, "epochs", u64).unwrap_or(DEFAULT_HISTORY_EPOCHS);
+            command_stats(&config, &stake_pool_address, epochs)
+        }
         ("update", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             let no_merge = arg_matches.is_present("no_merge");
             let force = arg_matches.is_present("force");
             command_update(&config, &stake_pool_address, force, no_merge)
         }
+        ("crank", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let shard_index = value_t_or_exit!(arg_matches, "shard_index", usize);
+            let shard_count = value_t_or_exit!(arg_matches, "shard_count", usize);
+            let interval = value_t_or_exit!(arg_matches, "interval", u64);
+            let no_merge = arg_matches.is_present("no_merge");
+            command_crank(
+                &config,
+                &stake_pool_address,
+                shard_index,
+                shard_count,
+                Duration::from_secs(interval),
+                no_merge,
+            )
+        }
         ("withdraw-stake", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             let vote_account = pubkey_of(arg_matches, "vote_account");
@@ -2819,6 +4763,7 @@ fn main() {
             let pool_amount = value_t_or_exit!(arg_matches, "amount", f64);
             let stake_receiver = pubkey_of(arg_matches, "stake_receiver");
             let use_reserve = arg_matches.is_present("use_reserve");
+            let minimum_sol_out = value_t!(arg_matches, "minimum_sol_out", f64).ok();
             command_withdraw_stake(
                 &config,
                 &stake_pool_address,
@@ -2827,6 +4772,7 @@ fn main() {
                 &stake_receiver,
                 &pool_account,
                 pool_amount,
+                minimum_sol_out,
             )
         }
         ("withdraw-sol", Some(arg_matches)) => {
@@ -2843,12 +4789,38 @@ fn main() {
                 },
             )
             .pubkey();
+            let minimum_sol_out = value_t!(arg_matches, "minimum_sol_out", f64).ok();
             command_withdraw_sol(
                 &config,
                 &stake_pool_address,
                 &pool_account,
                 &sol_receiver,
                 pool_amount,
+                minimum_sol_out,
+            )
+        }
+        ("withdraw-to-ticket", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let pool_account = pubkey_of(arg_matches, "pool_account");
+            let pool_amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let minimum_sol_out = value_t!(arg_matches, "minimum_sol_out", f64).ok();
+            command_withdraw_to_ticket(
+                &config,
+                &stake_pool_address,
+                &pool_account,
+                pool_amount,
+                minimum_sol_out,
+            )
+        }
+        ("claim-ticket", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let withdraw_ticket_address = pubkey_of(arg_matches, "ticket").unwrap();
+            let sol_receiver = pubkey_of(arg_matches, "sol_receiver");
+            command_claim_ticket(
+                &config,
+                &stake_pool_address,
+                &withdraw_ticket_address,
+                &sol_receiver,
             )
         }
         ("set-manager", Some(arg_matches)) => {
@@ -2867,6 +4839,10 @@ fn main() {
             let new_staker = pubkey_of(arg_matches, "new_staker").unwrap();
             command_set_staker(&config, &stake_pool_address, &new_staker)
         }
+        ("realloc-pool", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            command_realloc_pool(&config, &stake_pool_address)
+        }
         ("set-funding-authority", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             let new_authority = pubkey_of(arg_matches, "new_authority");
@@ -2879,6 +4855,32 @@ fn main() {
             let _unset = arg_matches.is_present("unset");
             command_set_funding_authority(&config, &stake_pool_address, new_authority, funding_type)
         }
+        ("set-liquidity-buffer", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let target_reserve = Fee {
+                numerator: value_t_or_exit!(arg_matches, "target_numerator", u64),
+                denominator: value_t_or_exit!(arg_matches, "target_denominator", u64),
+            };
+            let max_sol_withdrawal_fee = Fee {
+                numerator: value_t_or_exit!(arg_matches, "max_fee_numerator", u64),
+                denominator: value_t_or_exit!(arg_matches, "max_fee_denominator", u64),
+            };
+            command_set_liquidity_buffer(
+                &config,
+                &stake_pool_address,
+                target_reserve,
+                max_sol_withdrawal_fee,
+            )
+        }
+        ("set-fee-vesting", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let vesting_epochs = value_t_or_exit!(arg_matches, "vesting_epochs", u64);
+            command_set_fee_vesting(&config, &stake_pool_address, vesting_epochs)
+        }
+        ("claim-manager-fee", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            command_claim_manager_fee(&config, &stake_pool_address)
+        }
         ("set-fee", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             let numerator = value_t_or_exit!(arg_matches, "fee_numerator", u64);
@@ -2905,6 +4907,11 @@ fn main() {
                     &stake_pool_address,
                     FeeType::SolWithdrawal(new_fee),
                 ),
+                "redelegation-deposit" => command_set_fee(
+                    &config,
+                    &stake_pool_address,
+                    FeeType::RedelegationDeposit(new_fee),
+                ),
                 _ => unreachable!(),
             }
         }
@@ -2924,6 +4931,20 @@ fn main() {
             command_set_fee(&config, &stake_pool_address, fee_type)
         }
         ("list-all", _) => command_list_all_pools(&config),
+        ("create-token-metadata", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let name = value_t_or_exit!(arg_matches, "name", String);
+            let symbol = value_t_or_exit!(arg_matches, "symbol", String);
+            let uri = value_t_or_exit!(arg_matches, "uri", String);
+            command_create_token_metadata(&config, &stake_pool_address, name, symbol, uri)
+        }
+        ("update-token-metadata", Some(arg_matches)) => {
+            let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
+            let name = value_t_or_exit!(arg_matches, "name", String);
+            let symbol = value_t_or_exit!(arg_matches, "symbol", String);
+            let uri = value_t_or_exit!(arg_matches, "uri", String);
+            command_update_token_metadata(&config, &stake_pool_address, name, symbol, uri)
+        }
         ("deposit-all-stake", Some(arg_matches)) => {
             let stake_pool_address = pubkey_of(arg_matches, "pool").unwrap();
             let stake_authority = pubkey_of(arg_matches, "stake_authority").unwrap();
@@ -2954,3 +4975,68 @@ fn main() {
         exit(1);
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-12,
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn exchange_rate() {
+        assert_eq!(lamports_per_pool_token(0, 0), None);
+        assert_eq!(lamports_per_pool_token(100, 0), None);
+        assert_eq!(lamports_per_pool_token(0, 100), None);
+        assert_eq!(lamports_per_pool_token(100, 100), Some(1.0));
+        assert_eq!(lamports_per_pool_token(150, 100), Some(1.5));
+
+        assert_eq!(epoch_yield(None, Some(1.0)), None);
+        assert_eq!(epoch_yield(Some(1.0), None), None);
+        assert_close(epoch_yield(Some(1.0), Some(1.0)).unwrap(), 0.0);
+        assert_close(epoch_yield(Some(1.0), Some(1.01)).unwrap(), 0.01);
+        assert_close(epoch_yield(Some(2.0), Some(1.5)).unwrap(), -0.25);
+    }
+
+    #[test]
+    fn yield_from_rewards() {
+        let reward = |amount, post_balance| RpcInflationReward {
+            epoch: 0,
+            effective_slot: 0,
+            amount,
+            post_balance,
+            commission: None,
+        };
+        let no_fee = Fee::default();
+        assert_eq!(rewards_epoch_yield(&[], &no_fee), None);
+        assert_close(
+            rewards_epoch_yield(&[reward(10, 1_010), reward(30, 2_030)], &no_fee).unwrap(),
+            40.0 / 3_000.0,
+        );
+        let epoch_fee = Fee {
+            numerator: 1,
+            denominator: 10,
+        };
+        assert_close(
+            rewards_epoch_yield(&[reward(10, 1_010), reward(30, 2_030)], &epoch_fee).unwrap(),
+            40.0 / 3_000.0 * 0.9,
+        );
+    }
+
+    #[test]
+    fn apy_compounds_average_yield() {
+        assert_eq!(apy(&[], 100.0), None);
+        assert_close(apy(&[0.0, 0.0], 100.0).unwrap(), 0.0);
+        assert_close(apy(&[0.01], 2.0).unwrap(), 1.01 * 1.01 - 1.0);
+        assert_close(apy(&[0.001; 4], 365.0).unwrap(), 1.001f64.powf(365.0) - 1.0);
+        // the growth over the history is spread evenly over its epochs
+        assert_close(apy(&[0.02, 0.0], 2.0).unwrap(), 0.02);
+        assert_close(apy(&[0.01, 0.03], 2.0).unwrap(), 1.01 * 1.03 - 1.0);
+    }
+}
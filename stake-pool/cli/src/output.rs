@@ -3,7 +3,9 @@ use {
     solana_cli_output::{QuietDisplay, VerboseDisplay},
     solana_sdk::native_token::Sol,
     solana_sdk::{pubkey::Pubkey, stake::state::Lockup},
-    spl_stake_pool::state::{Fee, StakePool, StakeStatus, ValidatorList, ValidatorStakeInfo},
+    spl_stake_pool::state::{
+        Fee, FeeVesting, LiquidityBuffer, StakePool, StakeStatus, ValidatorList, ValidatorStakeInfo,
+    },
     std::fmt::{Display, Formatter, Result, Write},
 };
 
@@ -70,6 +72,11 @@ pub(crate) struct CliStakePool {
     pub next_sol_withdrawal_fee: Option<CliStakePoolFee>,
     pub last_epoch_pool_token_supply: u64,
     pub last_epoch_total_lamports: u64,
+    pub liquidity_buffer: Option<CliStakePoolLiquidityBuffer>,
+    pub fee_vesting: Option<CliStakePoolFeeVesting>,
+    pub withdraw_ticket_lamports: u64,
+    pub redelegation_deposit_fee: CliStakePoolFee,
+    pub pending_redelegation_lamports: u64,
     pub details: Option<CliStakePoolDetails>,
 }
 
@@ -106,6 +113,9 @@ impl VerboseDisplay for CliStakePool {
         writeln!(w, "Withdraw Authority: {}", &self.pool_withdraw_authority)?;
         writeln!(w, "Pool Token Mint: {}", &self.pool_mint)?;
         writeln!(w, "Fee Account: {}", &self.manager_fee_account)?;
+        if let Some(fee_vesting) = &self.fee_vesting {
+            writeln!(w, "{}", fee_vesting)?;
+        }
         match &self.preferred_deposit_validator_vote_address {
             None => {}
             Some(s) => {
@@ -146,6 +156,9 @@ impl VerboseDisplay for CliStakePool {
                 next_sol_withdrawal_fee
             )?;
         }
+        if let Some(liquidity_buffer) = &self.liquidity_buffer {
+            writeln!(w, "{}", liquidity_buffer)?;
+        }
         writeln!(
             w,
             "Stake Deposit Fee: {} of deposit amount",
@@ -156,6 +169,11 @@ impl VerboseDisplay for CliStakePool {
             "SOL Deposit Fee: {} of deposit amount",
             &self.sol_deposit_fee
         )?;
+        writeln!(
+            w,
+            "Redelegation Deposit Fee: {} of deposit amount",
+            &self.redelegation_deposit_fee
+        )?;
         writeln!(
             w,
             "Stake Deposit Referral Fee: {}% of Stake Deposit Fee",
@@ -166,6 +184,20 @@ impl VerboseDisplay for CliStakePool {
             "SOL Deposit Referral Fee: {}% of SOL Deposit Fee",
             &self.sol_referral_fee
         )?;
+        if self.withdraw_ticket_lamports > 0 {
+            writeln!(
+                w,
+                "Owed to Withdrawal Tickets: {}",
+                Sol(self.withdraw_ticket_lamports)
+            )?;
+        }
+        if self.pending_redelegation_lamports > 0 {
+            writeln!(
+                w,
+                "Pending Redelegation: {}",
+                Sol(self.pending_redelegation_lamports)
+            )?;
+        }
         writeln!(w)?;
         writeln!(w, "Stake Accounts")?;
         writeln!(w, "--------------")?;
@@ -201,6 +233,13 @@ impl Display for CliStakePool {
             }
         }
         writeln!(f, "Epoch Fee: {} of epoch rewards", &self.epoch_fee)?;
+        if let Some(fee_vesting) = &self.fee_vesting {
+            writeln!(
+                f,
+                "Epoch Fee Vesting: {} epochs",
+                fee_vesting.vesting_epochs
+            )?;
+        }
         writeln!(
             f,
             "Stake Withdrawal Fee: {} of withdrawal amount",
@@ -211,6 +250,9 @@ impl Display for CliStakePool {
             "SOL Withdrawal Fee: {} of withdrawal amount",
             &self.sol_withdrawal_fee
         )?;
+        if let Some(liquidity_buffer) = &self.liquidity_buffer {
+            writeln!(f, "{}", liquidity_buffer)?;
+        }
         writeln!(
             f,
             "Stake Deposit Fee: {} of deposit amount",
@@ -221,6 +263,11 @@ impl Display for CliStakePool {
             "SOL Deposit Fee: {} of deposit amount",
             &self.sol_deposit_fee
         )?;
+        writeln!(
+            f,
+            "Redelegation Deposit Fee: {} of deposit amount",
+            &self.redelegation_deposit_fee
+        )?;
         writeln!(
             f,
             "Stake Deposit Referral Fee: {}% of Stake Deposit Fee",
@@ -246,6 +293,7 @@ pub(crate) struct CliStakePoolDetails {
     pub total_pool_tokens: f64,
     pub current_number_of_validators: u32,
     pub max_number_of_validators: u32,
+    pub updated_validators: u32,
     pub update_required: bool,
 }
 
@@ -306,13 +354,26 @@ impl VerboseDisplay for CliStakePoolDetails {
         for stake_account in &self.stake_accounts {
             writeln!(
                 w,
-                "Vote Account: {}\tStake Account: {}\tActive Balance: {}\tTransient Stake Account: {}\tTransient Balance: {}\tLast Update Epoch: {}{}",
+                "Vote Account: {}\tStake Account: {}\tActive Balance: {}\tTransient Stake Account: {}\tTransient Balance: {}\tLast Update Epoch: {}{}{}{}",
                 stake_account.vote_account_address,
                 stake_account.stake_account_address,
                 Sol(stake_account.validator_active_stake_lamports),
                 stake_account.validator_transient_stake_account_address,
                 Sol(stake_account.validator_transient_stake_lamports),
                 stake_account.validator_last_update_epoch,
+                if stake_account.validator_max_stake_bps > 0 {
+                    format!(
+                        "\tStake Cap: {}%",
+                        stake_account.validator_max_stake_bps as f64 / 100.0
+                    )
+                } else {
+                    String::new()
+                },
+                if stake_account.validator_no_deposits {
+                    " [NO DEPOSITS]"
+                } else {
+                    ""
+                },
                 if stake_account.update_required {
                     " [UPDATE REQUIRED]"
                 } else {
@@ -341,6 +402,11 @@ impl VerboseDisplay for CliStakePoolDetails {
             "Max Number of Validators: {}",
             &self.max_number_of_validators,
         )?;
+        writeln!(
+            w,
+            "Validators Updated This Epoch: {} of {}",
+            &self.updated_validators, &self.current_number_of_validators,
+        )?;
         Ok(())
     }
 }
@@ -355,6 +421,8 @@ pub(crate) struct CliStakePoolStakeAccountInfo {
     pub validator_lamports: u64,
     pub validator_transient_stake_account_address: String,
     pub validator_transient_stake_lamports: u64,
+    pub validator_max_stake_bps: u16,
+    pub validator_no_deposits: bool,
     pub update_required: bool,
 }
 
@@ -365,7 +433,8 @@ pub(crate) struct CliStakePoolValidator {
     pub transient_stake_lamports: u64,
     pub last_update_epoch: u64,
     pub transient_seed_suffix_start: u64,
-    pub transient_seed_suffix_end: u64,
+    pub max_stake_bps: u16,
+    pub no_deposits: bool,
     pub status: CliStakePoolValidatorStakeStatus,
     pub vote_account_address: String,
 }
@@ -377,7 +446,8 @@ impl From<ValidatorStakeInfo> for CliStakePoolValidator {
             transient_stake_lamports: v.transient_stake_lamports,
             last_update_epoch: v.last_update_epoch,
             transient_seed_suffix_start: v.transient_seed_suffix_start,
-            transient_seed_suffix_end: v.transient_seed_suffix_end,
+            max_stake_bps: v.max_stake_bps,
+            no_deposits: v.no_deposits,
             status: CliStakePoolValidatorStakeStatus::from(v.status),
             vote_account_address: v.vote_account_address.to_string(),
         }
@@ -443,6 +513,79 @@ impl From<Fee> for CliStakePoolFee {
     }
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliStakePoolLiquidityBuffer {
+    pub target_reserve: CliStakePoolFee,
+    pub max_sol_withdrawal_fee: CliStakePoolFee,
+    pub deficit_lamports: u64,
+}
+
+impl Display for CliStakePoolLiquidityBuffer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(
+            f,
+            "Liquidity Buffer: {} of total lamports kept in the reserve",
+            &self.target_reserve
+        )?;
+        writeln!(
+            f,
+            "Maximum SOL Withdrawal Fee: {} of withdrawal amount",
+            &self.max_sol_withdrawal_fee
+        )?;
+        write!(
+            f,
+            "Liquidity Buffer Deficit: {}",
+            Sol(self.deficit_lamports)
+        )
+    }
+}
+
+impl From<LiquidityBuffer> for CliStakePoolLiquidityBuffer {
+    fn from(l: LiquidityBuffer) -> Self {
+        Self {
+            target_reserve: CliStakePoolFee::from(l.target_reserve),
+            max_sol_withdrawal_fee: CliStakePoolFee::from(l.max_sol_withdrawal_fee),
+            deficit_lamports: l.deficit_lamports,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliStakePoolFeeVesting {
+    pub vesting_account: String,
+    pub vesting_epochs: u64,
+    pub locked_amount: u64,
+    pub vested_amount: u64,
+    pub end_epoch: u64,
+}
+
+impl Display for CliStakePoolFeeVesting {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "Fee Vesting Account: {}", &self.vesting_account)?;
+        writeln!(f, "Epoch Fee Vesting: {} epochs", self.vesting_epochs)?;
+        writeln!(
+            f,
+            "Locked Fee Tokens: {}, fully vested at epoch {}",
+            self.locked_amount, self.end_epoch
+        )?;
+        write!(f, "Vested Fee Tokens: {}", self.vested_amount)
+    }
+}
+
+impl From<FeeVesting> for CliStakePoolFeeVesting {
+    fn from(v: FeeVesting) -> Self {
+        Self {
+            vesting_account: v.vesting_account.to_string(),
+            vesting_epochs: v.vesting_epochs,
+            locked_amount: v.locked_amount,
+            vested_amount: v.vested_amount,
+            end_epoch: v.end_epoch,
+        }
+    }
+}
+
 impl From<(Pubkey, StakePool, ValidatorList, Pubkey)> for CliStakePool {
     fn from(s: (Pubkey, StakePool, ValidatorList, Pubkey)) -> Self {
         let (address, stake_pool, validator_list, pool_withdraw_authority) = s;
@@ -492,7 +635,168 @@ impl From<(Pubkey, StakePool, ValidatorList, Pubkey)> for CliStakePool {
                 .map(CliStakePoolFee::from),
             last_epoch_pool_token_supply: stake_pool.last_epoch_pool_token_supply,
             last_epoch_total_lamports: stake_pool.last_epoch_total_lamports,
+            liquidity_buffer: stake_pool
+                .liquidity_buffer
+                .map(CliStakePoolLiquidityBuffer::from),
+            fee_vesting: stake_pool.fee_vesting.map(CliStakePoolFeeVesting::from),
+            withdraw_ticket_lamports: stake_pool.withdraw_ticket_lamports,
+            redelegation_deposit_fee: CliStakePoolFee::from(stake_pool.redelegation_deposit_fee),
+            pending_redelegation_lamports: stake_pool.pending_redelegation_lamports,
             details: None,
         }
     }
 }
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliStakePoolStats {
+    pub address: String,
+    pub epoch: u64,
+    pub last_update_epoch: u64,
+    pub update_required: bool,
+    pub total_lamports: u64,
+    pub pool_token_supply: u64,
+    pub last_epoch_total_lamports: u64,
+    pub last_epoch_pool_token_supply: u64,
+    pub lamports_per_pool_token: Option<f64>,
+    pub last_epoch_lamports_per_pool_token: Option<f64>,
+    pub epoch_yield: Option<f64>,
+    pub epochs_per_year: f64,
+    pub apy: Option<f64>,
+    pub apy_epochs: u64,
+    pub history: Vec<CliStakePoolEpochYield>,
+    pub reserve_stake_lamports: u64,
+    pub withdraw_ticket_lamports: u64,
+    pub pending_redelegation_lamports: u64,
+    pub total_transient_stake_lamports: u64,
+    pub delinquent_validators: u32,
+    pub validators_not_updated: u32,
+    pub validators: Vec<CliStakePoolValidatorStats>,
+}
+
+impl Display for CliStakePoolStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "Stake Pool: {}", &self.address)?;
+        writeln!(
+            f,
+            "Epoch: {}\tLast Update Epoch: {}{}",
+            self.epoch,
+            self.last_update_epoch,
+            if self.update_required {
+                " [UPDATE REQUIRED]"
+            } else {
+                ""
+            },
+        )?;
+        writeln!(f, "Total Pool Stake: {}", Sol(self.total_lamports))?;
+        writeln!(f, "Pool Token Supply: {}", self.pool_token_supply)?;
+        match self.lamports_per_pool_token {
+            Some(rate) => writeln!(f, "SOL per Pool Token: {:.9}", rate / 1_000_000_000.0)?,
+            None => writeln!(f, "SOL per Pool Token: n/a")?,
+        }
+        match self.epoch_yield {
+            Some(epoch_yield) => writeln!(f, "Epoch Yield: {:.6}%", epoch_yield * 100.0)?,
+            None => writeln!(f, "Epoch Yield: n/a")?,
+        }
+        match self.apy {
+            Some(apy) => writeln!(
+                f,
+                "APY: {:.4}% (over the last {} epochs, assuming {:.1} epochs per year)",
+                apy * 100.0,
+                self.apy_epochs,
+                self.epochs_per_year
+            )?,
+            None => writeln!(f, "APY: n/a")?,
+        }
+        writeln!(f, "Reserve Stake: {}", Sol(self.reserve_stake_lamports))?;
+        writeln!(
+            f,
+            "Owed to Withdrawal Tickets: {}",
+            Sol(self.withdraw_ticket_lamports)
+        )?;
+        writeln!(
+            f,
+            "Pending Redelegation: {}",
+            Sol(self.pending_redelegation_lamports)
+        )?;
+        writeln!(
+            f,
+            "Transient Stake Outstanding: {}",
+            Sol(self.total_transient_stake_lamports)
+        )?;
+        writeln!(
+            f,
+            "Delinquent Validators: {} of {}",
+            self.delinquent_validators,
+            self.validators.len()
+        )?;
+        writeln!(
+            f,
+            "Validators Not Updated This Epoch: {} of {}",
+            self.validators_not_updated,
+            self.validators.len()
+        )?;
+        for epoch_yield in &self.history {
+            writeln!(f, "{}", epoch_yield)?;
+        }
+        for validator in &self.validators {
+            writeln!(
+                f,
+                "Vote Account: {}\tStake: {}\tShare: {:.4}%\tTransient Stake: {}{}{}",
+                validator.vote_account_address,
+                Sol(validator.stake_lamports),
+                validator.stake_share * 100.0,
+                Sol(validator.transient_stake_lamports),
+                if validator.delinquent {
+                    " [DELINQUENT]"
+                } else {
+                    ""
+                },
+                if validator.update_required {
+                    " [UPDATE REQUIRED]"
+                } else {
+                    ""
+                },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl QuietDisplay for CliStakePoolStats {}
+impl VerboseDisplay for CliStakePoolStats {}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliStakePoolValidatorStats {
+    pub vote_account_address: String,
+    pub stake_lamports: u64,
+    pub active_stake_lamports: u64,
+    pub transient_stake_lamports: u64,
+    pub stake_share: f64,
+    pub last_update_epoch: u64,
+    pub delinquent: bool,
+    pub update_required: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliStakePoolEpochYield {
+    pub epoch: u64,
+    pub epoch_yield: Option<f64>,
+    pub lamports_per_pool_token: Option<f64>,
+}
+
+impl Display for CliStakePoolEpochYield {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "Epoch: {}", self.epoch)?;
+        match self.epoch_yield {
+            Some(epoch_yield) => write!(f, "\tYield: {:.6}%", epoch_yield * 100.0)?,
+            None => write!(f, "\tYield: n/a")?,
+        }
+        match self.lamports_per_pool_token {
+            Some(rate) => write!(f, "\tSOL per Pool Token: {:.9}", rate / 1_000_000_000.0),
+            None => write!(f, "\tSOL per Pool Token: n/a"),
+        }
+    }
+}
@@ -9,7 +9,7 @@ import {
   SystemProgram,
   TransactionInstruction,
 } from '@solana/web3.js';
-import { ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID, Token } from '@solana/spl-token';
+import { ASSOCIATED_TOKEN_PROGRAM_ID, Token } from '@solana/spl-token';
 import {
   ValidatorAccount,
   addAssociatedTokenAccount,
@@ -28,7 +28,7 @@ import {
 import { StakePoolInstruction } from './instructions';
 import {
   StakePool,
-  StakePoolLayout,
+  decodeStakePool,
   ValidatorList,
   ValidatorListLayout,
   ValidatorStakeInfo,
@@ -82,7 +82,7 @@ export async function getStakePoolAccount(
   return {
     pubkey: stakePoolAddress,
     account: {
-      data: StakePoolLayout.decode(account.data),
+      data: decodeStakePool(account.data),
       executable: account.executable,
       lamports: account.lamports,
       owner: account.owner,
@@ -106,7 +106,7 @@ export async function getStakePoolAccounts(
 
     if (a.account.data.readUInt8() === 1) {
       try {
-        decodedData = StakePoolLayout.decode(a.account.data);
+        decodedData = decodeStakePool(a.account.data);
       } catch (error) {
         console.log('Could not decode StakeAccount. Error:', error);
         decodedData = undefined;
@@ -175,6 +175,7 @@ export async function depositStake(
       authorizedPubkey,
       poolMint,
       instructions,
+      stakePool.account.data.tokenProgramId,
     );
     poolTokenReceiverAccount = associatedAddress;
     rentFee += fee;
@@ -211,6 +212,7 @@ export async function depositStake(
       depositStake,
       validatorStake,
       poolMint,
+      tokenProgramId: stakePool.account.data.tokenProgramId,
     }),
   );
 
@@ -268,6 +270,7 @@ export async function depositSol(
       from,
       stakePool.poolMint,
       instructions,
+      stakePool.tokenProgramId,
     );
     destinationTokenAccount = associatedAddress;
     rentFee += fee;
@@ -287,6 +290,7 @@ export async function depositSol(
       managerFeeAccount: stakePool.managerFeeAccount,
       referralPoolAccount: referrerTokenAccount ?? destinationTokenAccount,
       poolMint: stakePool.poolMint,
+      tokenProgramId: stakePool.tokenProgramId,
       lamports,
       withdrawAuthority,
       depositAuthority,
@@ -320,7 +324,7 @@ export async function withdrawStake(
   if (!poolTokenAccount) {
     poolTokenAccount = await Token.getAssociatedTokenAddress(
       ASSOCIATED_TOKEN_PROGRAM_ID,
-      TOKEN_PROGRAM_ID,
+      stakePool.account.data.tokenProgramId,
       stakePool.account.data.poolMint,
       tokenOwner,
     );
@@ -410,7 +414,7 @@ export async function withdrawStake(
 
   instructions.push(
     Token.createApproveInstruction(
-      TOKEN_PROGRAM_ID,
+      stakePool.account.data.tokenProgramId,
       poolTokenAccount,
       userTransferAuthority.publicKey,
       tokenOwner,
@@ -467,6 +471,7 @@ export async function withdrawStake(
         sourcePoolAccount: poolTokenAccount,
         managerFeeAccount: stakePool.account.data.managerFeeAccount,
         poolMint: stakePool.account.data.poolMint,
+        tokenProgramId: stakePool.account.data.tokenProgramId,
         poolTokens: withdrawAccount.poolAmount,
         withdrawAuthority,
       }),
@@ -498,7 +503,7 @@ export async function withdrawSol(
 
   const poolTokenAccount = await Token.getAssociatedTokenAddress(
     ASSOCIATED_TOKEN_PROGRAM_ID,
-    TOKEN_PROGRAM_ID,
+    stakePool.account.data.tokenProgramId,
     stakePool.account.data.poolMint,
     tokenOwner,
   );
@@ -527,7 +532,7 @@ export async function withdrawSol(
 
   instructions.push(
     Token.createApproveInstruction(
-      TOKEN_PROGRAM_ID,
+      stakePool.account.data.tokenProgramId,
       poolTokenAccount,
       userTransferAuthority.publicKey,
       tokenOwner,
@@ -562,6 +567,7 @@ export async function withdrawSol(
     destinationSystemAccount: solReceiver,
     managerFeeAccount: stakePool.account.data.managerFeeAccount,
     poolMint: stakePool.account.data.poolMint,
+    tokenProgramId: stakePool.account.data.tokenProgramId,
     poolTokens: poolAmount,
     solWithdrawAuthority,
   });
@@ -762,8 +768,11 @@ export async function updateStakePool(
       stakePool: stakePoolAddress,
       validatorList: stakePool.account.data.validatorList,
       reserveStake: stakePool.account.data.reserveStake,
-      managerFeeAccount: stakePool.account.data.managerFeeAccount,
+      managerFeeAccount:
+        stakePool.account.data.feeVesting?.vestingAccount ??
+        stakePool.account.data.managerFeeAccount,
       poolMint: stakePool.account.data.poolMint,
+      tokenProgramId: stakePool.account.data.tokenProgramId,
       withdrawAuthority,
     }),
   );
@@ -854,7 +863,8 @@ export async function stakePoolInfo(connection: Connection, stakePoolAddress: Pu
         transientStakeLamports: validator.transientStakeLamports.toString(),
         lastUpdateEpoch: validator.lastUpdateEpoch.toString(),
         transientSeedSuffixStart: validator.transientSeedSuffixStart.toString(),
-        transientSeedSuffixEnd: validator.transientSeedSuffixEnd.toString(),
+        maxStakeBps: validator.maxStakeBps,
+        noDeposits: validator.noDeposits,
         status: validator.status.toString(),
         voteAccountAddress: validator.voteAccountAddress.toString(),
       };
@@ -129,6 +129,8 @@ export type UpdateStakePoolBalanceParams = {
   reserveStake: PublicKey;
   managerFeeAccount: PublicKey;
   poolMint: PublicKey;
+  // Defaults to the SPL token program
+  tokenProgramId?: PublicKey;
 };
 
 /**
@@ -179,6 +181,8 @@ export type DepositStakeParams = {
   managerFeeAccount: PublicKey;
   referralPoolAccount: PublicKey;
   poolMint: PublicKey;
+  // Defaults to the SPL token program
+  tokenProgramId?: PublicKey;
 };
 
 /**
@@ -195,6 +199,8 @@ export type WithdrawStakeParams = {
   sourcePoolAccount: PublicKey;
   managerFeeAccount: PublicKey;
   poolMint: PublicKey;
+  // Defaults to the SPL token program
+  tokenProgramId?: PublicKey;
   poolTokens: number;
 };
 
@@ -211,6 +217,8 @@ export type WithdrawSolParams = {
   solWithdrawAuthority?: PublicKey | undefined;
   managerFeeAccount: PublicKey;
   poolMint: PublicKey;
+  // Defaults to the SPL token program
+  tokenProgramId?: PublicKey;
   poolTokens: number;
 };
 
@@ -228,6 +236,8 @@ export type DepositSolParams = {
   managerFeeAccount: PublicKey;
   referralPoolAccount: PublicKey;
   poolMint: PublicKey;
+  // Defaults to the SPL token program
+  tokenProgramId?: PublicKey;
   lamports: number;
 };
 
@@ -255,7 +265,7 @@ export class StakePoolInstruction {
     const data = encodeData(type, { startIndex, noMerge: noMerge ? 1 : 0 });
 
     const keys = [
-      { pubkey: stakePool, isSigner: false, isWritable: false },
+      { pubkey: stakePool, isSigner: false, isWritable: true },
       { pubkey: withdrawAuthority, isSigner: false, isWritable: false },
       { pubkey: validatorList, isSigner: false, isWritable: true },
       { pubkey: reserveStake, isSigner: false, isWritable: true },
@@ -287,6 +297,7 @@ export class StakePoolInstruction {
       reserveStake,
       managerFeeAccount,
       poolMint,
+      tokenProgramId = TOKEN_PROGRAM_ID,
     } = params;
 
     const type = STAKE_POOL_INSTRUCTION_LAYOUTS.UpdateStakePoolBalance;
@@ -299,7 +310,7 @@ export class StakePoolInstruction {
       { pubkey: reserveStake, isSigner: false, isWritable: false },
       { pubkey: managerFeeAccount, isSigner: false, isWritable: true },
       { pubkey: poolMint, isSigner: false, isWritable: true },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: tokenProgramId, isSigner: false, isWritable: false },
     ];
 
     return new TransactionInstruction({
@@ -428,6 +439,7 @@ export class StakePoolInstruction {
       managerFeeAccount,
       referralPoolAccount,
       poolMint,
+      tokenProgramId = TOKEN_PROGRAM_ID,
     } = params;
 
     const type = STAKE_POOL_INSTRUCTION_LAYOUTS.DepositStake;
@@ -447,7 +459,7 @@ export class StakePoolInstruction {
       { pubkey: poolMint, isSigner: false, isWritable: true },
       { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
       { pubkey: SYSVAR_STAKE_HISTORY_PUBKEY, isSigner: false, isWritable: false },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: tokenProgramId, isSigner: false, isWritable: false },
       { pubkey: StakeProgram.programId, isSigner: false, isWritable: false },
     ];
 
@@ -472,6 +484,7 @@ export class StakePoolInstruction {
       managerFeeAccount,
       referralPoolAccount,
       poolMint,
+      tokenProgramId = TOKEN_PROGRAM_ID,
       lamports,
     } = params;
 
@@ -488,7 +501,7 @@ export class StakePoolInstruction {
       { pubkey: referralPoolAccount, isSigner: false, isWritable: true },
       { pubkey: poolMint, isSigner: false, isWritable: true },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: tokenProgramId, isSigner: false, isWritable: false },
     ];
 
     if (depositAuthority) {
@@ -521,6 +534,7 @@ export class StakePoolInstruction {
       sourcePoolAccount,
       managerFeeAccount,
       poolMint,
+      tokenProgramId = TOKEN_PROGRAM_ID,
       poolTokens,
     } = params;
 
@@ -539,7 +553,7 @@ export class StakePoolInstruction {
       { pubkey: managerFeeAccount, isSigner: false, isWritable: true },
       { pubkey: poolMint, isSigner: false, isWritable: true },
       { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: tokenProgramId, isSigner: false, isWritable: false },
       { pubkey: StakeProgram.programId, isSigner: false, isWritable: false },
     ];
 
@@ -564,6 +578,7 @@ export class StakePoolInstruction {
       managerFeeAccount,
       solWithdrawAuthority,
       poolMint,
+      tokenProgramId = TOKEN_PROGRAM_ID,
       poolTokens,
     } = params;
 
@@ -582,7 +597,7 @@ export class StakePoolInstruction {
       { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
       { pubkey: SYSVAR_STAKE_HISTORY_PUBKEY, isSigner: false, isWritable: false },
       { pubkey: StakeProgram.programId, isSigner: false, isWritable: false },
-      { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+      { pubkey: tokenProgramId, isSigner: false, isWritable: false },
     ];
 
     if (solWithdrawAuthority) {
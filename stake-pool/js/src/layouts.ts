@@ -1,4 +1,4 @@
-import { publicKey, struct, u32, u64, u8, option, vec } from '@project-serum/borsh';
+import { array, bool, publicKey, struct, u16, u32, u64, u8, option, vec } from '@project-serum/borsh';
 import { Lockup, PublicKey } from '@solana/web3.js';
 import { AccountInfo } from '@solana/spl-token';
 import BN from 'bn.js';
@@ -33,6 +33,21 @@ export enum AccountType {
   ValidatorList,
 }
 
+export interface LiquidityBuffer {
+  targetReserve: Fee;
+  maxSolWithdrawalFee: Fee;
+  deficitLamports: BN;
+}
+
+export interface FeeVesting {
+  vestingAccount: PublicKey;
+  vestingEpochs: BN;
+  lockedAmount: BN;
+  vestedAmount: BN;
+  lastVestingEpoch: BN;
+  endEpoch: BN;
+}
+
 export interface StakePool {
   accountType: AccountType;
   manager: PublicKey;
@@ -64,9 +79,17 @@ export interface StakePool {
   nextSolWithdrawalFee?: Fee | undefined;
   lastEpochPoolTokenSupply: BN;
   lastEpochTotalLamports: BN;
+  liquidityBuffer?: LiquidityBuffer | undefined;
+  feeVesting?: FeeVesting | undefined;
+  withdrawTicketLamports: BN;
+  redelegationDepositFee: Fee;
+  pendingRedelegationLamports: BN;
 }
 
-export const StakePoolLayout = struct<StakePool>([
+/**
+ * Fields of stake pool accounts created before the latest fields were added
+ */
+const stakePoolLegacyFields = [
   u8('accountType'),
   publicKey('manager'),
   publicKey('staker'),
@@ -97,56 +120,58 @@ export const StakePoolLayout = struct<StakePool>([
   option(struct(feeFields), 'nextSolWithdrawalFee'),
   u64('lastEpochPoolTokenSupply'),
   u64('lastEpochTotalLamports'),
-]);
+];
 
-export enum ValidatorStakeInfoStatus {
-  Active,
-  DeactivatingTransient,
-  ReadyForRemoval,
-}
+export const StakePoolLayout = struct<StakePool>([
+  ...stakePoolLegacyFields,
+  option(
+    struct<LiquidityBuffer>([
+      struct(feeFields, 'targetReserve'),
+      struct(feeFields, 'maxSolWithdrawalFee'),
+      u64('deficitLamports'),
+    ]),
+    'liquidityBuffer',
+  ),
+  option(
+    struct<FeeVesting>([
+      publicKey('vestingAccount'),
+      u64('vestingEpochs'),
+      u64('lockedAmount'),
+      u64('vestedAmount'),
+      u64('lastVestingEpoch'),
+      u64('endEpoch'),
+    ]),
+    'feeVesting',
+  ),
+  u64('withdrawTicketLamports'),
+  struct(feeFields, 'redelegationDepositFee'),
+  u64('pendingRedelegationLamports'),
+]);
 
-export interface ValidatorStakeInfo {
-  status: ValidatorStakeInfoStatus;
-  voteAccountAddress: PublicKey;
-  activeStakeLamports: BN;
-  transientStakeLamports: BN;
-  transientSeedSuffixStart: BN;
-  transientSeedSuffixEnd: BN;
-  lastUpdateEpoch: BN;
-}
+/**
+ * Size of stake pool accounts created before the liquidity buffer, fee vesting,
+ * withdrawal tickets and redelegation deposits
+ */
+export const STAKE_POOL_LEGACY_LEN = 611;
 
-export const ValidatorStakeInfoLayout = struct<ValidatorStakeInfo>([
-  /// Amount of active stake delegated to this validator
-  /// Note that if `last_update_epoch` does not match the current epoch then
-  /// this field may not be accurate
-  u64('activeStakeLamports'),
-  /// Amount of transient stake delegated to this validator
-  /// Note that if `last_update_epoch` does not match the current epoch then
-  /// this field may not be accurate
-  u64('transientStakeLamports'),
-  /// Last epoch the active and transient stake lamports fields were updated
-  u64('lastUpdateEpoch'),
-  /// Start of the validator transient account seed suffixes
-  u64('transientSeedSuffixStart'),
-  /// End of the validator transient account seed suffixes
-  u64('transientSeedSuffixEnd'),
-  /// Status of the validator stake account
-  u8('status'),
-  /// Validator vote account address
-  publicKey('voteAccountAddress'),
-]);
+const StakePoolLegacyLayout = struct<StakePool>(stakePoolLegacyFields);
 
-export interface ValidatorList {
-  /// Account type, must be ValidatorList currently
-  accountType: number;
-  /// Maximum allowable number of validators
-  maxValidators: number;
-  /// List of stake info for each validator in the pool
-  validators: ValidatorStakeInfo[];
+/**
+ * Decode a stake pool, including one created before the latest fields were
+ * added. Like the program, the account length tells whether the latest fields
+ * exist, since the rest of an older account may hold stale bytes. Otherwise
+ * they get their default values.
+ */
+export function decodeStakePool(data: Buffer): StakePool {
+  if (data.length > STAKE_POOL_LEGACY_LEN) {
+    return StakePoolLayout.decode(data);
+  }
+  return {
+    ...StakePoolLegacyLayout.decode(data),
+    liquidityBuffer: undefined,
+    feeVesting: undefined,
+    withdrawTicketLamports: new BN(0),
+    redelegationDepositFee: { denominator: new BN(0), numerator: new BN(0) },
+    pendingRedelegationLamports: new BN(0),
+  };
 }
-
-export const ValidatorListLayout = struct<ValidatorList>([
-  u8('accountType'),
-  u32('maxValidators'),
-  vec(ValidatorStakeInfoLayout, 'validators'),
-]);
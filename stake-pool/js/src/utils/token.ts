@@ -29,10 +29,11 @@ export async function addAssociatedTokenAccount(
   owner: PublicKey,
   mint: PublicKey,
   instructions: TransactionInstruction[],
+  tokenProgramId: PublicKey = TOKEN_PROGRAM_ID,
 ) {
   const associatedAddress = await Token.getAssociatedTokenAddress(
     ASSOCIATED_TOKEN_PROGRAM_ID,
-    TOKEN_PROGRAM_ID,
+    tokenProgramId,
     mint,
     owner,
   );
@@ -57,7 +58,7 @@ export async function addAssociatedTokenAccount(
       instructions.push(
         Token.createAssociatedTokenAccountInstruction(
           ASSOCIATED_TOKEN_PROGRAM_ID,
-          TOKEN_PROGRAM_ID,
+          tokenProgramId,
           mint,
           associatedAddress,
           owner,
@@ -1,4 +1,11 @@
-import { StakePoolLayout, ValidatorListLayout, ValidatorList } from '../src/layouts';
+import BN from 'bn.js';
+import {
+  decodeStakePool,
+  STAKE_POOL_LEGACY_LEN,
+  StakePoolLayout,
+  ValidatorListLayout,
+  ValidatorList,
+} from '../src/layouts';
 import { deepStrictEqualBN } from './equal';
 import { stakePoolMock, validatorListMock } from './mocks';
 
@@ -10,6 +17,39 @@ describe('layouts', () => {
       const decodedData = StakePoolLayout.decode(encodedData);
       deepStrictEqualBN(decodedData, stakePoolMock);
     });
+
+    it('should decode StakePoolAccount data created before the latest fields', () => {
+      const stakePool = {
+        ...stakePoolMock,
+        liquidityBuffer: undefined,
+        feeVesting: undefined,
+        withdrawTicketLamports: new BN(0),
+        pendingRedelegationLamports: new BN(0),
+      };
+      const encodedData = Buffer.alloc(1024);
+      const length = StakePoolLayout.encode(stakePool, encodedData);
+      // the latest fields take 34 bytes at their default values
+      const legacyData = encodedData.subarray(0, length - 34);
+      const decodedData = decodeStakePool(legacyData);
+      deepStrictEqualBN(decodedData, stakePool);
+    });
+
+    it('should not decode stale bytes of a StakePoolAccount created before the latest fields', () => {
+      const stakePool = {
+        ...stakePoolMock,
+        liquidityBuffer: undefined,
+        feeVesting: undefined,
+        withdrawTicketLamports: new BN(0),
+        pendingRedelegationLamports: new BN(0),
+      };
+      // bytes left over from optional fields that were later unset
+      const encodedData = Buffer.alloc(1024);
+      const length = StakePoolLayout.encode(stakePool, encodedData);
+      const legacyData = encodedData.subarray(0, STAKE_POOL_LEGACY_LEN);
+      legacyData.fill(2, length - 34);
+      const decodedData = decodeStakePool(legacyData);
+      deepStrictEqualBN(decodedData, stakePool);
+    });
   });
 
   describe('ValidatorListAccount', () => {
@@ -62,6 +62,24 @@ export const stakePoolMock = {
   },
   lastEpochPoolTokenSupply: new BN(0),
   lastEpochTotalLamports: new BN(0),
+  liquidityBuffer: {
+    targetReserve: {
+      denominator: new BN(100),
+      numerator: new BN(10),
+    },
+    maxSolWithdrawalFee: {
+      denominator: new BN(100),
+      numerator: new BN(5),
+    },
+    deficitLamports: new BN(0),
+  },
+  feeVesting: undefined,
+  withdrawTicketLamports: new BN(0),
+  redelegationDepositFee: {
+    denominator: new BN(0),
+    numerator: new BN(0),
+  },
+  pendingRedelegationLamports: new BN(0),
 };
 
 export const validatorListMock = {
@@ -77,7 +95,9 @@ export const validatorListMock = {
       activeStakeLamports: new BN(123),
       transientStakeLamports: new BN(999),
       transientSeedSuffixStart: new BN(999),
-      transientSeedSuffixEnd: new BN(999),
+      maxStakeBps: 2500,
+      noDeposits: true,
+      reserved: [0, 0, 0, 0, 0],
     },
     {
       status: ValidatorStakeInfoStatus.Active,
@@ -88,7 +108,9 @@ export const validatorListMock = {
       activeStakeLamports: new BN(LAMPORTS_PER_SOL * 100),
       transientStakeLamports: new BN(22),
       transientSeedSuffixStart: new BN(0),
-      transientSeedSuffixEnd: new BN(0),
+      maxStakeBps: 0,
+      noDeposits: false,
+      reserved: [0, 0, 0, 0, 0],
     },
     {
       status: ValidatorStakeInfoStatus.Active,
@@ -99,7 +121,9 @@ export const validatorListMock = {
       activeStakeLamports: new BN(0),
       transientStakeLamports: new BN(0),
       transientSeedSuffixStart: new BN('a', 'hex'),
-      transientSeedSuffixEnd: new BN('a', 'hex'),
+      maxStakeBps: 0,
+      noDeposits: false,
+      reserved: [0, 0, 0, 0, 0],
     },
   ],
 };
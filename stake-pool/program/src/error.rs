@@ -130,6 +130,66 @@ pub enum StakePoolError {
     /// Too much SOL withdrawn from the stake pool's reserve account
     #[error("SolWithdrawalTooLarge")]
     SolWithdrawalTooLarge,
+    /// Provided metadata account does not match metadata account derived for pool mint
+    #[error("InvalidMetadataAccount")]
+    InvalidMetadataAccount,
+    /// Token metadata name, symbol or uri is too long
+    #[error("TokenMetadataTooLong")]
+    TokenMetadataTooLong,
+    /// Redelegation source and destination validators are the same
+    #[error("RedelegateToSameValidator")]
+    RedelegateToSameValidator,
+    /// Pool tokens or lamports received are fewer than the provided minimum
+    #[error("ExceededSlippage")]
+    ExceededSlippage,
+
+    // 40.
+    /// Validator is configured to refuse stake deposits and increases
+    #[error("ValidatorDepositsDisabled")]
+    ValidatorDepositsDisabled,
+    /// Operation would put the validator above its maximum share of the pool
+    #[error("ValidatorStakeCapExceeded")]
+    ValidatorStakeCapExceeded,
+    /// Validator stake cap is greater than the whole pool
+    #[error("InvalidValidatorStakeCap")]
+    InvalidValidatorStakeCap,
+    /// Fee vesting account is not a pool token account owned by the withdraw authority
+    #[error("InvalidFeeVestingAccount")]
+    InvalidFeeVestingAccount,
+    /// Manager fee vesting is not enabled on the stake pool
+    #[error("FeeVestingDisabled")]
+    FeeVestingDisabled,
+
+    // 45.
+    /// Fee vesting can't be shortened while fees are locked
+    #[error("FeeVestingLocked")]
+    FeeVestingLocked,
+    /// Pool mint uses a token extension the stake pool does not support
+    #[error("UnsupportedMintExtension")]
+    UnsupportedMintExtension,
+    /// Fee account uses a token extension the stake pool does not support
+    #[error("UnsupportedFeeAccountExtension")]
+    UnsupportedFeeAccountExtension,
+    /// Withdrawal ticket is not initialized, belongs to another pool or owner
+    #[error("InvalidWithdrawTicket")]
+    InvalidWithdrawTicket,
+    /// Withdrawal ticket can't be claimed before the next epoch, or before
+    /// the reserve holds its lamports
+    #[error("WithdrawTicketNotClaimable")]
+    WithdrawTicketNotClaimable,
+
+    // 50.
+    /// Pending redelegation is not initialized, or belongs to another pool or
+    /// stake account
+    #[error("InvalidPendingRedelegation")]
+    InvalidPendingRedelegation,
+    /// Stake delegated to a pool validator must be deposited with `DepositStake`
+    #[error("ValidatorInPool")]
+    ValidatorInPool,
+    /// Stake pool account was created before the fields being stored, and
+    /// must be grown with `ReallocStakePool`
+    #[error("StakePoolNotReallocated")]
+    StakePoolNotReallocated,
 }
 impl From<StakePoolError> for ProgramError {
     fn from(e: StakePoolError) -> Self {
@@ -4,7 +4,8 @@
 use {
     crate::{
         find_deposit_authority_program_address, find_stake_program_address,
-        find_transient_stake_program_address, find_withdraw_authority_program_address,
+        find_token_metadata_program_address, find_transient_stake_program_address,
+        find_withdraw_authority_program_address,
         state::{Fee, FeeType, StakePool, ValidatorList},
         MAX_VALIDATORS_TO_UPDATE,
     },
@@ -218,6 +219,11 @@ pub enum StakePoolInstruction {
     ///  all other states, nothing is done, and the balance is simply added to
     ///  the canonical stake account balance.
     ///
+    ///  Each entry records the epoch of its last update, which tracks the
+    ///  progress of the update. Chunks of the list can be processed by anyone,
+    ///  in any order, until `UpdateStakePoolBalance` accepts to finalize the
+    ///  epoch.
+    ///
     ///  0. `[]` Stake pool
     ///  1. `[]` Stake pool withdraw authority
     ///  2. `[w]` Validator stake list storage account
@@ -243,7 +249,8 @@ pub enum StakePoolInstruction {
     ///   1. `[]` Stake pool withdraw authority
     ///   2. `[w]` Validator stake list storage account
     ///   3. `[]` Reserve stake account
-    ///   4. `[w]` Account to receive pool fee tokens
+    ///   4. `[w]` Account to receive pool fee tokens, the fee vesting account
+    ///      if enabled
     ///   5. `[w]` Pool mint account
     ///   6. `[]` Pool token program
     UpdateStakePoolBalance,
@@ -310,10 +317,18 @@ pub enum StakePoolInstruction {
 
     ///  (Manager only) Update manager
     ///
+    ///  With manager fee vesting, the locked fees are burned and the vested
+    ///  ones paid out to the current manager fee account.
+    ///
     ///  0. `[w]` StakePool
     ///  1. `[s]` Manager
     ///  2. `[s]` New manager
     ///  3. `[]` New manager fee account
+    ///  4. `[]` (Optional) Stake pool withdraw authority, required with fee vesting
+    ///  5. `[w]` (Optional) Fee vesting account
+    ///  6. `[w]` (Optional) Pool token mint account
+    ///  7. `[w]` (Optional) Current manager fee account
+    ///  8. `[]` (Optional) Pool token program id
     SetManager,
 
     ///  (Manager only) Update fee
@@ -373,6 +388,352 @@ pub enum StakePoolInstruction {
     ///  11. `[]` Token program id
     ///  12. `[s]` (Optional) Stake pool sol withdraw authority
     WithdrawSol(u64),
+
+    ///  (Manager only) Create token metadata for the stake-pool token, owned
+    ///  by the stake pool withdraw authority
+    ///
+    ///  0. `[]` Stake pool
+    ///  1. `[s]` Manager
+    ///  2. `[]` Stake pool withdraw authority
+    ///  3. `[]` Pool token mint account
+    ///  4. `[s, w]` Payer for creation of token metadata account
+    ///  5. `[w]` Token metadata account, derived with
+    ///     `find_token_metadata_program_address`
+    ///  6. `[]` System program id
+    CreateTokenMetadata {
+        /// Token name
+        #[allow(dead_code)] // but it's not
+        name: String,
+        /// Token symbol e.g. stkSOL
+        #[allow(dead_code)] // but it's not
+        symbol: String,
+        /// URI of the uploaded metadata of the spl-token
+        #[allow(dead_code)] // but it's not
+        uri: String,
+    },
+
+    ///  (Manager only) Update token metadata for the stake-pool token
+    ///
+    ///  0. `[]` Stake pool
+    ///  1. `[s]` Manager
+    ///  2. `[]` Stake pool withdraw authority
+    ///  3. `[w]` Token metadata account
+    UpdateTokenMetadata {
+        /// Token name
+        #[allow(dead_code)] // but it's not
+        name: String,
+        /// Token symbol e.g. stkSOL
+        #[allow(dead_code)] // but it's not
+        symbol: String,
+        /// URI of the uploaded metadata of the spl-token
+        #[allow(dead_code)] // but it's not
+        uri: String,
+    },
+
+    /// (Staker only) Decrease stake on a validator and increase stake on another
+    /// validator by the same amount
+    ///
+    /// This is the same as `DecreaseValidatorStake` followed by
+    /// `IncreaseValidatorStake` in the same epoch, checked and signed once: it
+    /// splits a validator stake account into its corresponding transient stake
+    /// account and deactivates it, then splits the same amount from the reserve
+    /// into the destination validator's transient stake account and delegates
+    /// it. The stake account itself is not redelegated, so the source stake
+    /// still goes back to the reserve through `UpdateValidatorListBalance`, and
+    /// the reserve must have the liquidity to front the destination stake.
+    ///
+    /// The instruction only succeeds if neither transient stake account exists,
+    /// and if the reserve can front the amount while staying rent-exempt. The
+    /// minimum amount to move is rent-exemption plus
+    /// `crate::MINIMUM_ACTIVE_STAKE`.
+    ///
+    ///  0. `[]` Stake pool
+    ///  1. `[s]` Stake pool staker
+    ///  2. `[]` Stake pool withdraw authority
+    ///  3. `[w]` Validator list
+    ///  4. `[w]` Stake pool reserve stake
+    ///  5. `[w]` Source canonical stake account to split from
+    ///  6. `[w]` Source transient stake account to receive split and be deactivated
+    ///  7. `[w]` Destination transient stake account to receive split from the reserve and be delegated
+    ///  8. `[]` Destination validator vote account
+    ///  9. `[]` Clock sysvar
+    /// 10. `[]` Rent sysvar
+    /// 11. `[]` Stake History sysvar
+    /// 12. `[]` Stake Config sysvar
+    /// 13. `[]` System program
+    /// 14. `[]` Stake program
+    Redelegate {
+        /// amount of lamports to redelegate
+        #[allow(dead_code)] // but it's not
+        lamports: u64,
+        /// seed used to create source transient stake account
+        #[allow(dead_code)] // but it's not
+        source_transient_stake_seed: u64,
+        /// seed used to create destination transient stake account
+        #[allow(dead_code)] // but it's not
+        destination_transient_stake_seed: u64,
+    },
+
+    ///   Deposit some stake into the pool, with a specified slippage constraint.
+    ///   The output is a "pool" token representing ownership into the pool.
+    ///   Inputs are converted at the current ratio.
+    ///
+    ///   Accounts are the same as `DepositStake`.
+    DepositStakeWithSlippage {
+        /// Minimum amount of pool tokens that must be received
+        #[allow(dead_code)] // but it's not
+        minimum_pool_tokens_out: u64,
+    },
+
+    ///   Withdraw the token from the pool at the current ratio, specifying a
+    ///   minimum expected output lamport amount.
+    ///
+    ///   Accounts are the same as `WithdrawStake`.
+    WithdrawStakeWithSlippage {
+        /// Pool tokens to burn in exchange for lamports
+        #[allow(dead_code)] // but it's not
+        pool_tokens_in: u64,
+        /// Minimum amount of lamports that must be received
+        #[allow(dead_code)] // but it's not
+        minimum_lamports_out: u64,
+    },
+
+    ///   Deposit SOL directly into the pool's reserve account, with a specified
+    ///   slippage constraint. The output is a "pool" token representing
+    ///   ownership into the pool. Inputs are converted at the current ratio.
+    ///
+    ///   Accounts are the same as `DepositSol`.
+    DepositSolWithSlippage {
+        /// Amount of lamports to deposit into the reserve
+        #[allow(dead_code)] // but it's not
+        lamports_in: u64,
+        /// Minimum amount of pool tokens that must be received
+        #[allow(dead_code)] // but it's not
+        minimum_pool_tokens_out: u64,
+    },
+
+    ///   Withdraw SOL directly from the pool's reserve account, specifying a
+    ///   minimum expected output lamport amount. Fails if the reserve does not
+    ///   have enough SOL.
+    ///
+    ///   Accounts are the same as `WithdrawSol`.
+    WithdrawSolWithSlippage {
+        /// Pool tokens to burn in exchange for lamports
+        #[allow(dead_code)] // but it's not
+        pool_tokens_in: u64,
+        /// Minimum amount of lamports that must be received
+        #[allow(dead_code)] // but it's not
+        minimum_lamports_out: u64,
+    },
+
+    ///  (Manager only) Set the liquidity buffer, the proportion of the pool's
+    ///  lamports to keep in the reserve for instant SOL withdrawals.
+    ///
+    ///  While the reserve is below the target, the SOL withdrawal fee rises
+    ///  from `sol_withdrawal_fee` up to `max_sol_withdrawal_fee` when the
+    ///  reserve is empty. `UpdateStakePoolBalance` records how many lamports
+    ///  are missing to reach the target, for the staker to refill the reserve.
+    ///  A `target_reserve` of 0 disables the liquidity buffer.
+    ///
+    ///  0. `[w]` StakePool
+    ///  1. `[s]` Manager
+    SetLiquidityBuffer {
+        /// Proportion of the total lamports to keep in the reserve
+        #[allow(dead_code)] // but it's not
+        target_reserve: Fee,
+        /// SOL withdrawal fee charged when the reserve is emptied
+        #[allow(dead_code)] // but it's not
+        max_sol_withdrawal_fee: Fee,
+    },
+
+    ///  (Manager only) Set the stake cap and deposit flag of a validator in
+    ///  the pool.
+    ///
+    ///  `DepositStake`, `IncreaseValidatorStake` and the destination of
+    ///  `Redelegate` fail if the validator refuses deposits, or if it would
+    ///  hold more than `max_stake_bps` of the pool's total lamports.
+    ///  Existing stake above the cap is left in place.
+    ///
+    ///  0. `[]` Stake pool
+    ///  1. `[s]` Manager
+    ///  2. `[w]` Validator list
+    ///
+    ///  Fails if the validator is not part of the stake pool.
+    SetValidatorConfig {
+        /// Vote account of the validator to configure
+        #[allow(dead_code)] // but it's not
+        vote_account_address: Pubkey,
+        /// Maximum share of the pool's total lamports, in basis points,
+        /// 0 for no limit
+        #[allow(dead_code)] // but it's not
+        max_stake_bps: u16,
+        /// Refuse stake deposits and increases to this validator
+        #[allow(dead_code)] // but it's not
+        no_deposits: bool,
+    },
+
+    ///  (Manager only) Set the number of epochs over which the epoch fee
+    ///  vests.
+    ///
+    ///  With fee vesting, `UpdateStakePoolBalance` mints the epoch fee to
+    ///  the fee vesting account instead of the manager fee account. Each fee
+    ///  vests over `vesting_epochs`: locked fees vest linearly until the
+    ///  average of their end epochs weighted by their amounts, and are paid
+    ///  out with `ClaimManagerFee`.
+    ///
+    ///  The fee vesting account is set when enabling vesting, and must be a
+    ///  pool token account owned by the withdraw authority, without delegate
+    ///  or close authority. The vesting period can't be shortened while fees
+    ///  are locked. A `vesting_epochs` of 0 disables vesting, burning the
+    ///  locked fees and paying out the vested ones.
+    ///
+    ///  0. `[w]` StakePool
+    ///  1. `[s]` Manager
+    ///  2. `[]` Stake pool withdraw authority
+    ///  3. `[w]` Fee vesting account
+    ///  4. `[w]` Pool token mint account
+    ///  5. `[w]` Manager fee account
+    ///  6. `[]` Pool token program id
+    SetManagerFeeVesting {
+        /// Number of epochs over which the fees vest, 0 to disable vesting
+        #[allow(dead_code)] // but it's not
+        vesting_epochs: u64,
+    },
+
+    ///  Transfer the vested manager fees from the fee vesting account to the
+    ///  manager fee account. Anyone can claim, the fees always go to the
+    ///  manager fee account.
+    ///
+    ///  0. `[w]` StakePool
+    ///  1. `[]` Stake pool withdraw authority
+    ///  2. `[w]` Fee vesting account
+    ///  3. `[]` Pool token mint account
+    ///  4. `[w]` Manager fee account
+    ///  5. `[]` Pool token program id
+    ClaimManagerFee,
+
+    ///  Withdraw SOL after unbonding: burn pool tokens now in exchange for a
+    ///  ticket claimable from the next epoch with `ClaimTicket`.
+    ///
+    ///  The ticket's lamports leave `total_lamports` immediately and are owed
+    ///  by the pool until claimed. The staker deactivates enough validator
+    ///  stake with `DecreaseValidatorStake` to refill the reserve, and those
+    ///  lamports can't be used for SOL withdrawals or stake increases. The
+    ///  stake withdrawal fee applies.
+    ///
+    ///  The ticket account must be allocated with `WithdrawTicket::LEN`
+    ///  bytes, owned by the stake pool program and rent-exempt.
+    ///
+    ///  0. `[w]` Stake pool
+    ///  1. `[s]` User transfer authority, for pool token account
+    ///  2. `[w]` User account to burn pool tokens
+    ///  3. `[w]` Uninitialized withdrawal ticket account
+    ///  4. `[]` Ticket owner, who can claim the ticket
+    ///  5. `[w]` Account to receive pool fee tokens
+    ///  6. `[w]` Pool token mint account
+    ///  7. `[]` Pool token program id
+    WithdrawToTicket {
+        /// Pool tokens to burn in exchange for the ticket
+        #[allow(dead_code)] // but it's not
+        pool_tokens_in: u64,
+        /// Minimum amount of lamports the ticket must be worth
+        #[allow(dead_code)] // but it's not
+        minimum_lamports_out: u64,
+    },
+
+    ///  Pay out a withdrawal ticket from the reserve and close the ticket
+    ///  account, sending its rent to the destination as well.
+    ///
+    ///  Fails before the epoch following the withdrawal, or if the reserve
+    ///  does not hold the ticket's lamports yet.
+    ///
+    ///  0. `[w]` Stake pool
+    ///  1. `[]` Stake pool withdraw authority
+    ///  2. `[w]` Withdrawal ticket account
+    ///  3. `[s]` Ticket owner
+    ///  4. `[w]` Reserve stake account, to withdraw SOL
+    ///  5. `[w]` Account receiving the lamports from the reserve, must be a system account
+    ///  6. `[]` Clock sysvar
+    ///  7. `[]` Stake history sysvar
+    ///  8. `[]` Stake program account
+    ClaimTicket,
+
+    ///  Deposit active stake delegated to a validator outside of the pool.
+    ///  The stake account is credited at its full lamport value minus the
+    ///  redelegation deposit fee, and held as a pending redelegation until
+    ///  it is deactivated with `DeactivatePendingStake`.
+    ///
+    ///  As with `DepositStake`, the stake account's staker and withdrawer
+    ///  must first be set to the stake pool deposit authority. The stake must
+    ///  be fully active according to the stake history, and without a lockup
+    ///  in force.
+    ///
+    ///  The pending redelegation account must be allocated with
+    ///  `PendingRedelegation::LEN` bytes, owned by the stake pool program and
+    ///  rent-exempt. Its rent goes to the rent receiver once the deposit has
+    ///  moved into the pool.
+    ///
+    ///  0. `[w]` Stake pool
+    ///  1. `[]` Validator stake list storage account
+    ///  2. `[s]/[]` Stake pool deposit authority
+    ///  3. `[]` Stake pool withdraw authority
+    ///  4. `[w]` Stake account to join the pool (staker and withdrawer must be set to deposit authority)
+    ///  5. `[w]` Uninitialized pending redelegation account
+    ///  6. `[]` Receiver of the pending redelegation account's rent-exemption
+    ///  7. `[w]` User account to receive pool tokens
+    ///  8. `[w]` Account to receive pool fee tokens
+    ///  9. `[w]` Pool token mint account
+    /// 10. `[]` Sysvar clock account
+    /// 11. `[]` Sysvar stake history account
+    /// 12. `[]` Pool token program id
+    /// 13. `[]` Stake program id
+    DepositStakeForRedelegation {
+        /// Minimum amount of pool tokens that must be received
+        #[allow(dead_code)] // but it's not
+        minimum_pool_tokens_out: u64,
+    },
+
+    /// Deactivate a pending redelegation deposit. Anyone can deactivate it,
+    /// since the deposit only ever moves into the reserve.
+    ///
+    /// Once the stake is inactive, `ClosePendingRedelegation` moves all of
+    /// its lamports into the reserve, from where the staker delegates them
+    /// to pool validators with `IncreaseValidatorStake`.
+    ///
+    ///  0. `[]` Stake pool
+    ///  1. `[]` Stake pool withdraw authority
+    ///  2. `[]` Pending redelegation account
+    ///  3. `[w]` Deposited stake account to deactivate
+    ///  4. `[]` Clock sysvar
+    ///  5. `[]` Stake program
+    DeactivatePendingStake,
+
+    ///  Withdraw a deactivated deposit into the reserve, once its stake is
+    ///  inactive according to the stake history, and close its pending
+    ///  redelegation account. Anyone can close it, the
+    ///  rent-exemption always goes to the receiver given on deposit.
+    ///
+    ///  0. `[w]` Stake pool
+    ///  1. `[]` Stake pool withdraw authority
+    ///  2. `[w]` Reserve stake account
+    ///  3. `[w]` Pending redelegation account
+    ///  4. `[w]` Deposited stake account, inactive
+    ///  5. `[w]` Receiver of the pending redelegation account's rent-exemption
+    ///  6. `[]` Clock sysvar
+    ///  7. `[]` Stake history sysvar
+    ///  8. `[]` Stake program account
+    ClosePendingRedelegation,
+
+    ///  Grow a stake pool account created before the latest fields were
+    ///  added to `StakePool`, so that instructions can store them. The new
+    ///  fields start at their default values, and the payer funds the
+    ///  additional rent-exemption. Anyone can grow the account, since nothing
+    ///  else changes. Does nothing if the account is already large enough.
+    ///
+    ///  0. `[w]` StakePool
+    ///  1. `[s, w]` Payer of the additional rent-exemption
+    ///  2. `[]` System program
+    ReallocStakePool,
 }
 
 /// Creates an 'initialize' instruction.
@@ -569,6 +930,53 @@ pub fn increase_validator_stake(
     }
 }
 
+/// Creates `Redelegate` instruction (decrease stake on one validator account
+/// and increase it on another)
+pub fn redelegate(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    staker: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    validator_list: &Pubkey,
+    reserve_stake: &Pubkey,
+    source_validator_stake: &Pubkey,
+    source_transient_stake: &Pubkey,
+    destination_transient_stake: &Pubkey,
+    destination_validator: &Pubkey,
+    lamports: u64,
+    source_transient_stake_seed: u64,
+    destination_transient_stake_seed: u64,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*stake_pool, false),
+        AccountMeta::new_readonly(*staker, true),
+        AccountMeta::new_readonly(*stake_pool_withdraw_authority, false),
+        AccountMeta::new(*validator_list, false),
+        AccountMeta::new(*reserve_stake, false),
+        AccountMeta::new(*source_validator_stake, false),
+        AccountMeta::new(*source_transient_stake, false),
+        AccountMeta::new(*destination_transient_stake, false),
+        AccountMeta::new_readonly(*destination_validator, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::rent::id(), false),
+        AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+        AccountMeta::new_readonly(stake::config::id(), false),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new_readonly(stake::program::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::Redelegate {
+            lamports,
+            source_transient_stake_seed,
+            destination_transient_stake_seed,
+        }
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
 /// Creates `SetPreferredDepositValidator` instruction
 pub fn set_preferred_validator(
     program_id: &Pubkey,
@@ -719,6 +1127,51 @@ pub fn decrease_validator_stake_with_vote(
     )
 }
 
+/// Create a `Redelegate` instruction given an existing stake pool and
+/// source and destination vote accounts
+pub fn redelegate_with_vote(
+    program_id: &Pubkey,
+    stake_pool: &StakePool,
+    stake_pool_address: &Pubkey,
+    source_vote_account_address: &Pubkey,
+    source_transient_stake_seed: u64,
+    destination_vote_account_address: &Pubkey,
+    destination_transient_stake_seed: u64,
+    lamports: u64,
+) -> Instruction {
+    let pool_withdraw_authority =
+        find_withdraw_authority_program_address(program_id, stake_pool_address).0;
+    let (source_validator_stake_address, _) =
+        find_stake_program_address(program_id, source_vote_account_address, stake_pool_address);
+    let (source_transient_stake_address, _) = find_transient_stake_program_address(
+        program_id,
+        source_vote_account_address,
+        stake_pool_address,
+        source_transient_stake_seed,
+    );
+    let (destination_transient_stake_address, _) = find_transient_stake_program_address(
+        program_id,
+        destination_vote_account_address,
+        stake_pool_address,
+        destination_transient_stake_seed,
+    );
+    redelegate(
+        program_id,
+        stake_pool_address,
+        &stake_pool.staker,
+        &pool_withdraw_authority,
+        &stake_pool.validator_list,
+        &stake_pool.reserve_stake,
+        &source_validator_stake_address,
+        &source_transient_stake_address,
+        &destination_transient_stake_address,
+        destination_vote_account_address,
+        lamports,
+        source_transient_stake_seed,
+        destination_transient_stake_seed,
+    )
+}
+
 /// Creates `UpdateValidatorListBalance` instruction (update validator stake account balances)
 pub fn update_validator_list_balance(
     program_id: &Pubkey,
@@ -866,7 +1319,7 @@ pub fn update_stake_pool(
             &withdraw_authority,
             &stake_pool.validator_list,
             &stake_pool.reserve_stake,
-            &stake_pool.manager_fee_account,
+            stake_pool.epoch_fee_account(),
             &stake_pool.pool_mint,
             &stake_pool.token_program_id,
         ),
@@ -879,12 +1332,72 @@ pub fn update_stake_pool(
     (update_list_instructions, final_instructions)
 }
 
-/// Creates instructions required to deposit into a stake pool, given a stake
-/// account owned by the user.
-pub fn deposit_stake(
+/// Creates an `UpdateValidatorListBalance` instruction for the chunk of
+/// `MAX_VALIDATORS_TO_UPDATE` entries starting at `start_index`.
+pub fn update_validator_list_balance_chunk(
+    program_id: &Pubkey,
+    stake_pool: &StakePool,
+    validator_list: &ValidatorList,
+    stake_pool_address: &Pubkey,
+    start_index: u32,
+    no_merge: bool,
+) -> Instruction {
+    let (withdraw_authority, _) =
+        find_withdraw_authority_program_address(program_id, stake_pool_address);
+    let vote_accounts: Vec<Pubkey> = validator_list
+        .validators
+        .iter()
+        .skip(start_index as usize)
+        .take(MAX_VALIDATORS_TO_UPDATE)
+        .map(|validator| validator.vote_account_address)
+        .collect();
+    update_validator_list_balance(
+        program_id,
+        stake_pool_address,
+        &withdraw_authority,
+        &stake_pool.validator_list,
+        &stake_pool.reserve_stake,
+        validator_list,
+        &vote_accounts,
+        start_index,
+        no_merge,
+    )
+}
+
+/// Creates `UpdateValidatorListBalance` instructions for the chunks of the
+/// validator list still containing entries not updated during `epoch`.
+///
+/// Chunks have the same boundaries as in `update_stake_pool`, so several
+/// crankers can pick up the remaining chunks independently.
+pub fn update_stale_validator_list_balance(
+    program_id: &Pubkey,
+    stake_pool: &StakePool,
+    validator_list: &ValidatorList,
+    stake_pool_address: &Pubkey,
+    epoch: u64,
+    no_merge: bool,
+) -> Vec<Instruction> {
+    validator_list
+        .stale_update_chunks(epoch)
+        .into_iter()
+        .map(|start_index| {
+            update_validator_list_balance_chunk(
+                program_id,
+                stake_pool,
+                validator_list,
+                stake_pool_address,
+                start_index,
+                no_merge,
+            )
+        })
+        .collect()
+}
+
+fn deposit_stake_internal(
     program_id: &Pubkey,
     stake_pool: &Pubkey,
     validator_list_storage: &Pubkey,
+    stake_pool_deposit_authority: Option<&Pubkey>,
     stake_pool_withdraw_authority: &Pubkey,
     deposit_stake_address: &Pubkey,
     deposit_stake_withdraw_authority: &Pubkey,
@@ -895,13 +1408,21 @@ pub fn deposit_stake(
     referrer_pool_tokens_account: &Pubkey,
     pool_mint: &Pubkey,
     token_program_id: &Pubkey,
+    minimum_pool_tokens_out: Option<u64>,
 ) -> Vec<Instruction> {
-    let stake_pool_deposit_authority =
-        find_deposit_authority_program_address(program_id, stake_pool).0;
+    let (stake_pool_deposit_authority, is_signer) =
+        if let Some(stake_pool_deposit_authority) = stake_pool_deposit_authority {
+            (*stake_pool_deposit_authority, true)
+        } else {
+            (
+                find_deposit_authority_program_address(program_id, stake_pool).0,
+                false,
+            )
+        };
     let accounts = vec![
         AccountMeta::new(*stake_pool, false),
         AccountMeta::new(*validator_list_storage, false),
-        AccountMeta::new_readonly(stake_pool_deposit_authority, false),
+        AccountMeta::new_readonly(stake_pool_deposit_authority, is_signer),
         AccountMeta::new_readonly(*stake_pool_withdraw_authority, false),
         AccountMeta::new(*deposit_stake_address, false),
         AccountMeta::new(*validator_stake_account, false),
@@ -915,6 +1436,13 @@ pub fn deposit_stake(
         AccountMeta::new_readonly(*token_program_id, false),
         AccountMeta::new_readonly(stake::program::id(), false),
     ];
+    let data = if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+        StakePoolInstruction::DepositStakeWithSlippage {
+            minimum_pool_tokens_out,
+        }
+    } else {
+        StakePoolInstruction::DepositStake
+    };
     vec![
         stake::instruction::authorize(
             deposit_stake_address,
@@ -933,19 +1461,17 @@ pub fn deposit_stake(
         Instruction {
             program_id: *program_id,
             accounts,
-            data: StakePoolInstruction::DepositStake.try_to_vec().unwrap(),
+            data: data.try_to_vec().unwrap(),
         },
     ]
 }
 
 /// Creates instructions required to deposit into a stake pool, given a stake
-/// account owned by the user. The difference with `deposit()` is that a deposit
-/// authority must sign this instruction, which is required for private pools.
-pub fn deposit_stake_with_authority(
+/// account owned by the user.
+pub fn deposit_stake(
     program_id: &Pubkey,
     stake_pool: &Pubkey,
     validator_list_storage: &Pubkey,
-    stake_pool_deposit_authority: &Pubkey,
     stake_pool_withdraw_authority: &Pubkey,
     deposit_stake_address: &Pubkey,
     deposit_stake_withdraw_authority: &Pubkey,
@@ -957,63 +1483,158 @@ pub fn deposit_stake_with_authority(
     pool_mint: &Pubkey,
     token_program_id: &Pubkey,
 ) -> Vec<Instruction> {
-    let accounts = vec![
-        AccountMeta::new(*stake_pool, false),
-        AccountMeta::new(*validator_list_storage, false),
-        AccountMeta::new_readonly(*stake_pool_deposit_authority, true),
-        AccountMeta::new_readonly(*stake_pool_withdraw_authority, false),
-        AccountMeta::new(*deposit_stake_address, false),
-        AccountMeta::new(*validator_stake_account, false),
-        AccountMeta::new(*reserve_stake_account, false),
-        AccountMeta::new(*pool_tokens_to, false),
-        AccountMeta::new(*manager_fee_account, false),
-        AccountMeta::new(*referrer_pool_tokens_account, false),
-        AccountMeta::new(*pool_mint, false),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
-        AccountMeta::new_readonly(sysvar::stake_history::id(), false),
-        AccountMeta::new_readonly(*token_program_id, false),
-        AccountMeta::new_readonly(stake::program::id(), false),
-    ];
-    vec![
-        stake::instruction::authorize(
-            deposit_stake_address,
-            deposit_stake_withdraw_authority,
-            stake_pool_deposit_authority,
-            stake::state::StakeAuthorize::Staker,
-            None,
-        ),
-        stake::instruction::authorize(
-            deposit_stake_address,
-            deposit_stake_withdraw_authority,
-            stake_pool_deposit_authority,
-            stake::state::StakeAuthorize::Withdrawer,
-            None,
-        ),
-        Instruction {
-            program_id: *program_id,
-            accounts,
-            data: StakePoolInstruction::DepositStake.try_to_vec().unwrap(),
-        },
-    ]
+    deposit_stake_internal(
+        program_id,
+        stake_pool,
+        validator_list_storage,
+        None,
+        stake_pool_withdraw_authority,
+        deposit_stake_address,
+        deposit_stake_withdraw_authority,
+        validator_stake_account,
+        reserve_stake_account,
+        pool_tokens_to,
+        manager_fee_account,
+        referrer_pool_tokens_account,
+        pool_mint,
+        token_program_id,
+        None,
+    )
 }
 
-/// Creates instructions required to deposit SOL directly into a stake pool.
-pub fn deposit_sol(
+/// Creates instructions required to deposit into a stake pool, given a stake
+/// account owned by the user, failing if fewer than `minimum_pool_tokens_out`
+/// pool tokens are received.
+pub fn deposit_stake_with_slippage(
     program_id: &Pubkey,
     stake_pool: &Pubkey,
+    validator_list_storage: &Pubkey,
     stake_pool_withdraw_authority: &Pubkey,
+    deposit_stake_address: &Pubkey,
+    deposit_stake_withdraw_authority: &Pubkey,
+    validator_stake_account: &Pubkey,
     reserve_stake_account: &Pubkey,
-    lamports_from: &Pubkey,
     pool_tokens_to: &Pubkey,
     manager_fee_account: &Pubkey,
     referrer_pool_tokens_account: &Pubkey,
     pool_mint: &Pubkey,
     token_program_id: &Pubkey,
-    amount: u64,
-) -> Instruction {
-    let accounts = vec![
-        AccountMeta::new(*stake_pool, false),
-        AccountMeta::new_readonly(*stake_pool_withdraw_authority, false),
+    minimum_pool_tokens_out: u64,
+) -> Vec<Instruction> {
+    deposit_stake_internal(
+        program_id,
+        stake_pool,
+        validator_list_storage,
+        None,
+        stake_pool_withdraw_authority,
+        deposit_stake_address,
+        deposit_stake_withdraw_authority,
+        validator_stake_account,
+        reserve_stake_account,
+        pool_tokens_to,
+        manager_fee_account,
+        referrer_pool_tokens_account,
+        pool_mint,
+        token_program_id,
+        Some(minimum_pool_tokens_out),
+    )
+}
+
+/// Creates instructions required to deposit into a stake pool, given a stake
+/// account owned by the user. The difference with `deposit()` is that a deposit
+/// authority must sign this instruction, which is required for private pools.
+pub fn deposit_stake_with_authority(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    validator_list_storage: &Pubkey,
+    stake_pool_deposit_authority: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    deposit_stake_address: &Pubkey,
+    deposit_stake_withdraw_authority: &Pubkey,
+    validator_stake_account: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    pool_tokens_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    referrer_pool_tokens_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Vec<Instruction> {
+    deposit_stake_internal(
+        program_id,
+        stake_pool,
+        validator_list_storage,
+        Some(stake_pool_deposit_authority),
+        stake_pool_withdraw_authority,
+        deposit_stake_address,
+        deposit_stake_withdraw_authority,
+        validator_stake_account,
+        reserve_stake_account,
+        pool_tokens_to,
+        manager_fee_account,
+        referrer_pool_tokens_account,
+        pool_mint,
+        token_program_id,
+        None,
+    )
+}
+
+/// Creates instructions required to deposit into a stake pool with a deposit
+/// authority, failing if fewer than `minimum_pool_tokens_out` pool tokens are
+/// received.
+pub fn deposit_stake_with_authority_and_slippage(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    validator_list_storage: &Pubkey,
+    stake_pool_deposit_authority: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    deposit_stake_address: &Pubkey,
+    deposit_stake_withdraw_authority: &Pubkey,
+    validator_stake_account: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    pool_tokens_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    referrer_pool_tokens_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    minimum_pool_tokens_out: u64,
+) -> Vec<Instruction> {
+    deposit_stake_internal(
+        program_id,
+        stake_pool,
+        validator_list_storage,
+        Some(stake_pool_deposit_authority),
+        stake_pool_withdraw_authority,
+        deposit_stake_address,
+        deposit_stake_withdraw_authority,
+        validator_stake_account,
+        reserve_stake_account,
+        pool_tokens_to,
+        manager_fee_account,
+        referrer_pool_tokens_account,
+        pool_mint,
+        token_program_id,
+        Some(minimum_pool_tokens_out),
+    )
+}
+
+fn deposit_sol_internal(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_from: &Pubkey,
+    pool_tokens_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    referrer_pool_tokens_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    sol_deposit_authority: Option<&Pubkey>,
+    lamports_in: u64,
+    minimum_pool_tokens_out: Option<u64>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(*stake_pool_withdraw_authority, false),
         AccountMeta::new(*reserve_stake_account, false),
         AccountMeta::new(*lamports_from, true),
         AccountMeta::new(*pool_tokens_to, false),
@@ -1023,208 +1644,836 @@ pub fn deposit_sol(
         AccountMeta::new_readonly(system_program::id(), false),
         AccountMeta::new_readonly(*token_program_id, false),
     ];
+    if let Some(sol_deposit_authority) = sol_deposit_authority {
+        accounts.push(AccountMeta::new_readonly(*sol_deposit_authority, true));
+    }
+    let data = if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+        StakePoolInstruction::DepositSolWithSlippage {
+            lamports_in,
+            minimum_pool_tokens_out,
+        }
+    } else {
+        StakePoolInstruction::DepositSol(lamports_in)
+    };
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: data.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates instructions required to deposit SOL directly into a stake pool.
+pub fn deposit_sol(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_from: &Pubkey,
+    pool_tokens_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    referrer_pool_tokens_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    deposit_sol_internal(
+        program_id,
+        stake_pool,
+        stake_pool_withdraw_authority,
+        reserve_stake_account,
+        lamports_from,
+        pool_tokens_to,
+        manager_fee_account,
+        referrer_pool_tokens_account,
+        pool_mint,
+        token_program_id,
+        None,
+        amount,
+        None,
+    )
+}
+
+/// Creates instruction required to deposit SOL directly into a stake pool,
+/// failing if fewer than `minimum_pool_tokens_out` pool tokens are received.
+pub fn deposit_sol_with_slippage(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_from: &Pubkey,
+    pool_tokens_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    referrer_pool_tokens_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    lamports_in: u64,
+    minimum_pool_tokens_out: u64,
+) -> Instruction {
+    deposit_sol_internal(
+        program_id,
+        stake_pool,
+        stake_pool_withdraw_authority,
+        reserve_stake_account,
+        lamports_from,
+        pool_tokens_to,
+        manager_fee_account,
+        referrer_pool_tokens_account,
+        pool_mint,
+        token_program_id,
+        None,
+        lamports_in,
+        Some(minimum_pool_tokens_out),
+    )
+}
+
+/// Creates instruction required to deposit SOL directly into a stake pool.
+/// The difference with `deposit_sol()` is that a deposit
+/// authority must sign this instruction.
+pub fn deposit_sol_with_authority(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    sol_deposit_authority: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_from: &Pubkey,
+    pool_tokens_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    referrer_pool_tokens_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    deposit_sol_internal(
+        program_id,
+        stake_pool,
+        stake_pool_withdraw_authority,
+        reserve_stake_account,
+        lamports_from,
+        pool_tokens_to,
+        manager_fee_account,
+        referrer_pool_tokens_account,
+        pool_mint,
+        token_program_id,
+        Some(sol_deposit_authority),
+        amount,
+        None,
+    )
+}
+
+/// Creates instruction required to deposit SOL directly into a stake pool with
+/// a deposit authority, failing if fewer than `minimum_pool_tokens_out` pool
+/// tokens are received.
+pub fn deposit_sol_with_authority_and_slippage(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    sol_deposit_authority: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_from: &Pubkey,
+    pool_tokens_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    referrer_pool_tokens_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    lamports_in: u64,
+    minimum_pool_tokens_out: u64,
+) -> Instruction {
+    deposit_sol_internal(
+        program_id,
+        stake_pool,
+        stake_pool_withdraw_authority,
+        reserve_stake_account,
+        lamports_from,
+        pool_tokens_to,
+        manager_fee_account,
+        referrer_pool_tokens_account,
+        pool_mint,
+        token_program_id,
+        Some(sol_deposit_authority),
+        lamports_in,
+        Some(minimum_pool_tokens_out),
+    )
+}
+
+fn withdraw_stake_internal(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    validator_list_storage: &Pubkey,
+    stake_pool_withdraw: &Pubkey,
+    stake_to_split: &Pubkey,
+    stake_to_receive: &Pubkey,
+    user_stake_authority: &Pubkey,
+    user_transfer_authority: &Pubkey,
+    user_pool_token_account: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_tokens_in: u64,
+    minimum_lamports_out: Option<u64>,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new(*validator_list_storage, false),
+        AccountMeta::new_readonly(*stake_pool_withdraw, false),
+        AccountMeta::new(*stake_to_split, false),
+        AccountMeta::new(*stake_to_receive, false),
+        AccountMeta::new_readonly(*user_stake_authority, false),
+        AccountMeta::new_readonly(*user_transfer_authority, true),
+        AccountMeta::new(*user_pool_token_account, false),
+        AccountMeta::new(*manager_fee_account, false),
+        AccountMeta::new(*pool_mint, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(stake::program::id(), false),
+    ];
+    let data = if let Some(minimum_lamports_out) = minimum_lamports_out {
+        StakePoolInstruction::WithdrawStakeWithSlippage {
+            pool_tokens_in,
+            minimum_lamports_out,
+        }
+    } else {
+        StakePoolInstruction::WithdrawStake(pool_tokens_in)
+    };
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: data.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a 'WithdrawStake' instruction.
+pub fn withdraw_stake(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    validator_list_storage: &Pubkey,
+    stake_pool_withdraw: &Pubkey,
+    stake_to_split: &Pubkey,
+    stake_to_receive: &Pubkey,
+    user_stake_authority: &Pubkey,
+    user_transfer_authority: &Pubkey,
+    user_pool_token_account: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    amount: u64,
+) -> Instruction {
+    withdraw_stake_internal(
+        program_id,
+        stake_pool,
+        validator_list_storage,
+        stake_pool_withdraw,
+        stake_to_split,
+        stake_to_receive,
+        user_stake_authority,
+        user_transfer_authority,
+        user_pool_token_account,
+        manager_fee_account,
+        pool_mint,
+        token_program_id,
+        amount,
+        None,
+    )
+}
+
+/// Creates a 'WithdrawStakeWithSlippage' instruction, failing if the split
+/// stake account receives fewer than `minimum_lamports_out` lamports.
+pub fn withdraw_stake_with_slippage(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    validator_list_storage: &Pubkey,
+    stake_pool_withdraw: &Pubkey,
+    stake_to_split: &Pubkey,
+    stake_to_receive: &Pubkey,
+    user_stake_authority: &Pubkey,
+    user_transfer_authority: &Pubkey,
+    user_pool_token_account: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_tokens_in: u64,
+    minimum_lamports_out: u64,
+) -> Instruction {
+    withdraw_stake_internal(
+        program_id,
+        stake_pool,
+        validator_list_storage,
+        stake_pool_withdraw,
+        stake_to_split,
+        stake_to_receive,
+        user_stake_authority,
+        user_transfer_authority,
+        user_pool_token_account,
+        manager_fee_account,
+        pool_mint,
+        token_program_id,
+        pool_tokens_in,
+        Some(minimum_lamports_out),
+    )
+}
+
+fn withdraw_sol_internal(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    user_transfer_authority: &Pubkey,
+    pool_tokens_from: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    sol_withdraw_authority: Option<&Pubkey>,
+    pool_tokens_in: u64,
+    minimum_lamports_out: Option<u64>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(*stake_pool_withdraw_authority, false),
+        AccountMeta::new_readonly(*user_transfer_authority, true),
+        AccountMeta::new(*pool_tokens_from, false),
+        AccountMeta::new(*reserve_stake_account, false),
+        AccountMeta::new(*lamports_to, false),
+        AccountMeta::new(*manager_fee_account, false),
+        AccountMeta::new(*pool_mint, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+        AccountMeta::new_readonly(stake::program::id(), false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    if let Some(sol_withdraw_authority) = sol_withdraw_authority {
+        accounts.push(AccountMeta::new_readonly(*sol_withdraw_authority, true));
+    }
+    let data = if let Some(minimum_lamports_out) = minimum_lamports_out {
+        StakePoolInstruction::WithdrawSolWithSlippage {
+            pool_tokens_in,
+            minimum_lamports_out,
+        }
+    } else {
+        StakePoolInstruction::WithdrawSol(pool_tokens_in)
+    };
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: data.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates instruction required to withdraw SOL directly from a stake pool.
+pub fn withdraw_sol(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    user_transfer_authority: &Pubkey,
+    pool_tokens_from: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_tokens: u64,
+) -> Instruction {
+    withdraw_sol_internal(
+        program_id,
+        stake_pool,
+        stake_pool_withdraw_authority,
+        user_transfer_authority,
+        pool_tokens_from,
+        reserve_stake_account,
+        lamports_to,
+        manager_fee_account,
+        pool_mint,
+        token_program_id,
+        None,
+        pool_tokens,
+        None,
+    )
+}
+
+/// Creates instruction required to withdraw SOL directly from a stake pool,
+/// failing if fewer than `minimum_lamports_out` lamports are received.
+pub fn withdraw_sol_with_slippage(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    user_transfer_authority: &Pubkey,
+    pool_tokens_from: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_tokens_in: u64,
+    minimum_lamports_out: u64,
+) -> Instruction {
+    withdraw_sol_internal(
+        program_id,
+        stake_pool,
+        stake_pool_withdraw_authority,
+        user_transfer_authority,
+        pool_tokens_from,
+        reserve_stake_account,
+        lamports_to,
+        manager_fee_account,
+        pool_mint,
+        token_program_id,
+        None,
+        pool_tokens_in,
+        Some(minimum_lamports_out),
+    )
+}
+
+/// Creates instruction required to withdraw SOL directly from a stake pool.
+/// The difference with `withdraw_sol()` is that the sol withdraw authority
+/// must sign this instruction.
+pub fn withdraw_sol_with_authority(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    sol_withdraw_authority: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    user_transfer_authority: &Pubkey,
+    pool_tokens_from: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_tokens: u64,
+) -> Instruction {
+    withdraw_sol_internal(
+        program_id,
+        stake_pool,
+        stake_pool_withdraw_authority,
+        user_transfer_authority,
+        pool_tokens_from,
+        reserve_stake_account,
+        lamports_to,
+        manager_fee_account,
+        pool_mint,
+        token_program_id,
+        Some(sol_withdraw_authority),
+        pool_tokens,
+        None,
+    )
+}
+
+/// Creates instruction required to withdraw SOL directly from a stake pool
+/// with the sol withdraw authority, failing if fewer than
+/// `minimum_lamports_out` lamports are received.
+pub fn withdraw_sol_with_authority_and_slippage(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    sol_withdraw_authority: &Pubkey,
+    stake_pool_withdraw_authority: &Pubkey,
+    user_transfer_authority: &Pubkey,
+    pool_tokens_from: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_to: &Pubkey,
+    manager_fee_account: &Pubkey,
+    pool_mint: &Pubkey,
+    token_program_id: &Pubkey,
+    pool_tokens_in: u64,
+    minimum_lamports_out: u64,
+) -> Instruction {
+    withdraw_sol_internal(
+        program_id,
+        stake_pool,
+        stake_pool_withdraw_authority,
+        user_transfer_authority,
+        pool_tokens_from,
+        reserve_stake_account,
+        lamports_to,
+        manager_fee_account,
+        pool_mint,
+        token_program_id,
+        Some(sol_withdraw_authority),
+        pool_tokens_in,
+        Some(minimum_lamports_out),
+    )
+}
+
+/// Creates a 'set manager' instruction.
+pub fn set_manager(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    new_manager: &Pubkey,
+    new_fee_receiver: &Pubkey,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new_readonly(*new_manager, true),
+        AccountMeta::new_readonly(*new_fee_receiver, false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::SetManager.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a 'set manager' instruction for a stake pool with manager fee
+/// vesting, which releases the fees vesting for the current manager.
+#[allow(clippy::too_many_arguments)]
+pub fn set_manager_with_fee_vesting(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    new_manager: &Pubkey,
+    new_fee_receiver: &Pubkey,
+    fee_vesting_account: &Pubkey,
+    pool_mint: &Pubkey,
+    manager_fee_account: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Instruction {
+    let (withdraw_authority, _) = find_withdraw_authority_program_address(program_id, stake_pool);
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new_readonly(*new_manager, true),
+        AccountMeta::new_readonly(*new_fee_receiver, false),
+        AccountMeta::new_readonly(withdraw_authority, false),
+        AccountMeta::new(*fee_vesting_account, false),
+        AccountMeta::new(*pool_mint, false),
+        AccountMeta::new(*manager_fee_account, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
     Instruction {
         program_id: *program_id,
         accounts,
-        data: StakePoolInstruction::DepositSol(amount)
-            .try_to_vec()
-            .unwrap(),
+        data: StakePoolInstruction::SetManager.try_to_vec().unwrap(),
     }
 }
 
-/// Creates instruction required to deposit SOL directly into a stake pool.
-/// The difference with `deposit_sol()` is that a deposit
-/// authority must sign this instruction.
-pub fn deposit_sol_with_authority(
+/// Creates a 'set fee' instruction.
+pub fn set_fee(
     program_id: &Pubkey,
     stake_pool: &Pubkey,
-    sol_deposit_authority: &Pubkey,
-    stake_pool_withdraw_authority: &Pubkey,
-    reserve_stake_account: &Pubkey,
-    lamports_from: &Pubkey,
-    pool_tokens_to: &Pubkey,
-    manager_fee_account: &Pubkey,
-    referrer_pool_tokens_account: &Pubkey,
+    manager: &Pubkey,
+    fee: FeeType,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::SetFee { fee }.try_to_vec().unwrap(),
+    }
+}
+
+/// Creates a 'set liquidity buffer' instruction.
+pub fn set_liquidity_buffer(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    target_reserve: Fee,
+    max_sol_withdrawal_fee: Fee,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::SetLiquidityBuffer {
+            target_reserve,
+            max_sol_withdrawal_fee,
+        }
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a 'set validator config' instruction.
+pub fn set_validator_config(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    validator_list: &Pubkey,
+    vote_account_address: &Pubkey,
+    max_stake_bps: u16,
+    no_deposits: bool,
+) -> Instruction {
+    let accounts = vec![
+        AccountMeta::new_readonly(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new(*validator_list, false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::SetValidatorConfig {
+            vote_account_address: *vote_account_address,
+            max_stake_bps,
+            no_deposits,
+        }
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a 'set manager fee vesting' instruction.
+#[allow(clippy::too_many_arguments)]
+pub fn set_manager_fee_vesting(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    fee_vesting_account: &Pubkey,
     pool_mint: &Pubkey,
+    manager_fee_account: &Pubkey,
     token_program_id: &Pubkey,
-    amount: u64,
+    vesting_epochs: u64,
 ) -> Instruction {
+    let (withdraw_authority, _) = find_withdraw_authority_program_address(program_id, stake_pool);
     let accounts = vec![
         AccountMeta::new(*stake_pool, false),
-        AccountMeta::new_readonly(*stake_pool_withdraw_authority, false),
-        AccountMeta::new(*reserve_stake_account, false),
-        AccountMeta::new(*lamports_from, true),
-        AccountMeta::new(*pool_tokens_to, false),
-        AccountMeta::new(*manager_fee_account, false),
-        AccountMeta::new(*referrer_pool_tokens_account, false),
+        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new_readonly(withdraw_authority, false),
+        AccountMeta::new(*fee_vesting_account, false),
         AccountMeta::new(*pool_mint, false),
-        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(*manager_fee_account, false),
         AccountMeta::new_readonly(*token_program_id, false),
-        AccountMeta::new_readonly(*sol_deposit_authority, true),
     ];
     Instruction {
         program_id: *program_id,
         accounts,
-        data: StakePoolInstruction::DepositSol(amount)
+        data: StakePoolInstruction::SetManagerFeeVesting { vesting_epochs }
             .try_to_vec()
             .unwrap(),
     }
 }
 
-/// Creates a 'WithdrawStake' instruction.
-pub fn withdraw_stake(
+/// Creates a 'claim manager fee' instruction.
+pub fn claim_manager_fee(
     program_id: &Pubkey,
     stake_pool: &Pubkey,
-    validator_list_storage: &Pubkey,
-    stake_pool_withdraw: &Pubkey,
-    stake_to_split: &Pubkey,
-    stake_to_receive: &Pubkey,
-    user_stake_authority: &Pubkey,
-    user_transfer_authority: &Pubkey,
-    user_pool_token_account: &Pubkey,
-    manager_fee_account: &Pubkey,
+    fee_vesting_account: &Pubkey,
     pool_mint: &Pubkey,
+    manager_fee_account: &Pubkey,
     token_program_id: &Pubkey,
-    amount: u64,
 ) -> Instruction {
+    let (withdraw_authority, _) = find_withdraw_authority_program_address(program_id, stake_pool);
     let accounts = vec![
         AccountMeta::new(*stake_pool, false),
-        AccountMeta::new(*validator_list_storage, false),
-        AccountMeta::new_readonly(*stake_pool_withdraw, false),
-        AccountMeta::new(*stake_to_split, false),
-        AccountMeta::new(*stake_to_receive, false),
-        AccountMeta::new_readonly(*user_stake_authority, false),
-        AccountMeta::new_readonly(*user_transfer_authority, true),
-        AccountMeta::new(*user_pool_token_account, false),
+        AccountMeta::new_readonly(withdraw_authority, false),
+        AccountMeta::new(*fee_vesting_account, false),
+        AccountMeta::new_readonly(*pool_mint, false),
         AccountMeta::new(*manager_fee_account, false),
-        AccountMeta::new(*pool_mint, false),
-        AccountMeta::new_readonly(sysvar::clock::id(), false),
         AccountMeta::new_readonly(*token_program_id, false),
-        AccountMeta::new_readonly(stake::program::id(), false),
     ];
     Instruction {
         program_id: *program_id,
         accounts,
-        data: StakePoolInstruction::WithdrawStake(amount)
-            .try_to_vec()
-            .unwrap(),
+        data: StakePoolInstruction::ClaimManagerFee.try_to_vec().unwrap(),
     }
 }
 
-/// Creates instruction required to withdraw SOL directly from a stake pool.
-pub fn withdraw_sol(
+/// Creates a 'withdraw to ticket' instruction, the ticket account must be
+/// created beforehand, see `WithdrawToTicket`
+pub fn withdraw_to_ticket(
     program_id: &Pubkey,
     stake_pool: &Pubkey,
-    stake_pool_withdraw_authority: &Pubkey,
     user_transfer_authority: &Pubkey,
     pool_tokens_from: &Pubkey,
-    reserve_stake_account: &Pubkey,
-    lamports_to: &Pubkey,
+    withdraw_ticket: &Pubkey,
+    ticket_owner: &Pubkey,
     manager_fee_account: &Pubkey,
     pool_mint: &Pubkey,
     token_program_id: &Pubkey,
-    pool_tokens: u64,
+    pool_tokens_in: u64,
+    minimum_lamports_out: u64,
 ) -> Instruction {
     let accounts = vec![
         AccountMeta::new(*stake_pool, false),
-        AccountMeta::new_readonly(*stake_pool_withdraw_authority, false),
         AccountMeta::new_readonly(*user_transfer_authority, true),
         AccountMeta::new(*pool_tokens_from, false),
-        AccountMeta::new(*reserve_stake_account, false),
-        AccountMeta::new(*lamports_to, false),
+        AccountMeta::new(*withdraw_ticket, false),
+        AccountMeta::new_readonly(*ticket_owner, false),
         AccountMeta::new(*manager_fee_account, false),
         AccountMeta::new(*pool_mint, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::WithdrawToTicket {
+            pool_tokens_in,
+            minimum_lamports_out,
+        }
+        .try_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Creates a 'claim ticket' instruction.
+pub fn claim_ticket(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    withdraw_ticket: &Pubkey,
+    ticket_owner: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    lamports_to: &Pubkey,
+) -> Instruction {
+    let (withdraw_authority, _) = find_withdraw_authority_program_address(program_id, stake_pool);
+    let accounts = vec![
+        AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(withdraw_authority, false),
+        AccountMeta::new(*withdraw_ticket, false),
+        AccountMeta::new_readonly(*ticket_owner, true),
+        AccountMeta::new(*reserve_stake_account, false),
+        AccountMeta::new(*lamports_to, false),
         AccountMeta::new_readonly(sysvar::clock::id(), false),
         AccountMeta::new_readonly(sysvar::stake_history::id(), false),
         AccountMeta::new_readonly(stake::program::id(), false),
-        AccountMeta::new_readonly(*token_program_id, false),
     ];
     Instruction {
         program_id: *program_id,
         accounts,
-        data: StakePoolInstruction::WithdrawSol(pool_tokens)
-            .try_to_vec()
-            .unwrap(),
+        data: StakePoolInstruction::ClaimTicket.try_to_vec().unwrap(),
     }
 }
 
-/// Creates instruction required to withdraw SOL directly from a stake pool.
-/// The difference with `withdraw_sol()` is that the sol withdraw authority
-/// must sign this instruction.
-pub fn withdraw_sol_with_authority(
+/// Creates instructions required to deposit stake delegated to a validator
+/// outside of the pool, the pending redelegation account must be created
+/// beforehand, see `DepositStakeForRedelegation`
+pub fn deposit_stake_for_redelegation(
     program_id: &Pubkey,
     stake_pool: &Pubkey,
-    sol_withdraw_authority: &Pubkey,
+    validator_list_storage: &Pubkey,
+    stake_pool_deposit_authority: Option<&Pubkey>,
     stake_pool_withdraw_authority: &Pubkey,
-    user_transfer_authority: &Pubkey,
-    pool_tokens_from: &Pubkey,
-    reserve_stake_account: &Pubkey,
-    lamports_to: &Pubkey,
+    deposit_stake_address: &Pubkey,
+    deposit_stake_withdraw_authority: &Pubkey,
+    pending_redelegation: &Pubkey,
+    rent_receiver: &Pubkey,
+    pool_tokens_to: &Pubkey,
     manager_fee_account: &Pubkey,
     pool_mint: &Pubkey,
     token_program_id: &Pubkey,
-    pool_tokens: u64,
-) -> Instruction {
+    minimum_pool_tokens_out: u64,
+) -> Vec<Instruction> {
+    let (stake_pool_deposit_authority, is_signer) =
+        if let Some(stake_pool_deposit_authority) = stake_pool_deposit_authority {
+            (*stake_pool_deposit_authority, true)
+        } else {
+            (
+                find_deposit_authority_program_address(program_id, stake_pool).0,
+                false,
+            )
+        };
     let accounts = vec![
         AccountMeta::new(*stake_pool, false),
+        AccountMeta::new_readonly(*validator_list_storage, false),
+        AccountMeta::new_readonly(stake_pool_deposit_authority, is_signer),
         AccountMeta::new_readonly(*stake_pool_withdraw_authority, false),
-        AccountMeta::new_readonly(*user_transfer_authority, true),
-        AccountMeta::new(*pool_tokens_from, false),
-        AccountMeta::new(*reserve_stake_account, false),
-        AccountMeta::new(*lamports_to, false),
+        AccountMeta::new(*deposit_stake_address, false),
+        AccountMeta::new(*pending_redelegation, false),
+        AccountMeta::new_readonly(*rent_receiver, false),
+        AccountMeta::new(*pool_tokens_to, false),
         AccountMeta::new(*manager_fee_account, false),
         AccountMeta::new(*pool_mint, false),
         AccountMeta::new_readonly(sysvar::clock::id(), false),
         AccountMeta::new_readonly(sysvar::stake_history::id(), false),
-        AccountMeta::new_readonly(stake::program::id(), false),
         AccountMeta::new_readonly(*token_program_id, false),
-        AccountMeta::new_readonly(*sol_withdraw_authority, true),
+        AccountMeta::new_readonly(stake::program::id(), false),
+    ];
+    vec![
+        stake::instruction::authorize(
+            deposit_stake_address,
+            deposit_stake_withdraw_authority,
+            &stake_pool_deposit_authority,
+            stake::state::StakeAuthorize::Staker,
+            None,
+        ),
+        stake::instruction::authorize(
+            deposit_stake_address,
+            deposit_stake_withdraw_authority,
+            &stake_pool_deposit_authority,
+            stake::state::StakeAuthorize::Withdrawer,
+            None,
+        ),
+        Instruction {
+            program_id: *program_id,
+            accounts,
+            data: StakePoolInstruction::DepositStakeForRedelegation {
+                minimum_pool_tokens_out,
+            }
+            .try_to_vec()
+            .unwrap(),
+        },
+    ]
+}
+
+/// Creates a 'deactivate pending stake' instruction.
+pub fn deactivate_pending_stake(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    pending_redelegation: &Pubkey,
+    pending_stake: &Pubkey,
+) -> Instruction {
+    let (withdraw_authority, _) = find_withdraw_authority_program_address(program_id, stake_pool);
+    let accounts = vec![
+        AccountMeta::new_readonly(*stake_pool, false),
+        AccountMeta::new_readonly(withdraw_authority, false),
+        AccountMeta::new_readonly(*pending_redelegation, false),
+        AccountMeta::new(*pending_stake, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(stake::program::id(), false),
     ];
     Instruction {
         program_id: *program_id,
         accounts,
-        data: StakePoolInstruction::WithdrawSol(pool_tokens)
+        data: StakePoolInstruction::DeactivatePendingStake
             .try_to_vec()
             .unwrap(),
     }
 }
 
-/// Creates a 'set manager' instruction.
-pub fn set_manager(
+/// Creates a 'close pending redelegation' instruction.
+pub fn close_pending_redelegation(
     program_id: &Pubkey,
     stake_pool: &Pubkey,
-    manager: &Pubkey,
-    new_manager: &Pubkey,
-    new_fee_receiver: &Pubkey,
+    reserve_stake_account: &Pubkey,
+    pending_redelegation: &Pubkey,
+    pending_stake: &Pubkey,
+    rent_receiver: &Pubkey,
 ) -> Instruction {
+    let (withdraw_authority, _) = find_withdraw_authority_program_address(program_id, stake_pool);
     let accounts = vec![
         AccountMeta::new(*stake_pool, false),
-        AccountMeta::new_readonly(*manager, true),
-        AccountMeta::new_readonly(*new_manager, true),
-        AccountMeta::new_readonly(*new_fee_receiver, false),
+        AccountMeta::new_readonly(withdraw_authority, false),
+        AccountMeta::new(*reserve_stake_account, false),
+        AccountMeta::new(*pending_redelegation, false),
+        AccountMeta::new(*pending_stake, false),
+        AccountMeta::new(*rent_receiver, false),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+        AccountMeta::new_readonly(sysvar::stake_history::id(), false),
+        AccountMeta::new_readonly(stake::program::id(), false),
     ];
     Instruction {
         program_id: *program_id,
         accounts,
-        data: StakePoolInstruction::SetManager.try_to_vec().unwrap(),
+        data: StakePoolInstruction::ClosePendingRedelegation
+            .try_to_vec()
+            .unwrap(),
     }
 }
 
-/// Creates a 'set fee' instruction.
-pub fn set_fee(
-    program_id: &Pubkey,
-    stake_pool: &Pubkey,
-    manager: &Pubkey,
-    fee: FeeType,
-) -> Instruction {
+/// Creates a 'realloc stake pool' instruction, growing a stake pool account
+/// to the current `StakePool` layout.
+pub fn realloc_stake_pool(program_id: &Pubkey, stake_pool: &Pubkey, payer: &Pubkey) -> Instruction {
     let accounts = vec![
         AccountMeta::new(*stake_pool, false),
-        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new_readonly(system_program::id(), false),
     ];
     Instruction {
         program_id: *program_id,
         accounts,
-        data: StakePoolInstruction::SetFee { fee }.try_to_vec().unwrap(),
+        data: StakePoolInstruction::ReallocStakePool.try_to_vec().unwrap(),
     }
 }
 
@@ -1270,3 +2519,65 @@ pub fn set_funding_authority(
             .unwrap(),
     }
 }
+
+/// Creates an instruction to create the token metadata of the pool token
+pub fn create_token_metadata(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    pool_mint: &Pubkey,
+    payer: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Instruction {
+    let (stake_pool_withdraw_authority, _) =
+        find_withdraw_authority_program_address(program_id, stake_pool);
+    let (token_metadata, _) = find_token_metadata_program_address(program_id, pool_mint);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new_readonly(stake_pool_withdraw_authority, false),
+        AccountMeta::new_readonly(*pool_mint, false),
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(token_metadata, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::CreateTokenMetadata { name, symbol, uri }
+            .try_to_vec()
+            .unwrap(),
+    }
+}
+
+/// Creates an instruction to update the token metadata of the pool token
+pub fn update_token_metadata(
+    program_id: &Pubkey,
+    stake_pool: &Pubkey,
+    manager: &Pubkey,
+    pool_mint: &Pubkey,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Instruction {
+    let (stake_pool_withdraw_authority, _) =
+        find_withdraw_authority_program_address(program_id, stake_pool);
+    let (token_metadata, _) = find_token_metadata_program_address(program_id, pool_mint);
+
+    let accounts = vec![
+        AccountMeta::new_readonly(*stake_pool, false),
+        AccountMeta::new_readonly(*manager, true),
+        AccountMeta::new_readonly(stake_pool_withdraw_authority, false),
+        AccountMeta::new(token_metadata, false),
+    ];
+    Instruction {
+        program_id: *program_id,
+        accounts,
+        data: StakePoolInstruction::UpdateTokenMetadata { name, symbol, uri }
+            .try_to_vec()
+            .unwrap(),
+    }
+}
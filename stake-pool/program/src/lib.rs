@@ -27,6 +27,9 @@ const AUTHORITY_WITHDRAW: &[u8] = b"withdraw";
 /// Seed for transient stake account
 const TRANSIENT_STAKE_SEED_PREFIX: &[u8] = b"transient";
 
+/// Seed for pool token metadata account
+const TOKEN_METADATA_SEED: &[u8] = b"metadata";
+
 /// Minimum amount of staked SOL required in a validator stake account to allow
 /// for merges without a mismatch on credits observed
 pub const MINIMUM_ACTIVE_STAKE: u64 = LAMPORTS_PER_SOL / 1_000;
@@ -52,6 +55,10 @@ pub const WITHDRAWAL_BASELINE_FEE: Fee = Fee {
 /// transaction account limits.
 pub const MAX_TRANSIENT_STAKE_ACCOUNTS: usize = 10;
 
+/// Basis points denominator for the per-validator stake cap, 10_000 being the
+/// whole pool
+pub const MAX_STAKE_BPS: u16 = 10_000;
+
 /// Get the stake amount under consideration when calculating pool token
 /// conversions
 #[inline]
@@ -122,4 +129,15 @@ pub fn find_transient_stake_program_address(
     )
 }
 
+/// Generates the token metadata program address for the stake pool's mint
+pub fn find_token_metadata_program_address(
+    program_id: &Pubkey,
+    pool_mint_address: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[TOKEN_METADATA_SEED, &pool_mint_address.to_bytes()],
+        program_id,
+    )
+}
+
 solana_program::declare_id!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
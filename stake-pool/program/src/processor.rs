@@ -3,21 +3,23 @@
 use {
     crate::{
         error::StakePoolError,
-        find_deposit_authority_program_address,
+        find_deposit_authority_program_address, find_token_metadata_program_address,
         instruction::{FundingType, PreferredValidatorType, StakePoolInstruction},
         minimum_reserve_lamports, minimum_stake_lamports,
         state::{
-            AccountType, Fee, FeeType, StakePool, StakeStatus, ValidatorList, ValidatorListHeader,
-            ValidatorStakeInfo,
+            check_fee_account_extensions, check_pool_mint_extensions, AccountType, Fee, FeeType,
+            FeeVesting, LiquidityBuffer, PendingRedelegation, StakePool, StakeStatus,
+            TokenMetadata, ValidatorList, ValidatorListHeader, ValidatorStakeInfo, WithdrawTicket,
         },
-        AUTHORITY_DEPOSIT, AUTHORITY_WITHDRAW, MINIMUM_ACTIVE_STAKE, TRANSIENT_STAKE_SEED_PREFIX,
+        AUTHORITY_DEPOSIT, AUTHORITY_WITHDRAW, MAX_STAKE_BPS, MINIMUM_ACTIVE_STAKE,
+        TOKEN_METADATA_SEED, TRANSIENT_STAKE_SEED_PREFIX,
     },
     borsh::{BorshDeserialize, BorshSerialize},
     num_traits::FromPrimitive,
     solana_program::{
         account_info::next_account_info,
         account_info::AccountInfo,
-        borsh::try_from_slice_unchecked,
+        borsh::{get_packed_len, try_from_slice_unchecked},
         clock::{Clock, Epoch},
         decode_error::DecodeError,
         entrypoint::ProgramResult,
@@ -25,13 +27,18 @@ use {
         program::{invoke, invoke_signed},
         program_error::PrintProgramError,
         program_error::ProgramError,
-        program_pack::Pack,
         pubkey::Pubkey,
         rent::Rent,
-        stake, system_instruction, system_program,
+        stake,
+        stake_history::StakeHistory,
+        system_instruction, system_program,
         sysvar::Sysvar,
     },
-    spl_token::state::Mint,
+    spl_token_2022::{
+        check_spl_token_program_account,
+        extension::StateWithExtensions,
+        state::{Account, Mint},
+    },
 };
 
 /// Deserialize the stake state from AccountInfo
@@ -459,7 +466,7 @@ impl Processor {
         )
     }
 
-    /// Issue a spl_token `Burn` instruction.
+    /// Issue a token program `Burn` instruction.
     #[allow(clippy::too_many_arguments)]
     fn token_burn<'a>(
         token_program: AccountInfo<'a>,
@@ -468,7 +475,7 @@ impl Processor {
         authority: AccountInfo<'a>,
         amount: u64,
     ) -> Result<(), ProgramError> {
-        let ix = spl_token::instruction::burn(
+        let ix = spl_token_2022::instruction::burn(
             token_program.key,
             burn_account.key,
             mint.key,
@@ -480,7 +487,7 @@ impl Processor {
         invoke(&ix, &[burn_account, mint, authority, token_program])
     }
 
-    /// Issue a spl_token `MintTo` instruction.
+    /// Issue a token program `MintTo` instruction.
     #[allow(clippy::too_many_arguments)]
     fn token_mint_to<'a>(
         stake_pool: &Pubkey,
@@ -496,7 +503,7 @@ impl Processor {
         let authority_signature_seeds = [&me_bytes[..32], authority_type, &[bump_seed]];
         let signers = &[&authority_signature_seeds[..]];
 
-        let ix = spl_token::instruction::mint_to(
+        let ix = spl_token_2022::instruction::mint_to(
             token_program.key,
             mint.key,
             destination.key,
@@ -508,24 +515,176 @@ impl Processor {
         invoke_signed(&ix, &[mint, destination, authority, token_program], signers)
     }
 
-    /// Issue a spl_token `Transfer` instruction.
+    /// Issue a token program `TransferChecked` instruction.
     #[allow(clippy::too_many_arguments)]
     fn token_transfer<'a>(
         token_program: AccountInfo<'a>,
         source: AccountInfo<'a>,
+        mint: AccountInfo<'a>,
+        destination: AccountInfo<'a>,
+        authority: AccountInfo<'a>,
+        amount: u64,
+    ) -> Result<(), ProgramError> {
+        let decimals = StateWithExtensions::<Mint>::unpack(&mint.data.borrow())?
+            .base
+            .decimals;
+        let ix = spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            source.key,
+            mint.key,
+            destination.key,
+            authority.key,
+            &[],
+            amount,
+            decimals,
+        )?;
+        invoke(&ix, &[source, mint, destination, authority, token_program])
+    }
+
+    /// Issue a token program `Burn` instruction signed by a program authority.
+    #[allow(clippy::too_many_arguments)]
+    fn token_burn_signed<'a>(
+        stake_pool: &Pubkey,
+        token_program: AccountInfo<'a>,
+        burn_account: AccountInfo<'a>,
+        mint: AccountInfo<'a>,
+        authority: AccountInfo<'a>,
+        authority_type: &[u8],
+        bump_seed: u8,
+        amount: u64,
+    ) -> Result<(), ProgramError> {
+        let me_bytes = stake_pool.to_bytes();
+        let authority_signature_seeds = [&me_bytes[..32], authority_type, &[bump_seed]];
+        let signers = &[&authority_signature_seeds[..]];
+
+        let ix = spl_token_2022::instruction::burn(
+            token_program.key,
+            burn_account.key,
+            mint.key,
+            authority.key,
+            &[],
+            amount,
+        )?;
+
+        invoke_signed(
+            &ix,
+            &[burn_account, mint, authority, token_program],
+            signers,
+        )
+    }
+
+    /// Issue a token program `TransferChecked` instruction signed by a program authority.
+    #[allow(clippy::too_many_arguments)]
+    fn token_transfer_signed<'a>(
+        stake_pool: &Pubkey,
+        token_program: AccountInfo<'a>,
+        source: AccountInfo<'a>,
+        mint: AccountInfo<'a>,
         destination: AccountInfo<'a>,
         authority: AccountInfo<'a>,
+        authority_type: &[u8],
+        bump_seed: u8,
         amount: u64,
     ) -> Result<(), ProgramError> {
-        let ix = spl_token::instruction::transfer(
+        let me_bytes = stake_pool.to_bytes();
+        let authority_signature_seeds = [&me_bytes[..32], authority_type, &[bump_seed]];
+        let signers = &[&authority_signature_seeds[..]];
+
+        let decimals = StateWithExtensions::<Mint>::unpack(&mint.data.borrow())?
+            .base
+            .decimals;
+        let ix = spl_token_2022::instruction::transfer_checked(
             token_program.key,
             source.key,
+            mint.key,
             destination.key,
             authority.key,
             &[],
             amount,
+            decimals,
         )?;
-        invoke(&ix, &[source, destination, authority, token_program])
+        invoke_signed(
+            &ix,
+            &[source, mint, destination, authority, token_program],
+            signers,
+        )
+    }
+
+    /// Burn the locked manager fees, and pay out the vested ones to the
+    /// current manager fee account. Vested fees are burned too if that
+    /// account can't receive them.
+    #[allow(clippy::too_many_arguments)]
+    fn release_fee_vesting<'a>(
+        stake_pool_address: &Pubkey,
+        stake_pool: &mut StakePool,
+        withdraw_info: &AccountInfo<'a>,
+        fee_vesting_info: &AccountInfo<'a>,
+        pool_mint_info: &AccountInfo<'a>,
+        manager_fee_info: &AccountInfo<'a>,
+        token_program_info: &AccountInfo<'a>,
+        epoch: u64,
+    ) -> ProgramResult {
+        if stake_pool.manager_fee_account != *manager_fee_info.key {
+            return Err(StakePoolError::InvalidFeeAccount.into());
+        }
+        let can_receive_fee = stake_pool.check_manager_fee_info(manager_fee_info).is_ok();
+        let stake_withdraw_bump_seed = stake_pool.stake_withdraw_bump_seed;
+        let fee_vesting = stake_pool
+            .fee_vesting
+            .as_mut()
+            .ok_or(StakePoolError::FeeVestingDisabled)?;
+        if fee_vesting.vesting_account != *fee_vesting_info.key {
+            return Err(StakePoolError::InvalidFeeVestingAccount.into());
+        }
+        fee_vesting
+            .vest(epoch)
+            .ok_or(StakePoolError::CalculationFailure)?;
+
+        let (burn_amount, transfer_amount) = if can_receive_fee {
+            (fee_vesting.locked_amount, fee_vesting.vested_amount)
+        } else {
+            (
+                fee_vesting
+                    .locked_amount
+                    .checked_add(fee_vesting.vested_amount)
+                    .ok_or(StakePoolError::CalculationFailure)?,
+                0,
+            )
+        };
+        fee_vesting.locked_amount = 0;
+        fee_vesting.vested_amount = 0;
+
+        if burn_amount > 0 {
+            msg!("Forfeiting {} unvested manager fee tokens", burn_amount);
+            Self::token_burn_signed(
+                stake_pool_address,
+                token_program_info.clone(),
+                fee_vesting_info.clone(),
+                pool_mint_info.clone(),
+                withdraw_info.clone(),
+                AUTHORITY_WITHDRAW,
+                stake_withdraw_bump_seed,
+                burn_amount,
+            )?;
+            stake_pool.pool_token_supply = stake_pool
+                .pool_token_supply
+                .checked_sub(burn_amount)
+                .ok_or(StakePoolError::CalculationFailure)?;
+        }
+        if transfer_amount > 0 {
+            Self::token_transfer_signed(
+                stake_pool_address,
+                token_program_info.clone(),
+                fee_vesting_info.clone(),
+                pool_mint_info.clone(),
+                manager_fee_info.clone(),
+                withdraw_info.clone(),
+                AUTHORITY_WITHDRAW,
+                stake_withdraw_bump_seed,
+                transfer_amount,
+            )?;
+        }
+        Ok(())
     }
 
     fn sol_transfer<'a>(
@@ -623,10 +782,9 @@ impl Processor {
             return Err(StakePoolError::FeeTooHigh.into());
         }
 
-        if *token_program_info.key != spl_token::id() {
+        if check_spl_token_program_account(token_program_info.key).is_err() {
             msg!(
-                "Only the SPL token program is currently supported, expected {}, received {}",
-                spl_token::id(),
+                "Only the SPL token and Token-2022 programs are supported, received {}",
                 *token_program_info.key
             );
             return Err(ProgramError::IncorrectProgramId);
@@ -641,7 +799,9 @@ impl Processor {
         }
 
         if *pool_mint_info.key
-            != spl_token::state::Account::unpack_from_slice(&manager_fee_info.data.borrow())?.mint
+            != StateWithExtensions::<Account>::unpack(&manager_fee_info.data.borrow())?
+                .base
+                .mint
         {
             return Err(StakePoolError::WrongAccountMint.into());
         }
@@ -668,7 +828,15 @@ impl Processor {
             return Err(StakePoolError::InvalidProgramAddress.into());
         }
 
-        let pool_mint = Mint::unpack_from_slice(&pool_mint_info.data.borrow())?;
+        let pool_mint = {
+            let pool_mint_data = pool_mint_info.data.borrow();
+            let pool_mint = StateWithExtensions::<Mint>::unpack(&pool_mint_data)?;
+            check_pool_mint_extensions(&pool_mint)?;
+            pool_mint.base
+        };
+        check_fee_account_extensions(&StateWithExtensions::<Account>::unpack(
+            &manager_fee_info.data.borrow(),
+        )?)?;
 
         if pool_mint.supply != 0 {
             return Err(StakePoolError::NonZeroPoolTokenSupply.into());
@@ -766,10 +934,13 @@ impl Processor {
         stake_pool.next_sol_withdrawal_fee = None;
         stake_pool.last_epoch_pool_token_supply = 0;
         stake_pool.last_epoch_total_lamports = 0;
+        stake_pool.liquidity_buffer = None;
+        stake_pool.fee_vesting = None;
+        stake_pool.withdraw_ticket_lamports = 0;
+        stake_pool.redelegation_deposit_fee = Fee::default();
+        stake_pool.pending_redelegation_lamports = 0;
 
-        stake_pool
-            .serialize(&mut *stake_pool_info.data.borrow_mut())
-            .map_err(|e| e.into())
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())
     }
 
     /// Processes `AddValidatorToPool` instruction.
@@ -899,7 +1070,9 @@ impl Processor {
             transient_stake_lamports: 0,
             last_update_epoch: clock.epoch,
             transient_seed_suffix_start: 0,
-            transient_seed_suffix_end: 0,
+            max_stake_bps: 0,
+            no_deposits: false,
+            reserved: [0; 5],
         })?;
 
         Ok(())
@@ -1058,7 +1231,7 @@ impl Processor {
         if stake_pool.preferred_withdraw_validator_vote_address == Some(vote_account_address) {
             stake_pool.preferred_withdraw_validator_vote_address = None;
         }
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
 
         Ok(())
     }
@@ -1310,6 +1483,7 @@ impl Processor {
             msg!("Validator is marked for removal and no longer allows increases");
             return Err(StakePoolError::ValidatorNotFound.into());
         }
+        validator_stake_info.check_stake_increase(lamports, stake_pool.total_lamports)?;
 
         let stake_rent = rent.minimum_balance(std::mem::size_of::<stake::state::StakeState>());
         if lamports < MINIMUM_ACTIVE_STAKE {
@@ -1324,14 +1498,12 @@ impl Processor {
         // the stake account rent exemption is withdrawn after the merge, so
         let total_lamports = lamports.saturating_add(stake_rent);
 
-        if reserve_stake_account_info
+        // lamports owed to withdrawal tickets must stay in the reserve
+        let reserve_lamports = reserve_stake_account_info
             .lamports()
-            .saturating_sub(total_lamports)
-            <= stake_rent
-        {
-            let max_split_amount = reserve_stake_account_info
-                .lamports()
-                .saturating_sub(2 * stake_rent);
+            .saturating_sub(stake_pool.withdraw_ticket_lamports);
+        if reserve_lamports.saturating_sub(total_lamports) <= stake_rent {
+            let max_split_amount = reserve_lamports.saturating_sub(2 * stake_rent);
             msg!(
                 "Reserve stake does not have enough lamports for increase, must be less than {}, {} requested",
                 max_split_amount,
@@ -1376,135 +1548,399 @@ impl Processor {
         Ok(())
     }
 
-    /// Process `SetPreferredValidator` instruction
+    /// Processes `Redelegate` instruction.
     #[inline(never)] // needed due to stack size violation
-    fn process_set_preferred_validator(
+    fn process_redelegate(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        validator_type: PreferredValidatorType,
-        vote_account_address: Option<Pubkey>,
+        lamports: u64,
+        source_transient_stake_seed: u64,
+        destination_transient_stake_seed: u64,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let stake_pool_info = next_account_info(account_info_iter)?;
         let staker_info = next_account_info(account_info_iter)?;
+        let withdraw_authority_info = next_account_info(account_info_iter)?;
         let validator_list_info = next_account_info(account_info_iter)?;
+        let reserve_stake_account_info = next_account_info(account_info_iter)?;
+        let source_validator_stake_account_info = next_account_info(account_info_iter)?;
+        let source_transient_stake_account_info = next_account_info(account_info_iter)?;
+        let destination_transient_stake_account_info = next_account_info(account_info_iter)?;
+        let destination_vote_account_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+        let rent_info = next_account_info(account_info_iter)?;
+        let rent = &Rent::from_account_info(rent_info)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let stake_config_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
 
+        check_system_program(system_program_info.key)?;
+        check_stake_program(stake_program_info.key)?;
         check_account_owner(stake_pool_info, program_id)?;
-        check_account_owner(validator_list_info, program_id)?;
 
-        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
         if !stake_pool.is_valid() {
             msg!("Expected valid stake pool");
             return Err(StakePoolError::InvalidState.into());
         }
 
+        stake_pool.check_authority_withdraw(
+            withdraw_authority_info.key,
+            program_id,
+            stake_pool_info.key,
+        )?;
         stake_pool.check_staker(staker_info)?;
+
+        if stake_pool.last_update_epoch < clock.epoch {
+            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+        }
+
         stake_pool.check_validator_list(validator_list_info)?;
+        stake_pool.check_reserve_stake(reserve_stake_account_info)?;
+        check_account_owner(validator_list_info, program_id)?;
 
         let mut validator_list_data = validator_list_info.data.borrow_mut();
-        let (header, validator_list) =
+        let (header, mut validator_list) =
             ValidatorListHeader::deserialize_vec(&mut validator_list_data)?;
         if !header.is_valid() {
             return Err(StakePoolError::InvalidState.into());
         }
 
-        if let Some(vote_account_address) = vote_account_address {
+        let (meta, stake) = get_stake_state(source_validator_stake_account_info)?;
+        let source_vote_account_address = stake.delegation.voter_pubkey;
+        check_validator_stake_address(
+            program_id,
+            stake_pool_info.key,
+            source_validator_stake_account_info.key,
+            &source_vote_account_address,
+        )?;
+        let destination_vote_account_address = destination_vote_account_info.key;
+        if source_vote_account_address == *destination_vote_account_address {
+            msg!(
+                "Cannot redelegate from vote account {} to itself",
+                source_vote_account_address
+            );
+            return Err(StakePoolError::RedelegateToSameValidator.into());
+        }
+
+        for vote_account_address in [
+            &source_vote_account_address,
+            destination_vote_account_address,
+        ] {
             let maybe_validator_stake_info = validator_list.find::<ValidatorStakeInfo>(
                 vote_account_address.as_ref(),
                 ValidatorStakeInfo::memcmp_pubkey,
             );
-            match maybe_validator_stake_info {
-                Some(vsi) => {
-                    if vsi.status != StakeStatus::Active {
-                        msg!("Validator for {:?} about to be removed, cannot set as preferred deposit account", validator_type);
-                        return Err(StakePoolError::InvalidPreferredValidator.into());
-                    }
-                }
-                None => {
-                    msg!("Validator for {:?} not present in the stake pool, cannot set as preferred deposit account", validator_type);
-                    return Err(StakePoolError::ValidatorNotFound.into());
-                }
+            if maybe_validator_stake_info.is_none() {
+                msg!(
+                    "Vote account {} not found in stake pool",
+                    vote_account_address
+                );
+                return Err(StakePoolError::ValidatorNotFound.into());
             }
-        }
-
-        match validator_type {
-            PreferredValidatorType::Deposit => {
-                stake_pool.preferred_deposit_validator_vote_address = vote_account_address
+            let validator_stake_info = maybe_validator_stake_info.unwrap();
+            if validator_stake_info.transient_stake_lamports > 0 {
+                return Err(StakePoolError::TransientAccountInUse.into());
             }
-            PreferredValidatorType::Withdraw => {
-                stake_pool.preferred_withdraw_validator_vote_address = vote_account_address
+            if validator_stake_info.status != StakeStatus::Active {
+                msg!("Validator is marked for removal and no longer allows redelegation");
+                return Err(StakePoolError::ValidatorNotFound.into());
             }
-        };
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
-        Ok(())
-    }
-
-    /// Processes `UpdateValidatorListBalance` instruction.
-    #[inline(always)] // needed to maximize number of validators
-    fn process_update_validator_list_balance(
-        program_id: &Pubkey,
-        accounts: &[AccountInfo],
-        start_index: u32,
-        no_merge: bool,
-    ) -> ProgramResult {
-        let account_info_iter = &mut accounts.iter();
-        let stake_pool_info = next_account_info(account_info_iter)?;
-        let withdraw_authority_info = next_account_info(account_info_iter)?;
-        let validator_list_info = next_account_info(account_info_iter)?;
-        let reserve_stake_info = next_account_info(account_info_iter)?;
-        let clock_info = next_account_info(account_info_iter)?;
-        let clock = &Clock::from_account_info(clock_info)?;
-        let stake_history_info = next_account_info(account_info_iter)?;
-        let stake_program_info = next_account_info(account_info_iter)?;
-        let validator_stake_accounts = account_info_iter.as_slice();
-
-        check_account_owner(stake_pool_info, program_id)?;
-        let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
-        if !stake_pool.is_valid() {
-            return Err(StakePoolError::InvalidState.into());
         }
-        stake_pool.check_validator_list(validator_list_info)?;
-        stake_pool.check_authority_withdraw(
-            withdraw_authority_info.key,
+        validator_list
+            .find::<ValidatorStakeInfo>(
+                destination_vote_account_address.as_ref(),
+                ValidatorStakeInfo::memcmp_pubkey,
+            )
+            .ok_or(StakePoolError::ValidatorNotFound)?
+            .check_stake_increase(lamports, stake_pool.total_lamports)?;
+
+        let source_transient_stake_bump_seed = check_transient_stake_address(
             program_id,
             stake_pool_info.key,
+            source_transient_stake_account_info.key,
+            &source_vote_account_address,
+            source_transient_stake_seed,
         )?;
-        stake_pool.check_reserve_stake(reserve_stake_info)?;
-        check_stake_program(stake_program_info.key)?;
+        let source_transient_stake_account_signer_seeds: &[&[_]] = &[
+            TRANSIENT_STAKE_SEED_PREFIX,
+            &source_vote_account_address.to_bytes(),
+            &stake_pool_info.key.to_bytes(),
+            &source_transient_stake_seed.to_le_bytes(),
+            &[source_transient_stake_bump_seed],
+        ];
+        let destination_transient_stake_bump_seed = check_transient_stake_address(
+            program_id,
+            stake_pool_info.key,
+            destination_transient_stake_account_info.key,
+            destination_vote_account_address,
+            destination_transient_stake_seed,
+        )?;
+        let destination_transient_stake_account_signer_seeds: &[&[_]] = &[
+            TRANSIENT_STAKE_SEED_PREFIX,
+            &destination_vote_account_address.to_bytes(),
+            &stake_pool_info.key.to_bytes(),
+            &destination_transient_stake_seed.to_le_bytes(),
+            &[destination_transient_stake_bump_seed],
+        ];
 
-        if validator_stake_accounts
-            .len()
-            .checked_rem(2)
-            .ok_or(StakePoolError::CalculationFailure)?
-            != 0
-        {
-            msg!("Odd number of validator stake accounts passed in, should be pairs of validator stake and transient stake accounts");
-            return Err(StakePoolError::UnexpectedValidatorListAccountSize.into());
+        // both transient stake accounts must be rent-exempt and mergeable once
+        // the stake has moved
+        let stake_rent = rent.minimum_balance(std::mem::size_of::<stake::state::StakeState>());
+        let minimum_lamports = stake_rent.saturating_add(MINIMUM_ACTIVE_STAKE);
+        if lamports < minimum_lamports {
+            msg!(
+                "Need at least {} lamports for redelegated stake to be rent-exempt and mergeable, {} provided",
+                minimum_lamports,
+                lamports
+            );
+            return Err(ProgramError::AccountNotRentExempt);
         }
 
-        check_account_owner(validator_list_info, program_id)?;
-        let mut validator_list_data = validator_list_info.data.borrow_mut();
-        let (validator_list_header, mut validator_slice) =
-            ValidatorListHeader::deserialize_mut_slice(
-                &mut validator_list_data,
-                start_index as usize,
-                validator_stake_accounts.len() / 2,
-            )?;
+        let remaining_lamports = source_validator_stake_account_info
+            .lamports()
+            .checked_sub(lamports)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        let required_lamports = minimum_stake_lamports(&meta);
+        if remaining_lamports < required_lamports {
+            msg!("Need at least {} lamports in the stake account after redelegation, {} requested, {} is the current possible maximum",
+                required_lamports,
+                lamports,
+                source_validator_stake_account_info.lamports().checked_sub(required_lamports).ok_or(StakePoolError::CalculationFailure)?
+            );
+            return Err(ProgramError::InsufficientFunds);
+        }
 
-        if !validator_list_header.is_valid() {
-            return Err(StakePoolError::InvalidState.into());
+        // the reserve fronts the destination stake until the source transient
+        // stake is deactivated and merged back into it, and lamports owed to
+        // withdrawal tickets must stay in the reserve
+        let reserve_lamports = reserve_stake_account_info
+            .lamports()
+            .saturating_sub(stake_pool.withdraw_ticket_lamports);
+        if reserve_lamports.saturating_sub(lamports) <= stake_rent {
+            let max_split_amount = reserve_lamports.saturating_sub(2 * stake_rent);
+            msg!(
+                "Reserve stake does not have enough lamports to activate the redelegated stake, must be less than {}, {} requested",
+                max_split_amount,
+                lamports
+            );
+            return Err(ProgramError::InsufficientFunds);
         }
 
-        let validator_iter = &mut validator_slice
-            .iter_mut()
-            .zip(validator_stake_accounts.chunks_exact(2));
-        for (validator_stake_record, validator_stakes) in validator_iter {
-            // chunks_exact means that we always get 2 elements, making this safe
-            let validator_stake_info = validator_stakes.first().unwrap();
-            let transient_stake_info = validator_stakes.last().unwrap();
-            if check_validator_stake_address(
-                program_id,
-                stake_pool_info.key,
+        create_transient_stake_account(
+            source_transient_stake_account_info.clone(),
+            source_transient_stake_account_signer_seeds,
+            system_program_info.clone(),
+        )?;
+
+        // split into source transient stake account
+        Self::stake_split(
+            stake_pool_info.key,
+            source_validator_stake_account_info.clone(),
+            withdraw_authority_info.clone(),
+            AUTHORITY_WITHDRAW,
+            stake_pool.stake_withdraw_bump_seed,
+            lamports,
+            source_transient_stake_account_info.clone(),
+        )?;
+
+        // deactivate source transient stake
+        Self::stake_deactivate(
+            source_transient_stake_account_info.clone(),
+            clock_info.clone(),
+            withdraw_authority_info.clone(),
+            stake_pool_info.key,
+            AUTHORITY_WITHDRAW,
+            stake_pool.stake_withdraw_bump_seed,
+        )?;
+
+        create_transient_stake_account(
+            destination_transient_stake_account_info.clone(),
+            destination_transient_stake_account_signer_seeds,
+            system_program_info.clone(),
+        )?;
+
+        // split from the reserve into destination transient stake account
+        Self::stake_split(
+            stake_pool_info.key,
+            reserve_stake_account_info.clone(),
+            withdraw_authority_info.clone(),
+            AUTHORITY_WITHDRAW,
+            stake_pool.stake_withdraw_bump_seed,
+            lamports,
+            destination_transient_stake_account_info.clone(),
+        )?;
+
+        // activate destination transient stake to validator
+        Self::stake_delegate(
+            destination_transient_stake_account_info.clone(),
+            destination_vote_account_info.clone(),
+            clock_info.clone(),
+            stake_history_info.clone(),
+            stake_config_info.clone(),
+            withdraw_authority_info.clone(),
+            stake_pool_info.key,
+            AUTHORITY_WITHDRAW,
+            stake_pool.stake_withdraw_bump_seed,
+        )?;
+
+        let source_validator_stake_info = validator_list
+            .find_mut::<ValidatorStakeInfo>(
+                source_vote_account_address.as_ref(),
+                ValidatorStakeInfo::memcmp_pubkey,
+            )
+            .unwrap();
+        source_validator_stake_info.active_stake_lamports = source_validator_stake_info
+            .active_stake_lamports
+            .checked_sub(lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        source_validator_stake_info.transient_stake_lamports = lamports;
+        source_validator_stake_info.transient_seed_suffix_start = source_transient_stake_seed;
+
+        let destination_validator_stake_info = validator_list
+            .find_mut::<ValidatorStakeInfo>(
+                destination_vote_account_address.as_ref(),
+                ValidatorStakeInfo::memcmp_pubkey,
+            )
+            .unwrap();
+        destination_validator_stake_info.transient_stake_lamports = lamports;
+        destination_validator_stake_info.transient_seed_suffix_start =
+            destination_transient_stake_seed;
+
+        Ok(())
+    }
+
+    /// Process `SetPreferredValidator` instruction
+    #[inline(never)] // needed due to stack size violation
+    fn process_set_preferred_validator(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        validator_type: PreferredValidatorType,
+        vote_account_address: Option<Pubkey>,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let staker_info = next_account_info(account_info_iter)?;
+        let validator_list_info = next_account_info(account_info_iter)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        check_account_owner(validator_list_info, program_id)?;
+
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            msg!("Expected valid stake pool");
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        stake_pool.check_staker(staker_info)?;
+        stake_pool.check_validator_list(validator_list_info)?;
+
+        let mut validator_list_data = validator_list_info.data.borrow_mut();
+        let (header, validator_list) =
+            ValidatorListHeader::deserialize_vec(&mut validator_list_data)?;
+        if !header.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        if let Some(vote_account_address) = vote_account_address {
+            let maybe_validator_stake_info = validator_list.find::<ValidatorStakeInfo>(
+                vote_account_address.as_ref(),
+                ValidatorStakeInfo::memcmp_pubkey,
+            );
+            match maybe_validator_stake_info {
+                Some(vsi) => {
+                    if vsi.status != StakeStatus::Active {
+                        msg!("Validator for {:?} about to be removed, cannot set as preferred deposit account", validator_type);
+                        return Err(StakePoolError::InvalidPreferredValidator.into());
+                    }
+                }
+                None => {
+                    msg!("Validator for {:?} not present in the stake pool, cannot set as preferred deposit account", validator_type);
+                    return Err(StakePoolError::ValidatorNotFound.into());
+                }
+            }
+        }
+
+        match validator_type {
+            PreferredValidatorType::Deposit => {
+                stake_pool.preferred_deposit_validator_vote_address = vote_account_address
+            }
+            PreferredValidatorType::Withdraw => {
+                stake_pool.preferred_withdraw_validator_vote_address = vote_account_address
+            }
+        };
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+        Ok(())
+    }
+
+    /// Processes `UpdateValidatorListBalance` instruction.
+    #[inline(always)] // needed to maximize number of validators
+    fn process_update_validator_list_balance(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        start_index: u32,
+        no_merge: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let withdraw_authority_info = next_account_info(account_info_iter)?;
+        let validator_list_info = next_account_info(account_info_iter)?;
+        let reserve_stake_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+        let validator_stake_accounts = account_info_iter.as_slice();
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_validator_list(validator_list_info)?;
+        stake_pool.check_authority_withdraw(
+            withdraw_authority_info.key,
+            program_id,
+            stake_pool_info.key,
+        )?;
+        stake_pool.check_reserve_stake(reserve_stake_info)?;
+        check_stake_program(stake_program_info.key)?;
+
+        if validator_stake_accounts
+            .len()
+            .checked_rem(2)
+            .ok_or(StakePoolError::CalculationFailure)?
+            != 0
+        {
+            msg!("Odd number of validator stake accounts passed in, should be pairs of validator stake and transient stake accounts");
+            return Err(StakePoolError::UnexpectedValidatorListAccountSize.into());
+        }
+
+        check_account_owner(validator_list_info, program_id)?;
+        let mut validator_list_data = validator_list_info.data.borrow_mut();
+        let (validator_list_header, mut validator_slice) =
+            ValidatorListHeader::deserialize_mut_slice(
+                &mut validator_list_data,
+                start_index as usize,
+                validator_stake_accounts.len() / 2,
+            )?;
+
+        if !validator_list_header.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        let validator_iter = &mut validator_slice
+            .iter_mut()
+            .zip(validator_stake_accounts.chunks_exact(2));
+        for (validator_stake_record, validator_stakes) in validator_iter {
+            // chunks_exact means that we always get 2 elements, making this safe
+            let validator_stake_info = validator_stakes.first().unwrap();
+            let transient_stake_info = validator_stakes.last().unwrap();
+            if check_validator_stake_address(
+                program_id,
+                stake_pool_info.key,
                 validator_stake_info.key,
                 &validator_stake_record.vote_account_address,
             )
@@ -1575,9 +2011,13 @@ impl Processor {
                     if meta.authorized.staker == *withdraw_authority_info.key
                         && meta.authorized.withdrawer == *withdraw_authority_info.key
                     {
+                        // a redelegated transient stake only keeps its
+                        // rent-exemption, even though its delegation still
+                        // shows the redelegated amount
                         let account_stake = meta
                             .rent_exempt_reserve
-                            .saturating_add(stake.delegation.stake);
+                            .saturating_add(stake.delegation.stake)
+                            .min(transient_stake_info.lamports());
                         if no_merge {
                             transient_stake_lamports = account_stake;
                         } else if stake.delegation.deactivation_epoch < clock.epoch {
@@ -1712,7 +2152,7 @@ impl Processor {
         stake_pool.check_mint(pool_mint_info)?;
         stake_pool.check_authority_withdraw(withdraw_info.key, program_id, stake_pool_info.key)?;
         stake_pool.check_reserve_stake(reserve_stake_info)?;
-        if stake_pool.manager_fee_account != *manager_fee_info.key {
+        if stake_pool.epoch_fee_account() != manager_fee_info.key {
             return Err(StakePoolError::InvalidFeeAccount.into());
         }
 
@@ -1746,8 +2186,18 @@ impl Processor {
             msg!("Reserve stake account in unknown state, aborting");
             return Err(StakePoolError::WrongStakeState.into());
         };
+        let reserve_lamports = total_lamports;
         for validator_stake_record in validator_list.iter::<ValidatorStakeInfo>() {
             if validator_stake_record.last_update_epoch < clock.epoch {
+                msg!(
+                    "Validator {} not updated this epoch, {} of {} validators updated so far",
+                    validator_stake_record.vote_account_address,
+                    validator_list
+                        .iter::<ValidatorStakeInfo>()
+                        .filter(|validator| validator.last_update_epoch >= clock.epoch)
+                        .count(),
+                    validator_list.len()
+                );
                 return Err(StakePoolError::StakeListOutOfDate.into());
             }
             total_lamports = total_lamports
@@ -1755,6 +2205,15 @@ impl Processor {
                 .ok_or(StakePoolError::CalculationFailure)?;
         }
 
+        // lamports owed to withdrawal tickets no longer belong to the pool
+        // token holders, wherever they are in the unbonding process, while
+        // deposits waiting for redelegation already do
+        let total_lamports = total_lamports
+            .checked_add(stake_pool.pending_redelegation_lamports)
+            .and_then(|lamports| lamports.checked_sub(stake_pool.withdraw_ticket_lamports))
+            .ok_or(StakePoolError::CalculationFailure)?;
+        let reserve_lamports = reserve_lamports.saturating_sub(stake_pool.withdraw_ticket_lamports);
+
         let reward_lamports = total_lamports.saturating_sub(previous_lamports);
 
         // If the manager fee info is invalid, they don't deserve to receive the fee.
@@ -1777,6 +2236,11 @@ impl Processor {
                 stake_pool.stake_withdraw_bump_seed,
                 fee,
             )?;
+            if let Some(fee_vesting) = stake_pool.fee_vesting.as_mut() {
+                fee_vesting
+                    .lock(fee, clock.epoch)
+                    .ok_or(StakePoolError::CalculationFailure)?;
+            }
         }
 
         if stake_pool.last_update_epoch < clock.epoch {
@@ -1798,10 +2262,24 @@ impl Processor {
         }
         stake_pool.total_lamports = total_lamports;
 
-        let pool_mint = Mint::unpack_from_slice(&pool_mint_info.data.borrow())?;
-        stake_pool.pool_token_supply = pool_mint.supply;
+        if let Some(liquidity_buffer) = stake_pool.liquidity_buffer.as_mut() {
+            liquidity_buffer.deficit_lamports = liquidity_buffer
+                .target_lamports(total_lamports)
+                .ok_or(StakePoolError::CalculationFailure)?
+                .saturating_sub(reserve_lamports);
+            if liquidity_buffer.deficit_lamports > 0 {
+                msg!(
+                    "Reserve is {} lamports below the liquidity buffer target",
+                    liquidity_buffer.deficit_lamports
+                );
+            }
+        }
+
+        let pool_mint_data = pool_mint_info.data.borrow();
+        let pool_mint = StateWithExtensions::<Mint>::unpack(&pool_mint_data)?;
+        stake_pool.pool_token_supply = pool_mint.base.supply;
 
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
 
         Ok(())
     }
@@ -1838,7 +2316,11 @@ impl Processor {
 
     /// Processes [DepositStake](enum.Instruction.html).
     #[inline(never)] // needed to avoid stack size violation
-    fn process_deposit_stake(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    fn process_deposit_stake(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        minimum_pool_tokens_out: Option<u64>,
+    ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let stake_pool_info = next_account_info(account_info_iter)?;
         let validator_list_info = next_account_info(account_info_iter)?;
@@ -1971,6 +2453,13 @@ impl Processor {
         let total_deposit_lamports = post_all_validator_lamports
             .checked_sub(pre_all_validator_lamports)
             .ok_or(StakePoolError::CalculationFailure)?;
+        validator_stake_info.check_stake_increase(
+            total_deposit_lamports,
+            stake_pool
+                .total_lamports
+                .checked_add(total_deposit_lamports)
+                .ok_or(StakePoolError::CalculationFailure)?,
+        )?;
         let stake_deposit_lamports = post_validator_stake
             .delegation
             .stake
@@ -2024,6 +2513,17 @@ impl Processor {
             return Err(StakePoolError::DepositTooSmall.into());
         }
 
+        if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+            if pool_tokens_user < minimum_pool_tokens_out {
+                msg!(
+                    "Deposit would receive {} pool tokens, minimum requested is {}",
+                    pool_tokens_user,
+                    minimum_pool_tokens_out
+                );
+                return Err(StakePoolError::ExceededSlippage.into());
+            }
+        }
+
         Self::token_mint_to(
             stake_pool_info.key,
             token_program_info.clone(),
@@ -2085,7 +2585,7 @@ impl Processor {
             .total_lamports
             .checked_add(total_deposit_lamports)
             .ok_or(StakePoolError::CalculationFailure)?;
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
 
         validator_stake_info.active_stake_lamports = post_validator_stake
             .delegation
@@ -2102,6 +2602,7 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         deposit_lamports: u64,
+        minimum_pool_tokens_out: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let stake_pool_info = next_account_info(account_info_iter)?;
@@ -2178,6 +2679,17 @@ impl Processor {
             return Err(StakePoolError::DepositTooSmall.into());
         }
 
+        if let Some(minimum_pool_tokens_out) = minimum_pool_tokens_out {
+            if pool_tokens_user < minimum_pool_tokens_out {
+                msg!(
+                    "Deposit would receive {} pool tokens, minimum requested is {}",
+                    pool_tokens_user,
+                    minimum_pool_tokens_out
+                );
+                return Err(StakePoolError::ExceededSlippage.into());
+            }
+        }
+
         Self::sol_transfer(
             from_user_lamports_info.clone(),
             reserve_stake_account_info.clone(),
@@ -2230,7 +2742,7 @@ impl Processor {
             .total_lamports
             .checked_add(deposit_lamports)
             .ok_or(StakePoolError::CalculationFailure)?;
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
 
         Ok(())
     }
@@ -2241,6 +2753,7 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         pool_tokens: u64,
+        minimum_lamports_out: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let stake_pool_info = next_account_info(account_info_iter)?;
@@ -2315,6 +2828,17 @@ impl Processor {
             return Err(StakePoolError::WithdrawalTooSmall.into());
         }
 
+        if let Some(minimum_lamports_out) = minimum_lamports_out {
+            if withdraw_lamports < minimum_lamports_out {
+                msg!(
+                    "Withdrawal would receive {} lamports, minimum requested is {}",
+                    withdraw_lamports,
+                    minimum_lamports_out
+                );
+                return Err(StakePoolError::ExceededSlippage.into());
+            }
+        }
+
         let has_active_stake = validator_list
             .find::<ValidatorStakeInfo>(
                 &0u64.to_le_bytes(),
@@ -2340,10 +2864,21 @@ impl Processor {
                 &stake_split_from.data.borrow(),
             )?;
             let meta = stake_state.meta().ok_or(StakePoolError::WrongStakeState)?;
-            stake_split_from
+            let available_reserve_lamports = stake_split_from
                 .lamports()
                 .checked_sub(minimum_reserve_lamports(&meta))
                 .ok_or(StakePoolError::StakeLamportsNotEqualToMinimum)?;
+            // lamports owed to withdrawal tickets must stay in the reserve
+            if withdraw_lamports
+                > available_reserve_lamports.saturating_sub(stake_pool.withdraw_ticket_lamports)
+            {
+                msg!(
+                    "Reserve has {} lamports available, {} owed to withdrawal tickets",
+                    available_reserve_lamports,
+                    stake_pool.withdraw_ticket_lamports
+                );
+                return Err(StakePoolError::StakeLamportsNotEqualToMinimum.into());
+            }
             None
         } else {
             let (_, stake) = get_stake_state(stake_split_from)?;
@@ -2440,6 +2975,7 @@ impl Processor {
             Self::token_transfer(
                 token_program_info.clone(),
                 burn_from_pool_info.clone(),
+                pool_mint_info.clone(),
                 manager_fee_info.clone(),
                 user_transfer_authority_info.clone(),
                 pool_tokens_fee,
@@ -2454,7 +2990,7 @@ impl Processor {
             .total_lamports
             .checked_sub(withdraw_lamports)
             .ok_or(StakePoolError::CalculationFailure)?;
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
 
         if let Some((validator_list_item, withdrawing_from_transient_stake_account)) =
             validator_list_item_info
@@ -2481,6 +3017,7 @@ impl Processor {
         program_id: &Pubkey,
         accounts: &[AccountInfo],
         pool_tokens: u64,
+        minimum_lamports_out: Option<u64>,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let stake_pool_info = next_account_info(account_info_iter)?;
@@ -2527,6 +3064,21 @@ impl Processor {
             return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
         }
 
+        let stake_state = try_from_slice_unchecked::<stake::state::StakeState>(
+            &reserve_stake_info.data.borrow(),
+        )?;
+        let minimum_reserve_lamports =
+            if let stake::state::StakeState::Initialized(meta) = stake_state {
+                minimum_reserve_lamports(&meta)
+            } else {
+                msg!("Reserve stake account not in intialized state");
+                return Err(StakePoolError::WrongStakeState.into());
+            };
+        let available_reserve_lamports = reserve_stake_info
+            .lamports()
+            .saturating_sub(minimum_reserve_lamports)
+            .saturating_sub(stake_pool.withdraw_ticket_lamports);
+
         // To prevent a faulty manager fee account from preventing withdrawals
         // if the token program does not own the account, or if the account is not initialized
         let pool_tokens_fee = if stake_pool.manager_fee_account == *burn_from_pool_info.key
@@ -2535,7 +3087,10 @@ impl Processor {
             0
         } else {
             stake_pool
-                .calc_pool_tokens_sol_withdrawal_fee(pool_tokens)
+                .calc_pool_tokens_sol_withdrawal_fee_with_buffer(
+                    pool_tokens,
+                    available_reserve_lamports,
+                )
                 .ok_or(StakePoolError::CalculationFailure)?
         };
         let pool_tokens_burnt = pool_tokens
@@ -2550,25 +3105,24 @@ impl Processor {
             return Err(StakePoolError::WithdrawalTooSmall.into());
         }
 
-        let new_reserve_lamports = reserve_stake_info
-            .lamports()
-            .saturating_sub(withdraw_lamports);
-        let stake_state = try_from_slice_unchecked::<stake::state::StakeState>(
-            &reserve_stake_info.data.borrow(),
-        )?;
-        if let stake::state::StakeState::Initialized(meta) = stake_state {
-            let minimum_reserve_lamports = minimum_reserve_lamports(&meta);
-            if new_reserve_lamports < minimum_reserve_lamports {
-                msg!("Attempting to withdraw {} lamports, maximum possible SOL withdrawal is {} lamports",
+        if let Some(minimum_lamports_out) = minimum_lamports_out {
+            if withdraw_lamports < minimum_lamports_out {
+                msg!(
+                    "Withdrawal would receive {} lamports, minimum requested is {}",
                     withdraw_lamports,
-                    reserve_stake_info.lamports().saturating_sub(minimum_reserve_lamports)
+                    minimum_lamports_out
                 );
-                return Err(StakePoolError::SolWithdrawalTooLarge.into());
+                return Err(StakePoolError::ExceededSlippage.into());
             }
-        } else {
-            msg!("Reserve stake account not in intialized state");
-            return Err(StakePoolError::WrongStakeState.into());
-        };
+        }
+
+        if withdraw_lamports > available_reserve_lamports {
+            msg!("Attempting to withdraw {} lamports, maximum possible SOL withdrawal is {} lamports",
+                withdraw_lamports,
+                available_reserve_lamports
+            );
+            return Err(StakePoolError::SolWithdrawalTooLarge.into());
+        }
 
         Self::token_burn(
             token_program_info.clone(),
@@ -2582,6 +3136,7 @@ impl Processor {
             Self::token_transfer(
                 token_program_info.clone(),
                 burn_from_pool_info.clone(),
+                pool_mint_info.clone(),
                 manager_fee_info.clone(),
                 user_transfer_authority_info.clone(),
                 pool_tokens_fee,
@@ -2609,56 +3164,851 @@ impl Processor {
             .total_lamports
             .checked_sub(withdraw_lamports)
             .ok_or(StakePoolError::CalculationFailure)?;
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
 
         Ok(())
     }
 
-    /// Processes [SetManager](enum.Instruction.html).
+    /// Processes [WithdrawToTicket](enum.Instruction.html).
     #[inline(never)] // needed to avoid stack size violation
-    fn process_set_manager(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    fn process_withdraw_to_ticket(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        pool_tokens: u64,
+        minimum_lamports_out: u64,
+    ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let stake_pool_info = next_account_info(account_info_iter)?;
-        let manager_info = next_account_info(account_info_iter)?;
-        let new_manager_info = next_account_info(account_info_iter)?;
+        let user_transfer_authority_info = next_account_info(account_info_iter)?;
+        let burn_from_pool_info = next_account_info(account_info_iter)?;
+        let withdraw_ticket_info = next_account_info(account_info_iter)?;
+        let ticket_owner_info = next_account_info(account_info_iter)?;
+        let manager_fee_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let clock = Clock::get()?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_mint(pool_mint_info)?;
+        if stake_pool.token_program_id != *token_program_info.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+        if stake_pool.manager_fee_account != *manager_fee_info.key {
+            return Err(StakePoolError::InvalidFeeAccount.into());
+        }
+
+        // The ticket is priced like any other withdrawal, at the rate of the
+        // current epoch
+        if stake_pool.last_update_epoch < clock.epoch {
+            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+        }
+
+        check_account_owner(withdraw_ticket_info, program_id)?;
+        if withdraw_ticket_info.data_len() != WithdrawTicket::LEN {
+            msg!(
+                "Withdrawal ticket account must be {} bytes, {} provided",
+                WithdrawTicket::LEN,
+                withdraw_ticket_info.data_len()
+            );
+            return Err(StakePoolError::InvalidWithdrawTicket.into());
+        }
+        let withdraw_ticket =
+            try_from_slice_unchecked::<WithdrawTicket>(&withdraw_ticket_info.data.borrow())?;
+        if !withdraw_ticket.is_uninitialized() {
+            return Err(StakePoolError::AlreadyInUse.into());
+        }
+        let rent = Rent::get()?;
+        if !rent.is_exempt(
+            withdraw_ticket_info.lamports(),
+            withdraw_ticket_info.data_len(),
+        ) {
+            msg!("Withdrawal ticket not rent-exempt");
+            return Err(ProgramError::AccountNotRentExempt);
+        }
+
+        // To prevent a faulty manager fee account from preventing withdrawals
+        // if the token program does not own the account, or if the account is not initialized
+        let pool_tokens_fee = if stake_pool.manager_fee_account == *burn_from_pool_info.key
+            || stake_pool.check_manager_fee_info(manager_fee_info).is_err()
+        {
+            0
+        } else {
+            stake_pool
+                .calc_pool_tokens_stake_withdrawal_fee(pool_tokens)
+                .ok_or(StakePoolError::CalculationFailure)?
+        };
+        let pool_tokens_burnt = pool_tokens
+            .checked_sub(pool_tokens_fee)
+            .ok_or(StakePoolError::CalculationFailure)?;
+
+        let withdraw_lamports = stake_pool
+            .calc_lamports_withdraw_amount(pool_tokens_burnt)
+            .ok_or(StakePoolError::CalculationFailure)?;
+
+        if withdraw_lamports == 0 {
+            return Err(StakePoolError::WithdrawalTooSmall.into());
+        }
+
+        if withdraw_lamports < minimum_lamports_out {
+            msg!(
+                "Withdrawal would receive {} lamports, minimum requested is {}",
+                withdraw_lamports,
+                minimum_lamports_out
+            );
+            return Err(StakePoolError::ExceededSlippage.into());
+        }
+
+        Self::token_burn(
+            token_program_info.clone(),
+            burn_from_pool_info.clone(),
+            pool_mint_info.clone(),
+            user_transfer_authority_info.clone(),
+            pool_tokens_burnt,
+        )?;
+
+        if pool_tokens_fee > 0 {
+            Self::token_transfer(
+                token_program_info.clone(),
+                burn_from_pool_info.clone(),
+                pool_mint_info.clone(),
+                manager_fee_info.clone(),
+                user_transfer_authority_info.clone(),
+                pool_tokens_fee,
+            )?;
+        }
+
+        let withdraw_ticket = WithdrawTicket {
+            account_type: AccountType::WithdrawTicket,
+            stake_pool: *stake_pool_info.key,
+            owner: *ticket_owner_info.key,
+            lamports: withdraw_lamports,
+            created_epoch: clock.epoch,
+        };
+        withdraw_ticket.serialize(&mut *withdraw_ticket_info.data.borrow_mut())?;
+
+        stake_pool.pool_token_supply = stake_pool
+            .pool_token_supply
+            .checked_sub(pool_tokens_burnt)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.total_lamports = stake_pool
+            .total_lamports
+            .checked_sub(withdraw_lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.withdraw_ticket_lamports = stake_pool
+            .withdraw_ticket_lamports
+            .checked_add(withdraw_lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes [ClaimTicket](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_claim_ticket(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let withdraw_authority_info = next_account_info(account_info_iter)?;
+        let withdraw_ticket_info = next_account_info(account_info_iter)?;
+        let ticket_owner_info = next_account_info(account_info_iter)?;
+        let reserve_stake_info = next_account_info(account_info_iter)?;
+        let destination_lamports_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+        let clock = Clock::from_account_info(clock_info)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_authority_withdraw(
+            withdraw_authority_info.key,
+            program_id,
+            stake_pool_info.key,
+        )?;
+        stake_pool.check_reserve_stake(reserve_stake_info)?;
+        check_stake_program(stake_program_info.key)?;
+
+        // Deactivated stake is only merged into the reserve by the update
+        if stake_pool.last_update_epoch < clock.epoch {
+            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+        }
+
+        check_account_owner(withdraw_ticket_info, program_id)?;
+        let withdraw_ticket =
+            try_from_slice_unchecked::<WithdrawTicket>(&withdraw_ticket_info.data.borrow())?;
+        if !withdraw_ticket.is_valid() || withdraw_ticket.stake_pool != *stake_pool_info.key {
+            return Err(StakePoolError::InvalidWithdrawTicket.into());
+        }
+        if withdraw_ticket.owner != *ticket_owner_info.key {
+            msg!(
+                "Withdrawal ticket owned by {}, received {}",
+                withdraw_ticket.owner,
+                ticket_owner_info.key
+            );
+            return Err(StakePoolError::InvalidWithdrawTicket.into());
+        }
+        if !ticket_owner_info.is_signer {
+            msg!("Withdrawal ticket owner signature missing");
+            return Err(StakePoolError::SignatureMissing.into());
+        }
+
+        if !withdraw_ticket.is_claimable(clock.epoch) {
+            msg!(
+                "Withdrawal ticket created in epoch {} can only be claimed from the next epoch",
+                withdraw_ticket.created_epoch
+            );
+            return Err(StakePoolError::WithdrawTicketNotClaimable.into());
+        }
+
+        let stake_state = try_from_slice_unchecked::<stake::state::StakeState>(
+            &reserve_stake_info.data.borrow(),
+        )?;
+        let minimum_reserve_lamports =
+            if let stake::state::StakeState::Initialized(meta) = stake_state {
+                minimum_reserve_lamports(&meta)
+            } else {
+                msg!("Reserve stake account not in intialized state");
+                return Err(StakePoolError::WrongStakeState.into());
+            };
+        let available_reserve_lamports = reserve_stake_info
+            .lamports()
+            .saturating_sub(minimum_reserve_lamports);
+        if withdraw_ticket.lamports > available_reserve_lamports {
+            msg!(
+                "Withdrawal ticket of {} lamports, only {} lamports in the reserve, the staker must deactivate more stake",
+                withdraw_ticket.lamports,
+                available_reserve_lamports
+            );
+            return Err(StakePoolError::WithdrawTicketNotClaimable.into());
+        }
+
+        Self::stake_withdraw(
+            stake_pool_info.key,
+            reserve_stake_info.clone(),
+            withdraw_authority_info.clone(),
+            AUTHORITY_WITHDRAW,
+            stake_pool.stake_withdraw_bump_seed,
+            destination_lamports_info.clone(),
+            clock_info.clone(),
+            stake_history_info.clone(),
+            stake_program_info.clone(),
+            withdraw_ticket.lamports,
+        )?;
+
+        stake_pool.withdraw_ticket_lamports = stake_pool
+            .withdraw_ticket_lamports
+            .checked_sub(withdraw_ticket.lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+
+        // Close the ticket, the runtime removes accounts without lamports
+        let ticket_rent = withdraw_ticket_info.lamports();
+        **destination_lamports_info.lamports.borrow_mut() = destination_lamports_info
+            .lamports()
+            .checked_add(ticket_rent)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        **withdraw_ticket_info.lamports.borrow_mut() = 0;
+        withdraw_ticket_info.data.borrow_mut().fill(0);
+
+        Ok(())
+    }
+
+    /// Processes [DepositStakeForRedelegation](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_deposit_stake_for_redelegation(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        minimum_pool_tokens_out: u64,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let validator_list_info = next_account_info(account_info_iter)?;
+        let stake_deposit_authority_info = next_account_info(account_info_iter)?;
+        let withdraw_authority_info = next_account_info(account_info_iter)?;
+        let stake_info = next_account_info(account_info_iter)?;
+        let pending_redelegation_info = next_account_info(account_info_iter)?;
+        let rent_receiver_info = next_account_info(account_info_iter)?;
+        let dest_user_pool_info = next_account_info(account_info_iter)?;
+        let manager_fee_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let clock = &Clock::from_account_info(clock_info)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+
+        check_stake_program(stake_program_info.key)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        stake_pool.check_authority_withdraw(
+            withdraw_authority_info.key,
+            program_id,
+            stake_pool_info.key,
+        )?;
+        stake_pool.check_stake_deposit_authority(stake_deposit_authority_info.key)?;
+        stake_pool.check_mint(pool_mint_info)?;
+        stake_pool.check_validator_list(validator_list_info)?;
+
+        if stake_pool.token_program_id != *token_program_info.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if stake_pool.manager_fee_account != *manager_fee_info.key {
+            return Err(StakePoolError::InvalidFeeAccount.into());
+        }
+
+        if stake_pool.last_update_epoch < clock.epoch {
+            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+        }
+
+        check_account_owner(validator_list_info, program_id)?;
+        let (meta, stake) = get_stake_state(stake_info)?;
+        let vote_account_address = stake.delegation.voter_pubkey;
+        let (header, in_pool) = ValidatorListHeader::deserialize_contains(
+            &validator_list_info.data.borrow(),
+            &vote_account_address,
+        )?;
+        if !header.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        if in_pool {
+            msg!(
+                "Vote account {} is in the stake pool, deposit with DepositStake instead",
+                vote_account_address
+            );
+            return Err(StakePoolError::ValidatorInPool.into());
+        }
+
+        let stake_history = StakeHistory::from_account_info(stake_history_info)?;
+        let stake_status = stake
+            .delegation
+            .stake_activating_and_deactivating(clock.epoch, Some(&stake_history));
+        if stake.delegation.deactivation_epoch != Epoch::MAX
+            || stake_status.effective == 0
+            || stake_status.activating != 0
+        {
+            msg!(
+                "Deposited stake must be fully active and not deactivating, effective {}, activating {}",
+                stake_status.effective,
+                stake_status.activating
+            );
+            return Err(StakePoolError::WrongStakeState.into());
+        }
+        if meta.lockup.is_in_force(clock, None) {
+            msg!("Deposited stake has a lockup in force until it can be redelegated");
+            return Err(StakePoolError::WrongStakeState.into());
+        }
+
+        check_account_owner(pending_redelegation_info, program_id)?;
+        if pending_redelegation_info.data_len() != PendingRedelegation::LEN {
+            msg!(
+                "Pending redelegation account must be {} bytes, {} provided",
+                PendingRedelegation::LEN,
+                pending_redelegation_info.data_len()
+            );
+            return Err(StakePoolError::InvalidPendingRedelegation.into());
+        }
+        let pending_redelegation = try_from_slice_unchecked::<PendingRedelegation>(
+            &pending_redelegation_info.data.borrow(),
+        )?;
+        if !pending_redelegation.is_uninitialized() {
+            return Err(StakePoolError::AlreadyInUse.into());
+        }
+        let rent = Rent::get()?;
+        if !rent.is_exempt(
+            pending_redelegation_info.lamports(),
+            pending_redelegation_info.data_len(),
+        ) {
+            msg!("Pending redelegation account not rent-exempt");
+            return Err(ProgramError::AccountNotRentExempt);
+        }
+
+        let (stake_deposit_authority_program_address, deposit_bump_seed) =
+            find_deposit_authority_program_address(program_id, stake_pool_info.key);
+        if *stake_deposit_authority_info.key == stake_deposit_authority_program_address {
+            Self::stake_authorize_signed(
+                stake_pool_info.key,
+                stake_info.clone(),
+                stake_deposit_authority_info.clone(),
+                AUTHORITY_DEPOSIT,
+                deposit_bump_seed,
+                withdraw_authority_info.key,
+                clock_info.clone(),
+                stake_program_info.clone(),
+            )?;
+        } else {
+            Self::stake_authorize(
+                stake_info.clone(),
+                stake_deposit_authority_info.clone(),
+                withdraw_authority_info.key,
+                clock_info.clone(),
+                stake_program_info.clone(),
+            )?;
+        }
+
+        // The whole account is credited, it keeps earning rewards until it
+        // is deactivated and the fee pays for the epoch lost deactivating
+        let deposit_lamports = stake_info.lamports();
+        let new_pool_tokens = stake_pool
+            .calc_pool_tokens_for_deposit(deposit_lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        let pool_tokens_manager_deposit_fee = stake_pool
+            .calc_pool_tokens_redelegation_deposit_fee(new_pool_tokens)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        let pool_tokens_user = new_pool_tokens
+            .checked_sub(pool_tokens_manager_deposit_fee)
+            .ok_or(StakePoolError::CalculationFailure)?;
+
+        if pool_tokens_user == 0 {
+            return Err(StakePoolError::DepositTooSmall.into());
+        }
+
+        if pool_tokens_user < minimum_pool_tokens_out {
+            msg!(
+                "Deposit would receive {} pool tokens, minimum requested is {}",
+                pool_tokens_user,
+                minimum_pool_tokens_out
+            );
+            return Err(StakePoolError::ExceededSlippage.into());
+        }
+
+        Self::token_mint_to(
+            stake_pool_info.key,
+            token_program_info.clone(),
+            pool_mint_info.clone(),
+            dest_user_pool_info.clone(),
+            withdraw_authority_info.clone(),
+            AUTHORITY_WITHDRAW,
+            stake_pool.stake_withdraw_bump_seed,
+            pool_tokens_user,
+        )?;
+        if pool_tokens_manager_deposit_fee > 0 {
+            Self::token_mint_to(
+                stake_pool_info.key,
+                token_program_info.clone(),
+                pool_mint_info.clone(),
+                manager_fee_info.clone(),
+                withdraw_authority_info.clone(),
+                AUTHORITY_WITHDRAW,
+                stake_pool.stake_withdraw_bump_seed,
+                pool_tokens_manager_deposit_fee,
+            )?;
+        }
+
+        let pending_redelegation = PendingRedelegation {
+            account_type: AccountType::PendingRedelegation,
+            stake_pool: *stake_pool_info.key,
+            stake_account: *stake_info.key,
+            rent_receiver: *rent_receiver_info.key,
+            lamports: deposit_lamports,
+        };
+        pending_redelegation.serialize(&mut *pending_redelegation_info.data.borrow_mut())?;
+
+        stake_pool.pool_token_supply = stake_pool
+            .pool_token_supply
+            .checked_add(new_pool_tokens)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.total_lamports = stake_pool
+            .total_lamports
+            .checked_add(deposit_lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.pending_redelegation_lamports = stake_pool
+            .pending_redelegation_lamports
+            .checked_add(deposit_lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Processes [DeactivatePendingStake](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_deactivate_pending_stake(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let withdraw_authority_info = next_account_info(account_info_iter)?;
+        let pending_redelegation_info = next_account_info(account_info_iter)?;
+        let pending_stake_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+
+        check_stake_program(stake_program_info.key)?;
+        check_account_owner(stake_pool_info, program_id)?;
+
+        let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            msg!("Expected valid stake pool");
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        stake_pool.check_authority_withdraw(
+            withdraw_authority_info.key,
+            program_id,
+            stake_pool_info.key,
+        )?;
+
+        check_account_owner(pending_redelegation_info, program_id)?;
+        let pending_redelegation = try_from_slice_unchecked::<PendingRedelegation>(
+            &pending_redelegation_info.data.borrow(),
+        )?;
+        if !pending_redelegation.is_valid()
+            || pending_redelegation.stake_pool != *stake_pool_info.key
+            || pending_redelegation.stake_account != *pending_stake_info.key
+        {
+            return Err(StakePoolError::InvalidPendingRedelegation.into());
+        }
+
+        let (_, stake) = get_stake_state(pending_stake_info)?;
+        if stake.delegation.deactivation_epoch != Epoch::MAX {
+            msg!(
+                "Pending stake {} already deactivated, close it once inactive",
+                pending_stake_info.key
+            );
+            return Err(StakePoolError::WrongStakeState.into());
+        }
+
+        Self::stake_deactivate(
+            pending_stake_info.clone(),
+            clock_info.clone(),
+            withdraw_authority_info.clone(),
+            stake_pool_info.key,
+            AUTHORITY_WITHDRAW,
+            stake_pool.stake_withdraw_bump_seed,
+        )?;
+
+        Ok(())
+    }
+
+    /// Processes [ClosePendingRedelegation](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_close_pending_redelegation(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let withdraw_authority_info = next_account_info(account_info_iter)?;
+        let reserve_stake_info = next_account_info(account_info_iter)?;
+        let pending_redelegation_info = next_account_info(account_info_iter)?;
+        let pending_stake_info = next_account_info(account_info_iter)?;
+        let rent_receiver_info = next_account_info(account_info_iter)?;
+        let clock_info = next_account_info(account_info_iter)?;
+        let stake_history_info = next_account_info(account_info_iter)?;
+        let stake_program_info = next_account_info(account_info_iter)?;
+        let clock = Clock::from_account_info(clock_info)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_authority_withdraw(
+            withdraw_authority_info.key,
+            program_id,
+            stake_pool_info.key,
+        )?;
+        stake_pool.check_reserve_stake(reserve_stake_info)?;
+        check_stake_program(stake_program_info.key)?;
+
+        check_account_owner(pending_redelegation_info, program_id)?;
+        let pending_redelegation = try_from_slice_unchecked::<PendingRedelegation>(
+            &pending_redelegation_info.data.borrow(),
+        )?;
+        if !pending_redelegation.is_valid()
+            || pending_redelegation.stake_pool != *stake_pool_info.key
+            || pending_redelegation.stake_account != *pending_stake_info.key
+        {
+            return Err(StakePoolError::InvalidPendingRedelegation.into());
+        }
+        if pending_redelegation.rent_receiver != *rent_receiver_info.key {
+            msg!(
+                "Pending redelegation rent goes to {}, received {}",
+                pending_redelegation.rent_receiver,
+                rent_receiver_info.key
+            );
+            return Err(StakePoolError::InvalidPendingRedelegation.into());
+        }
+
+        let (_, stake) = get_stake_state(pending_stake_info)?;
+        let stake_history = StakeHistory::from_account_info(stake_history_info)?;
+        let stake_status = stake
+            .delegation
+            .stake_activating_and_deactivating(clock.epoch, Some(&stake_history));
+        if stake.delegation.deactivation_epoch == Epoch::MAX
+            || stake_status.effective != 0
+            || stake_status.activating != 0
+            || stake_status.deactivating != 0
+        {
+            msg!(
+                "Pending stake {} must be deactivated and inactive before closing, effective {}, deactivating {}",
+                pending_stake_info.key,
+                stake_status.effective,
+                stake_status.deactivating
+            );
+            return Err(StakePoolError::WrongStakeState.into());
+        }
+
+        Self::stake_withdraw(
+            stake_pool_info.key,
+            pending_stake_info.clone(),
+            withdraw_authority_info.clone(),
+            AUTHORITY_WITHDRAW,
+            stake_pool.stake_withdraw_bump_seed,
+            reserve_stake_info.clone(),
+            clock_info.clone(),
+            stake_history_info.clone(),
+            stake_program_info.clone(),
+            pending_stake_info.lamports(),
+        )?;
+
+        stake_pool.pending_redelegation_lamports = stake_pool
+            .pending_redelegation_lamports
+            .checked_sub(pending_redelegation.lamports)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+
+        // Close the pending redelegation, the runtime removes accounts
+        // without lamports
+        let pending_redelegation_rent = pending_redelegation_info.lamports();
+        **rent_receiver_info.lamports.borrow_mut() = rent_receiver_info
+            .lamports()
+            .checked_add(pending_redelegation_rent)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        **pending_redelegation_info.lamports.borrow_mut() = 0;
+        pending_redelegation_info.data.borrow_mut().fill(0);
+
+        Ok(())
+    }
+
+    /// Processes [SetManager](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_set_manager(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let manager_info = next_account_info(account_info_iter)?;
+        let new_manager_info = next_account_info(account_info_iter)?;
         let new_manager_fee_info = next_account_info(account_info_iter)?;
 
         check_account_owner(stake_pool_info, program_id)?;
-        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
-        check_account_owner(new_manager_fee_info, &stake_pool.token_program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        check_account_owner(new_manager_fee_info, &stake_pool.token_program_id)?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        stake_pool.check_manager(manager_info)?;
+        if !new_manager_info.is_signer {
+            msg!("New manager signature missing");
+            return Err(StakePoolError::SignatureMissing.into());
+        }
+
+        {
+            let new_manager_fee_data = new_manager_fee_info.data.borrow();
+            let new_manager_fee_account =
+                StateWithExtensions::<Account>::unpack(&new_manager_fee_data)?;
+            if stake_pool.pool_mint != new_manager_fee_account.base.mint {
+                return Err(StakePoolError::WrongAccountMint.into());
+            }
+            check_fee_account_extensions(&new_manager_fee_account)?;
+        }
+
+        if stake_pool.fee_vesting.is_some() {
+            let withdraw_info = next_account_info(account_info_iter)?;
+            let fee_vesting_info = next_account_info(account_info_iter)?;
+            let pool_mint_info = next_account_info(account_info_iter)?;
+            let manager_fee_info = next_account_info(account_info_iter)?;
+            let token_program_info = next_account_info(account_info_iter)?;
+            let clock = Clock::get()?;
+
+            stake_pool.check_authority_withdraw(
+                withdraw_info.key,
+                program_id,
+                stake_pool_info.key,
+            )?;
+            stake_pool.check_mint(pool_mint_info)?;
+            if stake_pool.token_program_id != *token_program_info.key {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+            Self::release_fee_vesting(
+                stake_pool_info.key,
+                &mut stake_pool,
+                withdraw_info,
+                fee_vesting_info,
+                pool_mint_info,
+                manager_fee_info,
+                token_program_info,
+                clock.epoch,
+            )?;
+        }
+
+        stake_pool.manager = *new_manager_info.key;
+        stake_pool.manager_fee_account = *new_manager_fee_info.key;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+        Ok(())
+    }
+
+    /// Processes [SetFee](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_set_fee(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        fee: FeeType,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let manager_info = next_account_info(account_info_iter)?;
+        let clock = Clock::get()?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_manager(manager_info)?;
+
+        if fee.can_only_change_next_epoch() && stake_pool.last_update_epoch < clock.epoch {
+            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+        }
+
+        fee.check_too_high()?;
+        stake_pool.update_fee(&fee)?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+        Ok(())
+    }
+
+    /// Processes [SetLiquidityBuffer](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_set_liquidity_buffer(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        target_reserve: Fee,
+        max_sol_withdrawal_fee: Fee,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let manager_info = next_account_info(account_info_iter)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_manager(manager_info)?;
+
+        if target_reserve.numerator > target_reserve.denominator
+            || max_sol_withdrawal_fee.numerator > max_sol_withdrawal_fee.denominator
+        {
+            msg!(
+                "Liquidity buffer target {} or maximum fee {} greater than 100%",
+                target_reserve,
+                max_sol_withdrawal_fee
+            );
+            return Err(StakePoolError::FeeTooHigh.into());
+        }
+
+        stake_pool.liquidity_buffer =
+            if target_reserve.numerator == 0 || target_reserve.denominator == 0 {
+                None
+            } else {
+                Some(LiquidityBuffer {
+                    target_reserve,
+                    max_sol_withdrawal_fee,
+                    deficit_lamports: 0,
+                })
+            };
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+        Ok(())
+    }
+
+    /// Processes [SetValidatorConfig](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_set_validator_config(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        vote_account_address: Pubkey,
+        max_stake_bps: u16,
+        no_deposits: bool,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let manager_info = next_account_info(account_info_iter)?;
+        let validator_list_info = next_account_info(account_info_iter)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        check_account_owner(validator_list_info, program_id)?;
+
+        let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
         if !stake_pool.is_valid() {
+            msg!("Expected valid stake pool");
             return Err(StakePoolError::InvalidState.into());
         }
 
         stake_pool.check_manager(manager_info)?;
-        if !new_manager_info.is_signer {
-            msg!("New manager signature missing");
-            return Err(StakePoolError::SignatureMissing.into());
+        stake_pool.check_validator_list(validator_list_info)?;
+
+        if max_stake_bps > MAX_STAKE_BPS {
+            msg!(
+                "Validator stake cap of {} basis points greater than {}",
+                max_stake_bps,
+                MAX_STAKE_BPS
+            );
+            return Err(StakePoolError::InvalidValidatorStakeCap.into());
         }
 
-        if stake_pool.pool_mint
-            != spl_token::state::Account::unpack_from_slice(&new_manager_fee_info.data.borrow())?
-                .mint
-        {
-            return Err(StakePoolError::WrongAccountMint.into());
+        let mut validator_list_data = validator_list_info.data.borrow_mut();
+        let (header, mut validator_list) =
+            ValidatorListHeader::deserialize_vec(&mut validator_list_data)?;
+        if !header.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
         }
 
-        stake_pool.manager = *new_manager_info.key;
-        stake_pool.manager_fee_account = *new_manager_fee_info.key;
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        let validator_stake_info = validator_list
+            .find_mut::<ValidatorStakeInfo>(
+                vote_account_address.as_ref(),
+                ValidatorStakeInfo::memcmp_pubkey,
+            )
+            .ok_or_else(|| {
+                msg!(
+                    "Vote account {} not found in stake pool",
+                    vote_account_address
+                );
+                StakePoolError::ValidatorNotFound
+            })?;
+        validator_stake_info.max_stake_bps = max_stake_bps;
+        validator_stake_info.no_deposits = no_deposits;
         Ok(())
     }
 
-    /// Processes [SetFee](enum.Instruction.html).
+    /// Processes [SetManagerFeeVesting](enum.Instruction.html).
     #[inline(never)] // needed to avoid stack size violation
-    fn process_set_fee(
+    fn process_set_manager_fee_vesting(
         program_id: &Pubkey,
         accounts: &[AccountInfo],
-        fee: FeeType,
+        vesting_epochs: u64,
     ) -> ProgramResult {
         let account_info_iter = &mut accounts.iter();
         let stake_pool_info = next_account_info(account_info_iter)?;
         let manager_info = next_account_info(account_info_iter)?;
+        let withdraw_info = next_account_info(account_info_iter)?;
+        let fee_vesting_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let manager_fee_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
         let clock = Clock::get()?;
 
         check_account_owner(stake_pool_info, program_id)?;
@@ -2667,14 +4017,126 @@ impl Processor {
             return Err(StakePoolError::InvalidState.into());
         }
         stake_pool.check_manager(manager_info)?;
+        stake_pool.check_authority_withdraw(withdraw_info.key, program_id, stake_pool_info.key)?;
+        stake_pool.check_mint(pool_mint_info)?;
+        if stake_pool.token_program_id != *token_program_info.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
 
-        if fee.can_only_change_next_epoch() && stake_pool.last_update_epoch < clock.epoch {
-            return Err(StakePoolError::StakeListAndPoolOutOfDate.into());
+        match stake_pool.fee_vesting.as_mut() {
+            None => {
+                if vesting_epochs > 0 {
+                    stake_pool.check_fee_vesting_account(fee_vesting_info, withdraw_info.key)?;
+                    stake_pool.fee_vesting = Some(FeeVesting {
+                        vesting_account: *fee_vesting_info.key,
+                        vesting_epochs,
+                        locked_amount: 0,
+                        vested_amount: 0,
+                        last_vesting_epoch: clock.epoch,
+                        end_epoch: clock.epoch,
+                    });
+                }
+            }
+            Some(fee_vesting) => {
+                if vesting_epochs == 0 {
+                    Self::release_fee_vesting(
+                        stake_pool_info.key,
+                        &mut stake_pool,
+                        withdraw_info,
+                        fee_vesting_info,
+                        pool_mint_info,
+                        manager_fee_info,
+                        token_program_info,
+                        clock.epoch,
+                    )?;
+                    stake_pool.fee_vesting = None;
+                } else {
+                    if fee_vesting.vesting_account != *fee_vesting_info.key {
+                        return Err(StakePoolError::InvalidFeeVestingAccount.into());
+                    }
+                    fee_vesting
+                        .vest(clock.epoch)
+                        .ok_or(StakePoolError::CalculationFailure)?;
+                    if vesting_epochs < fee_vesting.vesting_epochs && fee_vesting.locked_amount > 0
+                    {
+                        msg!(
+                            "Can't shorten vesting from {} to {} epochs while {} fee tokens are locked",
+                            fee_vesting.vesting_epochs,
+                            vesting_epochs,
+                            fee_vesting.locked_amount
+                        );
+                        return Err(StakePoolError::FeeVestingLocked.into());
+                    }
+                    fee_vesting.vesting_epochs = vesting_epochs;
+                }
+            }
         }
 
-        fee.check_too_high()?;
-        stake_pool.update_fee(&fee)?;
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+        Ok(())
+    }
+
+    /// Processes [ClaimManagerFee](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_claim_manager_fee(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let withdraw_info = next_account_info(account_info_iter)?;
+        let fee_vesting_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let manager_fee_info = next_account_info(account_info_iter)?;
+        let token_program_info = next_account_info(account_info_iter)?;
+        let clock = Clock::get()?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let mut stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_authority_withdraw(withdraw_info.key, program_id, stake_pool_info.key)?;
+        stake_pool.check_mint(pool_mint_info)?;
+        if stake_pool.manager_fee_account != *manager_fee_info.key {
+            return Err(StakePoolError::InvalidFeeAccount.into());
+        }
+        stake_pool.check_manager_fee_info(manager_fee_info)?;
+        if stake_pool.token_program_id != *token_program_info.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let stake_withdraw_bump_seed = stake_pool.stake_withdraw_bump_seed;
+        let fee_vesting = stake_pool
+            .fee_vesting
+            .as_mut()
+            .ok_or(StakePoolError::FeeVestingDisabled)?;
+        if fee_vesting.vesting_account != *fee_vesting_info.key {
+            return Err(StakePoolError::InvalidFeeVestingAccount.into());
+        }
+        fee_vesting
+            .vest(clock.epoch)
+            .ok_or(StakePoolError::CalculationFailure)?;
+        let amount = fee_vesting.vested_amount;
+        fee_vesting.vested_amount = 0;
+
+        if amount > 0 {
+            Self::token_transfer_signed(
+                stake_pool_info.key,
+                token_program_info.clone(),
+                fee_vesting_info.clone(),
+                pool_mint_info.clone(),
+                manager_fee_info.clone(),
+                withdraw_info.clone(),
+                AUTHORITY_WITHDRAW,
+                stake_withdraw_bump_seed,
+                amount,
+            )?;
+        }
+        msg!(
+            "Claimed {} manager fee tokens, {} still locked",
+            amount,
+            fee_vesting.locked_amount
+        );
+
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
         Ok(())
     }
 
@@ -2698,7 +4160,7 @@ impl Processor {
             return Err(StakePoolError::SignatureMissing.into());
         }
         stake_pool.staker = *new_staker_info.key;
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
         Ok(())
     }
 
@@ -2732,10 +4194,180 @@ impl Processor {
             FundingType::SolDeposit => stake_pool.sol_deposit_authority = new_authority,
             FundingType::SolWithdraw => stake_pool.sol_withdraw_authority = new_authority,
         }
-        stake_pool.serialize(&mut *stake_pool_info.data.borrow_mut())?;
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())?;
+        Ok(())
+    }
+
+    /// Processes [CreateTokenMetadata](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_create_token_metadata(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let manager_info = next_account_info(account_info_iter)?;
+        let withdraw_authority_info = next_account_info(account_info_iter)?;
+        let pool_mint_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let token_metadata_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_manager(manager_info)?;
+        stake_pool.check_authority_withdraw(
+            withdraw_authority_info.key,
+            program_id,
+            stake_pool_info.key,
+        )?;
+        stake_pool.check_mint(pool_mint_info)?;
+        check_system_program(system_program_info.key)?;
+        TokenMetadata::check_lengths(&name, &symbol, &uri)?;
+
+        let (token_metadata_address, bump_seed) =
+            find_token_metadata_program_address(program_id, pool_mint_info.key);
+        if token_metadata_address != *token_metadata_info.key {
+            msg!(
+                "Incorrect token metadata account provided, expected {}, received {}",
+                token_metadata_address,
+                token_metadata_info.key
+            );
+            return Err(StakePoolError::InvalidMetadataAccount.into());
+        }
+        if !token_metadata_info.data_is_empty() {
+            return Err(StakePoolError::AlreadyInUse.into());
+        }
+
+        let token_metadata_signer_seeds: &[&[_]] = &[
+            TOKEN_METADATA_SEED,
+            &pool_mint_info.key.to_bytes()[..32],
+            &[bump_seed],
+        ];
+        let rent = Rent::get()?;
+        create_pda_account(
+            payer_info,
+            rent.minimum_balance(TokenMetadata::LEN),
+            TokenMetadata::LEN,
+            program_id,
+            system_program_info,
+            token_metadata_info,
+            token_metadata_signer_seeds,
+        )?;
+
+        let token_metadata = TokenMetadata {
+            account_type: AccountType::TokenMetadata,
+            update_authority: *withdraw_authority_info.key,
+            stake_pool: *stake_pool_info.key,
+            mint: *pool_mint_info.key,
+            name,
+            symbol,
+            uri,
+        };
+        token_metadata.serialize(&mut *token_metadata_info.data.borrow_mut())?;
+        Ok(())
+    }
+
+    /// Processes [UpdateTokenMetadata](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_update_token_metadata(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let manager_info = next_account_info(account_info_iter)?;
+        let withdraw_authority_info = next_account_info(account_info_iter)?;
+        let token_metadata_info = next_account_info(account_info_iter)?;
+
+        check_account_owner(stake_pool_info, program_id)?;
+        let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+        stake_pool.check_manager(manager_info)?;
+        stake_pool.check_authority_withdraw(
+            withdraw_authority_info.key,
+            program_id,
+            stake_pool_info.key,
+        )?;
+        TokenMetadata::check_lengths(&name, &symbol, &uri)?;
+
+        let (token_metadata_address, _) =
+            find_token_metadata_program_address(program_id, &stake_pool.pool_mint);
+        if token_metadata_address != *token_metadata_info.key {
+            msg!(
+                "Incorrect token metadata account provided, expected {}, received {}",
+                token_metadata_address,
+                token_metadata_info.key
+            );
+            return Err(StakePoolError::InvalidMetadataAccount.into());
+        }
+        check_account_owner(token_metadata_info, program_id)?;
+        let mut token_metadata =
+            try_from_slice_unchecked::<TokenMetadata>(&token_metadata_info.data.borrow())?;
+        if !token_metadata.is_valid()
+            || token_metadata.stake_pool != *stake_pool_info.key
+            || token_metadata.update_authority != *withdraw_authority_info.key
+        {
+            return Err(StakePoolError::InvalidMetadataAccount.into());
+        }
+
+        token_metadata.name = name;
+        token_metadata.symbol = symbol;
+        token_metadata.uri = uri;
+        token_metadata.serialize(&mut *token_metadata_info.data.borrow_mut())?;
         Ok(())
     }
 
+    /// Processes [ReallocStakePool](enum.Instruction.html).
+    #[inline(never)] // needed to avoid stack size violation
+    fn process_realloc_stake_pool(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let account_info_iter = &mut accounts.iter();
+        let stake_pool_info = next_account_info(account_info_iter)?;
+        let payer_info = next_account_info(account_info_iter)?;
+        let system_program_info = next_account_info(account_info_iter)?;
+
+        check_system_program(system_program_info.key)?;
+        check_account_owner(stake_pool_info, program_id)?;
+        let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data.borrow())?;
+        if !stake_pool.is_valid() {
+            return Err(StakePoolError::InvalidState.into());
+        }
+
+        let space = get_packed_len::<StakePool>();
+        if stake_pool_info.data_len() >= space {
+            msg!("Stake pool account already fits the current layout");
+            return Ok(());
+        }
+
+        let required_lamports = Rent::get()?
+            .minimum_balance(space)
+            .saturating_sub(stake_pool_info.lamports());
+        if required_lamports > 0 {
+            Self::sol_transfer(
+                payer_info.clone(),
+                stake_pool_info.clone(),
+                system_program_info.clone(),
+                required_lamports,
+            )?;
+        }
+
+        stake_pool_info.realloc(space, true)?;
+        // the new fields may start inside the old account, over bytes left by
+        // optional fields that were unset, so they are written out as defaults
+        stake_pool.serialize_into(&mut stake_pool_info.data.borrow_mut())
+    }
+
     /// Processes [Instruction](enum.Instruction.html).
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], input: &[u8]) -> ProgramResult {
         let instruction = StakePoolInstruction::try_from_slice(input)?;
@@ -2824,11 +4456,11 @@ impl Processor {
             }
             StakePoolInstruction::DepositStake => {
                 msg!("Instruction: DepositStake");
-                Self::process_deposit_stake(program_id, accounts)
+                Self::process_deposit_stake(program_id, accounts, None)
             }
             StakePoolInstruction::WithdrawStake(amount) => {
                 msg!("Instruction: WithdrawStake");
-                Self::process_withdraw_stake(program_id, accounts, amount)
+                Self::process_withdraw_stake(program_id, accounts, amount, None)
             }
             StakePoolInstruction::SetFee { fee } => {
                 msg!("Instruction: SetFee");
@@ -2848,11 +4480,147 @@ impl Processor {
             }
             StakePoolInstruction::DepositSol(lamports) => {
                 msg!("Instruction: DepositSol");
-                Self::process_deposit_sol(program_id, accounts, lamports)
+                Self::process_deposit_sol(program_id, accounts, lamports, None)
             }
             StakePoolInstruction::WithdrawSol(pool_tokens) => {
                 msg!("Instruction: WithdrawSol");
-                Self::process_withdraw_sol(program_id, accounts, pool_tokens)
+                Self::process_withdraw_sol(program_id, accounts, pool_tokens, None)
+            }
+            StakePoolInstruction::CreateTokenMetadata { name, symbol, uri } => {
+                msg!("Instruction: CreateTokenMetadata");
+                Self::process_create_token_metadata(program_id, accounts, name, symbol, uri)
+            }
+            StakePoolInstruction::UpdateTokenMetadata { name, symbol, uri } => {
+                msg!("Instruction: UpdateTokenMetadata");
+                Self::process_update_token_metadata(program_id, accounts, name, symbol, uri)
+            }
+            StakePoolInstruction::Redelegate {
+                lamports,
+                source_transient_stake_seed,
+                destination_transient_stake_seed,
+            } => {
+                msg!("Instruction: Redelegate");
+                Self::process_redelegate(
+                    program_id,
+                    accounts,
+                    lamports,
+                    source_transient_stake_seed,
+                    destination_transient_stake_seed,
+                )
+            }
+            StakePoolInstruction::DepositStakeWithSlippage {
+                minimum_pool_tokens_out,
+            } => {
+                msg!("Instruction: DepositStakeWithSlippage");
+                Self::process_deposit_stake(program_id, accounts, Some(minimum_pool_tokens_out))
+            }
+            StakePoolInstruction::WithdrawStakeWithSlippage {
+                pool_tokens_in,
+                minimum_lamports_out,
+            } => {
+                msg!("Instruction: WithdrawStakeWithSlippage");
+                Self::process_withdraw_stake(
+                    program_id,
+                    accounts,
+                    pool_tokens_in,
+                    Some(minimum_lamports_out),
+                )
+            }
+            StakePoolInstruction::DepositSolWithSlippage {
+                lamports_in,
+                minimum_pool_tokens_out,
+            } => {
+                msg!("Instruction: DepositSolWithSlippage");
+                Self::process_deposit_sol(
+                    program_id,
+                    accounts,
+                    lamports_in,
+                    Some(minimum_pool_tokens_out),
+                )
+            }
+            StakePoolInstruction::WithdrawSolWithSlippage {
+                pool_tokens_in,
+                minimum_lamports_out,
+            } => {
+                msg!("Instruction: WithdrawSolWithSlippage");
+                Self::process_withdraw_sol(
+                    program_id,
+                    accounts,
+                    pool_tokens_in,
+                    Some(minimum_lamports_out),
+                )
+            }
+            StakePoolInstruction::SetLiquidityBuffer {
+                target_reserve,
+                max_sol_withdrawal_fee,
+            } => {
+                msg!("Instruction: SetLiquidityBuffer");
+                Self::process_set_liquidity_buffer(
+                    program_id,
+                    accounts,
+                    target_reserve,
+                    max_sol_withdrawal_fee,
+                )
+            }
+            StakePoolInstruction::SetValidatorConfig {
+                vote_account_address,
+                max_stake_bps,
+                no_deposits,
+            } => {
+                msg!("Instruction: SetValidatorConfig");
+                Self::process_set_validator_config(
+                    program_id,
+                    accounts,
+                    vote_account_address,
+                    max_stake_bps,
+                    no_deposits,
+                )
+            }
+            StakePoolInstruction::SetManagerFeeVesting { vesting_epochs } => {
+                msg!("Instruction: SetManagerFeeVesting");
+                Self::process_set_manager_fee_vesting(program_id, accounts, vesting_epochs)
+            }
+            StakePoolInstruction::ClaimManagerFee => {
+                msg!("Instruction: ClaimManagerFee");
+                Self::process_claim_manager_fee(program_id, accounts)
+            }
+            StakePoolInstruction::WithdrawToTicket {
+                pool_tokens_in,
+                minimum_lamports_out,
+            } => {
+                msg!("Instruction: WithdrawToTicket");
+                Self::process_withdraw_to_ticket(
+                    program_id,
+                    accounts,
+                    pool_tokens_in,
+                    minimum_lamports_out,
+                )
+            }
+            StakePoolInstruction::ClaimTicket => {
+                msg!("Instruction: ClaimTicket");
+                Self::process_claim_ticket(program_id, accounts)
+            }
+            StakePoolInstruction::DepositStakeForRedelegation {
+                minimum_pool_tokens_out,
+            } => {
+                msg!("Instruction: DepositStakeForRedelegation");
+                Self::process_deposit_stake_for_redelegation(
+                    program_id,
+                    accounts,
+                    minimum_pool_tokens_out,
+                )
+            }
+            StakePoolInstruction::DeactivatePendingStake => {
+                msg!("Instruction: DeactivatePendingStake");
+                Self::process_deactivate_pending_stake(program_id, accounts)
+            }
+            StakePoolInstruction::ClosePendingRedelegation => {
+                msg!("Instruction: ClosePendingRedelegation");
+                Self::process_close_pending_redelegation(program_id, accounts)
+            }
+            StakePoolInstruction::ReallocStakePool => {
+                msg!("Instruction: ReallocStakePool");
+                Self::process_realloc_stake_pool(program_id, accounts)
             }
         }
     }
@@ -2902,6 +4670,23 @@ impl PrintProgramError for StakePoolError {
             StakePoolError::TransientAccountInUse => msg!("Error: Provided validator stake account already has a transient stake account in use"),
             StakePoolError::InvalidSolWithdrawAuthority => msg!("Error: Provided sol withdraw authority does not match the program's"),
             StakePoolError::SolWithdrawalTooLarge => msg!("Error: Too much SOL withdrawn from the stake pool's reserve account"),
+            StakePoolError::InvalidMetadataAccount => msg!("Error: Metadata account derived from pool mint account does not match the one passed to program"),
+            StakePoolError::TokenMetadataTooLong => msg!("Error: Token metadata name, symbol or uri is too long"),
+            StakePoolError::RedelegateToSameValidator => msg!("Error: Redelegation source and destination validators are the same"),
+            StakePoolError::ExceededSlippage => msg!("Error: Pool tokens or lamports received are fewer than the provided minimum"),
+            StakePoolError::ValidatorDepositsDisabled => msg!("Error: Validator is configured to refuse stake deposits and increases"),
+            StakePoolError::ValidatorStakeCapExceeded => msg!("Error: Operation would put the validator above its maximum share of the pool"),
+            StakePoolError::InvalidValidatorStakeCap => msg!("Error: Validator stake cap is greater than the whole pool"),
+            StakePoolError::InvalidFeeVestingAccount => msg!("Error: Fee vesting account is not a pool token account owned by the withdraw authority"),
+            StakePoolError::FeeVestingDisabled => msg!("Error: Manager fee vesting is not enabled on the stake pool"),
+            StakePoolError::FeeVestingLocked => msg!("Error: Fee vesting can't be shortened while fees are locked"),
+            StakePoolError::UnsupportedMintExtension => msg!("Error: Pool mint uses an unsupported token extension"),
+            StakePoolError::UnsupportedFeeAccountExtension => msg!("Error: Fee account uses an unsupported token extension"),
+            StakePoolError::InvalidWithdrawTicket => msg!("Error: Withdrawal ticket is not initialized, or belongs to another stake pool or owner"),
+            StakePoolError::WithdrawTicketNotClaimable => msg!("Error: Withdrawal ticket can't be claimed yet"),
+            StakePoolError::InvalidPendingRedelegation => msg!("Error: Pending redelegation is not initialized, or belongs to another stake pool or stake account"),
+            StakePoolError::ValidatorInPool => msg!("Error: Stake is delegated to a validator in the stake pool, deposit it with DepositStake"),
+            StakePoolError::StakePoolNotReallocated => msg!("Error: Stake pool account must be grown with ReallocStakePool before storing this change"),
         }
     }
 }
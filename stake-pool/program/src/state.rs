@@ -1,17 +1,23 @@
 //! State transition types
 
-use spl_token::state::{Account, AccountState};
+use spl_token_2022::{
+    extension::{
+        default_account_state::DefaultAccountState, mint_close_authority::MintCloseAuthority,
+        ExtensionType, StateWithExtensions,
+    },
+    state::{Account, AccountState, Mint},
+};
 use {
     crate::{
-        big_vec::BigVec, error::StakePoolError, MAX_WITHDRAWAL_FEE_INCREASE,
-        WITHDRAWAL_BASELINE_FEE,
+        big_vec::BigVec, error::StakePoolError, MAX_STAKE_BPS, MAX_VALIDATORS_TO_UPDATE,
+        MAX_WITHDRAWAL_FEE_INCREASE, WITHDRAWAL_BASELINE_FEE,
     },
     borsh::{BorshDeserialize, BorshSchema, BorshSerialize},
     num_derive::FromPrimitive,
     num_traits::FromPrimitive,
     solana_program::{
         account_info::AccountInfo,
-        borsh::get_instance_packed_len,
+        borsh::{get_instance_packed_len, get_packed_len},
         msg,
         program_error::ProgramError,
         program_memory::sol_memcmp,
@@ -32,6 +38,12 @@ pub enum AccountType {
     StakePool,
     /// Validator stake list
     ValidatorList,
+    /// Pool token metadata
+    TokenMetadata,
+    /// Delayed withdrawal ticket
+    WithdrawTicket,
+    /// Deposited stake waiting to be redelegated to a pool validator
+    PendingRedelegation,
 }
 
 impl Default for AccountType {
@@ -40,9 +52,103 @@ impl Default for AccountType {
     }
 }
 
+/// Reserve target for instant SOL withdrawals, set by the manager
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct LiquidityBuffer {
+    /// Proportion of the pool's total lamports to keep undelegated in the reserve
+    pub target_reserve: Fee,
+
+    /// SOL withdrawal fee charged when a withdrawal empties the buffer
+    /// completely. The fee rises from `sol_withdrawal_fee` to this value with
+    /// the square of the depleted share of the buffer.
+    pub max_sol_withdrawal_fee: Fee,
+
+    /// Lamports missing from the reserve to reach the target, as of the last
+    /// `UpdateStakePoolBalance`
+    pub deficit_lamports: u64,
+}
+
+impl LiquidityBuffer {
+    /// Lamports to keep in the reserve for a pool with `total_lamports`
+    #[inline]
+    pub fn target_lamports(&self, total_lamports: u64) -> Option<u64> {
+        u64::try_from(self.target_reserve.apply(total_lamports)?).ok()
+    }
+}
+
+/// Vesting of the manager's epoch fee, set by the manager
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, BorshSerialize, BorshDeserialize, BorshSchema)]
+pub struct FeeVesting {
+    /// Pool token account owned by the withdraw authority, receiving the
+    /// epoch fee until it is claimed by the manager
+    pub vesting_account: Pubkey,
+
+    /// Number of epochs over which each epoch fee vests
+    pub vesting_epochs: u64,
+
+    /// Fees in the vesting account not vested as of `last_vesting_epoch`
+    pub locked_amount: u64,
+
+    /// Fees in the vesting account vested but not claimed yet
+    pub vested_amount: u64,
+
+    /// Epoch of the last vesting computation
+    pub last_vesting_epoch: u64,
+
+    /// Epoch at which all locked fees are vested, the average of the end
+    /// epochs of the locked fees weighted by their amounts
+    pub end_epoch: u64,
+}
+
+impl FeeVesting {
+    /// Move the share of the locked fees vested since the last computation
+    /// to the vested amount, linearly until `end_epoch`
+    pub fn vest(&mut self, epoch: u64) -> Option<()> {
+        if epoch <= self.last_vesting_epoch {
+            return Some(());
+        }
+        let released = if epoch >= self.end_epoch {
+            self.locked_amount
+        } else {
+            u64::try_from(
+                (self.locked_amount as u128)
+                    .checked_mul(epoch.checked_sub(self.last_vesting_epoch)? as u128)?
+                    .checked_div(self.end_epoch.checked_sub(self.last_vesting_epoch)? as u128)?,
+            )
+            .ok()?
+        };
+        self.locked_amount = self.locked_amount.checked_sub(released)?;
+        self.vested_amount = self.vested_amount.checked_add(released)?;
+        self.last_vesting_epoch = epoch;
+        Some(())
+    }
+
+    /// Lock a newly minted fee to vest over `vesting_epochs` from `epoch`,
+    /// without delaying the fees already locked: the end epoch moves to the
+    /// average of both schedules, weighted by the amounts, rounded down so
+    /// that rounding never pushes back the fees already locked
+    pub fn lock(&mut self, amount: u64, epoch: u64) -> Option<()> {
+        self.vest(epoch)?;
+        let locked_amount = self.locked_amount.checked_add(amount)?;
+        if locked_amount == 0 {
+            return Some(());
+        }
+        let new_end_epoch = epoch.checked_add(self.vesting_epochs)?;
+        let weighted_end_epochs = (self.locked_amount as u128)
+            .checked_mul(self.end_epoch as u128)?
+            .checked_add((amount as u128).checked_mul(new_end_epoch as u128)?)?;
+        self.end_epoch =
+            u64::try_from(weighted_end_epochs.checked_div(locked_amount as u128)?).ok()?;
+        self.locked_amount = locked_amount;
+        Some(())
+    }
+}
+
 /// Initialized program details.
 #[repr(C)]
-#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+#[derive(Clone, Debug, Default, PartialEq, BorshSerialize, BorshSchema)]
 pub struct StakePool {
     /// Account type, must be StakePool currently
     pub account_type: AccountType,
@@ -153,8 +259,118 @@ pub struct StakePool {
 
     /// Last epoch's total lamports, used only for APR estimation
     pub last_epoch_total_lamports: u64,
+
+    /// Target of undelegated lamports in the reserve for instant SOL
+    /// withdrawals, which makes the SOL withdrawal fee rise as the reserve
+    /// goes below the target
+    pub liquidity_buffer: Option<LiquidityBuffer>,
+
+    /// If set, the epoch fee is minted to a vesting account and claimed by
+    /// the manager as it vests
+    pub fee_vesting: Option<FeeVesting>,
+
+    /// Lamports owed to unclaimed withdrawal tickets, kept out of
+    /// `total_lamports` and reserved in the reserve stake account once the
+    /// staker has deactivated them
+    pub withdraw_ticket_lamports: u64,
+
+    /// Fee assessed on deposits of stake delegated to validators outside of
+    /// the pool, covering the epoch spent redelegating it
+    pub redelegation_deposit_fee: Fee,
+
+    /// Lamports in deposited stake accounts not yet moved into the reserve,
+    /// counted in `total_lamports`
+    pub pending_redelegation_lamports: u64,
+}
+impl BorshDeserialize for StakePool {
+    fn deserialize(buf: &mut &[u8]) -> std::io::Result<Self> {
+        // The whole account is expected, its length giving the layout
+        let data_len = buf.len();
+        let mut stake_pool = Self {
+            account_type: BorshDeserialize::deserialize(buf)?,
+            manager: BorshDeserialize::deserialize(buf)?,
+            staker: BorshDeserialize::deserialize(buf)?,
+            stake_deposit_authority: BorshDeserialize::deserialize(buf)?,
+            stake_withdraw_bump_seed: BorshDeserialize::deserialize(buf)?,
+            validator_list: BorshDeserialize::deserialize(buf)?,
+            reserve_stake: BorshDeserialize::deserialize(buf)?,
+            pool_mint: BorshDeserialize::deserialize(buf)?,
+            manager_fee_account: BorshDeserialize::deserialize(buf)?,
+            token_program_id: BorshDeserialize::deserialize(buf)?,
+            total_lamports: BorshDeserialize::deserialize(buf)?,
+            pool_token_supply: BorshDeserialize::deserialize(buf)?,
+            last_update_epoch: BorshDeserialize::deserialize(buf)?,
+            lockup: BorshDeserialize::deserialize(buf)?,
+            epoch_fee: BorshDeserialize::deserialize(buf)?,
+            next_epoch_fee: BorshDeserialize::deserialize(buf)?,
+            preferred_deposit_validator_vote_address: BorshDeserialize::deserialize(buf)?,
+            preferred_withdraw_validator_vote_address: BorshDeserialize::deserialize(buf)?,
+            stake_deposit_fee: BorshDeserialize::deserialize(buf)?,
+            stake_withdrawal_fee: BorshDeserialize::deserialize(buf)?,
+            next_stake_withdrawal_fee: BorshDeserialize::deserialize(buf)?,
+            stake_referral_fee: BorshDeserialize::deserialize(buf)?,
+            sol_deposit_authority: BorshDeserialize::deserialize(buf)?,
+            sol_deposit_fee: BorshDeserialize::deserialize(buf)?,
+            sol_referral_fee: BorshDeserialize::deserialize(buf)?,
+            sol_withdraw_authority: BorshDeserialize::deserialize(buf)?,
+            sol_withdrawal_fee: BorshDeserialize::deserialize(buf)?,
+            next_sol_withdrawal_fee: BorshDeserialize::deserialize(buf)?,
+            last_epoch_pool_token_supply: BorshDeserialize::deserialize(buf)?,
+            last_epoch_total_lamports: BorshDeserialize::deserialize(buf)?,
+            ..Self::default()
+        };
+        // Pools created before the fields below were added are too short to
+        // hold them, so they keep their default values. Their remaining bytes
+        // may be left over from optional fields that were later unset, so
+        // they are never read.
+        if data_len > Self::legacy_len() {
+            stake_pool.liquidity_buffer = BorshDeserialize::deserialize(buf)?;
+            stake_pool.fee_vesting = BorshDeserialize::deserialize(buf)?;
+            stake_pool.withdraw_ticket_lamports = BorshDeserialize::deserialize(buf)?;
+            stake_pool.redelegation_deposit_fee = BorshDeserialize::deserialize(buf)?;
+            stake_pool.pending_redelegation_lamports = BorshDeserialize::deserialize(buf)?;
+        }
+        Ok(stake_pool)
+    }
 }
+
 impl StakePool {
+    /// Size of stake pool accounts created before the liquidity buffer, fee
+    /// vesting, withdrawal tickets and redelegation deposits, which must be
+    /// grown with `ReallocStakePool` to store them
+    pub fn legacy_len() -> usize {
+        get_packed_len::<Self>()
+            - get_packed_len::<Option<LiquidityBuffer>>()
+            - get_packed_len::<Option<FeeVesting>>()
+            - get_packed_len::<u64>()
+            - get_packed_len::<Fee>()
+            - get_packed_len::<u64>()
+    }
+
+    /// Writes the stake pool into its account data. Accounts of the legacy
+    /// length only get the fields they were created with, so the newer
+    /// fields must still be at their default values.
+    pub fn serialize_into(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        if data.len() > Self::legacy_len() {
+            return self.serialize(&mut &mut *data).map_err(Into::into);
+        }
+        if self.liquidity_buffer.is_some()
+            || self.fee_vesting.is_some()
+            || self.withdraw_ticket_lamports != 0
+            || self.redelegation_deposit_fee != Fee::default()
+            || self.pending_redelegation_lamports != 0
+        {
+            msg!("Stake pool account must be grown with ReallocStakePool to store this change");
+            return Err(StakePoolError::StakePoolNotReallocated.into());
+        }
+        // The default values of the newer fields serialize to zeroes, so
+        // whatever does not fit in the account is dropped
+        let packed = self.try_to_vec()?;
+        let len = packed.len().min(data.len());
+        data[..len].copy_from_slice(&packed[..len]);
+        Ok(())
+    }
+
     /// calculate the pool tokens that should be minted for a deposit of `stake_lamports`
     #[inline]
     pub fn calc_pool_tokens_for_deposit(&self, stake_lamports: u64) -> Option<u64> {
@@ -197,12 +413,59 @@ impl StakePool {
         u64::try_from(self.sol_withdrawal_fee.apply(pool_tokens)?).ok()
     }
 
+    /// calculate pool tokens to be deducted as SOL withdrawal fees when
+    /// `reserve_lamports` are available in the reserve, following the
+    /// liquidity buffer curve if there is one
+    #[inline]
+    pub fn calc_pool_tokens_sol_withdrawal_fee_with_buffer(
+        &self,
+        pool_tokens: u64,
+        reserve_lamports: u64,
+    ) -> Option<u64> {
+        let base_fee = self.calc_pool_tokens_sol_withdrawal_fee(pool_tokens)?;
+        let liquidity_buffer = match &self.liquidity_buffer {
+            Some(liquidity_buffer) => liquidity_buffer,
+            None => return Some(base_fee),
+        };
+        let max_fee =
+            u64::try_from(liquidity_buffer.max_sol_withdrawal_fee.apply(pool_tokens)?).ok()?;
+        let target_lamports = liquidity_buffer.target_lamports(self.total_lamports)?;
+        if max_fee <= base_fee || target_lamports == 0 {
+            return Some(base_fee);
+        }
+
+        // The whole amount is used to find how far the withdrawal takes the
+        // reserve below its target, so the fee is never underestimated
+        let withdraw_lamports = self.calc_lamports_withdraw_amount(pool_tokens)?;
+        let remaining_lamports = reserve_lamports.saturating_sub(withdraw_lamports);
+        let shortfall = target_lamports.saturating_sub(remaining_lamports) as u128;
+        let target_lamports = target_lamports as u128;
+
+        // The extra fee grows with the square of the depleted share of the buffer
+        let extra_fee = (max_fee.checked_sub(base_fee)? as u128)
+            .checked_mul(shortfall)?
+            .checked_div(target_lamports)?
+            .checked_mul(shortfall)?
+            .checked_div(target_lamports)?;
+        base_fee.checked_add(u64::try_from(extra_fee).ok()?)
+    }
+
     /// calculate pool tokens to be deducted as stake deposit fees
     #[inline]
     pub fn calc_pool_tokens_stake_deposit_fee(&self, pool_tokens_minted: u64) -> Option<u64> {
         u64::try_from(self.stake_deposit_fee.apply(pool_tokens_minted)?).ok()
     }
 
+    /// calculate pool tokens to be deducted as fees on deposits of stake
+    /// delegated outside of the pool
+    #[inline]
+    pub fn calc_pool_tokens_redelegation_deposit_fee(
+        &self,
+        pool_tokens_minted: u64,
+    ) -> Option<u64> {
+        u64::try_from(self.redelegation_deposit_fee.apply(pool_tokens_minted)?).ok()
+    }
+
     /// calculate pool tokens to be deducted from deposit fees as referral fees
     #[inline]
     pub fn calc_pool_tokens_stake_referral_fee(&self, stake_deposit_fee: u64) -> Option<u64> {
@@ -289,15 +552,49 @@ impl StakePool {
         &self,
         manager_fee_info: &AccountInfo,
     ) -> Result<(), ProgramError> {
-        let token_account = Account::unpack(&manager_fee_info.data.borrow())?;
+        let account_data = manager_fee_info.data.borrow();
+        let token_account = StateWithExtensions::<Account>::unpack(&account_data)?;
         if manager_fee_info.owner != &self.token_program_id
-            || token_account.state != AccountState::Initialized
-            || token_account.mint != self.pool_mint
+            || token_account.base.state != AccountState::Initialized
+            || token_account.base.mint != self.pool_mint
         {
             msg!("Manager fee account is not owned by token program, is not initialized, or does not match stake pool's mint");
             return Err(StakePoolError::InvalidFeeAccount.into());
         }
-        Ok(())
+        check_fee_account_extensions(&token_account)
+    }
+
+    /// Account receiving the epoch fee, the fee vesting account if enabled
+    pub fn epoch_fee_account(&self) -> &Pubkey {
+        self.fee_vesting
+            .as_ref()
+            .map(|fee_vesting| &fee_vesting.vesting_account)
+            .unwrap_or(&self.manager_fee_account)
+    }
+
+    /// Check the fee vesting account is a pool token account owned by the
+    /// withdraw authority, without delegate or close authority, so that only
+    /// the program can move its tokens
+    pub(crate) fn check_fee_vesting_account(
+        &self,
+        fee_vesting_info: &AccountInfo,
+        withdraw_authority: &Pubkey,
+    ) -> Result<(), ProgramError> {
+        if fee_vesting_info.owner != &self.token_program_id {
+            return Err(StakePoolError::InvalidFeeVestingAccount.into());
+        }
+        let account_data = fee_vesting_info.data.borrow();
+        let token_account = StateWithExtensions::<Account>::unpack(&account_data)?;
+        if token_account.base.state != AccountState::Initialized
+            || token_account.base.mint != self.pool_mint
+            || token_account.base.owner != *withdraw_authority
+            || token_account.base.delegate.is_some()
+            || token_account.base.close_authority.is_some()
+        {
+            msg!("Fee vesting account must be a pool token account owned by the withdraw authority, without delegate or close authority");
+            return Err(StakePoolError::InvalidFeeVestingAccount.into());
+        }
+        check_fee_account_extensions(&token_account)
     }
 
     /// Checks that the withdraw authority is valid
@@ -474,11 +771,56 @@ impl StakePool {
             }
             FeeType::SolDeposit(new_fee) => self.sol_deposit_fee = *new_fee,
             FeeType::StakeDeposit(new_fee) => self.stake_deposit_fee = *new_fee,
+            FeeType::RedelegationDeposit(new_fee) => self.redelegation_deposit_fee = *new_fee,
         };
         Ok(())
     }
 }
 
+/// Check that the pool mint only uses token extensions the stake pool
+/// supports. A mint close authority or a frozen default account state could
+/// lock pool tokens, and can't be set again once the freeze authority and
+/// close authority are gone, so they're only accepted when disabled.
+pub(crate) fn check_pool_mint_extensions(
+    pool_mint: &StateWithExtensions<Mint>,
+) -> Result<(), ProgramError> {
+    for extension_type in pool_mint.get_extension_types()? {
+        let supported = match extension_type {
+            ExtensionType::MintCloseAuthority => {
+                let extension = pool_mint.get_extension::<MintCloseAuthority>()?;
+                Option::<Pubkey>::from(extension.close_authority).is_none()
+            }
+            ExtensionType::DefaultAccountState => {
+                let extension = pool_mint.get_extension::<DefaultAccountState>()?;
+                extension.state == AccountState::Initialized as u8
+            }
+            _ => false,
+        };
+        if !supported {
+            msg!("Pool mint extension {:?} is not supported", extension_type);
+            return Err(StakePoolError::UnsupportedMintExtension.into());
+        }
+    }
+    Ok(())
+}
+
+/// Check that a fee account only uses token extensions that can't block
+/// incoming fee transfers
+pub(crate) fn check_fee_account_extensions(
+    fee_account: &StateWithExtensions<Account>,
+) -> Result<(), ProgramError> {
+    for extension_type in fee_account.get_extension_types()? {
+        if extension_type != ExtensionType::ImmutableOwner {
+            msg!(
+                "Fee account extension {:?} is not supported",
+                extension_type
+            );
+            return Err(StakePoolError::UnsupportedFeeAccountExtension.into());
+        }
+    }
+    Ok(())
+}
+
 /// Storage list for all validator stake accounts in the pool.
 #[repr(C)]
 #[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
@@ -553,8 +895,15 @@ pub struct ValidatorStakeInfo {
     /// Start of the validator transient account seed suffixess
     pub transient_seed_suffix_start: u64,
 
-    /// End of the validator transient account seed suffixes
-    pub transient_seed_suffix_end: u64,
+    /// Maximum proportion of the pool's total lamports that may be delegated
+    /// to this validator, in basis points, or 0 for no limit
+    pub max_stake_bps: u16,
+
+    /// If true, stake deposits and stake increases to this validator are refused
+    pub no_deposits: bool,
+
+    /// Reserved space, previously part of the unused transient seed suffix end
+    pub reserved: [u8; 5],
 
     /// Status of the validator stake account
     pub status: StakeStatus,
@@ -571,6 +920,53 @@ impl ValidatorStakeInfo {
             .unwrap()
     }
 
+    /// Get the maximum lamports that may be delegated to this validator in a
+    /// pool holding `total_lamports`, or `None` if the validator has no cap
+    pub fn max_stake_lamports(&self, total_lamports: u64) -> Option<u64> {
+        if self.max_stake_bps == 0 {
+            None
+        } else {
+            u64::try_from(
+                (total_lamports as u128)
+                    .checked_mul(self.max_stake_bps as u128)?
+                    .checked_div(MAX_STAKE_BPS as u128)?,
+            )
+            .ok()
+        }
+    }
+
+    /// Check that delegating `lamports` more to this validator respects its
+    /// configuration, in a pool holding `total_lamports` after the operation
+    pub fn check_stake_increase(
+        &self,
+        lamports: u64,
+        total_lamports: u64,
+    ) -> Result<(), ProgramError> {
+        if self.no_deposits {
+            msg!(
+                "Validator {} does not accept deposits",
+                self.vote_account_address
+            );
+            return Err(StakePoolError::ValidatorDepositsDisabled.into());
+        }
+        if let Some(max_stake_lamports) = self.max_stake_lamports(total_lamports) {
+            let stake_lamports = self
+                .stake_lamports()
+                .checked_add(lamports)
+                .ok_or(StakePoolError::CalculationFailure)?;
+            if stake_lamports > max_stake_lamports {
+                msg!(
+                    "Validator {} would hold {} lamports, above its cap of {} lamports",
+                    self.vote_account_address,
+                    stake_lamports,
+                    max_stake_lamports
+                );
+                return Err(StakePoolError::ValidatorStakeCapExceeded.into());
+            }
+        }
+        Ok(())
+    }
+
     /// Performs a very cheap comparison, for checking if this validator stake
     /// info matches the vote account address
     pub fn memcmp_pubkey(data: &[u8], vote_address_bytes: &[u8]) -> bool {
@@ -631,6 +1027,30 @@ impl ValidatorList {
         buffer_length.saturating_sub(header_size) / ValidatorStakeInfo::LEN
     }
 
+    /// Start indices of the `UpdateValidatorListBalance` chunks, of
+    /// `MAX_VALIDATORS_TO_UPDATE` entries each, still containing entries not
+    /// updated during `epoch`
+    pub fn stale_update_chunks(&self, epoch: u64) -> Vec<u32> {
+        self.validators
+            .chunks(MAX_VALIDATORS_TO_UPDATE)
+            .enumerate()
+            .filter(|(_, chunk)| {
+                chunk
+                    .iter()
+                    .any(|validator| validator.last_update_epoch < epoch)
+            })
+            .map(|(chunk_index, _)| (chunk_index * MAX_VALIDATORS_TO_UPDATE) as u32)
+            .collect()
+    }
+
+    /// Number of entries updated during `epoch`
+    pub fn updated_validators(&self, epoch: u64) -> usize {
+        self.validators
+            .iter()
+            .filter(|validator| validator.last_update_epoch >= epoch)
+            .count()
+    }
+
     /// Check if contains validator with particular pubkey
     pub fn contains(&self, vote_account_address: &Pubkey) -> bool {
         self.validators
@@ -682,6 +1102,27 @@ impl ValidatorListHeader {
         Ok((header, validator_list))
     }
 
+    /// Check if the validator list holds an entry for the vote account,
+    /// reading the account data without borrowing it mutably
+    pub fn deserialize_contains(
+        data: &[u8],
+        vote_account_address: &Pubkey,
+    ) -> Result<(Self, bool), ProgramError> {
+        let mut data_ref = data;
+        let header = ValidatorListHeader::deserialize(&mut data_ref)?;
+        let length = get_instance_packed_len(&header)?;
+        let vec_len = data
+            .get(length..length.saturating_add(4))
+            .and_then(|bytes| <[u8; 4]>::try_from(bytes).ok())
+            .map(u32::from_le_bytes)
+            .ok_or(ProgramError::AccountDataTooSmall)?;
+        let contains = data[length.saturating_add(4)..]
+            .chunks_exact(ValidatorStakeInfo::LEN)
+            .take(vec_len as usize)
+            .any(|entry| ValidatorStakeInfo::memcmp_pubkey(entry, vote_account_address.as_ref()));
+        Ok((header, contains))
+    }
+
     /// Extracts the validator list into its header and internal BigVec
     pub fn deserialize_vec(data: &mut [u8]) -> Result<(Self, BigVec), ProgramError> {
         let mut data_mut = &data[..];
@@ -787,6 +1228,8 @@ pub enum FeeType {
     StakeDeposit(Fee),
     /// SOL withdrawal fee
     SolWithdrawal(Fee),
+    /// Deposit fee for stake delegated to validators outside of the pool
+    RedelegationDeposit(Fee),
 }
 
 impl FeeType {
@@ -800,6 +1243,7 @@ impl FeeType {
             Self::SolWithdrawal(fee) => fee.numerator > fee.denominator,
             Self::SolDeposit(fee) => fee.numerator > fee.denominator,
             Self::StakeDeposit(fee) => fee.numerator > fee.denominator,
+            Self::RedelegationDeposit(fee) => fee.numerator > fee.denominator,
         };
         if too_high {
             msg!("Fee greater than 100%: {:?}", self);
@@ -818,6 +1262,151 @@ impl FeeType {
     }
 }
 
+/// Maximum length of the pool token name, in bytes
+pub const MAX_TOKEN_NAME_LENGTH: usize = 32;
+/// Maximum length of the pool token symbol, in bytes
+pub const MAX_TOKEN_SYMBOL_LENGTH: usize = 10;
+/// Maximum length of the pool token uri, in bytes
+pub const MAX_TOKEN_URI_LENGTH: usize = 200;
+
+/// Name, symbol and uri of a stake pool's pool token, stored at the address
+/// given by `find_token_metadata_program_address`
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct TokenMetadata {
+    /// Account type, must be TokenMetadata currently
+    pub account_type: AccountType,
+
+    /// Authority of the metadata, the stake pool withdraw authority
+    pub update_authority: Pubkey,
+
+    /// Stake pool that owns the pool token
+    pub stake_pool: Pubkey,
+
+    /// Pool token mint described by the metadata
+    pub mint: Pubkey,
+
+    /// Name of the pool token
+    pub name: String,
+
+    /// Symbol of the pool token
+    pub symbol: String,
+
+    /// Uri of a JSON document describing the pool token
+    pub uri: String,
+}
+impl TokenMetadata {
+    /// Size of the account, large enough for the longest name, symbol and uri
+    pub const LEN: usize = 1
+        + PUBKEY_BYTES * 3
+        + 4
+        + MAX_TOKEN_NAME_LENGTH
+        + 4
+        + MAX_TOKEN_SYMBOL_LENGTH
+        + 4
+        + MAX_TOKEN_URI_LENGTH;
+
+    /// Check if TokenMetadata is actually initialized as token metadata
+    pub fn is_valid(&self) -> bool {
+        self.account_type == AccountType::TokenMetadata
+    }
+
+    /// Check that the name, symbol and uri fit in the account
+    pub fn check_lengths(name: &str, symbol: &str, uri: &str) -> Result<(), StakePoolError> {
+        if name.len() > MAX_TOKEN_NAME_LENGTH
+            || symbol.len() > MAX_TOKEN_SYMBOL_LENGTH
+            || uri.len() > MAX_TOKEN_URI_LENGTH
+        {
+            msg!(
+                "Token metadata too long, maximum lengths are {} for the name, {} for the symbol and {} for the uri",
+                MAX_TOKEN_NAME_LENGTH,
+                MAX_TOKEN_SYMBOL_LENGTH,
+                MAX_TOKEN_URI_LENGTH
+            );
+            return Err(StakePoolError::TokenMetadataTooLong);
+        }
+        Ok(())
+    }
+}
+
+/// Claim on lamports of the reserve, created by `WithdrawToTicket` and paid
+/// out by `ClaimTicket`
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct WithdrawTicket {
+    /// Account type, must be WithdrawTicket currently
+    pub account_type: AccountType,
+
+    /// Stake pool owing the lamports
+    pub stake_pool: Pubkey,
+
+    /// Owner of the ticket, who must sign to claim it
+    pub owner: Pubkey,
+
+    /// Lamports paid out on claim
+    pub lamports: u64,
+
+    /// Epoch of the withdrawal, the ticket can be claimed from the next epoch
+    pub created_epoch: u64,
+}
+impl WithdrawTicket {
+    /// Size of the account
+    pub const LEN: usize = 1 + PUBKEY_BYTES * 2 + 8 + 8;
+
+    /// Check if WithdrawTicket is actually initialized as a withdrawal ticket
+    pub fn is_valid(&self) -> bool {
+        self.account_type == AccountType::WithdrawTicket
+    }
+
+    /// Check if the ticket account is uninitialized
+    pub fn is_uninitialized(&self) -> bool {
+        self.account_type == AccountType::Uninitialized
+    }
+
+    /// Check if the ticket can be claimed at `epoch`, deactivated stake only
+    /// reaches the reserve after an epoch boundary
+    pub fn is_claimable(&self, epoch: u64) -> bool {
+        epoch > self.created_epoch
+    }
+}
+
+/// Stake account deposited on a validator outside of the pool, created by
+/// `DepositStakeForRedelegation` and closed by `ClosePendingRedelegation`
+#[repr(C)]
+#[derive(Clone, Debug, Default, PartialEq, BorshDeserialize, BorshSerialize, BorshSchema)]
+pub struct PendingRedelegation {
+    /// Account type, must be PendingRedelegation currently
+    pub account_type: AccountType,
+
+    /// Stake pool that received the deposit
+    pub stake_pool: Pubkey,
+
+    /// Deposited stake account, authorized to the pool withdraw authority
+    pub stake_account: Pubkey,
+
+    /// Receiver of this account's rent-exemption once it is closed
+    pub rent_receiver: Pubkey,
+
+    /// Lamports of the stake account counted in
+    /// `StakePool::pending_redelegation_lamports`
+    pub lamports: u64,
+}
+impl PendingRedelegation {
+    /// Size of the account
+    pub const LEN: usize = 1 + PUBKEY_BYTES * 3 + 8;
+
+    /// Check if PendingRedelegation is actually initialized as a pending
+    /// redelegation
+    pub fn is_valid(&self) -> bool {
+        self.account_type == AccountType::PendingRedelegation
+    }
+
+    /// Check if the pending redelegation account is uninitialized
+    pub fn is_uninitialized(&self) -> bool {
+        self.account_type == AccountType::Uninitialized
+    }
+}
+
 #[cfg(test)]
 mod test {
     use {
@@ -856,7 +1445,9 @@ mod test {
                     transient_stake_lamports: u64::from_le_bytes([128; 8]),
                     last_update_epoch: u64::from_le_bytes([64; 8]),
                     transient_seed_suffix_start: 0,
-                    transient_seed_suffix_end: 0,
+                    max_stake_bps: 0,
+                    no_deposits: false,
+                    reserved: [0; 5],
                 },
                 ValidatorStakeInfo {
                     status: StakeStatus::DeactivatingTransient,
@@ -865,7 +1456,9 @@ mod test {
                     transient_stake_lamports: 222222222,
                     last_update_epoch: 11223445566,
                     transient_seed_suffix_start: 0,
-                    transient_seed_suffix_end: 0,
+                    max_stake_bps: 2_500,
+                    no_deposits: true,
+                    reserved: [0; 5],
                 },
                 ValidatorStakeInfo {
                     status: StakeStatus::ReadyForRemoval,
@@ -874,7 +1467,9 @@ mod test {
                     transient_stake_lamports: 0,
                     last_update_epoch: 999999999999999,
                     transient_seed_suffix_start: 0,
-                    transient_seed_suffix_end: 0,
+                    max_stake_bps: 0,
+                    no_deposits: false,
+                    reserved: [0; 5],
                 },
             ],
         }
@@ -914,6 +1509,210 @@ mod test {
         assert_eq!(stake_list_unpacked, stake_list);
     }
 
+    #[test]
+    fn legacy_stake_pool_packing() {
+        let legacy_len = StakePool::legacy_len();
+        // also hardcoded by the JS client
+        assert_eq!(legacy_len, 611);
+        let default_tail_len = get_instance_packed_len(&(
+            None::<LiquidityBuffer>,
+            None::<FeeVesting>,
+            0u64,
+            Fee::default(),
+            0u64,
+        ))
+        .unwrap();
+        let fee = Fee {
+            numerator: 1,
+            denominator: 100,
+        };
+
+        // Every optional field set, filling the legacy account completely
+        let stake_pool = StakePool {
+            account_type: AccountType::StakePool,
+            manager: Pubkey::new_unique(),
+            total_lamports: 1_000,
+            pool_token_supply: 900,
+            next_epoch_fee: Some(fee),
+            preferred_deposit_validator_vote_address: Some(Pubkey::new_unique()),
+            preferred_withdraw_validator_vote_address: Some(Pubkey::new_unique()),
+            next_stake_withdrawal_fee: Some(fee),
+            sol_deposit_authority: Some(Pubkey::new_unique()),
+            sol_withdraw_authority: Some(Pubkey::new_unique()),
+            next_sol_withdrawal_fee: Some(fee),
+            last_epoch_total_lamports: 800,
+            ..StakePool::default()
+        };
+        let packed = stake_pool.try_to_vec().unwrap();
+        let legacy_data = &packed[..packed.len() - default_tail_len];
+        assert_eq!(legacy_data.len(), legacy_len);
+        let unpacked = try_from_slice_unchecked::<StakePool>(legacy_data).unwrap();
+        assert_eq!(unpacked, stake_pool);
+
+        // No optional field set, leaving zero padding in the legacy account
+        let stake_pool = StakePool {
+            account_type: AccountType::StakePool,
+            total_lamports: 1_000,
+            last_epoch_total_lamports: 800,
+            ..StakePool::default()
+        };
+        let packed = stake_pool.try_to_vec().unwrap();
+        let mut legacy_data = vec![0u8; legacy_len];
+        legacy_data[..packed.len() - default_tail_len]
+            .copy_from_slice(&packed[..packed.len() - default_tail_len]);
+        let unpacked = try_from_slice_unchecked::<StakePool>(&legacy_data).unwrap();
+        assert_eq!(unpacked, stake_pool);
+
+        // Bytes left over from optional fields that were later unset are not
+        // read as the new fields, even if they look like them
+        let mut stale_data = legacy_data.clone();
+        let stale_tail = StakePool {
+            withdraw_ticket_lamports: u64::MAX,
+            pending_redelegation_lamports: u64::MAX,
+            ..StakePool::default()
+        }
+        .try_to_vec()
+        .unwrap();
+        let stale_tail = &stale_tail[stale_tail.len() - default_tail_len..];
+        let legacy_fields_len = packed.len() - default_tail_len;
+        stale_data[legacy_fields_len..legacy_fields_len + default_tail_len]
+            .copy_from_slice(stale_tail);
+        for byte in stale_data[legacy_fields_len + default_tail_len..].iter_mut() {
+            *byte = 2;
+        }
+        let unpacked = try_from_slice_unchecked::<StakePool>(&stale_data).unwrap();
+        assert_eq!(unpacked, stake_pool);
+        stale_data[legacy_fields_len] = 2;
+        let unpacked = try_from_slice_unchecked::<StakePool>(&stale_data).unwrap();
+        assert_eq!(unpacked, stake_pool);
+
+        // Legacy accounts are written without the new fields, which can't be
+        // set until the account is grown
+        stake_pool.serialize_into(&mut stale_data).unwrap();
+        assert_eq!(
+            try_from_slice_unchecked::<StakePool>(&stale_data).unwrap(),
+            stake_pool
+        );
+        let with_new_fields = StakePool {
+            pending_redelegation_lamports: 1,
+            ..stake_pool.clone()
+        };
+        assert_eq!(
+            with_new_fields.serialize_into(&mut stale_data),
+            Err(StakePoolError::StakePoolNotReallocated.into())
+        );
+
+        // The new fields are read once the account is large enough
+        let stake_pool = StakePool {
+            fee_vesting: Some(FeeVesting {
+                vesting_epochs: 10,
+                ..FeeVesting::default()
+            }),
+            withdraw_ticket_lamports: 100,
+            redelegation_deposit_fee: fee,
+            ..stake_pool
+        };
+        let mut data = vec![0u8; get_packed_len::<StakePool>()];
+        stake_pool.serialize_into(&mut data).unwrap();
+        let unpacked = try_from_slice_unchecked::<StakePool>(&data).unwrap();
+        assert_eq!(unpacked, stake_pool);
+    }
+
+    #[test]
+    fn token_metadata_packing() {
+        let token_metadata = TokenMetadata {
+            account_type: AccountType::TokenMetadata,
+            update_authority: Pubkey::new_unique(),
+            stake_pool: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            name: "n".repeat(MAX_TOKEN_NAME_LENGTH),
+            symbol: "s".repeat(MAX_TOKEN_SYMBOL_LENGTH),
+            uri: "u".repeat(MAX_TOKEN_URI_LENGTH),
+        };
+        assert_eq!(
+            get_instance_packed_len(&token_metadata).unwrap(),
+            TokenMetadata::LEN
+        );
+        let mut byte_vec = vec![0u8; TokenMetadata::LEN];
+        let mut bytes = byte_vec.as_mut_slice();
+        token_metadata.serialize(&mut bytes).unwrap();
+        let unpacked = try_from_slice_unchecked::<TokenMetadata>(&byte_vec).unwrap();
+        assert_eq!(unpacked, token_metadata);
+
+        // shorter fields leave zeroes at the end of the account
+        let token_metadata = TokenMetadata {
+            name: "Pool".to_string(),
+            symbol: "POOL".to_string(),
+            uri: String::new(),
+            ..token_metadata
+        };
+        let mut byte_vec = vec![0u8; TokenMetadata::LEN];
+        let mut bytes = byte_vec.as_mut_slice();
+        token_metadata.serialize(&mut bytes).unwrap();
+        let unpacked = try_from_slice_unchecked::<TokenMetadata>(&byte_vec).unwrap();
+        assert_eq!(unpacked, token_metadata);
+
+        assert_eq!(
+            TokenMetadata::check_lengths(&"n".repeat(MAX_TOKEN_NAME_LENGTH + 1), "", ""),
+            Err(StakePoolError::TokenMetadataTooLong)
+        );
+    }
+
+    #[test]
+    fn withdraw_ticket_packing() {
+        let ticket = WithdrawTicket {
+            account_type: AccountType::WithdrawTicket,
+            stake_pool: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            lamports: u64::MAX,
+            created_epoch: 10,
+        };
+        assert_eq!(
+            get_instance_packed_len(&ticket).unwrap(),
+            WithdrawTicket::LEN
+        );
+        let mut byte_vec = vec![0u8; WithdrawTicket::LEN];
+        let mut bytes = byte_vec.as_mut_slice();
+        ticket.serialize(&mut bytes).unwrap();
+        let unpacked = try_from_slice_unchecked::<WithdrawTicket>(&byte_vec).unwrap();
+        assert_eq!(unpacked, ticket);
+        assert!(unpacked.is_valid());
+
+        let empty =
+            try_from_slice_unchecked::<WithdrawTicket>(&[0u8; WithdrawTicket::LEN]).unwrap();
+        assert!(empty.is_uninitialized());
+
+        assert!(!ticket.is_claimable(9));
+        assert!(!ticket.is_claimable(10));
+        assert!(ticket.is_claimable(11));
+    }
+
+    #[test]
+    fn pending_redelegation_packing() {
+        let pending_redelegation = PendingRedelegation {
+            account_type: AccountType::PendingRedelegation,
+            stake_pool: Pubkey::new_unique(),
+            stake_account: Pubkey::new_unique(),
+            rent_receiver: Pubkey::new_unique(),
+            lamports: u64::MAX,
+        };
+        assert_eq!(
+            get_instance_packed_len(&pending_redelegation).unwrap(),
+            PendingRedelegation::LEN
+        );
+        let mut byte_vec = vec![0u8; PendingRedelegation::LEN];
+        let mut bytes = byte_vec.as_mut_slice();
+        pending_redelegation.serialize(&mut bytes).unwrap();
+        let unpacked = try_from_slice_unchecked::<PendingRedelegation>(&byte_vec).unwrap();
+        assert_eq!(unpacked, pending_redelegation);
+        assert!(unpacked.is_valid());
+
+        let empty =
+            try_from_slice_unchecked::<PendingRedelegation>(&[0u8; PendingRedelegation::LEN])
+                .unwrap();
+        assert!(empty.is_uninitialized());
+    }
+
     #[test]
     fn validator_list_active_stake() {
         let max_validators = 10_000;
@@ -969,6 +1768,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn validator_list_deserialize_contains() {
+        let stake_list = test_validator_list(10);
+        let serialized = stake_list.try_to_vec().unwrap();
+        for validator in &stake_list.validators {
+            let (header, contains) = ValidatorListHeader::deserialize_contains(
+                &serialized,
+                &validator.vote_account_address,
+            )
+            .unwrap();
+            assert!(header.is_valid());
+            assert!(contains);
+        }
+        let (_, contains) =
+            ValidatorListHeader::deserialize_contains(&serialized, &Pubkey::new_unique()).unwrap();
+        assert!(!contains);
+
+        // entries past the length of the vec are not part of the list
+        let mut short_list = stake_list;
+        let removed = short_list.validators.pop().unwrap();
+        let mut serialized = short_list.try_to_vec().unwrap();
+        serialized.extend_from_slice(&removed.try_to_vec().unwrap());
+        let (_, contains) =
+            ValidatorListHeader::deserialize_contains(&serialized, &removed.vote_account_address)
+                .unwrap();
+        assert!(!contains);
+
+        assert_eq!(
+            ValidatorListHeader::deserialize_contains(
+                &serialized[..ValidatorListHeader::LEN + 2],
+                &Pubkey::new_unique()
+            )
+            .unwrap_err(),
+            ProgramError::AccountDataTooSmall
+        );
+    }
+
     #[test]
     fn validator_list_iter() {
         let max_validators = 10;
@@ -1089,6 +1925,257 @@ mod test {
         assert!((yearly_rate - 0.080355).abs() < EPSILON);
     }
 
+    fn liquidity_buffer_pool() -> StakePool {
+        StakePool {
+            total_lamports: 1_000 * LAMPORTS_PER_SOL,
+            pool_token_supply: 1_000 * LAMPORTS_PER_SOL,
+            sol_withdrawal_fee: Fee {
+                numerator: 1,
+                denominator: 1_000,
+            },
+            liquidity_buffer: Some(LiquidityBuffer {
+                target_reserve: Fee {
+                    numerator: 10,
+                    denominator: 100,
+                },
+                max_sol_withdrawal_fee: Fee {
+                    numerator: 101,
+                    denominator: 1_000,
+                },
+                deficit_lamports: 0,
+            }),
+            ..StakePool::default()
+        }
+    }
+
+    #[test]
+    fn stale_update_chunks() {
+        let epoch = 10;
+        let mut validator_list = ValidatorList::new(MAX_VALIDATORS_TO_UPDATE as u32 * 3);
+        validator_list.validators = vec![
+            ValidatorStakeInfo {
+                last_update_epoch: epoch,
+                ..ValidatorStakeInfo::default()
+            };
+            MAX_VALIDATORS_TO_UPDATE * 3
+        ];
+        assert!(validator_list.stale_update_chunks(epoch).is_empty());
+        assert_eq!(
+            validator_list.updated_validators(epoch),
+            MAX_VALIDATORS_TO_UPDATE * 3
+        );
+
+        validator_list.validators[MAX_VALIDATORS_TO_UPDATE + 1].last_update_epoch = epoch - 1;
+        validator_list.validators[MAX_VALIDATORS_TO_UPDATE * 3 - 1].last_update_epoch = 0;
+        assert_eq!(
+            validator_list.updated_validators(epoch),
+            MAX_VALIDATORS_TO_UPDATE * 3 - 2
+        );
+        assert_eq!(validator_list.updated_validators(epoch + 1), 0);
+        assert_eq!(
+            validator_list.stale_update_chunks(epoch),
+            vec![
+                MAX_VALIDATORS_TO_UPDATE as u32,
+                MAX_VALIDATORS_TO_UPDATE as u32 * 2
+            ]
+        );
+        assert_eq!(validator_list.stale_update_chunks(epoch + 1).len(), 3);
+    }
+
+    #[test]
+    fn validator_stake_cap() {
+        let mut validator = ValidatorStakeInfo {
+            active_stake_lamports: 20 * LAMPORTS_PER_SOL,
+            transient_stake_lamports: 5 * LAMPORTS_PER_SOL,
+            ..ValidatorStakeInfo::default()
+        };
+        let total_lamports = 100 * LAMPORTS_PER_SOL;
+        assert_eq!(validator.max_stake_lamports(total_lamports), None);
+        assert_eq!(
+            validator.check_stake_increase(total_lamports, total_lamports),
+            Ok(())
+        );
+
+        validator.max_stake_bps = 3_000;
+        assert_eq!(
+            validator.max_stake_lamports(total_lamports),
+            Some(30 * LAMPORTS_PER_SOL)
+        );
+        assert_eq!(
+            validator.check_stake_increase(5 * LAMPORTS_PER_SOL, total_lamports),
+            Ok(())
+        );
+        assert_eq!(
+            validator.check_stake_increase(5 * LAMPORTS_PER_SOL + 1, total_lamports),
+            Err(StakePoolError::ValidatorStakeCapExceeded.into())
+        );
+
+        validator.max_stake_bps = MAX_STAKE_BPS;
+        assert_eq!(validator.max_stake_lamports(u64::MAX), Some(u64::MAX));
+
+        validator.no_deposits = true;
+        assert_eq!(
+            validator.check_stake_increase(1, total_lamports),
+            Err(StakePoolError::ValidatorDepositsDisabled.into())
+        );
+    }
+
+    #[test]
+    fn liquidity_buffer_fee_without_buffer() {
+        let stake_pool = StakePool {
+            liquidity_buffer: None,
+            ..liquidity_buffer_pool()
+        };
+        let pool_tokens = 10 * LAMPORTS_PER_SOL;
+        assert_eq!(
+            stake_pool
+                .calc_pool_tokens_sol_withdrawal_fee_with_buffer(pool_tokens, 0)
+                .unwrap(),
+            stake_pool
+                .calc_pool_tokens_sol_withdrawal_fee(pool_tokens)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn liquidity_buffer_fee_curve() {
+        let stake_pool = liquidity_buffer_pool();
+        let pool_tokens = 10 * LAMPORTS_PER_SOL;
+        let base_fee = stake_pool
+            .calc_pool_tokens_sol_withdrawal_fee(pool_tokens)
+            .unwrap();
+        let max_fee = pool_tokens * 101 / 1_000;
+
+        // reserve stays above the 100 SOL target
+        let full = stake_pool
+            .calc_pool_tokens_sol_withdrawal_fee_with_buffer(pool_tokens, 200 * LAMPORTS_PER_SOL)
+            .unwrap();
+        assert_eq!(full, base_fee);
+
+        // reserve ends at half of the target, a quarter of the extra fee
+        let half = stake_pool
+            .calc_pool_tokens_sol_withdrawal_fee_with_buffer(pool_tokens, 60 * LAMPORTS_PER_SOL)
+            .unwrap();
+        assert_eq!(half, base_fee + (max_fee - base_fee) / 4);
+
+        // reserve emptied, the full fee
+        let empty = stake_pool
+            .calc_pool_tokens_sol_withdrawal_fee_with_buffer(pool_tokens, 10 * LAMPORTS_PER_SOL)
+            .unwrap();
+        assert_eq!(empty, max_fee);
+        assert!(full < half && half < empty);
+    }
+
+    proptest! {
+        #[test]
+        fn liquidity_buffer_fee_bounds(
+            pool_tokens in 1..1_000 * LAMPORTS_PER_SOL,
+            reserve_lamports in 0..2_000 * LAMPORTS_PER_SOL,
+        ) {
+            let stake_pool = liquidity_buffer_pool();
+            let fee = stake_pool
+                .calc_pool_tokens_sol_withdrawal_fee_with_buffer(pool_tokens, reserve_lamports)
+                .unwrap();
+            let base_fee = stake_pool.calc_pool_tokens_sol_withdrawal_fee(pool_tokens).unwrap();
+            let max_fee = pool_tokens * 101 / 1_000;
+            assert!(fee >= base_fee);
+            assert!(fee <= max_fee);
+        }
+    }
+
+    #[test]
+    fn fee_vesting_schedule() {
+        let mut fee_vesting = FeeVesting {
+            vesting_epochs: 4,
+            ..FeeVesting::default()
+        };
+        fee_vesting.lock(100, 10).unwrap();
+        assert_eq!(fee_vesting.end_epoch, 14);
+        assert_eq!(fee_vesting.locked_amount, 100);
+
+        fee_vesting.vest(11).unwrap();
+        assert_eq!(fee_vesting.locked_amount, 75);
+        assert_eq!(fee_vesting.vested_amount, 25);
+
+        // nothing more vests within the same epoch
+        fee_vesting.vest(11).unwrap();
+        assert_eq!(fee_vesting.vested_amount, 25);
+
+        // a new fee moves the end epoch to the weighted average of the
+        // remaining 50 ending at epoch 14 and the new 40 ending at epoch 16,
+        // rounded down
+        fee_vesting.lock(40, 12).unwrap();
+        assert_eq!(fee_vesting.vested_amount, 50);
+        assert_eq!(fee_vesting.locked_amount, 90);
+        assert_eq!(fee_vesting.end_epoch, 14);
+
+        fee_vesting.vest(14).unwrap();
+        assert_eq!(fee_vesting.locked_amount, 0);
+        assert_eq!(fee_vesting.vested_amount, 140);
+    }
+
+    #[test]
+    fn fee_vesting_with_fees_every_epoch() {
+        let vesting_epochs = 10;
+        let mut fee_vesting = FeeVesting {
+            vesting_epochs,
+            ..FeeVesting::default()
+        };
+        let last_fee_epoch = 100;
+        let mut total_fees = 0;
+        for epoch in 1..=last_fee_epoch {
+            let fee = 1_000 + epoch * 7;
+            fee_vesting.lock(fee, epoch).unwrap();
+            total_fees += fee;
+            assert!(fee_vesting.end_epoch <= epoch + vesting_epochs);
+            // fees keep vesting instead of being pushed back by each new one
+            if epoch > vesting_epochs {
+                assert!(fee_vesting.vested_amount > 0);
+            }
+        }
+        assert!(fee_vesting.vested_amount > total_fees / 2);
+
+        fee_vesting.vest(last_fee_epoch + vesting_epochs).unwrap();
+        assert_eq!(fee_vesting.locked_amount, 0);
+        assert_eq!(fee_vesting.vested_amount, total_fees);
+
+        // a large fee still vests on its own schedule when small fees keep
+        // arriving every epoch after it
+        let large_fee = 1_000_000;
+        let first_epoch = last_fee_epoch + vesting_epochs;
+        fee_vesting.lock(large_fee, first_epoch).unwrap();
+        for epoch in first_epoch + 1..=first_epoch + vesting_epochs {
+            fee_vesting.lock(1, epoch).unwrap();
+        }
+        assert!(fee_vesting.vested_amount >= total_fees + large_fee);
+    }
+
+    proptest! {
+        #[test]
+        fn fee_vesting_conserves_fees(
+            vesting_epochs in 1..100_u64,
+            fees in prop::collection::vec((0..u32::MAX as u64, 0..10_u64), 1..20),
+        ) {
+            let mut fee_vesting = FeeVesting {
+                vesting_epochs,
+                ..FeeVesting::default()
+            };
+            let mut epoch = 0;
+            let mut total_fees = 0;
+            for (fee, elapsed_epochs) in fees {
+                epoch += elapsed_epochs;
+                let vested_amount = fee_vesting.vested_amount;
+                fee_vesting.lock(fee, epoch).unwrap();
+                total_fees += fee;
+                assert!(fee_vesting.vested_amount >= vested_amount);
+                assert_eq!(fee_vesting.locked_amount + fee_vesting.vested_amount, total_fees);
+            }
+            fee_vesting.vest(epoch + vesting_epochs).unwrap();
+            assert_eq!(fee_vesting.locked_amount, 0);
+            assert_eq!(fee_vesting.vested_amount, total_fees);
+        }
+    }
+
     proptest! {
         #[test]
         fn fee_calculation(
@@ -0,0 +1,589 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{
+        borsh::try_from_slice_unchecked, clock::Clock, instruction::InstructionError,
+        pubkey::Pubkey, stake,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+        transport::TransportError,
+    },
+    spl_stake_pool::{
+        error::StakePoolError,
+        id, instruction,
+        state::{self, Fee, FeeType},
+    },
+};
+
+const REDELEGATION_DEPOSIT_FEE: Fee = Fee {
+    numerator: 1,
+    denominator: 100,
+};
+
+struct OutsideStake {
+    vote: Keypair,
+    stake: Keypair,
+    authority: Keypair,
+    pool_account: Keypair,
+    stake_lamports: u64,
+}
+
+async fn create_delegated_stake(
+    context: &mut ProgramTestContext,
+    stake: &Keypair,
+    authority: &Keypair,
+    vote: &Pubkey,
+) -> u64 {
+    let authorized = stake::state::Authorized {
+        staker: authority.pubkey(),
+        withdrawer: authority.pubkey(),
+    };
+    let stake_lamports = create_independent_stake_account(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        stake,
+        &authorized,
+        &stake::state::Lockup::default(),
+        TEST_STAKE_AMOUNT,
+    )
+    .await;
+    delegate_stake_account(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake.pubkey(),
+        authority,
+        vote,
+    )
+    .await;
+    stake_lamports
+}
+
+async fn setup() -> (
+    ProgramTestContext,
+    StakePoolAccounts,
+    ValidatorStakeAccount,
+    OutsideStake,
+) {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts = StakePoolAccounts::new();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .unwrap();
+
+    let validator_stake_account = simple_add_validator_to_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts,
+    )
+    .await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_fee(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.manager.pubkey(),
+            FeeType::RedelegationDeposit(REDELEGATION_DEPOSIT_FEE),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &stake_pool_accounts.manager],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let validator = Keypair::new();
+    let vote = Keypair::new();
+    create_vote(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &validator,
+        &vote,
+    )
+    .await;
+    let stake = Keypair::new();
+    let authority = Keypair::new();
+    let stake_lamports =
+        create_delegated_stake(&mut context, &stake, &authority, &vote.pubkey()).await;
+
+    let pool_account = Keypair::new();
+    create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &pool_account,
+        &stake_pool_accounts.pool_mint.pubkey(),
+        &authority.pubkey(),
+    )
+    .await
+    .unwrap();
+
+    // activate the outside stake
+    let first_normal_slot = context.genesis_config().epoch_schedule.first_normal_slot;
+    let slots_per_epoch = context.genesis_config().epoch_schedule.slots_per_epoch;
+    context
+        .warp_to_slot(first_normal_slot + slots_per_epoch)
+        .unwrap();
+    let error = stake_pool_accounts
+        .update_all(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &[validator_stake_account.vote.pubkey()],
+            false,
+        )
+        .await;
+    assert!(error.is_none());
+
+    (
+        context,
+        stake_pool_accounts,
+        validator_stake_account,
+        OutsideStake {
+            vote,
+            stake,
+            authority,
+            pool_account,
+            stake_lamports,
+        },
+    )
+}
+
+async fn get_stake_pool(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+) -> state::StakePool {
+    let stake_pool = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    try_from_slice_unchecked::<state::StakePool>(stake_pool.data.as_slice()).unwrap()
+}
+
+fn unwrap_instruction_error(error: Option<TransportError>) -> InstructionError {
+    match error.unwrap() {
+        TransportError::TransactionError(TransactionError::InstructionError(_, error)) => error,
+        _ => panic!("Wrong error occurs"),
+    }
+}
+
+#[tokio::test]
+async fn success() {
+    let (mut context, stake_pool_accounts, validator_stake_account, outside_stake) = setup().await;
+    let pre_stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    let pre_fee_balance = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+
+    let pending_redelegation = Keypair::new();
+    let error = stake_pool_accounts
+        .deposit_stake_for_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &outside_stake.stake.pubkey(),
+            &outside_stake.pool_account.pubkey(),
+            &outside_stake.authority,
+            &pending_redelegation,
+        )
+        .await;
+    assert!(error.is_none());
+
+    // The full value is credited, minus the redelegation deposit fee
+    let pool_tokens = pre_stake_pool
+        .calc_pool_tokens_for_deposit(outside_stake.stake_lamports)
+        .unwrap();
+    let fee = pre_stake_pool
+        .calc_pool_tokens_redelegation_deposit_fee(pool_tokens)
+        .unwrap();
+    assert!(fee > 0);
+    assert_eq!(
+        get_token_balance(
+            &mut context.banks_client,
+            &outside_stake.pool_account.pubkey()
+        )
+        .await,
+        pool_tokens - fee
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut context.banks_client,
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+        )
+        .await,
+        pre_fee_balance + fee
+    );
+
+    let pending = get_account(&mut context.banks_client, &pending_redelegation.pubkey()).await;
+    let pending =
+        try_from_slice_unchecked::<state::PendingRedelegation>(pending.data.as_slice()).unwrap();
+    assert!(pending.is_valid());
+    assert_eq!(pending.stake_pool, stake_pool_accounts.stake_pool.pubkey());
+    assert_eq!(pending.stake_account, outside_stake.stake.pubkey());
+    assert_eq!(pending.rent_receiver, context.payer.pubkey());
+    assert_eq!(pending.lamports, outside_stake.stake_lamports);
+
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    assert_eq!(
+        stake_pool.total_lamports,
+        pre_stake_pool.total_lamports + outside_stake.stake_lamports
+    );
+    assert_eq!(
+        stake_pool.pool_token_supply,
+        pre_stake_pool.pool_token_supply + pool_tokens
+    );
+    assert_eq!(
+        stake_pool.pending_redelegation_lamports,
+        outside_stake.stake_lamports
+    );
+
+    // The stake now belongs to the pool
+    let stake = get_account(&mut context.banks_client, &outside_stake.stake.pubkey()).await;
+    let stake_state =
+        try_from_slice_unchecked::<stake::state::StakeState>(stake.data.as_slice()).unwrap();
+    let meta = stake_state.meta().unwrap();
+    assert_eq!(
+        meta.authorized.staker,
+        stake_pool_accounts.withdraw_authority
+    );
+    assert_eq!(
+        meta.authorized.withdrawer,
+        stake_pool_accounts.withdraw_authority
+    );
+
+    // The pending deposit is still counted after an update
+    let first_normal_slot = context.genesis_config().epoch_schedule.first_normal_slot;
+    let slots_per_epoch = context.genesis_config().epoch_schedule.slots_per_epoch;
+    context
+        .warp_to_slot(first_normal_slot + 2 * slots_per_epoch)
+        .unwrap();
+    let error = stake_pool_accounts
+        .update_all(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &[validator_stake_account.vote.pubkey()],
+            false,
+        )
+        .await;
+    assert!(error.is_none());
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    assert!(
+        stake_pool.total_lamports >= pre_stake_pool.total_lamports + outside_stake.stake_lamports
+    );
+}
+
+#[tokio::test]
+async fn fail_with_pool_validator_stake() {
+    let (mut context, stake_pool_accounts, validator_stake_account, outside_stake) = setup().await;
+
+    let stake = Keypair::new();
+    create_delegated_stake(
+        &mut context,
+        &stake,
+        &outside_stake.authority,
+        &validator_stake_account.vote.pubkey(),
+    )
+    .await;
+
+    let error = stake_pool_accounts
+        .deposit_stake_for_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &stake.pubkey(),
+            &outside_stake.pool_account.pubkey(),
+            &outside_stake.authority,
+            &Keypair::new(),
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::ValidatorInPool as u32)
+    );
+}
+
+#[tokio::test]
+async fn fail_with_activating_stake() {
+    let (mut context, stake_pool_accounts, _, outside_stake) = setup().await;
+
+    let stake = Keypair::new();
+    create_delegated_stake(
+        &mut context,
+        &stake,
+        &outside_stake.authority,
+        &outside_stake.vote.pubkey(),
+    )
+    .await;
+
+    let error = stake_pool_accounts
+        .deposit_stake_for_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &stake.pubkey(),
+            &outside_stake.pool_account.pubkey(),
+            &outside_stake.authority,
+            &Keypair::new(),
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::WrongStakeState as u32)
+    );
+}
+
+#[tokio::test]
+async fn fail_with_deactivating_stake() {
+    let (mut context, stake_pool_accounts, _, outside_stake) = setup().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[stake::instruction::deactivate_stake(
+            &outside_stake.stake.pubkey(),
+            &outside_stake.authority.pubkey(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &outside_stake.authority],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let error = stake_pool_accounts
+        .deposit_stake_for_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &outside_stake.stake.pubkey(),
+            &outside_stake.pool_account.pubkey(),
+            &outside_stake.authority,
+            &Keypair::new(),
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::WrongStakeState as u32)
+    );
+}
+
+#[tokio::test]
+async fn success_deactivate_and_close() {
+    let (mut context, stake_pool_accounts, validator_stake_account, outside_stake) = setup().await;
+    let pre_stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+
+    let pending_redelegation = Keypair::new();
+    let error = stake_pool_accounts
+        .deposit_stake_for_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &outside_stake.stake.pubkey(),
+            &outside_stake.pool_account.pubkey(),
+            &outside_stake.authority,
+            &pending_redelegation,
+        )
+        .await;
+    assert!(error.is_none());
+
+    // anyone can deactivate, the payer is not the staker
+    let error = stake_pool_accounts
+        .deactivate_pending_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &pending_redelegation.pubkey(),
+            &outside_stake.stake.pubkey(),
+        )
+        .await;
+    assert!(error.is_none());
+
+    let stake = get_account(&mut context.banks_client, &outside_stake.stake.pubkey()).await;
+    let stake_state =
+        try_from_slice_unchecked::<stake::state::StakeState>(stake.data.as_slice()).unwrap();
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    assert_eq!(
+        stake_state.delegation().unwrap().deactivation_epoch,
+        clock.epoch
+    );
+
+    // the stake is still deactivating
+    let error = stake_pool_accounts
+        .close_pending_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &pending_redelegation.pubkey(),
+            &outside_stake.stake.pubkey(),
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::WrongStakeState as u32)
+    );
+
+    let first_normal_slot = context.genesis_config().epoch_schedule.first_normal_slot;
+    let slots_per_epoch = context.genesis_config().epoch_schedule.slots_per_epoch;
+    context
+        .warp_to_slot(first_normal_slot + 2 * slots_per_epoch)
+        .unwrap();
+    let error = stake_pool_accounts
+        .update_all(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &[validator_stake_account.vote.pubkey()],
+            false,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let pre_reserve_lamports = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+    )
+    .await
+    .lamports;
+    let stake_lamports = get_account(&mut context.banks_client, &outside_stake.stake.pubkey())
+        .await
+        .lamports;
+    assert!(stake_lamports >= outside_stake.stake_lamports);
+
+    let error = stake_pool_accounts
+        .close_pending_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &pending_redelegation.pubkey(),
+            &outside_stake.stake.pubkey(),
+        )
+        .await;
+    assert!(error.is_none());
+
+    // all of the stake moved into the reserve, and both accounts are gone
+    let reserve_lamports = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+    )
+    .await
+    .lamports;
+    assert_eq!(reserve_lamports, pre_reserve_lamports + stake_lamports);
+    assert!(context
+        .banks_client
+        .get_account(outside_stake.stake.pubkey())
+        .await
+        .unwrap()
+        .is_none());
+    assert!(context
+        .banks_client
+        .get_account(pending_redelegation.pubkey())
+        .await
+        .unwrap()
+        .is_none());
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    assert_eq!(stake_pool.pending_redelegation_lamports, 0);
+
+    // the pool keeps the deposit and its rewards once updated
+    let last_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    let error = stake_pool_accounts
+        .update_stake_pool_balance(&mut context.banks_client, &context.payer, &last_blockhash)
+        .await;
+    assert!(error.is_none());
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    assert!(
+        stake_pool.total_lamports >= pre_stake_pool.total_lamports + outside_stake.stake_lamports
+    );
+}
+
+#[tokio::test]
+async fn fail_deactivate_wrong_stake_account() {
+    let (mut context, stake_pool_accounts, validator_stake_account, outside_stake) = setup().await;
+
+    let pending_redelegation = Keypair::new();
+    let error = stake_pool_accounts
+        .deposit_stake_for_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &outside_stake.stake.pubkey(),
+            &outside_stake.pool_account.pubkey(),
+            &outside_stake.authority,
+            &pending_redelegation,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let error = stake_pool_accounts
+        .deactivate_pending_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &pending_redelegation.pubkey(),
+            &validator_stake_account.stake_account,
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::InvalidPendingRedelegation as u32)
+    );
+}
+
+#[tokio::test]
+async fn fail_close_before_deactivation() {
+    let (mut context, stake_pool_accounts, _, outside_stake) = setup().await;
+
+    let pending_redelegation = Keypair::new();
+    let error = stake_pool_accounts
+        .deposit_stake_for_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &outside_stake.stake.pubkey(),
+            &outside_stake.pool_account.pubkey(),
+            &outside_stake.authority,
+            &pending_redelegation,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let error = stake_pool_accounts
+        .close_pending_redelegation(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &pending_redelegation.pubkey(),
+            &outside_stake.stake.pubkey(),
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::WrongStakeState as u32)
+    );
+}
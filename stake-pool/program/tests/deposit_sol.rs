@@ -471,3 +471,83 @@ async fn fail_with_invalid_referrer() {
         ),
     }
 }
+
+#[tokio::test]
+async fn success_with_slippage() {
+    let (mut context, stake_pool_accounts, _user, pool_token_account) = setup().await;
+
+    let tokens_issued =
+        TEST_STAKE_AMOUNT - stake_pool_accounts.calculate_sol_deposit_fee(TEST_STAKE_AMOUNT);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::deposit_sol_with_slippage(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.withdraw_authority,
+            &stake_pool_accounts.reserve_stake.pubkey(),
+            &context.payer.pubkey(),
+            &pool_token_account,
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &spl_token::id(),
+            TEST_STAKE_AMOUNT,
+            tokens_issued,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let user_token_balance =
+        get_token_balance(&mut context.banks_client, &pool_token_account).await;
+    assert_eq!(user_token_balance, tokens_issued);
+}
+
+#[tokio::test]
+async fn fail_with_exceeded_slippage() {
+    let (mut context, stake_pool_accounts, _user, pool_token_account) = setup().await;
+
+    let tokens_issued =
+        TEST_STAKE_AMOUNT - stake_pool_accounts.calculate_sol_deposit_fee(TEST_STAKE_AMOUNT);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::deposit_sol_with_slippage(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.withdraw_authority,
+            &stake_pool_accounts.reserve_stake.pubkey(),
+            &context.payer.pubkey(),
+            &pool_token_account,
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &spl_token::id(),
+            TEST_STAKE_AMOUNT,
+            tokens_issued + 1,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    let error = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(error::StakePoolError::ExceededSlippage as u32)
+        )
+    );
+}
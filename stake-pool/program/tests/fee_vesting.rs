@@ -0,0 +1,459 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{
+        borsh::try_from_slice_unchecked, clock::Clock, instruction::InstructionError,
+        pubkey::Pubkey,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_stake_pool::{error::StakePoolError, id, instruction, state::StakePool},
+};
+
+const VESTING_EPOCHS: u64 = 4;
+
+async fn setup() -> (
+    ProgramTestContext,
+    StakePoolAccounts,
+    Vec<ValidatorStakeAccount>,
+    Keypair,
+) {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts = StakePoolAccounts::new();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .unwrap();
+
+    let mut stake_accounts: Vec<ValidatorStakeAccount> = vec![];
+    for _ in 0..3 {
+        let validator_stake_account = simple_add_validator_to_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &stake_pool_accounts,
+        )
+        .await;
+
+        let _deposit_info = simple_deposit_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &stake_pool_accounts,
+            &validator_stake_account,
+            TEST_STAKE_AMOUNT,
+        )
+        .await
+        .unwrap();
+
+        stake_accounts.push(validator_stake_account);
+    }
+
+    let vesting_account = Keypair::new();
+    create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &vesting_account,
+        &stake_pool_accounts.pool_mint.pubkey(),
+        &stake_pool_accounts.withdraw_authority,
+    )
+    .await
+    .unwrap();
+
+    let error = set_manager_fee_vesting(
+        &mut context,
+        &stake_pool_accounts,
+        &vesting_account.pubkey(),
+        VESTING_EPOCHS,
+    )
+    .await;
+    assert!(error.is_none());
+
+    (
+        context,
+        stake_pool_accounts,
+        stake_accounts,
+        vesting_account,
+    )
+}
+
+async fn set_manager_fee_vesting(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    vesting_account: &Pubkey,
+    vesting_epochs: u64,
+) -> Option<TransactionError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_manager_fee_vesting(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.manager.pubkey(),
+            vesting_account,
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &spl_token::id(),
+            vesting_epochs,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &stake_pool_accounts.manager],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .map(|error| error.unwrap())
+}
+
+async fn get_stake_pool(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+) -> StakePool {
+    let stake_pool = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    try_from_slice_unchecked::<StakePool>(stake_pool.data.as_slice()).unwrap()
+}
+
+async fn warp_to_next_epoch(context: &mut ProgramTestContext) -> u64 {
+    let clock = context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    let slot = context
+        .genesis_config()
+        .epoch_schedule
+        .get_first_slot_in_epoch(clock.epoch + 1);
+    context.warp_to_slot(slot).unwrap();
+    clock.epoch + 1
+}
+
+/// Earn rewards, move to the next epoch and update the pool, returning the
+/// new epoch
+async fn earn_rewards_and_update(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    stake_accounts: &[ValidatorStakeAccount],
+) -> u64 {
+    for stake_account in stake_accounts {
+        context.increment_vote_account_credits(&stake_account.vote.pubkey(), 1_000);
+    }
+    let epoch = warp_to_next_epoch(context).await;
+
+    let stake_pool = get_stake_pool(context, stake_pool_accounts).await;
+    let validator_list = stake_pool_accounts
+        .get_validator_list(&mut context.banks_client)
+        .await;
+    let (update_list_instructions, final_instructions) = instruction::update_stake_pool(
+        &id(),
+        &stake_pool,
+        &validator_list,
+        &stake_pool_accounts.stake_pool.pubkey(),
+        false,
+    );
+    let transaction = Transaction::new_signed_with_payer(
+        &[update_list_instructions, final_instructions].concat(),
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+    epoch
+}
+
+#[tokio::test]
+async fn success_vesting_and_claim() {
+    let (mut context, stake_pool_accounts, stake_accounts, vesting_account) = setup().await;
+
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    let fee_vesting = stake_pool.fee_vesting.unwrap();
+    assert_eq!(fee_vesting.vesting_account, vesting_account.pubkey());
+    assert_eq!(fee_vesting.vesting_epochs, VESTING_EPOCHS);
+
+    let pre_fee = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+
+    let epoch = earn_rewards_and_update(&mut context, &stake_pool_accounts, &stake_accounts).await;
+
+    // the whole fee is locked in the vesting account
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    let fee_vesting = stake_pool.fee_vesting.unwrap();
+    let fee = get_token_balance(&mut context.banks_client, &vesting_account.pubkey()).await;
+    assert!(fee > 0);
+    assert_eq!(fee_vesting.locked_amount, fee);
+    assert_eq!(fee_vesting.vested_amount, 0);
+    assert_eq!(fee_vesting.end_epoch, epoch + VESTING_EPOCHS);
+    assert_eq!(
+        get_token_balance(
+            &mut context.banks_client,
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+        )
+        .await,
+        pre_fee
+    );
+
+    // a quarter vests after one epoch
+    warp_to_next_epoch(&mut context).await;
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::claim_manager_fee(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &vesting_account.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &spl_token::id(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let claimed = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await
+        - pre_fee;
+    assert_eq!(claimed, fee / VESTING_EPOCHS);
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    let fee_vesting = stake_pool.fee_vesting.unwrap();
+    assert_eq!(fee_vesting.vested_amount, 0);
+    assert_eq!(fee_vesting.locked_amount, fee - claimed);
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &vesting_account.pubkey()).await,
+        fee - claimed
+    );
+}
+
+#[tokio::test]
+async fn success_set_manager_forfeits_locked_fees() {
+    let (mut context, stake_pool_accounts, stake_accounts, vesting_account) = setup().await;
+    earn_rewards_and_update(&mut context, &stake_pool_accounts, &stake_accounts).await;
+
+    let pre_supply = get_token_supply(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_mint.pubkey(),
+    )
+    .await;
+    let locked = get_token_balance(&mut context.banks_client, &vesting_account.pubkey()).await;
+    assert!(locked > 0);
+
+    let new_pool_fee = Keypair::new();
+    let new_manager = Keypair::new();
+    create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &new_pool_fee,
+        &stake_pool_accounts.pool_mint.pubkey(),
+        &new_manager.pubkey(),
+    )
+    .await
+    .unwrap();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_manager_with_fee_vesting(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.manager.pubkey(),
+            &new_manager.pubkey(),
+            &new_pool_fee.pubkey(),
+            &vesting_account.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &spl_token::id(),
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &stake_pool_accounts.manager, &new_manager],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let post_supply = get_token_supply(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_mint.pubkey(),
+    )
+    .await;
+    assert_eq!(pre_supply - post_supply, locked);
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &vesting_account.pubkey()).await,
+        0
+    );
+
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    assert_eq!(stake_pool.manager, new_manager.pubkey());
+    assert_eq!(stake_pool.pool_token_supply, post_supply);
+    let fee_vesting = stake_pool.fee_vesting.unwrap();
+    assert_eq!(fee_vesting.locked_amount, 0);
+    assert_eq!(fee_vesting.vested_amount, 0);
+}
+
+#[tokio::test]
+async fn success_disable_forfeits_locked_fees() {
+    let (mut context, stake_pool_accounts, stake_accounts, vesting_account) = setup().await;
+    earn_rewards_and_update(&mut context, &stake_pool_accounts, &stake_accounts).await;
+
+    let pre_supply = get_token_supply(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_mint.pubkey(),
+    )
+    .await;
+    let locked = get_token_balance(&mut context.banks_client, &vesting_account.pubkey()).await;
+
+    let error = set_manager_fee_vesting(
+        &mut context,
+        &stake_pool_accounts,
+        &vesting_account.pubkey(),
+        0,
+    )
+    .await;
+    assert!(error.is_none());
+
+    let post_supply = get_token_supply(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_mint.pubkey(),
+    )
+    .await;
+    assert_eq!(pre_supply - post_supply, locked);
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    assert!(stake_pool.fee_vesting.is_none());
+
+    // fees go to the manager fee account again
+    let pre_fee = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+    earn_rewards_and_update(&mut context, &stake_pool_accounts, &stake_accounts).await;
+    let post_fee = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+    assert!(post_fee > pre_fee);
+}
+
+#[tokio::test]
+async fn fail_shorten_while_locked() {
+    let (mut context, stake_pool_accounts, stake_accounts, vesting_account) = setup().await;
+    earn_rewards_and_update(&mut context, &stake_pool_accounts, &stake_accounts).await;
+
+    let error = set_manager_fee_vesting(
+        &mut context,
+        &stake_pool_accounts,
+        &vesting_account.pubkey(),
+        VESTING_EPOCHS - 1,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(StakePoolError::FeeVestingLocked as u32)
+        )
+    );
+
+    // extending is fine
+    let error = set_manager_fee_vesting(
+        &mut context,
+        &stake_pool_accounts,
+        &vesting_account.pubkey(),
+        VESTING_EPOCHS + 1,
+    )
+    .await;
+    assert!(error.is_none());
+}
+
+#[tokio::test]
+async fn fail_update_with_manager_fee_account() {
+    let (mut context, stake_pool_accounts, _, _) = setup().await;
+
+    let error = stake_pool_accounts
+        .update_stake_pool_balance(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(StakePoolError::InvalidFeeAccount as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn fail_with_vesting_account_not_owned_by_pool() {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts = StakePoolAccounts::new();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .unwrap();
+
+    let vesting_account = Keypair::new();
+    create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &vesting_account,
+        &stake_pool_accounts.pool_mint.pubkey(),
+        &stake_pool_accounts.manager.pubkey(),
+    )
+    .await
+    .unwrap();
+
+    let error = set_manager_fee_vesting(
+        &mut context,
+        &stake_pool_accounts,
+        &vesting_account.pubkey(),
+        VESTING_EPOCHS,
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(StakePoolError::InvalidFeeVestingAccount as u32)
+        )
+    );
+}
@@ -4,7 +4,6 @@ use {
     solana_program::{
         borsh::{get_instance_packed_len, get_packed_len, try_from_slice_unchecked},
         hash::Hash,
-        program_pack::Pack,
         pubkey::Pubkey,
         stake, system_instruction, system_program,
     },
@@ -26,6 +25,10 @@ use {
         state::{self, FeeType, ValidatorList},
         MINIMUM_ACTIVE_STAKE,
     },
+    spl_token_2022::{
+        extension::{ExtensionType, StateWithExtensions},
+        state::AccountState,
+    },
 };
 
 pub const TEST_STAKE_AMOUNT: u64 = 1_500_000_000;
@@ -33,11 +36,17 @@ pub const MAX_TEST_VALIDATORS: u32 = 10_000;
 pub const DEFAULT_TRANSIENT_STAKE_SEED: u64 = 42;
 
 pub fn program_test() -> ProgramTest {
-    ProgramTest::new(
+    let mut program_test = ProgramTest::new(
         "spl_stake_pool",
         id(),
         processor!(processor::Processor::process),
-    )
+    );
+    program_test.add_program(
+        "spl_token_2022",
+        spl_token_2022::id(),
+        processor!(spl_token_2022::processor::Processor::process),
+    );
+    program_test
 }
 
 pub async fn get_account(banks_client: &mut BanksClient, pubkey: &Pubkey) -> Account {
@@ -48,6 +57,13 @@ pub async fn get_account(banks_client: &mut BanksClient, pubkey: &Pubkey) -> Acc
         .expect("account empty")
 }
 
+/// Token-2022 extension to initialize on a test pool mint
+pub enum MintExtension {
+    CloseAuthority(Option<Pubkey>),
+    DefaultAccountState(AccountState),
+    TransferFeeConfig(u16),
+}
+
 pub async fn create_mint(
     banks_client: &mut BanksClient,
     payer: &Keypair,
@@ -55,29 +71,89 @@ pub async fn create_mint(
     pool_mint: &Keypair,
     manager: &Pubkey,
 ) -> Result<(), TransportError> {
+    create_mint_with_extensions(
+        banks_client,
+        payer,
+        recent_blockhash,
+        &spl_token::id(),
+        pool_mint,
+        manager,
+        &[],
+    )
+    .await
+}
+
+pub async fn create_mint_with_extensions(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    token_program_id: &Pubkey,
+    pool_mint: &Keypair,
+    manager: &Pubkey,
+    extensions: &[MintExtension],
+) -> Result<(), TransportError> {
+    let extension_types = extensions
+        .iter()
+        .map(|extension| match extension {
+            MintExtension::CloseAuthority(_) => ExtensionType::MintCloseAuthority,
+            MintExtension::DefaultAccountState(_) => ExtensionType::DefaultAccountState,
+            MintExtension::TransferFeeConfig(_) => ExtensionType::TransferFeeConfig,
+        })
+        .collect::<Vec<_>>();
+    let space = ExtensionType::get_account_len::<spl_token_2022::state::Mint>(&extension_types);
     let rent = banks_client.get_rent().await.unwrap();
-    let mint_rent = rent.minimum_balance(spl_token::state::Mint::LEN);
+    let mint_rent = rent.minimum_balance(space);
 
-    let mut transaction = Transaction::new_with_payer(
-        &[
-            system_instruction::create_account(
-                &payer.pubkey(),
-                &pool_mint.pubkey(),
-                mint_rent,
-                spl_token::state::Mint::LEN as u64,
-                &spl_token::id(),
-            ),
-            spl_token::instruction::initialize_mint(
-                &spl_token::id(),
-                &pool_mint.pubkey(),
-                manager,
-                None,
-                0,
-            )
-            .unwrap(),
-        ],
-        Some(&payer.pubkey()),
+    let mut instructions = vec![system_instruction::create_account(
+        &payer.pubkey(),
+        &pool_mint.pubkey(),
+        mint_rent,
+        space as u64,
+        token_program_id,
+    )];
+    for extension in extensions {
+        instructions.push(match extension {
+            MintExtension::CloseAuthority(close_authority) => {
+                spl_token_2022::instruction::initialize_mint_close_authority(
+                    token_program_id,
+                    &pool_mint.pubkey(),
+                    close_authority.as_ref(),
+                )
+                .unwrap()
+            }
+            MintExtension::DefaultAccountState(state) => {
+                spl_token_2022::extension::default_account_state::instruction::initialize_default_account_state(
+                    token_program_id,
+                    &pool_mint.pubkey(),
+                    state,
+                )
+                .unwrap()
+            }
+            MintExtension::TransferFeeConfig(transfer_fee_basis_points) => {
+                spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config(
+                    token_program_id,
+                    &pool_mint.pubkey(),
+                    None,
+                    None,
+                    *transfer_fee_basis_points,
+                    u64::MAX,
+                )
+                .unwrap()
+            }
+        });
+    }
+    instructions.push(
+        spl_token_2022::instruction::initialize_mint(
+            token_program_id,
+            &pool_mint.pubkey(),
+            manager,
+            None,
+            0,
+        )
+        .unwrap(),
     );
+
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
     transaction.sign(&[payer, pool_mint], *recent_blockhash);
     #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
     banks_client
@@ -140,28 +216,72 @@ pub async fn create_token_account(
     pool_mint: &Pubkey,
     manager: &Pubkey,
 ) -> Result<(), TransportError> {
+    create_token_account_with_program(
+        banks_client,
+        payer,
+        recent_blockhash,
+        &spl_token::id(),
+        account,
+        pool_mint,
+        manager,
+    )
+    .await
+}
+
+/// Token-2022 accounts get the immutable owner extension, like associated
+/// token accounts, along with any extension required by the mint
+pub async fn create_token_account_with_program(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    recent_blockhash: &Hash,
+    token_program_id: &Pubkey,
+    account: &Keypair,
+    pool_mint: &Pubkey,
+    manager: &Pubkey,
+) -> Result<(), TransportError> {
+    let mut extension_types = vec![];
+    if *token_program_id == spl_token_2022::id() {
+        let mint_account = get_account(banks_client, pool_mint).await;
+        let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(
+            mint_account.data.as_slice(),
+        )
+        .unwrap();
+        extension_types = ExtensionType::get_required_init_account_extensions(
+            &mint.get_extension_types().unwrap(),
+        );
+        extension_types.push(ExtensionType::ImmutableOwner);
+    }
+    let space = ExtensionType::get_account_len::<spl_token_2022::state::Account>(&extension_types);
     let rent = banks_client.get_rent().await.unwrap();
-    let account_rent = rent.minimum_balance(spl_token::state::Account::LEN);
+    let account_rent = rent.minimum_balance(space);
 
-    let mut transaction = Transaction::new_with_payer(
-        &[
-            system_instruction::create_account(
-                &payer.pubkey(),
-                &account.pubkey(),
-                account_rent,
-                spl_token::state::Account::LEN as u64,
-                &spl_token::id(),
-            ),
-            spl_token::instruction::initialize_account(
-                &spl_token::id(),
+    let mut instructions = vec![system_instruction::create_account(
+        &payer.pubkey(),
+        &account.pubkey(),
+        account_rent,
+        space as u64,
+        token_program_id,
+    )];
+    if *token_program_id == spl_token_2022::id() {
+        instructions.push(
+            spl_token_2022::instruction::initialize_immutable_owner(
+                token_program_id,
                 &account.pubkey(),
-                pool_mint,
-                manager,
             )
             .unwrap(),
-        ],
-        Some(&payer.pubkey()),
+        );
+    }
+    instructions.push(
+        spl_token_2022::instruction::initialize_account(
+            token_program_id,
+            &account.pubkey(),
+            pool_mint,
+            manager,
+        )
+        .unwrap(),
     );
+
+    let mut transaction = Transaction::new_with_payer(&instructions, Some(&payer.pubkey()));
     transaction.sign(&[payer, account], *recent_blockhash);
     #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
     banks_client
@@ -286,16 +406,19 @@ pub async fn burn_tokens(
 
 pub async fn get_token_balance(banks_client: &mut BanksClient, token: &Pubkey) -> u64 {
     let token_account = banks_client.get_account(*token).await.unwrap().unwrap();
-    let account_info: spl_token::state::Account =
-        spl_token::state::Account::unpack_from_slice(token_account.data.as_slice()).unwrap();
-    account_info.amount
+    let account_info = StateWithExtensions::<spl_token_2022::state::Account>::unpack(
+        token_account.data.as_slice(),
+    )
+    .unwrap();
+    account_info.base.amount
 }
 
 pub async fn get_token_supply(banks_client: &mut BanksClient, mint: &Pubkey) -> u64 {
     let mint_account = banks_client.get_account(*mint).await.unwrap().unwrap();
     let account_info =
-        spl_token::state::Mint::unpack_from_slice(mint_account.data.as_slice()).unwrap();
-    account_info.supply
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(mint_account.data.as_slice())
+            .unwrap();
+    account_info.base.supply
 }
 
 pub async fn delegate_tokens(
@@ -334,6 +457,7 @@ pub async fn create_stake_pool(
     reserve_stake: &Pubkey,
     pool_mint: &Pubkey,
     pool_token_account: &Pubkey,
+    token_program_id: &Pubkey,
     manager: &Keypair,
     staker: &Pubkey,
     withdraw_authority: &Pubkey,
@@ -378,7 +502,7 @@ pub async fn create_stake_pool(
                 reserve_stake,
                 pool_mint,
                 pool_token_account,
-                &spl_token::id(),
+                token_program_id,
                 stake_deposit_authority.as_ref().map(|k| k.pubkey()),
                 *epoch_fee,
                 *withdrawal_fee,
@@ -641,6 +765,8 @@ pub struct StakePoolAccounts {
     pub sol_deposit_fee: state::Fee,
     pub sol_referral_fee: u8,
     pub max_validators: u32,
+    pub token_program_id: Pubkey,
+    pub mint_extensions: Vec<MintExtension>,
 }
 
 impl StakePoolAccounts {
@@ -688,6 +814,8 @@ impl StakePoolAccounts {
             },
             sol_referral_fee: 50,
             max_validators: MAX_TEST_VALIDATORS,
+            token_program_id: spl_token::id(),
+            mint_extensions: vec![],
         }
     }
 
@@ -698,6 +826,16 @@ impl StakePoolAccounts {
         stake_pool_accounts
     }
 
+    pub fn new_with_token_program(
+        token_program_id: Pubkey,
+        mint_extensions: Vec<MintExtension>,
+    ) -> Self {
+        let mut stake_pool_accounts = Self::new();
+        stake_pool_accounts.token_program_id = token_program_id;
+        stake_pool_accounts.mint_extensions = mint_extensions;
+        stake_pool_accounts
+    }
+
     pub fn calculate_fee(&self, amount: u64) -> u64 {
         amount * self.epoch_fee.numerator / self.epoch_fee.denominator
     }
@@ -725,18 +863,21 @@ impl StakePoolAccounts {
         recent_blockhash: &Hash,
         reserve_lamports: u64,
     ) -> Result<(), TransportError> {
-        create_mint(
+        create_mint_with_extensions(
             banks_client,
             payer,
             recent_blockhash,
+            &self.token_program_id,
             &self.pool_mint,
             &self.withdraw_authority,
+            &self.mint_extensions,
         )
         .await?;
-        create_token_account(
+        create_token_account_with_program(
             banks_client,
             payer,
             recent_blockhash,
+            &self.token_program_id,
             &self.pool_fee_account,
             &self.pool_mint.pubkey(),
             &self.manager.pubkey(),
@@ -764,6 +905,7 @@ impl StakePoolAccounts {
             &self.reserve_stake.pubkey(),
             &self.pool_mint.pubkey(),
             &self.pool_fee_account.pubkey(),
+            &self.token_program_id,
             &self.manager,
             &self.staker.pubkey(),
             &self.withdraw_authority,
@@ -835,7 +977,7 @@ impl StakePoolAccounts {
                     &self.pool_fee_account.pubkey(),
                     referrer,
                     &self.pool_mint.pubkey(),
-                    &spl_token::id(),
+                    &self.token_program_id,
                 )
             } else {
                 instruction::deposit_stake(
@@ -851,7 +993,7 @@ impl StakePoolAccounts {
                     &self.pool_fee_account.pubkey(),
                     referrer,
                     &self.pool_mint.pubkey(),
-                    &spl_token::id(),
+                    &self.token_program_id,
                 )
             };
         let transaction = Transaction::new_signed_with_payer(
@@ -892,7 +1034,7 @@ impl StakePoolAccounts {
                 &self.pool_fee_account.pubkey(),
                 &self.pool_fee_account.pubkey(),
                 &self.pool_mint.pubkey(),
-                &spl_token::id(),
+                &self.token_program_id,
                 amount,
             )
         } else {
@@ -906,7 +1048,7 @@ impl StakePoolAccounts {
                 &self.pool_fee_account.pubkey(),
                 &self.pool_fee_account.pubkey(),
                 &self.pool_mint.pubkey(),
-                &spl_token::id(),
+                &self.token_program_id,
                 amount,
             )
         };
@@ -950,7 +1092,7 @@ impl StakePoolAccounts {
                 pool_account,
                 &self.pool_fee_account.pubkey(),
                 &self.pool_mint.pubkey(),
-                &spl_token::id(),
+                &self.token_program_id,
                 amount,
             )],
             Some(&payer.pubkey()),
@@ -990,7 +1132,7 @@ impl StakePoolAccounts {
                 &user.pubkey(),
                 &self.pool_fee_account.pubkey(),
                 &self.pool_mint.pubkey(),
-                &spl_token::id(),
+                &self.token_program_id,
                 amount,
             )
         } else {
@@ -1004,7 +1146,7 @@ impl StakePoolAccounts {
                 &user.pubkey(),
                 &self.pool_fee_account.pubkey(),
                 &self.pool_mint.pubkey(),
-                &spl_token::id(),
+                &self.token_program_id,
                 amount,
             )
         };
@@ -1022,6 +1164,195 @@ impl StakePoolAccounts {
             .err()
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub async fn withdraw_to_ticket(
+        &self,
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        recent_blockhash: &Hash,
+        user: &Keypair,
+        pool_account: &Pubkey,
+        withdraw_ticket: &Keypair,
+        amount: u64,
+    ) -> Option<TransportError> {
+        let rent = banks_client.get_rent().await.unwrap();
+        let transaction = Transaction::new_signed_with_payer(
+            &[
+                system_instruction::create_account(
+                    &payer.pubkey(),
+                    &withdraw_ticket.pubkey(),
+                    rent.minimum_balance(state::WithdrawTicket::LEN),
+                    state::WithdrawTicket::LEN as u64,
+                    &id(),
+                ),
+                instruction::withdraw_to_ticket(
+                    &id(),
+                    &self.stake_pool.pubkey(),
+                    &user.pubkey(),
+                    pool_account,
+                    &withdraw_ticket.pubkey(),
+                    &user.pubkey(),
+                    &self.pool_fee_account.pubkey(),
+                    &self.pool_mint.pubkey(),
+                    &self.token_program_id,
+                    amount,
+                    0,
+                ),
+            ],
+            Some(&payer.pubkey()),
+            &[payer, user, withdraw_ticket],
+            *recent_blockhash,
+        );
+        #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.into())
+            .err()
+    }
+
+    pub async fn claim_ticket(
+        &self,
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        recent_blockhash: &Hash,
+        owner: &Keypair,
+        withdraw_ticket: &Pubkey,
+    ) -> Option<TransportError> {
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction::claim_ticket(
+                &id(),
+                &self.stake_pool.pubkey(),
+                withdraw_ticket,
+                &owner.pubkey(),
+                &self.reserve_stake.pubkey(),
+                &owner.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+            &[payer, owner],
+            *recent_blockhash,
+        );
+        #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.into())
+            .err()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn deposit_stake_for_redelegation(
+        &self,
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        recent_blockhash: &Hash,
+        stake: &Pubkey,
+        pool_account: &Pubkey,
+        current_staker: &Keypair,
+        pending_redelegation: &Keypair,
+    ) -> Option<TransportError> {
+        let rent = banks_client.get_rent().await.unwrap();
+        let mut signers = vec![payer, current_staker, pending_redelegation];
+        let stake_deposit_authority =
+            if let Some(stake_deposit_authority) = self.stake_deposit_authority_keypair.as_ref() {
+                signers.push(stake_deposit_authority);
+                Some(&self.stake_deposit_authority)
+            } else {
+                None
+            };
+        let mut instructions = vec![system_instruction::create_account(
+            &payer.pubkey(),
+            &pending_redelegation.pubkey(),
+            rent.minimum_balance(state::PendingRedelegation::LEN),
+            state::PendingRedelegation::LEN as u64,
+            &id(),
+        )];
+        instructions.extend(instruction::deposit_stake_for_redelegation(
+            &id(),
+            &self.stake_pool.pubkey(),
+            &self.validator_list.pubkey(),
+            stake_deposit_authority,
+            &self.withdraw_authority,
+            stake,
+            &current_staker.pubkey(),
+            &pending_redelegation.pubkey(),
+            &payer.pubkey(),
+            pool_account,
+            &self.pool_fee_account.pubkey(),
+            &self.pool_mint.pubkey(),
+            &self.token_program_id,
+            0,
+        ));
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&payer.pubkey()),
+            &signers,
+            *recent_blockhash,
+        );
+        #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.into())
+            .err()
+    }
+
+    pub async fn deactivate_pending_stake(
+        &self,
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        recent_blockhash: &Hash,
+        pending_redelegation: &Pubkey,
+        pending_stake: &Pubkey,
+    ) -> Option<TransportError> {
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction::deactivate_pending_stake(
+                &id(),
+                &self.stake_pool.pubkey(),
+                pending_redelegation,
+                pending_stake,
+            )],
+            Some(&payer.pubkey()),
+            &[payer],
+            *recent_blockhash,
+        );
+        #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.into())
+            .err()
+    }
+
+    pub async fn close_pending_redelegation(
+        &self,
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        recent_blockhash: &Hash,
+        pending_redelegation: &Pubkey,
+        pending_stake: &Pubkey,
+    ) -> Option<TransportError> {
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction::close_pending_redelegation(
+                &id(),
+                &self.stake_pool.pubkey(),
+                &self.reserve_stake.pubkey(),
+                pending_redelegation,
+                pending_stake,
+                &payer.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+            &[payer],
+            *recent_blockhash,
+        );
+        #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.into())
+            .err()
+    }
+
     pub async fn get_validator_list(&self, banks_client: &mut BanksClient) -> ValidatorList {
         let validator_list_account = get_account(banks_client, &self.validator_list.pubkey()).await;
         try_from_slice_unchecked::<ValidatorList>(validator_list_account.data.as_slice()).unwrap()
@@ -1075,7 +1406,7 @@ impl StakePoolAccounts {
                 &self.reserve_stake.pubkey(),
                 &self.pool_fee_account.pubkey(),
                 &self.pool_mint.pubkey(),
-                &spl_token::id(),
+                &self.token_program_id,
             )],
             Some(&payer.pubkey()),
             &[payer],
@@ -1143,7 +1474,7 @@ impl StakePoolAccounts {
                     &self.reserve_stake.pubkey(),
                     &self.pool_fee_account.pubkey(),
                     &self.pool_mint.pubkey(),
-                    &spl_token::id(),
+                    &self.token_program_id,
                 ),
                 instruction::cleanup_removed_validator_entries(
                     &id(),
@@ -1309,6 +1640,48 @@ impl StakePoolAccounts {
             .err()
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub async fn redelegate(
+        &self,
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        recent_blockhash: &Hash,
+        source_validator_stake: &Pubkey,
+        source_transient_stake: &Pubkey,
+        destination_transient_stake: &Pubkey,
+        destination_validator: &Pubkey,
+        lamports: u64,
+        source_transient_stake_seed: u64,
+        destination_transient_stake_seed: u64,
+    ) -> Option<TransportError> {
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction::redelegate(
+                &id(),
+                &self.stake_pool.pubkey(),
+                &self.staker.pubkey(),
+                &self.withdraw_authority,
+                &self.validator_list.pubkey(),
+                &self.reserve_stake.pubkey(),
+                source_validator_stake,
+                source_transient_stake,
+                destination_transient_stake,
+                destination_validator,
+                lamports,
+                source_transient_stake_seed,
+                destination_transient_stake_seed,
+            )],
+            Some(&payer.pubkey()),
+            &[payer, &self.staker],
+            *recent_blockhash,
+        );
+        #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.into())
+            .err()
+    }
+
     pub async fn set_preferred_validator(
         &self,
         banks_client: &mut BanksClient,
@@ -1337,6 +1710,37 @@ impl StakePoolAccounts {
             .map_err(|e| e.into())
             .err()
     }
+
+    pub async fn set_validator_config(
+        &self,
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        recent_blockhash: &Hash,
+        vote_account_address: &Pubkey,
+        max_stake_bps: u16,
+        no_deposits: bool,
+    ) -> Option<TransportError> {
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction::set_validator_config(
+                &id(),
+                &self.stake_pool.pubkey(),
+                &self.manager.pubkey(),
+                &self.validator_list.pubkey(),
+                vote_account_address,
+                max_stake_bps,
+                no_deposits,
+            )],
+            Some(&payer.pubkey()),
+            &[payer, &self.manager],
+            *recent_blockhash,
+        );
+        #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .map_err(|e| e.into())
+            .err()
+    }
 }
 
 pub async fn simple_add_validator_to_pool(
@@ -1444,10 +1848,11 @@ impl DepositStakeAccount {
         stake_pool_accounts: &StakePoolAccounts,
     ) {
         // make pool token account
-        create_token_account(
+        create_token_account_with_program(
             banks_client,
             payer,
             recent_blockhash,
+            &stake_pool_accounts.token_program_id,
             &self.pool_account,
             &stake_pool_accounts.pool_mint.pubkey(),
             &self.authority.pubkey(),
@@ -1509,10 +1914,11 @@ pub async fn simple_deposit_stake(
     .await;
     // make pool token account
     let pool_account = Keypair::new();
-    create_token_account(
+    create_token_account_with_program(
         banks_client,
         payer,
         recent_blockhash,
+        &stake_pool_accounts.token_program_id,
         &pool_account,
         &stake_pool_accounts.pool_mint.pubkey(),
         &authority.pubkey(),
@@ -88,6 +88,11 @@ async fn setup(
         next_sol_withdrawal_fee: None,
         last_epoch_pool_token_supply: 0,
         last_epoch_total_lamports: 0,
+        liquidity_buffer: None,
+        fee_vesting: None,
+        withdraw_ticket_lamports: 0,
+        redelegation_deposit_fee: Fee::default(),
+        pending_redelegation_lamports: 0,
     };
 
     let mut validator_list = ValidatorList::new(max_validators);
@@ -166,7 +171,9 @@ async fn setup(
             transient_stake_lamports: 0,
             last_update_epoch: 0,
             transient_seed_suffix_start: 0,
-            transient_seed_suffix_end: 0,
+            max_stake_bps: 0,
+            no_deposits: false,
+            reserved: [0; 5],
         });
 
         stake_pool.total_lamports += active_stake_lamports;
@@ -398,3 +398,109 @@ async fn fail_overdraw_reserve() {
         _ => panic!("Wrong error occurs while overdrawing reserve stake"),
     }
 }
+
+#[tokio::test]
+async fn fail_above_validator_stake_cap() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        validator_stake,
+        reserve_lamports,
+    ) = setup().await;
+
+    let error = stake_pool_accounts
+        .set_validator_config(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &validator_stake.vote.pubkey(),
+            1_000,
+            false,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let error = stake_pool_accounts
+        .increase_validator_stake(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &validator_stake.transient_stake_account,
+            &validator_stake.vote.pubkey(),
+            reserve_lamports / 2,
+            validator_stake.transient_stake_seed,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = StakePoolError::ValidatorStakeCapExceeded as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while increasing above the validator stake cap"),
+    }
+
+    // an increase within the cap goes through
+    let error = stake_pool_accounts
+        .increase_validator_stake(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &validator_stake.transient_stake_account,
+            &validator_stake.vote.pubkey(),
+            reserve_lamports / 20,
+            validator_stake.transient_stake_seed,
+        )
+        .await;
+    assert!(error.is_none());
+}
+
+#[tokio::test]
+async fn fail_with_validator_deposits_disabled() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        validator_stake,
+        reserve_lamports,
+    ) = setup().await;
+
+    let error = stake_pool_accounts
+        .set_validator_config(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &validator_stake.vote.pubkey(),
+            0,
+            true,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let error = stake_pool_accounts
+        .increase_validator_stake(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &validator_stake.transient_stake_account,
+            &validator_stake.vote.pubkey(),
+            reserve_lamports / 2,
+            validator_stake.transient_stake_seed,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = StakePoolError::ValidatorDepositsDisabled as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while increasing a validator refusing deposits"),
+    }
+}
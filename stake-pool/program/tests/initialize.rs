@@ -325,6 +325,7 @@ async fn fail_with_wrong_mint_authority() {
         &stake_pool_accounts.reserve_stake.pubkey(),
         &wrong_mint.pubkey(),
         &stake_pool_accounts.pool_fee_account.pubkey(),
+        &spl_token::id(),
         &stake_pool_accounts.manager,
         &stake_pool_accounts.staker.pubkey(),
         &stake_pool_accounts.withdraw_authority,
@@ -416,6 +417,7 @@ async fn fail_with_freeze_authority() {
         &stake_pool_accounts.reserve_stake.pubkey(),
         &wrong_mint.pubkey(),
         &pool_fee_account.pubkey(),
+        &spl_token::id(),
         &stake_pool_accounts.manager,
         &stake_pool_accounts.staker.pubkey(),
         &stake_pool_accounts.withdraw_authority,
@@ -689,6 +691,7 @@ async fn fail_with_wrong_fee_account() {
         &stake_pool_accounts.reserve_stake.pubkey(),
         &stake_pool_accounts.pool_mint.pubkey(),
         &stake_pool_accounts.pool_fee_account.pubkey(),
+        &spl_token::id(),
         &stake_pool_accounts.manager,
         &stake_pool_accounts.staker.pubkey(),
         &stake_pool_accounts.withdraw_authority,
@@ -1042,6 +1045,7 @@ async fn fail_with_pre_minted_pool_tokens() {
         &stake_pool_accounts.reserve_stake.pubkey(),
         &stake_pool_accounts.pool_mint.pubkey(),
         &stake_pool_accounts.pool_fee_account.pubkey(),
+        &spl_token::id(),
         &stake_pool_accounts.manager,
         &stake_pool_accounts.staker.pubkey(),
         &stake_pool_accounts.withdraw_authority,
@@ -1109,6 +1113,7 @@ async fn fail_with_bad_reserve() {
             &bad_stake.pubkey(),
             &stake_pool_accounts.pool_mint.pubkey(),
             &stake_pool_accounts.pool_fee_account.pubkey(),
+            &spl_token::id(),
             &stake_pool_accounts.manager,
             &stake_pool_accounts.staker.pubkey(),
             &stake_pool_accounts.withdraw_authority,
@@ -1160,6 +1165,7 @@ async fn fail_with_bad_reserve() {
             &bad_stake.pubkey(),
             &stake_pool_accounts.pool_mint.pubkey(),
             &stake_pool_accounts.pool_fee_account.pubkey(),
+            &spl_token::id(),
             &stake_pool_accounts.manager,
             &stake_pool_accounts.staker.pubkey(),
             &stake_pool_accounts.withdraw_authority,
@@ -1214,6 +1220,7 @@ async fn fail_with_bad_reserve() {
             &bad_stake.pubkey(),
             &stake_pool_accounts.pool_mint.pubkey(),
             &stake_pool_accounts.pool_fee_account.pubkey(),
+            &spl_token::id(),
             &stake_pool_accounts.manager,
             &stake_pool_accounts.staker.pubkey(),
             &stake_pool_accounts.withdraw_authority,
@@ -1268,6 +1275,7 @@ async fn fail_with_bad_reserve() {
             &bad_stake.pubkey(),
             &stake_pool_accounts.pool_mint.pubkey(),
             &stake_pool_accounts.pool_fee_account.pubkey(),
+            &spl_token::id(),
             &stake_pool_accounts.manager,
             &stake_pool_accounts.staker.pubkey(),
             &stake_pool_accounts.withdraw_authority,
@@ -0,0 +1,226 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use {
+    borsh::BorshSerialize,
+    helpers::*,
+    solana_program::{
+        borsh::{get_packed_len, try_from_slice_unchecked},
+        native_token::LAMPORTS_PER_SOL,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_stake_pool::{
+        error, id, instruction,
+        state::{Fee, StakePool},
+    },
+};
+
+async fn setup() -> (ProgramTestContext, StakePoolAccounts, StakePool) {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts = StakePoolAccounts::new();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .unwrap();
+
+    // Truncating the account gives the data of a legacy pool, and the bytes
+    // past its fields are filled like ones left over by unset optional fields
+    let stake_pool_account = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_account.data).unwrap();
+    let legacy_len = StakePool::legacy_len();
+    let mut legacy_data = stake_pool_account.data[..legacy_len].to_vec();
+    let legacy_fields_len = stake_pool.try_to_vec().unwrap().len() - 34;
+    for byte in legacy_data[legacy_fields_len..].iter_mut() {
+        *byte = 2;
+    }
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let legacy_account = Account {
+        lamports: rent.minimum_balance(legacy_len),
+        data: legacy_data,
+        ..stake_pool_account
+    };
+    context.set_account(
+        &stake_pool_accounts.stake_pool.pubkey(),
+        &legacy_account.into(),
+    );
+
+    (context, stake_pool_accounts, stake_pool)
+}
+
+async fn realloc_stake_pool(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    payer: &Keypair,
+) -> Option<TransactionError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::realloc_stake_pool(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+        &[payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .map(|error| error.unwrap())
+}
+
+async fn set_liquidity_buffer(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+) -> Option<TransactionError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_liquidity_buffer(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.manager.pubkey(),
+            Fee {
+                numerator: 1,
+                denominator: 2,
+            },
+            Fee {
+                numerator: 5,
+                denominator: 100,
+            },
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &stake_pool_accounts.manager],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .map(|error| error.unwrap())
+}
+
+#[tokio::test]
+async fn success() {
+    let (mut context, stake_pool_accounts, stake_pool) = setup().await;
+
+    let legacy_account = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    assert_eq!(legacy_account.data.len(), StakePool::legacy_len());
+    assert_eq!(
+        try_from_slice_unchecked::<StakePool>(&legacy_account.data).unwrap(),
+        stake_pool
+    );
+
+    // anyone can grow the pool, not only the manager
+    let payer = Keypair::new();
+    transfer(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &payer.pubkey(),
+        LAMPORTS_PER_SOL,
+    )
+    .await;
+    let error = realloc_stake_pool(&mut context, &stake_pool_accounts, &payer).await;
+    assert!(error.is_none());
+
+    // the bytes left in the legacy account are not read as the new fields
+    let stake_pool_account = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    let rent = context.banks_client.get_rent().await.unwrap();
+    assert_eq!(stake_pool_account.data.len(), get_packed_len::<StakePool>());
+    assert_eq!(
+        stake_pool_account.lamports,
+        rent.minimum_balance(get_packed_len::<StakePool>())
+    );
+    assert_eq!(
+        try_from_slice_unchecked::<StakePool>(&stake_pool_account.data).unwrap(),
+        stake_pool
+    );
+
+    // instructions storing the new fields now succeed
+    let error = set_liquidity_buffer(&mut context, &stake_pool_accounts).await;
+    assert!(error.is_none());
+
+    // a second realloc does nothing
+    let last_blockhash = context
+        .banks_client
+        .get_new_latest_blockhash(&context.last_blockhash)
+        .await
+        .unwrap();
+    context.last_blockhash = last_blockhash;
+    let error = realloc_stake_pool(&mut context, &stake_pool_accounts, &payer).await;
+    assert!(error.is_none());
+    let stake_pool_account = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    assert_eq!(stake_pool_account.data.len(), get_packed_len::<StakePool>());
+}
+
+#[tokio::test]
+async fn success_update_legacy_pool() {
+    let (mut context, stake_pool_accounts, stake_pool) = setup().await;
+
+    // instructions leaving the new fields at their defaults don't need a realloc
+    let error = stake_pool_accounts
+        .update_stake_pool_balance(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let stake_pool_account = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    assert_eq!(stake_pool_account.data.len(), StakePool::legacy_len());
+    let updated_stake_pool =
+        try_from_slice_unchecked::<StakePool>(&stake_pool_account.data).unwrap();
+    assert_eq!(updated_stake_pool.total_lamports, stake_pool.total_lamports);
+    assert_eq!(updated_stake_pool.liquidity_buffer, None);
+}
+
+#[tokio::test]
+async fn fail_store_new_fields_before_realloc() {
+    let (mut context, stake_pool_accounts, _stake_pool) = setup().await;
+
+    let error = set_liquidity_buffer(&mut context, &stake_pool_accounts)
+        .await
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = error::StakePoolError::StakePoolNotReallocated as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while storing new fields in a legacy pool"),
+    }
+}
@@ -0,0 +1,576 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use {
+    bincode::deserialize,
+    helpers::*,
+    solana_program::{
+        clock::Epoch, hash::Hash, instruction::InstructionError, pubkey::Pubkey, stake,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_stake_pool::{
+        error::StakePoolError, find_transient_stake_program_address, id, instruction,
+    },
+};
+
+async fn setup(
+    reserve_lamports: u64,
+) -> (
+    BanksClient,
+    Keypair,
+    Hash,
+    StakePoolAccounts,
+    ValidatorStakeAccount,
+    ValidatorStakeAccount,
+    u64,
+) {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+    let stake_pool_accounts = StakePoolAccounts::new();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            reserve_lamports,
+        )
+        .await
+        .unwrap();
+
+    let source_validator_stake = simple_add_validator_to_pool(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &stake_pool_accounts,
+    )
+    .await;
+    let destination_validator_stake = simple_add_validator_to_pool(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &stake_pool_accounts,
+    )
+    .await;
+
+    let deposit_info = simple_deposit_stake(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &stake_pool_accounts,
+        &source_validator_stake,
+        100_000_000,
+    )
+    .await
+    .unwrap();
+
+    let lamports = deposit_info.stake_lamports / 2;
+
+    (
+        banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        destination_validator_stake,
+        lamports,
+    )
+}
+
+#[tokio::test]
+async fn success() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        destination_validator_stake,
+        redelegate_lamports,
+    ) = setup(100_000_000_000).await;
+
+    let pre_source_stake_account =
+        get_account(&mut banks_client, &source_validator_stake.stake_account).await;
+    let pre_reserve_stake_account = get_account(
+        &mut banks_client,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+    )
+    .await;
+    let pre_validator_list = stake_pool_accounts
+        .get_validator_list(&mut banks_client)
+        .await;
+    let pre_source_stake_info = pre_validator_list
+        .find(&source_validator_stake.vote.pubkey())
+        .unwrap();
+    let pre_destination_stake_info = pre_validator_list
+        .find(&destination_validator_stake.vote.pubkey())
+        .unwrap();
+
+    let error = stake_pool_accounts
+        .redelegate(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &source_validator_stake.stake_account,
+            &source_validator_stake.transient_stake_account,
+            &destination_validator_stake.transient_stake_account,
+            &destination_validator_stake.vote.pubkey(),
+            redelegate_lamports,
+            source_validator_stake.transient_stake_seed,
+            destination_validator_stake.transient_stake_seed,
+        )
+        .await;
+    assert!(error.is_none());
+
+    // Check source validator stake and its deactivating transient stake
+    let source_stake_account =
+        get_account(&mut banks_client, &source_validator_stake.stake_account).await;
+    assert_eq!(
+        pre_source_stake_account.lamports - redelegate_lamports,
+        source_stake_account.lamports
+    );
+    let source_transient_stake_account = get_account(
+        &mut banks_client,
+        &source_validator_stake.transient_stake_account,
+    )
+    .await;
+    let source_transient_stake_state =
+        deserialize::<stake::state::StakeState>(&source_transient_stake_account.data).unwrap();
+    assert_eq!(source_transient_stake_account.lamports, redelegate_lamports);
+    let source_delegation = source_transient_stake_state.delegation().unwrap();
+    assert_eq!(
+        source_delegation.voter_pubkey,
+        source_validator_stake.vote.pubkey()
+    );
+    assert_ne!(source_delegation.deactivation_epoch, Epoch::MAX);
+
+    // Check the reserve fronted the activating destination transient stake
+    let reserve_stake_account = get_account(
+        &mut banks_client,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+    )
+    .await;
+    assert_eq!(
+        pre_reserve_stake_account.lamports - redelegate_lamports,
+        reserve_stake_account.lamports
+    );
+    let destination_transient_stake_account = get_account(
+        &mut banks_client,
+        &destination_validator_stake.transient_stake_account,
+    )
+    .await;
+    let destination_transient_stake_state =
+        deserialize::<stake::state::StakeState>(&destination_transient_stake_account.data).unwrap();
+    assert_eq!(
+        destination_transient_stake_account.lamports,
+        redelegate_lamports
+    );
+    let destination_delegation = destination_transient_stake_state.delegation().unwrap();
+    assert_eq!(
+        destination_delegation.voter_pubkey,
+        destination_validator_stake.vote.pubkey()
+    );
+    assert_eq!(destination_delegation.deactivation_epoch, Epoch::MAX);
+
+    // Check validator list entries
+    let validator_list = stake_pool_accounts
+        .get_validator_list(&mut banks_client)
+        .await;
+    let source_stake_info = validator_list
+        .find(&source_validator_stake.vote.pubkey())
+        .unwrap();
+    assert_eq!(
+        source_stake_info.active_stake_lamports,
+        pre_source_stake_info.active_stake_lamports - redelegate_lamports
+    );
+    assert_eq!(
+        source_stake_info.transient_stake_lamports,
+        redelegate_lamports
+    );
+    assert_eq!(
+        source_stake_info.transient_seed_suffix_start,
+        source_validator_stake.transient_stake_seed
+    );
+    let destination_stake_info = validator_list
+        .find(&destination_validator_stake.vote.pubkey())
+        .unwrap();
+    assert_eq!(
+        destination_stake_info.active_stake_lamports,
+        pre_destination_stake_info.active_stake_lamports
+    );
+    assert_eq!(
+        destination_stake_info.transient_stake_lamports,
+        redelegate_lamports
+    );
+    assert_eq!(
+        destination_stake_info.transient_seed_suffix_start,
+        destination_validator_stake.transient_stake_seed
+    );
+}
+
+#[tokio::test]
+async fn fail_with_wrong_withdraw_authority() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        destination_validator_stake,
+        redelegate_lamports,
+    ) = setup(100_000_000_000).await;
+
+    let wrong_authority = Pubkey::new_unique();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::redelegate(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.staker.pubkey(),
+            &wrong_authority,
+            &stake_pool_accounts.validator_list.pubkey(),
+            &stake_pool_accounts.reserve_stake.pubkey(),
+            &source_validator_stake.stake_account,
+            &source_validator_stake.transient_stake_account,
+            &destination_validator_stake.transient_stake_account,
+            &destination_validator_stake.vote.pubkey(),
+            redelegate_lamports,
+            source_validator_stake.transient_stake_seed,
+            destination_validator_stake.transient_stake_seed,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &stake_pool_accounts.staker],
+        recent_blockhash,
+    );
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = StakePoolError::InvalidProgramAddress as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while redelegating with wrong withdraw authority"),
+    }
+}
+
+#[tokio::test]
+async fn fail_with_wrong_staker() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        destination_validator_stake,
+        redelegate_lamports,
+    ) = setup(100_000_000_000).await;
+
+    let wrong_staker = Keypair::new();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::redelegate(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &wrong_staker.pubkey(),
+            &stake_pool_accounts.withdraw_authority,
+            &stake_pool_accounts.validator_list.pubkey(),
+            &stake_pool_accounts.reserve_stake.pubkey(),
+            &source_validator_stake.stake_account,
+            &source_validator_stake.transient_stake_account,
+            &destination_validator_stake.transient_stake_account,
+            &destination_validator_stake.vote.pubkey(),
+            redelegate_lamports,
+            source_validator_stake.transient_stake_seed,
+            destination_validator_stake.transient_stake_seed,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &wrong_staker],
+        recent_blockhash,
+    );
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = StakePoolError::WrongStaker as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while redelegating with wrong staker"),
+    }
+}
+
+#[tokio::test]
+async fn fail_to_same_validator() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        _destination_validator_stake,
+        redelegate_lamports,
+    ) = setup(100_000_000_000).await;
+
+    let destination_transient_stake_seed = source_validator_stake.transient_stake_seed + 1;
+    let destination_transient_stake_address = find_transient_stake_program_address(
+        &id(),
+        &source_validator_stake.vote.pubkey(),
+        &stake_pool_accounts.stake_pool.pubkey(),
+        destination_transient_stake_seed,
+    )
+    .0;
+    let error = stake_pool_accounts
+        .redelegate(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &source_validator_stake.stake_account,
+            &source_validator_stake.transient_stake_account,
+            &destination_transient_stake_address,
+            &source_validator_stake.vote.pubkey(),
+            redelegate_lamports,
+            source_validator_stake.transient_stake_seed,
+            destination_transient_stake_seed,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = StakePoolError::RedelegateToSameValidator as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while redelegating to the same validator"),
+    }
+}
+
+#[tokio::test]
+async fn fail_with_unknown_destination_validator() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        _destination_validator_stake,
+        redelegate_lamports,
+    ) = setup(100_000_000_000).await;
+
+    let unknown_stake = ValidatorStakeAccount::new(&stake_pool_accounts.stake_pool.pubkey(), 222);
+
+    let error = stake_pool_accounts
+        .redelegate(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &source_validator_stake.stake_account,
+            &source_validator_stake.transient_stake_account,
+            &unknown_stake.transient_stake_account,
+            &unknown_stake.vote.pubkey(),
+            redelegate_lamports,
+            source_validator_stake.transient_stake_seed,
+            unknown_stake.transient_stake_seed,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = StakePoolError::ValidatorNotFound as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while redelegating to unknown validator"),
+    }
+}
+
+#[tokio::test]
+async fn fail_with_destination_transient_in_use() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        destination_validator_stake,
+        redelegate_lamports,
+    ) = setup(100_000_000_000).await;
+
+    simple_deposit_stake(
+        &mut banks_client,
+        &payer,
+        &recent_blockhash,
+        &stake_pool_accounts,
+        &destination_validator_stake,
+        100_000_000,
+    )
+    .await
+    .unwrap();
+
+    let error = stake_pool_accounts
+        .decrease_validator_stake(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &destination_validator_stake.stake_account,
+            &destination_validator_stake.transient_stake_account,
+            redelegate_lamports,
+            destination_validator_stake.transient_stake_seed,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let destination_transient_stake_seed = destination_validator_stake.transient_stake_seed * 100;
+    let destination_transient_stake_address = find_transient_stake_program_address(
+        &id(),
+        &destination_validator_stake.vote.pubkey(),
+        &stake_pool_accounts.stake_pool.pubkey(),
+        destination_transient_stake_seed,
+    )
+    .0;
+    let error = stake_pool_accounts
+        .redelegate(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &source_validator_stake.stake_account,
+            &source_validator_stake.transient_stake_account,
+            &destination_transient_stake_address,
+            &destination_validator_stake.vote.pubkey(),
+            redelegate_lamports,
+            source_validator_stake.transient_stake_seed,
+            destination_transient_stake_seed,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = StakePoolError::TransientAccountInUse as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while redelegating into a transient account in use"),
+    }
+}
+
+#[tokio::test]
+async fn fail_with_small_lamport_amount() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        destination_validator_stake,
+        _redelegate_lamports,
+    ) = setup(100_000_000_000).await;
+
+    let rent = banks_client.get_rent().await.unwrap();
+    let lamports = rent.minimum_balance(std::mem::size_of::<stake::state::StakeState>());
+
+    let error = stake_pool_accounts
+        .redelegate(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &source_validator_stake.stake_account,
+            &source_validator_stake.transient_stake_account,
+            &destination_validator_stake.transient_stake_account,
+            &destination_validator_stake.vote.pubkey(),
+            lamports,
+            source_validator_stake.transient_stake_seed,
+            destination_validator_stake.transient_stake_seed,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::AccountNotRentExempt) => {}
+        _ => panic!("Wrong error occurs while redelegating with small lamport amount"),
+    }
+}
+
+#[tokio::test]
+async fn fail_overdraw() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        destination_validator_stake,
+        redelegate_lamports,
+    ) = setup(100_000_000_000).await;
+
+    let error = stake_pool_accounts
+        .redelegate(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &source_validator_stake.stake_account,
+            &source_validator_stake.transient_stake_account,
+            &destination_validator_stake.transient_stake_account,
+            &destination_validator_stake.vote.pubkey(),
+            redelegate_lamports * 1_000,
+            source_validator_stake.transient_stake_seed,
+            destination_validator_stake.transient_stake_seed,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::InsufficientFunds) => {}
+        _ => panic!("Wrong error occurs while overdrawing the source validator stake"),
+    }
+}
+
+#[tokio::test]
+async fn fail_with_insufficient_reserve() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        source_validator_stake,
+        destination_validator_stake,
+        redelegate_lamports,
+    ) = setup(1).await;
+
+    let error = stake_pool_accounts
+        .redelegate(
+            &mut banks_client,
+            &payer,
+            &recent_blockhash,
+            &source_validator_stake.stake_account,
+            &source_validator_stake.transient_stake_account,
+            &destination_validator_stake.transient_stake_account,
+            &destination_validator_stake.vote.pubkey(),
+            redelegate_lamports,
+            source_validator_stake.transient_stake_seed,
+            destination_validator_stake.transient_stake_seed,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::InsufficientFunds) => {}
+        _ => panic!("Wrong error occurs while redelegating without enough lamports in the reserve"),
+    }
+}
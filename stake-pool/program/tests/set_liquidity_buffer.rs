@@ -0,0 +1,312 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::stake,
+    solana_program_test::*,
+    solana_sdk::{
+        borsh::try_from_slice_unchecked,
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_stake_pool::{
+        error, id, instruction,
+        state::{Fee, LiquidityBuffer, StakePool},
+    },
+};
+
+async fn setup() -> (ProgramTestContext, StakePoolAccounts, Fee, Fee) {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts = StakePoolAccounts::new();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .unwrap();
+    let target_reserve = Fee {
+        numerator: 1,
+        denominator: 2,
+    };
+    let max_sol_withdrawal_fee = Fee {
+        numerator: 5,
+        denominator: 100,
+    };
+
+    (
+        context,
+        stake_pool_accounts,
+        target_reserve,
+        max_sol_withdrawal_fee,
+    )
+}
+
+async fn set_liquidity_buffer(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    manager: &Keypair,
+    target_reserve: Fee,
+    max_sol_withdrawal_fee: Fee,
+) -> Option<TransactionError> {
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_liquidity_buffer(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &manager.pubkey(),
+            target_reserve,
+            max_sol_withdrawal_fee,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, manager],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .map(|error| error.unwrap())
+}
+
+#[tokio::test]
+async fn success() {
+    let (mut context, stake_pool_accounts, target_reserve, max_sol_withdrawal_fee) = setup().await;
+
+    let error = set_liquidity_buffer(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.manager,
+        target_reserve,
+        max_sol_withdrawal_fee,
+    )
+    .await;
+    assert!(error.is_none());
+
+    let stake_pool = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    let stake_pool = try_from_slice_unchecked::<StakePool>(stake_pool.data.as_slice()).unwrap();
+    assert_eq!(
+        stake_pool.liquidity_buffer,
+        Some(LiquidityBuffer {
+            target_reserve,
+            max_sol_withdrawal_fee,
+            deficit_lamports: 0,
+        })
+    );
+}
+
+#[tokio::test]
+async fn success_disable() {
+    let (mut context, stake_pool_accounts, target_reserve, max_sol_withdrawal_fee) = setup().await;
+
+    let error = set_liquidity_buffer(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.manager,
+        target_reserve,
+        max_sol_withdrawal_fee,
+    )
+    .await;
+    assert!(error.is_none());
+
+    let error = set_liquidity_buffer(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.manager,
+        Fee {
+            numerator: 0,
+            denominator: 1,
+        },
+        max_sol_withdrawal_fee,
+    )
+    .await;
+    assert!(error.is_none());
+
+    let stake_pool = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    let stake_pool = try_from_slice_unchecked::<StakePool>(stake_pool.data.as_slice()).unwrap();
+    assert_eq!(stake_pool.liquidity_buffer, None);
+}
+
+#[tokio::test]
+async fn fail_wrong_manager() {
+    let (mut context, stake_pool_accounts, target_reserve, max_sol_withdrawal_fee) = setup().await;
+
+    let wrong_manager = Keypair::new();
+    let error = set_liquidity_buffer(
+        &mut context,
+        &stake_pool_accounts,
+        &wrong_manager,
+        target_reserve,
+        max_sol_withdrawal_fee,
+    )
+    .await
+    .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = error::StakePoolError::WrongManager as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while signing with the wrong manager"),
+    }
+}
+
+#[tokio::test]
+async fn fail_high_target() {
+    let (mut context, stake_pool_accounts, _target_reserve, max_sol_withdrawal_fee) = setup().await;
+
+    let error = set_liquidity_buffer(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.manager,
+        Fee {
+            numerator: 11,
+            denominator: 10,
+        },
+        max_sol_withdrawal_fee,
+    )
+    .await
+    .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = error::StakePoolError::FeeTooHigh as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs when setting a target above 100%"),
+    }
+}
+
+#[tokio::test]
+async fn fail_high_max_fee() {
+    let (mut context, stake_pool_accounts, target_reserve, _max_sol_withdrawal_fee) = setup().await;
+
+    let error = set_liquidity_buffer(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.manager,
+        target_reserve,
+        Fee {
+            numerator: 101,
+            denominator: 100,
+        },
+    )
+    .await
+    .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = error::StakePoolError::FeeTooHigh as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs when setting a maximum fee above 100%"),
+    }
+}
+
+#[tokio::test]
+async fn success_update_flags_deficit() {
+    let (mut context, stake_pool_accounts, target_reserve, max_sol_withdrawal_fee) = setup().await;
+
+    let pool_token_account = Keypair::new();
+    create_token_account(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &pool_token_account,
+        &stake_pool_accounts.pool_mint.pubkey(),
+        &context.payer.pubkey(),
+    )
+    .await
+    .unwrap();
+    let error = stake_pool_accounts
+        .deposit_sol(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &pool_token_account.pubkey(),
+            TEST_STAKE_AMOUNT,
+            None,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let error = set_liquidity_buffer(
+        &mut context,
+        &stake_pool_accounts,
+        &stake_pool_accounts.manager,
+        target_reserve,
+        max_sol_withdrawal_fee,
+    )
+    .await;
+    assert!(error.is_none());
+
+    // move most of the reserve to a validator
+    let validator_stake = simple_add_validator_to_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts,
+    )
+    .await;
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let stake_rent = rent.minimum_balance(std::mem::size_of::<stake::state::StakeState>());
+    let increase_lamports = TEST_STAKE_AMOUNT - stake_rent;
+    let error = stake_pool_accounts
+        .increase_validator_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake.transient_stake_account,
+            &validator_stake.vote.pubkey(),
+            increase_lamports,
+            validator_stake.transient_stake_seed,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let error = stake_pool_accounts
+        .update_stake_pool_balance(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let stake_pool = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    let stake_pool = try_from_slice_unchecked::<StakePool>(stake_pool.data.as_slice()).unwrap();
+    let reserve_lamports = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+    )
+    .await
+    .lamports
+        - stake_rent
+        - 1;
+    let liquidity_buffer = stake_pool.liquidity_buffer.unwrap();
+    let target_lamports = liquidity_buffer
+        .target_lamports(stake_pool.total_lamports)
+        .unwrap();
+    assert!(liquidity_buffer.deficit_lamports > 0);
+    assert_eq!(
+        liquidity_buffer.deficit_lamports,
+        target_lamports - reserve_lamports
+    );
+}
@@ -0,0 +1,184 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{instruction::InstructionError, pubkey::Pubkey},
+    solana_program_test::*,
+    solana_sdk::{
+        borsh::try_from_slice_unchecked,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+    },
+    spl_stake_pool::{
+        error, id, instruction,
+        state::{StakeStatus, ValidatorList},
+        MAX_STAKE_BPS,
+    },
+};
+
+async fn setup() -> (ProgramTestContext, StakePoolAccounts, ValidatorStakeAccount) {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts = StakePoolAccounts::new();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .unwrap();
+
+    let validator_stake = simple_add_validator_to_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts,
+    )
+    .await;
+
+    (context, stake_pool_accounts, validator_stake)
+}
+
+#[tokio::test]
+async fn success() {
+    let (mut context, stake_pool_accounts, validator_stake) = setup().await;
+
+    let error = stake_pool_accounts
+        .set_validator_config(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake.vote.pubkey(),
+            2_500,
+            true,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let validator_list = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.validator_list.pubkey(),
+    )
+    .await;
+    let validator_list =
+        try_from_slice_unchecked::<ValidatorList>(validator_list.data.as_slice()).unwrap();
+    let validator_stake_info = validator_list.find(&validator_stake.vote.pubkey()).unwrap();
+    assert_eq!(validator_stake_info.max_stake_bps, 2_500);
+    assert!(validator_stake_info.no_deposits);
+    assert_eq!(validator_stake_info.status, StakeStatus::Active);
+
+    // clear the config
+    let error = stake_pool_accounts
+        .set_validator_config(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake.vote.pubkey(),
+            0,
+            false,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let validator_list = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.validator_list.pubkey(),
+    )
+    .await;
+    let validator_list =
+        try_from_slice_unchecked::<ValidatorList>(validator_list.data.as_slice()).unwrap();
+    let validator_stake_info = validator_list.find(&validator_stake.vote.pubkey()).unwrap();
+    assert_eq!(validator_stake_info.max_stake_bps, 0);
+    assert!(!validator_stake_info.no_deposits);
+}
+
+#[tokio::test]
+async fn fail_wrong_manager() {
+    let (mut context, stake_pool_accounts, validator_stake) = setup().await;
+
+    let wrong_manager = Keypair::new();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_validator_config(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &wrong_manager.pubkey(),
+            &stake_pool_accounts.validator_list.pubkey(),
+            &validator_stake.vote.pubkey(),
+            2_500,
+            false,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &wrong_manager],
+        context.last_blockhash,
+    );
+    let error = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = error::StakePoolError::WrongManager as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while signing with the wrong manager"),
+    }
+}
+
+#[tokio::test]
+async fn fail_cap_above_pool() {
+    let (mut context, stake_pool_accounts, validator_stake) = setup().await;
+
+    let error = stake_pool_accounts
+        .set_validator_config(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake.vote.pubkey(),
+            MAX_STAKE_BPS + 1,
+            false,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = error::StakePoolError::InvalidValidatorStakeCap as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while setting a cap above the whole pool"),
+    }
+}
+
+#[tokio::test]
+async fn fail_unknown_validator() {
+    let (mut context, stake_pool_accounts, _validator_stake) = setup().await;
+
+    let error = stake_pool_accounts
+        .set_validator_config(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &Pubkey::new_unique(),
+            2_500,
+            false,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(error_index)) => {
+            let program_error = error::StakePoolError::ValidatorNotFound as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while configuring an unknown validator"),
+    }
+}
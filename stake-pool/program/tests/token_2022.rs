@@ -0,0 +1,311 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{
+        borsh::try_from_slice_unchecked, instruction::InstructionError, pubkey::Pubkey,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        signature::{Keypair, Signer},
+        transaction::TransactionError,
+        transport::TransportError,
+    },
+    spl_stake_pool::{error::StakePoolError, state},
+    spl_token_2022::state::AccountState,
+};
+
+async fn setup(
+    mint_extensions: Vec<MintExtension>,
+) -> (ProgramTestContext, StakePoolAccounts, Keypair, Pubkey, u64) {
+    let mut context = program_test().start_with_context().await;
+
+    let stake_pool_accounts =
+        StakePoolAccounts::new_with_token_program(spl_token_2022::id(), mint_extensions);
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .unwrap();
+
+    let user = Keypair::new();
+    let pool_token_account = Keypair::new();
+    create_token_account_with_program(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &spl_token_2022::id(),
+        &pool_token_account,
+        &stake_pool_accounts.pool_mint.pubkey(),
+        &user.pubkey(),
+    )
+    .await
+    .unwrap();
+
+    let error = stake_pool_accounts
+        .deposit_sol(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &pool_token_account.pubkey(),
+            TEST_STAKE_AMOUNT,
+            None,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let tokens_issued =
+        get_token_balance(&mut context.banks_client, &pool_token_account.pubkey()).await;
+
+    (
+        context,
+        stake_pool_accounts,
+        user,
+        pool_token_account.pubkey(),
+        tokens_issued,
+    )
+}
+
+async fn fail_initialize(mint_extensions: Vec<MintExtension>) -> InstructionError {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts =
+        StakePoolAccounts::new_with_token_program(spl_token_2022::id(), mint_extensions);
+    let transaction_error = stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .err()
+        .unwrap();
+    match transaction_error {
+        TransportError::TransactionError(TransactionError::InstructionError(_, error)) => error,
+        _ => panic!("Wrong error occurs while initializing with an unsupported mint"),
+    }
+}
+
+#[tokio::test]
+async fn success_initialize() {
+    let (mut context, stake_pool_accounts, _, _, pool_tokens) = setup(vec![
+        MintExtension::CloseAuthority(None),
+        MintExtension::DefaultAccountState(AccountState::Initialized),
+    ])
+    .await;
+
+    let stake_pool = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    let stake_pool =
+        try_from_slice_unchecked::<state::StakePool>(stake_pool.data.as_slice()).unwrap();
+    assert_eq!(stake_pool.token_program_id, spl_token_2022::id());
+    assert_eq!(
+        stake_pool.pool_token_supply,
+        get_token_supply(
+            &mut context.banks_client,
+            &stake_pool_accounts.pool_mint.pubkey()
+        )
+        .await
+    );
+    assert!(pool_tokens > 0);
+}
+
+#[tokio::test]
+async fn success_withdraw_sol() {
+    let (mut context, stake_pool_accounts, user, pool_token_account, pool_tokens) = setup(vec![
+        MintExtension::CloseAuthority(None),
+        MintExtension::DefaultAccountState(AccountState::Initialized),
+    ])
+    .await;
+
+    let pre_fee_balance = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+    let pre_supply = get_token_supply(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_mint.pubkey(),
+    )
+    .await;
+
+    let error = stake_pool_accounts
+        .withdraw_sol(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &user,
+            &pool_token_account,
+            pool_tokens,
+            None,
+        )
+        .await;
+    assert!(error.is_none());
+
+    // fee is transferred to the extension-sized manager fee account, the
+    // rest is burned from the user's extension-sized account
+    let withdrawal_fee = stake_pool_accounts.calculate_withdrawal_fee(pool_tokens);
+    assert_eq!(
+        get_token_balance(&mut context.banks_client, &pool_token_account).await,
+        0
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut context.banks_client,
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+        )
+        .await,
+        pre_fee_balance + withdrawal_fee
+    );
+    assert_eq!(
+        get_token_supply(
+            &mut context.banks_client,
+            &stake_pool_accounts.pool_mint.pubkey(),
+        )
+        .await,
+        pre_supply - (pool_tokens - withdrawal_fee)
+    );
+}
+
+#[tokio::test]
+async fn success_withdraw_stake() {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts = StakePoolAccounts::new_with_token_program(
+        spl_token_2022::id(),
+        vec![MintExtension::DefaultAccountState(
+            AccountState::Initialized,
+        )],
+    );
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .unwrap();
+
+    let validator_stake_account = simple_add_validator_to_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts,
+    )
+    .await;
+
+    let deposit_info = simple_deposit_stake(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts,
+        &validator_stake_account,
+        TEST_STAKE_AMOUNT,
+    )
+    .await
+    .unwrap();
+
+    let user_stake_recipient = Keypair::new();
+    create_blank_stake_account(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &user_stake_recipient,
+    )
+    .await;
+
+    let pre_fee_balance = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+    let pre_supply = get_token_supply(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_mint.pubkey(),
+    )
+    .await;
+
+    let tokens_to_withdraw = deposit_info.pool_tokens / 4;
+    let error = stake_pool_accounts
+        .withdraw_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &user_stake_recipient.pubkey(),
+            &deposit_info.authority,
+            &deposit_info.pool_account.pubkey(),
+            &validator_stake_account.stake_account,
+            &Pubkey::new_unique(),
+            tokens_to_withdraw,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let withdrawal_fee = stake_pool_accounts.calculate_withdrawal_fee(tokens_to_withdraw);
+    assert_eq!(
+        get_token_balance(
+            &mut context.banks_client,
+            &deposit_info.pool_account.pubkey()
+        )
+        .await,
+        deposit_info.pool_tokens - tokens_to_withdraw
+    );
+    assert_eq!(
+        get_token_balance(
+            &mut context.banks_client,
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+        )
+        .await,
+        pre_fee_balance + withdrawal_fee
+    );
+    assert_eq!(
+        get_token_supply(
+            &mut context.banks_client,
+            &stake_pool_accounts.pool_mint.pubkey(),
+        )
+        .await,
+        pre_supply - (tokens_to_withdraw - withdrawal_fee)
+    );
+}
+
+#[tokio::test]
+async fn fail_with_mint_close_authority() {
+    let error = fail_initialize(vec![MintExtension::CloseAuthority(Some(
+        Pubkey::new_unique(),
+    ))])
+    .await;
+    assert_eq!(
+        error,
+        InstructionError::Custom(StakePoolError::UnsupportedMintExtension as u32)
+    );
+}
+
+#[tokio::test]
+async fn fail_with_frozen_default_account_state() {
+    let error = fail_initialize(vec![MintExtension::DefaultAccountState(
+        AccountState::Frozen,
+    )])
+    .await;
+    assert_eq!(
+        error,
+        InstructionError::Custom(StakePoolError::UnsupportedMintExtension as u32)
+    );
+}
+
+#[tokio::test]
+async fn fail_with_transfer_fee() {
+    let error = fail_initialize(vec![MintExtension::TransferFeeConfig(100)]).await;
+    assert_eq!(
+        error,
+        InstructionError::Custom(StakePoolError::UnsupportedMintExtension as u32)
+    );
+}
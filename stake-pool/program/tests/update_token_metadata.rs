@@ -0,0 +1,192 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{borsh::try_from_slice_unchecked, hash::Hash},
+    solana_program_test::*,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+        transport::TransportError,
+    },
+    spl_stake_pool::{
+        error::StakePoolError, find_token_metadata_program_address, id, instruction,
+        state::TokenMetadata,
+    },
+};
+
+async fn setup() -> (BanksClient, Keypair, Hash, StakePoolAccounts) {
+    let (mut banks_client, payer, recent_blockhash) = program_test().start().await;
+    let stake_pool_accounts = StakePoolAccounts::new();
+    stake_pool_accounts
+        .initialize_stake_pool(&mut banks_client, &payer, &recent_blockhash, 1)
+        .await
+        .unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::create_token_metadata(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.manager.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &payer.pubkey(),
+            "test_name".to_string(),
+            "SYM".to_string(),
+            "test_uri".to_string(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &stake_pool_accounts.manager], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    (banks_client, payer, recent_blockhash, stake_pool_accounts)
+}
+
+#[tokio::test]
+async fn success_update_pool_token_metadata() {
+    let (mut banks_client, payer, recent_blockhash, stake_pool_accounts) = setup().await;
+
+    let updated_name = "updated_name";
+    let updated_symbol = "USYM";
+    let updated_uri = "updated_uri";
+
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::update_token_metadata(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.manager.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            updated_name.to_string(),
+            updated_symbol.to_string(),
+            updated_uri.to_string(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &stake_pool_accounts.manager], recent_blockhash);
+    banks_client.process_transaction(transaction).await.unwrap();
+
+    let (token_metadata_address, _) =
+        find_token_metadata_program_address(&id(), &stake_pool_accounts.pool_mint.pubkey());
+    let token_metadata = get_account(&mut banks_client, &token_metadata_address).await;
+    let token_metadata =
+        try_from_slice_unchecked::<TokenMetadata>(token_metadata.data.as_slice()).unwrap();
+
+    assert_eq!(token_metadata.name, updated_name);
+    assert_eq!(token_metadata.symbol, updated_symbol);
+    assert_eq!(token_metadata.uri, updated_uri);
+}
+
+#[tokio::test]
+async fn fail_manager_did_not_sign() {
+    let (mut banks_client, payer, recent_blockhash, stake_pool_accounts) = setup().await;
+
+    let mut instruction = instruction::update_token_metadata(
+        &id(),
+        &stake_pool_accounts.stake_pool.pubkey(),
+        &stake_pool_accounts.manager.pubkey(),
+        &stake_pool_accounts.pool_mint.pubkey(),
+        "updated_name".to_string(),
+        "USYM".to_string(),
+        "updated_uri".to_string(),
+    );
+    instruction.accounts[1].is_signer = false;
+
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer], recent_blockhash);
+    #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .into();
+
+    match error {
+        TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(error_index),
+        )) => {
+            let program_error = StakePoolError::SignatureMissing as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while manager signature missing"),
+    }
+}
+
+#[tokio::test]
+async fn fail_wrong_manager_signed() {
+    let (mut banks_client, payer, recent_blockhash, stake_pool_accounts) = setup().await;
+
+    let random_keypair = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[instruction::update_token_metadata(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &random_keypair.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            "updated_name".to_string(),
+            "USYM".to_string(),
+            "updated_uri".to_string(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &random_keypair], recent_blockhash);
+    #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .into();
+
+    match error {
+        TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(error_index),
+        )) => {
+            let program_error = StakePoolError::WrongManager as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while signing with the wrong manager"),
+    }
+}
+
+#[tokio::test]
+async fn fail_wrong_metadata_account() {
+    let (mut banks_client, payer, recent_blockhash, stake_pool_accounts) = setup().await;
+
+    let mut instruction = instruction::update_token_metadata(
+        &id(),
+        &stake_pool_accounts.stake_pool.pubkey(),
+        &stake_pool_accounts.manager.pubkey(),
+        &stake_pool_accounts.pool_mint.pubkey(),
+        "updated_name".to_string(),
+        "USYM".to_string(),
+        "updated_uri".to_string(),
+    );
+    instruction.accounts[3].pubkey = Keypair::new().pubkey();
+
+    let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+    transaction.sign(&[&payer, &stake_pool_accounts.manager], recent_blockhash);
+    #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+    let error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .into();
+
+    match error {
+        TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(error_index),
+        )) => {
+            let program_error = StakePoolError::InvalidMetadataAccount as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while using the wrong metadata account"),
+    }
+}
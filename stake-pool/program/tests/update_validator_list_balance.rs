@@ -4,14 +4,19 @@ mod helpers;
 
 use {
     helpers::*,
-    solana_program::{borsh::try_from_slice_unchecked, program_pack::Pack, pubkey::Pubkey, stake},
+    solana_program::{
+        borsh::try_from_slice_unchecked, instruction::InstructionError, program_pack::Pack,
+        pubkey::Pubkey, stake,
+    },
     solana_program_test::*,
     solana_sdk::{
         signature::{Keypair, Signer},
         system_instruction,
-        transaction::Transaction,
+        transaction::{Transaction, TransactionError},
+        transport::TransportError,
     },
     spl_stake_pool::{
+        error::StakePoolError,
         find_transient_stake_program_address, id, instruction,
         state::{StakePool, StakeStatus, ValidatorList},
         MAX_VALIDATORS_TO_UPDATE, MINIMUM_ACTIVE_STAKE,
@@ -328,6 +333,112 @@ async fn merge_into_reserve() {
     assert_eq!(expected_lamports, stake_pool.total_lamports);
 }
 
+#[tokio::test]
+async fn success_with_progress_across_chunks() {
+    let (mut context, stake_pool_accounts, _, _, _, _, mut slot) =
+        setup(MAX_VALIDATORS_TO_UPDATE + 1).await;
+
+    let slots_per_epoch = context.genesis_config().epoch_schedule.slots_per_epoch;
+    slot += slots_per_epoch;
+    context.warp_to_slot(slot).unwrap();
+    let epoch = context.genesis_config().epoch_schedule.get_epoch(slot);
+
+    let stake_pool_info = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data).unwrap();
+    let validator_list = stake_pool_accounts
+        .get_validator_list(&mut context.banks_client)
+        .await;
+    assert_eq!(validator_list.updated_validators(epoch), 0);
+    let mut stale_instructions = instruction::update_stale_validator_list_balance(
+        &id(),
+        &stake_pool,
+        &validator_list,
+        &stake_pool_accounts.stake_pool.pubkey(),
+        epoch,
+        false,
+    );
+    assert_eq!(stale_instructions.len(), 2);
+
+    // update the second chunk only
+    let transaction = Transaction::new_signed_with_payer(
+        &[stale_instructions.pop().unwrap()],
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let validator_list = stake_pool_accounts
+        .get_validator_list(&mut context.banks_client)
+        .await;
+    assert_eq!(validator_list.updated_validators(epoch), 1);
+
+    // the pool can't be finalized until every chunk is done
+    let error = stake_pool_accounts
+        .update_stake_pool_balance(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+        )
+        .await
+        .unwrap();
+    match error {
+        TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(error_index),
+        )) => {
+            assert_eq!(error_index, StakePoolError::StakeListOutOfDate as u32);
+        }
+        _ => panic!("Wrong error occurs while finalizing a partially updated pool"),
+    }
+
+    // only the first chunk is left
+    assert_eq!(validator_list.stale_update_chunks(epoch), vec![0]);
+    let transaction = Transaction::new_signed_with_payer(
+        &stale_instructions,
+        Some(&context.payer.pubkey()),
+        &[&context.payer],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let error = stake_pool_accounts
+        .update_stake_pool_balance(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let stake_pool_info = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    let stake_pool = try_from_slice_unchecked::<StakePool>(&stake_pool_info.data).unwrap();
+    assert_eq!(stake_pool.last_update_epoch, epoch);
+    let validator_list = stake_pool_accounts
+        .get_validator_list(&mut context.banks_client)
+        .await;
+    assert_eq!(
+        validator_list.updated_validators(epoch),
+        MAX_VALIDATORS_TO_UPDATE + 1
+    );
+}
+
 #[tokio::test]
 async fn merge_into_validator_stake() {
     let (mut context, stake_pool_accounts, stake_accounts, _, lamports, reserve_lamports, mut slot) =
@@ -93,7 +93,9 @@ async fn success() {
                 active_stake_lamports: 0,
                 transient_stake_lamports: 0,
                 transient_seed_suffix_start: 0,
-                transient_seed_suffix_end: 0,
+                max_stake_bps: 0,
+                no_deposits: false,
+                reserved: [0; 5],
             }]
         }
     );
@@ -580,7 +580,9 @@ async fn success_with_deactivating_transient_stake() {
             active_stake_lamports: 0,
             transient_stake_lamports: TEST_STAKE_AMOUNT + stake_rent,
             transient_seed_suffix_start: validator_stake.transient_stake_seed,
-            transient_seed_suffix_end: 0,
+            max_stake_bps: 0,
+            no_deposits: false,
+            reserved: [0; 5],
         }],
     };
     assert_eq!(validator_list, expected_list);
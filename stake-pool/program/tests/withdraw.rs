@@ -1544,3 +1544,62 @@ async fn success_empty_out_stake_with_fee() {
         minimum_stake_lamports(&meta)
     );
 }
+
+#[tokio::test]
+async fn fail_with_exceeded_slippage() {
+    let (
+        mut banks_client,
+        payer,
+        recent_blockhash,
+        stake_pool_accounts,
+        validator_stake_account,
+        deposit_info,
+        user_transfer_authority,
+        user_stake_recipient,
+        tokens_to_burn,
+    ) = setup().await;
+
+    let new_authority = Pubkey::new_unique();
+    let lamports_out =
+        tokens_to_burn - stake_pool_accounts.calculate_withdrawal_fee(tokens_to_burn);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::withdraw_stake_with_slippage(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.validator_list.pubkey(),
+            &stake_pool_accounts.withdraw_authority,
+            &validator_stake_account.stake_account,
+            &user_stake_recipient.pubkey(),
+            &new_authority,
+            &user_transfer_authority.pubkey(),
+            &deposit_info.pool_account.pubkey(),
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &spl_token::id(),
+            tokens_to_burn,
+            lamports_out + 1,
+        )],
+        Some(&payer.pubkey()),
+        &[&payer, &user_transfer_authority],
+        recent_blockhash,
+    );
+    #[allow(clippy::useless_conversion)] // Remove during upgrade to 1.10
+    let transaction_error = banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .into();
+
+    match transaction_error {
+        TransportError::TransactionError(TransactionError::InstructionError(
+            _,
+            InstructionError::Custom(error_index),
+        )) => {
+            let program_error = StakePoolError::ExceededSlippage as u32;
+            assert_eq!(error_index, program_error);
+        }
+        _ => panic!("Wrong error occurs while withdrawing with exceeded slippage"),
+    }
+}
@@ -310,3 +310,168 @@ async fn fail_without_sol_withdraw_authority_signature() {
         )
     );
 }
+
+#[tokio::test]
+async fn success_with_slippage() {
+    let (mut context, stake_pool_accounts, user, pool_token_account, pool_tokens) = setup().await;
+
+    let amount_withdrawn_minus_fee =
+        pool_tokens - stake_pool_accounts.calculate_withdrawal_fee(pool_tokens);
+    let receiver = Pubkey::new_unique();
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::withdraw_sol_with_slippage(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.withdraw_authority,
+            &user.pubkey(),
+            &pool_token_account,
+            &stake_pool_accounts.reserve_stake.pubkey(),
+            &receiver,
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &spl_token::id(),
+            pool_tokens,
+            amount_withdrawn_minus_fee,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &user],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let receiver_lamports = get_account(&mut context.banks_client, &receiver)
+        .await
+        .lamports;
+    assert_eq!(receiver_lamports, amount_withdrawn_minus_fee);
+}
+
+#[tokio::test]
+async fn fail_with_exceeded_slippage() {
+    let (mut context, stake_pool_accounts, user, pool_token_account, pool_tokens) = setup().await;
+
+    let amount_withdrawn_minus_fee =
+        pool_tokens - stake_pool_accounts.calculate_withdrawal_fee(pool_tokens);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::withdraw_sol_with_slippage(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.withdraw_authority,
+            &user.pubkey(),
+            &pool_token_account,
+            &stake_pool_accounts.reserve_stake.pubkey(),
+            &user.pubkey(),
+            &stake_pool_accounts.pool_fee_account.pubkey(),
+            &stake_pool_accounts.pool_mint.pubkey(),
+            &spl_token::id(),
+            pool_tokens,
+            amount_withdrawn_minus_fee + 1,
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &user],
+        context.last_blockhash,
+    );
+    let error = context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .err()
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        error,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(StakePoolError::ExceededSlippage as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn success_with_liquidity_buffer() {
+    let (mut context, stake_pool_accounts, user, pool_token_account, pool_tokens) = setup().await;
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction::set_liquidity_buffer(
+            &id(),
+            &stake_pool_accounts.stake_pool.pubkey(),
+            &stake_pool_accounts.manager.pubkey(),
+            state::Fee {
+                numerator: 1,
+                denominator: 1,
+            },
+            state::Fee {
+                numerator: 10,
+                denominator: 100,
+            },
+        )],
+        Some(&context.payer.pubkey()),
+        &[&context.payer, &stake_pool_accounts.manager],
+        context.last_blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .unwrap();
+
+    let stake_pool = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    let stake_pool =
+        try_from_slice_unchecked::<state::StakePool>(stake_pool.data.as_slice()).unwrap();
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let stake_rent = rent.minimum_balance(std::mem::size_of::<stake::state::StakeState>());
+    let available_reserve_lamports = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.reserve_stake.pubkey(),
+    )
+    .await
+    .lamports
+        - stake_rent
+        - 1;
+
+    let withdraw_tokens = pool_tokens / 2;
+    let expected_fee = stake_pool
+        .calc_pool_tokens_sol_withdrawal_fee_with_buffer(
+            withdraw_tokens,
+            available_reserve_lamports,
+        )
+        .unwrap();
+    // draining the reserve below its target costs more than the base fee
+    assert!(expected_fee > stake_pool_accounts.calculate_withdrawal_fee(withdraw_tokens));
+
+    let pre_fee_balance = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+
+    let error = stake_pool_accounts
+        .withdraw_sol(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &user,
+            &pool_token_account,
+            withdraw_tokens,
+            None,
+        )
+        .await;
+    assert!(error.is_none());
+
+    let post_fee_balance = get_token_balance(
+        &mut context.banks_client,
+        &stake_pool_accounts.pool_fee_account.pubkey(),
+    )
+    .await;
+    assert_eq!(post_fee_balance - pre_fee_balance, expected_fee);
+}
@@ -0,0 +1,372 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use {
+    helpers::*,
+    solana_program::{borsh::try_from_slice_unchecked, instruction::InstructionError},
+    solana_program_test::*,
+    solana_sdk::{
+        signature::{Keypair, Signer},
+        transaction::TransactionError,
+        transport::TransportError,
+    },
+    spl_stake_pool::{error::StakePoolError, state},
+};
+
+async fn setup() -> (
+    ProgramTestContext,
+    StakePoolAccounts,
+    ValidatorStakeAccount,
+    DepositStakeAccount,
+) {
+    let mut context = program_test().start_with_context().await;
+    let stake_pool_accounts = StakePoolAccounts::new();
+    stake_pool_accounts
+        .initialize_stake_pool(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            1,
+        )
+        .await
+        .unwrap();
+
+    let validator_stake_account = simple_add_validator_to_pool(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts,
+    )
+    .await;
+
+    let deposit_info = simple_deposit_stake(
+        &mut context.banks_client,
+        &context.payer,
+        &context.last_blockhash,
+        &stake_pool_accounts,
+        &validator_stake_account,
+        TEST_STAKE_AMOUNT,
+    )
+    .await
+    .unwrap();
+
+    (
+        context,
+        stake_pool_accounts,
+        validator_stake_account,
+        deposit_info,
+    )
+}
+
+async fn get_stake_pool(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+) -> state::StakePool {
+    let stake_pool = get_account(
+        &mut context.banks_client,
+        &stake_pool_accounts.stake_pool.pubkey(),
+    )
+    .await;
+    try_from_slice_unchecked::<state::StakePool>(stake_pool.data.as_slice()).unwrap()
+}
+
+async fn warp_and_update(
+    context: &mut ProgramTestContext,
+    stake_pool_accounts: &StakePoolAccounts,
+    validator_stake_account: &ValidatorStakeAccount,
+) {
+    let first_normal_slot = context.genesis_config().epoch_schedule.first_normal_slot;
+    let slots_per_epoch = context.genesis_config().epoch_schedule.slots_per_epoch;
+    context
+        .warp_to_slot(first_normal_slot + slots_per_epoch)
+        .unwrap();
+
+    let error = stake_pool_accounts
+        .update_all(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &[validator_stake_account.vote.pubkey()],
+            false,
+        )
+        .await;
+    assert!(error.is_none());
+}
+
+fn unwrap_instruction_error(error: Option<TransportError>) -> InstructionError {
+    match error.unwrap() {
+        TransportError::TransactionError(TransactionError::InstructionError(_, error)) => error,
+        _ => panic!("Wrong error occurs"),
+    }
+}
+
+#[tokio::test]
+async fn success() {
+    let (mut context, stake_pool_accounts, validator_stake_account, deposit_info) = setup().await;
+    let pre_stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+
+    let pool_tokens = deposit_info.pool_tokens / 2;
+    let withdraw_ticket = Keypair::new();
+    let error = stake_pool_accounts
+        .withdraw_to_ticket(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &deposit_info.authority,
+            &deposit_info.pool_account.pubkey(),
+            &withdraw_ticket,
+            pool_tokens,
+        )
+        .await;
+    assert!(error.is_none());
+
+    // Tokens are burned and the lamports move from the pool to the ticket
+    assert_eq!(
+        get_token_balance(
+            &mut context.banks_client,
+            &deposit_info.pool_account.pubkey()
+        )
+        .await,
+        deposit_info.pool_tokens - pool_tokens
+    );
+    let ticket = get_account(&mut context.banks_client, &withdraw_ticket.pubkey()).await;
+    let ticket = try_from_slice_unchecked::<state::WithdrawTicket>(ticket.data.as_slice()).unwrap();
+    assert!(ticket.is_valid());
+    assert_eq!(ticket.stake_pool, stake_pool_accounts.stake_pool.pubkey());
+    assert_eq!(ticket.owner, deposit_info.authority.pubkey());
+    let pool_tokens_burnt = pool_tokens - stake_pool_accounts.calculate_withdrawal_fee(pool_tokens);
+    assert_eq!(
+        ticket.lamports,
+        pre_stake_pool
+            .calc_lamports_withdraw_amount(pool_tokens_burnt)
+            .unwrap()
+    );
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    assert_eq!(
+        stake_pool.total_lamports,
+        pre_stake_pool.total_lamports - ticket.lamports
+    );
+    assert_eq!(
+        stake_pool.pool_token_supply,
+        pre_stake_pool.pool_token_supply - pool_tokens_burnt
+    );
+    assert_eq!(stake_pool.withdraw_ticket_lamports, ticket.lamports);
+
+    // Not claimable in the epoch of the withdrawal
+    let error = stake_pool_accounts
+        .claim_ticket(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &deposit_info.authority,
+            &withdraw_ticket.pubkey(),
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::WithdrawTicketNotClaimable as u32)
+    );
+
+    // Staker deactivates the ticket's lamports
+    let error = stake_pool_accounts
+        .decrease_validator_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake_account.stake_account,
+            &validator_stake_account.transient_stake_account,
+            ticket.lamports,
+            validator_stake_account.transient_stake_seed,
+        )
+        .await;
+    assert!(error.is_none());
+
+    warp_and_update(&mut context, &stake_pool_accounts, &validator_stake_account).await;
+
+    // The ticket's lamports are not counted as pool lamports after the merge
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    assert_eq!(
+        stake_pool.total_lamports,
+        pre_stake_pool.total_lamports - ticket.lamports
+    );
+
+    // ... and can't be taken by SOL withdrawals
+    let error = stake_pool_accounts
+        .withdraw_sol(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &deposit_info.authority,
+            &deposit_info.pool_account.pubkey(),
+            (deposit_info.pool_tokens - pool_tokens) / 10,
+            None,
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::SolWithdrawalTooLarge as u32)
+    );
+
+    let ticket_rent = get_account(&mut context.banks_client, &withdraw_ticket.pubkey())
+        .await
+        .lamports;
+    let pre_owner_lamports = context
+        .banks_client
+        .get_balance(deposit_info.authority.pubkey())
+        .await
+        .unwrap();
+    let error = stake_pool_accounts
+        .claim_ticket(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &deposit_info.authority,
+            &withdraw_ticket.pubkey(),
+        )
+        .await;
+    assert!(error.is_none());
+
+    let post_owner_lamports = context
+        .banks_client
+        .get_balance(deposit_info.authority.pubkey())
+        .await
+        .unwrap();
+    assert_eq!(
+        post_owner_lamports,
+        pre_owner_lamports + ticket.lamports + ticket_rent
+    );
+    assert!(context
+        .banks_client
+        .get_account(withdraw_ticket.pubkey())
+        .await
+        .unwrap()
+        .is_none());
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    assert_eq!(stake_pool.withdraw_ticket_lamports, 0);
+}
+
+#[tokio::test]
+async fn fail_claim_wrong_owner() {
+    let (mut context, stake_pool_accounts, validator_stake_account, deposit_info) = setup().await;
+
+    let withdraw_ticket = Keypair::new();
+    let error = stake_pool_accounts
+        .withdraw_to_ticket(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &deposit_info.authority,
+            &deposit_info.pool_account.pubkey(),
+            &withdraw_ticket,
+            deposit_info.pool_tokens / 2,
+        )
+        .await;
+    assert!(error.is_none());
+
+    warp_and_update(&mut context, &stake_pool_accounts, &validator_stake_account).await;
+
+    let wrong_owner = Keypair::new();
+    let error = stake_pool_accounts
+        .claim_ticket(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &wrong_owner,
+            &withdraw_ticket.pubkey(),
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::InvalidWithdrawTicket as u32)
+    );
+}
+
+#[tokio::test]
+async fn fail_claim_stake_not_deactivated() {
+    let (mut context, stake_pool_accounts, validator_stake_account, deposit_info) = setup().await;
+
+    let withdraw_ticket = Keypair::new();
+    let error = stake_pool_accounts
+        .withdraw_to_ticket(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &deposit_info.authority,
+            &deposit_info.pool_account.pubkey(),
+            &withdraw_ticket,
+            deposit_info.pool_tokens / 2,
+        )
+        .await;
+    assert!(error.is_none());
+
+    // Next epoch, but the staker did not deactivate anything
+    warp_and_update(&mut context, &stake_pool_accounts, &validator_stake_account).await;
+
+    let error = stake_pool_accounts
+        .claim_ticket(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &deposit_info.authority,
+            &withdraw_ticket.pubkey(),
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::Custom(StakePoolError::WithdrawTicketNotClaimable as u32)
+    );
+}
+
+#[tokio::test]
+async fn fail_increase_with_ticket_lamports() {
+    let (mut context, stake_pool_accounts, validator_stake_account, deposit_info) = setup().await;
+
+    let withdraw_ticket = Keypair::new();
+    let error = stake_pool_accounts
+        .withdraw_to_ticket(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &deposit_info.authority,
+            &deposit_info.pool_account.pubkey(),
+            &withdraw_ticket,
+            deposit_info.pool_tokens / 2,
+        )
+        .await;
+    assert!(error.is_none());
+    let stake_pool = get_stake_pool(&mut context, &stake_pool_accounts).await;
+    let ticket_lamports = stake_pool.withdraw_ticket_lamports;
+
+    let error = stake_pool_accounts
+        .decrease_validator_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake_account.stake_account,
+            &validator_stake_account.transient_stake_account,
+            ticket_lamports,
+            validator_stake_account.transient_stake_seed,
+        )
+        .await;
+    assert!(error.is_none());
+
+    warp_and_update(&mut context, &stake_pool_accounts, &validator_stake_account).await;
+
+    // The deactivated lamports are in the reserve, but owed to the ticket
+    let error = stake_pool_accounts
+        .increase_validator_stake(
+            &mut context.banks_client,
+            &context.payer,
+            &context.last_blockhash,
+            &validator_stake_account.transient_stake_account,
+            &validator_stake_account.vote.pubkey(),
+            ticket_lamports / 2,
+            validator_stake_account.transient_stake_seed,
+        )
+        .await;
+    assert_eq!(
+        unwrap_instruction_error(error),
+        InstructionError::InsufficientFunds
+    );
+}
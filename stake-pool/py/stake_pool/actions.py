@@ -452,7 +452,10 @@ async def update_stake_pool(client: AsyncClient, payer: Keypair, stake_pool_addr
                 withdraw_authority=withdraw_authority,
                 validator_list=stake_pool.validator_list,
                 reserve_stake=stake_pool.reserve_stake,
-                manager_fee_account=stake_pool.manager_fee_account,
+                manager_fee_account=(
+                    stake_pool.fee_vesting.vesting_account
+                    if stake_pool.fee_vesting else stake_pool.manager_fee_account
+                ),
                 pool_mint=stake_pool.pool_mint,
                 token_program_id=stake_pool.token_program_id,
             )
@@ -793,7 +793,7 @@ def withdraw_sol(params: WithdrawSolParams) -> TransactionInstruction:
 def update_validator_list_balance(params: UpdateValidatorListBalanceParams) -> TransactionInstruction:
     """Creates instruction to update a set of validators in the stake pool."""
     keys = [
-        AccountMeta(pubkey=params.stake_pool, is_signer=False, is_writable=False),
+        AccountMeta(pubkey=params.stake_pool, is_signer=False, is_writable=True),
         AccountMeta(pubkey=params.withdraw_authority, is_signer=False, is_writable=False),
         AccountMeta(pubkey=params.validator_list, is_signer=False, is_writable=True),
         AccountMeta(pubkey=params.reserve_stake, is_signer=False, is_writable=True),
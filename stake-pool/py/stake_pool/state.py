@@ -2,7 +2,7 @@
 
 from enum import IntEnum
 from typing import List, NamedTuple, Optional
-from construct import Container, Struct, Switch, Int8ul, Int32ul, Int64ul, Pass  # type: ignore
+from construct import Bytes, Container, Flag, Struct, Switch, Int8ul, Int16ul, Int32ul, Int64ul, Pass  # type: ignore
 
 from solana.publickey import PublicKey
 from solana.utils.helpers import decode_byte_string
@@ -37,6 +37,48 @@ class Fee(NamedTuple):
             return None
 
 
+class LiquidityBuffer(NamedTuple):
+    """Reserve target for instant SOL withdrawals."""
+    target_reserve: Fee
+    max_sol_withdrawal_fee: Fee
+    deficit_lamports: int
+
+    @classmethod
+    def decode_optional_container(cls, container: Container):
+        if container:
+            return LiquidityBuffer(
+                target_reserve=Fee.decode_container(container['target_reserve']),
+                max_sol_withdrawal_fee=Fee.decode_container(container['max_sol_withdrawal_fee']),
+                deficit_lamports=container['deficit_lamports'],
+            )
+        else:
+            return None
+
+
+class FeeVesting(NamedTuple):
+    """Vesting of the manager's epoch fee."""
+    vesting_account: PublicKey
+    vesting_epochs: int
+    locked_amount: int
+    vested_amount: int
+    last_vesting_epoch: int
+    end_epoch: int
+
+    @classmethod
+    def decode_optional_container(cls, container: Container):
+        if container:
+            return FeeVesting(
+                vesting_account=PublicKey(container['vesting_account']),
+                vesting_epochs=container['vesting_epochs'],
+                locked_amount=container['locked_amount'],
+                vested_amount=container['vested_amount'],
+                last_vesting_epoch=container['last_vesting_epoch'],
+                end_epoch=container['end_epoch'],
+            )
+        else:
+            return None
+
+
 class StakePool(NamedTuple):
     """Stake pool and all its data."""
     manager: PublicKey
@@ -68,10 +110,18 @@ class StakePool(NamedTuple):
     next_sol_withdrawal_fee: Optional[Fee]
     last_epoch_pool_token_supply: int
     last_epoch_total_lamports: int
+    liquidity_buffer: Optional[LiquidityBuffer]
+    fee_vesting: Optional[FeeVesting]
+    withdraw_ticket_lamports: int
+    redelegation_deposit_fee: Fee
+    pending_redelegation_lamports: int
 
     @classmethod
     def decode(cls, data: str, encoding: str):
         data_bytes = decode_byte_string(data, encoding)
+        # pools created before the latest fields were added are shorter, and
+        # zero padding decodes to the default values, like in the program
+        data_bytes = data_bytes.ljust(STAKE_POOL_LAYOUT.sizeof(), b'\0')
         parsed = DECODE_STAKE_POOL_LAYOUT.parse(data_bytes)
         return StakePool(
             manager=PublicKey(parsed['manager']),
@@ -103,6 +153,11 @@ class StakePool(NamedTuple):
             next_sol_withdrawal_fee=Fee.decode_optional_container(parsed['next_sol_withdrawal_fee']),
             last_epoch_pool_token_supply=parsed['last_epoch_pool_token_supply'],
             last_epoch_total_lamports=parsed['last_epoch_total_lamports'],
+            liquidity_buffer=LiquidityBuffer.decode_optional_container(parsed['liquidity_buffer']),
+            fee_vesting=FeeVesting.decode_optional_container(parsed['fee_vesting']),
+            withdraw_ticket_lamports=parsed['withdraw_ticket_lamports'],
+            redelegation_deposit_fee=Fee.decode_container(parsed['redelegation_deposit_fee']),
+            pending_redelegation_lamports=parsed['pending_redelegation_lamports'],
         )
 
 
@@ -130,8 +185,11 @@ class ValidatorStakeInfo(NamedTuple):
     transient_seed_suffix_start: int
     """Start of the validator transient account seed suffixes."""
 
-    transient_seed_suffix_end: int
-    """End of the validator transient account seed suffixes."""
+    max_stake_bps: int
+    """Maximum share of the pool's total lamports delegated to this validator, in basis points, or 0 for no limit."""
+
+    no_deposits: bool
+    """If true, stake deposits and increases to this validator are refused."""
 
     status: StakeStatus
     """Status of the validator stake account."""
@@ -146,7 +204,8 @@ class ValidatorStakeInfo(NamedTuple):
             transient_stake_lamports=container['transient_stake_lamports'],
             last_update_epoch=container['last_update_epoch'],
             transient_seed_suffix_start=container['transient_seed_suffix_start'],
-            transient_seed_suffix_end=container['transient_seed_suffix_end'],
+            max_stake_bps=container['max_stake_bps'],
+            no_deposits=container['no_deposits'],
             status=container['status'],
             vote_account_address=PublicKey(container['vote_account_address']),
         )
@@ -182,6 +241,21 @@ FEE_LAYOUT = Struct(
     "numerator" / Int64ul,
 )
 
+LIQUIDITY_BUFFER_LAYOUT = Struct(
+    "target_reserve" / FEE_LAYOUT,
+    "max_sol_withdrawal_fee" / FEE_LAYOUT,
+    "deficit_lamports" / Int64ul,
+)
+
+FEE_VESTING_LAYOUT = Struct(
+    "vesting_account" / PUBLIC_KEY_LAYOUT,
+    "vesting_epochs" / Int64ul,
+    "locked_amount" / Int64ul,
+    "vested_amount" / Int64ul,
+    "last_vesting_epoch" / Int64ul,
+    "end_epoch" / Int64ul,
+)
+
 STAKE_POOL_LAYOUT = Struct(
     "account_type" / Int8ul,
     "manager" / PUBLIC_KEY_LAYOUT,
@@ -220,6 +294,13 @@ STAKE_POOL_LAYOUT = Struct(
     "next_sol_withdrawal_fee" / FEE_LAYOUT,
     "last_epoch_pool_token_supply" / Int64ul,
     "last_epoch_total_lamports" / Int64ul,
+    "liquidity_buffer_option" / Int8ul,
+    "liquidity_buffer" / LIQUIDITY_BUFFER_LAYOUT,
+    "fee_vesting_option" / Int8ul,
+    "fee_vesting" / FEE_VESTING_LAYOUT,
+    "withdraw_ticket_lamports" / Int64ul,
+    "redelegation_deposit_fee" / FEE_LAYOUT,
+    "pending_redelegation_lamports" / Int64ul,
 )
 
 DECODE_STAKE_POOL_LAYOUT = Struct(
@@ -295,6 +376,23 @@ DECODE_STAKE_POOL_LAYOUT = Struct(
         }),
     "last_epoch_pool_token_supply" / Int64ul,
     "last_epoch_total_lamports" / Int64ul,
+    "liquidity_buffer_option" / Int8ul,
+    "liquidity_buffer" / Switch(
+        lambda this: this.liquidity_buffer_option,
+        {
+            0: Pass,
+            1: LIQUIDITY_BUFFER_LAYOUT,
+        }),
+    "fee_vesting_option" / Int8ul,
+    "fee_vesting" / Switch(
+        lambda this: this.fee_vesting_option,
+        {
+            0: Pass,
+            1: FEE_VESTING_LAYOUT,
+        }),
+    "withdraw_ticket_lamports" / Int64ul,
+    "redelegation_deposit_fee" / FEE_LAYOUT,
+    "pending_redelegation_lamports" / Int64ul,
 )
 
 VALIDATOR_INFO_LAYOUT = Struct(
@@ -302,7 +400,9 @@ VALIDATOR_INFO_LAYOUT = Struct(
     "transient_stake_lamports" / Int64ul,
     "last_update_epoch" / Int64ul,
     "transient_seed_suffix_start" / Int64ul,
-    "transient_seed_suffix_end" / Int64ul,
+    "max_stake_bps" / Int16ul,
+    "no_deposits" / Flag,
+    "reserved" / Bytes(5),
     "status" / Int8ul,
     "vote_account_address" / PUBLIC_KEY_LAYOUT,
 )
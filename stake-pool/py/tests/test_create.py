@@ -66,3 +66,8 @@ async def test_create_stake_pool(async_client, payer):
     assert pool_data.next_sol_withdrawal_fee is None
     assert pool_data.last_epoch_pool_token_supply == 0
     assert pool_data.last_epoch_total_lamports == 0
+    assert pool_data.liquidity_buffer is None
+    assert pool_data.fee_vesting is None
+    assert pool_data.withdraw_ticket_lamports == 0
+    assert pool_data.redelegation_deposit_fee == Fee(numerator=0, denominator=0)
+    assert pool_data.pending_redelegation_lamports == 0
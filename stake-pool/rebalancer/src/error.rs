@@ -0,0 +1,20 @@
+//! Error types
+
+use thiserror::Error;
+
+/// Errors that may be returned while computing a rebalance plan
+#[derive(Error, Debug)]
+pub enum RebalanceError {
+    /// The scores could not be read from their source
+    #[error("Unable to read scores: {0}")]
+    ScoreSource(String),
+    /// An address in the scores is not a valid pubkey
+    #[error("Invalid vote account address in scores: {0}")]
+    InvalidVoteAccount(String),
+    /// None of the active validators in the pool has a positive score
+    #[error("No active validator in the pool has a positive score")]
+    NoScores,
+    /// Overflow or underflow while computing the targets
+    #[error("Calculation failure")]
+    CalculationFailure,
+}
@@ -0,0 +1,15 @@
+#![deny(missing_docs)]
+
+//! Off-chain rebalancing strategy for stake pools
+
+pub mod error;
+pub mod plan;
+pub mod score;
+
+pub use {
+    error::RebalanceError,
+    plan::{
+        RebalanceAction, RebalanceConfig, RebalanceDirection, RebalancePlan, MAX_ACTIONS_PER_PLAN,
+    },
+    score::{JsonFileScores, ScoreSource, StaticScores, UniformScores},
+};
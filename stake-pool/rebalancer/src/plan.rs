@@ -0,0 +1,482 @@
+//! Computation of the instructions needed to rebalance a pool
+
+use {
+    crate::{error::RebalanceError, score::ScoreSource},
+    solana_program::{instruction::Instruction, pubkey::Pubkey},
+    spl_stake_pool::{
+        instruction,
+        state::{StakePool, StakeStatus, ValidatorList, ValidatorStakeInfo},
+        MINIMUM_ACTIVE_STAKE,
+    },
+    std::{cmp::Reverse, collections::HashMap, convert::TryFrom},
+};
+
+/// Default maximum number of actions in a plan, each one sent as its own
+/// transaction, so that a single run stays short
+pub const MAX_ACTIONS_PER_PLAN: usize = 10;
+
+/// Inputs needed to rebalance a pool, besides its validator list and scores
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RebalanceConfig {
+    /// Current lamports in the pool's reserve stake account
+    pub reserve_lamports: u64,
+    /// Rent-exemption for a stake account
+    pub stake_rent: u64,
+    /// Lamports to leave undelegated in the reserve, such as the pool's
+    /// liquidity buffer target
+    pub reserve_target_lamports: u64,
+    /// Maximum number of actions, and so of instructions, in the plan
+    pub max_actions: usize,
+}
+
+impl RebalanceConfig {
+    /// Create a config with the default limit on actions
+    pub fn new(reserve_lamports: u64, stake_rent: u64) -> Self {
+        Self {
+            reserve_lamports,
+            stake_rent,
+            reserve_target_lamports: 0,
+            max_actions: MAX_ACTIONS_PER_PLAN,
+        }
+    }
+
+    /// Minimum lamports kept in a validator stake account
+    fn minimum_validator_lamports(&self) -> u64 {
+        self.stake_rent.saturating_add(MINIMUM_ACTIVE_STAKE)
+    }
+
+    /// Smallest stake movement worth a transient stake account
+    fn minimum_lamports_to_move(&self) -> u64 {
+        self.stake_rent.saturating_add(MINIMUM_ACTIVE_STAKE)
+    }
+}
+
+/// Direction of a stake movement
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RebalanceDirection {
+    /// Move stake from the reserve to the validator
+    Increase,
+    /// Move stake from the validator to the reserve
+    Decrease,
+}
+
+/// One stake movement, mapping to one instruction
+#[derive(Clone, Debug, PartialEq)]
+pub struct RebalanceAction {
+    /// Validator vote account
+    pub vote_account_address: Pubkey,
+    /// Whether stake is added or removed
+    pub direction: RebalanceDirection,
+    /// Lamports moved
+    pub lamports: u64,
+    /// Seed of the transient stake account to use
+    pub transient_stake_seed: u64,
+    /// Current stake on the validator, active and transient
+    pub current_lamports: u64,
+    /// Stake targeted on the validator according to its score
+    pub target_lamports: u64,
+}
+
+/// Set of stake movements bringing the pool closer to its targets
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RebalancePlan {
+    /// Movements, largest first
+    pub actions: Vec<RebalanceAction>,
+    /// Validators left aside because their transient stake account is in use
+    pub busy_validators: Vec<Pubkey>,
+}
+
+impl RebalancePlan {
+    /// Compute the plan for a pool, using the scores provided by `source`
+    pub fn new<S: ScoreSource + ?Sized>(
+        validator_list: &ValidatorList,
+        source: &S,
+        config: &RebalanceConfig,
+    ) -> Result<Self, RebalanceError> {
+        let scores = source.scores(validator_list)?;
+        Self::with_scores(validator_list, &scores, config)
+    }
+
+    /// Compute the plan for a pool, using already fetched scores
+    pub fn with_scores(
+        validator_list: &ValidatorList,
+        scores: &HashMap<Pubkey, u64>,
+        config: &RebalanceConfig,
+    ) -> Result<Self, RebalanceError> {
+        let validators: Vec<&ValidatorStakeInfo> = validator_list
+            .validators
+            .iter()
+            .filter(|validator| validator.status == StakeStatus::Active)
+            .collect();
+
+        let score_of = |validator: &ValidatorStakeInfo| {
+            scores
+                .get(&validator.vote_account_address)
+                .copied()
+                .unwrap_or(0)
+        };
+        let total_score = validators
+            .iter()
+            .try_fold(0u128, |acc, validator| {
+                acc.checked_add(score_of(validator) as u128)
+            })
+            .ok_or(RebalanceError::CalculationFailure)?;
+        if total_score == 0 {
+            return Err(RebalanceError::NoScores);
+        }
+
+        // Everything above the reserve's own rent-exemption can be delegated
+        let reserve_stake = config.reserve_lamports.saturating_sub(config.stake_rent);
+        let total_lamports = validators
+            .iter()
+            .try_fold(reserve_stake as u128, |acc, validator| {
+                acc.checked_add(validator.stake_lamports() as u128)
+            })
+            .ok_or(RebalanceError::CalculationFailure)?;
+        let pool_lamports =
+            u64::try_from(total_lamports).map_err(|_| RebalanceError::CalculationFailure)?;
+        // except for the lamports meant to stay in the reserve
+        let total_lamports = total_lamports.saturating_sub(config.reserve_target_lamports as u128);
+
+        let minimum_validator_lamports = config.minimum_validator_lamports();
+        let minimum_lamports_to_move = config.minimum_lamports_to_move();
+
+        let mut plan = RebalancePlan::default();
+        let mut decreases = vec![];
+        let mut increases = vec![];
+        for validator in validators {
+            let target_lamports = total_lamports
+                .checked_mul(score_of(validator) as u128)
+                .map(|product| product / total_score)
+                .and_then(|target| u64::try_from(target).ok())
+                .ok_or(RebalanceError::CalculationFailure)?;
+            // stake above a validator's cap stays in the reserve
+            let target_lamports = validator
+                .max_stake_lamports(pool_lamports)
+                .map_or(target_lamports, |max_stake_lamports| {
+                    target_lamports.min(max_stake_lamports)
+                })
+                .max(minimum_validator_lamports);
+            let current_lamports = validator.stake_lamports();
+            if current_lamports == target_lamports {
+                continue;
+            }
+            if validator.transient_stake_lamports > 0 {
+                plan.busy_validators.push(validator.vote_account_address);
+                continue;
+            }
+            let action = RebalanceAction {
+                vote_account_address: validator.vote_account_address,
+                direction: RebalanceDirection::Increase,
+                lamports: 0,
+                transient_stake_seed: validator.transient_seed_suffix_start,
+                current_lamports,
+                target_lamports,
+            };
+            if current_lamports > target_lamports {
+                let lamports = current_lamports
+                    .saturating_sub(target_lamports)
+                    .min(current_lamports.saturating_sub(minimum_validator_lamports));
+                if lamports >= minimum_lamports_to_move {
+                    decreases.push(RebalanceAction {
+                        direction: RebalanceDirection::Decrease,
+                        lamports,
+                        ..action
+                    });
+                }
+            } else if !validator.no_deposits {
+                increases.push(RebalanceAction {
+                    lamports: target_lamports.saturating_sub(current_lamports),
+                    ..action
+                });
+            }
+        }
+
+        // Each increase takes its lamports plus a transient account's rent from
+        // the reserve, which must keep more than its own rent-exemption
+        let mut reserve_budget = config
+            .reserve_lamports
+            .saturating_sub(config.stake_rent)
+            .saturating_sub(config.reserve_target_lamports)
+            .saturating_sub(1);
+        increases.sort_by_key(|action| Reverse(action.lamports));
+        let increases = increases.into_iter().filter_map(|mut action| {
+            action.lamports = action
+                .lamports
+                .min(reserve_budget.saturating_sub(config.stake_rent));
+            if action.lamports < minimum_lamports_to_move {
+                return None;
+            }
+            reserve_budget = reserve_budget
+                .saturating_sub(action.lamports)
+                .saturating_sub(config.stake_rent);
+            Some(action)
+        });
+
+        plan.actions = decreases.into_iter().chain(increases).collect();
+        plan.actions.sort_by_key(|action| Reverse(action.lamports));
+        plan.actions.truncate(config.max_actions);
+        Ok(plan)
+    }
+
+    /// Whether the pool is already balanced as well as possible
+    pub fn is_empty(&self) -> bool {
+        self.actions.is_empty()
+    }
+
+    /// Instructions executing the plan, to be signed by the staker
+    pub fn instructions(
+        &self,
+        program_id: &Pubkey,
+        stake_pool: &StakePool,
+        stake_pool_address: &Pubkey,
+    ) -> Vec<Instruction> {
+        self.actions
+            .iter()
+            .map(|action| match action.direction {
+                RebalanceDirection::Increase => instruction::increase_validator_stake_with_vote(
+                    program_id,
+                    stake_pool,
+                    stake_pool_address,
+                    &action.vote_account_address,
+                    action.lamports,
+                    action.transient_stake_seed,
+                ),
+                RebalanceDirection::Decrease => instruction::decrease_validator_stake_with_vote(
+                    program_id,
+                    stake_pool,
+                    stake_pool_address,
+                    &action.vote_account_address,
+                    action.lamports,
+                    action.transient_stake_seed,
+                ),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::score::StaticScores, solana_program::native_token::LAMPORTS_PER_SOL};
+
+    const STAKE_RENT: u64 = 2_282_880;
+
+    fn validator(active_stake_lamports: u64, transient_stake_lamports: u64) -> ValidatorStakeInfo {
+        ValidatorStakeInfo {
+            active_stake_lamports,
+            transient_stake_lamports,
+            status: StakeStatus::Active,
+            vote_account_address: Pubkey::new_unique(),
+            ..ValidatorStakeInfo::default()
+        }
+    }
+
+    fn validator_list(validators: Vec<ValidatorStakeInfo>) -> ValidatorList {
+        let mut validator_list = ValidatorList::new(validators.len() as u32);
+        validator_list.validators = validators;
+        validator_list
+    }
+
+    fn scores(validator_list: &ValidatorList, scores: &[u64]) -> StaticScores {
+        StaticScores(
+            validator_list
+                .validators
+                .iter()
+                .zip(scores)
+                .map(|(validator, score)| (validator.vote_account_address, *score))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn balanced_pool_has_no_action() {
+        let list = validator_list(vec![
+            validator(10 * LAMPORTS_PER_SOL, 0),
+            validator(10 * LAMPORTS_PER_SOL, 0),
+        ]);
+        let config = RebalanceConfig::new(STAKE_RENT, STAKE_RENT);
+        let plan = RebalancePlan::new(&list, &scores(&list, &[1, 1]), &config).unwrap();
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn spread_reserve_by_score() {
+        let list = validator_list(vec![
+            validator(STAKE_RENT + MINIMUM_ACTIVE_STAKE, 0),
+            validator(STAKE_RENT + MINIMUM_ACTIVE_STAKE, 0),
+        ]);
+        let reserve_lamports = 30 * LAMPORTS_PER_SOL;
+        let config = RebalanceConfig::new(reserve_lamports, STAKE_RENT);
+        let plan = RebalancePlan::new(&list, &scores(&list, &[2, 1]), &config).unwrap();
+
+        assert_eq!(plan.actions.len(), 2);
+        assert!(plan
+            .actions
+            .iter()
+            .all(|action| action.direction == RebalanceDirection::Increase));
+        assert_eq!(
+            plan.actions[0].vote_account_address,
+            list.validators[0].vote_account_address
+        );
+        assert!(plan.actions[0].lamports > plan.actions[1].lamports);
+
+        // the reserve keeps its rent-exemption plus the transient accounts' rent
+        let spent: u64 = plan
+            .actions
+            .iter()
+            .map(|action| action.lamports + STAKE_RENT)
+            .sum();
+        assert!(reserve_lamports - spent > STAKE_RENT);
+    }
+
+    #[test]
+    fn decrease_validator_without_score() {
+        let list = validator_list(vec![
+            validator(10 * LAMPORTS_PER_SOL, 0),
+            validator(10 * LAMPORTS_PER_SOL, 0),
+        ]);
+        let config = RebalanceConfig::new(STAKE_RENT, STAKE_RENT);
+        let plan = RebalancePlan::new(&list, &scores(&list, &[1, 0]), &config).unwrap();
+
+        // nothing in the reserve, so only the decrease is possible this epoch
+        assert_eq!(plan.actions.len(), 1);
+        let action = &plan.actions[0];
+        assert_eq!(action.direction, RebalanceDirection::Decrease);
+        assert_eq!(
+            action.vote_account_address,
+            list.validators[1].vote_account_address
+        );
+        assert_eq!(
+            action.lamports,
+            10 * LAMPORTS_PER_SOL - STAKE_RENT - MINIMUM_ACTIVE_STAKE
+        );
+    }
+
+    #[test]
+    fn skip_validators_with_transient_stake() {
+        let list = validator_list(vec![
+            validator(10 * LAMPORTS_PER_SOL, LAMPORTS_PER_SOL),
+            validator(STAKE_RENT + MINIMUM_ACTIVE_STAKE, 0),
+        ]);
+        let config = RebalanceConfig::new(STAKE_RENT, STAKE_RENT);
+        let plan = RebalancePlan::new(&list, &scores(&list, &[0, 1]), &config).unwrap();
+        assert!(plan.is_empty());
+        assert_eq!(
+            plan.busy_validators,
+            vec![list.validators[0].vote_account_address]
+        );
+    }
+
+    #[test]
+    fn ignore_inactive_validators() {
+        let mut list = validator_list(vec![
+            validator(10 * LAMPORTS_PER_SOL, 0),
+            validator(10 * LAMPORTS_PER_SOL, 0),
+        ]);
+        list.validators[1].status = StakeStatus::ReadyForRemoval;
+        let config = RebalanceConfig::new(STAKE_RENT, STAKE_RENT);
+        let plan = RebalancePlan::new(&list, &scores(&list, &[1, 1]), &config).unwrap();
+        assert!(plan.is_empty());
+    }
+
+    #[test]
+    fn respect_action_limit() {
+        let list = validator_list(
+            (0..MAX_ACTIONS_PER_PLAN * 2)
+                .map(|_| validator(STAKE_RENT + MINIMUM_ACTIVE_STAKE, 0))
+                .collect(),
+        );
+        let config = RebalanceConfig::new(1_000 * LAMPORTS_PER_SOL, STAKE_RENT);
+        let plan = RebalancePlan::new(&list, &crate::score::UniformScores, &config).unwrap();
+        assert_eq!(plan.actions.len(), MAX_ACTIONS_PER_PLAN);
+
+        let config = RebalanceConfig {
+            max_actions: 3,
+            ..config
+        };
+        let plan = RebalancePlan::new(&list, &crate::score::UniformScores, &config).unwrap();
+        assert_eq!(plan.actions.len(), 3);
+    }
+
+    #[test]
+    fn respect_validator_config() {
+        let mut list = validator_list(vec![
+            validator(STAKE_RENT + MINIMUM_ACTIVE_STAKE, 0),
+            validator(STAKE_RENT + MINIMUM_ACTIVE_STAKE, 0),
+            validator(STAKE_RENT + MINIMUM_ACTIVE_STAKE, 0),
+        ]);
+        list.validators[0].max_stake_bps = 1_000;
+        list.validators[1].no_deposits = true;
+        let reserve_lamports = 100 * LAMPORTS_PER_SOL;
+        let config = RebalanceConfig::new(reserve_lamports, STAKE_RENT);
+        let plan = RebalancePlan::new(&list, &scores(&list, &[1, 1, 1]), &config).unwrap();
+
+        assert_eq!(plan.actions.len(), 2);
+        let capped = plan
+            .actions
+            .iter()
+            .find(|action| action.vote_account_address == list.validators[0].vote_account_address)
+            .unwrap();
+        assert!(capped.target_lamports <= 10 * LAMPORTS_PER_SOL + 3 * STAKE_RENT);
+        assert_eq!(
+            capped.current_lamports + capped.lamports,
+            capped.target_lamports
+        );
+        assert!(plan
+            .actions
+            .iter()
+            .all(|action| action.vote_account_address != list.validators[1].vote_account_address));
+    }
+
+    #[test]
+    fn keep_reserve_target() {
+        let list = validator_list(vec![validator(STAKE_RENT + MINIMUM_ACTIVE_STAKE, 0)]);
+        let reserve_target_lamports = 10 * LAMPORTS_PER_SOL;
+        let config = RebalanceConfig {
+            reserve_target_lamports,
+            ..RebalanceConfig::new(30 * LAMPORTS_PER_SOL, STAKE_RENT)
+        };
+        let plan = RebalancePlan::new(&list, &scores(&list, &[1]), &config).unwrap();
+        assert_eq!(plan.actions.len(), 1);
+        let spent = plan.actions[0].lamports + STAKE_RENT;
+        assert!(30 * LAMPORTS_PER_SOL - spent >= reserve_target_lamports + STAKE_RENT);
+
+        // with the reserve below its target, stake goes back to the reserve
+        let list = validator_list(vec![validator(30 * LAMPORTS_PER_SOL, 0)]);
+        let config = RebalanceConfig {
+            reserve_target_lamports,
+            ..RebalanceConfig::new(STAKE_RENT, STAKE_RENT)
+        };
+        let plan = RebalancePlan::new(&list, &scores(&list, &[1]), &config).unwrap();
+        assert_eq!(plan.actions.len(), 1);
+        assert_eq!(plan.actions[0].direction, RebalanceDirection::Decrease);
+        assert_eq!(plan.actions[0].lamports, reserve_target_lamports);
+    }
+
+    #[test]
+    fn fail_without_scores() {
+        let list = validator_list(vec![validator(10 * LAMPORTS_PER_SOL, 0)]);
+        let config = RebalanceConfig::new(STAKE_RENT, STAKE_RENT);
+        assert!(matches!(
+            RebalancePlan::new(&list, &StaticScores::default(), &config),
+            Err(RebalanceError::NoScores)
+        ));
+    }
+
+    #[test]
+    fn build_instructions() {
+        let list = validator_list(vec![
+            validator(10 * LAMPORTS_PER_SOL, 0),
+            validator(STAKE_RENT + MINIMUM_ACTIVE_STAKE, 0),
+        ]);
+        let config = RebalanceConfig::new(10 * LAMPORTS_PER_SOL, STAKE_RENT);
+        let plan = RebalancePlan::new(&list, &scores(&list, &[0, 1]), &config).unwrap();
+        assert_eq!(plan.actions.len(), 2);
+        let instructions = plan.instructions(
+            &spl_stake_pool::id(),
+            &StakePool::default(),
+            &Pubkey::new_unique(),
+        );
+        assert_eq!(instructions.len(), 2);
+    }
+}
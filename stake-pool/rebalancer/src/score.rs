@@ -0,0 +1,121 @@
+//! Sources of target weights for the validators of a pool
+
+use {
+    crate::error::RebalanceError,
+    solana_program::pubkey::Pubkey,
+    spl_stake_pool::state::{StakeStatus, ValidatorList},
+    std::{collections::HashMap, fs, path::PathBuf, str::FromStr},
+};
+
+/// Provides a score per validator vote account. Each validator receives a share
+/// of the pool's stake proportional to its score, and validators without a
+/// score are targeted to hold only the minimum stake.
+pub trait ScoreSource {
+    /// Get the scores for the validators of the given list
+    fn scores(
+        &self,
+        validator_list: &ValidatorList,
+    ) -> Result<HashMap<Pubkey, u64>, RebalanceError>;
+}
+
+/// Scores read from a JSON file containing an object mapping vote account
+/// addresses to integer scores, ie. `{"<vote account>": 100, ...}`
+pub struct JsonFileScores {
+    /// Path to the JSON file
+    pub path: PathBuf,
+}
+
+impl JsonFileScores {
+    /// Create a new source reading the given file
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Parse scores from a JSON string
+    pub fn parse(json: &str) -> Result<HashMap<Pubkey, u64>, RebalanceError> {
+        let raw: HashMap<String, u64> = serde_json::from_str(json)
+            .map_err(|err| RebalanceError::ScoreSource(err.to_string()))?;
+        raw.into_iter()
+            .map(|(address, score)| {
+                Pubkey::from_str(&address)
+                    .map(|vote_account| (vote_account, score))
+                    .map_err(|_| RebalanceError::InvalidVoteAccount(address))
+            })
+            .collect()
+    }
+}
+
+impl ScoreSource for JsonFileScores {
+    fn scores(
+        &self,
+        _validator_list: &ValidatorList,
+    ) -> Result<HashMap<Pubkey, u64>, RebalanceError> {
+        let json = fs::read_to_string(&self.path).map_err(|err| {
+            RebalanceError::ScoreSource(format!("{}: {}", self.path.display(), err))
+        })?;
+        Self::parse(&json)
+    }
+}
+
+/// Scores held in memory, standing in for a validator data service, for
+/// example when scores are computed by the caller or in tests
+#[derive(Clone, Debug, Default)]
+pub struct StaticScores(pub HashMap<Pubkey, u64>);
+
+impl ScoreSource for StaticScores {
+    fn scores(
+        &self,
+        _validator_list: &ValidatorList,
+    ) -> Result<HashMap<Pubkey, u64>, RebalanceError> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Gives the same score to every active validator, spreading the stake evenly
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UniformScores;
+
+impl ScoreSource for UniformScores {
+    fn scores(
+        &self,
+        validator_list: &ValidatorList,
+    ) -> Result<HashMap<Pubkey, u64>, RebalanceError> {
+        Ok(validator_list
+            .validators
+            .iter()
+            .filter(|validator| validator.status == StakeStatus::Active)
+            .map(|validator| (validator.vote_account_address, 1))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_json_scores() {
+        let vote_account = Pubkey::new_unique();
+        let json = format!("{{\"{}\": 42}}", vote_account);
+        let scores = JsonFileScores::parse(&json).unwrap();
+        assert_eq!(scores.len(), 1);
+        assert_eq!(scores[&vote_account], 42);
+    }
+
+    #[test]
+    fn parse_json_scores_with_bad_address() {
+        let json = "{\"not-a-pubkey\": 42}";
+        assert!(matches!(
+            JsonFileScores::parse(json),
+            Err(RebalanceError::InvalidVoteAccount(_))
+        ));
+    }
+
+    #[test]
+    fn parse_json_scores_with_bad_format() {
+        assert!(matches!(
+            JsonFileScores::parse("[1, 2]"),
+            Err(RebalanceError::ScoreSource(_))
+        ));
+    }
+}
@@ -1,7 +1,7 @@
 use {
     clap::{
-        crate_description, crate_name, crate_version, value_t, App, AppSettings, Arg, ArgMatches,
-        SubCommand,
+        crate_description, crate_name, crate_version, value_t, App, AppSettings, Arg, ArgGroup,
+        ArgMatches, SubCommand,
     },
     solana_clap_utils::{
         fee_payer::fee_payer_arg,
@@ -25,9 +25,12 @@ use {
     },
     spl_token_lending::{
         self,
-        instruction::{init_lending_market, init_reserve},
+        instruction::{
+            init_lending_market, init_reserve, redeem_fees, refresh_reserve, set_elevation_group,
+            set_reserve_oracle, update_reserve_config, OracleAccounts,
+        },
         math::WAD,
-        state::{LendingMarket, Reserve, ReserveConfig, ReserveFees},
+        state::{BorrowRateCurve, LendingMarket, Reserve, ReserveConfig, ReserveFees},
     },
     std::{borrow::Borrow, process::exit, str::FromStr},
     system_instruction::create_account,
@@ -208,16 +211,6 @@ fn main() {
                         .required(true)
                         .help("Pyth price account: https://pyth.network/developers/consumers/accounts"),
                 )
-                .arg(
-                    Arg::with_name("optimal_utilization_rate")
-                        .long("optimal-utilization-rate")
-                        .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
-                        .takes_value(true)
-                        .required(true)
-                        .default_value("80")
-                        .help("Optimal utilization rate: [0, 100]"),
-                )
                 .arg(
                     Arg::with_name("loan_to_value_ratio")
                         .long("loan-to-value-ratio")
@@ -249,34 +242,149 @@ fn main() {
                         .help("Loan to value ratio at which an obligation can be liquidated: (LTV, 100]"),
                 )
                 .arg(
-                    Arg::with_name("min_borrow_rate")
-                        .long("min-borrow-rate")
+                    Arg::with_name("borrow_rate_curve")
+                        .long("borrow-rate-curve")
+                        .validator(is_borrow_rate_curve)
+                        .value_name("UTILIZATION_BPS:RATE_BPS,...")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("0:0,8000:400,10000:3000")
+                        .help("Borrow APY by utilization rate, in basis points, from 0 to 10000 utilization"),
+                )
+                .arg(
+                    Arg::with_name("borrow_fee")
+                        .long("borrow-fee")
+                        .validator(is_parsable::<f64>)
+                        .value_name("DECIMAL_PERCENT")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("0.00001")
+                        .help("Fee assessed on borrow, expressed as a percentage: [0, 1)"),
+                )
+                .arg(
+                    Arg::with_name("flash_loan_fee")
+                        .long("flash-loan-fee")
+                        .validator(is_parsable::<f64>)
+                        .value_name("DECIMAL_PERCENT")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value(".3")
+                        .help("Fee assessed for flash loans, expressed as a percentage: [0, 1)"),
+                )
+                .arg(
+                    Arg::with_name("host_fee_percentage")
+                        .long("host-fee-percentage")
                         .validator(is_parsable::<u8>)
                         .value_name("INTEGER_PERCENT")
                         .takes_value(true)
                         .required(true)
+                        .default_value("20")
+                        .help("Amount of fee going to host account: [0, 100]"),
+                )
+                .arg(
+                    Arg::with_name("max_price_confidence")
+                        .long("max-price-confidence")
+                        .validator(is_parsable::<u16>)
+                        .value_name("INTEGER_BPS")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("200")
+                        .help("Max oracle price confidence interval in basis points of the price, 0 to disable: [0, 10000]"),
+                )
+                .arg(
+                    Arg::with_name("deposit_limit")
+                        .long("deposit-limit")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER_AMOUNT")
+                        .takes_value(true)
+                        .required(true)
                         .default_value("0")
-                        .help("Min borrow APY: min <= optimal <= max"),
+                        .help("Max total liquidity supply in token base units, 0 for unlimited"),
                 )
                 .arg(
-                    Arg::with_name("optimal_borrow_rate")
-                        .long("optimal-borrow-rate")
+                    Arg::with_name("borrow_limit")
+                        .long("borrow-limit")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER_AMOUNT")
+                        .takes_value(true)
+                        .required(true)
+                        .default_value("0")
+                        .help("Max total liquidity borrowed in token base units, 0 for unlimited"),
+                )
+                .arg(
+                    Arg::with_name("elevation_group")
+                        .long("elevation-group")
                         .validator(is_parsable::<u8>)
-                        .value_name("INTEGER_PERCENT")
+                        .value_name("INTEGER")
                         .takes_value(true)
                         .required(true)
-                        .default_value("4")
-                        .help("Optimal (utilization) borrow APY: min <= optimal <= max"),
+                        .default_value("0")
+                        .help("Lending market elevation group of the reserve, 0 for none: [0, 8]"),
                 )
                 .arg(
-                    Arg::with_name("max_borrow_rate")
-                        .long("max-borrow-rate")
+                    Arg::with_name("protocol_take_rate")
+                        .long("protocol-take-rate")
                         .validator(is_parsable::<u8>)
                         .value_name("INTEGER_PERCENT")
                         .takes_value(true)
                         .required(true)
-                        .default_value("30")
-                        .help("Max borrow APY: min <= optimal <= max"),
+                        .default_value("0")
+                        .help("Share of accrued interest set aside as protocol fees for the fee receiver: [0, 100]"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("update-reserve")
+                .about("Update the configuration of a reserve, leaving unspecified values unchanged")
+                // @TODO: use is_valid_signer
+                .arg(
+                    Arg::with_name("lending_market_owner")
+                        .long("market-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the lending market"),
+                )
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address"),
+                )
+                .arg(
+                    Arg::with_name("loan_to_value_ratio")
+                        .long("loan-to-value-ratio")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .help("Target ratio of the value of borrows to deposits: [0, 100)"),
+                )
+                .arg(
+                    Arg::with_name("liquidation_bonus")
+                        .long("liquidation-bonus")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .help("Bonus a liquidator gets when repaying part of an unhealthy obligation: [0, 100]"),
+                )
+                .arg(
+                    Arg::with_name("liquidation_threshold")
+                        .long("liquidation-threshold")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .help("Loan to value ratio at which an obligation can be liquidated: (LTV, 100]"),
+                )
+                .arg(
+                    Arg::with_name("borrow_rate_curve")
+                        .long("borrow-rate-curve")
+                        .validator(is_borrow_rate_curve)
+                        .value_name("UTILIZATION_BPS:RATE_BPS,...")
+                        .takes_value(true)
+                        .help("Borrow APY by utilization rate, in basis points, from 0 to 10000 utilization"),
                 )
                 .arg(
                     Arg::with_name("borrow_fee")
@@ -284,8 +392,6 @@ fn main() {
                         .validator(is_parsable::<f64>)
                         .value_name("DECIMAL_PERCENT")
                         .takes_value(true)
-                        .required(true)
-                        .default_value("0.00001")
                         .help("Fee assessed on borrow, expressed as a percentage: [0, 1)"),
                 )
                 .arg(
@@ -294,8 +400,6 @@ fn main() {
                         .validator(is_parsable::<f64>)
                         .value_name("DECIMAL_PERCENT")
                         .takes_value(true)
-                        .required(true)
-                        .default_value(".3")
                         .help("Fee assessed for flash loans, expressed as a percentage: [0, 1)"),
                 )
                 .arg(
@@ -304,10 +408,214 @@ fn main() {
                         .validator(is_parsable::<u8>)
                         .value_name("INTEGER_PERCENT")
                         .takes_value(true)
-                        .required(true)
-                        .default_value("20")
                         .help("Amount of fee going to host account: [0, 100]"),
                 )
+                .arg(
+                    Arg::with_name("max_price_confidence")
+                        .long("max-price-confidence")
+                        .validator(is_parsable::<u16>)
+                        .value_name("INTEGER_BPS")
+                        .takes_value(true)
+                        .help("Max oracle price confidence interval in basis points of the price, 0 to disable: [0, 10000]"),
+                )
+                .arg(
+                    Arg::with_name("deposit_limit")
+                        .long("deposit-limit")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER_AMOUNT")
+                        .takes_value(true)
+                        .help("Max total liquidity supply in token base units, 0 for unlimited"),
+                )
+                .arg(
+                    Arg::with_name("borrow_limit")
+                        .long("borrow-limit")
+                        .validator(is_parsable::<u64>)
+                        .value_name("INTEGER_AMOUNT")
+                        .takes_value(true)
+                        .help("Max total liquidity borrowed in token base units, 0 for unlimited"),
+                )
+                .arg(
+                    Arg::with_name("elevation_group")
+                        .long("elevation-group")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .help("Lending market elevation group of the reserve, 0 for none: [0, 8]"),
+                )
+                .arg(
+                    Arg::with_name("protocol_take_rate")
+                        .long("protocol-take-rate")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .help("Share of accrued interest set aside as protocol fees for the fee receiver: [0, 100]"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("set-reserve-oracle")
+                .about("Set the oracle and optional fallback oracle used to price a reserve's liquidity")
+                // @TODO: use is_valid_signer
+                .arg(
+                    Arg::with_name("lending_market_owner")
+                        .long("market-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the lending market"),
+                )
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address"),
+                )
+                .arg(
+                    Arg::with_name("pyth_product")
+                        .long("pyth-product")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .requires("pyth_price")
+                        .help("Pyth product account: https://pyth.network/developers/consumers/accounts"),
+                )
+                .arg(
+                    Arg::with_name("pyth_price")
+                        .long("pyth-price")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .requires("pyth_product")
+                        .help("Pyth price account: https://pyth.network/developers/consumers/accounts"),
+                )
+                .arg(
+                    Arg::with_name("switchboard_aggregator")
+                        .long("switchboard-aggregator")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Switchboard aggregator account"),
+                )
+                .arg(
+                    Arg::with_name("fixed_price")
+                        .long("fixed-price")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Fixed price account owned by the lending market oracle program"),
+                )
+                .group(
+                    ArgGroup::with_name("oracle")
+                        .args(&["pyth_price", "switchboard_aggregator", "fixed_price"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("fallback_pyth_product")
+                        .long("fallback-pyth-product")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .requires("fallback_pyth_price")
+                        .help("Fallback Pyth product account"),
+                )
+                .arg(
+                    Arg::with_name("fallback_pyth_price")
+                        .long("fallback-pyth-price")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .requires("fallback_pyth_product")
+                        .help("Fallback Pyth price account"),
+                )
+                .arg(
+                    Arg::with_name("fallback_switchboard_aggregator")
+                        .long("fallback-switchboard-aggregator")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Fallback Switchboard aggregator account"),
+                )
+                .arg(
+                    Arg::with_name("fallback_fixed_price")
+                        .long("fallback-fixed-price")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .help("Fallback fixed price account"),
+                )
+                .group(
+                    ArgGroup::with_name("fallback_oracle").args(&[
+                        "fallback_pyth_price",
+                        "fallback_switchboard_aggregator",
+                        "fallback_fixed_price",
+                    ]),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("set-elevation-group")
+                .about("Set the risk parameters of a lending market elevation group")
+                // @TODO: use is_valid_signer
+                .arg(
+                    Arg::with_name("lending_market_owner")
+                        .long("market-owner")
+                        .validator(is_keypair)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Owner of the lending market"),
+                )
+                .arg(
+                    Arg::with_name("lending_market")
+                        .long("market")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Lending market address"),
+                )
+                .arg(
+                    Arg::with_name("elevation_group")
+                        .long("elevation-group")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Elevation group: [1, 8]"),
+                )
+                .arg(
+                    Arg::with_name("loan_to_value_ratio")
+                        .long("loan-to-value-ratio")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Target ratio of the value of borrows to deposits within the group: [0, 100)"),
+                )
+                .arg(
+                    Arg::with_name("liquidation_threshold")
+                        .long("liquidation-threshold")
+                        .validator(is_parsable::<u8>)
+                        .value_name("INTEGER_PERCENT")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Loan to value ratio at which obligations within the group can be liquidated, 0 to remove the group: (LTV, 100]"),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("redeem-fees")
+                .about("Redeem a reserve's accumulated protocol fees to its fee receiver")
+                .arg(
+                    Arg::with_name("reserve")
+                        .long("reserve")
+                        .validator(is_pubkey)
+                        .value_name("PUBKEY")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Reserve address"),
+                )
         )
         .get_matches();
 
@@ -369,17 +677,18 @@ fn main() {
             let ui_amount = value_of(arg_matches, "liquidity_amount").unwrap();
             let pyth_product_pubkey = pubkey_of(arg_matches, "pyth_product").unwrap();
             let pyth_price_pubkey = pubkey_of(arg_matches, "pyth_price").unwrap();
-            let optimal_utilization_rate =
-                value_of(arg_matches, "optimal_utilization_rate").unwrap();
             let loan_to_value_ratio = value_of(arg_matches, "loan_to_value_ratio").unwrap();
             let liquidation_bonus = value_of(arg_matches, "liquidation_bonus").unwrap();
             let liquidation_threshold = value_of(arg_matches, "liquidation_threshold").unwrap();
-            let min_borrow_rate = value_of(arg_matches, "min_borrow_rate").unwrap();
-            let optimal_borrow_rate = value_of(arg_matches, "optimal_borrow_rate").unwrap();
-            let max_borrow_rate = value_of(arg_matches, "max_borrow_rate").unwrap();
+            let borrow_rate_curve = borrow_rate_curve_of(arg_matches, "borrow_rate_curve").unwrap();
             let borrow_fee = value_of::<f64>(arg_matches, "borrow_fee").unwrap();
             let flash_loan_fee = value_of::<f64>(arg_matches, "flash_loan_fee").unwrap();
             let host_fee_percentage = value_of(arg_matches, "host_fee_percentage").unwrap();
+            let max_price_confidence_bps = value_of(arg_matches, "max_price_confidence").unwrap();
+            let deposit_limit = value_of(arg_matches, "deposit_limit").unwrap();
+            let borrow_limit = value_of(arg_matches, "borrow_limit").unwrap();
+            let elevation_group = value_of(arg_matches, "elevation_group").unwrap();
+            let protocol_take_rate = value_of(arg_matches, "protocol_take_rate").unwrap();
 
             let borrow_fee_wad = (borrow_fee * WAD as f64) as u64;
             let flash_loan_fee_wad = (flash_loan_fee * WAD as f64) as u64;
@@ -388,18 +697,20 @@ fn main() {
                 &config,
                 ui_amount,
                 ReserveConfig {
-                    optimal_utilization_rate,
                     loan_to_value_ratio,
                     liquidation_bonus,
                     liquidation_threshold,
-                    min_borrow_rate,
-                    optimal_borrow_rate,
-                    max_borrow_rate,
+                    borrow_rate_curve,
                     fees: ReserveFees {
                         borrow_fee_wad,
                         flash_loan_fee_wad,
                         host_fee_percentage,
                     },
+                    max_price_confidence_bps,
+                    deposit_limit,
+                    borrow_limit,
+                    elevation_group,
+                    protocol_take_rate,
                 },
                 source_liquidity_pubkey,
                 source_liquidity_owner_keypair,
@@ -409,6 +720,71 @@ fn main() {
                 pyth_price_pubkey,
             )
         }
+        ("update-reserve", Some(arg_matches)) => {
+            let lending_market_owner_keypair =
+                keypair_of(arg_matches, "lending_market_owner").unwrap();
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+            let reserve_config_update = ReserveConfigUpdate {
+                loan_to_value_ratio: value_of(arg_matches, "loan_to_value_ratio"),
+                liquidation_bonus: value_of(arg_matches, "liquidation_bonus"),
+                liquidation_threshold: value_of(arg_matches, "liquidation_threshold"),
+                borrow_rate_curve: borrow_rate_curve_of(arg_matches, "borrow_rate_curve"),
+                borrow_fee_wad: value_of::<f64>(arg_matches, "borrow_fee")
+                    .map(|borrow_fee| (borrow_fee * WAD as f64) as u64),
+                flash_loan_fee_wad: value_of::<f64>(arg_matches, "flash_loan_fee")
+                    .map(|flash_loan_fee| (flash_loan_fee * WAD as f64) as u64),
+                host_fee_percentage: value_of(arg_matches, "host_fee_percentage"),
+                max_price_confidence_bps: value_of(arg_matches, "max_price_confidence"),
+                deposit_limit: value_of(arg_matches, "deposit_limit"),
+                borrow_limit: value_of(arg_matches, "borrow_limit"),
+                elevation_group: value_of(arg_matches, "elevation_group"),
+                protocol_take_rate: value_of(arg_matches, "protocol_take_rate"),
+            };
+
+            command_update_reserve(
+                &config,
+                reserve_config_update,
+                reserve_pubkey,
+                lending_market_owner_keypair,
+            )
+        }
+        ("set-reserve-oracle", Some(arg_matches)) => {
+            let lending_market_owner_keypair =
+                keypair_of(arg_matches, "lending_market_owner").unwrap();
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+            let oracle = oracle_accounts_of(arg_matches, "").unwrap();
+            let fallback_oracle = oracle_accounts_of(arg_matches, "fallback_");
+
+            command_set_reserve_oracle(
+                &config,
+                reserve_pubkey,
+                lending_market_owner_keypair,
+                oracle,
+                fallback_oracle,
+            )
+        }
+        ("set-elevation-group", Some(arg_matches)) => {
+            let lending_market_owner_keypair =
+                keypair_of(arg_matches, "lending_market_owner").unwrap();
+            let lending_market_pubkey = pubkey_of(arg_matches, "lending_market").unwrap();
+            let elevation_group = value_of(arg_matches, "elevation_group").unwrap();
+            let loan_to_value_ratio = value_of(arg_matches, "loan_to_value_ratio").unwrap();
+            let liquidation_threshold = value_of(arg_matches, "liquidation_threshold").unwrap();
+
+            command_set_elevation_group(
+                &config,
+                lending_market_pubkey,
+                lending_market_owner_keypair,
+                elevation_group,
+                loan_to_value_ratio,
+                liquidation_threshold,
+            )
+        }
+        ("redeem-fees", Some(arg_matches)) => {
+            let reserve_pubkey = pubkey_of(arg_matches, "reserve").unwrap();
+
+            command_redeem_fees(&config, reserve_pubkey)
+        }
         _ => unreachable!(),
     }
     .map_err(|err| {
@@ -690,8 +1066,244 @@ fn command_add_reserve(
     Ok(())
 }
 
+struct ReserveConfigUpdate {
+    loan_to_value_ratio: Option<u8>,
+    liquidation_bonus: Option<u8>,
+    liquidation_threshold: Option<u8>,
+    borrow_rate_curve: Option<BorrowRateCurve>,
+    borrow_fee_wad: Option<u64>,
+    flash_loan_fee_wad: Option<u64>,
+    host_fee_percentage: Option<u8>,
+    max_price_confidence_bps: Option<u16>,
+    deposit_limit: Option<u64>,
+    borrow_limit: Option<u64>,
+    elevation_group: Option<u8>,
+    protocol_take_rate: Option<u8>,
+}
+
+impl ReserveConfigUpdate {
+    fn apply(&self, config: &mut ReserveConfig) {
+        if let Some(loan_to_value_ratio) = self.loan_to_value_ratio {
+            config.loan_to_value_ratio = loan_to_value_ratio;
+        }
+        if let Some(liquidation_bonus) = self.liquidation_bonus {
+            config.liquidation_bonus = liquidation_bonus;
+        }
+        if let Some(liquidation_threshold) = self.liquidation_threshold {
+            config.liquidation_threshold = liquidation_threshold;
+        }
+        if let Some(borrow_rate_curve) = self.borrow_rate_curve {
+            config.borrow_rate_curve = borrow_rate_curve;
+        }
+        if let Some(borrow_fee_wad) = self.borrow_fee_wad {
+            config.fees.borrow_fee_wad = borrow_fee_wad;
+        }
+        if let Some(flash_loan_fee_wad) = self.flash_loan_fee_wad {
+            config.fees.flash_loan_fee_wad = flash_loan_fee_wad;
+        }
+        if let Some(host_fee_percentage) = self.host_fee_percentage {
+            config.fees.host_fee_percentage = host_fee_percentage;
+        }
+        if let Some(max_price_confidence_bps) = self.max_price_confidence_bps {
+            config.max_price_confidence_bps = max_price_confidence_bps;
+        }
+        if let Some(deposit_limit) = self.deposit_limit {
+            config.deposit_limit = deposit_limit;
+        }
+        if let Some(borrow_limit) = self.borrow_limit {
+            config.borrow_limit = borrow_limit;
+        }
+        if let Some(elevation_group) = self.elevation_group {
+            config.elevation_group = elevation_group;
+        }
+        if let Some(protocol_take_rate) = self.protocol_take_rate {
+            config.protocol_take_rate = protocol_take_rate;
+        }
+    }
+}
+
+fn command_update_reserve(
+    config: &Config,
+    reserve_config_update: ReserveConfigUpdate,
+    reserve_pubkey: Pubkey,
+    lending_market_owner_keypair: Keypair,
+) -> CommandResult {
+    let reserve_account = config.rpc_client.get_account(&reserve_pubkey)?;
+    let reserve = Reserve::unpack_from_slice(reserve_account.data.borrow())?;
+
+    let mut reserve_config = reserve.config;
+    reserve_config_update.apply(&mut reserve_config);
+
+    println!("Updating reserve {}", reserve_pubkey);
+    if config.verbose {
+        println!("Previous config: {:?}", reserve.config);
+        println!("New config: {:?}", reserve_config);
+    }
+
+    let recent_blockhash = config.rpc_client.get_latest_blockhash()?;
+    let message = Message::new_with_blockhash(
+        &[update_reserve_config(
+            config.lending_program_id,
+            reserve_config,
+            reserve_pubkey,
+            reserve.lending_market,
+            lending_market_owner_keypair.pubkey(),
+        )],
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+
+    check_fee_payer_balance(config, config.rpc_client.get_fee_for_message(&message)?)?;
+    let transaction = Transaction::new(
+        &vec![config.fee_payer.as_ref(), &lending_market_owner_keypair],
+        message,
+        recent_blockhash,
+    );
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_reserve_oracle(
+    config: &Config,
+    reserve_pubkey: Pubkey,
+    lending_market_owner_keypair: Keypair,
+    oracle: OracleAccounts,
+    fallback_oracle: Option<OracleAccounts>,
+) -> CommandResult {
+    let reserve_account = config.rpc_client.get_account(&reserve_pubkey)?;
+    let reserve = Reserve::unpack_from_slice(reserve_account.data.borrow())?;
+
+    println!(
+        "Setting oracle of reserve {} to {:?} {}",
+        reserve_pubkey,
+        oracle.source(),
+        oracle.price_pubkey()
+    );
+    match fallback_oracle {
+        Some(fallback_oracle) => println!(
+            "Setting fallback oracle of reserve {} to {:?} {}",
+            reserve_pubkey,
+            fallback_oracle.source(),
+            fallback_oracle.price_pubkey()
+        ),
+        None => println!("Removing fallback oracle of reserve {}", reserve_pubkey),
+    }
+
+    let recent_blockhash = config.rpc_client.get_latest_blockhash()?;
+    let message = Message::new_with_blockhash(
+        &[set_reserve_oracle(
+            config.lending_program_id,
+            reserve_pubkey,
+            oracle,
+            fallback_oracle,
+            reserve.lending_market,
+            lending_market_owner_keypair.pubkey(),
+        )],
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+
+    check_fee_payer_balance(config, config.rpc_client.get_fee_for_message(&message)?)?;
+    let transaction = Transaction::new(
+        &vec![config.fee_payer.as_ref(), &lending_market_owner_keypair],
+        message,
+        recent_blockhash,
+    );
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn command_set_elevation_group(
+    config: &Config,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_keypair: Keypair,
+    elevation_group: u8,
+    loan_to_value_ratio: u8,
+    liquidation_threshold: u8,
+) -> CommandResult {
+    println!(
+        "Setting elevation group {} of lending market {} to {}% LTV, {}% liquidation threshold",
+        elevation_group, lending_market_pubkey, loan_to_value_ratio, liquidation_threshold
+    );
+
+    let recent_blockhash = config.rpc_client.get_latest_blockhash()?;
+    let message = Message::new_with_blockhash(
+        &[set_elevation_group(
+            config.lending_program_id,
+            elevation_group,
+            loan_to_value_ratio,
+            liquidation_threshold,
+            lending_market_pubkey,
+            lending_market_owner_keypair.pubkey(),
+        )],
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+
+    check_fee_payer_balance(config, config.rpc_client.get_fee_for_message(&message)?)?;
+    let transaction = Transaction::new(
+        &vec![config.fee_payer.as_ref(), &lending_market_owner_keypair],
+        message,
+        recent_blockhash,
+    );
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
 // HELPERS
 
+fn command_redeem_fees(config: &Config, reserve_pubkey: Pubkey) -> CommandResult {
+    let reserve_account = config.rpc_client.get_account(&reserve_pubkey)?;
+    let reserve = Reserve::unpack_from_slice(reserve_account.data.borrow())?;
+
+    println!(
+        "Redeeming protocol fees of reserve {} to {}",
+        reserve_pubkey, reserve.liquidity.fee_receiver
+    );
+
+    let fallback_oracle_pubkey = Some(reserve.liquidity.fallback_oracle_pubkey)
+        .filter(|fallback_oracle_pubkey| fallback_oracle_pubkey != &Pubkey::default());
+    let recent_blockhash = config.rpc_client.get_latest_blockhash()?;
+    let message = Message::new_with_blockhash(
+        &[
+            refresh_reserve(
+                config.lending_program_id,
+                reserve_pubkey,
+                reserve.liquidity.oracle_pubkey,
+                fallback_oracle_pubkey,
+            ),
+            redeem_fees(
+                config.lending_program_id,
+                reserve_pubkey,
+                reserve.liquidity.fee_receiver,
+                reserve.liquidity.supply_pubkey,
+                reserve.lending_market,
+            ),
+        ],
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+
+    check_fee_payer_balance(config, config.rpc_client.get_fee_for_message(&message)?)?;
+    let transaction = Transaction::new(&vec![config.fee_payer.as_ref()], message, recent_blockhash);
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn oracle_accounts_of(matches: &ArgMatches<'_>, prefix: &str) -> Option<OracleAccounts> {
+    let pubkey_of_arg = |name: &str| pubkey_of(matches, &format!("{}{}", prefix, name));
+    if let Some(price_pubkey) = pubkey_of_arg("pyth_price") {
+        Some(OracleAccounts::Pyth {
+            product_pubkey: pubkey_of_arg("pyth_product").unwrap(),
+            price_pubkey,
+        })
+    } else if let Some(aggregator_pubkey) = pubkey_of_arg("switchboard_aggregator") {
+        Some(OracleAccounts::Switchboard { aggregator_pubkey })
+    } else {
+        pubkey_of_arg("fixed_price").map(|price_pubkey| OracleAccounts::FixedPrice { price_pubkey })
+    }
+}
+
 fn check_fee_payer_balance(config: &Config, required_balance: u64) -> Result<(), Error> {
     let balance = config.rpc_client.get_balance(&config.fee_payer.pubkey())?;
     if balance < required_balance {
@@ -738,3 +1350,39 @@ fn quote_currency_of(matches: &ArgMatches<'_>, name: &str) -> Option<[u8; 32]> {
         None
     }
 }
+
+fn parse_borrow_rate_curve(value: &str) -> Result<BorrowRateCurve, String> {
+    let points = value
+        .split(',')
+        .map(|point| {
+            let mut rates = point.splitn(2, ':');
+            match (rates.next(), rates.next()) {
+                (Some(utilization_rate_bps), Some(borrow_rate_bps)) => Ok((
+                    utilization_rate_bps
+                        .trim()
+                        .parse::<u16>()
+                        .map_err(|e| format!("Unable to parse {}: {}", point, e))?,
+                    borrow_rate_bps
+                        .trim()
+                        .parse::<u16>()
+                        .map_err(|e| format!("Unable to parse {}: {}", point, e))?,
+                )),
+                _ => Err(format!(
+                    "Unable to parse {}, expected UTILIZATION_BPS:RATE_BPS",
+                    point
+                )),
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    BorrowRateCurve::new(&points).map_err(|e| format!("Invalid borrow rate curve: {}", e))
+}
+
+fn is_borrow_rate_curve(value: String) -> Result<(), String> {
+    parse_borrow_rate_curve(&value).map(|_| ())
+}
+
+fn borrow_rate_curve_of(matches: &ArgMatches<'_>, name: &str) -> Option<BorrowRateCurve> {
+    matches
+        .value_of(name)
+        .map(|value| parse_borrow_rate_curve(value).unwrap())
+}
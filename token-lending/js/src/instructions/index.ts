@@ -6,8 +6,14 @@ export * from './initObligation';
 export * from './initReserve';
 export * from './instruction';
 export * from './liquidateObligation';
+export * from './reallocObligation';
+export * from './redeemFees';
 export * from './redeemReserveCollateral';
 export * from './refreshObligation';
 export * from './refreshReserve';
 export * from './repayObligationLiquidity';
+export * from './setElevationGroup';
+export * from './setObligationElevationGroup';
+export * from './setReserveOracle';
+export * from './updateReserveConfig';
 export * from './withdrawObligationCollateral';
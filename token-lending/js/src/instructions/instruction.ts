@@ -14,4 +14,10 @@ export enum LendingInstruction {
     RepayObligationLiquidity = 11,
     LiquidateObligation = 12,
     FlashLoan = 13,
+    UpdateReserveConfig = 14,
+    SetReserveOracle = 15,
+    SetElevationGroup = 16,
+    SetObligationElevationGroup = 17,
+    RedeemFees = 18,
+    ReallocObligation = 19,
 }
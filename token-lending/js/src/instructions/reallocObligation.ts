@@ -0,0 +1,35 @@
+import { PublicKey, SystemProgram, SYSVAR_RENT_PUBKEY, TransactionInstruction } from '@solana/web3.js';
+import { struct, u8 } from 'buffer-layout';
+import { LENDING_PROGRAM_ID } from '../constants';
+import { LendingInstruction } from './instruction';
+
+interface Data {
+    instruction: number;
+}
+
+const DataLayout = struct<Data>([u8('instruction')]);
+
+export const reallocObligationInstruction = (
+    obligation: PublicKey,
+    lendingMarket: PublicKey,
+    obligationOwner: PublicKey,
+    payer: PublicKey
+): TransactionInstruction => {
+    const data = Buffer.alloc(DataLayout.span);
+    DataLayout.encode({ instruction: LendingInstruction.ReallocObligation }, data);
+
+    const keys = [
+        { pubkey: obligation, isSigner: false, isWritable: true },
+        { pubkey: lendingMarket, isSigner: false, isWritable: false },
+        { pubkey: obligationOwner, isSigner: true, isWritable: false },
+        { pubkey: payer, isSigner: true, isWritable: true },
+        { pubkey: SYSVAR_RENT_PUBKEY, isSigner: false, isWritable: false },
+        { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+    ];
+
+    return new TransactionInstruction({
+        keys,
+        programId: LENDING_PROGRAM_ID,
+        data,
+    });
+};
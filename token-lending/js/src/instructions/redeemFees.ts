@@ -0,0 +1,38 @@
+import { TOKEN_PROGRAM_ID } from '@solana/spl-token';
+import { PublicKey, SYSVAR_CLOCK_PUBKEY, TransactionInstruction } from '@solana/web3.js';
+import { struct, u8 } from 'buffer-layout';
+import { LENDING_PROGRAM_ID } from '../constants';
+import { LendingInstruction } from './instruction';
+
+interface Data {
+    instruction: number;
+}
+
+const DataLayout = struct<Data>([u8('instruction')]);
+
+export const redeemFeesInstruction = (
+    reserve: PublicKey,
+    reserveLiquidityFeeReceiver: PublicKey,
+    reserveLiquiditySupply: PublicKey,
+    lendingMarket: PublicKey,
+    lendingMarketAuthority: PublicKey
+): TransactionInstruction => {
+    const data = Buffer.alloc(DataLayout.span);
+    DataLayout.encode({ instruction: LendingInstruction.RedeemFees }, data);
+
+    const keys = [
+        { pubkey: reserve, isSigner: false, isWritable: true },
+        { pubkey: reserveLiquidityFeeReceiver, isSigner: false, isWritable: true },
+        { pubkey: reserveLiquiditySupply, isSigner: false, isWritable: true },
+        { pubkey: lendingMarket, isSigner: false, isWritable: false },
+        { pubkey: lendingMarketAuthority, isSigner: false, isWritable: false },
+        { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
+        { pubkey: TOKEN_PROGRAM_ID, isSigner: false, isWritable: false },
+    ];
+
+    return new TransactionInstruction({
+        keys,
+        programId: LENDING_PROGRAM_ID,
+        data,
+    });
+};
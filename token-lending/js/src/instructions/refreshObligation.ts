@@ -12,7 +12,8 @@ const DataLayout = struct<Data>([u8('instruction')]);
 export const refreshObligationInstruction = (
     obligation: PublicKey,
     depositReserves: PublicKey[],
-    borrowReserves: PublicKey[]
+    borrowReserves: PublicKey[],
+    lendingMarket?: PublicKey
 ): TransactionInstruction => {
     const data = Buffer.alloc(DataLayout.span);
     DataLayout.encode({ instruction: LendingInstruction.RefreshObligation }, data);
@@ -30,6 +31,11 @@ export const refreshObligationInstruction = (
         keys.push({ pubkey: borrowReserve, isSigner: false, isWritable: false });
     }
 
+    // required if the obligation is in an elevation group
+    if (lendingMarket) {
+        keys.push({ pubkey: lendingMarket, isSigner: false, isWritable: false });
+    }
+
     return new TransactionInstruction({
         keys,
         programId: LENDING_PROGRAM_ID,
@@ -9,7 +9,11 @@ interface Data {
 
 const DataLayout = struct<Data>([u8('instruction')]);
 
-export const refreshReserveInstruction = (reserve: PublicKey, oracle: PublicKey): TransactionInstruction => {
+export const refreshReserveInstruction = (
+    reserve: PublicKey,
+    oracle: PublicKey,
+    fallbackOracle?: PublicKey
+): TransactionInstruction => {
     const data = Buffer.alloc(DataLayout.span);
     DataLayout.encode({ instruction: LendingInstruction.RefreshReserve }, data);
 
@@ -19,6 +23,10 @@ export const refreshReserveInstruction = (reserve: PublicKey, oracle: PublicKey)
         { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
     ];
 
+    if (fallbackOracle) {
+        keys.push({ pubkey: fallbackOracle, isSigner: false, isWritable: false });
+    }
+
     return new TransactionInstruction({
         keys,
         programId: LENDING_PROGRAM_ID,
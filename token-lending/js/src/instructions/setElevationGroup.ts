@@ -0,0 +1,48 @@
+import { PublicKey, TransactionInstruction } from '@solana/web3.js';
+import { struct, u8 } from 'buffer-layout';
+import { LENDING_PROGRAM_ID } from '../constants';
+import { LendingInstruction } from './instruction';
+
+interface Data {
+    instruction: number;
+    elevationGroup: number;
+    loanToValueRatio: number;
+    liquidationThreshold: number;
+}
+
+const DataLayout = struct<Data>([
+    u8('instruction'),
+    u8('elevationGroup'),
+    u8('loanToValueRatio'),
+    u8('liquidationThreshold'),
+]);
+
+export const setElevationGroupInstruction = (
+    elevationGroup: number,
+    loanToValueRatio: number,
+    liquidationThreshold: number,
+    lendingMarket: PublicKey,
+    lendingMarketOwner: PublicKey
+): TransactionInstruction => {
+    const data = Buffer.alloc(DataLayout.span);
+    DataLayout.encode(
+        {
+            instruction: LendingInstruction.SetElevationGroup,
+            elevationGroup,
+            loanToValueRatio,
+            liquidationThreshold,
+        },
+        data
+    );
+
+    const keys = [
+        { pubkey: lendingMarket, isSigner: false, isWritable: true },
+        { pubkey: lendingMarketOwner, isSigner: true, isWritable: false },
+    ];
+
+    return new TransactionInstruction({
+        keys,
+        programId: LENDING_PROGRAM_ID,
+        data,
+    });
+};
@@ -0,0 +1,76 @@
+import { AccountMeta, PublicKey, SYSVAR_CLOCK_PUBKEY, TransactionInstruction } from '@solana/web3.js';
+import { struct, u8 } from 'buffer-layout';
+import { LENDING_PROGRAM_ID } from '../constants';
+import { OracleSource } from '../state';
+import { bool } from '../util';
+import { LendingInstruction } from './instruction';
+
+export type OracleAccounts =
+    | { source: OracleSource.Pyth; product: PublicKey; price: PublicKey }
+    | { source: OracleSource.Switchboard; aggregator: PublicKey }
+    | { source: OracleSource.FixedPrice; price: PublicKey };
+
+interface Data {
+    instruction: number;
+    oracleSource: OracleSource;
+    hasFallbackOracle: boolean;
+    fallbackOracleSource: OracleSource;
+}
+
+const DataLayout = struct<Data>([
+    u8('instruction'),
+    u8('oracleSource'),
+    bool('hasFallbackOracle'),
+    u8('fallbackOracleSource'),
+]);
+
+const oracleKeys = (oracle: OracleAccounts): AccountMeta[] => {
+    switch (oracle.source) {
+        case OracleSource.Pyth:
+            return [
+                { pubkey: oracle.price, isSigner: false, isWritable: false },
+                { pubkey: oracle.product, isSigner: false, isWritable: false },
+            ];
+        case OracleSource.Switchboard:
+            return [{ pubkey: oracle.aggregator, isSigner: false, isWritable: false }];
+        case OracleSource.FixedPrice:
+            return [{ pubkey: oracle.price, isSigner: false, isWritable: false }];
+    }
+};
+
+export const setReserveOracleInstruction = (
+    reserve: PublicKey,
+    lendingMarket: PublicKey,
+    lendingMarketOwner: PublicKey,
+    oracle: OracleAccounts,
+    fallbackOracle?: OracleAccounts
+): TransactionInstruction => {
+    const data = Buffer.alloc(DataLayout.span);
+    DataLayout.encode(
+        {
+            instruction: LendingInstruction.SetReserveOracle,
+            oracleSource: oracle.source,
+            hasFallbackOracle: !!fallbackOracle,
+            fallbackOracleSource: fallbackOracle ? fallbackOracle.source : OracleSource.Pyth,
+        },
+        data
+    );
+
+    const keys = [
+        { pubkey: reserve, isSigner: false, isWritable: true },
+        { pubkey: lendingMarket, isSigner: false, isWritable: false },
+        { pubkey: lendingMarketOwner, isSigner: true, isWritable: false },
+        { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
+        ...oracleKeys(oracle),
+    ];
+
+    if (fallbackOracle) {
+        keys.push(...oracleKeys(fallbackOracle));
+    }
+
+    return new TransactionInstruction({
+        keys,
+        programId: LENDING_PROGRAM_ID,
+        data,
+    });
+};
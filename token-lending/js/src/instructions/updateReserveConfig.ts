@@ -0,0 +1,41 @@
+import { PublicKey, SYSVAR_CLOCK_PUBKEY, TransactionInstruction } from '@solana/web3.js';
+import { struct, u8 } from 'buffer-layout';
+import { LENDING_PROGRAM_ID } from '../constants';
+import { ReserveConfig, ReserveConfigLayout } from '../state';
+import { LendingInstruction } from './instruction';
+
+interface Data {
+    instruction: number;
+    config: ReserveConfig;
+}
+
+const DataLayout = struct<Data>([u8('instruction'), ReserveConfigLayout]);
+
+export const updateReserveConfigInstruction = (
+    config: ReserveConfig,
+    reserve: PublicKey,
+    lendingMarket: PublicKey,
+    lendingMarketOwner: PublicKey
+): TransactionInstruction => {
+    const data = Buffer.alloc(DataLayout.span);
+    DataLayout.encode(
+        {
+            instruction: LendingInstruction.UpdateReserveConfig,
+            config,
+        },
+        data
+    );
+
+    const keys = [
+        { pubkey: reserve, isSigner: false, isWritable: true },
+        { pubkey: lendingMarket, isSigner: false, isWritable: false },
+        { pubkey: lendingMarketOwner, isSigner: true, isWritable: false },
+        { pubkey: SYSVAR_CLOCK_PUBKEY, isSigner: false, isWritable: false },
+    ];
+
+    return new TransactionInstruction({
+        keys,
+        programId: LENDING_PROGRAM_ID,
+        data,
+    });
+};
@@ -1,5 +1,5 @@
 import { AccountInfo, PublicKey } from '@solana/web3.js';
-import { blob, struct, u8 } from 'buffer-layout';
+import { blob, seq, struct, u8 } from 'buffer-layout';
 import { Parser, publicKey } from '../util';
 
 export interface LendingMarket {
@@ -9,8 +9,22 @@ export interface LendingMarket {
     quoteCurrency: Buffer;
     tokenProgramId: PublicKey;
     oracleProgramId: PublicKey;
+    elevationGroups: ElevationGroup[];
 }
 
+export interface ElevationGroup {
+    loanToValueRatio: number;
+    liquidationThreshold: number;
+}
+
+export const MAX_ELEVATION_GROUPS = 8;
+
+/** @internal */
+export const ElevationGroupLayout = struct<ElevationGroup>(
+    [u8('loanToValueRatio'), u8('liquidationThreshold')],
+    'elevationGroup'
+);
+
 /** @internal */
 export const LendingMarketLayout = struct<LendingMarket>(
     [
@@ -20,7 +34,8 @@ export const LendingMarketLayout = struct<LendingMarket>(
         blob(32, 'quoteCurrency'),
         publicKey('tokenProgramId'),
         publicKey('oracleProgramId'),
-        blob(128, 'padding'),
+        seq(ElevationGroupLayout, MAX_ELEVATION_GROUPS, 'elevationGroups'),
+        blob(112, 'padding'),
     ],
     'lendingMarket'
 );
@@ -15,6 +15,7 @@ export interface Obligation {
     borrowedValue: BigNumber;
     allowedBorrowValue: BigNumber;
     unhealthyBorrowValue: BigNumber;
+    elevationGroup: number;
 }
 
 export interface ObligationCollateral {
@@ -43,6 +44,7 @@ export interface ObligationDataFlat {
     depositsLen: number;
     borrowsLen: number;
     dataFlat: Buffer;
+    elevationGroup: number;
 }
 
 /** @internal */
@@ -76,6 +78,8 @@ export const ObligationLayout = struct<ObligationDataFlat>(
         u8('depositsLen'),
         u8('borrowsLen'),
         blob(ObligationCollateralLayout.span + 9 * ObligationLiquidityLayout.span, 'dataFlat'),
+        u8('elevationGroup'),
+        blob(63, 'padding'),
     ],
     'obligation'
 );
@@ -102,6 +106,7 @@ export const parseObligation: Parser<Obligation> = (pubkey: PublicKey, info: Acc
         depositsLen,
         borrowsLen,
         dataFlat,
+        elevationGroup,
     } = ObligationLayout.decode(buffer);
 
     if (!version) return;
@@ -126,6 +131,7 @@ export const parseObligation: Parser<Obligation> = (pubkey: PublicKey, info: Acc
         unhealthyBorrowValue,
         deposits,
         borrows,
+        elevationGroup,
     };
 
     return {
@@ -1,6 +1,6 @@
 import { AccountInfo, PublicKey } from '@solana/web3.js';
 import BigNumber from 'bignumber.js';
-import { blob, struct, u8 } from 'buffer-layout';
+import { blob, seq, struct, u16, u8 } from 'buffer-layout';
 import { decimal, Parser, publicKey, u64 } from '../util';
 import { LastUpdate, LastUpdateLayout } from './lastUpdate';
 
@@ -11,6 +11,15 @@ export interface Reserve {
     liquidity: ReserveLiquidity;
     collateral: ReserveCollateral;
     config: ReserveConfig;
+    oracleSource: OracleSource;
+    fallbackOracle: PublicKey;
+    fallbackOracleSource: OracleSource;
+}
+
+export enum OracleSource {
+    Pyth = 0,
+    Switchboard = 1,
+    FixedPrice = 2,
 }
 
 export interface ReserveLiquidity {
@@ -23,6 +32,7 @@ export interface ReserveLiquidity {
     borrowedAmountWads: BigNumber;
     cumulativeBorrowRateWads: BigNumber;
     marketPrice: BigNumber;
+    accumulatedProtocolFeesWads: BigNumber;
 }
 
 export interface ReserveCollateral {
@@ -32,16 +42,33 @@ export interface ReserveCollateral {
 }
 
 export interface ReserveConfig {
-    optimalUtilizationRate: number;
     loanToValueRatio: number;
     liquidationBonus: number;
     liquidationThreshold: number;
-    minBorrowRate: number;
-    optimalBorrowRate: number;
-    maxBorrowRate: number;
+    borrowRateCurve: BorrowRateCurve;
     fees: ReserveFees;
+    maxPriceConfidenceBps: number;
+    depositLimit: bigint;
+    borrowLimit: bigint;
+    elevationGroup: number;
+    protocolTakeRate: number;
+}
+
+export interface BorrowRateCurvePoint {
+    utilizationRateBps: number;
+    borrowRateBps: number;
+}
+
+/** Only the first `pointsLen` points are used; `points` always has `MAX_BORROW_RATE_CURVE_POINTS` entries */
+export interface BorrowRateCurve {
+    pointsLen: number;
+    points: BorrowRateCurvePoint[];
 }
 
+export const MAX_BORROW_RATE_CURVE_POINTS = 11;
+
+export const RESERVE_CONFIG_VERSION = 1;
+
 export interface ReserveFees {
     borrowFeeWad: bigint;
     flashLoanFeeWad: bigint;
@@ -49,7 +76,10 @@ export interface ReserveFees {
 }
 
 /** @internal */
-export const ReserveLiquidityLayout = struct<ReserveLiquidity>(
+export type ReserveAccountLiquidity = Omit<ReserveLiquidity, 'accumulatedProtocolFeesWads'>;
+
+/** @internal */
+export const ReserveLiquidityLayout = struct<ReserveAccountLiquidity>(
     [
         publicKey('mintPubkey'),
         u8('mintDecimals'),
@@ -76,34 +106,144 @@ export const ReserveFeesLayout = struct<ReserveFees>(
     'fees'
 );
 
+/** @internal */
+export const BorrowRateCurvePointLayout = struct<BorrowRateCurvePoint>(
+    [u16('utilizationRateBps'), u16('borrowRateBps')],
+    'point'
+);
+
+/** @internal */
+export const BorrowRateCurveLayout = struct<BorrowRateCurve>(
+    [u8('pointsLen'), seq(BorrowRateCurvePointLayout, MAX_BORROW_RATE_CURVE_POINTS, 'points')],
+    'borrowRateCurve'
+);
+
 /** @internal */
 export const ReserveConfigLayout = struct<ReserveConfig>(
     [
-        u8('optimalUtilizationRate'),
         u8('loanToValueRatio'),
         u8('liquidationBonus'),
         u8('liquidationThreshold'),
-        u8('minBorrowRate'),
-        u8('optimalBorrowRate'),
-        u8('maxBorrowRate'),
+        BorrowRateCurveLayout,
         ReserveFeesLayout,
+        u16('maxPriceConfidenceBps'),
+        u64('depositLimit'),
+        u64('borrowLimit'),
+        u8('elevationGroup'),
+        u8('protocolTakeRate'),
     ],
     'config'
 );
 
 /** @internal */
-export const ReserveLayout = struct<Reserve>([
+export interface ReserveAccountConfig
+    extends Omit<
+        ReserveConfig,
+        'borrowRateCurve' | 'depositLimit' | 'borrowLimit' | 'elevationGroup' | 'protocolTakeRate'
+    > {
+    legacyOptimalUtilizationRate: number;
+    legacyMinBorrowRate: number;
+    legacyOptimalBorrowRate: number;
+    legacyMaxBorrowRate: number;
+}
+
+/** @internal */
+export interface ReserveAccount extends Omit<Reserve, 'liquidity' | 'config'> {
+    liquidity: ReserveAccountLiquidity;
+    config: ReserveAccountConfig;
+    depositLimit: bigint;
+    borrowLimit: bigint;
+    elevationGroup: number;
+    configVersion: number;
+    borrowRateCurve: BorrowRateCurve;
+    protocolTakeRate: number;
+    accumulatedProtocolFeesWads: BigNumber;
+}
+
+/** @internal */
+export const ReserveAccountConfigLayout = struct<ReserveAccountConfig>(
+    [
+        u8('legacyOptimalUtilizationRate'),
+        u8('loanToValueRatio'),
+        u8('liquidationBonus'),
+        u8('liquidationThreshold'),
+        u8('legacyMinBorrowRate'),
+        u8('legacyOptimalBorrowRate'),
+        u8('legacyMaxBorrowRate'),
+        ReserveFeesLayout,
+        u16('maxPriceConfidenceBps'),
+    ],
+    'config'
+);
+
+/**
+ * Reserve account layout. Config values added after the oracle sources are stored after them, so they are decoded
+ * separately and moved into the config by `parseReserve`.
+ * @internal
+ */
+export const ReserveLayout = struct<ReserveAccount>([
     u8('version'),
     LastUpdateLayout,
     publicKey('lendingMarket'),
     ReserveLiquidityLayout,
     ReserveCollateralLayout,
-    ReserveConfigLayout,
-    blob(248, 'padding'),
+    ReserveAccountConfigLayout,
+    u8('oracleSource'),
+    publicKey('fallbackOracle'),
+    u8('fallbackOracleSource'),
+    u64('depositLimit'),
+    u64('borrowLimit'),
+    u8('elevationGroup'),
+    u8('configVersion'),
+    BorrowRateCurveLayout,
+    u8('protocolTakeRate'),
+    decimal('accumulatedProtocolFeesWads'),
+    blob(132, 'padding'),
 ]);
 
 export const RESERVE_SIZE = ReserveLayout.span;
 
+/** Creates a borrow rate curve from (utilization rate, borrow rate) points, in basis points */
+export const borrowRateCurve = (points: [number, number][]): BorrowRateCurve => {
+    if (points.length > MAX_BORROW_RATE_CURVE_POINTS) {
+        throw new Error(`Borrow rate curve cannot have more than ${MAX_BORROW_RATE_CURVE_POINTS} points`);
+    }
+
+    const curvePoints: BorrowRateCurvePoint[] = [];
+    for (let i = 0; i < MAX_BORROW_RATE_CURVE_POINTS; i++) {
+        const [utilizationRateBps, borrowRateBps] = points[i] || [0, 0];
+        curvePoints.push({ utilizationRateBps, borrowRateBps });
+    }
+
+    return { pointsLen: points.length, points: curvePoints };
+};
+
+// Reserve config version 0 stores a two-segment curve as percentages
+const legacyBorrowRateCurve = (
+    optimalUtilizationRate: number,
+    minBorrowRate: number,
+    optimalBorrowRate: number,
+    maxBorrowRate: number
+): BorrowRateCurve => {
+    if (optimalUtilizationRate === 0) {
+        return borrowRateCurve([
+            [0, optimalBorrowRate * 100],
+            [10000, maxBorrowRate * 100],
+        ]);
+    }
+    if (optimalUtilizationRate >= 100) {
+        return borrowRateCurve([
+            [0, minBorrowRate * 100],
+            [10000, optimalBorrowRate * 100],
+        ]);
+    }
+    return borrowRateCurve([
+        [0, minBorrowRate * 100],
+        [optimalUtilizationRate * 100, optimalBorrowRate * 100],
+        [10000, maxBorrowRate * 100],
+    ]);
+};
+
 export const isReserve = (info: AccountInfo<Buffer>): boolean => {
     return info.data.length === RESERVE_SIZE;
 };
@@ -112,9 +252,48 @@ export const parseReserve: Parser<Reserve> = (pubkey: PublicKey, info: AccountIn
     if (!isReserve(info)) return;
 
     const buffer = Buffer.from(info.data);
-    const reserve = ReserveLayout.decode(buffer);
+    const {
+        liquidity,
+        config,
+        depositLimit,
+        borrowLimit,
+        elevationGroup,
+        configVersion,
+        borrowRateCurve,
+        protocolTakeRate,
+        accumulatedProtocolFeesWads,
+        ...account
+    } = ReserveLayout.decode(buffer);
+
+    if (!account.version) return;
 
-    if (!reserve.version) return;
+    const {
+        legacyOptimalUtilizationRate,
+        legacyMinBorrowRate,
+        legacyOptimalBorrowRate,
+        legacyMaxBorrowRate,
+        ...reserveConfig
+    } = config;
+
+    const reserve: Reserve = {
+        ...account,
+        liquidity: { ...liquidity, accumulatedProtocolFeesWads },
+        config: {
+            ...reserveConfig,
+            borrowRateCurve: configVersion
+                ? borrowRateCurve
+                : legacyBorrowRateCurve(
+                      legacyOptimalUtilizationRate,
+                      legacyMinBorrowRate,
+                      legacyOptimalBorrowRate,
+                      legacyMaxBorrowRate
+                  ),
+            depositLimit,
+            borrowLimit,
+            elevationGroup,
+            protocolTakeRate,
+        },
+    };
 
     return {
         pubkey,
@@ -159,7 +159,23 @@ pub enum LendingError {
     /// Not enough liquidity after flash loan
     #[error("Not enough liquidity after flash loan")]
     NotEnoughLiquidityAfterFlashLoan,
+
     // 45
+    /// Oracle price confidence interval is too wide
+    #[error("Oracle price confidence interval is too wide")]
+    OraclePriceConfidenceTooWide,
+    /// Deposit would exceed the reserve deposit limit
+    #[error("Deposit would exceed the reserve deposit limit")]
+    DepositLimitExceeded,
+    /// Borrow would exceed the reserve borrow limit
+    #[error("Borrow would exceed the reserve borrow limit")]
+    BorrowLimitExceeded,
+    /// Elevation group is out of range or not configured
+    #[error("Elevation group is invalid")]
+    InvalidElevationGroup,
+    /// Reserve is not in the obligation's elevation group
+    #[error("Reserve is not in the obligation elevation group")]
+    ElevationGroupMismatch,
 }
 
 impl From<LendingError> for ProgramError {
@@ -2,14 +2,16 @@
 
 use crate::{
     error::LendingError,
-    state::{ReserveConfig, ReserveFees},
+    oracle::OracleSource,
+    state::{BorrowRateCurve, ReserveConfig, ReserveFees, MAX_BORROW_RATE_CURVE_POINTS},
 };
+use num_traits::FromPrimitive;
 use solana_program::{
     instruction::{AccountMeta, Instruction},
     msg,
     program_error::ProgramError,
     pubkey::{Pubkey, PUBKEY_BYTES},
-    sysvar,
+    system_program, sysvar,
 };
 use std::{convert::TryInto, mem::size_of};
 
@@ -83,8 +85,10 @@ pub enum LendingInstruction {
     ///
     ///   0. `[writable]` Reserve account.
     ///   1. `[]` Reserve liquidity oracle account.
-    ///             Must be the Pyth price account specified at InitReserve.
+    ///             Must be the oracle account specified at InitReserve or SetReserveOracle.
     ///   2. `[]` Clock sysvar.
+    ///   3. `[optional]` Reserve liquidity fallback oracle account.
+    ///             Read when the price from the reserve liquidity oracle is unavailable.
     RefreshReserve,
 
     // 4
@@ -154,6 +158,7 @@ pub enum LendingInstruction {
     ///   1. `[]` Clock sysvar.
     ///   .. `[]` Collateral deposit reserve accounts - refreshed, all, in order.
     ///   .. `[]` Liquidity borrow reserve accounts - refreshed, all, in order.
+    ///   .. `[]` Lending market account, if the obligation is in an elevation group.
     RefreshObligation,
 
     // 8
@@ -308,6 +313,111 @@ pub enum LendingInstruction {
         /// The amount that is to be borrowed - u64::MAX for up to 100% of available liquidity
         amount: u64,
     },
+
+    // 14
+    /// Updates the configuration of a lending market reserve. Interest is accrued at the previous
+    /// rates up to the current slot, and the reserve must be refreshed before it can be used again.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner.
+    ///   3. `[]` Clock sysvar.
+    UpdateReserveConfig {
+        /// Reserve configuration values
+        config: ReserveConfig,
+    },
+
+    // 15
+    /// Sets the oracle and optional fallback oracle used to price a lending market reserve's
+    /// liquidity. The reserve must be refreshed before it can be used again.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Lending market owner.
+    ///   3. `[]` Clock sysvar.
+    ///   4. `[]` Oracle account - Pyth price, Switchboard aggregator, or fixed price account.
+    ///             This will be used as the reserve liquidity oracle account.
+    ///   5. `[]` Pyth product account, if the oracle source is Pyth.
+    ///   .. `[]` Fallback oracle account, if a fallback oracle source is provided.
+    ///             This will be used as the reserve liquidity fallback oracle account.
+    ///   .. `[]` Fallback Pyth product account, if the fallback oracle source is Pyth.
+    SetReserveOracle {
+        /// Provider of the oracle account
+        oracle_source: OracleSource,
+        /// Provider of the fallback oracle account, none to remove the fallback oracle
+        fallback_oracle_source: Option<OracleSource>,
+    },
+
+    // 16
+    /// Sets the loan to value ratio and liquidation threshold of a lending market elevation group.
+    /// Obligations whose deposits and borrows are all in the group use these values instead of
+    /// each reserve's own once they are refreshed.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Lending market account.
+    ///   1. `[signer]` Lending market owner.
+    SetElevationGroup {
+        /// Elevation group id, in range [1, MAX_ELEVATION_GROUPS]
+        elevation_group: u8,
+        /// Target ratio of the value of borrows to deposits, as a percentage
+        loan_to_value_ratio: u8,
+        /// Loan to value ratio at which an obligation can be liquidated, as a percentage
+        /// 0 to remove the elevation group
+        liquidation_threshold: u8,
+    },
+
+    // 17
+    /// Opts an obligation in to an elevation group, or out of its elevation group. Requires a
+    /// refreshed obligation, and every obligation deposit and borrow reserve to be in the group.
+    /// The obligation cannot leave or join a group if its borrows would exceed its new allowed
+    /// borrow value.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account - refreshed.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Obligation owner.
+    ///   3. `[]` Clock sysvar.
+    ///   .. `[]` Collateral deposit reserve accounts - all, in order.
+    ///   .. `[]` Liquidity borrow reserve accounts - all, in order.
+    SetObligationElevationGroup {
+        /// Elevation group id, 0 to leave the obligation's elevation group
+        elevation_group: u8,
+    },
+
+    // 18
+    /// Redeem protocol fees accumulated from accrued interest to the reserve liquidity fee
+    /// receiver, up to the reserve's available liquidity. Requires a refreshed reserve.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Reserve account - refreshed.
+    ///   1. `[writable]` Reserve liquidity fee receiver account.
+    ///   2. `[writable]` Reserve liquidity supply SPL Token account.
+    ///   3. `[]` Lending market account.
+    ///   4. `[]` Derived lending market authority.
+    ///   5. `[]` Clock sysvar.
+    ///   6. `[]` Token program id.
+    RedeemFees,
+
+    // 19
+    /// Grows an obligation created before elevation groups to the current obligation length, so
+    /// that it can join an elevation group. The payer funds the additional rent-exemption.
+    ///
+    /// Accounts expected by this instruction:
+    ///
+    ///   0. `[writable]` Obligation account.
+    ///   1. `[]` Lending market account.
+    ///   2. `[signer]` Obligation owner.
+    ///   3. `[writable, signer]` Payer of the additional rent-exemption.
+    ///   4. `[]` Rent sysvar.
+    ///   5. `[]` System program id.
+    ReallocObligation,
 }
 
 impl LendingInstruction {
@@ -331,32 +441,10 @@ impl LendingInstruction {
             }
             2 => {
                 let (liquidity_amount, rest) = Self::unpack_u64(rest)?;
-                let (optimal_utilization_rate, rest) = Self::unpack_u8(rest)?;
-                let (loan_to_value_ratio, rest) = Self::unpack_u8(rest)?;
-                let (liquidation_bonus, rest) = Self::unpack_u8(rest)?;
-                let (liquidation_threshold, rest) = Self::unpack_u8(rest)?;
-                let (min_borrow_rate, rest) = Self::unpack_u8(rest)?;
-                let (optimal_borrow_rate, rest) = Self::unpack_u8(rest)?;
-                let (max_borrow_rate, rest) = Self::unpack_u8(rest)?;
-                let (borrow_fee_wad, rest) = Self::unpack_u64(rest)?;
-                let (flash_loan_fee_wad, rest) = Self::unpack_u64(rest)?;
-                let (host_fee_percentage, _rest) = Self::unpack_u8(rest)?;
+                let (config, _rest) = Self::unpack_reserve_config(rest)?;
                 Self::InitReserve {
                     liquidity_amount,
-                    config: ReserveConfig {
-                        optimal_utilization_rate,
-                        loan_to_value_ratio,
-                        liquidation_bonus,
-                        liquidation_threshold,
-                        min_borrow_rate,
-                        optimal_borrow_rate,
-                        max_borrow_rate,
-                        fees: ReserveFees {
-                            borrow_fee_wad,
-                            flash_loan_fee_wad,
-                            host_fee_percentage,
-                        },
-                    },
+                    config,
                 }
             }
             3 => Self::RefreshReserve,
@@ -394,6 +482,42 @@ impl LendingInstruction {
                 let (amount, _rest) = Self::unpack_u64(rest)?;
                 Self::FlashLoan { amount }
             }
+            14 => {
+                let (config, _rest) = Self::unpack_reserve_config(rest)?;
+                Self::UpdateReserveConfig { config }
+            }
+            15 => {
+                let (oracle_source, rest) = Self::unpack_oracle_source(rest)?;
+                let (has_fallback_oracle, rest) = Self::unpack_u8(rest)?;
+                let fallback_oracle_source = match has_fallback_oracle {
+                    0 => None,
+                    1 => Some(Self::unpack_oracle_source(rest)?.0),
+                    _ => {
+                        msg!("Fallback oracle source cannot be unpacked");
+                        return Err(LendingError::InstructionUnpackError.into());
+                    }
+                };
+                Self::SetReserveOracle {
+                    oracle_source,
+                    fallback_oracle_source,
+                }
+            }
+            16 => {
+                let (elevation_group, rest) = Self::unpack_u8(rest)?;
+                let (loan_to_value_ratio, rest) = Self::unpack_u8(rest)?;
+                let (liquidation_threshold, _rest) = Self::unpack_u8(rest)?;
+                Self::SetElevationGroup {
+                    elevation_group,
+                    loan_to_value_ratio,
+                    liquidation_threshold,
+                }
+            }
+            17 => {
+                let (elevation_group, _rest) = Self::unpack_u8(rest)?;
+                Self::SetObligationElevationGroup { elevation_group }
+            }
+            18 => Self::RedeemFees,
+            19 => Self::ReallocObligation,
             _ => {
                 msg!("Instruction cannot be unpacked");
                 return Err(LendingError::InstructionUnpackError.into());
@@ -401,6 +525,67 @@ impl LendingInstruction {
         })
     }
 
+    fn unpack_reserve_config(input: &[u8]) -> Result<(ReserveConfig, &[u8]), ProgramError> {
+        let (loan_to_value_ratio, rest) = Self::unpack_u8(input)?;
+        let (liquidation_bonus, rest) = Self::unpack_u8(rest)?;
+        let (liquidation_threshold, rest) = Self::unpack_u8(rest)?;
+        let (borrow_rate_curve, rest) = Self::unpack_borrow_rate_curve(rest)?;
+        let (borrow_fee_wad, rest) = Self::unpack_u64(rest)?;
+        let (flash_loan_fee_wad, rest) = Self::unpack_u64(rest)?;
+        let (host_fee_percentage, rest) = Self::unpack_u8(rest)?;
+        let (max_price_confidence_bps, rest) = Self::unpack_u16(rest)?;
+        let (deposit_limit, rest) = Self::unpack_u64(rest)?;
+        let (borrow_limit, rest) = Self::unpack_u64(rest)?;
+        let (elevation_group, rest) = Self::unpack_u8(rest)?;
+        let (protocol_take_rate, rest) = Self::unpack_u8(rest)?;
+        Ok((
+            ReserveConfig {
+                loan_to_value_ratio,
+                liquidation_bonus,
+                liquidation_threshold,
+                borrow_rate_curve,
+                fees: ReserveFees {
+                    borrow_fee_wad,
+                    flash_loan_fee_wad,
+                    host_fee_percentage,
+                },
+                max_price_confidence_bps,
+                deposit_limit,
+                borrow_limit,
+                elevation_group,
+                protocol_take_rate,
+            },
+            rest,
+        ))
+    }
+
+    fn unpack_borrow_rate_curve(input: &[u8]) -> Result<(BorrowRateCurve, &[u8]), ProgramError> {
+        let (points_len, mut rest) = Self::unpack_u8(input)?;
+        if points_len as usize > MAX_BORROW_RATE_CURVE_POINTS {
+            msg!("Borrow rate curve cannot be unpacked");
+            return Err(LendingError::InstructionUnpackError.into());
+        }
+
+        let mut points = Vec::with_capacity(MAX_BORROW_RATE_CURVE_POINTS);
+        for _ in 0..MAX_BORROW_RATE_CURVE_POINTS {
+            let (utilization_rate_bps, next) = Self::unpack_u16(rest)?;
+            let (borrow_rate_bps, next) = Self::unpack_u16(next)?;
+            points.push((utilization_rate_bps, borrow_rate_bps));
+            rest = next;
+        }
+        let borrow_rate_curve = BorrowRateCurve::new(&points[..points_len as usize])?;
+        Ok((borrow_rate_curve, rest))
+    }
+
+    fn unpack_oracle_source(input: &[u8]) -> Result<(OracleSource, &[u8]), ProgramError> {
+        let (oracle_source, rest) = Self::unpack_u8(input)?;
+        let oracle_source = OracleSource::from_u8(oracle_source).ok_or_else(|| {
+            msg!("Oracle source cannot be unpacked");
+            LendingError::InstructionUnpackError
+        })?;
+        Ok((oracle_source, rest))
+    }
+
     fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
         if input.len() < 8 {
             msg!("u64 cannot be unpacked");
@@ -415,6 +600,20 @@ impl LendingInstruction {
         Ok((value, rest))
     }
 
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        if input.len() < 2 {
+            msg!("u16 cannot be unpacked");
+            return Err(LendingError::InstructionUnpackError.into());
+        }
+        let (bytes, rest) = input.split_at(2);
+        let value = bytes
+            .get(..2)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u16::from_le_bytes)
+            .ok_or(LendingError::InstructionUnpackError)?;
+        Ok((value, rest))
+    }
+
     fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
         if input.is_empty() {
             msg!("u8 cannot be unpacked");
@@ -471,35 +670,11 @@ impl LendingInstruction {
             }
             Self::InitReserve {
                 liquidity_amount,
-                config:
-                    ReserveConfig {
-                        optimal_utilization_rate,
-                        loan_to_value_ratio,
-                        liquidation_bonus,
-                        liquidation_threshold,
-                        min_borrow_rate,
-                        optimal_borrow_rate,
-                        max_borrow_rate,
-                        fees:
-                            ReserveFees {
-                                borrow_fee_wad,
-                                flash_loan_fee_wad,
-                                host_fee_percentage,
-                            },
-                    },
+                config,
             } => {
                 buf.push(2);
                 buf.extend_from_slice(&liquidity_amount.to_le_bytes());
-                buf.extend_from_slice(&optimal_utilization_rate.to_le_bytes());
-                buf.extend_from_slice(&loan_to_value_ratio.to_le_bytes());
-                buf.extend_from_slice(&liquidation_bonus.to_le_bytes());
-                buf.extend_from_slice(&liquidation_threshold.to_le_bytes());
-                buf.extend_from_slice(&min_borrow_rate.to_le_bytes());
-                buf.extend_from_slice(&optimal_borrow_rate.to_le_bytes());
-                buf.extend_from_slice(&max_borrow_rate.to_le_bytes());
-                buf.extend_from_slice(&borrow_fee_wad.to_le_bytes());
-                buf.extend_from_slice(&flash_loan_fee_wad.to_le_bytes());
-                buf.extend_from_slice(&host_fee_percentage.to_le_bytes());
+                Self::pack_reserve_config(config, &mut buf);
             }
             Self::RefreshReserve => {
                 buf.push(3);
@@ -542,9 +717,90 @@ impl LendingInstruction {
                 buf.push(13);
                 buf.extend_from_slice(&amount.to_le_bytes());
             }
+            Self::UpdateReserveConfig { config } => {
+                buf.push(14);
+                Self::pack_reserve_config(config, &mut buf);
+            }
+            Self::SetReserveOracle {
+                oracle_source,
+                fallback_oracle_source,
+            } => {
+                buf.push(15);
+                buf.push(oracle_source as u8);
+                match fallback_oracle_source {
+                    Some(fallback_oracle_source) => {
+                        buf.push(1);
+                        buf.push(fallback_oracle_source as u8);
+                    }
+                    None => buf.push(0),
+                }
+            }
+            Self::SetElevationGroup {
+                elevation_group,
+                loan_to_value_ratio,
+                liquidation_threshold,
+            } => {
+                buf.push(16);
+                buf.extend_from_slice(&elevation_group.to_le_bytes());
+                buf.extend_from_slice(&loan_to_value_ratio.to_le_bytes());
+                buf.extend_from_slice(&liquidation_threshold.to_le_bytes());
+            }
+            Self::SetObligationElevationGroup { elevation_group } => {
+                buf.push(17);
+                buf.extend_from_slice(&elevation_group.to_le_bytes());
+            }
+            Self::RedeemFees => {
+                buf.push(18);
+            }
+            Self::ReallocObligation => {
+                buf.push(19);
+            }
         }
         buf
     }
+
+    fn pack_reserve_config(config: ReserveConfig, buf: &mut Vec<u8>) {
+        let ReserveConfig {
+            loan_to_value_ratio,
+            liquidation_bonus,
+            liquidation_threshold,
+            borrow_rate_curve,
+            fees:
+                ReserveFees {
+                    borrow_fee_wad,
+                    flash_loan_fee_wad,
+                    host_fee_percentage,
+                },
+            max_price_confidence_bps,
+            deposit_limit,
+            borrow_limit,
+            elevation_group,
+            protocol_take_rate,
+        } = config;
+        buf.extend_from_slice(&loan_to_value_ratio.to_le_bytes());
+        buf.extend_from_slice(&liquidation_bonus.to_le_bytes());
+        buf.extend_from_slice(&liquidation_threshold.to_le_bytes());
+        Self::pack_borrow_rate_curve(&borrow_rate_curve, buf);
+        buf.extend_from_slice(&borrow_fee_wad.to_le_bytes());
+        buf.extend_from_slice(&flash_loan_fee_wad.to_le_bytes());
+        buf.extend_from_slice(&host_fee_percentage.to_le_bytes());
+        buf.extend_from_slice(&max_price_confidence_bps.to_le_bytes());
+        buf.extend_from_slice(&deposit_limit.to_le_bytes());
+        buf.extend_from_slice(&borrow_limit.to_le_bytes());
+        buf.extend_from_slice(&elevation_group.to_le_bytes());
+        buf.extend_from_slice(&protocol_take_rate.to_le_bytes());
+    }
+
+    // unused points are packed as zeros, so the curve has a fixed length
+    fn pack_borrow_rate_curve(borrow_rate_curve: &BorrowRateCurve, buf: &mut Vec<u8>) {
+        let points = borrow_rate_curve.points();
+        buf.push(points.len() as u8);
+        for i in 0..MAX_BORROW_RATE_CURVE_POINTS {
+            let point = points.get(i).copied().unwrap_or_default();
+            buf.extend_from_slice(&point.utilization_rate_bps.to_le_bytes());
+            buf.extend_from_slice(&point.borrow_rate_bps.to_le_bytes());
+        }
+    }
 }
 
 /// Creates an 'InitLendingMarket' instruction.
@@ -647,12 +903,20 @@ pub fn refresh_reserve(
     program_id: Pubkey,
     reserve_pubkey: Pubkey,
     reserve_liquidity_oracle_pubkey: Pubkey,
+    reserve_liquidity_fallback_oracle_pubkey: Option<Pubkey>,
 ) -> Instruction {
-    let accounts = vec![
+    let mut accounts = vec![
         AccountMeta::new(reserve_pubkey, false),
         AccountMeta::new_readonly(reserve_liquidity_oracle_pubkey, false),
         AccountMeta::new_readonly(sysvar::clock::id(), false),
     ];
+    if let Some(reserve_liquidity_fallback_oracle_pubkey) = reserve_liquidity_fallback_oracle_pubkey
+    {
+        accounts.push(AccountMeta::new_readonly(
+            reserve_liquidity_fallback_oracle_pubkey,
+            false,
+        ));
+    }
     Instruction {
         program_id,
         accounts,
@@ -758,6 +1022,7 @@ pub fn refresh_obligation(
     program_id: Pubkey,
     obligation_pubkey: Pubkey,
     reserve_pubkeys: Vec<Pubkey>,
+    lending_market_pubkey: Option<Pubkey>,
 ) -> Instruction {
     let mut accounts = vec![
         AccountMeta::new(obligation_pubkey, false),
@@ -768,6 +1033,9 @@ pub fn refresh_obligation(
             .into_iter()
             .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
     );
+    if let Some(lending_market_pubkey) = lending_market_pubkey {
+        accounts.push(AccountMeta::new_readonly(lending_market_pubkey, false));
+    }
     Instruction {
         program_id,
         accounts,
@@ -982,6 +1250,216 @@ pub fn flash_loan(
     }
 }
 
+/// Creates an 'UpdateReserveConfig' instruction.
+pub fn update_reserve_config(
+    program_id: Pubkey,
+    config: ReserveConfig,
+    reserve_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+        ],
+        data: LendingInstruction::UpdateReserveConfig { config }.pack(),
+    }
+}
+
+/// Oracle accounts provided to a 'SetReserveOracle' instruction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OracleAccounts {
+    /// Pyth product and price accounts
+    Pyth {
+        /// Pyth product account
+        product_pubkey: Pubkey,
+        /// Pyth price account
+        price_pubkey: Pubkey,
+    },
+    /// Switchboard aggregator account
+    Switchboard {
+        /// Switchboard aggregator account
+        aggregator_pubkey: Pubkey,
+    },
+    /// Fixed price account
+    FixedPrice {
+        /// Fixed price account
+        price_pubkey: Pubkey,
+    },
+}
+
+impl OracleAccounts {
+    /// Provider of the oracle accounts
+    pub fn source(&self) -> OracleSource {
+        match self {
+            Self::Pyth { .. } => OracleSource::Pyth,
+            Self::Switchboard { .. } => OracleSource::Switchboard,
+            Self::FixedPrice { .. } => OracleSource::FixedPrice,
+        }
+    }
+
+    /// Account the price is read from, which is stored on the reserve
+    pub fn price_pubkey(&self) -> Pubkey {
+        match *self {
+            Self::Pyth { price_pubkey, .. } => price_pubkey,
+            Self::Switchboard { aggregator_pubkey } => aggregator_pubkey,
+            Self::FixedPrice { price_pubkey } => price_pubkey,
+        }
+    }
+
+    fn account_metas(&self) -> Vec<AccountMeta> {
+        match *self {
+            Self::Pyth {
+                product_pubkey,
+                price_pubkey,
+            } => vec![
+                AccountMeta::new_readonly(price_pubkey, false),
+                AccountMeta::new_readonly(product_pubkey, false),
+            ],
+            Self::Switchboard { aggregator_pubkey } => {
+                vec![AccountMeta::new_readonly(aggregator_pubkey, false)]
+            }
+            Self::FixedPrice { price_pubkey } => {
+                vec![AccountMeta::new_readonly(price_pubkey, false)]
+            }
+        }
+    }
+}
+
+/// Creates a 'SetReserveOracle' instruction.
+pub fn set_reserve_oracle(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    oracle: OracleAccounts,
+    fallback_oracle: Option<OracleAccounts>,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(reserve_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    accounts.extend(oracle.account_metas());
+    if let Some(fallback_oracle) = fallback_oracle {
+        accounts.extend(fallback_oracle.account_metas());
+    }
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::SetReserveOracle {
+            oracle_source: oracle.source(),
+            fallback_oracle_source: fallback_oracle.map(|oracle| oracle.source()),
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'SetElevationGroup' instruction.
+pub fn set_elevation_group(
+    program_id: Pubkey,
+    elevation_group: u8,
+    loan_to_value_ratio: u8,
+    liquidation_threshold: u8,
+    lending_market_pubkey: Pubkey,
+    lending_market_owner_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_owner_pubkey, true),
+        ],
+        data: LendingInstruction::SetElevationGroup {
+            elevation_group,
+            loan_to_value_ratio,
+            liquidation_threshold,
+        }
+        .pack(),
+    }
+}
+
+/// Creates a 'SetObligationElevationGroup' instruction.
+pub fn set_obligation_elevation_group(
+    program_id: Pubkey,
+    elevation_group: u8,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    reserve_pubkeys: Vec<Pubkey>,
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(obligation_pubkey, false),
+        AccountMeta::new_readonly(lending_market_pubkey, false),
+        AccountMeta::new_readonly(obligation_owner_pubkey, true),
+        AccountMeta::new_readonly(sysvar::clock::id(), false),
+    ];
+    accounts.extend(
+        reserve_pubkeys
+            .into_iter()
+            .map(|pubkey| AccountMeta::new_readonly(pubkey, false)),
+    );
+    Instruction {
+        program_id,
+        accounts,
+        data: LendingInstruction::SetObligationElevationGroup { elevation_group }.pack(),
+    }
+}
+
+/// Creates a 'RedeemFees' instruction.
+pub fn redeem_fees(
+    program_id: Pubkey,
+    reserve_pubkey: Pubkey,
+    reserve_liquidity_fee_receiver_pubkey: Pubkey,
+    reserve_liquidity_supply_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+) -> Instruction {
+    let (lending_market_authority_pubkey, _bump_seed) = Pubkey::find_program_address(
+        &[&lending_market_pubkey.to_bytes()[..PUBKEY_BYTES]],
+        &program_id,
+    );
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(reserve_pubkey, false),
+            AccountMeta::new(reserve_liquidity_fee_receiver_pubkey, false),
+            AccountMeta::new(reserve_liquidity_supply_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(lending_market_authority_pubkey, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+        ],
+        data: LendingInstruction::RedeemFees.pack(),
+    }
+}
+
+/// Creates a 'ReallocObligation' instruction.
+pub fn realloc_obligation(
+    program_id: Pubkey,
+    obligation_pubkey: Pubkey,
+    lending_market_pubkey: Pubkey,
+    obligation_owner_pubkey: Pubkey,
+    payer_pubkey: Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(obligation_pubkey, false),
+            AccountMeta::new_readonly(lending_market_pubkey, false),
+            AccountMeta::new_readonly(obligation_owner_pubkey, true),
+            AccountMeta::new(payer_pubkey, true),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: LendingInstruction::ReallocObligation.pack(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1039,18 +1517,26 @@ mod tests {
         let program_id = Pubkey::new_unique();
         let liquidity_amount = u64::MAX;
         let config = ReserveConfig {
-            optimal_utilization_rate: 50,
             loan_to_value_ratio: 1,
             liquidation_bonus: 10,
             liquidation_threshold: 5,
-            min_borrow_rate: 2,
-            optimal_borrow_rate: 4,
-            max_borrow_rate: 10,
+            borrow_rate_curve: BorrowRateCurve::new(&[
+                (0, 200),
+                (5_000, 400),
+                (9_000, 1_000),
+                (10_000, 15_000),
+            ])
+            .unwrap(),
             fees: ReserveFees {
                 borrow_fee_wad: 1,
                 flash_loan_fee_wad: 3,
                 host_fee_percentage: 1,
             },
+            max_price_confidence_bps: 100,
+            deposit_limit: 1_000_000,
+            borrow_limit: 500_000,
+            elevation_group: 1,
+            protocol_take_rate: 20,
         };
         let source_liquidity_pubkey = Pubkey::new_unique();
         let destination_collateral_pubkey = Pubkey::new_unique();
@@ -1100,13 +1586,38 @@ mod tests {
         let program_id = Pubkey::new_unique();
         let reserve_pubkey = Pubkey::new_unique();
         let reserve_liquidity_oracle_pubkey = Pubkey::new_unique();
-        let instruction =
-            refresh_reserve(program_id, reserve_pubkey, reserve_liquidity_oracle_pubkey);
+        let instruction = refresh_reserve(
+            program_id,
+            reserve_pubkey,
+            reserve_liquidity_oracle_pubkey,
+            None,
+        );
         assert_eq!(instruction.program_id, program_id);
         assert_eq!(instruction.accounts.len(), 3);
         assert_eq!(instruction.data, LendingInstruction::RefreshReserve.pack());
     }
 
+    #[test]
+    fn test_refresh_reserve_with_fallback_oracle() {
+        let program_id = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_oracle_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_fallback_oracle_pubkey = Pubkey::new_unique();
+        let instruction = refresh_reserve(
+            program_id,
+            reserve_pubkey,
+            reserve_liquidity_oracle_pubkey,
+            Some(reserve_liquidity_fallback_oracle_pubkey),
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_eq!(
+            instruction.accounts[3].pubkey,
+            reserve_liquidity_fallback_oracle_pubkey
+        );
+        assert_eq!(instruction.data, LendingInstruction::RefreshReserve.pack());
+    }
+
     #[test]
     fn test_deposit_reserve_liquidity() {
         let program_id = Pubkey::new_unique();
@@ -1189,7 +1700,7 @@ mod tests {
         let program_id = Pubkey::new_unique();
         let obligation_pubkey = Pubkey::new_unique();
         let reserve_pubkeys = vec![Pubkey::new_unique()];
-        let instruction = refresh_obligation(program_id, obligation_pubkey, reserve_pubkeys);
+        let instruction = refresh_obligation(program_id, obligation_pubkey, reserve_pubkeys, None);
         assert_eq!(instruction.program_id, program_id);
         assert_eq!(instruction.accounts.len(), 3);
         assert_eq!(
@@ -1198,6 +1709,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_refresh_obligation_with_lending_market() {
+        let program_id = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let reserve_pubkeys = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let lending_market_pubkey = Pubkey::new_unique();
+        let instruction = refresh_obligation(
+            program_id,
+            obligation_pubkey,
+            reserve_pubkeys,
+            Some(lending_market_pubkey),
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 5);
+        assert_eq!(instruction.accounts[4].pubkey, lending_market_pubkey);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::RefreshObligation.pack()
+        );
+    }
+
     #[test]
     fn test_deposit_obligation_collateral() {
         let program_id = Pubkey::new_unique();
@@ -1386,4 +1918,203 @@ mod tests {
             LendingInstruction::FlashLoan { amount }.pack()
         );
     }
+
+    #[test]
+    fn test_update_reserve_config() {
+        let program_id = Pubkey::new_unique();
+        let config = ReserveConfig {
+            loan_to_value_ratio: 50,
+            liquidation_bonus: 5,
+            liquidation_threshold: 55,
+            borrow_rate_curve: BorrowRateCurve::from_legacy(80, 0, 4, 30),
+            fees: ReserveFees {
+                borrow_fee_wad: 100_000_000_000,
+                flash_loan_fee_wad: 3_000_000_000_000_000,
+                host_fee_percentage: 20,
+            },
+            max_price_confidence_bps: 200,
+            deposit_limit: 0,
+            borrow_limit: u64::MAX,
+            elevation_group: 0,
+            protocol_take_rate: 10,
+        };
+        let reserve_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner_pubkey = Pubkey::new_unique();
+        let instruction = update_reserve_config(
+            program_id,
+            config,
+            reserve_pubkey,
+            lending_market_pubkey,
+            lending_market_owner_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 4);
+        assert_eq!(
+            instruction.data,
+            LendingInstruction::UpdateReserveConfig { config }.pack()
+        );
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::UpdateReserveConfig { config }
+        );
+    }
+
+    #[test]
+    fn test_set_reserve_oracle() {
+        let program_id = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let oracle = OracleAccounts::Pyth {
+            product_pubkey: Pubkey::new_unique(),
+            price_pubkey: Pubkey::new_unique(),
+        };
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner_pubkey = Pubkey::new_unique();
+        let instruction = set_reserve_oracle(
+            program_id,
+            reserve_pubkey,
+            oracle,
+            None,
+            lending_market_pubkey,
+            lending_market_owner_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_eq!(instruction.accounts[4].pubkey, oracle.price_pubkey());
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::SetReserveOracle {
+                oracle_source: OracleSource::Pyth,
+                fallback_oracle_source: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_reserve_oracle_with_fallback_oracle() {
+        let program_id = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let oracle = OracleAccounts::Switchboard {
+            aggregator_pubkey: Pubkey::new_unique(),
+        };
+        let fallback_oracle = OracleAccounts::FixedPrice {
+            price_pubkey: Pubkey::new_unique(),
+        };
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner_pubkey = Pubkey::new_unique();
+        let instruction = set_reserve_oracle(
+            program_id,
+            reserve_pubkey,
+            oracle,
+            Some(fallback_oracle),
+            lending_market_pubkey,
+            lending_market_owner_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_eq!(instruction.accounts[4].pubkey, oracle.price_pubkey());
+        assert_eq!(
+            instruction.accounts[5].pubkey,
+            fallback_oracle.price_pubkey()
+        );
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::SetReserveOracle {
+                oracle_source: OracleSource::Switchboard,
+                fallback_oracle_source: Some(OracleSource::FixedPrice),
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_elevation_group() {
+        let program_id = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let lending_market_owner_pubkey = Pubkey::new_unique();
+        let instruction = set_elevation_group(
+            program_id,
+            1,
+            90,
+            95,
+            lending_market_pubkey,
+            lending_market_owner_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 2);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::SetElevationGroup {
+                elevation_group: 1,
+                loan_to_value_ratio: 90,
+                liquidation_threshold: 95,
+            }
+        );
+    }
+
+    #[test]
+    fn test_set_obligation_elevation_group() {
+        let program_id = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let obligation_owner_pubkey = Pubkey::new_unique();
+        let reserve_pubkeys = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+        let instruction = set_obligation_elevation_group(
+            program_id,
+            2,
+            obligation_pubkey,
+            lending_market_pubkey,
+            obligation_owner_pubkey,
+            reserve_pubkeys,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::SetObligationElevationGroup { elevation_group: 2 }
+        );
+    }
+
+    #[test]
+    fn test_redeem_fees() {
+        let program_id = Pubkey::new_unique();
+        let reserve_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_fee_receiver_pubkey = Pubkey::new_unique();
+        let reserve_liquidity_supply_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let instruction = redeem_fees(
+            program_id,
+            reserve_pubkey,
+            reserve_liquidity_fee_receiver_pubkey,
+            reserve_liquidity_supply_pubkey,
+            lending_market_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 7);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::RedeemFees
+        );
+    }
+
+    #[test]
+    fn test_realloc_obligation() {
+        let program_id = Pubkey::new_unique();
+        let obligation_pubkey = Pubkey::new_unique();
+        let lending_market_pubkey = Pubkey::new_unique();
+        let obligation_owner_pubkey = Pubkey::new_unique();
+        let payer_pubkey = Pubkey::new_unique();
+        let instruction = realloc_obligation(
+            program_id,
+            obligation_pubkey,
+            lending_market_pubkey,
+            obligation_owner_pubkey,
+            payer_pubkey,
+        );
+        assert_eq!(instruction.program_id, program_id);
+        assert_eq!(instruction.accounts.len(), 6);
+        assert_eq!(
+            LendingInstruction::unpack(&instruction.data).unwrap(),
+            LendingInstruction::ReallocObligation
+        );
+    }
 }
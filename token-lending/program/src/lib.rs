@@ -6,9 +6,11 @@ pub mod entrypoint;
 pub mod error;
 pub mod instruction;
 pub mod math;
+pub mod oracle;
 pub mod processor;
 pub mod pyth;
 pub mod state;
+pub mod switchboard;
 
 // Export current sdk types for downstream users building with a different sdk version
 pub use solana_program;
@@ -56,6 +56,11 @@ impl Rate {
         Self(U128::from(percent as u64 * PERCENT_SCALER))
     }
 
+    /// Create scaled decimal from basis points value
+    pub fn from_bps(bps: u16) -> Self {
+        Self(U128::from(bps as u64 * (PERCENT_SCALER / 100)))
+    }
+
     /// Return raw scaled value
     #[allow(clippy::wrong_self_convention)]
     pub fn to_scaled_val(&self) -> u128 {
@@ -0,0 +1,465 @@
+//! Oracle price reads
+
+use crate::{
+    error::LendingError,
+    math::{Decimal, TryDiv, TryMul},
+    pyth, switchboard,
+};
+use bytemuck::{Pod, Zeroable};
+use num_derive::FromPrimitive;
+use solana_program::{
+    account_info::AccountInfo, msg, program_error::ProgramError, sysvar::clock::Clock,
+};
+use std::{convert::TryInto, mem::size_of};
+
+/// Basis points in one whole, used for the price confidence share
+pub const BPS_SCALER: u64 = 10_000;
+
+/// Provider of a reserve liquidity oracle account
+#[derive(Clone, Copy, Debug, PartialEq, Eq, FromPrimitive)]
+pub enum OracleSource {
+    /// Pyth v2 price account
+    Pyth = 0,
+    /// Switchboard v2 aggregator account
+    Switchboard = 1,
+    /// Fixed price account, intended for test and devnet markets
+    FixedPrice = 2,
+}
+
+impl Default for OracleSource {
+    fn default() -> Self {
+        Self::Pyth
+    }
+}
+
+/// Magic number of a fixed price account
+pub const FIXED_PRICE_MAGIC: u32 = 0x4658_5052;
+
+/// Fixed price account layout. Fixed price accounts must be owned by the lending market's oracle
+/// program, never go stale, and report no confidence interval.
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct FixedPrice {
+    /// Fixed price magic number
+    pub magic: u32,
+    /// Price exponent
+    pub expo: i32,
+    /// Price mantissa
+    pub price: i64,
+    /// Currency the price is quoted in, padded with zeroes like the lending market's
+    pub quote_currency: [u8; 32],
+}
+
+#[cfg(target_endian = "little")]
+unsafe impl Zeroable for FixedPrice {}
+
+#[cfg(target_endian = "little")]
+unsafe impl Pod for FixedPrice {}
+
+/// Reads a fixed price account, checking its length and magic number
+pub fn load_fixed_price(data: &[u8]) -> Result<&FixedPrice, ProgramError> {
+    if data.len() < size_of::<FixedPrice>() {
+        msg!("Oracle fixed price account data is too short");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    let fixed_price =
+        pyth::load::<FixedPrice>(data).map_err(|_| ProgramError::InvalidAccountData)?;
+    if fixed_price.magic != FIXED_PRICE_MAGIC {
+        msg!("Oracle fixed price account is invalid");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    Ok(fixed_price)
+}
+
+/// Price read from an oracle account before scaling
+struct OraclePrice {
+    price: i64,
+    conf: u64,
+    expo: i32,
+}
+
+/// Reads the market price from an oracle account, rejecting prices which are stale, negative, or
+/// whose confidence interval is wider than `max_confidence_bps` of the price. A
+/// `max_confidence_bps` of zero disables the confidence check.
+pub fn get_oracle_price(
+    oracle_source: OracleSource,
+    oracle_info: &AccountInfo,
+    clock: &Clock,
+    max_confidence_bps: u16,
+) -> Result<Decimal, ProgramError> {
+    let OraclePrice { price, conf, expo } = match oracle_source {
+        OracleSource::Pyth => get_pyth_price(oracle_info, clock)?,
+        OracleSource::Switchboard => get_switchboard_price(oracle_info, clock)?,
+        OracleSource::FixedPrice => get_fixed_price(oracle_info)?,
+    };
+
+    let price: u64 = price.try_into().map_err(|_| {
+        msg!("Oracle price cannot be negative");
+        LendingError::InvalidOracleConfig
+    })?;
+
+    if max_confidence_bps > 0 {
+        let max_conf = (price as u128)
+            .checked_mul(max_confidence_bps as u128)
+            .ok_or(LendingError::MathOverflow)?;
+        let scaled_conf = (conf as u128)
+            .checked_mul(BPS_SCALER as u128)
+            .ok_or(LendingError::MathOverflow)?;
+        if scaled_conf > max_conf {
+            msg!("Oracle price confidence interval is too wide");
+            return Err(LendingError::OraclePriceConfidenceTooWide.into());
+        }
+    }
+
+    let market_price = if expo >= 0 {
+        let exponent = expo.try_into().map_err(|_| LendingError::MathOverflow)?;
+        let zeros = 10u64
+            .checked_pow(exponent)
+            .ok_or(LendingError::MathOverflow)?;
+        Decimal::from(price).try_mul(zeros)?
+    } else {
+        let exponent = expo
+            .checked_abs()
+            .ok_or(LendingError::MathOverflow)?
+            .try_into()
+            .map_err(|_| LendingError::MathOverflow)?;
+        let decimals = 10u64
+            .checked_pow(exponent)
+            .ok_or(LendingError::MathOverflow)?;
+        Decimal::from(price).try_div(decimals)?
+    };
+
+    Ok(market_price)
+}
+
+fn get_pyth_price(
+    pyth_price_info: &AccountInfo,
+    clock: &Clock,
+) -> Result<OraclePrice, ProgramError> {
+    const STALE_AFTER_SLOTS_ELAPSED: u64 = 5;
+
+    let pyth_price_data = pyth_price_info.try_borrow_data()?;
+    let pyth_price = pyth::load::<pyth::Price>(&pyth_price_data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+
+    if pyth_price.ptype != pyth::PriceType::Price {
+        msg!("Oracle price type is invalid");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    if pyth_price.agg.status != pyth::PriceStatus::Trading {
+        msg!("Oracle price status is invalid");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let slots_elapsed = clock
+        .slot
+        .checked_sub(pyth_price.valid_slot)
+        .ok_or(LendingError::MathOverflow)?;
+    if slots_elapsed >= STALE_AFTER_SLOTS_ELAPSED {
+        msg!("Oracle price is stale");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    Ok(OraclePrice {
+        price: pyth_price.agg.price,
+        conf: pyth_price.agg.conf,
+        expo: pyth_price.expo,
+    })
+}
+
+fn get_switchboard_price(
+    aggregator_info: &AccountInfo,
+    clock: &Clock,
+) -> Result<OraclePrice, ProgramError> {
+    // aggregator rounds are opened less often than Pyth publishes
+    const STALE_AFTER_SLOTS_ELAPSED: u64 = 25;
+
+    let aggregator_data = aggregator_info.try_borrow_data()?;
+    let aggregator = switchboard::Aggregator::unpack(&aggregator_data)?;
+    let round = aggregator.latest_confirmed_round;
+
+    if round.num_success == 0 || round.num_success < aggregator.min_oracle_results {
+        msg!("Oracle aggregator round is not confirmed");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let slots_elapsed = clock
+        .slot
+        .checked_sub(round.round_open_slot)
+        .ok_or(LendingError::MathOverflow)?;
+    if slots_elapsed >= STALE_AFTER_SLOTS_ELAPSED {
+        msg!("Oracle price is stale");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let price: i64 = round.result.mantissa.try_into().map_err(|_| {
+        msg!("Oracle aggregator result is too large");
+        LendingError::InvalidOracleConfig
+    })?;
+    let scale: i32 = round
+        .result
+        .scale
+        .try_into()
+        .map_err(|_| LendingError::MathOverflow)?;
+
+    // the standard deviation has its own scale, express it in the result's, rounding up
+    let std_deviation: u128 = round.std_deviation.mantissa.try_into().map_err(|_| {
+        msg!("Oracle aggregator standard deviation cannot be negative");
+        LendingError::InvalidOracleConfig
+    })?;
+    let conf = if round.std_deviation.scale > round.result.scale {
+        let divisor = 10u128
+            .checked_pow(round.std_deviation.scale - round.result.scale)
+            .ok_or(LendingError::MathOverflow)?;
+        std_deviation
+            .checked_add(divisor - 1)
+            .ok_or(LendingError::MathOverflow)?
+            / divisor
+    } else {
+        10u128
+            .checked_pow(round.result.scale - round.std_deviation.scale)
+            .and_then(|multiplier| std_deviation.checked_mul(multiplier))
+            .ok_or(LendingError::MathOverflow)?
+    };
+    let conf: u64 = conf.try_into().map_err(|_| LendingError::MathOverflow)?;
+
+    Ok(OraclePrice {
+        price,
+        conf,
+        expo: -scale,
+    })
+}
+
+fn get_fixed_price(fixed_price_info: &AccountInfo) -> Result<OraclePrice, ProgramError> {
+    let fixed_price_data = fixed_price_info.try_borrow_data()?;
+    let fixed_price = load_fixed_price(&fixed_price_data)?;
+
+    Ok(OraclePrice {
+        price: fixed_price.price,
+        conf: 0,
+        expo: fixed_price.expo,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+
+    const QUOTE_CURRENCY: [u8; 32] =
+        *b"USD\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+    fn aggregator(result: i128, std_deviation: i128) -> switchboard::Aggregator {
+        let mut name = [0u8; 32];
+        name[..7].copy_from_slice(b"SOL_USD");
+        switchboard::Aggregator {
+            name,
+            min_oracle_results: 1,
+            latest_confirmed_round: switchboard::AggregatorRound {
+                num_success: 1,
+                num_error: 0,
+                is_closed: true,
+                round_open_slot: 10,
+                round_open_timestamp: 0,
+                result: switchboard::SwitchboardDecimal {
+                    mantissa: result,
+                    scale: 2,
+                },
+                std_deviation: switchboard::SwitchboardDecimal {
+                    mantissa: std_deviation,
+                    scale: 2,
+                },
+            },
+        }
+    }
+
+    fn read_price(
+        oracle_source: OracleSource,
+        mut data: Vec<u8>,
+        slot: u64,
+        max_confidence_bps: u16,
+    ) -> Result<Decimal, ProgramError> {
+        let key = Pubkey::new_unique();
+        let owner = switchboard::program::id();
+        let mut lamports = 0;
+        let account_info = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        let clock = Clock {
+            slot,
+            ..Clock::default()
+        };
+        get_oracle_price(oracle_source, &account_info, &clock, max_confidence_bps)
+    }
+
+    fn read_switchboard_price(
+        aggregator: switchboard::Aggregator,
+        slot: u64,
+        max_confidence_bps: u16,
+    ) -> Result<Decimal, ProgramError> {
+        let mut data = vec![0; switchboard::AGGREGATOR_READ_LEN];
+        aggregator.pack(&mut data).unwrap();
+        read_price(OracleSource::Switchboard, data, slot, max_confidence_bps)
+    }
+
+    #[test]
+    fn switchboard_price() {
+        let price = read_switchboard_price(aggregator(2_050, 10), 11, 100).unwrap();
+        assert_eq!(price, Decimal::from(41u64).try_div(2u64).unwrap());
+    }
+
+    #[test]
+    fn switchboard_aggregator_packing() {
+        let aggregator = aggregator(-2_050, 10);
+        let mut data = vec![0; switchboard::AGGREGATOR_READ_LEN + 10];
+        aggregator.pack(&mut data).unwrap();
+        assert_eq!(data[..8], switchboard::AGGREGATOR_DISCRIMINATOR);
+        assert_eq!(switchboard::Aggregator::unpack(&data), Ok(aggregator));
+        assert_eq!(aggregator.quote_currency(), Some(QUOTE_CURRENCY));
+    }
+
+    #[test]
+    fn switchboard_quote_currency() {
+        let quote_currency = |name: &[u8]| {
+            let mut aggregator = switchboard::Aggregator::default();
+            aggregator.name[..name.len()].copy_from_slice(name);
+            aggregator.quote_currency()
+        };
+        assert_eq!(quote_currency(b"BTC/USD"), Some(QUOTE_CURRENCY));
+        assert_eq!(quote_currency(b"USDC_USD"), Some(QUOTE_CURRENCY));
+        assert_eq!(quote_currency(b"SOL_USD_"), None);
+        assert_eq!(quote_currency(b"SOLUSD"), None);
+        assert_eq!(quote_currency(b""), None);
+    }
+
+    #[test]
+    fn switchboard_invalid_account() {
+        let mut data = vec![0; switchboard::AGGREGATOR_READ_LEN];
+        aggregator(2_050, 10).pack(&mut data).unwrap();
+
+        // too short to hold the latest confirmed round, without panicking
+        assert_eq!(
+            read_price(
+                OracleSource::Switchboard,
+                data[..switchboard::AGGREGATOR_READ_LEN - 1].to_vec(),
+                11,
+                0
+            ),
+            Err(LendingError::InvalidOracleConfig.into())
+        );
+        assert_eq!(
+            read_price(OracleSource::Switchboard, vec![], 11, 0),
+            Err(LendingError::InvalidOracleConfig.into())
+        );
+
+        // not an aggregator account
+        let mut wrong_discriminator = data;
+        wrong_discriminator[0] = 0;
+        assert_eq!(
+            read_price(OracleSource::Switchboard, wrong_discriminator, 11, 0),
+            Err(LendingError::InvalidOracleConfig.into())
+        );
+    }
+
+    #[test]
+    fn switchboard_round_not_confirmed() {
+        let mut aggregator = aggregator(2_050, 10);
+        aggregator.min_oracle_results = 2;
+        assert_eq!(
+            read_switchboard_price(aggregator, 11, 0),
+            Err(LendingError::InvalidOracleConfig.into())
+        );
+        aggregator.latest_confirmed_round.num_success = 2;
+        assert!(read_switchboard_price(aggregator, 11, 0).is_ok());
+    }
+
+    #[test]
+    fn switchboard_price_stale() {
+        assert_eq!(
+            read_switchboard_price(aggregator(2_050, 10), 35, 0),
+            Err(LendingError::InvalidOracleConfig.into())
+        );
+    }
+
+    #[test]
+    fn switchboard_price_negative() {
+        assert_eq!(
+            read_switchboard_price(aggregator(-2_050, 10), 11, 0),
+            Err(LendingError::InvalidOracleConfig.into())
+        );
+    }
+
+    #[test]
+    fn price_confidence_too_wide() {
+        // 1% confidence interval
+        let aggregator = aggregator(2_000, 20);
+        assert!(read_switchboard_price(aggregator, 11, 100).is_ok());
+        assert_eq!(
+            read_switchboard_price(aggregator, 11, 99),
+            Err(LendingError::OraclePriceConfidenceTooWide.into())
+        );
+
+        // confidence check disabled
+        assert!(read_switchboard_price(aggregator, 11, 0).is_ok());
+    }
+
+    #[test]
+    fn switchboard_std_deviation_scale() {
+        // 1% confidence interval, the standard deviation with more decimals than the result is
+        // rounded up
+        let mut aggregator = aggregator(2_000, 0);
+        aggregator.latest_confirmed_round.std_deviation = switchboard::SwitchboardDecimal {
+            mantissa: 200_000,
+            scale: 6,
+        };
+        assert!(read_switchboard_price(aggregator, 11, 100).is_ok());
+        aggregator.latest_confirmed_round.std_deviation.mantissa = 200_001;
+        assert_eq!(
+            read_switchboard_price(aggregator, 11, 100),
+            Err(LendingError::OraclePriceConfidenceTooWide.into())
+        );
+
+        // and with fewer decimals
+        aggregator.latest_confirmed_round.std_deviation = switchboard::SwitchboardDecimal {
+            mantissa: 2,
+            scale: 1,
+        };
+        assert_eq!(
+            read_switchboard_price(aggregator, 11, 99),
+            Err(LendingError::OraclePriceConfidenceTooWide.into())
+        );
+        assert!(read_switchboard_price(aggregator, 11, 100).is_ok());
+    }
+
+    #[test]
+    fn fixed_price() {
+        let data = bytemuck::bytes_of(&FixedPrice {
+            magic: FIXED_PRICE_MAGIC,
+            expo: 1,
+            price: 3,
+            quote_currency: QUOTE_CURRENCY,
+        })
+        .to_vec();
+        assert_eq!(
+            read_price(OracleSource::FixedPrice, data.clone(), u64::MAX, 1),
+            Ok(Decimal::from(30u64))
+        );
+        assert_eq!(
+            read_price(
+                OracleSource::FixedPrice,
+                data[..data.len() - 1].to_vec(),
+                u64::MAX,
+                1
+            ),
+            Err(LendingError::InvalidOracleConfig.into())
+        );
+    }
+}
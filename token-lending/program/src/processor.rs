@@ -4,13 +4,15 @@ use crate::{
     error::LendingError,
     instruction::LendingInstruction,
     math::{Decimal, Rate, TryAdd, TryDiv, TryMul, WAD},
+    oracle::{get_oracle_price, load_fixed_price, OracleSource, BPS_SCALER},
     pyth,
     state::{
-        CalculateBorrowResult, CalculateLiquidationResult, CalculateRepayResult,
-        InitLendingMarketParams, InitObligationParams, InitReserveParams, LendingMarket,
-        NewReserveCollateralParams, NewReserveLiquidityParams, Obligation, Reserve,
-        ReserveCollateral, ReserveConfig, ReserveLiquidity,
+        BorrowRateCurve, CalculateBorrowResult, CalculateLiquidationResult, CalculateRepayResult,
+        ElevationGroup, InitLendingMarketParams, InitObligationParams, InitReserveParams,
+        LendingMarket, NewReserveCollateralParams, NewReserveLiquidityParams, Obligation, Reserve,
+        ReserveCollateral, ReserveConfig, ReserveLiquidity, MAX_ELEVATION_GROUPS,
     },
+    switchboard,
 };
 use num_traits::FromPrimitive;
 use solana_program::{
@@ -23,6 +25,7 @@ use solana_program::{
     program_error::{PrintProgramError, ProgramError},
     program_pack::{IsInitialized, Pack},
     pubkey::Pubkey,
+    system_instruction, system_program,
     sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
 use spl_token::solana_program::instruction::AccountMeta;
@@ -99,6 +102,43 @@ pub fn process_instruction(
             msg!("Instruction: Flash Loan");
             process_flash_loan(program_id, amount, accounts)
         }
+        LendingInstruction::UpdateReserveConfig { config } => {
+            msg!("Instruction: Update Reserve Config");
+            process_update_reserve_config(program_id, config, accounts)
+        }
+        LendingInstruction::SetReserveOracle {
+            oracle_source,
+            fallback_oracle_source,
+        } => {
+            msg!("Instruction: Set Reserve Oracle");
+            process_set_reserve_oracle(program_id, oracle_source, fallback_oracle_source, accounts)
+        }
+        LendingInstruction::SetElevationGroup {
+            elevation_group,
+            loan_to_value_ratio,
+            liquidation_threshold,
+        } => {
+            msg!("Instruction: Set Elevation Group");
+            process_set_elevation_group(
+                program_id,
+                elevation_group,
+                loan_to_value_ratio,
+                liquidation_threshold,
+                accounts,
+            )
+        }
+        LendingInstruction::SetObligationElevationGroup { elevation_group } => {
+            msg!("Instruction: Set Obligation Elevation Group");
+            process_set_obligation_elevation_group(program_id, elevation_group, accounts)
+        }
+        LendingInstruction::RedeemFees => {
+            msg!("Instruction: Redeem Fees");
+            process_redeem_fees(program_id, accounts)
+        }
+        LendingInstruction::ReallocObligation => {
+            msg!("Instruction: Realloc Obligation");
+            process_realloc_obligation(program_id, accounts)
+        }
     }
 }
 
@@ -173,44 +213,7 @@ fn process_init_reserve(
         msg!("Reserve must be initialized with liquidity");
         return Err(LendingError::InvalidAmount.into());
     }
-    if config.optimal_utilization_rate > 100 {
-        msg!("Optimal utilization rate must be in range [0, 100]");
-        return Err(LendingError::InvalidConfig.into());
-    }
-    if config.loan_to_value_ratio >= 100 {
-        msg!("Loan to value ratio must be in range [0, 100)");
-        return Err(LendingError::InvalidConfig.into());
-    }
-    if config.liquidation_bonus > 100 {
-        msg!("Liquidation bonus must be in range [0, 100]");
-        return Err(LendingError::InvalidConfig.into());
-    }
-    if config.liquidation_threshold <= config.loan_to_value_ratio
-        || config.liquidation_threshold > 100
-    {
-        msg!("Liquidation threshold must be in range (LTV, 100]");
-        return Err(LendingError::InvalidConfig.into());
-    }
-    if config.optimal_borrow_rate < config.min_borrow_rate {
-        msg!("Optimal borrow rate must be >= min borrow rate");
-        return Err(LendingError::InvalidConfig.into());
-    }
-    if config.optimal_borrow_rate > config.max_borrow_rate {
-        msg!("Optimal borrow rate must be <= max borrow rate");
-        return Err(LendingError::InvalidConfig.into());
-    }
-    if config.fees.borrow_fee_wad >= WAD {
-        msg!("Borrow fee must be in range [0, 1_000_000_000_000_000_000)");
-        return Err(LendingError::InvalidConfig.into());
-    }
-    if config.fees.flash_loan_fee_wad >= WAD {
-        msg!("Flash loan fee must be in range [0, 1_000_000_000_000_000_000)");
-        return Err(LendingError::InvalidConfig.into());
-    }
-    if config.fees.host_fee_percentage > 100 {
-        msg!("Host fee percentage must be in range [0, 100]");
-        return Err(LendingError::InvalidConfig.into());
-    }
+    validate_reserve_config(config)?;
 
     let account_info_iter = &mut accounts.iter().peekable();
     let source_liquidity_info = next_account_info(account_info_iter)?;
@@ -262,48 +265,14 @@ fn process_init_reserve(
         return Err(LendingError::InvalidSigner.into());
     }
 
-    if &lending_market.oracle_program_id != pyth_product_info.owner {
-        msg!("Pyth product account provided is not owned by the lending market oracle program");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
-    if &lending_market.oracle_program_id != pyth_price_info.owner {
-        msg!("Pyth price account provided is not owned by the lending market oracle program");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
-
-    let pyth_product_data = pyth_product_info.try_borrow_data()?;
-    let pyth_product = pyth::load::<pyth::Product>(&pyth_product_data)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
-    if pyth_product.magic != pyth::MAGIC {
-        msg!("Pyth product account provided is not a valid Pyth account");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
-    if pyth_product.ver != pyth::VERSION_2 {
-        msg!("Pyth product account provided has a different version than expected");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
-    if pyth_product.atype != pyth::AccountType::Product as u32 {
-        msg!("Pyth product account provided is not a valid Pyth product account");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
-
-    let pyth_price_pubkey_bytes: &[u8; 32] = pyth_price_info
-        .key
-        .as_ref()
-        .try_into()
-        .map_err(|_| LendingError::InvalidAccountInput)?;
-    if &pyth_product.px_acc.val != pyth_price_pubkey_bytes {
-        msg!("Pyth product price account does not match the Pyth price provided");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
-
-    let quote_currency = get_pyth_product_quote_currency(pyth_product)?;
-    if lending_market.quote_currency != quote_currency {
-        msg!("Lending market quote currency does not match the oracle quote currency");
-        return Err(LendingError::InvalidOracleConfig.into());
-    }
+    validate_pyth_oracle(&lending_market, pyth_product_info, pyth_price_info)?;
 
-    let market_price = get_pyth_price(pyth_price_info, clock)?;
+    let market_price = get_oracle_price(
+        OracleSource::Pyth,
+        pyth_price_info,
+        clock,
+        config.max_price_confidence_bps,
+    )?;
 
     let authority_signer_seeds = &[
         lending_market_info.key.as_ref(),
@@ -333,6 +302,7 @@ fn process_init_reserve(
             supply_pubkey: *reserve_liquidity_supply_info.key,
             fee_receiver: *reserve_liquidity_fee_receiver_info.key,
             oracle_pubkey: *pyth_price_info.key,
+            oracle_source: OracleSource::Pyth,
             market_price,
         }),
         collateral: ReserveCollateral::new(NewReserveCollateralParams {
@@ -411,6 +381,7 @@ fn process_refresh_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> Pro
     let reserve_info = next_account_info(account_info_iter)?;
     let reserve_liquidity_oracle_info = next_account_info(account_info_iter)?;
     let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let reserve_liquidity_fallback_oracle_info = next_account_info(account_info_iter).ok();
 
     let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
     if reserve_info.owner != program_id {
@@ -421,8 +392,37 @@ fn process_refresh_reserve(program_id: &Pubkey, accounts: &[AccountInfo]) -> Pro
         msg!("Reserve liquidity oracle does not match the reserve liquidity oracle provided");
         return Err(LendingError::InvalidAccountInput.into());
     }
+    if let Some(reserve_liquidity_fallback_oracle_info) = reserve_liquidity_fallback_oracle_info {
+        if reserve.liquidity.fallback_oracle_pubkey == Pubkey::default()
+            || &reserve.liquidity.fallback_oracle_pubkey
+                != reserve_liquidity_fallback_oracle_info.key
+        {
+            msg!("Reserve liquidity fallback oracle does not match the reserve liquidity fallback oracle provided");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+    }
 
-    reserve.liquidity.market_price = get_pyth_price(reserve_liquidity_oracle_info, clock)?;
+    let max_price_confidence_bps = reserve.config.max_price_confidence_bps;
+    reserve.liquidity.market_price = match get_oracle_price(
+        reserve.liquidity.oracle_source,
+        reserve_liquidity_oracle_info,
+        clock,
+        max_price_confidence_bps,
+    ) {
+        Ok(market_price) => market_price,
+        Err(err) => match reserve_liquidity_fallback_oracle_info {
+            Some(reserve_liquidity_fallback_oracle_info) => {
+                msg!("Reserve liquidity oracle price is unavailable, reading the fallback oracle");
+                get_oracle_price(
+                    reserve.liquidity.fallback_oracle_source,
+                    reserve_liquidity_fallback_oracle_info,
+                    clock,
+                    max_price_confidence_bps,
+                )?
+            }
+            None => return Err(err),
+        },
+    };
 
     reserve.accrue_interest(clock.slot)?;
     reserve.last_update.update_slot(clock.slot);
@@ -508,6 +508,17 @@ fn process_deposit_reserve_liquidity(
         return Err(LendingError::InvalidMarketAuthority.into());
     }
 
+    if reserve.config.deposit_limit > 0 {
+        let total_supply = reserve
+            .liquidity
+            .total_supply()?
+            .try_add(Decimal::from(liquidity_amount))?;
+        if total_supply > Decimal::from(reserve.config.deposit_limit) {
+            msg!("Deposit would exceed the reserve deposit limit");
+            return Err(LendingError::DepositLimitExceeded.into());
+        }
+    }
+
     let collateral_amount = reserve.deposit_liquidity(liquidity_amount)?;
     reserve.last_update.mark_stale();
     Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
@@ -692,6 +703,7 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
     let mut borrowed_value = Decimal::zero();
     let mut allowed_borrow_value = Decimal::zero();
     let mut unhealthy_borrow_value = Decimal::zero();
+    let mut in_elevation_group = obligation.elevation_group != 0;
 
     for (index, collateral) in obligation.deposits.iter_mut().enumerate() {
         let deposit_reserve_info = next_account_info(account_info_iter)?;
@@ -734,6 +746,7 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
         let loan_to_value_rate = Rate::from_percent(deposit_reserve.config.loan_to_value_ratio);
         let liquidation_threshold_rate =
             Rate::from_percent(deposit_reserve.config.liquidation_threshold);
+        in_elevation_group &= deposit_reserve.config.elevation_group == obligation.elevation_group;
 
         deposited_value = deposited_value.try_add(market_value)?;
         allowed_borrow_value =
@@ -780,10 +793,40 @@ fn process_refresh_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) ->
             .try_mul(borrow_reserve.liquidity.market_price)?
             .try_div(decimals)?;
         liquidity.market_value = market_value;
+        in_elevation_group &= borrow_reserve.config.elevation_group == obligation.elevation_group;
 
         borrowed_value = borrowed_value.try_add(market_value)?;
     }
 
+    if obligation.elevation_group != 0 {
+        let lending_market_info = next_account_info(account_info_iter).map_err(|_| {
+            msg!("Lending market must be provided to refresh an obligation in an elevation group");
+            LendingError::InvalidAccountInput
+        })?;
+        if lending_market_info.owner != program_id {
+            msg!("Lending market provided is not owned by the lending program");
+            return Err(LendingError::InvalidAccountOwner.into());
+        }
+        if &obligation.lending_market != lending_market_info.key {
+            msg!("Obligation lending market does not match the lending market provided");
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        // elevation group values only apply while every deposit and borrow is in the group
+        let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+        match lending_market.find_elevation_group(obligation.elevation_group) {
+            Some(group) if in_elevation_group => {
+                allowed_borrow_value =
+                    deposited_value.try_mul(Rate::from_percent(group.loan_to_value_ratio))?;
+                unhealthy_borrow_value =
+                    deposited_value.try_mul(Rate::from_percent(group.liquidation_threshold))?;
+            }
+            _ => {
+                msg!("Obligation elevation group does not apply, using reserve values");
+            }
+        }
+    }
+
     if account_info_iter.peek().is_some() {
         msg!("Too many obligation deposit or borrow reserves provided");
         return Err(LendingError::InvalidAccountInput.into());
@@ -877,6 +920,12 @@ fn process_deposit_obligation_collateral(
         msg!("Obligation owner provided must be a signer");
         return Err(LendingError::InvalidSigner.into());
     }
+    if obligation.elevation_group != 0
+        && deposit_reserve.config.elevation_group != obligation.elevation_group
+    {
+        msg!("Deposit reserve is not in the obligation elevation group");
+        return Err(LendingError::ElevationGroupMismatch.into());
+    }
 
     obligation
         .find_or_add_collateral_to_deposits(*deposit_reserve_info.key)?
@@ -1139,6 +1188,12 @@ fn process_borrow_obligation_liquidity(
         msg!("Obligation deposits have zero value");
         return Err(LendingError::ObligationDepositsZero.into());
     }
+    if obligation.elevation_group != 0
+        && borrow_reserve.config.elevation_group != obligation.elevation_group
+    {
+        msg!("Borrow reserve is not in the obligation elevation group");
+        return Err(LendingError::ElevationGroupMismatch.into());
+    }
 
     let authority_signer_seeds = &[
         lending_market_info.key.as_ref(),
@@ -1171,6 +1226,17 @@ fn process_borrow_obligation_liquidity(
         return Err(LendingError::BorrowTooSmall.into());
     }
 
+    if borrow_reserve.config.borrow_limit > 0 {
+        let borrowed_amount = borrow_reserve
+            .liquidity
+            .borrowed_amount_wads
+            .try_add(borrow_amount)?;
+        if borrowed_amount > Decimal::from(borrow_reserve.config.borrow_limit) {
+            msg!("Borrow would exceed the reserve borrow limit");
+            return Err(LendingError::BorrowLimitExceeded.into());
+        }
+    }
+
     borrow_reserve.liquidity.borrow(borrow_amount)?;
     borrow_reserve.last_update.mark_stale();
     Reserve::pack(borrow_reserve, &mut borrow_reserve_info.data.borrow_mut())?;
@@ -1696,127 +1762,697 @@ fn process_flash_loan(
     Ok(())
 }
 
-fn assert_rent_exempt(rent: &Rent, account_info: &AccountInfo) -> ProgramResult {
-    if !rent.is_exempt(account_info.lamports(), account_info.data_len()) {
-        msg!(&rent.minimum_balance(account_info.data_len()).to_string());
-        Err(LendingError::NotRentExempt.into())
-    } else {
-        Ok(())
+#[inline(never)] // avoid stack frame limit
+fn process_update_reserve_config(
+    program_id: &Pubkey,
+    config: ReserveConfig,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    validate_reserve_config(config)?;
+
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
     }
-}
 
-fn assert_uninitialized<T: Pack + IsInitialized>(
-    account_info: &AccountInfo,
-) -> Result<T, ProgramError> {
-    let account: T = T::unpack_unchecked(&account_info.data.borrow())?;
-    if account.is_initialized() {
-        Err(LendingError::AlreadyInitialized.into())
-    } else {
-        Ok(account)
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
     }
-}
 
-/// Unpacks a spl_token `Mint`.
-fn unpack_mint(data: &[u8]) -> Result<Mint, LendingError> {
-    Mint::unpack(data).map_err(|_| LendingError::InvalidTokenMint)
-}
+    // Interest up to now is owed at the previous rates
+    reserve.accrue_interest(clock.slot)?;
+    reserve.last_update.update_slot(clock.slot);
+    reserve.last_update.mark_stale();
 
-fn get_pyth_product_quote_currency(pyth_product: &pyth::Product) -> Result<[u8; 32], ProgramError> {
-    const LEN: usize = 14;
-    const KEY: &[u8; LEN] = b"quote_currency";
+    reserve.config = config;
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
 
-    let mut start = 0;
-    while start < pyth::PROD_ATTR_SIZE {
-        let mut length = pyth_product.attr[start] as usize;
-        start += 1;
+    Ok(())
+}
 
-        if length == LEN {
-            let mut end = start + length;
-            if end > pyth::PROD_ATTR_SIZE {
-                msg!("Pyth product attribute key length too long");
-                return Err(LendingError::InvalidOracleConfig.into());
-            }
+#[inline(never)] // avoid stack frame limit
+fn process_set_reserve_oracle(
+    program_id: &Pubkey,
+    oracle_source: OracleSource,
+    fallback_oracle_source: Option<OracleSource>,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
 
-            let key = &pyth_product.attr[start..end];
-            if key == KEY {
-                start += length;
-                length = pyth_product.attr[start] as usize;
-                start += 1;
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
 
-                end = start + length;
-                if length > 32 || end > pyth::PROD_ATTR_SIZE {
-                    msg!("Pyth product quote currency value too long");
-                    return Err(LendingError::InvalidOracleConfig.into());
-                }
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
 
-                let mut value = [0u8; 32];
-                value[0..length].copy_from_slice(&pyth_product.attr[start..end]);
-                return Ok(value);
+    let oracle_info = next_oracle_info(&lending_market, oracle_source, account_info_iter)?;
+    let market_price = get_oracle_price(
+        oracle_source,
+        oracle_info,
+        clock,
+        reserve.config.max_price_confidence_bps,
+    )?;
+
+    let (fallback_oracle_pubkey, fallback_oracle_source) = match fallback_oracle_source {
+        Some(fallback_oracle_source) => {
+            let fallback_oracle_info =
+                next_oracle_info(&lending_market, fallback_oracle_source, account_info_iter)?;
+            if fallback_oracle_info.key == oracle_info.key {
+                msg!("Fallback oracle cannot be the same account as the oracle");
+                return Err(LendingError::InvalidOracleConfig.into());
             }
+            (*fallback_oracle_info.key, fallback_oracle_source)
         }
+        None => (Pubkey::default(), OracleSource::default()),
+    };
 
-        start += length;
-        start += 1 + pyth_product.attr[start] as usize;
-    }
+    reserve.liquidity.oracle_pubkey = *oracle_info.key;
+    reserve.liquidity.oracle_source = oracle_source;
+    reserve.liquidity.fallback_oracle_pubkey = fallback_oracle_pubkey;
+    reserve.liquidity.fallback_oracle_source = fallback_oracle_source;
+    reserve.liquidity.market_price = market_price;
+    reserve.last_update.mark_stale();
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
 
-    msg!("Pyth product quote currency not found");
-    Err(LendingError::InvalidOracleConfig.into())
+    Ok(())
 }
 
-fn get_pyth_price(pyth_price_info: &AccountInfo, clock: &Clock) -> Result<Decimal, ProgramError> {
-    const STALE_AFTER_SLOTS_ELAPSED: u64 = 5;
-
-    let pyth_price_data = pyth_price_info.try_borrow_data()?;
-    let pyth_price = pyth::load::<pyth::Price>(&pyth_price_data)
-        .map_err(|_| ProgramError::InvalidAccountData)?;
-
-    if pyth_price.ptype != pyth::PriceType::Price {
-        msg!("Oracle price type is invalid");
-        return Err(LendingError::InvalidOracleConfig.into());
+#[inline(never)] // avoid stack frame limit
+fn process_set_elevation_group(
+    program_id: &Pubkey,
+    elevation_group: u8,
+    loan_to_value_ratio: u8,
+    liquidation_threshold: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    if elevation_group == 0 || elevation_group > MAX_ELEVATION_GROUPS {
+        msg!(
+            "Elevation group must be in range [1, {}]",
+            MAX_ELEVATION_GROUPS
+        );
+        return Err(LendingError::InvalidElevationGroup.into());
     }
-
-    if pyth_price.agg.status != pyth::PriceStatus::Trading {
-        msg!("Oracle price status is invalid");
-        return Err(LendingError::InvalidOracleConfig.into());
+    if loan_to_value_ratio >= 100 {
+        msg!("Loan to value ratio must be in range [0, 100)");
+        return Err(LendingError::InvalidConfig.into());
     }
-
-    let slots_elapsed = clock
-        .slot
-        .checked_sub(pyth_price.valid_slot)
-        .ok_or(LendingError::MathOverflow)?;
-    if slots_elapsed >= STALE_AFTER_SLOTS_ELAPSED {
-        msg!("Oracle price is stale");
-        return Err(LendingError::InvalidOracleConfig.into());
+    if liquidation_threshold == 0 {
+        if loan_to_value_ratio != 0 {
+            msg!("Loan to value ratio must be 0 to remove an elevation group");
+            return Err(LendingError::InvalidConfig.into());
+        }
+    } else if liquidation_threshold <= loan_to_value_ratio || liquidation_threshold > 100 {
+        msg!("Liquidation threshold must be in range (LTV, 100]");
+        return Err(LendingError::InvalidConfig.into());
     }
 
-    let price: u64 = pyth_price.agg.price.try_into().map_err(|_| {
-        msg!("Oracle price cannot be negative");
-        LendingError::InvalidOracleConfig
-    })?;
+    let account_info_iter = &mut accounts.iter();
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_owner_info = next_account_info(account_info_iter)?;
 
-    let market_price = if pyth_price.expo >= 0 {
-        let exponent = pyth_price
-            .expo
-            .try_into()
-            .map_err(|_| LendingError::MathOverflow)?;
-        let zeros = 10u64
-            .checked_pow(exponent)
-            .ok_or(LendingError::MathOverflow)?;
-        Decimal::from(price).try_mul(zeros)?
+    let mut lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.owner != lending_market_owner_info.key {
+        msg!("Lending market owner does not match the lending market owner provided");
+        return Err(LendingError::InvalidMarketOwner.into());
+    }
+    if !lending_market_owner_info.is_signer {
+        msg!("Lending market owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    lending_market.elevation_groups[elevation_group as usize - 1] = ElevationGroup {
+        loan_to_value_ratio,
+        liquidation_threshold,
+    };
+    LendingMarket::pack(lending_market, &mut lending_market_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_set_obligation_elevation_group(
+    program_id: &Pubkey,
+    elevation_group: u8,
+    accounts: &[AccountInfo],
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter().peekable();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let mut obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+    if obligation.last_update.is_stale(clock.slot)? {
+        msg!("Obligation is stale and must be refreshed in the current slot");
+        return Err(LendingError::ObligationStale.into());
+    }
+
+    let group = if elevation_group == 0 {
+        None
     } else {
-        let exponent = pyth_price
-            .expo
-            .checked_abs()
-            .ok_or(LendingError::MathOverflow)?
-            .try_into()
-            .map_err(|_| LendingError::MathOverflow)?;
-        let decimals = 10u64
-            .checked_pow(exponent)
-            .ok_or(LendingError::MathOverflow)?;
-        Decimal::from(price).try_div(decimals)?
+        let group = lending_market
+            .find_elevation_group(elevation_group)
+            .ok_or_else(|| {
+                msg!("Elevation group is out of range or not configured");
+                LendingError::InvalidElevationGroup
+            })?;
+        Some(*group)
+    };
+
+    let mut allowed_borrow_value = Decimal::zero();
+    let mut unhealthy_borrow_value = Decimal::zero();
+
+    for (index, collateral) in obligation.deposits.iter().enumerate() {
+        let deposit_reserve_info = next_account_info(account_info_iter)?;
+        if deposit_reserve_info.owner != program_id {
+            msg!(
+                "Deposit reserve provided for collateral {} is not owned by the lending program",
+                index
+            );
+            return Err(LendingError::InvalidAccountOwner.into());
+        }
+        if collateral.deposit_reserve != *deposit_reserve_info.key {
+            msg!(
+                "Deposit reserve of collateral {} does not match the deposit reserve provided",
+                index
+            );
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        let deposit_reserve = Reserve::unpack(&deposit_reserve_info.data.borrow())?;
+        let (loan_to_value_ratio, liquidation_threshold) = match group {
+            Some(group) => {
+                if deposit_reserve.config.elevation_group != elevation_group {
+                    msg!(
+                        "Deposit reserve provided for collateral {} is not in the elevation group",
+                        index
+                    );
+                    return Err(LendingError::ElevationGroupMismatch.into());
+                }
+                (group.loan_to_value_ratio, group.liquidation_threshold)
+            }
+            None => (
+                deposit_reserve.config.loan_to_value_ratio,
+                deposit_reserve.config.liquidation_threshold,
+            ),
+        };
+
+        allowed_borrow_value = allowed_borrow_value.try_add(
+            collateral
+                .market_value
+                .try_mul(Rate::from_percent(loan_to_value_ratio))?,
+        )?;
+        unhealthy_borrow_value = unhealthy_borrow_value.try_add(
+            collateral
+                .market_value
+                .try_mul(Rate::from_percent(liquidation_threshold))?,
+        )?;
+    }
+
+    for (index, liquidity) in obligation.borrows.iter().enumerate() {
+        let borrow_reserve_info = next_account_info(account_info_iter)?;
+        if borrow_reserve_info.owner != program_id {
+            msg!(
+                "Borrow reserve provided for liquidity {} is not owned by the lending program",
+                index
+            );
+            return Err(LendingError::InvalidAccountOwner.into());
+        }
+        if liquidity.borrow_reserve != *borrow_reserve_info.key {
+            msg!(
+                "Borrow reserve of liquidity {} does not match the borrow reserve provided",
+                index
+            );
+            return Err(LendingError::InvalidAccountInput.into());
+        }
+
+        let borrow_reserve = Reserve::unpack(&borrow_reserve_info.data.borrow())?;
+        if group.is_some() && borrow_reserve.config.elevation_group != elevation_group {
+            msg!(
+                "Borrow reserve provided for liquidity {} is not in the elevation group",
+                index
+            );
+            return Err(LendingError::ElevationGroupMismatch.into());
+        }
+    }
+
+    if account_info_iter.peek().is_some() {
+        msg!("Too many obligation deposit or borrow reserves provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+
+    if obligation.borrowed_value > allowed_borrow_value {
+        msg!("Obligation borrowed value would exceed its allowed borrow value");
+        return Err(LendingError::BorrowTooLarge.into());
+    }
+
+    obligation.elevation_group = elevation_group;
+    obligation.allowed_borrow_value = allowed_borrow_value;
+    obligation.unhealthy_borrow_value = unhealthy_borrow_value;
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_redeem_fees(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let reserve_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_fee_receiver_info = next_account_info(account_info_iter)?;
+    let reserve_liquidity_supply_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let lending_market_authority_info = next_account_info(account_info_iter)?;
+    let clock = &Clock::from_account_info(next_account_info(account_info_iter)?)?;
+    let token_program_id = next_account_info(account_info_iter)?;
+
+    let lending_market = LendingMarket::unpack(&lending_market_info.data.borrow())?;
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &lending_market.token_program_id != token_program_id.key {
+        msg!("Lending market token program does not match the token program provided");
+        return Err(LendingError::InvalidTokenProgram.into());
+    }
+
+    let mut reserve = Reserve::unpack(&reserve_info.data.borrow())?;
+    if reserve_info.owner != program_id {
+        msg!("Reserve provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &reserve.lending_market != lending_market_info.key {
+        msg!("Reserve lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.fee_receiver != reserve_liquidity_fee_receiver_info.key {
+        msg!("Reserve liquidity fee receiver does not match the reserve liquidity fee receiver provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &reserve.liquidity.supply_pubkey != reserve_liquidity_supply_info.key {
+        msg!("Reserve liquidity supply does not match the reserve liquidity supply provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if reserve.last_update.is_stale(clock.slot)? {
+        msg!("Reserve is stale and must be refreshed in the current slot");
+        return Err(LendingError::ReserveStale.into());
+    }
+
+    let authority_signer_seeds = &[
+        lending_market_info.key.as_ref(),
+        &[lending_market.bump_seed],
+    ];
+    let lending_market_authority_pubkey =
+        Pubkey::create_program_address(authority_signer_seeds, program_id)?;
+    if &lending_market_authority_pubkey != lending_market_authority_info.key {
+        msg!(
+            "Derived lending market authority does not match the lending market authority provided"
+        );
+        return Err(LendingError::InvalidMarketAuthority.into());
+    }
+
+    let withdraw_amount = reserve.calculate_redeem_fees()?;
+    if withdraw_amount == 0 {
+        msg!("No protocol fees can be redeemed from the reserve available liquidity");
+        return Ok(());
+    }
+
+    reserve.liquidity.redeem_fees(withdraw_amount)?;
+    reserve.last_update.mark_stale();
+    Reserve::pack(reserve, &mut reserve_info.data.borrow_mut())?;
+
+    spl_token_transfer(TokenTransferParams {
+        source: reserve_liquidity_supply_info.clone(),
+        destination: reserve_liquidity_fee_receiver_info.clone(),
+        amount: withdraw_amount,
+        authority: lending_market_authority_info.clone(),
+        authority_signer_seeds,
+        token_program: token_program_id.clone(),
+    })?;
+
+    Ok(())
+}
+
+#[inline(never)] // avoid stack frame limit
+fn process_realloc_obligation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let obligation_info = next_account_info(account_info_iter)?;
+    let lending_market_info = next_account_info(account_info_iter)?;
+    let obligation_owner_info = next_account_info(account_info_iter)?;
+    let payer_info = next_account_info(account_info_iter)?;
+    let rent = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if system_program_info.key != &system_program::id() {
+        msg!("System program provided does not match the system program id");
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    if lending_market_info.owner != program_id {
+        msg!("Lending market provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+
+    let obligation = Obligation::unpack(&obligation_info.data.borrow())?;
+    if obligation_info.owner != program_id {
+        msg!("Obligation provided is not owned by the lending program");
+        return Err(LendingError::InvalidAccountOwner.into());
+    }
+    if &obligation.lending_market != lending_market_info.key {
+        msg!("Obligation lending market does not match the lending market provided");
+        return Err(LendingError::InvalidAccountInput.into());
+    }
+    if &obligation.owner != obligation_owner_info.key {
+        msg!("Obligation owner does not match the obligation owner provided");
+        return Err(LendingError::InvalidObligationOwner.into());
+    }
+    if !obligation_owner_info.is_signer {
+        msg!("Obligation owner provided must be a signer");
+        return Err(LendingError::InvalidSigner.into());
+    }
+
+    if obligation_info.data_len() == Obligation::LEN {
+        msg!("Obligation account is already the current length");
+        return Ok(());
+    }
+
+    let required_lamports = rent
+        .minimum_balance(Obligation::LEN)
+        .saturating_sub(obligation_info.lamports());
+    if required_lamports > 0 {
+        invoke(
+            &system_instruction::transfer(payer_info.key, obligation_info.key, required_lamports),
+            &[
+                payer_info.clone(),
+                obligation_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+
+    obligation_info.realloc(Obligation::LEN, true)?;
+    Obligation::pack(obligation, &mut obligation_info.data.borrow_mut())?;
+
+    Ok(())
+}
+
+fn validate_reserve_config(config: ReserveConfig) -> ProgramResult {
+    if config.loan_to_value_ratio >= 100 {
+        msg!("Loan to value ratio must be in range [0, 100)");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.liquidation_bonus > 100 {
+        msg!("Liquidation bonus must be in range [0, 100]");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.liquidation_threshold <= config.loan_to_value_ratio
+        || config.liquidation_threshold > 100
+    {
+        msg!("Liquidation threshold must be in range (LTV, 100]");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    validate_borrow_rate_curve(&config.borrow_rate_curve)?;
+    if config.fees.borrow_fee_wad >= WAD {
+        msg!("Borrow fee must be in range [0, 1_000_000_000_000_000_000)");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.fees.flash_loan_fee_wad >= WAD {
+        msg!("Flash loan fee must be in range [0, 1_000_000_000_000_000_000)");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.fees.host_fee_percentage > 100 {
+        msg!("Host fee percentage must be in range [0, 100]");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.max_price_confidence_bps as u64 > BPS_SCALER {
+        msg!("Max price confidence must be in range [0, 10_000] basis points");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.protocol_take_rate > 100 {
+        msg!("Protocol take rate must be in range [0, 100]");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if config.elevation_group > MAX_ELEVATION_GROUPS {
+        msg!(
+            "Elevation group must be in range [0, {}]",
+            MAX_ELEVATION_GROUPS
+        );
+        return Err(LendingError::InvalidConfig.into());
+    }
+
+    Ok(())
+}
+
+fn validate_borrow_rate_curve(borrow_rate_curve: &BorrowRateCurve) -> ProgramResult {
+    let points = borrow_rate_curve.points();
+    if points.len() < 2 {
+        msg!("Borrow rate curve must have at least 2 points");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    if points[0].utilization_rate_bps != 0
+        || points[points.len() - 1].utilization_rate_bps as u64 != BPS_SCALER
+    {
+        msg!("Borrow rate curve must start at 0 and end at 10_000 basis points of utilization");
+        return Err(LendingError::InvalidConfig.into());
+    }
+    for window in points.windows(2) {
+        if window[1].utilization_rate_bps <= window[0].utilization_rate_bps {
+            msg!("Borrow rate curve utilization rates must be increasing");
+            return Err(LendingError::InvalidConfig.into());
+        }
+        if window[1].borrow_rate_bps < window[0].borrow_rate_bps {
+            msg!("Borrow rate curve borrow rates must not decrease");
+            return Err(LendingError::InvalidConfig.into());
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the oracle account for `oracle_source` from the account iterator, along with the Pyth
+/// product account for Pyth oracles, and validates it for use by the lending market, including
+/// that its prices are quoted in the lending market's quote currency.
+fn next_oracle_info<'a, 'b, I: Iterator<Item = &'a AccountInfo<'b>>>(
+    lending_market: &LendingMarket,
+    oracle_source: OracleSource,
+    account_info_iter: &mut I,
+) -> Result<&'a AccountInfo<'b>, ProgramError> {
+    let oracle_info = next_account_info(account_info_iter)?;
+    let quote_currency = match oracle_source {
+        OracleSource::Pyth => {
+            let pyth_product_info = next_account_info(account_info_iter)?;
+            // also checks the quote currency of the Pyth product
+            validate_pyth_oracle(lending_market, pyth_product_info, oracle_info)?;
+            return Ok(oracle_info);
+        }
+        OracleSource::Switchboard => {
+            if oracle_info.owner != &switchboard::program::id() {
+                msg!("Switchboard aggregator account provided is not owned by the Switchboard program");
+                return Err(LendingError::InvalidOracleConfig.into());
+            }
+            let aggregator = switchboard::Aggregator::unpack(&oracle_info.try_borrow_data()?)?;
+            aggregator.quote_currency().ok_or_else(|| {
+                msg!("Switchboard aggregator name does not end with a quote currency");
+                LendingError::InvalidOracleConfig
+            })?
+        }
+        OracleSource::FixedPrice => {
+            if &lending_market.oracle_program_id != oracle_info.owner {
+                msg!("Fixed price account provided is not owned by the lending market oracle program");
+                return Err(LendingError::InvalidOracleConfig.into());
+            }
+            load_fixed_price(&oracle_info.try_borrow_data()?)?.quote_currency
+        }
     };
+    if lending_market.quote_currency != quote_currency {
+        msg!("Lending market quote currency does not match the oracle quote currency");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    Ok(oracle_info)
+}
+
+fn validate_pyth_oracle(
+    lending_market: &LendingMarket,
+    pyth_product_info: &AccountInfo,
+    pyth_price_info: &AccountInfo,
+) -> ProgramResult {
+    if &lending_market.oracle_program_id != pyth_product_info.owner {
+        msg!("Pyth product account provided is not owned by the lending market oracle program");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if &lending_market.oracle_program_id != pyth_price_info.owner {
+        msg!("Pyth price account provided is not owned by the lending market oracle program");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let pyth_product_data = pyth_product_info.try_borrow_data()?;
+    let pyth_product = pyth::load::<pyth::Product>(&pyth_product_data)
+        .map_err(|_| ProgramError::InvalidAccountData)?;
+    if pyth_product.magic != pyth::MAGIC {
+        msg!("Pyth product account provided is not a valid Pyth account");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if pyth_product.ver != pyth::VERSION_2 {
+        msg!("Pyth product account provided has a different version than expected");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+    if pyth_product.atype != pyth::AccountType::Product as u32 {
+        msg!("Pyth product account provided is not a valid Pyth product account");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let pyth_price_pubkey_bytes: &[u8; 32] = pyth_price_info
+        .key
+        .as_ref()
+        .try_into()
+        .map_err(|_| LendingError::InvalidAccountInput)?;
+    if &pyth_product.px_acc.val != pyth_price_pubkey_bytes {
+        msg!("Pyth product price account does not match the Pyth price provided");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    let quote_currency = get_pyth_product_quote_currency(pyth_product)?;
+    if lending_market.quote_currency != quote_currency {
+        msg!("Lending market quote currency does not match the oracle quote currency");
+        return Err(LendingError::InvalidOracleConfig.into());
+    }
+
+    Ok(())
+}
+
+fn assert_rent_exempt(rent: &Rent, account_info: &AccountInfo) -> ProgramResult {
+    if !rent.is_exempt(account_info.lamports(), account_info.data_len()) {
+        msg!(&rent.minimum_balance(account_info.data_len()).to_string());
+        Err(LendingError::NotRentExempt.into())
+    } else {
+        Ok(())
+    }
+}
+
+fn assert_uninitialized<T: Pack + IsInitialized>(
+    account_info: &AccountInfo,
+) -> Result<T, ProgramError> {
+    let account: T = T::unpack_unchecked(&account_info.data.borrow())?;
+    if account.is_initialized() {
+        Err(LendingError::AlreadyInitialized.into())
+    } else {
+        Ok(account)
+    }
+}
+
+/// Unpacks a spl_token `Mint`.
+fn unpack_mint(data: &[u8]) -> Result<Mint, LendingError> {
+    Mint::unpack(data).map_err(|_| LendingError::InvalidTokenMint)
+}
+
+fn get_pyth_product_quote_currency(pyth_product: &pyth::Product) -> Result<[u8; 32], ProgramError> {
+    const LEN: usize = 14;
+    const KEY: &[u8; LEN] = b"quote_currency";
+
+    let mut start = 0;
+    while start < pyth::PROD_ATTR_SIZE {
+        let mut length = pyth_product.attr[start] as usize;
+        start += 1;
+
+        if length == LEN {
+            let mut end = start + length;
+            if end > pyth::PROD_ATTR_SIZE {
+                msg!("Pyth product attribute key length too long");
+                return Err(LendingError::InvalidOracleConfig.into());
+            }
+
+            let key = &pyth_product.attr[start..end];
+            if key == KEY {
+                start += length;
+                length = pyth_product.attr[start] as usize;
+                start += 1;
+
+                end = start + length;
+                if length > 32 || end > pyth::PROD_ATTR_SIZE {
+                    msg!("Pyth product quote currency value too long");
+                    return Err(LendingError::InvalidOracleConfig.into());
+                }
+
+                let mut value = [0u8; 32];
+                value[0..length].copy_from_slice(&pyth_product.attr[start..end]);
+                return Ok(value);
+            }
+        }
 
-    Ok(market_price)
+        start += length;
+        start += 1 + pyth_product.attr[start] as usize;
+    }
+
+    msg!("Pyth product quote currency not found");
+    Err(LendingError::InvalidOracleConfig.into())
 }
 
 /// Issue a spl_token `InitializeAccount` instruction.
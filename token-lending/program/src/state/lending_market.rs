@@ -7,6 +7,9 @@ use solana_program::{
     pubkey::{Pubkey, PUBKEY_BYTES},
 };
 
+/// Max number of elevation groups in a lending market
+pub const MAX_ELEVATION_GROUPS: u8 = 8;
+
 /// Lending market state
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct LendingMarket {
@@ -23,6 +26,8 @@ pub struct LendingMarket {
     pub token_program_id: Pubkey,
     /// Oracle (Pyth) program id
     pub oracle_program_id: Pubkey,
+    /// Elevation groups, where elevation group `n` is at index `n - 1`
+    pub elevation_groups: [ElevationGroup; MAX_ELEVATION_GROUPS as usize],
 }
 
 impl LendingMarket {
@@ -42,6 +47,34 @@ impl LendingMarket {
         self.token_program_id = params.token_program_id;
         self.oracle_program_id = params.oracle_program_id;
     }
+
+    /// Find a configured elevation group by id, ignoring id 0 which means no elevation group
+    pub fn find_elevation_group(&self, elevation_group: u8) -> Option<&ElevationGroup> {
+        if elevation_group == 0 {
+            return None;
+        }
+        self.elevation_groups
+            .get(elevation_group as usize - 1)
+            .filter(|group| group.is_configured())
+    }
+}
+
+/// Risk parameters shared by a set of correlated reserves, such as stablecoins. They apply to an
+/// obligation which opted in to the group when all of its deposits and borrows are in the group.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ElevationGroup {
+    /// Target ratio of the value of borrows to deposits, as a percentage
+    pub loan_to_value_ratio: u8,
+    /// Loan to value ratio at which an obligation can be liquidated, as a percentage
+    /// 0 if the elevation group is not configured
+    pub liquidation_threshold: u8,
+}
+
+impl ElevationGroup {
+    /// Check if the elevation group has been configured by the lending market owner
+    pub fn is_configured(&self) -> bool {
+        self.liquidation_threshold > 0
+    }
 }
 
 /// Initialize a lending market
@@ -66,7 +99,8 @@ impl IsInitialized for LendingMarket {
     }
 }
 
-const LENDING_MARKET_LEN: usize = 258; // 1 + 1 + 32 + 32 + 32 + 32 + 128
+const ELEVATION_GROUP_LEN: usize = 2; // 1 + 1
+const LENDING_MARKET_LEN: usize = 258; // 1 + 1 + 32 + 32 + 32 + 32 + (2 * 8) + 112
 impl Pack for LendingMarket {
     const LEN: usize = LENDING_MARKET_LEN;
 
@@ -80,6 +114,7 @@ impl Pack for LendingMarket {
             quote_currency,
             token_program_id,
             oracle_program_id,
+            elevation_groups_flat,
             _padding,
         ) = mut_array_refs![
             output,
@@ -89,7 +124,8 @@ impl Pack for LendingMarket {
             32,
             PUBKEY_BYTES,
             PUBKEY_BYTES,
-            128
+            ELEVATION_GROUP_LEN * MAX_ELEVATION_GROUPS as usize,
+            112
         ];
 
         *version = self.version.to_le_bytes();
@@ -98,6 +134,18 @@ impl Pack for LendingMarket {
         quote_currency.copy_from_slice(self.quote_currency.as_ref());
         token_program_id.copy_from_slice(self.token_program_id.as_ref());
         oracle_program_id.copy_from_slice(self.oracle_program_id.as_ref());
+
+        for (index, group) in self.elevation_groups.iter().enumerate() {
+            let group_flat = array_mut_ref![
+                elevation_groups_flat,
+                index * ELEVATION_GROUP_LEN,
+                ELEVATION_GROUP_LEN
+            ];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (loan_to_value_ratio, liquidation_threshold) = mut_array_refs![group_flat, 1, 1];
+            *loan_to_value_ratio = group.loan_to_value_ratio.to_le_bytes();
+            *liquidation_threshold = group.liquidation_threshold.to_le_bytes();
+        }
     }
 
     /// Unpacks a byte buffer into a [LendingMarketInfo](struct.LendingMarketInfo.html)
@@ -111,6 +159,7 @@ impl Pack for LendingMarket {
             quote_currency,
             token_program_id,
             oracle_program_id,
+            elevation_groups_flat,
             _padding,
         ) = array_refs![
             input,
@@ -120,7 +169,8 @@ impl Pack for LendingMarket {
             32,
             PUBKEY_BYTES,
             PUBKEY_BYTES,
-            128
+            ELEVATION_GROUP_LEN * MAX_ELEVATION_GROUPS as usize,
+            112
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -129,6 +179,19 @@ impl Pack for LendingMarket {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        let mut elevation_groups = [ElevationGroup::default(); MAX_ELEVATION_GROUPS as usize];
+        for (index, group) in elevation_groups.iter_mut().enumerate() {
+            let group_flat = array_ref![
+                elevation_groups_flat,
+                index * ELEVATION_GROUP_LEN,
+                ELEVATION_GROUP_LEN
+            ];
+            #[allow(clippy::ptr_offset_with_cast)]
+            let (loan_to_value_ratio, liquidation_threshold) = array_refs![group_flat, 1, 1];
+            group.loan_to_value_ratio = u8::from_le_bytes(*loan_to_value_ratio);
+            group.liquidation_threshold = u8::from_le_bytes(*liquidation_threshold);
+        }
+
         Ok(Self {
             version,
             bump_seed: u8::from_le_bytes(*bump_seed),
@@ -136,6 +199,7 @@ impl Pack for LendingMarket {
             quote_currency: *quote_currency,
             token_program_id: Pubkey::new_from_array(*token_program_id),
             oracle_program_id: Pubkey::new_from_array(*oracle_program_id),
+            elevation_groups,
         })
     }
 }
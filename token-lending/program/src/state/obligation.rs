@@ -43,6 +43,9 @@ pub struct Obligation {
     pub allowed_borrow_value: Decimal,
     /// The dangerous borrow value at the weighted average liquidation threshold
     pub unhealthy_borrow_value: Decimal,
+    /// Lending market elevation group the owner opted in to
+    /// 0 if the obligation is not in an elevation group
+    pub elevation_group: u8,
 }
 
 impl Obligation {
@@ -326,11 +329,48 @@ impl ObligationLiquidity {
 
 const OBLIGATION_COLLATERAL_LEN: usize = 56; // 32 + 8 + 16
 const OBLIGATION_LIQUIDITY_LEN: usize = 80; // 32 + 16 + 16 + 16
-const OBLIGATION_LEN: usize = 916; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 1 + 1 + (56 * 1) + (80 * 9)
+const OBLIGATION_LEN: usize = 980; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 1 + 1 + (56 * 1) + (80 * 9) + 1 + 63
                                    // @TODO: break this up by obligation / collateral / liquidity https://git.io/JOCca
+/// Length of obligations created before elevation groups, which are read as not in a group
+const OBLIGATION_LEN_V1: usize = 916; // 1 + 8 + 1 + 32 + 32 + 16 + 16 + 16 + 16 + 1 + 1 + (56 * 1) + (80 * 9)
+
 impl Pack for Obligation {
     const LEN: usize = OBLIGATION_LEN;
 
+    /// Packs into an account of either the current or the pre-elevation group length
+    fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        match dst.len() {
+            OBLIGATION_LEN => {
+                src.pack_into_slice(dst);
+                Ok(())
+            }
+            OBLIGATION_LEN_V1 => {
+                if src.elevation_group != 0 {
+                    msg!("Obligation account is too small to store an elevation group, realloc it first");
+                    return Err(ProgramError::AccountDataTooSmall);
+                }
+                let mut output = [0u8; OBLIGATION_LEN];
+                src.pack_into_slice(&mut output);
+                dst.copy_from_slice(&output[..OBLIGATION_LEN_V1]);
+                Ok(())
+            }
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
+    /// Unpacks an account of either the current or the pre-elevation group length
+    fn unpack_unchecked(input: &[u8]) -> Result<Self, ProgramError> {
+        match input.len() {
+            OBLIGATION_LEN => Self::unpack_from_slice(input),
+            OBLIGATION_LEN_V1 => {
+                let mut padded = [0u8; OBLIGATION_LEN];
+                padded[..OBLIGATION_LEN_V1].copy_from_slice(input);
+                Self::unpack_from_slice(&padded)
+            }
+            _ => Err(ProgramError::InvalidAccountData),
+        }
+    }
+
     fn pack_into_slice(&self, dst: &mut [u8]) {
         let output = array_mut_ref![dst, 0, OBLIGATION_LEN];
         #[allow(clippy::ptr_offset_with_cast)]
@@ -347,6 +387,8 @@ impl Pack for Obligation {
             deposits_len,
             borrows_len,
             data_flat,
+            elevation_group,
+            _padding,
         ) = mut_array_refs![
             output,
             1,
@@ -360,7 +402,9 @@ impl Pack for Obligation {
             16,
             1,
             1,
-            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1))
+            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1)),
+            1,
+            63
         ];
 
         // obligation
@@ -375,6 +419,7 @@ impl Pack for Obligation {
         pack_decimal(self.unhealthy_borrow_value, unhealthy_borrow_value);
         *deposits_len = u8::try_from(self.deposits.len()).unwrap().to_le_bytes();
         *borrows_len = u8::try_from(self.borrows.len()).unwrap().to_le_bytes();
+        *elevation_group = self.elevation_group.to_le_bytes();
 
         let mut offset = 0;
 
@@ -424,6 +469,8 @@ impl Pack for Obligation {
             deposits_len,
             borrows_len,
             data_flat,
+            elevation_group,
+            _padding,
         ) = array_refs![
             input,
             1,
@@ -437,7 +484,9 @@ impl Pack for Obligation {
             16,
             1,
             1,
-            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1))
+            OBLIGATION_COLLATERAL_LEN + (OBLIGATION_LIQUIDITY_LEN * (MAX_OBLIGATION_RESERVES - 1)),
+            1,
+            63
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -492,6 +541,7 @@ impl Pack for Obligation {
             borrowed_value: unpack_decimal(borrowed_value),
             allowed_borrow_value: unpack_decimal(allowed_borrow_value),
             unhealthy_borrow_value: unpack_decimal(unhealthy_borrow_value),
+            elevation_group: u8::from_le_bytes(*elevation_group),
         })
     }
 }
@@ -504,6 +554,62 @@ mod test {
 
     const MAX_COMPOUNDED_INTEREST: u64 = 100; // 10,000%
 
+    fn packed_obligation(elevation_group: u8) -> Obligation {
+        Obligation {
+            version: PROGRAM_VERSION,
+            last_update: LastUpdate::new(7),
+            lending_market: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            deposits: vec![ObligationCollateral {
+                deposit_reserve: Pubkey::new_unique(),
+                deposited_amount: 100,
+                market_value: Decimal::from(100u64),
+            }],
+            borrows: vec![ObligationLiquidity {
+                borrow_reserve: Pubkey::new_unique(),
+                cumulative_borrow_rate_wads: Decimal::one(),
+                borrowed_amount_wads: Decimal::from(50u64),
+                market_value: Decimal::from(50u64),
+            }],
+            deposited_value: Decimal::from(100u64),
+            borrowed_value: Decimal::from(50u64),
+            elevation_group,
+            ..Obligation::default()
+        }
+    }
+
+    #[test]
+    fn pack_and_unpack_obligation() {
+        let obligation = packed_obligation(2);
+        let mut data = [0u8; OBLIGATION_LEN];
+        Obligation::pack(obligation.clone(), &mut data).unwrap();
+        assert_eq!(Obligation::unpack(&data).unwrap(), obligation);
+    }
+
+    #[test]
+    fn unpack_legacy_length_obligation() {
+        let obligation = packed_obligation(0);
+        let mut data = [0u8; OBLIGATION_LEN];
+        Obligation::pack(obligation.clone(), &mut data).unwrap();
+
+        // obligations created before elevation groups end right after the reserves
+        let mut legacy = data[..OBLIGATION_LEN_V1].to_vec();
+        assert_eq!(Obligation::unpack(&legacy).unwrap(), obligation);
+
+        // and can still be written back, but only outside of an elevation group
+        Obligation::pack(obligation, &mut legacy).unwrap();
+        assert_eq!(legacy, data[..OBLIGATION_LEN_V1].to_vec());
+        assert_eq!(
+            Obligation::pack(packed_obligation(1), &mut legacy),
+            Err(ProgramError::AccountDataTooSmall)
+        );
+
+        assert_eq!(
+            Obligation::unpack(&data[..OBLIGATION_LEN_V1 - 1]),
+            Err(ProgramError::InvalidAccountData)
+        );
+    }
+
     #[test]
     fn obligation_accrue_interest_failure() {
         assert_eq!(
@@ -2,8 +2,10 @@ use super::*;
 use crate::{
     error::LendingError,
     math::{Decimal, Rate, TryAdd, TryDiv, TryMul, TrySub},
+    oracle::OracleSource,
 };
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
+use num_traits::FromPrimitive;
 use solana_program::{
     clock::Slot,
     entrypoint::ProgramResult,
@@ -23,6 +25,11 @@ pub const LIQUIDATION_CLOSE_FACTOR: u8 = 50;
 /// Obligation borrow amount that is small enough to close out
 pub const LIQUIDATION_CLOSE_AMOUNT: u64 = 2;
 
+/// Current version of the packed reserve config
+/// Version 0 configs store an optimal utilization rate and min, optimal, and max borrow rates
+/// instead of a borrow rate curve
+pub const RESERVE_CONFIG_VERSION: u8 = 1;
+
 /// Lending market reserve state
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Reserve {
@@ -85,37 +92,16 @@ impl Reserve {
     /// Calculate the current borrow rate
     pub fn current_borrow_rate(&self) -> Result<Rate, ProgramError> {
         let utilization_rate = self.liquidity.utilization_rate()?;
-        let optimal_utilization_rate = Rate::from_percent(self.config.optimal_utilization_rate);
-        let low_utilization = utilization_rate < optimal_utilization_rate;
-        if low_utilization || self.config.optimal_utilization_rate == 100 {
-            let normalized_rate = utilization_rate.try_div(optimal_utilization_rate)?;
-            let min_rate = Rate::from_percent(self.config.min_borrow_rate);
-            let rate_range = Rate::from_percent(
-                self.config
-                    .optimal_borrow_rate
-                    .checked_sub(self.config.min_borrow_rate)
-                    .ok_or(LendingError::MathOverflow)?,
-            );
-
-            Ok(normalized_rate.try_mul(rate_range)?.try_add(min_rate)?)
-        } else {
-            let normalized_rate = utilization_rate
-                .try_sub(optimal_utilization_rate)?
-                .try_div(Rate::from_percent(
-                    100u8
-                        .checked_sub(self.config.optimal_utilization_rate)
-                        .ok_or(LendingError::MathOverflow)?,
-                ))?;
-            let min_rate = Rate::from_percent(self.config.optimal_borrow_rate);
-            let rate_range = Rate::from_percent(
-                self.config
-                    .max_borrow_rate
-                    .checked_sub(self.config.optimal_borrow_rate)
-                    .ok_or(LendingError::MathOverflow)?,
-            );
+        self.config.borrow_rate_curve.borrow_rate(utilization_rate)
+    }
 
-            Ok(normalized_rate.try_mul(rate_range)?.try_add(min_rate)?)
-        }
+    /// Calculate the amount of protocol fees that can be redeemed from available liquidity
+    pub fn calculate_redeem_fees(&self) -> Result<u64, ProgramError> {
+        Ok(self
+            .liquidity
+            .accumulated_protocol_fees_wads
+            .try_floor_u64()?
+            .min(self.liquidity.available_amount))
     }
 
     /// Collateral exchange rate
@@ -129,8 +115,12 @@ impl Reserve {
         let slots_elapsed = self.last_update.slots_elapsed(current_slot)?;
         if slots_elapsed > 0 {
             let current_borrow_rate = self.current_borrow_rate()?;
-            self.liquidity
-                .compound_interest(current_borrow_rate, slots_elapsed)?;
+            let protocol_take_rate = Rate::from_percent(self.config.protocol_take_rate);
+            self.liquidity.compound_interest(
+                current_borrow_rate,
+                slots_elapsed,
+                protocol_take_rate,
+            )?;
         }
         Ok(())
     }
@@ -357,6 +347,13 @@ pub struct ReserveLiquidity {
     pub fee_receiver: Pubkey,
     /// Reserve liquidity oracle account
     pub oracle_pubkey: Pubkey,
+    /// Reserve liquidity oracle provider
+    pub oracle_source: OracleSource,
+    /// Reserve liquidity fallback oracle account, read when the oracle price is unavailable
+    /// Default pubkey if the reserve has no fallback oracle
+    pub fallback_oracle_pubkey: Pubkey,
+    /// Reserve liquidity fallback oracle provider
+    pub fallback_oracle_source: OracleSource,
     /// Reserve liquidity available
     pub available_amount: u64,
     /// Reserve liquidity borrowed
@@ -365,6 +362,8 @@ pub struct ReserveLiquidity {
     pub cumulative_borrow_rate_wads: Decimal,
     /// Reserve liquidity market price in quote currency
     pub market_price: Decimal,
+    /// Reserve liquidity owed to the fee receiver from accrued interest, not yet redeemed
+    pub accumulated_protocol_fees_wads: Decimal,
 }
 
 impl ReserveLiquidity {
@@ -376,16 +375,22 @@ impl ReserveLiquidity {
             supply_pubkey: params.supply_pubkey,
             fee_receiver: params.fee_receiver,
             oracle_pubkey: params.oracle_pubkey,
+            oracle_source: params.oracle_source,
+            fallback_oracle_pubkey: Pubkey::default(),
+            fallback_oracle_source: OracleSource::default(),
             available_amount: 0,
             borrowed_amount_wads: Decimal::zero(),
             cumulative_borrow_rate_wads: Decimal::one(),
             market_price: params.market_price,
+            accumulated_protocol_fees_wads: Decimal::zero(),
         }
     }
 
-    /// Calculate the total reserve supply including active loans
+    /// Calculate the total reserve supply including active loans, excluding protocol fees
     pub fn total_supply(&self) -> Result<Decimal, ProgramError> {
-        Decimal::from(self.available_amount).try_add(self.borrowed_amount_wads)
+        Decimal::from(self.available_amount)
+            .try_add(self.borrowed_amount_wads)?
+            .try_sub(self.accumulated_protocol_fees_wads)
     }
 
     /// Add liquidity to available amount
@@ -438,6 +443,24 @@ impl ReserveLiquidity {
         Ok(())
     }
 
+    /// Remove redeemed protocol fees from available liquidity and accumulated protocol fees
+    pub fn redeem_fees(&mut self, withdraw_amount: u64) -> ProgramResult {
+        if withdraw_amount > self.available_amount {
+            msg!("Redeem fees amount cannot exceed available amount");
+            return Err(LendingError::InsufficientLiquidity.into());
+        }
+
+        self.available_amount = self
+            .available_amount
+            .checked_sub(withdraw_amount)
+            .ok_or(LendingError::MathOverflow)?;
+        self.accumulated_protocol_fees_wads = self
+            .accumulated_protocol_fees_wads
+            .try_sub(withdraw_amount.into())?;
+
+        Ok(())
+    }
+
     /// Calculate the liquidity utilization rate of the reserve
     pub fn utilization_rate(&self) -> Result<Rate, ProgramError> {
         let total_supply = self.total_supply()?;
@@ -447,11 +470,13 @@ impl ReserveLiquidity {
         self.borrowed_amount_wads.try_div(total_supply)?.try_into()
     }
 
-    /// Compound current borrow rate over elapsed slots
+    /// Compound current borrow rate over elapsed slots, setting aside the protocol take rate of
+    /// the accrued interest as protocol fees
     fn compound_interest(
         &mut self,
         current_borrow_rate: Rate,
         slots_elapsed: u64,
+        protocol_take_rate: Rate,
     ) -> ProgramResult {
         let slot_interest_rate = current_borrow_rate.try_div(SLOTS_PER_YEAR)?;
         let compounded_interest_rate = Rate::one()
@@ -460,9 +485,17 @@ impl ReserveLiquidity {
         self.cumulative_borrow_rate_wads = self
             .cumulative_borrow_rate_wads
             .try_mul(compounded_interest_rate)?;
-        self.borrowed_amount_wads = self
+
+        let borrowed_amount_wads = self
             .borrowed_amount_wads
             .try_mul(compounded_interest_rate)?;
+        let protocol_fees_wads = borrowed_amount_wads
+            .try_sub(self.borrowed_amount_wads)?
+            .try_mul(protocol_take_rate)?;
+        self.accumulated_protocol_fees_wads = self
+            .accumulated_protocol_fees_wads
+            .try_add(protocol_fees_wads)?;
+        self.borrowed_amount_wads = borrowed_amount_wads;
         Ok(())
     }
 }
@@ -479,6 +512,8 @@ pub struct NewReserveLiquidityParams {
     pub fee_receiver: Pubkey,
     /// Reserve liquidity oracle account
     pub oracle_pubkey: Pubkey,
+    /// Reserve liquidity oracle provider
+    pub oracle_source: OracleSource,
     /// Reserve liquidity market price in quote currency
     pub market_price: Decimal,
 }
@@ -589,8 +624,6 @@ impl From<CollateralExchangeRate> for Rate {
 /// Reserve configuration values
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct ReserveConfig {
-    /// Optimal utilization rate, as a percentage
-    pub optimal_utilization_rate: u8,
     /// Target ratio of the value of borrows to deposits, as a percentage
     /// 0 if use as collateral is disabled
     pub loan_to_value_ratio: u8,
@@ -598,14 +631,143 @@ pub struct ReserveConfig {
     pub liquidation_bonus: u8,
     /// Loan to value ratio at which an obligation can be liquidated, as a percentage
     pub liquidation_threshold: u8,
-    /// Min borrow APY
-    pub min_borrow_rate: u8,
-    /// Optimal (utilization) borrow APY
-    pub optimal_borrow_rate: u8,
-    /// Max borrow APY
-    pub max_borrow_rate: u8,
+    /// Borrow APY by utilization rate
+    pub borrow_rate_curve: BorrowRateCurve,
     /// Program owner fees assessed, separate from gains due to interest accrual
     pub fees: ReserveFees,
+    /// Max width of the oracle price confidence interval, in basis points of the price
+    /// 0 if the confidence check is disabled
+    pub max_price_confidence_bps: u16,
+    /// Max total liquidity supplied to the reserve, including borrows, in liquidity tokens
+    /// 0 if deposits are unlimited
+    pub deposit_limit: u64,
+    /// Max total liquidity borrowed from the reserve, in liquidity tokens
+    /// 0 if borrows are unlimited
+    pub borrow_limit: u64,
+    /// Lending market elevation group the reserve belongs to
+    /// 0 if the reserve is not in an elevation group
+    pub elevation_group: u8,
+    /// Share of accrued interest set aside as protocol fees for the fee receiver, as a percentage
+    pub protocol_take_rate: u8,
+}
+
+/// Max number of points in a borrow rate curve
+pub const MAX_BORROW_RATE_CURVE_POINTS: usize = 11;
+
+/// Point of a borrow rate curve
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BorrowRateCurvePoint {
+    /// Utilization rate, in basis points
+    pub utilization_rate_bps: u16,
+    /// Borrow APY at the utilization rate, in basis points
+    pub borrow_rate_bps: u16,
+}
+
+/// Piecewise-linear borrow rate curve
+///
+/// The borrow rate is interpolated between the two points around the utilization rate. A valid
+/// curve starts at 0% utilization, ends at 100% utilization, and its borrow rate never decreases.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct BorrowRateCurve {
+    points_len: u8,
+    points: [BorrowRateCurvePoint; MAX_BORROW_RATE_CURVE_POINTS],
+}
+
+impl BorrowRateCurve {
+    /// Create a borrow rate curve from (utilization rate, borrow rate) points, in basis points
+    pub fn new(points: &[(u16, u16)]) -> Result<Self, ProgramError> {
+        if points.len() > MAX_BORROW_RATE_CURVE_POINTS {
+            msg!(
+                "Borrow rate curve cannot have more than {} points",
+                MAX_BORROW_RATE_CURVE_POINTS
+            );
+            return Err(LendingError::InvalidConfig.into());
+        }
+
+        Ok(Self::from_points(points))
+    }
+
+    /// Create the borrow rate curve equivalent to the optimal utilization rate and min, optimal,
+    /// and max borrow APY of reserve config version 0, all as percentages
+    pub const fn from_legacy(
+        optimal_utilization_rate: u8,
+        min_borrow_rate: u8,
+        optimal_borrow_rate: u8,
+        max_borrow_rate: u8,
+    ) -> Self {
+        let optimal_utilization_rate = optimal_utilization_rate as u16 * 100;
+        let min_borrow_rate = min_borrow_rate as u16 * 100;
+        let optimal_borrow_rate = optimal_borrow_rate as u16 * 100;
+        let max_borrow_rate = max_borrow_rate as u16 * 100;
+        match optimal_utilization_rate {
+            0 => Self::from_points(&[(0, optimal_borrow_rate), (10_000, max_borrow_rate)]),
+            10_000..=u16::MAX => {
+                Self::from_points(&[(0, min_borrow_rate), (10_000, optimal_borrow_rate)])
+            }
+            _ => Self::from_points(&[
+                (0, min_borrow_rate),
+                (optimal_utilization_rate, optimal_borrow_rate),
+                (10_000, max_borrow_rate),
+            ]),
+        }
+    }
+
+    const fn from_points(points: &[(u16, u16)]) -> Self {
+        let mut curve = Self {
+            points_len: points.len() as u8,
+            points: [BorrowRateCurvePoint {
+                utilization_rate_bps: 0,
+                borrow_rate_bps: 0,
+            }; MAX_BORROW_RATE_CURVE_POINTS],
+        };
+        let mut i = 0;
+        while i < points.len() {
+            curve.points[i] = BorrowRateCurvePoint {
+                utilization_rate_bps: points[i].0,
+                borrow_rate_bps: points[i].1,
+            };
+            i += 1;
+        }
+        curve
+    }
+
+    /// Points of the curve, in order of utilization rate
+    pub fn points(&self) -> &[BorrowRateCurvePoint] {
+        &self.points[..self.points_len as usize]
+    }
+
+    /// Calculate the borrow rate at a utilization rate
+    pub fn borrow_rate(&self, utilization_rate: Rate) -> Result<Rate, ProgramError> {
+        let points = self.points();
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Ok(Rate::zero()),
+        };
+        if utilization_rate <= Rate::from_bps(first.utilization_rate_bps) {
+            return Ok(Rate::from_bps(first.borrow_rate_bps));
+        }
+
+        for window in points.windows(2) {
+            let (start, end) = (window[0], window[1]);
+            let end_utilization_rate = Rate::from_bps(end.utilization_rate_bps);
+            if utilization_rate <= end_utilization_rate {
+                let start_utilization_rate = Rate::from_bps(start.utilization_rate_bps);
+                let normalized_rate = utilization_rate
+                    .try_sub(start_utilization_rate)?
+                    .try_div(end_utilization_rate.try_sub(start_utilization_rate)?)?;
+                let min_rate = Rate::from_bps(start.borrow_rate_bps);
+                let rate_range = Rate::from_bps(
+                    end.borrow_rate_bps
+                        .checked_sub(start.borrow_rate_bps)
+                        .ok_or(LendingError::MathOverflow)?,
+                );
+
+                return normalized_rate.try_mul(rate_range)?.try_add(min_rate);
+            }
+        }
+
+        Ok(Rate::from_bps(last.borrow_rate_bps))
+    }
 }
 
 /// Additional fee information on a reserve
@@ -716,7 +878,8 @@ impl IsInitialized for Reserve {
     }
 }
 
-const RESERVE_LEN: usize = 571; // 1 + 8 + 1 + 32 + 32 + 1 + 32 + 32 + 32 + 8 + 16 + 16 + 16 + 32 + 8 + 32 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + 1 + 248
+const BORROW_RATE_CURVE_LEN: usize = 45; // 1 + 4 * 11
+const RESERVE_LEN: usize = 571; // 1 + 8 + 1 + 32 + 32 + 1 + 32 + 32 + 32 + 8 + 16 + 16 + 16 + 32 + 8 + 32 + 1 + 1 + 1 + 1 + 1 + 1 + 1 + 8 + 8 + 1 + 2 + 1 + 32 + 1 + 8 + 8 + 1 + 1 + 45 + 1 + 16 + 132
 impl Pack for Reserve {
     const LEN: usize = RESERVE_LEN;
 
@@ -741,16 +904,27 @@ impl Pack for Reserve {
             collateral_mint_pubkey,
             collateral_mint_total_supply,
             collateral_supply_pubkey,
-            config_optimal_utilization_rate,
+            config_legacy_optimal_utilization_rate,
             config_loan_to_value_ratio,
             config_liquidation_bonus,
             config_liquidation_threshold,
-            config_min_borrow_rate,
-            config_optimal_borrow_rate,
-            config_max_borrow_rate,
+            config_legacy_min_borrow_rate,
+            config_legacy_optimal_borrow_rate,
+            config_legacy_max_borrow_rate,
             config_fees_borrow_fee_wad,
             config_fees_flash_loan_fee_wad,
             config_fees_host_fee_percentage,
+            config_max_price_confidence_bps,
+            liquidity_oracle_source,
+            liquidity_fallback_oracle_pubkey,
+            liquidity_fallback_oracle_source,
+            config_deposit_limit,
+            config_borrow_limit,
+            config_elevation_group,
+            config_version,
+            config_borrow_rate_curve,
+            config_protocol_take_rate,
+            liquidity_accumulated_protocol_fees_wads,
             _padding,
         ) = mut_array_refs![
             output,
@@ -780,7 +954,18 @@ impl Pack for Reserve {
             8,
             8,
             1,
-            248
+            2,
+            1,
+            PUBKEY_BYTES,
+            1,
+            8,
+            8,
+            1,
+            1,
+            BORROW_RATE_CURVE_LEN,
+            1,
+            16,
+            132
         ];
 
         // reserve
@@ -795,6 +980,11 @@ impl Pack for Reserve {
         liquidity_supply_pubkey.copy_from_slice(self.liquidity.supply_pubkey.as_ref());
         liquidity_fee_receiver.copy_from_slice(self.liquidity.fee_receiver.as_ref());
         liquidity_oracle_pubkey.copy_from_slice(self.liquidity.oracle_pubkey.as_ref());
+        *liquidity_oracle_source = (self.liquidity.oracle_source as u8).to_le_bytes();
+        liquidity_fallback_oracle_pubkey
+            .copy_from_slice(self.liquidity.fallback_oracle_pubkey.as_ref());
+        *liquidity_fallback_oracle_source =
+            (self.liquidity.fallback_oracle_source as u8).to_le_bytes();
         *liquidity_available_amount = self.liquidity.available_amount.to_le_bytes();
         pack_decimal(
             self.liquidity.borrowed_amount_wads,
@@ -805,6 +995,10 @@ impl Pack for Reserve {
             liquidity_cumulative_borrow_rate_wads,
         );
         pack_decimal(self.liquidity.market_price, liquidity_market_price);
+        pack_decimal(
+            self.liquidity.accumulated_protocol_fees_wads,
+            liquidity_accumulated_protocol_fees_wads,
+        );
 
         // collateral
         collateral_mint_pubkey.copy_from_slice(self.collateral.mint_pubkey.as_ref());
@@ -812,16 +1006,23 @@ impl Pack for Reserve {
         collateral_supply_pubkey.copy_from_slice(self.collateral.supply_pubkey.as_ref());
 
         // config
-        *config_optimal_utilization_rate = self.config.optimal_utilization_rate.to_le_bytes();
+        *config_version = RESERVE_CONFIG_VERSION.to_le_bytes();
+        *config_legacy_optimal_utilization_rate = [0];
         *config_loan_to_value_ratio = self.config.loan_to_value_ratio.to_le_bytes();
         *config_liquidation_bonus = self.config.liquidation_bonus.to_le_bytes();
         *config_liquidation_threshold = self.config.liquidation_threshold.to_le_bytes();
-        *config_min_borrow_rate = self.config.min_borrow_rate.to_le_bytes();
-        *config_optimal_borrow_rate = self.config.optimal_borrow_rate.to_le_bytes();
-        *config_max_borrow_rate = self.config.max_borrow_rate.to_le_bytes();
+        *config_legacy_min_borrow_rate = [0];
+        *config_legacy_optimal_borrow_rate = [0];
+        *config_legacy_max_borrow_rate = [0];
+        pack_borrow_rate_curve(&self.config.borrow_rate_curve, config_borrow_rate_curve);
         *config_fees_borrow_fee_wad = self.config.fees.borrow_fee_wad.to_le_bytes();
         *config_fees_flash_loan_fee_wad = self.config.fees.flash_loan_fee_wad.to_le_bytes();
         *config_fees_host_fee_percentage = self.config.fees.host_fee_percentage.to_le_bytes();
+        *config_max_price_confidence_bps = self.config.max_price_confidence_bps.to_le_bytes();
+        *config_deposit_limit = self.config.deposit_limit.to_le_bytes();
+        *config_borrow_limit = self.config.borrow_limit.to_le_bytes();
+        *config_elevation_group = self.config.elevation_group.to_le_bytes();
+        *config_protocol_take_rate = self.config.protocol_take_rate.to_le_bytes();
     }
 
     /// Unpacks a byte buffer into a [ReserveInfo](struct.ReserveInfo.html).
@@ -845,16 +1046,27 @@ impl Pack for Reserve {
             collateral_mint_pubkey,
             collateral_mint_total_supply,
             collateral_supply_pubkey,
-            config_optimal_utilization_rate,
+            config_legacy_optimal_utilization_rate,
             config_loan_to_value_ratio,
             config_liquidation_bonus,
             config_liquidation_threshold,
-            config_min_borrow_rate,
-            config_optimal_borrow_rate,
-            config_max_borrow_rate,
+            config_legacy_min_borrow_rate,
+            config_legacy_optimal_borrow_rate,
+            config_legacy_max_borrow_rate,
             config_fees_borrow_fee_wad,
             config_fees_flash_loan_fee_wad,
             config_fees_host_fee_percentage,
+            config_max_price_confidence_bps,
+            liquidity_oracle_source,
+            liquidity_fallback_oracle_pubkey,
+            liquidity_fallback_oracle_source,
+            config_deposit_limit,
+            config_borrow_limit,
+            config_elevation_group,
+            config_version,
+            config_borrow_rate_curve,
+            config_protocol_take_rate,
+            liquidity_accumulated_protocol_fees_wads,
             _padding,
         ) = array_refs![
             input,
@@ -884,7 +1096,18 @@ impl Pack for Reserve {
             8,
             8,
             1,
-            248
+            2,
+            1,
+            PUBKEY_BYTES,
+            1,
+            8,
+            8,
+            1,
+            1,
+            BORROW_RATE_CURVE_LEN,
+            1,
+            16,
+            132
         ];
 
         let version = u8::from_le_bytes(*version);
@@ -893,6 +1116,21 @@ impl Pack for Reserve {
             return Err(ProgramError::InvalidAccountData);
         }
 
+        let borrow_rate_curve = match u8::from_le_bytes(*config_version) {
+            // version 0 configs have a two-segment curve, which is migrated when unpacked
+            0 => BorrowRateCurve::from_legacy(
+                u8::from_le_bytes(*config_legacy_optimal_utilization_rate),
+                u8::from_le_bytes(*config_legacy_min_borrow_rate),
+                u8::from_le_bytes(*config_legacy_optimal_borrow_rate),
+                u8::from_le_bytes(*config_legacy_max_borrow_rate),
+            ),
+            RESERVE_CONFIG_VERSION => unpack_borrow_rate_curve(config_borrow_rate_curve)?,
+            _ => {
+                msg!("Reserve config version does not match lending program version");
+                return Err(ProgramError::InvalidAccountData);
+            }
+        };
+
         Ok(Self {
             version,
             last_update: LastUpdate {
@@ -906,10 +1144,16 @@ impl Pack for Reserve {
                 supply_pubkey: Pubkey::new_from_array(*liquidity_supply_pubkey),
                 fee_receiver: Pubkey::new_from_array(*liquidity_fee_receiver),
                 oracle_pubkey: Pubkey::new_from_array(*liquidity_oracle_pubkey),
+                oracle_source: unpack_oracle_source(liquidity_oracle_source)?,
+                fallback_oracle_pubkey: Pubkey::new_from_array(*liquidity_fallback_oracle_pubkey),
+                fallback_oracle_source: unpack_oracle_source(liquidity_fallback_oracle_source)?,
                 available_amount: u64::from_le_bytes(*liquidity_available_amount),
                 borrowed_amount_wads: unpack_decimal(liquidity_borrowed_amount_wads),
                 cumulative_borrow_rate_wads: unpack_decimal(liquidity_cumulative_borrow_rate_wads),
                 market_price: unpack_decimal(liquidity_market_price),
+                accumulated_protocol_fees_wads: unpack_decimal(
+                    liquidity_accumulated_protocol_fees_wads,
+                ),
             },
             collateral: ReserveCollateral {
                 mint_pubkey: Pubkey::new_from_array(*collateral_mint_pubkey),
@@ -917,23 +1161,68 @@ impl Pack for Reserve {
                 supply_pubkey: Pubkey::new_from_array(*collateral_supply_pubkey),
             },
             config: ReserveConfig {
-                optimal_utilization_rate: u8::from_le_bytes(*config_optimal_utilization_rate),
                 loan_to_value_ratio: u8::from_le_bytes(*config_loan_to_value_ratio),
                 liquidation_bonus: u8::from_le_bytes(*config_liquidation_bonus),
                 liquidation_threshold: u8::from_le_bytes(*config_liquidation_threshold),
-                min_borrow_rate: u8::from_le_bytes(*config_min_borrow_rate),
-                optimal_borrow_rate: u8::from_le_bytes(*config_optimal_borrow_rate),
-                max_borrow_rate: u8::from_le_bytes(*config_max_borrow_rate),
+                borrow_rate_curve,
                 fees: ReserveFees {
                     borrow_fee_wad: u64::from_le_bytes(*config_fees_borrow_fee_wad),
                     flash_loan_fee_wad: u64::from_le_bytes(*config_fees_flash_loan_fee_wad),
                     host_fee_percentage: u8::from_le_bytes(*config_fees_host_fee_percentage),
                 },
+                max_price_confidence_bps: u16::from_le_bytes(*config_max_price_confidence_bps),
+                deposit_limit: u64::from_le_bytes(*config_deposit_limit),
+                borrow_limit: u64::from_le_bytes(*config_borrow_limit),
+                elevation_group: u8::from_le_bytes(*config_elevation_group),
+                protocol_take_rate: u8::from_le_bytes(*config_protocol_take_rate),
             },
         })
     }
 }
 
+fn pack_borrow_rate_curve(curve: &BorrowRateCurve, dst: &mut [u8; BORROW_RATE_CURVE_LEN]) {
+    #[allow(clippy::ptr_offset_with_cast)]
+    let (points_len, points_flat) = mut_array_refs![dst, 1, 4 * MAX_BORROW_RATE_CURVE_POINTS];
+    *points_len = curve.points_len.to_le_bytes();
+    for (point, point_flat) in curve.points.iter().zip(points_flat.chunks_exact_mut(4)) {
+        let point_flat = array_mut_ref![point_flat, 0, 4];
+        let (utilization_rate_bps, borrow_rate_bps) = mut_array_refs![point_flat, 2, 2];
+        *utilization_rate_bps = point.utilization_rate_bps.to_le_bytes();
+        *borrow_rate_bps = point.borrow_rate_bps.to_le_bytes();
+    }
+}
+
+fn unpack_borrow_rate_curve(
+    src: &[u8; BORROW_RATE_CURVE_LEN],
+) -> Result<BorrowRateCurve, ProgramError> {
+    #[allow(clippy::ptr_offset_with_cast)]
+    let (points_len, points_flat) = array_refs![src, 1, 4 * MAX_BORROW_RATE_CURVE_POINTS];
+    let points_len = u8::from_le_bytes(*points_len);
+    if points_len as usize > MAX_BORROW_RATE_CURVE_POINTS {
+        msg!("Borrow rate curve cannot be unpacked");
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    let mut curve = BorrowRateCurve {
+        points_len,
+        ..BorrowRateCurve::default()
+    };
+    for (point, point_flat) in curve.points.iter_mut().zip(points_flat.chunks_exact(4)) {
+        let point_flat = array_ref![point_flat, 0, 4];
+        let (utilization_rate_bps, borrow_rate_bps) = array_refs![point_flat, 2, 2];
+        point.utilization_rate_bps = u16::from_le_bytes(*utilization_rate_bps);
+        point.borrow_rate_bps = u16::from_le_bytes(*borrow_rate_bps);
+    }
+    Ok(curve)
+}
+
+fn unpack_oracle_source(src: &[u8; 1]) -> Result<OracleSource, ProgramError> {
+    OracleSource::from_u8(u8::from_le_bytes(*src)).ok_or_else(|| {
+        msg!("Oracle source cannot be unpacked");
+        ProgramError::InvalidAccountData
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1001,7 +1290,10 @@ mod test {
                     available_amount: total_liquidity - borrowed_amount_wads.try_round_u64()?,
                     ..ReserveLiquidity::default()
                 },
-                config: ReserveConfig { optimal_utilization_rate, min_borrow_rate, optimal_borrow_rate, max_borrow_rate, ..ReserveConfig::default() },
+                config: ReserveConfig {
+                    borrow_rate_curve: BorrowRateCurve::from_legacy(optimal_utilization_rate, min_borrow_rate, optimal_borrow_rate, max_borrow_rate),
+                    ..ReserveConfig::default()
+                },
                 ..Reserve::default()
             };
 
@@ -1009,22 +1301,22 @@ mod test {
             assert!(current_borrow_rate >= Rate::from_percent(min_borrow_rate));
             assert!(current_borrow_rate <= Rate::from_percent(max_borrow_rate));
 
-            let optimal_borrow_rate = Rate::from_percent(optimal_borrow_rate);
+            let optimal_rate = Rate::from_percent(optimal_borrow_rate);
             let current_rate = reserve.liquidity.utilization_rate()?;
             match current_rate.cmp(&Rate::from_percent(optimal_utilization_rate)) {
                 Ordering::Less => {
-                    if min_borrow_rate == reserve.config.optimal_borrow_rate {
-                        assert_eq!(current_borrow_rate, optimal_borrow_rate);
+                    if min_borrow_rate == optimal_borrow_rate {
+                        assert_eq!(current_borrow_rate, optimal_rate);
                     } else {
-                        assert!(current_borrow_rate < optimal_borrow_rate);
+                        assert!(current_borrow_rate < optimal_rate);
                     }
                 }
-                Ordering::Equal => assert!(current_borrow_rate == optimal_borrow_rate),
+                Ordering::Equal => assert!(current_borrow_rate == optimal_rate),
                 Ordering::Greater => {
-                    if max_borrow_rate == reserve.config.optimal_borrow_rate {
-                        assert_eq!(current_borrow_rate, optimal_borrow_rate);
+                    if max_borrow_rate == optimal_borrow_rate {
+                        assert_eq!(current_borrow_rate, optimal_rate);
                     } else {
-                        assert!(current_borrow_rate > optimal_borrow_rate);
+                        assert!(current_borrow_rate > optimal_rate);
                     }
                 }
             }
@@ -1068,9 +1360,7 @@ mod test {
                     ..ReserveLiquidity::default()
                 },
                 config: ReserveConfig {
-                    min_borrow_rate: borrow_rate,
-                    optimal_borrow_rate: borrow_rate,
-                    optimal_utilization_rate: 100,
+                    borrow_rate_curve: BorrowRateCurve::from_legacy(100, borrow_rate, borrow_rate, 0),
                     ..ReserveConfig::default()
                 },
                 ..Reserve::default()
@@ -1101,7 +1391,7 @@ mod test {
             // Simulate running for max 1000 years, assuming that interest is
             // compounded at least once a year
             for _ in 0..1000 {
-                reserve.liquidity.compound_interest(borrow_rate, slots_elapsed)?;
+                reserve.liquidity.compound_interest(borrow_rate, slots_elapsed, Rate::zero())?;
                 reserve.liquidity.cumulative_borrow_rate_wads.to_scaled_val()?;
             }
         }
@@ -1119,7 +1409,7 @@ mod test {
                     ..ReserveLiquidity::default()
                 },
                 config: ReserveConfig {
-                    max_borrow_rate: borrow_rate,
+                    borrow_rate_curve: BorrowRateCurve::from_legacy(0, 0, 0, borrow_rate),
                     ..ReserveConfig::default()
                 },
                 ..Reserve::default()
@@ -1134,6 +1424,41 @@ mod test {
             }
         }
 
+        #[test]
+        fn reserve_accrue_interest_protocol_fees(
+            slots_elapsed in 0..=SLOTS_PER_YEAR,
+            borrowed_liquidity in 0..=u64::MAX,
+            borrow_rate in 0..=u8::MAX,
+            protocol_take_rate in 0..=100u8,
+        ) {
+            let borrowed_amount_wads = Decimal::from(borrowed_liquidity);
+            let mut reserve = Reserve {
+                liquidity: ReserveLiquidity {
+                    borrowed_amount_wads,
+                    ..ReserveLiquidity::default()
+                },
+                config: ReserveConfig {
+                    borrow_rate_curve: BorrowRateCurve::from_legacy(0, 0, 0, borrow_rate),
+                    protocol_take_rate,
+                    ..ReserveConfig::default()
+                },
+                ..Reserve::default()
+            };
+
+            reserve.accrue_interest(slots_elapsed)?;
+
+            // Protocol fees are a share of the accrued interest, and never reduce depositor supply
+            let interest = reserve.liquidity.borrowed_amount_wads.try_sub(borrowed_amount_wads)?;
+            let protocol_fees = reserve.liquidity.accumulated_protocol_fees_wads;
+            assert!(protocol_fees <= interest);
+            assert!(reserve.liquidity.total_supply()? >= borrowed_amount_wads);
+            if protocol_take_rate == 0 {
+                assert_eq!(protocol_fees, Decimal::zero());
+            } else if protocol_take_rate == 100 {
+                assert_eq!(protocol_fees, interest);
+            }
+        }
+
         #[test]
         fn borrow_fee_calculation(
             borrow_fee_wad in 0..WAD, // at WAD, fee == borrow amount, which fails
@@ -1221,6 +1546,139 @@ mod test {
         }
     }
 
+    #[test]
+    fn redeem_fees() {
+        let mut reserve = Reserve {
+            liquidity: ReserveLiquidity {
+                available_amount: 100,
+                borrowed_amount_wads: Decimal::from(900u64),
+                accumulated_protocol_fees_wads: Decimal::from(250u64)
+                    .try_add(Decimal::from_scaled_val(WAD as u128 / 2))
+                    .unwrap(),
+                ..ReserveLiquidity::default()
+            },
+            ..Reserve::default()
+        };
+        let total_supply = reserve.liquidity.total_supply().unwrap();
+
+        // limited by available liquidity
+        let redeem_amount = reserve.calculate_redeem_fees().unwrap();
+        assert_eq!(redeem_amount, 100);
+        reserve.liquidity.redeem_fees(redeem_amount).unwrap();
+        assert_eq!(reserve.liquidity.available_amount, 0);
+
+        // repaid liquidity is redeemed, leaving fractional fees
+        reserve.liquidity.repay(500, Decimal::from(500u64)).unwrap();
+        let redeem_amount = reserve.calculate_redeem_fees().unwrap();
+        assert_eq!(redeem_amount, 150);
+        reserve.liquidity.redeem_fees(redeem_amount).unwrap();
+        assert_eq!(reserve.liquidity.available_amount, 350);
+        assert_eq!(
+            reserve.liquidity.accumulated_protocol_fees_wads,
+            Decimal::from_scaled_val(WAD as u128 / 2)
+        );
+
+        // depositor supply is unchanged by redeeming fees
+        assert_eq!(reserve.liquidity.total_supply(), Ok(total_supply));
+
+        assert_eq!(
+            reserve.liquidity.redeem_fees(351),
+            Err(LendingError::InsufficientLiquidity.into())
+        );
+    }
+
+    #[test]
+    fn borrow_rate_curve() {
+        let curve = BorrowRateCurve::new(&[(0, 0), (5_000, 200), (9_000, 1_000), (10_000, 15_000)])
+            .unwrap();
+
+        assert_eq!(curve.borrow_rate(Rate::zero()).unwrap(), Rate::zero());
+        assert_eq!(
+            curve.borrow_rate(Rate::from_percent(25)).unwrap(),
+            Rate::from_bps(100)
+        );
+        assert_eq!(
+            curve.borrow_rate(Rate::from_percent(90)).unwrap(),
+            Rate::from_bps(1_000)
+        );
+        assert_eq!(
+            curve.borrow_rate(Rate::from_percent(95)).unwrap(),
+            Rate::from_bps(8_000)
+        );
+        assert_eq!(
+            curve.borrow_rate(Rate::one()).unwrap(),
+            Rate::from_bps(15_000)
+        );
+
+        // empty curves have no interest
+        assert_eq!(
+            BorrowRateCurve::default().borrow_rate(Rate::one()).unwrap(),
+            Rate::zero()
+        );
+    }
+
+    #[test]
+    fn borrow_rate_curve_too_many_points() {
+        let points = [(0, 0); MAX_BORROW_RATE_CURVE_POINTS + 1];
+        assert_eq!(
+            BorrowRateCurve::new(&points),
+            Err(LendingError::InvalidConfig.into())
+        );
+    }
+
+    #[test]
+    fn pack_borrow_rate_curve() {
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            config: ReserveConfig {
+                borrow_rate_curve: BorrowRateCurve::new(&[
+                    (0, 100),
+                    (8_000, 400),
+                    (10_000, 30_000),
+                ])
+                .unwrap(),
+                ..ReserveConfig::default()
+            },
+            ..Reserve::default()
+        };
+
+        let mut data = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut data).unwrap();
+        assert_eq!(Reserve::unpack(&data).unwrap(), reserve);
+    }
+
+    #[test]
+    fn unpack_legacy_borrow_rates() {
+        const CONFIG_OFFSET: usize = 299;
+        const CONFIG_VERSION_OFFSET: usize = 376;
+
+        let reserve = Reserve {
+            version: PROGRAM_VERSION,
+            ..Reserve::default()
+        };
+        let mut data = [0u8; Reserve::LEN];
+        Reserve::pack(reserve, &mut data).unwrap();
+
+        // version 0 config with 80% optimal utilization and 1% min, 4% optimal, and 30% max rates
+        data[CONFIG_VERSION_OFFSET] = 0;
+        data[CONFIG_OFFSET] = 80;
+        data[CONFIG_OFFSET + 4] = 1;
+        data[CONFIG_OFFSET + 5] = 4;
+        data[CONFIG_OFFSET + 6] = 30;
+
+        let reserve = Reserve::unpack(&data).unwrap();
+        assert_eq!(
+            reserve.config.borrow_rate_curve,
+            BorrowRateCurve::new(&[(0, 100), (8_000, 400), (10_000, 3_000)]).unwrap()
+        );
+
+        // repacked at the current config version
+        let mut repacked = [0u8; Reserve::LEN];
+        Reserve::pack(reserve.clone(), &mut repacked).unwrap();
+        assert_eq!(repacked[CONFIG_VERSION_OFFSET], RESERVE_CONFIG_VERSION);
+        assert_eq!(Reserve::unpack(&repacked).unwrap(), reserve);
+    }
+
     #[test]
     fn borrow_fee_calculation_min_host() {
         let fees = ReserveFees {
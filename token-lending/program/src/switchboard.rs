@@ -0,0 +1,166 @@
+#![allow(missing_docs)]
+/// Reads the fields of a Switchboard v2 `AggregatorAccountData` account used for prices, see
+/// https://github.com/switchboard-xyz/switchboard-v2/blob/main/libraries/rs/src/aggregator.rs
+///
+/// The account is an Anchor zero-copy account with a packed layout, so its fields are read at
+/// fixed offsets rather than cast to a struct.
+use crate::error::LendingError;
+use arrayref::{array_mut_ref, array_ref};
+use solana_program::{msg, program_error::ProgramError};
+
+/// Anchor discriminator of `AggregatorAccountData` accounts
+pub const AGGREGATOR_DISCRIMINATOR: [u8; 8] = [217, 230, 65, 101, 201, 162, 27, 125];
+
+/// Switchboard v2 program which owns aggregator accounts
+pub mod program {
+    solana_program::declare_id!("SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f");
+}
+
+const DISCRIMINATOR_OFFSET: usize = 0;
+const NAME_OFFSET: usize = 8;
+const MIN_ORACLE_RESULTS_OFFSET: usize = 236;
+const LATEST_CONFIRMED_ROUND_OFFSET: usize = 341;
+
+// offsets in `AggregatorRound`
+const NUM_SUCCESS_OFFSET: usize = 0;
+const NUM_ERROR_OFFSET: usize = 4;
+const IS_CLOSED_OFFSET: usize = 8;
+const ROUND_OPEN_SLOT_OFFSET: usize = 9;
+const ROUND_OPEN_TIMESTAMP_OFFSET: usize = 17;
+const RESULT_OFFSET: usize = 25;
+const STD_DEVIATION_OFFSET: usize = 45;
+
+/// Length of a `SwitchboardDecimal`: an i128 mantissa and a u32 scale
+const DECIMAL_LEN: usize = 16 + 4;
+
+/// Bytes of the aggregator account read by the lending program, up to the standard deviation of
+/// the latest confirmed round
+pub const AGGREGATOR_READ_LEN: usize =
+    LATEST_CONFIRMED_ROUND_OFFSET + STD_DEVIATION_OFFSET + DECIMAL_LEN;
+
+/// Switchboard decimal, `mantissa / 10^scale`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SwitchboardDecimal {
+    pub mantissa: i128,
+    pub scale: u32,
+}
+
+/// Latest confirmed round of an aggregator
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct AggregatorRound {
+    pub num_success: u32,
+    pub num_error: u32,
+    pub is_closed: bool,
+    pub round_open_slot: u64,
+    pub round_open_timestamp: i64,
+    pub result: SwitchboardDecimal,
+    pub std_deviation: SwitchboardDecimal,
+}
+
+/// Fields of `AggregatorAccountData` read by the lending program
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Aggregator {
+    pub name: [u8; 32],
+    pub min_oracle_results: u32,
+    pub latest_confirmed_round: AggregatorRound,
+}
+
+impl Aggregator {
+    /// Reads an aggregator from the account data, checking its length and discriminator
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() < AGGREGATOR_READ_LEN {
+            msg!("Switchboard aggregator account data is too short");
+            return Err(LendingError::InvalidOracleConfig.into());
+        }
+        if array_ref![data, DISCRIMINATOR_OFFSET, 8] != &AGGREGATOR_DISCRIMINATOR {
+            msg!("Switchboard aggregator account discriminator is invalid");
+            return Err(LendingError::InvalidOracleConfig.into());
+        }
+
+        let round = &data[LATEST_CONFIRMED_ROUND_OFFSET..AGGREGATOR_READ_LEN];
+        Ok(Self {
+            name: *array_ref![data, NAME_OFFSET, 32],
+            min_oracle_results: u32::from_le_bytes(*array_ref![data, MIN_ORACLE_RESULTS_OFFSET, 4]),
+            latest_confirmed_round: AggregatorRound {
+                num_success: u32::from_le_bytes(*array_ref![round, NUM_SUCCESS_OFFSET, 4]),
+                num_error: u32::from_le_bytes(*array_ref![round, NUM_ERROR_OFFSET, 4]),
+                is_closed: round[IS_CLOSED_OFFSET] != 0,
+                round_open_slot: u64::from_le_bytes(*array_ref![round, ROUND_OPEN_SLOT_OFFSET, 8]),
+                round_open_timestamp: i64::from_le_bytes(*array_ref![
+                    round,
+                    ROUND_OPEN_TIMESTAMP_OFFSET,
+                    8
+                ]),
+                result: unpack_decimal(array_ref![round, RESULT_OFFSET, DECIMAL_LEN]),
+                std_deviation: unpack_decimal(array_ref![round, STD_DEVIATION_OFFSET, DECIMAL_LEN]),
+            },
+        })
+    }
+
+    /// Writes the aggregator fields and discriminator into account data of at least
+    /// `AGGREGATOR_READ_LEN` bytes, used to create aggregator accounts in tests
+    pub fn pack(&self, data: &mut [u8]) -> Result<(), ProgramError> {
+        if data.len() < AGGREGATOR_READ_LEN {
+            return Err(ProgramError::AccountDataTooSmall);
+        }
+        data[DISCRIMINATOR_OFFSET..DISCRIMINATOR_OFFSET + 8]
+            .copy_from_slice(&AGGREGATOR_DISCRIMINATOR);
+        data[NAME_OFFSET..NAME_OFFSET + 32].copy_from_slice(&self.name);
+        data[MIN_ORACLE_RESULTS_OFFSET..MIN_ORACLE_RESULTS_OFFSET + 4]
+            .copy_from_slice(&self.min_oracle_results.to_le_bytes());
+
+        let round = &mut data[LATEST_CONFIRMED_ROUND_OFFSET..AGGREGATOR_READ_LEN];
+        let latest = &self.latest_confirmed_round;
+        round[NUM_SUCCESS_OFFSET..NUM_SUCCESS_OFFSET + 4]
+            .copy_from_slice(&latest.num_success.to_le_bytes());
+        round[NUM_ERROR_OFFSET..NUM_ERROR_OFFSET + 4]
+            .copy_from_slice(&latest.num_error.to_le_bytes());
+        round[IS_CLOSED_OFFSET] = latest.is_closed as u8;
+        round[ROUND_OPEN_SLOT_OFFSET..ROUND_OPEN_SLOT_OFFSET + 8]
+            .copy_from_slice(&latest.round_open_slot.to_le_bytes());
+        round[ROUND_OPEN_TIMESTAMP_OFFSET..ROUND_OPEN_TIMESTAMP_OFFSET + 8]
+            .copy_from_slice(&latest.round_open_timestamp.to_le_bytes());
+        pack_decimal(
+            &latest.result,
+            array_mut_ref![round, RESULT_OFFSET, DECIMAL_LEN],
+        );
+        pack_decimal(
+            &latest.std_deviation,
+            array_mut_ref![round, STD_DEVIATION_OFFSET, DECIMAL_LEN],
+        );
+        Ok(())
+    }
+
+    /// Quote currency of the aggregator, taken from its name which Switchboard feeds set to
+    /// `<BASE>_<QUOTE>` or `<BASE>/<QUOTE>`, padded with zeroes like the lending market's
+    pub fn quote_currency(&self) -> Option<[u8; 32]> {
+        let name_len = self
+            .name
+            .iter()
+            .rposition(|byte| *byte != 0)
+            .map_or(0, |position| position + 1);
+        let name = &self.name[..name_len];
+        let separator = name
+            .iter()
+            .rposition(|byte| *byte == b'_' || *byte == b'/')?;
+        let quote = &name[separator + 1..];
+        if quote.is_empty() {
+            return None;
+        }
+        let mut quote_currency = [0u8; 32];
+        quote_currency[..quote.len()].copy_from_slice(quote);
+        Some(quote_currency)
+    }
+}
+
+fn unpack_decimal(src: &[u8; DECIMAL_LEN]) -> SwitchboardDecimal {
+    SwitchboardDecimal {
+        mantissa: i128::from_le_bytes(*array_ref![src, 0, 16]),
+        scale: u32::from_le_bytes(*array_ref![src, 16, 4]),
+    }
+}
+
+fn pack_decimal(decimal: &SwitchboardDecimal, dst: &mut [u8; DECIMAL_LEN]) {
+    dst[..16].copy_from_slice(&decimal.mantissa.to_le_bytes());
+    dst[16..].copy_from_slice(&decimal.scale.to_le_bytes());
+}
@@ -19,16 +19,18 @@ use spl_token::{
 use spl_token_lending::{
     instruction::{
         borrow_obligation_liquidity, deposit_reserve_liquidity, init_lending_market,
-        init_obligation, init_reserve, liquidate_obligation, refresh_reserve,
+        init_obligation, init_reserve, liquidate_obligation, refresh_reserve, OracleAccounts,
     },
     math::{Decimal, Rate, TryAdd, TryMul},
+    oracle::{FixedPrice, OracleSource, FIXED_PRICE_MAGIC},
     pyth,
     state::{
-        InitLendingMarketParams, InitObligationParams, InitReserveParams, LendingMarket,
-        NewReserveCollateralParams, NewReserveLiquidityParams, Obligation, ObligationCollateral,
-        ObligationLiquidity, Reserve, ReserveCollateral, ReserveConfig, ReserveFees,
-        ReserveLiquidity, INITIAL_COLLATERAL_RATIO, PROGRAM_VERSION,
+        BorrowRateCurve, InitLendingMarketParams, InitObligationParams, InitReserveParams,
+        LendingMarket, NewReserveCollateralParams, NewReserveLiquidityParams, Obligation,
+        ObligationCollateral, ObligationLiquidity, Reserve, ReserveCollateral, ReserveConfig,
+        ReserveFees, ReserveLiquidity, INITIAL_COLLATERAL_RATIO, PROGRAM_VERSION,
     },
+    switchboard,
 };
 use std::{convert::TryInto, str::FromStr};
 
@@ -39,13 +41,10 @@ pub const LAMPORTS_TO_SOL: u64 = 1_000_000_000;
 pub const FRACTIONAL_TO_USDC: u64 = 1_000_000;
 
 pub const TEST_RESERVE_CONFIG: ReserveConfig = ReserveConfig {
-    optimal_utilization_rate: 80,
     loan_to_value_ratio: 50,
     liquidation_bonus: 5,
     liquidation_threshold: 55,
-    min_borrow_rate: 0,
-    optimal_borrow_rate: 4,
-    max_borrow_rate: 30,
+    borrow_rate_curve: BorrowRateCurve::from_legacy(80, 0, 4, 30),
     fees: ReserveFees {
         /// 0.00001% (Aave borrow fee)
         borrow_fee_wad: 100_000_000_000,
@@ -53,6 +52,11 @@ pub const TEST_RESERVE_CONFIG: ReserveConfig = ReserveConfig {
         flash_loan_fee_wad: 3_000_000_000_000_000,
         host_fee_percentage: 20,
     },
+    max_price_confidence_bps: 200,
+    deposit_limit: 0,
+    borrow_limit: 0,
+    elevation_group: 0,
+    protocol_take_rate: 0,
 };
 
 pub const SOL_PYTH_PRODUCT: &str = "3Mnn2fX6rQyUsyELYms1sBJyChWofzSNRoqYzvgMVz5E";
@@ -126,6 +130,7 @@ pub struct AddObligationArgs<'a> {
     pub borrows: &'a [(&'a TestReserve, u64)],
     pub mark_fresh: bool,
     pub slots_elapsed: u64,
+    pub elevation_group: u8,
 }
 
 pub fn add_obligation(
@@ -139,6 +144,7 @@ pub fn add_obligation(
         borrows,
         mark_fresh,
         slots_elapsed,
+        elevation_group,
     } = args;
 
     let obligation_keypair = Keypair::new();
@@ -191,6 +197,8 @@ pub fn add_obligation(
         borrows: obligation_borrows,
     });
 
+    obligation.elevation_group = elevation_group;
+
     if mark_fresh {
         obligation.last_update.update_slot(current_slot);
     }
@@ -221,6 +229,7 @@ pub struct AddReserveArgs {
     pub user_liquidity_amount: u64,
     pub borrow_amount: u64,
     pub initial_borrow_rate: u8,
+    pub accumulated_protocol_fees: u64,
     pub collateral_amount: u64,
     pub mark_fresh: bool,
     pub slots_elapsed: u64,
@@ -242,6 +251,7 @@ pub fn add_reserve(
         user_liquidity_amount,
         borrow_amount,
         initial_borrow_rate,
+        accumulated_protocol_fees,
         collateral_amount,
         mark_fresh,
         slots_elapsed,
@@ -343,6 +353,7 @@ pub fn add_reserve(
             supply_pubkey: liquidity_supply_pubkey,
             fee_receiver: liquidity_fee_receiver_pubkey,
             oracle_pubkey: oracle.price_pubkey,
+            oracle_source: oracle.source,
             market_price: oracle.price,
         }),
         collateral: ReserveCollateral::new(NewReserveCollateralParams {
@@ -358,6 +369,7 @@ pub fn add_reserve(
         .unwrap();
     reserve.liquidity.cumulative_borrow_rate_wads =
         Decimal::one().try_mul(borrow_rate_multiplier).unwrap();
+    reserve.liquidity.accumulated_protocol_fees_wads = accumulated_protocol_fees.into();
 
     if mark_fresh {
         reserve.last_update.update_slot(current_slot);
@@ -529,6 +541,7 @@ impl TestLendingMarket {
                 spl_token_lending::id(),
                 reserve.pubkey,
                 reserve.liquidity_oracle_pubkey,
+                None,
             )],
             Some(&payer.pubkey()),
         );
@@ -1062,11 +1075,29 @@ pub fn add_usdc_mint(test: &mut ProgramTest) -> TestMint {
 }
 
 pub struct TestOracle {
+    pub source: OracleSource,
     pub product_pubkey: Pubkey,
     pub price_pubkey: Pubkey,
     pub price: Decimal,
 }
 
+impl TestOracle {
+    pub fn accounts(&self) -> OracleAccounts {
+        match self.source {
+            OracleSource::Pyth => OracleAccounts::Pyth {
+                product_pubkey: self.product_pubkey,
+                price_pubkey: self.price_pubkey,
+            },
+            OracleSource::Switchboard => OracleAccounts::Switchboard {
+                aggregator_pubkey: self.price_pubkey,
+            },
+            OracleSource::FixedPrice => OracleAccounts::FixedPrice {
+                price_pubkey: self.price_pubkey,
+            },
+        }
+    }
+}
+
 pub fn add_sol_oracle(test: &mut ProgramTest) -> TestOracle {
     add_oracle(
         test,
@@ -1137,12 +1168,97 @@ pub fn add_oracle(
     );
 
     TestOracle {
+        source: OracleSource::Pyth,
         product_pubkey,
         price_pubkey,
         price,
     }
 }
 
+/// Adds a Switchboard aggregator with a confidence interval of `confidence_bps` of the price
+pub fn add_switchboard_oracle(
+    test: &mut ProgramTest,
+    price: Decimal,
+    confidence_bps: u64,
+) -> TestOracle {
+    const SCALE: u32 = 6;
+
+    let aggregator_pubkey = Pubkey::new_unique();
+    let result = price
+        .try_mul(10u64.pow(SCALE))
+        .unwrap()
+        .try_round_u64()
+        .unwrap();
+    let mut name = [0u8; 32];
+    name[..7].copy_from_slice(b"SOL_USD");
+    let aggregator = switchboard::Aggregator {
+        name,
+        min_oracle_results: 1,
+        latest_confirmed_round: switchboard::AggregatorRound {
+            num_success: 1,
+            result: switchboard::SwitchboardDecimal {
+                mantissa: result.into(),
+                scale: SCALE,
+            },
+            std_deviation: switchboard::SwitchboardDecimal {
+                mantissa: (result * confidence_bps / 10_000).into(),
+                scale: SCALE,
+            },
+            ..switchboard::AggregatorRound::default()
+        },
+    };
+    let mut data = vec![0; switchboard::AGGREGATOR_READ_LEN];
+    aggregator.pack(&mut data).unwrap();
+
+    test.add_account(
+        aggregator_pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data,
+            owner: switchboard::program::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+
+    TestOracle {
+        source: OracleSource::Switchboard,
+        product_pubkey: Pubkey::default(),
+        price_pubkey: aggregator_pubkey,
+        price,
+    }
+}
+
+pub fn add_fixed_price_oracle(test: &mut ProgramTest, price: Decimal) -> TestOracle {
+    let oracle_program_id = read_keypair_file("tests/fixtures/oracle_program_id.json").unwrap();
+
+    let price_pubkey = Pubkey::new_unique();
+    let fixed_price = FixedPrice {
+        magic: FIXED_PRICE_MAGIC,
+        expo: 0,
+        price: price.try_round_u64().unwrap().try_into().unwrap(),
+        quote_currency: QUOTE_CURRENCY,
+    };
+
+    test.add_account(
+        price_pubkey,
+        Account {
+            lamports: u32::MAX as u64,
+            data: bytemuck::bytes_of(&fixed_price).to_vec(),
+            owner: oracle_program_id.pubkey(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+
+    TestOracle {
+        source: OracleSource::FixedPrice,
+        product_pubkey: Pubkey::default(),
+        price_pubkey,
+        price,
+    }
+}
+
 pub async fn create_and_mint_to_token_account(
     banks_client: &mut BanksClient,
     mint_pubkey: Pubkey,
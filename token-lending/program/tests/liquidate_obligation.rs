@@ -119,6 +119,7 @@ async fn test_success() {
                 spl_token_lending::id(),
                 test_obligation.pubkey,
                 vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+                None,
             ),
             liquidate_obligation(
                 spl_token_lending::id(),
@@ -126,6 +126,7 @@ async fn test_success() {
                 spl_token_lending::id(),
                 sol_test_reserve.pubkey,
                 sol_oracle.price_pubkey,
+                None,
             ),
             // 3
             approve(
@@ -154,12 +155,14 @@ async fn test_success() {
                 spl_token_lending::id(),
                 obligation_pubkey,
                 vec![sol_test_reserve.pubkey],
+                None,
             ),
             // 6
             refresh_reserve(
                 spl_token_lending::id(),
                 usdc_test_reserve.pubkey,
                 usdc_oracle.price_pubkey,
+                None,
             ),
             // 7
             borrow_obligation_liquidity(
@@ -179,12 +182,14 @@ async fn test_success() {
                 spl_token_lending::id(),
                 usdc_test_reserve.pubkey,
                 usdc_oracle.price_pubkey,
+                None,
             ),
             // 9
             refresh_obligation(
                 spl_token_lending::id(),
                 obligation_pubkey,
                 vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+                None,
             ),
             // 10
             approve(
@@ -212,6 +217,7 @@ async fn test_success() {
                 spl_token_lending::id(),
                 obligation_pubkey,
                 vec![sol_test_reserve.pubkey],
+                None,
             ),
             // 13
             withdraw_obligation_collateral(
@@ -0,0 +1,207 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program::{program_pack::Pack, pubkey::Pubkey};
+use solana_program_test::*;
+use solana_sdk::{
+    account::Account,
+    instruction::InstructionError,
+    rent::Rent,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{
+        realloc_obligation, refresh_obligation, set_elevation_group, set_obligation_elevation_group,
+    },
+    processor::process_instruction,
+    state::{InitObligationParams, Obligation},
+};
+
+/// Length of obligations created before elevation groups
+const LEGACY_OBLIGATION_LEN: usize = 916;
+
+fn add_legacy_obligation(
+    test: &mut ProgramTest,
+    lending_market: &TestLendingMarket,
+    user_accounts_owner: &Keypair,
+) -> TestObligation {
+    let obligation_pubkey = Pubkey::new_unique();
+    let mut obligation = Obligation::new(InitObligationParams {
+        current_slot: 1,
+        lending_market: lending_market.pubkey,
+        owner: user_accounts_owner.pubkey(),
+        deposits: vec![],
+        borrows: vec![],
+    });
+    obligation.last_update.update_slot(1);
+
+    let mut data = vec![0; Obligation::LEN];
+    Obligation::pack(obligation, &mut data).unwrap();
+    data.truncate(LEGACY_OBLIGATION_LEN);
+    test.add_account(
+        obligation_pubkey,
+        Account {
+            lamports: Rent::default().minimum_balance(LEGACY_OBLIGATION_LEN),
+            data,
+            owner: spl_token_lending::id(),
+            ..Account::default()
+        },
+    );
+
+    TestObligation {
+        pubkey: obligation_pubkey,
+        lending_market: lending_market.pubkey,
+        owner: user_accounts_owner.pubkey(),
+        deposits: vec![],
+        borrows: vec![],
+    }
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let test_obligation = add_legacy_obligation(&mut test, &lending_market, &user_accounts_owner);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // a legacy obligation cannot store an elevation group
+    let set_instructions = [
+        set_elevation_group(
+            spl_token_lending::id(),
+            1,
+            80,
+            90,
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        ),
+        refresh_obligation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            vec![],
+            None,
+        ),
+        set_obligation_elevation_group(
+            spl_token_lending::id(),
+            1,
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            test_obligation.owner,
+            vec![],
+        ),
+    ];
+    let mut transaction = Transaction::new_with_payer(&set_instructions, Some(&payer.pubkey()));
+    transaction.sign(
+        &[&payer, &lending_market.owner, &user_accounts_owner],
+        recent_blockhash,
+    );
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(2, InstructionError::AccountDataTooSmall)
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[realloc_obligation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            test_obligation.owner,
+            payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &user_accounts_owner], recent_blockhash);
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap())
+        .unwrap();
+
+    let rent = banks_client.get_rent().await.unwrap();
+    let obligation_account = banks_client
+        .get_account(test_obligation.pubkey)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(obligation_account.data.len(), Obligation::LEN);
+    assert_eq!(
+        obligation_account.lamports,
+        rent.minimum_balance(Obligation::LEN)
+    );
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.elevation_group, 0);
+    assert_eq!(obligation.owner, user_accounts_owner.pubkey());
+
+    // once grown, the obligation can join the elevation group
+    let recent_blockhash = banks_client
+        .get_new_latest_blockhash(&recent_blockhash)
+        .await
+        .unwrap();
+    let mut transaction = Transaction::new_with_payer(&set_instructions, Some(&payer.pubkey()));
+    transaction.sign(
+        &[&payer, &lending_market.owner, &user_accounts_owner],
+        recent_blockhash,
+    );
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap())
+        .unwrap();
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.elevation_group, 1);
+}
+
+#[tokio::test]
+async fn test_invalid_obligation_owner() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let test_obligation = add_legacy_obligation(&mut test, &lending_market, &user_accounts_owner);
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let invalid_owner = Keypair::new();
+    let mut transaction = Transaction::new_with_payer(
+        &[realloc_obligation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            lending_market.pubkey,
+            invalid_owner.pubkey(),
+            payer.pubkey(),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer, &invalid_owner], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidObligationOwner as u32)
+        )
+    );
+}
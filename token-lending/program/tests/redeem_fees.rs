@@ -0,0 +1,214 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError, instruction::redeem_fees, math::Decimal, processor::process_instruction,
+};
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // limit to track compute unit increase
+    test.set_compute_max_units(20_000);
+
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 10 * FRACTIONAL_TO_USDC;
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 5 * FRACTIONAL_TO_USDC;
+    const PROTOCOL_FEES_FRACTIONAL: u64 = FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            accumulated_protocol_fees: PROTOCOL_FEES_FRACTIONAL,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let initial_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[redeem_fees(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_fee_receiver_pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            lending_market.pubkey,
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let fee_receiver_balance = get_token_balance(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_fee_receiver_pubkey,
+    )
+    .await;
+    assert_eq!(fee_receiver_balance, PROTOCOL_FEES_FRACTIONAL);
+
+    let reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(
+        reserve.liquidity.accumulated_protocol_fees_wads,
+        Decimal::zero()
+    );
+    assert_eq!(
+        reserve.liquidity.available_amount,
+        initial_reserve.liquidity.available_amount - PROTOCOL_FEES_FRACTIONAL
+    );
+    assert_eq!(
+        reserve.liquidity.total_supply().unwrap(),
+        initial_reserve.liquidity.total_supply().unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_limited_by_available_liquidity() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 10 * FRACTIONAL_TO_USDC;
+    const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 9 * FRACTIONAL_TO_USDC + FRACTIONAL_TO_USDC / 2;
+    const PROTOCOL_FEES_FRACTIONAL: u64 = FRACTIONAL_TO_USDC;
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            accumulated_protocol_fees: PROTOCOL_FEES_FRACTIONAL,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[redeem_fees(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.liquidity_fee_receiver_pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            lending_market.pubkey,
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    // Only the available liquidity is redeemed, the rest of the fees stay accumulated
+    let available_amount = USDC_RESERVE_LIQUIDITY_FRACTIONAL - USDC_BORROW_AMOUNT_FRACTIONAL;
+    let fee_receiver_balance = get_token_balance(
+        &mut banks_client,
+        usdc_test_reserve.liquidity_fee_receiver_pubkey,
+    )
+    .await;
+    assert_eq!(fee_receiver_balance, available_amount);
+
+    let reserve = usdc_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(reserve.liquidity.available_amount, 0);
+    assert_eq!(
+        reserve.liquidity.accumulated_protocol_fees_wads,
+        Decimal::from(PROTOCOL_FEES_FRACTIONAL - available_amount)
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_fee_receiver() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let usdc_mint = add_usdc_mint(&mut test);
+    let usdc_oracle = add_usdc_oracle(&mut test);
+    let usdc_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &usdc_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 10 * FRACTIONAL_TO_USDC,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            accumulated_protocol_fees: FRACTIONAL_TO_USDC,
+            config: TEST_RESERVE_CONFIG,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[redeem_fees(
+            spl_token_lending::id(),
+            usdc_test_reserve.pubkey,
+            usdc_test_reserve.user_liquidity_pubkey,
+            usdc_test_reserve.liquidity_supply_pubkey,
+            lending_market.pubkey,
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        )
+    );
+}
@@ -14,7 +14,7 @@ use spl_token_lending::{
     instruction::{refresh_obligation, refresh_reserve},
     math::{Decimal, TryDiv},
     processor::process_instruction,
-    state::INITIAL_COLLATERAL_RATIO,
+    state::{BorrowRateCurve, INITIAL_COLLATERAL_RATIO},
 };
 
 #[tokio::test]
@@ -43,10 +43,9 @@ async fn test_success() {
     reserve_config.loan_to_value_ratio = 50;
 
     // Configure reserve to a fixed borrow rate of 1%
-    const BORROW_RATE: u8 = 1;
-    reserve_config.min_borrow_rate = BORROW_RATE;
-    reserve_config.optimal_borrow_rate = BORROW_RATE;
-    reserve_config.optimal_utilization_rate = 100;
+    const BORROW_RATE: u16 = 100;
+    reserve_config.borrow_rate_curve =
+        BorrowRateCurve::new(&[(0, BORROW_RATE), (10_000, BORROW_RATE)]).unwrap();
 
     let sol_oracle = add_sol_oracle(&mut test);
     let sol_test_reserve = add_reserve(
@@ -110,16 +109,19 @@ async fn test_success() {
                 spl_token_lending::id(),
                 usdc_test_reserve.pubkey,
                 usdc_oracle.price_pubkey,
+                None,
             ),
             refresh_reserve(
                 spl_token_lending::id(),
                 sol_test_reserve.pubkey,
                 sol_oracle.price_pubkey,
+                None,
             ),
             refresh_obligation(
                 spl_token_lending::id(),
                 test_obligation.pubkey,
                 vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+                None,
             ),
         ],
         Some(&payer.pubkey()),
@@ -137,9 +139,7 @@ async fn test_success() {
 
     let collateral_price = collateral.market_value.try_div(SOL_DEPOSIT_AMOUNT).unwrap();
 
-    let slot_rate = Rate::from_percent(BORROW_RATE)
-        .try_div(SLOTS_PER_YEAR)
-        .unwrap();
+    let slot_rate = Rate::from_bps(BORROW_RATE).try_div(SLOTS_PER_YEAR).unwrap();
     let compound_rate = Rate::one().try_add(slot_rate).unwrap();
     let compound_borrow = Decimal::from(USDC_BORROW_AMOUNT)
         .try_mul(compound_rate)
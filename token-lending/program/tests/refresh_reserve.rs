@@ -5,14 +5,16 @@ mod helpers;
 use helpers::*;
 use solana_program_test::*;
 use solana_sdk::{
+    instruction::InstructionError,
     signature::{Keypair, Signer},
-    transaction::Transaction,
+    transaction::{Transaction, TransactionError},
 };
 use spl_token_lending::{
-    instruction::refresh_reserve,
+    error::LendingError,
+    instruction::{refresh_reserve, set_reserve_oracle},
     math::{Decimal, Rate, TryAdd, TryDiv, TryMul},
     processor::process_instruction,
-    state::SLOTS_PER_YEAR,
+    state::{BorrowRateCurve, SLOTS_PER_YEAR},
 };
 
 #[tokio::test]
@@ -37,10 +39,9 @@ async fn test_success() {
     reserve_config.loan_to_value_ratio = 80;
 
     // Configure reserve to a fixed borrow rate of 1%
-    const BORROW_RATE: u8 = 1;
-    reserve_config.min_borrow_rate = BORROW_RATE;
-    reserve_config.optimal_borrow_rate = BORROW_RATE;
-    reserve_config.optimal_utilization_rate = 100;
+    const BORROW_RATE: u16 = 100;
+    reserve_config.borrow_rate_curve =
+        BorrowRateCurve::new(&[(0, BORROW_RATE), (10_000, BORROW_RATE)]).unwrap();
 
     let usdc_mint = add_usdc_mint(&mut test);
     let usdc_oracle = add_usdc_oracle(&mut test);
@@ -93,11 +94,13 @@ async fn test_success() {
                 spl_token_lending::id(),
                 usdc_test_reserve.pubkey,
                 usdc_oracle.price_pubkey,
+                None,
             ),
             refresh_reserve(
                 spl_token_lending::id(),
                 sol_test_reserve.pubkey,
                 sol_oracle.price_pubkey,
+                None,
             ),
         ],
         Some(&payer.pubkey()),
@@ -109,9 +112,7 @@ async fn test_success() {
     let sol_reserve = sol_test_reserve.get_state(&mut banks_client).await;
     let usdc_reserve = usdc_test_reserve.get_state(&mut banks_client).await;
 
-    let slot_rate = Rate::from_percent(BORROW_RATE)
-        .try_div(SLOTS_PER_YEAR)
-        .unwrap();
+    let slot_rate = Rate::from_bps(BORROW_RATE).try_div(SLOTS_PER_YEAR).unwrap();
     let compound_rate = Rate::one().try_add(slot_rate).unwrap();
     let compound_borrow = Decimal::from(BORROW_AMOUNT).try_mul(compound_rate).unwrap();
 
@@ -137,3 +138,105 @@ async fn test_success() {
         usdc_test_reserve.market_price
     );
 }
+
+#[tokio::test]
+async fn test_fallback_oracle() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    // limit to track compute unit increase
+    test.set_compute_max_units(40_000);
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+
+    let sol_oracle = add_sol_oracle(&mut test);
+    let fallback_oracle = add_fixed_price_oracle(&mut test, Decimal::from(21u64));
+    let sol_test_reserve = add_reserve(
+        &mut test,
+        &lending_market,
+        &sol_oracle,
+        &user_accounts_owner,
+        AddReserveArgs {
+            liquidity_amount: 100 * LAMPORTS_TO_SOL,
+            liquidity_mint_decimals: 9,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            config: TEST_RESERVE_CONFIG,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let mut test_context = test.start_with_context().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[set_reserve_oracle(
+            spl_token_lending::id(),
+            sol_test_reserve.pubkey,
+            sol_oracle.accounts(),
+            Some(fallback_oracle.accounts()),
+            lending_market.pubkey,
+            lending_market.owner.pubkey(),
+        )],
+        Some(&test_context.payer.pubkey()),
+    );
+    transaction.sign(
+        &[&test_context.payer, &lending_market.owner],
+        test_context.last_blockhash,
+    );
+    assert!(test_context
+        .banks_client
+        .process_transaction(transaction)
+        .await
+        .is_ok());
+
+    // Pyth price becomes stale
+    test_context.warp_to_slot(10).unwrap();
+
+    let ProgramTestContext {
+        mut banks_client,
+        payer,
+        ..
+    } = test_context;
+    let recent_blockhash = banks_client.get_latest_blockhash().await.unwrap();
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_reserve(
+            spl_token_lending::id(),
+            sol_test_reserve.pubkey,
+            sol_oracle.price_pubkey,
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidOracleConfig as u32)
+        )
+    );
+
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_reserve(
+            spl_token_lending::id(),
+            sol_test_reserve.pubkey,
+            sol_oracle.price_pubkey,
+            Some(fallback_oracle.price_pubkey),
+        )],
+        Some(&payer.pubkey()),
+    );
+    transaction.sign(&[&payer], recent_blockhash);
+    assert!(banks_client.process_transaction(transaction).await.is_ok());
+
+    let sol_reserve = sol_test_reserve.get_state(&mut banks_client).await;
+    assert_eq!(sol_reserve.liquidity.market_price, fallback_oracle.price);
+    assert!(!sol_reserve.last_update.stale);
+}
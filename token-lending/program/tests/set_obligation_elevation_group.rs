@@ -0,0 +1,283 @@
+#![cfg(feature = "test-bpf")]
+
+mod helpers;
+
+use helpers::*;
+use solana_program_test::*;
+use solana_sdk::{
+    instruction::InstructionError,
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_token_lending::{
+    error::LendingError,
+    instruction::{refresh_obligation, set_elevation_group, set_obligation_elevation_group},
+    math::{Rate, TryMul},
+    processor::process_instruction,
+    state::INITIAL_COLLATERAL_RATIO,
+};
+
+const SOL_DEPOSIT_AMOUNT_LAMPORTS: u64 = 100 * LAMPORTS_TO_SOL * INITIAL_COLLATERAL_RATIO;
+const USDC_BORROW_AMOUNT_FRACTIONAL: u64 = 1_000 * FRACTIONAL_TO_USDC;
+const SOL_RESERVE_COLLATERAL_LAMPORTS: u64 = 2 * SOL_DEPOSIT_AMOUNT_LAMPORTS;
+const USDC_RESERVE_LIQUIDITY_FRACTIONAL: u64 = 2 * USDC_BORROW_AMOUNT_FRACTIONAL;
+
+fn add_reserves(
+    test: &mut ProgramTest,
+    lending_market: &TestLendingMarket,
+    user_accounts_owner: &Keypair,
+    usdc_elevation_group: u8,
+) -> (TestReserve, TestReserve) {
+    let mut reserve_config = TEST_RESERVE_CONFIG;
+    reserve_config.loan_to_value_ratio = 50;
+    reserve_config.elevation_group = 1;
+
+    let sol_oracle = add_sol_oracle(test);
+    let sol_test_reserve = add_reserve(
+        test,
+        lending_market,
+        &sol_oracle,
+        user_accounts_owner,
+        AddReserveArgs {
+            collateral_amount: SOL_RESERVE_COLLATERAL_LAMPORTS,
+            liquidity_mint_pubkey: spl_token::native_mint::id(),
+            liquidity_mint_decimals: 9,
+            config: reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    let mut usdc_reserve_config = reserve_config;
+    usdc_reserve_config.elevation_group = usdc_elevation_group;
+
+    let usdc_mint = add_usdc_mint(test);
+    let usdc_oracle = add_usdc_oracle(test);
+    let usdc_test_reserve = add_reserve(
+        test,
+        lending_market,
+        &usdc_oracle,
+        user_accounts_owner,
+        AddReserveArgs {
+            borrow_amount: USDC_BORROW_AMOUNT_FRACTIONAL,
+            liquidity_amount: USDC_RESERVE_LIQUIDITY_FRACTIONAL,
+            liquidity_mint_pubkey: usdc_mint.pubkey,
+            liquidity_mint_decimals: usdc_mint.decimals,
+            config: usdc_reserve_config,
+            mark_fresh: true,
+            ..AddReserveArgs::default()
+        },
+    );
+
+    (sol_test_reserve, usdc_test_reserve)
+}
+
+#[tokio::test]
+async fn test_success() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let (sol_test_reserve, usdc_test_reserve) =
+        add_reserves(&mut test, &lending_market, &user_accounts_owner, 1);
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let reserve_pubkeys = vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey];
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_elevation_group(
+                spl_token_lending::id(),
+                1,
+                80,
+                90,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                reserve_pubkeys.clone(),
+                None,
+            ),
+            set_obligation_elevation_group(
+                spl_token_lending::id(),
+                1,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                reserve_pubkeys.clone(),
+            ),
+            refresh_obligation(
+                spl_token_lending::id(),
+                test_obligation.pubkey,
+                reserve_pubkeys,
+                Some(lending_market.pubkey),
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(
+        &[&payer, &lending_market.owner, &user_accounts_owner],
+        recent_blockhash,
+    );
+
+    banks_client
+        .process_transaction(transaction)
+        .await
+        .map_err(|e| e.unwrap())
+        .unwrap();
+
+    let obligation = test_obligation.get_state(&mut banks_client).await;
+    assert_eq!(obligation.elevation_group, 1);
+    assert_eq!(
+        obligation.allowed_borrow_value,
+        obligation
+            .deposited_value
+            .try_mul(Rate::from_percent(80))
+            .unwrap()
+    );
+    assert_eq!(
+        obligation.unhealthy_borrow_value,
+        obligation
+            .deposited_value
+            .try_mul(Rate::from_percent(90))
+            .unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_reserve_not_in_elevation_group() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let (sol_test_reserve, usdc_test_reserve) =
+        add_reserves(&mut test, &lending_market, &user_accounts_owner, 0);
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            mark_fresh: true,
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    let mut transaction = Transaction::new_with_payer(
+        &[
+            set_elevation_group(
+                spl_token_lending::id(),
+                1,
+                80,
+                90,
+                lending_market.pubkey,
+                lending_market.owner.pubkey(),
+            ),
+            set_obligation_elevation_group(
+                spl_token_lending::id(),
+                1,
+                test_obligation.pubkey,
+                lending_market.pubkey,
+                test_obligation.owner,
+                vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            ),
+        ],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(
+        &[&payer, &lending_market.owner, &user_accounts_owner],
+        recent_blockhash,
+    );
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            1,
+            InstructionError::Custom(LendingError::ElevationGroupMismatch as u32)
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_without_lending_market() {
+    let mut test = ProgramTest::new(
+        "spl_token_lending",
+        spl_token_lending::id(),
+        processor!(process_instruction),
+    );
+
+    let user_accounts_owner = Keypair::new();
+    let lending_market = add_lending_market(&mut test);
+    let (sol_test_reserve, usdc_test_reserve) =
+        add_reserves(&mut test, &lending_market, &user_accounts_owner, 1);
+
+    let test_obligation = add_obligation(
+        &mut test,
+        &lending_market,
+        &user_accounts_owner,
+        AddObligationArgs {
+            deposits: &[(&sol_test_reserve, SOL_DEPOSIT_AMOUNT_LAMPORTS)],
+            borrows: &[(&usdc_test_reserve, USDC_BORROW_AMOUNT_FRACTIONAL)],
+            elevation_group: 1,
+            ..AddObligationArgs::default()
+        },
+    );
+
+    let (mut banks_client, payer, recent_blockhash) = test.start().await;
+
+    // an obligation in an elevation group cannot be refreshed at the reserve values
+    let mut transaction = Transaction::new_with_payer(
+        &[refresh_obligation(
+            spl_token_lending::id(),
+            test_obligation.pubkey,
+            vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+            None,
+        )],
+        Some(&payer.pubkey()),
+    );
+
+    transaction.sign(&[&payer], recent_blockhash);
+
+    assert_eq!(
+        banks_client
+            .process_transaction(transaction)
+            .await
+            .unwrap_err()
+            .unwrap(),
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(LendingError::InvalidAccountInput as u32)
+        )
+    );
+}
@@ -104,6 +104,7 @@ async fn test_withdraw_fixed_amount() {
                 spl_token_lending::id(),
                 test_obligation.pubkey,
                 vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+                None,
             ),
             withdraw_obligation_collateral(
                 spl_token_lending::id(),
@@ -214,6 +215,7 @@ async fn test_withdraw_max_amount() {
                 spl_token_lending::id(),
                 test_obligation.pubkey,
                 vec![usdc_test_reserve.pubkey],
+                None,
             ),
             withdraw_obligation_collateral(
                 spl_token_lending::id(),
@@ -325,6 +327,7 @@ async fn test_withdraw_too_large() {
                 spl_token_lending::id(),
                 test_obligation.pubkey,
                 vec![sol_test_reserve.pubkey, usdc_test_reserve.pubkey],
+                None,
             ),
             withdraw_obligation_collateral(
                 spl_token_lending::id(),
@@ -0,0 +1,68 @@
+use {
+    solana_client::rpc_client::RpcClient,
+    solana_program::{clock::Clock, program_pack::Pack, pubkey::Pubkey, sysvar},
+    spl_token_swap::state::{SwapState, SwapVersion},
+    std::sync::Arc,
+};
+
+type Error = Box<dyn std::error::Error>;
+
+pub fn get_token_swap(
+    rpc_client: &RpcClient,
+    token_swap_address: &Pubkey,
+) -> Result<Arc<dyn SwapState>, Error> {
+    let account = rpc_client.get_account(token_swap_address)?;
+    if account.owner != spl_token_swap::id() {
+        return Err(format!(
+            "Invalid token swap {}: owned by {}, expected {}",
+            token_swap_address,
+            account.owner,
+            spl_token_swap::id()
+        )
+        .into());
+    }
+    let token_swap = SwapVersion::unpack(account.data.as_slice())
+        .map_err(|err| format!("Invalid token swap {}: {}", token_swap_address, err))?;
+    if !token_swap.is_initialized() {
+        return Err(format!("Token swap {} is not initialized", token_swap_address).into());
+    }
+    Ok(token_swap)
+}
+
+pub fn get_token_account(
+    rpc_client: &RpcClient,
+    token_account_address: &Pubkey,
+    expected_token_mint: &Pubkey,
+) -> Result<spl_token::state::Account, Error> {
+    let account_data = rpc_client.get_account_data(token_account_address)?;
+    let token_account = spl_token::state::Account::unpack_from_slice(account_data.as_slice())
+        .map_err(|err| format!("Invalid token account {}: {}", token_account_address, err))?;
+
+    if token_account.mint != *expected_token_mint {
+        Err(format!(
+            "Invalid token mint for {}, expected mint is {}",
+            token_account_address, expected_token_mint
+        )
+        .into())
+    } else {
+        Ok(token_account)
+    }
+}
+
+pub fn get_token_mint(
+    rpc_client: &RpcClient,
+    token_mint_address: &Pubkey,
+) -> Result<spl_token::state::Mint, Error> {
+    let account_data = rpc_client.get_account_data(token_mint_address)?;
+    let token_mint = spl_token::state::Mint::unpack_from_slice(account_data.as_slice())
+        .map_err(|err| format!("Invalid token mint {}: {}", token_mint_address, err))?;
+
+    Ok(token_mint)
+}
+
+pub fn get_clock(rpc_client: &RpcClient) -> Result<Clock, Error> {
+    let account_data = rpc_client.get_account_data(&sysvar::clock::id())?;
+    let clock = bincode::deserialize(account_data.as_slice())
+        .map_err(|err| format!("Invalid clock sysvar: {}", err))?;
+    Ok(clock)
+}
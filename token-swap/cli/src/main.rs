@@ -0,0 +1,1887 @@
+mod client;
+mod output;
+
+use {
+    crate::{
+        client::*,
+        output::{curve_type_name, CliTokenSwap, CliTokenSwapBalances, CliTokenSwapQuote},
+    },
+    clap::{
+        crate_description, crate_name, crate_version, value_t, value_t_or_exit, App, AppSettings,
+        Arg, ArgMatches, SubCommand,
+    },
+    solana_clap_utils::{
+        input_parsers::{keypair_of, pubkey_of},
+        input_validators::{
+            is_amount, is_keypair_or_ask_keyword, is_parsable, is_url, is_valid_pubkey,
+            is_valid_signer,
+        },
+        keypair::{signer_from_path_with_config, SignerFromPathConfig},
+    },
+    solana_cli_output::OutputFormat,
+    solana_client::rpc_client::RpcClient,
+    solana_program::{instruction::Instruction, program_pack::Pack, pubkey::Pubkey},
+    solana_remote_wallet::remote_wallet::RemoteWalletManager,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        hash::Hash,
+        message::Message,
+        native_token::Sol,
+        signature::{Keypair, Signer},
+        signers::Signers,
+        system_instruction,
+        transaction::Transaction,
+    },
+    spl_associated_token_account::get_associated_token_address,
+    spl_token_swap::{
+        constraints::SWAP_CONSTRAINTS,
+        curve::{
+            base::{CurveType, SwapCurve},
+            calculator::{RoundDirection, TradeDirection},
+            constant_price::ConstantPriceCurve,
+            constant_product::ConstantProductCurve,
+            fees::Fees,
+            offset::OffsetCurve,
+            stable::StableCurve,
+        },
+        instruction::{
+            DepositAllTokenTypes, DepositSingleTokenTypeExactAmountIn, Swap, WithdrawAllTokenTypes,
+            WithdrawSingleTokenTypeExactAmountOut,
+        },
+        processor::Processor,
+        state::{SwapState, SwapVersion},
+    },
+    spl_token_swap_quote::{self as quote, PoolBalances, SwapPool},
+    std::{convert::TryFrom, process::exit, sync::Arc},
+};
+// use instruction::create_associated_token_account once ATA 1.0.5 is released
+#[allow(deprecated)]
+use spl_associated_token_account::create_associated_token_account;
+
+pub(crate) struct Config {
+    rpc_client: RpcClient,
+    verbose: bool,
+    output_format: OutputFormat,
+    owner: Box<dyn Signer>,
+    fee_payer: Box<dyn Signer>,
+    dry_run: bool,
+}
+
+type Error = Box<dyn std::error::Error>;
+type CommandResult = Result<(), Error>;
+
+/// Decimals used for the pool token mint of new pools
+const POOL_TOKEN_DECIMALS: u8 = 9;
+
+/// Denominator used for slippage tolerances, which are given in basis points
+const SLIPPAGE_DENOMINATOR: u64 = 10_000;
+
+macro_rules! unique_signers {
+    ($vec:ident) => {
+        $vec.sort_by_key(|l| l.pubkey());
+        $vec.dedup();
+    };
+}
+
+fn check_fee_payer_balance(config: &Config, required_balance: u64) -> Result<(), Error> {
+    let balance = config.rpc_client.get_balance(&config.fee_payer.pubkey())?;
+    if balance < required_balance {
+        Err(format!(
+            "Fee payer, {}, has insufficient balance: {} required, {} available",
+            config.fee_payer.pubkey(),
+            Sol(required_balance),
+            Sol(balance)
+        )
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+fn check_swap_constraints(
+    fees: &Fees,
+    swap_curve: &SwapCurve,
+    fee_owner: &Pubkey,
+) -> Result<(), Error> {
+    if let Some(swap_constraints) = SWAP_CONSTRAINTS {
+        let owner_key = swap_constraints
+            .owner_key
+            .parse::<Pubkey>()
+            .map_err(|err| format!("Invalid swap constraints owner key: {}", err))?;
+        if *fee_owner != owner_key {
+            return Err(format!(
+                "Pool fee account must be owned by {} for this program, received {}",
+                owner_key, fee_owner
+            )
+            .into());
+        }
+        swap_constraints.validate_curve(swap_curve).map_err(|_| {
+            format!(
+                "Curve type {} is not allowed by this program",
+                curve_type_name(swap_curve.curve_type)
+            )
+        })?;
+        swap_constraints.validate_fees(fees).map_err(|_| {
+            format!(
+                "Fees are not allowed by this program, minimum fees are {:?}",
+                swap_constraints.fees
+            )
+        })?;
+    }
+    Ok(())
+}
+
+fn get_signer(
+    matches: &ArgMatches<'_>,
+    keypair_name: &str,
+    keypair_path: &str,
+    wallet_manager: &mut Option<Arc<RemoteWalletManager>>,
+    signer_from_path_config: SignerFromPathConfig,
+) -> Box<dyn Signer> {
+    signer_from_path_with_config(
+        matches,
+        matches.value_of(keypair_name).unwrap_or(keypair_path),
+        keypair_name,
+        wallet_manager,
+        &signer_from_path_config,
+    )
+    .unwrap_or_else(|e| {
+        eprintln!("error: {}", e);
+        exit(1);
+    })
+}
+
+fn get_latest_blockhash(client: &RpcClient) -> Result<Hash, Error> {
+    Ok(client
+        .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())?
+        .0)
+}
+
+fn send_transaction(
+    config: &Config,
+    transaction: Transaction,
+) -> solana_client::client_error::Result<()> {
+    if config.dry_run {
+        let result = config.rpc_client.simulate_transaction(&transaction)?;
+        println!("Simulate result: {:?}", result);
+    } else {
+        let signature = config
+            .rpc_client
+            .send_and_confirm_transaction_with_spinner(&transaction)?;
+        println!("Signature: {}", signature);
+    }
+    Ok(())
+}
+
+fn checked_transaction_with_signers<T: Signers>(
+    config: &Config,
+    instructions: &[Instruction],
+    signers: &T,
+    rent_free_balances: u64,
+) -> Result<Transaction, Error> {
+    let recent_blockhash = get_latest_blockhash(&config.rpc_client)?;
+    let message = Message::new_with_blockhash(
+        instructions,
+        Some(&config.fee_payer.pubkey()),
+        &recent_blockhash,
+    );
+    check_fee_payer_balance(
+        config,
+        rent_free_balances + config.rpc_client.get_fee_for_message(&message)?,
+    )?;
+    let transaction = Transaction::new(signers, message, recent_blockhash);
+    Ok(transaction)
+}
+
+fn add_associated_token_account(
+    config: &Config,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    instructions: &mut Vec<Instruction>,
+    rent_free_balances: &mut u64,
+) -> Pubkey {
+    // Account for tokens not specified, creating one
+    let account = get_associated_token_address(owner, mint);
+    if get_token_account(&config.rpc_client, &account, mint).is_err() {
+        println!(
+            "Creating associated token account {} for tokens of mint {}, owned by {}",
+            account, mint, owner
+        );
+
+        let min_account_balance = config
+            .rpc_client
+            .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)
+            .unwrap();
+
+        #[allow(deprecated)]
+        instructions.push(create_associated_token_account(
+            &config.fee_payer.pubkey(),
+            owner,
+            mint,
+        ));
+
+        *rent_free_balances += min_account_balance;
+    } else if config.verbose {
+        println!(
+            "Using existing associated token account {} for tokens of mint {}, owned by {}",
+            account, mint, owner
+        );
+    }
+
+    account
+}
+
+fn add_token_account(
+    config: &Config,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    instructions: &mut Vec<Instruction>,
+    rent_free_balances: &mut u64,
+) -> Result<Keypair, Error> {
+    let keypair = Keypair::new();
+    let min_account_balance = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(spl_token::state::Account::LEN)?;
+    instructions.push(system_instruction::create_account(
+        &config.fee_payer.pubkey(),
+        &keypair.pubkey(),
+        min_account_balance,
+        spl_token::state::Account::LEN as u64,
+        &spl_token::id(),
+    ));
+    instructions.push(spl_token::instruction::initialize_account(
+        &spl_token::id(),
+        &keypair.pubkey(),
+        mint,
+        owner,
+    )?);
+    *rent_free_balances += min_account_balance;
+    Ok(keypair)
+}
+
+fn to_u64(val: u128) -> Result<u64, Error> {
+    u64::try_from(val).map_err(|_| "Amount does not fit in a u64".into())
+}
+
+fn minimum_with_slippage(amount: u64, slippage: u64) -> u64 {
+    let minimum = u128::from(amount) * u128::from(SLIPPAGE_DENOMINATOR.saturating_sub(slippage))
+        / u128::from(SLIPPAGE_DENOMINATOR);
+    minimum as u64
+}
+
+fn maximum_with_slippage(amount: u64, slippage: u64) -> u64 {
+    let numerator = u128::from(amount) * u128::from(SLIPPAGE_DENOMINATOR + slippage);
+    let denominator = u128::from(SLIPPAGE_DENOMINATOR);
+    let mut maximum = numerator / denominator;
+    if numerator % denominator > 0 {
+        maximum += 1;
+    }
+    u64::try_from(maximum).unwrap_or(u64::MAX)
+}
+
+/// On-chain state of a token swap, along with all of its token balances
+struct TokenSwapInfo {
+    token_swap: Arc<dyn SwapState>,
+    authority: Pubkey,
+    token_a_amount: u64,
+    token_b_amount: u64,
+    token_a_decimals: u8,
+    token_b_decimals: u8,
+    pool_token_supply: u64,
+    pool_token_decimals: u8,
+    pool: SwapPool,
+    unix_timestamp: i64,
+}
+
+impl TokenSwapInfo {
+    fn trade_direction(&self, source_mint: &Pubkey) -> Result<TradeDirection, Error> {
+        if *source_mint == *self.token_swap.token_a_mint() {
+            Ok(TradeDirection::AtoB)
+        } else if *source_mint == *self.token_swap.token_b_mint() {
+            Ok(TradeDirection::BtoA)
+        } else {
+            Err(format!(
+                "Mint {} is neither token A nor token B of the swap",
+                source_mint
+            )
+            .into())
+        }
+    }
+
+    /// Swap token amounts and decimals, ordered as (source, destination)
+    fn source_and_destination(&self, trade_direction: TradeDirection) -> ((u64, u8), (u64, u8)) {
+        let a = (self.token_a_amount, self.token_a_decimals);
+        let b = (self.token_b_amount, self.token_b_decimals);
+        match trade_direction {
+            TradeDirection::AtoB => (a, b),
+            TradeDirection::BtoA => (b, a),
+        }
+    }
+
+    fn mint(&self, trade_direction: TradeDirection) -> &Pubkey {
+        match trade_direction {
+            TradeDirection::AtoB => self.token_swap.token_a_mint(),
+            TradeDirection::BtoA => self.token_swap.token_b_mint(),
+        }
+    }
+
+    fn account(&self, trade_direction: TradeDirection) -> &Pubkey {
+        match trade_direction {
+            TradeDirection::AtoB => self.token_swap.token_a_account(),
+            TradeDirection::BtoA => self.token_swap.token_b_account(),
+        }
+    }
+
+    fn balances(&self) -> PoolBalances {
+        PoolBalances {
+            token_a_amount: self.token_a_amount,
+            token_b_amount: self.token_b_amount,
+            pool_token_supply: self.pool_token_supply,
+        }
+    }
+
+    /// The curve holds its calculator behind an `Arc<dyn CurveCalculator>`,
+    /// so it is copied through its packed layout
+    fn swap_pool(token_swap: &dyn SwapState) -> Result<SwapPool, Error> {
+        let mut curve = [0u8; SwapCurve::LEN];
+        token_swap.swap_curve().pack_into_slice(&mut curve);
+        Ok(SwapPool {
+            curve: SwapCurve::unpack_from_slice(&curve)?,
+            fees: token_swap.fees().clone(),
+            dynamic_fee: token_swap
+                .dynamic_fee()
+                .filter(|dynamic_fee| dynamic_fee.is_enabled())
+                .cloned(),
+            is_paused: token_swap.is_paused(),
+        })
+    }
+
+    fn check_not_paused(&self) -> Result<(), Error> {
+        if self.token_swap.is_paused() {
+            Err("Token swap is paused".into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn get_token_swap_info(
+    config: &Config,
+    token_swap_address: &Pubkey,
+) -> Result<TokenSwapInfo, Error> {
+    let token_swap = get_token_swap(&config.rpc_client, token_swap_address)?;
+    let authority = Processor::authority_id(
+        &spl_token_swap::id(),
+        token_swap_address,
+        token_swap.bump_seed(),
+    )?;
+    let token_a = get_token_account(
+        &config.rpc_client,
+        token_swap.token_a_account(),
+        token_swap.token_a_mint(),
+    )?;
+    let token_b = get_token_account(
+        &config.rpc_client,
+        token_swap.token_b_account(),
+        token_swap.token_b_mint(),
+    )?;
+    let token_a_mint = get_token_mint(&config.rpc_client, token_swap.token_a_mint())?;
+    let token_b_mint = get_token_mint(&config.rpc_client, token_swap.token_b_mint())?;
+    let pool_mint = get_token_mint(&config.rpc_client, token_swap.pool_mint())?;
+    // dynamic fees depend on the current time
+    let unix_timestamp = match token_swap.dynamic_fee() {
+        Some(dynamic_fee) if dynamic_fee.is_enabled() => {
+            get_clock(&config.rpc_client)?.unix_timestamp
+        }
+        _ => 0,
+    };
+    let pool = TokenSwapInfo::swap_pool(token_swap.as_ref())?;
+    Ok(TokenSwapInfo {
+        token_swap,
+        authority,
+        token_a_amount: token_a.amount,
+        token_b_amount: token_b.amount,
+        token_a_decimals: token_a_mint.decimals,
+        token_b_decimals: token_b_mint.decimals,
+        pool_token_supply: pool_mint.supply,
+        pool_token_decimals: pool_mint.decimals,
+        pool,
+        unix_timestamp,
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn command_create_pool(
+    config: &Config,
+    token_a_mint: &Pubkey,
+    token_b_mint: &Pubkey,
+    token_a_amount: f64,
+    token_b_amount: f64,
+    token_a_source: &Option<Pubkey>,
+    token_b_source: &Option<Pubkey>,
+    fees: Fees,
+    swap_curve: SwapCurve,
+    fee_owner: &Option<Pubkey>,
+    admin: &Option<Pubkey>,
+    swap_keypair: Option<Keypair>,
+    pool_mint_keypair: Option<Keypair>,
+) -> CommandResult {
+    if token_a_mint == token_b_mint {
+        return Err("Token A and token B must have different mints".into());
+    }
+    let fee_owner = fee_owner.unwrap_or_else(|| config.owner.pubkey());
+    check_swap_constraints(&fees, &swap_curve, &fee_owner)?;
+    fees.validate()
+        .map_err(|err| format!("Invalid fees: {}", err))?;
+    swap_curve
+        .calculator
+        .validate()
+        .map_err(|err| format!("Invalid curve: {}", err))?;
+
+    let token_a_decimals = get_token_mint(&config.rpc_client, token_a_mint)?.decimals;
+    let token_b_decimals = get_token_mint(&config.rpc_client, token_b_mint)?.decimals;
+    let token_a_amount = spl_token::ui_amount_to_amount(token_a_amount, token_a_decimals);
+    let token_b_amount = spl_token::ui_amount_to_amount(token_b_amount, token_b_decimals);
+    swap_curve
+        .calculator
+        .validate_supply(token_a_amount, token_b_amount)
+        .map_err(|err| format!("Invalid initial liquidity: {}", err))?;
+
+    let token_a_source = token_a_source
+        .unwrap_or_else(|| get_associated_token_address(&config.owner.pubkey(), token_a_mint));
+    let token_b_source = token_b_source
+        .unwrap_or_else(|| get_associated_token_address(&config.owner.pubkey(), token_b_mint));
+    for (source, mint, amount) in [
+        (&token_a_source, token_a_mint, token_a_amount),
+        (&token_b_source, token_b_mint, token_b_amount),
+    ] {
+        let account = get_token_account(&config.rpc_client, source, mint)?;
+        if account.amount < amount {
+            return Err(format!(
+                "Not enough tokens in {} to provide initial liquidity: {} required, {} available",
+                source, amount, account.amount
+            )
+            .into());
+        }
+    }
+
+    let swap_keypair = swap_keypair.unwrap_or_else(Keypair::new);
+    let pool_mint_keypair = pool_mint_keypair.unwrap_or_else(Keypair::new);
+    let (authority, _) =
+        Pubkey::find_program_address(&[&swap_keypair.pubkey().to_bytes()], &spl_token_swap::id());
+    if config.verbose {
+        println!("Token swap authority {}", authority);
+    }
+
+    let mut setup_instructions = vec![];
+    let mut setup_rent_free_balances = 0;
+    let token_a_keypair = add_token_account(
+        config,
+        token_a_mint,
+        &authority,
+        &mut setup_instructions,
+        &mut setup_rent_free_balances,
+    )?;
+    println!("Creating token A account {}", token_a_keypair.pubkey());
+    let token_b_keypair = add_token_account(
+        config,
+        token_b_mint,
+        &authority,
+        &mut setup_instructions,
+        &mut setup_rent_free_balances,
+    )?;
+    println!("Creating token B account {}", token_b_keypair.pubkey());
+
+    let mint_account_balance = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)?;
+    setup_instructions.extend([
+        system_instruction::create_account(
+            &config.fee_payer.pubkey(),
+            &pool_mint_keypair.pubkey(),
+            mint_account_balance,
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(
+            &spl_token::id(),
+            &pool_mint_keypair.pubkey(),
+            &authority,
+            None,
+            POOL_TOKEN_DECIMALS,
+        )?,
+    ]);
+    setup_rent_free_balances += mint_account_balance;
+    println!("Creating pool token mint {}", pool_mint_keypair.pubkey());
+
+    let pool_fee_keypair = add_token_account(
+        config,
+        &pool_mint_keypair.pubkey(),
+        &fee_owner,
+        &mut setup_instructions,
+        &mut setup_rent_free_balances,
+    )?;
+    println!(
+        "Creating pool fee account {}, owned by {}",
+        pool_fee_keypair.pubkey(),
+        fee_owner
+    );
+
+    let mut setup_signers = vec![
+        config.fee_payer.as_ref(),
+        &token_a_keypair,
+        &token_b_keypair,
+        &pool_mint_keypair,
+        &pool_fee_keypair,
+    ];
+    unique_signers!(setup_signers);
+    let setup_transaction = checked_transaction_with_signers(
+        config,
+        &setup_instructions,
+        &setup_signers,
+        setup_rent_free_balances,
+    )?;
+
+    let mut initialize_instructions = vec![];
+    let mut initialize_rent_free_balances = 0;
+    let destination = add_associated_token_account(
+        config,
+        &pool_mint_keypair.pubkey(),
+        &config.owner.pubkey(),
+        &mut initialize_instructions,
+        &mut initialize_rent_free_balances,
+    );
+    let swap_account_balance = config
+        .rpc_client
+        .get_minimum_balance_for_rent_exemption(SwapVersion::LATEST_LEN)?;
+    initialize_rent_free_balances += swap_account_balance;
+    initialize_instructions.extend([
+        spl_token::instruction::transfer(
+            &spl_token::id(),
+            &token_a_source,
+            &token_a_keypair.pubkey(),
+            &config.owner.pubkey(),
+            &[],
+            token_a_amount,
+        )?,
+        spl_token::instruction::transfer(
+            &spl_token::id(),
+            &token_b_source,
+            &token_b_keypair.pubkey(),
+            &config.owner.pubkey(),
+            &[],
+            token_b_amount,
+        )?,
+        system_instruction::create_account(
+            &config.fee_payer.pubkey(),
+            &swap_keypair.pubkey(),
+            swap_account_balance,
+            SwapVersion::LATEST_LEN as u64,
+            &spl_token_swap::id(),
+        ),
+    ]);
+    initialize_instructions.push(if let Some(admin) = admin {
+        spl_token_swap::instruction::initialize_with_admin(
+            &spl_token_swap::id(),
+            &spl_token::id(),
+            &swap_keypair.pubkey(),
+            &authority,
+            &token_a_keypair.pubkey(),
+            &token_b_keypair.pubkey(),
+            &pool_mint_keypair.pubkey(),
+            &pool_fee_keypair.pubkey(),
+            &destination,
+            admin,
+            fees,
+            swap_curve,
+        )?
+    } else {
+        spl_token_swap::instruction::initialize(
+            &spl_token_swap::id(),
+            &spl_token::id(),
+            &swap_keypair.pubkey(),
+            &authority,
+            &token_a_keypair.pubkey(),
+            &token_b_keypair.pubkey(),
+            &pool_mint_keypair.pubkey(),
+            &pool_fee_keypair.pubkey(),
+            &destination,
+            fees,
+            swap_curve,
+        )?
+    });
+    let mut initialize_signers = vec![
+        config.fee_payer.as_ref(),
+        config.owner.as_ref(),
+        &swap_keypair,
+    ];
+    unique_signers!(initialize_signers);
+    let initialize_transaction = checked_transaction_with_signers(
+        config,
+        &initialize_instructions,
+        &initialize_signers,
+        initialize_rent_free_balances,
+    )?;
+
+    send_transaction(config, setup_transaction)?;
+    println!("Creating token swap {}", swap_keypair.pubkey());
+    send_transaction(config, initialize_transaction)?;
+    Ok(())
+}
+
+fn command_info(config: &Config, token_swap_address: &Pubkey) -> CommandResult {
+    let info = get_token_swap_info(config, token_swap_address)?;
+    let balances = CliTokenSwapBalances {
+        token_a_amount: info.token_a_amount,
+        token_a_ui_amount: spl_token::amount_to_ui_amount(
+            info.token_a_amount,
+            info.token_a_decimals,
+        ),
+        token_b_amount: info.token_b_amount,
+        token_b_ui_amount: spl_token::amount_to_ui_amount(
+            info.token_b_amount,
+            info.token_b_decimals,
+        ),
+        pool_token_supply: info.pool_token_supply,
+        pool_token_ui_supply: spl_token::amount_to_ui_amount(
+            info.pool_token_supply,
+            info.pool_token_decimals,
+        ),
+    };
+    let cli_token_swap = CliTokenSwap::new(
+        token_swap_address.to_string(),
+        info.authority.to_string(),
+        info.token_swap.as_ref(),
+        balances,
+    );
+    println!("{}", config.output_format.formatted_string(&cli_token_swap));
+    Ok(())
+}
+
+fn quote_swap(
+    info: &TokenSwapInfo,
+    source_mint: &Pubkey,
+    ui_amount_in: f64,
+    slippage: u64,
+) -> Result<CliTokenSwapQuote, Error> {
+    let trade_direction = info.trade_direction(source_mint)?;
+    let ((_, source_decimals), (_, destination_decimals)) =
+        info.source_and_destination(trade_direction);
+    let amount_in = spl_token::ui_amount_to_amount(ui_amount_in, source_decimals);
+    let quote = quote::quote_swap(
+        &info.pool,
+        &info.balances(),
+        amount_in,
+        trade_direction,
+        false,
+        info.unix_timestamp,
+    )
+    .map_err(|err| format!("Could not quote the swap: {}", err))?;
+    let minimum_amount_out = minimum_with_slippage(quote.amount_out, slippage);
+
+    Ok(CliTokenSwapQuote {
+        source_mint: source_mint.to_string(),
+        destination_mint: info.mint(trade_direction.opposite()).to_string(),
+        amount_in,
+        ui_amount_in: spl_token::amount_to_ui_amount(amount_in, source_decimals),
+        amount_out: quote.amount_out,
+        ui_amount_out: spl_token::amount_to_ui_amount(quote.amount_out, destination_decimals),
+        trade_fee: quote.trade_fee,
+        owner_fee: quote.owner_fee,
+        minimum_amount_out,
+        minimum_ui_amount_out: spl_token::amount_to_ui_amount(
+            minimum_amount_out,
+            destination_decimals,
+        ),
+        price_impact_percent: quote.price_impact_bps as f64 / 100.0,
+    })
+}
+
+fn command_quote(
+    config: &Config,
+    token_swap_address: &Pubkey,
+    source_mint: &Pubkey,
+    ui_amount_in: f64,
+    slippage: u64,
+) -> CommandResult {
+    let info = get_token_swap_info(config, token_swap_address)?;
+    let quote = quote_swap(&info, source_mint, ui_amount_in, slippage)?;
+    println!("{}", config.output_format.formatted_string(&quote));
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn command_swap(
+    config: &Config,
+    token_swap_address: &Pubkey,
+    source_mint: &Pubkey,
+    ui_amount_in: f64,
+    source: &Option<Pubkey>,
+    destination: &Option<Pubkey>,
+    host_fee_account: &Option<Pubkey>,
+    minimum_amount_out: Option<f64>,
+    slippage: u64,
+) -> CommandResult {
+    let info = get_token_swap_info(config, token_swap_address)?;
+    info.check_not_paused()?;
+    let quote = quote_swap(&info, source_mint, ui_amount_in, slippage)?;
+    let trade_direction = info.trade_direction(source_mint)?;
+    let destination_mint = *info.mint(trade_direction.opposite());
+    let minimum_amount_out = match minimum_amount_out {
+        Some(minimum_amount_out) => {
+            let (_, (_, destination_decimals)) = info.source_and_destination(trade_direction);
+            spl_token::ui_amount_to_amount(minimum_amount_out, destination_decimals)
+        }
+        None => quote.minimum_amount_out,
+    };
+    if quote.amount_out < minimum_amount_out {
+        return Err(format!(
+            "Expected output {} is below the minimum amount out {}",
+            quote.amount_out, minimum_amount_out
+        )
+        .into());
+    }
+    if config.verbose {
+        println!(
+            "Swapping {} tokens of mint {} for at least {} tokens of mint {}, expecting {}",
+            quote.amount_in, source_mint, minimum_amount_out, destination_mint, quote.amount_out
+        );
+    }
+
+    let source =
+        source.unwrap_or_else(|| get_associated_token_address(&config.owner.pubkey(), source_mint));
+    let source_account = get_token_account(&config.rpc_client, &source, source_mint)?;
+    if source_account.amount < quote.amount_in {
+        return Err(format!(
+            "Not enough tokens in {}: {} required, {} available",
+            source, quote.amount_in, source_account.amount
+        )
+        .into());
+    }
+
+    let mut instructions = vec![];
+    let mut rent_free_balances = 0;
+    let destination = destination.unwrap_or_else(|| {
+        add_associated_token_account(
+            config,
+            &destination_mint,
+            &config.owner.pubkey(),
+            &mut instructions,
+            &mut rent_free_balances,
+        )
+    });
+    let swap = if info.token_swap.dynamic_fee().is_some() {
+        spl_token_swap::instruction::swap_with_dynamic_fee
+    } else {
+        spl_token_swap::instruction::swap
+    };
+    instructions.push(swap(
+        &spl_token_swap::id(),
+        info.token_swap.token_program_id(),
+        token_swap_address,
+        &info.authority,
+        &config.owner.pubkey(),
+        &source,
+        info.account(trade_direction),
+        info.account(trade_direction.opposite()),
+        &destination,
+        info.token_swap.pool_mint(),
+        info.token_swap.pool_fee_account(),
+        host_fee_account.as_ref(),
+        Swap {
+            amount_in: quote.amount_in,
+            minimum_amount_out,
+        },
+    )?);
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.owner.as_ref()];
+    unique_signers!(signers);
+    let transaction =
+        checked_transaction_with_signers(config, &instructions, &signers, rent_free_balances)?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn command_deposit(
+    config: &Config,
+    token_swap_address: &Pubkey,
+    ui_pool_token_amount: f64,
+    token_a_source: &Option<Pubkey>,
+    token_b_source: &Option<Pubkey>,
+    pool_token_receiver: &Option<Pubkey>,
+    slippage: u64,
+) -> CommandResult {
+    let info = get_token_swap_info(config, token_swap_address)?;
+    info.check_not_paused()?;
+    if !info.token_swap.swap_curve().calculator.allows_deposits() {
+        return Err("Token swap curve does not allow deposits".into());
+    }
+    let pool_token_amount =
+        spl_token::ui_amount_to_amount(ui_pool_token_amount, info.pool_token_decimals);
+    let results = info
+        .token_swap
+        .swap_curve()
+        .calculator
+        .pool_tokens_to_trading_tokens(
+            u128::from(pool_token_amount),
+            u128::from(info.pool_token_supply),
+            u128::from(info.token_a_amount),
+            u128::from(info.token_b_amount),
+            RoundDirection::Ceiling,
+        )
+        .ok_or("Pool token amount too small or too large for the pool")?;
+    let token_a_amount = to_u64(results.token_a_amount)?;
+    let token_b_amount = to_u64(results.token_b_amount)?;
+    println!(
+        "Depositing up to {} of token A and {} of token B for {} pool tokens",
+        spl_token::amount_to_ui_amount(token_a_amount, info.token_a_decimals),
+        spl_token::amount_to_ui_amount(token_b_amount, info.token_b_decimals),
+        ui_pool_token_amount,
+    );
+
+    let token_a_source = token_a_source.unwrap_or_else(|| {
+        get_associated_token_address(&config.owner.pubkey(), info.token_swap.token_a_mint())
+    });
+    let token_b_source = token_b_source.unwrap_or_else(|| {
+        get_associated_token_address(&config.owner.pubkey(), info.token_swap.token_b_mint())
+    });
+
+    let mut instructions = vec![];
+    let mut rent_free_balances = 0;
+    let pool_token_receiver = pool_token_receiver.unwrap_or_else(|| {
+        add_associated_token_account(
+            config,
+            info.token_swap.pool_mint(),
+            &config.owner.pubkey(),
+            &mut instructions,
+            &mut rent_free_balances,
+        )
+    });
+    instructions.push(spl_token_swap::instruction::deposit_all_token_types(
+        &spl_token_swap::id(),
+        info.token_swap.token_program_id(),
+        token_swap_address,
+        &info.authority,
+        &config.owner.pubkey(),
+        &token_a_source,
+        &token_b_source,
+        info.token_swap.token_a_account(),
+        info.token_swap.token_b_account(),
+        info.token_swap.pool_mint(),
+        &pool_token_receiver,
+        DepositAllTokenTypes {
+            pool_token_amount,
+            maximum_token_a_amount: maximum_with_slippage(token_a_amount, slippage),
+            maximum_token_b_amount: maximum_with_slippage(token_b_amount, slippage),
+        },
+    )?);
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.owner.as_ref()];
+    unique_signers!(signers);
+    let transaction =
+        checked_transaction_with_signers(config, &instructions, &signers, rent_free_balances)?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn command_withdraw(
+    config: &Config,
+    token_swap_address: &Pubkey,
+    ui_pool_token_amount: f64,
+    pool_token_source: &Option<Pubkey>,
+    token_a_receiver: &Option<Pubkey>,
+    token_b_receiver: &Option<Pubkey>,
+    slippage: u64,
+) -> CommandResult {
+    let info = get_token_swap_info(config, token_swap_address)?;
+    let pool_token_amount =
+        spl_token::ui_amount_to_amount(ui_pool_token_amount, info.pool_token_decimals);
+    let pool_token_source = pool_token_source.unwrap_or_else(|| {
+        get_associated_token_address(&config.owner.pubkey(), info.token_swap.pool_mint())
+    });
+    let withdraw_fee = if pool_token_source == *info.token_swap.pool_fee_account() {
+        // withdrawing from the fee account is free
+        0
+    } else {
+        info.token_swap
+            .fees()
+            .owner_withdraw_fee(u128::from(pool_token_amount))
+            .ok_or("Could not calculate the withdraw fee")?
+    };
+    let results = info
+        .token_swap
+        .swap_curve()
+        .calculator
+        .pool_tokens_to_trading_tokens(
+            u128::from(pool_token_amount).saturating_sub(withdraw_fee),
+            u128::from(info.pool_token_supply),
+            u128::from(info.token_a_amount),
+            u128::from(info.token_b_amount),
+            RoundDirection::Floor,
+        )
+        .ok_or("Pool token amount too small or too large for the pool")?;
+    let token_a_amount = to_u64(results.token_a_amount)?;
+    let token_b_amount = to_u64(results.token_b_amount)?;
+    println!(
+        "Withdrawing {} pool tokens for about {} of token A and {} of token B",
+        ui_pool_token_amount,
+        spl_token::amount_to_ui_amount(token_a_amount, info.token_a_decimals),
+        spl_token::amount_to_ui_amount(token_b_amount, info.token_b_decimals),
+    );
+
+    let mut instructions = vec![];
+    let mut rent_free_balances = 0;
+    let token_a_receiver = token_a_receiver.unwrap_or_else(|| {
+        add_associated_token_account(
+            config,
+            info.token_swap.token_a_mint(),
+            &config.owner.pubkey(),
+            &mut instructions,
+            &mut rent_free_balances,
+        )
+    });
+    let token_b_receiver = token_b_receiver.unwrap_or_else(|| {
+        add_associated_token_account(
+            config,
+            info.token_swap.token_b_mint(),
+            &config.owner.pubkey(),
+            &mut instructions,
+            &mut rent_free_balances,
+        )
+    });
+    instructions.push(spl_token_swap::instruction::withdraw_all_token_types(
+        &spl_token_swap::id(),
+        info.token_swap.token_program_id(),
+        token_swap_address,
+        &info.authority,
+        &config.owner.pubkey(),
+        info.token_swap.pool_mint(),
+        info.token_swap.pool_fee_account(),
+        &pool_token_source,
+        info.token_swap.token_a_account(),
+        info.token_swap.token_b_account(),
+        &token_a_receiver,
+        &token_b_receiver,
+        WithdrawAllTokenTypes {
+            pool_token_amount,
+            minimum_token_a_amount: minimum_with_slippage(token_a_amount, slippage),
+            minimum_token_b_amount: minimum_with_slippage(token_b_amount, slippage),
+        },
+    )?);
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.owner.as_ref()];
+    unique_signers!(signers);
+    let transaction =
+        checked_transaction_with_signers(config, &instructions, &signers, rent_free_balances)?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn command_deposit_single(
+    config: &Config,
+    token_swap_address: &Pubkey,
+    source_mint: &Pubkey,
+    ui_amount: f64,
+    source: &Option<Pubkey>,
+    pool_token_receiver: &Option<Pubkey>,
+    slippage: u64,
+) -> CommandResult {
+    let info = get_token_swap_info(config, token_swap_address)?;
+    info.check_not_paused()?;
+    if !info.token_swap.swap_curve().calculator.allows_deposits() {
+        return Err("Token swap curve does not allow deposits".into());
+    }
+    let trade_direction = info.trade_direction(source_mint)?;
+    let ((_, source_decimals), _) = info.source_and_destination(trade_direction);
+    let source_token_amount = spl_token::ui_amount_to_amount(ui_amount, source_decimals);
+    let pool_token_amount = quote::quote_deposit_single_token_type(
+        &info.pool,
+        &info.balances(),
+        source_token_amount,
+        trade_direction,
+        info.unix_timestamp,
+    )
+    .map_err(|err| format!("Could not quote the deposit: {}", err))?
+    .pool_token_amount;
+    println!(
+        "Depositing {} tokens of mint {} for about {} pool tokens",
+        ui_amount,
+        source_mint,
+        spl_token::amount_to_ui_amount(pool_token_amount, info.pool_token_decimals),
+    );
+
+    let source =
+        source.unwrap_or_else(|| get_associated_token_address(&config.owner.pubkey(), source_mint));
+    let mut instructions = vec![];
+    let mut rent_free_balances = 0;
+    let pool_token_receiver = pool_token_receiver.unwrap_or_else(|| {
+        add_associated_token_account(
+            config,
+            info.token_swap.pool_mint(),
+            &config.owner.pubkey(),
+            &mut instructions,
+            &mut rent_free_balances,
+        )
+    });
+    instructions.push(
+        spl_token_swap::instruction::deposit_single_token_type_exact_amount_in(
+            &spl_token_swap::id(),
+            info.token_swap.token_program_id(),
+            token_swap_address,
+            &info.authority,
+            &config.owner.pubkey(),
+            &source,
+            info.token_swap.token_a_account(),
+            info.token_swap.token_b_account(),
+            info.token_swap.pool_mint(),
+            &pool_token_receiver,
+            DepositSingleTokenTypeExactAmountIn {
+                source_token_amount,
+                minimum_pool_token_amount: minimum_with_slippage(pool_token_amount, slippage),
+            },
+        )?,
+    );
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.owner.as_ref()];
+    unique_signers!(signers);
+    let transaction =
+        checked_transaction_with_signers(config, &instructions, &signers, rent_free_balances)?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn command_withdraw_single(
+    config: &Config,
+    token_swap_address: &Pubkey,
+    destination_mint: &Pubkey,
+    ui_amount: f64,
+    pool_token_source: &Option<Pubkey>,
+    destination: &Option<Pubkey>,
+    slippage: u64,
+) -> CommandResult {
+    let info = get_token_swap_info(config, token_swap_address)?;
+    let trade_direction = info.trade_direction(destination_mint)?;
+    let ((_, destination_decimals), _) = info.source_and_destination(trade_direction);
+    let destination_token_amount = spl_token::ui_amount_to_amount(ui_amount, destination_decimals);
+    let pool_token_source = pool_token_source.unwrap_or_else(|| {
+        get_associated_token_address(&config.owner.pubkey(), info.token_swap.pool_mint())
+    });
+    let pool_token_amount = quote::quote_withdraw_single_token_type_exact_out(
+        &info.pool,
+        &info.balances(),
+        destination_token_amount,
+        trade_direction,
+        pool_token_source == *info.token_swap.pool_fee_account(),
+        info.unix_timestamp,
+    )
+    .map_err(|err| format!("Could not quote the withdrawal: {}", err))?
+    .pool_token_amount;
+    println!(
+        "Withdrawing {} tokens of mint {} for about {} pool tokens",
+        ui_amount,
+        destination_mint,
+        spl_token::amount_to_ui_amount(pool_token_amount, info.pool_token_decimals),
+    );
+
+    let mut instructions = vec![];
+    let mut rent_free_balances = 0;
+    let destination = destination.unwrap_or_else(|| {
+        add_associated_token_account(
+            config,
+            destination_mint,
+            &config.owner.pubkey(),
+            &mut instructions,
+            &mut rent_free_balances,
+        )
+    });
+    instructions.push(
+        spl_token_swap::instruction::withdraw_single_token_type_exact_amount_out(
+            &spl_token_swap::id(),
+            info.token_swap.token_program_id(),
+            token_swap_address,
+            &info.authority,
+            &config.owner.pubkey(),
+            info.token_swap.pool_mint(),
+            info.token_swap.pool_fee_account(),
+            &pool_token_source,
+            info.token_swap.token_a_account(),
+            info.token_swap.token_b_account(),
+            &destination,
+            WithdrawSingleTokenTypeExactAmountOut {
+                destination_token_amount,
+                maximum_pool_token_amount: maximum_with_slippage(pool_token_amount, slippage),
+            },
+        )?,
+    );
+
+    let mut signers = vec![config.fee_payer.as_ref(), config.owner.as_ref()];
+    unique_signers!(signers);
+    let transaction =
+        checked_transaction_with_signers(config, &instructions, &signers, rent_free_balances)?;
+    send_transaction(config, transaction)?;
+    Ok(())
+}
+
+fn is_valid_slippage<T>(slippage: T) -> Result<(), String>
+where
+    T: AsRef<str> + std::fmt::Display,
+{
+    match slippage.as_ref().parse::<f64>() {
+        Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(()),
+        _ => Err(format!(
+            "Unable to parse input slippage percentage, provided: {}",
+            slippage
+        )),
+    }
+}
+
+fn slippage_of(matches: &ArgMatches<'_>) -> u64 {
+    let percent = value_t_or_exit!(matches, "slippage", f64);
+    (percent * (SLIPPAGE_DENOMINATOR / 100) as f64).round() as u64
+}
+
+fn fee_of(matches: &ArgMatches<'_>, name: &str) -> (u64, u64) {
+    let numerator = value_t!(matches, format!("{}_numerator", name), u64).unwrap_or(0);
+    let denominator = value_t!(matches, format!("{}_denominator", name), u64).unwrap_or(0);
+    (numerator, denominator)
+}
+
+fn main() {
+    solana_logger::setup_with_default("solana=info");
+
+    let slippage_arg = Arg::with_name("slippage")
+        .long("slippage")
+        .validator(is_valid_slippage)
+        .value_name("PERCENT")
+        .takes_value(true)
+        .default_value("1")
+        .help("Maximum price movement tolerated before the transaction fails, in percent");
+    let swap_arg = Arg::with_name("swap")
+        .index(1)
+        .validator(is_valid_pubkey)
+        .value_name("SWAP_ADDRESS")
+        .takes_value(true)
+        .required(true)
+        .help("Token swap address");
+    let pool_token_amount_arg = Arg::with_name("amount")
+        .index(2)
+        .validator(is_amount)
+        .value_name("POOL_TOKEN_AMOUNT")
+        .takes_value(true)
+        .required(true);
+    let matches = App::new(crate_name!())
+        .about(crate_description!())
+        .version(crate_version!())
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg({
+            let arg = Arg::with_name("config_file")
+                .short("C")
+                .long("config")
+                .value_name("PATH")
+                .takes_value(true)
+                .global(true)
+                .help("Configuration file to use");
+            if let Some(ref config_file) = *solana_cli_config::CONFIG_FILE {
+                arg.default_value(config_file)
+            } else {
+                arg
+            }
+        })
+        .arg(
+            Arg::with_name("verbose")
+                .long("verbose")
+                .short("v")
+                .takes_value(false)
+                .global(true)
+                .help("Show additional information"),
+        )
+        .arg(
+            Arg::with_name("output_format")
+                .long("output")
+                .value_name("FORMAT")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["json", "json-compact"])
+                .help("Return information in specified output format"),
+        )
+        .arg(
+            Arg::with_name("dry_run")
+                .long("dry-run")
+                .takes_value(false)
+                .global(true)
+                .help("Simulate transaction instead of executing"),
+        )
+        .arg(
+            Arg::with_name("json_rpc_url")
+                .long("url")
+                .value_name("URL")
+                .takes_value(true)
+                .validator(is_url)
+                .help("JSON RPC URL for the cluster.  Default from the configuration file."),
+        )
+        .arg(
+            Arg::with_name("owner")
+                .long("owner")
+                .value_name("KEYPAIR")
+                .validator(is_valid_signer)
+                .takes_value(true)
+                .help("Owner of the token accounts used, signing all transfers. [default: cli config keypair]"),
+        )
+        .arg(
+            Arg::with_name("fee_payer")
+                .long("fee-payer")
+                .value_name("KEYPAIR")
+                .validator(is_valid_signer)
+                .takes_value(true)
+                .help("Transaction fee payer account [default: cli config keypair]"),
+        )
+        .subcommand(SubCommand::with_name("create-pool")
+            .about("Create a new token swap, providing its initial liquidity")
+            .arg(
+                Arg::with_name("token_a_mint")
+                    .index(1)
+                    .validator(is_valid_pubkey)
+                    .value_name("TOKEN_A_MINT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Mint of token A"),
+            )
+            .arg(
+                Arg::with_name("token_b_mint")
+                    .index(2)
+                    .validator(is_valid_pubkey)
+                    .value_name("TOKEN_B_MINT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Mint of token B"),
+            )
+            .arg(
+                Arg::with_name("token_a_amount")
+                    .long("token-a-amount")
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Initial amount of token A in the pool"),
+            )
+            .arg(
+                Arg::with_name("token_b_amount")
+                    .long("token-b-amount")
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Initial amount of token B in the pool"),
+            )
+            .arg(
+                Arg::with_name("token_a_source")
+                    .long("token-a-source")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Source token A account. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("token_b_source")
+                    .long("token-b-source")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Source token B account. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("curve_type")
+                    .long("curve-type")
+                    .value_name("CURVE_TYPE")
+                    .takes_value(true)
+                    .possible_values(&["constant-product", "constant-price", "stable", "offset"])
+                    .default_value("constant-product")
+                    .help("Curve used to price trades"),
+            )
+            .arg(
+                Arg::with_name("token_b_price")
+                    .long("token-b-price")
+                    .validator(is_parsable::<u64>)
+                    .value_name("PRICE")
+                    .takes_value(true)
+                    .required_if("curve_type", "constant-price")
+                    .help("Price of token B in token A, for the constant price curve"),
+            )
+            .arg(
+                Arg::with_name("amp")
+                    .long("amp")
+                    .validator(is_parsable::<u64>)
+                    .value_name("AMP")
+                    .takes_value(true)
+                    .required_if("curve_type", "stable")
+                    .help("Amplification coefficient, for the stable curve"),
+            )
+            .arg(
+                Arg::with_name("token_b_offset")
+                    .long("token-b-offset")
+                    .validator(is_parsable::<u64>)
+                    .value_name("OFFSET")
+                    .takes_value(true)
+                    .required_if("curve_type", "offset")
+                    .help("Amount of token B faked in the pool, for the offset curve"),
+            )
+            .arg(
+                Arg::with_name("trade_fee_numerator")
+                    .long("trade-fee-numerator")
+                    .validator(is_parsable::<u64>)
+                    .value_name("NUMERATOR")
+                    .takes_value(true)
+                    .requires("trade_fee_denominator")
+                    .help("Trade fee numerator, kept by the pool on the swap amount, fee amount is numerator divided by denominator [default: 0]"),
+            )
+            .arg(
+                Arg::with_name("trade_fee_denominator")
+                    .long("trade-fee-denominator")
+                    .validator(is_parsable::<u64>)
+                    .value_name("DENOMINATOR")
+                    .takes_value(true)
+                    .requires("trade_fee_numerator")
+                    .help("Trade fee denominator, fee amount is numerator divided by denominator [default: 0]"),
+            )
+            .arg(
+                Arg::with_name("owner_trade_fee_numerator")
+                    .long("owner-trade-fee-numerator")
+                    .validator(is_parsable::<u64>)
+                    .value_name("NUMERATOR")
+                    .takes_value(true)
+                    .requires("owner_trade_fee_denominator")
+                    .help("Owner trade fee numerator, minted as pool tokens to the fee account on the swap amount, fee amount is numerator divided by denominator [default: 0]"),
+            )
+            .arg(
+                Arg::with_name("owner_trade_fee_denominator")
+                    .long("owner-trade-fee-denominator")
+                    .validator(is_parsable::<u64>)
+                    .value_name("DENOMINATOR")
+                    .takes_value(true)
+                    .requires("owner_trade_fee_numerator")
+                    .help("Owner trade fee denominator, fee amount is numerator divided by denominator [default: 0]"),
+            )
+            .arg(
+                Arg::with_name("owner_withdraw_fee_numerator")
+                    .long("owner-withdraw-fee-numerator")
+                    .validator(is_parsable::<u64>)
+                    .value_name("NUMERATOR")
+                    .takes_value(true)
+                    .requires("owner_withdraw_fee_denominator")
+                    .help("Owner withdraw fee numerator, taken on pool tokens withdrawn, fee amount is numerator divided by denominator [default: 0]"),
+            )
+            .arg(
+                Arg::with_name("owner_withdraw_fee_denominator")
+                    .long("owner-withdraw-fee-denominator")
+                    .validator(is_parsable::<u64>)
+                    .value_name("DENOMINATOR")
+                    .takes_value(true)
+                    .requires("owner_withdraw_fee_numerator")
+                    .help("Owner withdraw fee denominator, fee amount is numerator divided by denominator [default: 0]"),
+            )
+            .arg(
+                Arg::with_name("host_fee_numerator")
+                    .long("host-fee-numerator")
+                    .validator(is_parsable::<u64>)
+                    .value_name("NUMERATOR")
+                    .takes_value(true)
+                    .requires("host_fee_denominator")
+                    .help("Host fee numerator, taken on the owner trade fee, fee amount is numerator divided by denominator [default: 0]"),
+            )
+            .arg(
+                Arg::with_name("host_fee_denominator")
+                    .long("host-fee-denominator")
+                    .validator(is_parsable::<u64>)
+                    .value_name("DENOMINATOR")
+                    .takes_value(true)
+                    .requires("host_fee_numerator")
+                    .help("Host fee denominator, fee amount is numerator divided by denominator [default: 0]"),
+            )
+            .arg(
+                Arg::with_name("fee_owner")
+                    .long("fee-owner")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Owner of the pool fee account. [default: owner]"),
+            )
+            .arg(
+                Arg::with_name("admin")
+                    .long("admin")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Admin allowed to update fees and pause the swap"),
+            )
+            .arg(
+                Arg::with_name("swap_keypair")
+                    .long("swap-keypair")
+                    .validator(is_keypair_or_ask_keyword)
+                    .value_name("PATH")
+                    .takes_value(true)
+                    .help("Token swap account keypair to use. [default: new keypair]"),
+            )
+            .arg(
+                Arg::with_name("pool_mint_keypair")
+                    .long("pool-mint-keypair")
+                    .validator(is_keypair_or_ask_keyword)
+                    .value_name("PATH")
+                    .takes_value(true)
+                    .help("Pool token mint keypair to use. [default: new keypair]"),
+            )
+        )
+        .subcommand(SubCommand::with_name("info")
+            .about("Show information about a token swap")
+            .arg(swap_arg.clone())
+        )
+        .subcommand(SubCommand::with_name("quote")
+            .about("Quote the output of a swap, without sending a transaction")
+            .arg(swap_arg.clone())
+            .arg(
+                Arg::with_name("source_mint")
+                    .index(2)
+                    .validator(is_valid_pubkey)
+                    .value_name("SOURCE_MINT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Mint of the tokens to swap"),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(3)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Amount of source tokens to swap"),
+            )
+            .arg(slippage_arg.clone())
+        )
+        .subcommand(SubCommand::with_name("swap")
+            .about("Swap tokens")
+            .arg(swap_arg.clone())
+            .arg(
+                Arg::with_name("source_mint")
+                    .index(2)
+                    .validator(is_valid_pubkey)
+                    .value_name("SOURCE_MINT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Mint of the tokens to swap"),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(3)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Amount of source tokens to swap"),
+            )
+            .arg(
+                Arg::with_name("source")
+                    .long("source")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Source token account. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("destination")
+                    .long("destination")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Destination token account. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("host_fee_account")
+                    .long("host-fee-account")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Pool token account receiving the host fee"),
+            )
+            .arg(
+                Arg::with_name("minimum_amount_out")
+                    .long("minimum-amount-out")
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .help("Minimum amount of destination tokens to receive, overrides the slippage"),
+            )
+            .arg(slippage_arg.clone())
+        )
+        .subcommand(SubCommand::with_name("deposit")
+            .about("Deposit token A and token B in exchange for pool tokens")
+            .arg(swap_arg.clone())
+            .arg(pool_token_amount_arg.clone().help("Amount of pool tokens to receive"))
+            .arg(
+                Arg::with_name("token_a_source")
+                    .long("token-a-source")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Source token A account. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("token_b_source")
+                    .long("token-b-source")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Source token B account. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("pool_token_receiver")
+                    .long("pool-token-receiver")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Account to receive pool tokens. [default: owner's associated token account]"),
+            )
+            .arg(slippage_arg.clone())
+        )
+        .subcommand(SubCommand::with_name("withdraw")
+            .about("Burn pool tokens in exchange for token A and token B")
+            .arg(swap_arg.clone())
+            .arg(pool_token_amount_arg.clone().help("Amount of pool tokens to burn"))
+            .arg(
+                Arg::with_name("pool_token_source")
+                    .long("pool-token-source")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Pool token account to burn from. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("token_a_receiver")
+                    .long("token-a-receiver")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Account to receive token A. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("token_b_receiver")
+                    .long("token-b-receiver")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Account to receive token B. [default: owner's associated token account]"),
+            )
+            .arg(slippage_arg.clone())
+        )
+        .subcommand(SubCommand::with_name("deposit-single")
+            .about("Deposit only one of token A or token B in exchange for pool tokens")
+            .arg(swap_arg.clone())
+            .arg(
+                Arg::with_name("source_mint")
+                    .index(2)
+                    .validator(is_valid_pubkey)
+                    .value_name("SOURCE_MINT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Mint of the tokens to deposit"),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(3)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Amount of tokens to deposit"),
+            )
+            .arg(
+                Arg::with_name("source")
+                    .long("source")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Source token account. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("pool_token_receiver")
+                    .long("pool-token-receiver")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Account to receive pool tokens. [default: owner's associated token account]"),
+            )
+            .arg(slippage_arg.clone())
+        )
+        .subcommand(SubCommand::with_name("withdraw-single")
+            .about("Burn pool tokens in exchange for an exact amount of token A or token B")
+            .arg(swap_arg)
+            .arg(
+                Arg::with_name("destination_mint")
+                    .index(2)
+                    .validator(is_valid_pubkey)
+                    .value_name("DESTINATION_MINT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Mint of the tokens to withdraw"),
+            )
+            .arg(
+                Arg::with_name("amount")
+                    .index(3)
+                    .validator(is_amount)
+                    .value_name("AMOUNT")
+                    .takes_value(true)
+                    .required(true)
+                    .help("Amount of tokens to withdraw"),
+            )
+            .arg(
+                Arg::with_name("pool_token_source")
+                    .long("pool-token-source")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Pool token account to burn from. [default: owner's associated token account]"),
+            )
+            .arg(
+                Arg::with_name("destination")
+                    .long("destination")
+                    .validator(is_valid_pubkey)
+                    .value_name("ADDRESS")
+                    .takes_value(true)
+                    .help("Destination token account. [default: owner's associated token account]"),
+            )
+            .arg(slippage_arg)
+        )
+        .get_matches();
+
+    let mut wallet_manager = None;
+    let cli_config = if let Some(config_file) = matches.value_of("config_file") {
+        solana_cli_config::Config::load(config_file).unwrap_or_default()
+    } else {
+        solana_cli_config::Config::default()
+    };
+    let config = {
+        let json_rpc_url = value_t!(matches, "json_rpc_url", String)
+            .unwrap_or_else(|_| cli_config.json_rpc_url.clone());
+
+        let owner = get_signer(
+            &matches,
+            "owner",
+            &cli_config.keypair_path,
+            &mut wallet_manager,
+            SignerFromPathConfig {
+                allow_null_signer: false,
+            },
+        );
+        let fee_payer = get_signer(
+            &matches,
+            "fee_payer",
+            &cli_config.keypair_path,
+            &mut wallet_manager,
+            SignerFromPathConfig {
+                allow_null_signer: false,
+            },
+        );
+        let verbose = matches.is_present("verbose");
+        let output_format = matches
+            .value_of("output_format")
+            .map(|value| match value {
+                "json" => OutputFormat::Json,
+                "json-compact" => OutputFormat::JsonCompact,
+                _ => unreachable!(),
+            })
+            .unwrap_or(if verbose {
+                OutputFormat::DisplayVerbose
+            } else {
+                OutputFormat::Display
+            });
+        let dry_run = matches.is_present("dry_run");
+
+        Config {
+            rpc_client: RpcClient::new_with_commitment(json_rpc_url, CommitmentConfig::confirmed()),
+            verbose,
+            output_format,
+            owner,
+            fee_payer,
+            dry_run,
+        }
+    };
+
+    let _ = match matches.subcommand() {
+        ("create-pool", Some(arg_matches)) => {
+            let token_a_mint = pubkey_of(arg_matches, "token_a_mint").unwrap();
+            let token_b_mint = pubkey_of(arg_matches, "token_b_mint").unwrap();
+            let token_a_amount = value_t_or_exit!(arg_matches, "token_a_amount", f64);
+            let token_b_amount = value_t_or_exit!(arg_matches, "token_b_amount", f64);
+            let token_a_source = pubkey_of(arg_matches, "token_a_source");
+            let token_b_source = pubkey_of(arg_matches, "token_b_source");
+            let swap_curve = match arg_matches.value_of("curve_type").unwrap() {
+                "constant-product" => SwapCurve {
+                    curve_type: CurveType::ConstantProduct,
+                    calculator: Arc::new(ConstantProductCurve {}),
+                },
+                "constant-price" => SwapCurve {
+                    curve_type: CurveType::ConstantPrice,
+                    calculator: Arc::new(ConstantPriceCurve {
+                        token_b_price: value_t_or_exit!(arg_matches, "token_b_price", u64),
+                    }),
+                },
+                "stable" => SwapCurve {
+                    curve_type: CurveType::Stable,
+                    calculator: Arc::new(StableCurve {
+                        amp: value_t_or_exit!(arg_matches, "amp", u64),
+                    }),
+                },
+                "offset" => SwapCurve {
+                    curve_type: CurveType::Offset,
+                    calculator: Arc::new(OffsetCurve {
+                        token_b_offset: value_t_or_exit!(arg_matches, "token_b_offset", u64),
+                    }),
+                },
+                _ => unreachable!(),
+            };
+            let (trade_fee_numerator, trade_fee_denominator) = fee_of(arg_matches, "trade_fee");
+            let (owner_trade_fee_numerator, owner_trade_fee_denominator) =
+                fee_of(arg_matches, "owner_trade_fee");
+            let (owner_withdraw_fee_numerator, owner_withdraw_fee_denominator) =
+                fee_of(arg_matches, "owner_withdraw_fee");
+            let (host_fee_numerator, host_fee_denominator) = fee_of(arg_matches, "host_fee");
+            let fees = Fees {
+                trade_fee_numerator,
+                trade_fee_denominator,
+                owner_trade_fee_numerator,
+                owner_trade_fee_denominator,
+                owner_withdraw_fee_numerator,
+                owner_withdraw_fee_denominator,
+                host_fee_numerator,
+                host_fee_denominator,
+            };
+            let fee_owner = pubkey_of(arg_matches, "fee_owner");
+            let admin = pubkey_of(arg_matches, "admin");
+            let swap_keypair = keypair_of(arg_matches, "swap_keypair");
+            let pool_mint_keypair = keypair_of(arg_matches, "pool_mint_keypair");
+            command_create_pool(
+                &config,
+                &token_a_mint,
+                &token_b_mint,
+                token_a_amount,
+                token_b_amount,
+                &token_a_source,
+                &token_b_source,
+                fees,
+                swap_curve,
+                &fee_owner,
+                &admin,
+                swap_keypair,
+                pool_mint_keypair,
+            )
+        }
+        ("info", Some(arg_matches)) => {
+            let token_swap_address = pubkey_of(arg_matches, "swap").unwrap();
+            command_info(&config, &token_swap_address)
+        }
+        ("quote", Some(arg_matches)) => {
+            let token_swap_address = pubkey_of(arg_matches, "swap").unwrap();
+            let source_mint = pubkey_of(arg_matches, "source_mint").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let slippage = slippage_of(arg_matches);
+            command_quote(&config, &token_swap_address, &source_mint, amount, slippage)
+        }
+        ("swap", Some(arg_matches)) => {
+            let token_swap_address = pubkey_of(arg_matches, "swap").unwrap();
+            let source_mint = pubkey_of(arg_matches, "source_mint").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let source = pubkey_of(arg_matches, "source");
+            let destination = pubkey_of(arg_matches, "destination");
+            let host_fee_account = pubkey_of(arg_matches, "host_fee_account");
+            let minimum_amount_out = value_t!(arg_matches, "minimum_amount_out", f64).ok();
+            let slippage = slippage_of(arg_matches);
+            command_swap(
+                &config,
+                &token_swap_address,
+                &source_mint,
+                amount,
+                &source,
+                &destination,
+                &host_fee_account,
+                minimum_amount_out,
+                slippage,
+            )
+        }
+        ("deposit", Some(arg_matches)) => {
+            let token_swap_address = pubkey_of(arg_matches, "swap").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let token_a_source = pubkey_of(arg_matches, "token_a_source");
+            let token_b_source = pubkey_of(arg_matches, "token_b_source");
+            let pool_token_receiver = pubkey_of(arg_matches, "pool_token_receiver");
+            let slippage = slippage_of(arg_matches);
+            command_deposit(
+                &config,
+                &token_swap_address,
+                amount,
+                &token_a_source,
+                &token_b_source,
+                &pool_token_receiver,
+                slippage,
+            )
+        }
+        ("withdraw", Some(arg_matches)) => {
+            let token_swap_address = pubkey_of(arg_matches, "swap").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let pool_token_source = pubkey_of(arg_matches, "pool_token_source");
+            let token_a_receiver = pubkey_of(arg_matches, "token_a_receiver");
+            let token_b_receiver = pubkey_of(arg_matches, "token_b_receiver");
+            let slippage = slippage_of(arg_matches);
+            command_withdraw(
+                &config,
+                &token_swap_address,
+                amount,
+                &pool_token_source,
+                &token_a_receiver,
+                &token_b_receiver,
+                slippage,
+            )
+        }
+        ("deposit-single", Some(arg_matches)) => {
+            let token_swap_address = pubkey_of(arg_matches, "swap").unwrap();
+            let source_mint = pubkey_of(arg_matches, "source_mint").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let source = pubkey_of(arg_matches, "source");
+            let pool_token_receiver = pubkey_of(arg_matches, "pool_token_receiver");
+            let slippage = slippage_of(arg_matches);
+            command_deposit_single(
+                &config,
+                &token_swap_address,
+                &source_mint,
+                amount,
+                &source,
+                &pool_token_receiver,
+                slippage,
+            )
+        }
+        ("withdraw-single", Some(arg_matches)) => {
+            let token_swap_address = pubkey_of(arg_matches, "swap").unwrap();
+            let destination_mint = pubkey_of(arg_matches, "destination_mint").unwrap();
+            let amount = value_t_or_exit!(arg_matches, "amount", f64);
+            let pool_token_source = pubkey_of(arg_matches, "pool_token_source");
+            let destination = pubkey_of(arg_matches, "destination");
+            let slippage = slippage_of(arg_matches);
+            command_withdraw_single(
+                &config,
+                &token_swap_address,
+                &destination_mint,
+                amount,
+                &pool_token_source,
+                &destination,
+                slippage,
+            )
+        }
+        _ => unreachable!(),
+    }
+    .map_err(|err| {
+        eprintln!("{}", err);
+        exit(1);
+    });
+}
@@ -0,0 +1,378 @@
+use {
+    serde::{Deserialize, Serialize},
+    solana_cli_output::{QuietDisplay, VerboseDisplay},
+    solana_program::program_pack::Pack,
+    spl_token_swap::{
+        curve::{
+            base::{CurveType, SwapCurve},
+            constant_price::ConstantPriceCurve,
+            fees::{DynamicFee, Fees},
+            offset::OffsetCurve,
+            stable::StableCurve,
+        },
+        state::SwapState,
+    },
+    std::fmt::{Display, Formatter, Result, Write},
+};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTokenSwap {
+    pub address: String,
+    pub authority: String,
+    pub token_program_id: String,
+    pub token_a_account: String,
+    pub token_a_mint: String,
+    pub token_a_amount: u64,
+    pub token_a_ui_amount: f64,
+    pub token_b_account: String,
+    pub token_b_mint: String,
+    pub token_b_amount: u64,
+    pub token_b_ui_amount: f64,
+    pub pool_mint: String,
+    pub pool_token_supply: u64,
+    pub pool_token_ui_supply: f64,
+    pub pool_fee_account: String,
+    pub admin: Option<String>,
+    pub paused: bool,
+    pub curve: CliTokenSwapCurve,
+    pub fees: CliTokenSwapFees,
+    pub dynamic_fee: Option<CliTokenSwapDynamicFee>,
+}
+
+impl QuietDisplay for CliTokenSwap {}
+impl VerboseDisplay for CliTokenSwap {
+    fn write_str(&self, w: &mut dyn Write) -> Result {
+        writeln!(w, "Token Swap Info")?;
+        writeln!(w, "===============")?;
+        writeln!(w, "Token Swap: {}", &self.address)?;
+        writeln!(w, "Authority: {}", &self.authority)?;
+        writeln!(w, "Token Program: {}", &self.token_program_id)?;
+        writeln!(
+            w,
+            "Admin: {}",
+            &self.admin.as_ref().unwrap_or(&"None".to_string())
+        )?;
+        writeln!(w, "Paused: {}", &self.paused)?;
+        writeln!(w)?;
+        writeln!(w, "Token A Mint: {}", &self.token_a_mint)?;
+        writeln!(w, "Token A Account: {}", &self.token_a_account)?;
+        writeln!(
+            w,
+            "Token A Balance: {} ({})",
+            &self.token_a_ui_amount, &self.token_a_amount
+        )?;
+        writeln!(w, "Token B Mint: {}", &self.token_b_mint)?;
+        writeln!(w, "Token B Account: {}", &self.token_b_account)?;
+        writeln!(
+            w,
+            "Token B Balance: {} ({})",
+            &self.token_b_ui_amount, &self.token_b_amount
+        )?;
+        writeln!(w, "Pool Token Mint: {}", &self.pool_mint)?;
+        writeln!(
+            w,
+            "Pool Token Supply: {} ({})",
+            &self.pool_token_ui_supply, &self.pool_token_supply
+        )?;
+        writeln!(w, "Fee Account: {}", &self.pool_fee_account)?;
+        writeln!(w)?;
+        writeln!(w, "Curve: {}", &self.curve)?;
+        VerboseDisplay::write_str(&self.fees, w)?;
+        if let Some(dynamic_fee) = &self.dynamic_fee {
+            writeln!(w, "{}", dynamic_fee)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for CliTokenSwap {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "Token Swap: {}", &self.address)?;
+        writeln!(
+            f,
+            "Token A: {}\tBalance: {}",
+            &self.token_a_mint, &self.token_a_ui_amount
+        )?;
+        writeln!(
+            f,
+            "Token B: {}\tBalance: {}",
+            &self.token_b_mint, &self.token_b_ui_amount
+        )?;
+        writeln!(
+            f,
+            "Pool Token Mint: {}\tSupply: {}",
+            &self.pool_mint, &self.pool_token_ui_supply
+        )?;
+        writeln!(f, "Curve: {}", &self.curve)?;
+        write!(f, "{}", &self.fees)?;
+        if self.paused {
+            writeln!(f, "Swap is paused")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTokenSwapCurve {
+    pub curve_type: String,
+    pub token_b_price: Option<u64>,
+    pub amp: Option<u64>,
+    pub token_b_offset: Option<u64>,
+}
+
+impl Display for CliTokenSwapCurve {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}", &self.curve_type)?;
+        if let Some(token_b_price) = self.token_b_price {
+            write!(f, ", token B price {}", token_b_price)?;
+        }
+        if let Some(amp) = self.amp {
+            write!(f, ", amplification {}", amp)?;
+        }
+        if let Some(token_b_offset) = self.token_b_offset {
+            write!(f, ", token B offset {}", token_b_offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&SwapCurve> for CliTokenSwapCurve {
+    fn from(swap_curve: &SwapCurve) -> Self {
+        let mut packed = [0u8; SwapCurve::LEN];
+        Pack::pack_into_slice(swap_curve, &mut packed);
+        let calculator = &packed[1..];
+        let mut cli_curve = Self {
+            curve_type: curve_type_name(swap_curve.curve_type).to_string(),
+            token_b_price: None,
+            amp: None,
+            token_b_offset: None,
+        };
+        match swap_curve.curve_type {
+            CurveType::ConstantProduct => {}
+            CurveType::ConstantPrice => {
+                cli_curve.token_b_price = ConstantPriceCurve::unpack_from_slice(calculator)
+                    .ok()
+                    .map(|x| x.token_b_price);
+            }
+            CurveType::Stable => {
+                cli_curve.amp = StableCurve::unpack_from_slice(calculator)
+                    .ok()
+                    .map(|x| x.amp);
+            }
+            CurveType::Offset => {
+                cli_curve.token_b_offset = OffsetCurve::unpack_from_slice(calculator)
+                    .ok()
+                    .map(|x| x.token_b_offset);
+            }
+        }
+        cli_curve
+    }
+}
+
+pub(crate) fn curve_type_name(curve_type: CurveType) -> &'static str {
+    match curve_type {
+        CurveType::ConstantProduct => "constant-product",
+        CurveType::ConstantPrice => "constant-price",
+        CurveType::Stable => "stable",
+        CurveType::Offset => "offset",
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTokenSwapFee {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl Display for CliTokenSwapFee {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{}/{}", &self.numerator, &self.denominator)
+    }
+}
+
+impl From<(u64, u64)> for CliTokenSwapFee {
+    fn from(fee: (u64, u64)) -> Self {
+        let (numerator, denominator) = fee;
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTokenSwapFees {
+    pub trade_fee: CliTokenSwapFee,
+    pub owner_trade_fee: CliTokenSwapFee,
+    pub owner_withdraw_fee: CliTokenSwapFee,
+    pub host_fee: CliTokenSwapFee,
+}
+
+impl QuietDisplay for CliTokenSwapFees {}
+impl VerboseDisplay for CliTokenSwapFees {
+    fn write_str(&self, w: &mut dyn Write) -> Result {
+        write!(w, "{}", self)?;
+        writeln!(
+            w,
+            "Owner Withdraw Fee: {} of pool tokens withdrawn",
+            &self.owner_withdraw_fee
+        )?;
+        writeln!(w, "Host Fee: {} of owner trade fee", &self.host_fee)?;
+        Ok(())
+    }
+}
+
+impl Display for CliTokenSwapFees {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "Trade Fee: {} of swap amount", &self.trade_fee)?;
+        writeln!(
+            f,
+            "Owner Trade Fee: {} of swap amount",
+            &self.owner_trade_fee
+        )?;
+        Ok(())
+    }
+}
+
+impl From<&Fees> for CliTokenSwapFees {
+    fn from(fees: &Fees) -> Self {
+        Self {
+            trade_fee: (fees.trade_fee_numerator, fees.trade_fee_denominator).into(),
+            owner_trade_fee: (
+                fees.owner_trade_fee_numerator,
+                fees.owner_trade_fee_denominator,
+            )
+                .into(),
+            owner_withdraw_fee: (
+                fees.owner_withdraw_fee_numerator,
+                fees.owner_withdraw_fee_denominator,
+            )
+                .into(),
+            host_fee: (fees.host_fee_numerator, fees.host_fee_denominator).into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTokenSwapDynamicFee {
+    pub min_trade_fee_numerator: u64,
+    pub max_trade_fee_numerator: u64,
+    pub max_volatility: u64,
+    pub volatility_half_life: u64,
+    pub volatility: u64,
+    pub last_update_timestamp: i64,
+}
+
+impl Display for CliTokenSwapDynamicFee {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "Dynamic Trade Fee Numerator: {} to {}, maximum at {} bps volatility, half-life {}s, current volatility {} bps",
+            &self.min_trade_fee_numerator,
+            &self.max_trade_fee_numerator,
+            &self.max_volatility,
+            &self.volatility_half_life,
+            &self.volatility,
+        )
+    }
+}
+
+impl From<&DynamicFee> for CliTokenSwapDynamicFee {
+    fn from(dynamic_fee: &DynamicFee) -> Self {
+        Self {
+            min_trade_fee_numerator: dynamic_fee.min_trade_fee_numerator,
+            max_trade_fee_numerator: dynamic_fee.max_trade_fee_numerator,
+            max_volatility: dynamic_fee.max_volatility,
+            volatility_half_life: dynamic_fee.volatility_half_life,
+            volatility: dynamic_fee.volatility,
+            last_update_timestamp: dynamic_fee.last_update_timestamp,
+        }
+    }
+}
+
+impl CliTokenSwap {
+    pub fn new(
+        address: String,
+        authority: String,
+        token_swap: &dyn SwapState,
+        balances: CliTokenSwapBalances,
+    ) -> Self {
+        Self {
+            address,
+            authority,
+            token_program_id: token_swap.token_program_id().to_string(),
+            token_a_account: token_swap.token_a_account().to_string(),
+            token_a_mint: token_swap.token_a_mint().to_string(),
+            token_a_amount: balances.token_a_amount,
+            token_a_ui_amount: balances.token_a_ui_amount,
+            token_b_account: token_swap.token_b_account().to_string(),
+            token_b_mint: token_swap.token_b_mint().to_string(),
+            token_b_amount: balances.token_b_amount,
+            token_b_ui_amount: balances.token_b_ui_amount,
+            pool_mint: token_swap.pool_mint().to_string(),
+            pool_token_supply: balances.pool_token_supply,
+            pool_token_ui_supply: balances.pool_token_ui_supply,
+            pool_fee_account: token_swap.pool_fee_account().to_string(),
+            admin: token_swap.admin().map(|x| x.to_string()),
+            paused: token_swap.is_paused(),
+            curve: CliTokenSwapCurve::from(token_swap.swap_curve()),
+            fees: CliTokenSwapFees::from(token_swap.fees()),
+            dynamic_fee: token_swap
+                .dynamic_fee()
+                .filter(|x| x.is_enabled())
+                .map(CliTokenSwapDynamicFee::from),
+        }
+    }
+}
+
+pub(crate) struct CliTokenSwapBalances {
+    pub token_a_amount: u64,
+    pub token_a_ui_amount: f64,
+    pub token_b_amount: u64,
+    pub token_b_ui_amount: f64,
+    pub pool_token_supply: u64,
+    pub pool_token_ui_supply: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CliTokenSwapQuote {
+    pub source_mint: String,
+    pub destination_mint: String,
+    pub amount_in: u64,
+    pub ui_amount_in: f64,
+    pub amount_out: u64,
+    pub ui_amount_out: f64,
+    pub trade_fee: u64,
+    pub owner_fee: u64,
+    pub minimum_amount_out: u64,
+    pub minimum_ui_amount_out: f64,
+    pub price_impact_percent: f64,
+}
+
+impl QuietDisplay for CliTokenSwapQuote {}
+impl VerboseDisplay for CliTokenSwapQuote {
+    fn write_str(&self, w: &mut dyn Write) -> Result {
+        write!(w, "{}", self)?;
+        writeln!(w, "Source Mint: {}", &self.source_mint)?;
+        writeln!(w, "Destination Mint: {}", &self.destination_mint)?;
+        Ok(())
+    }
+}
+
+impl Display for CliTokenSwapQuote {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "Amount In: {}", &self.ui_amount_in)?;
+        writeln!(f, "Amount Out: {}", &self.ui_amount_out)?;
+        writeln!(f, "Minimum Amount Out: {}", &self.minimum_ui_amount_out)?;
+        writeln!(f, "Trade Fee: {} of source tokens", &self.trade_fee)?;
+        writeln!(f, "Owner Fee: {} of source tokens", &self.owner_fee)?;
+        writeln!(f, "Price Impact: {:.4}%", &self.price_impact_percent)?;
+        Ok(())
+    }
+}
@@ -54,7 +54,7 @@ export class Numberu64 extends BN {
   }
 }
 
-export const TokenSwapLayout = BufferLayout.struct([
+const tokenSwapV1Fields = [
   BufferLayout.u8('version'),
   BufferLayout.u8('isInitialized'),
   BufferLayout.u8('bumpSeed'),
@@ -75,6 +75,27 @@ export const TokenSwapLayout = BufferLayout.struct([
   Layout.uint64('hostFeeDenominator'),
   BufferLayout.u8('curveType'),
   BufferLayout.blob(32, 'curveParameters'),
+];
+
+/**
+ * Layout of swaps created before admins existed, or in a swap account sized
+ * for it
+ */
+export const TokenSwapLayoutV1 = BufferLayout.struct(tokenSwapV1Fields);
+
+/**
+ * Layout of swaps created by the current program
+ */
+export const TokenSwapLayout = BufferLayout.struct([
+  ...tokenSwapV1Fields,
+  Layout.publicKey('admin'),
+  BufferLayout.u8('isPaused'),
+  Layout.uint64('minTradeFeeNumerator'),
+  Layout.uint64('maxTradeFeeNumerator'),
+  Layout.uint64('maxVolatility'),
+  Layout.uint64('volatilityHalfLife'),
+  Layout.uint64('volatility'),
+  Layout.uint64('lastUpdateTimestamp'),
 ]);
 
 export const CurveType = Object.freeze({
@@ -110,6 +131,8 @@ export class TokenSwap {
    * @param hostFeeDenominator The host fee denominator
    * @param curveType The curve type
    * @param payer Pays for the transaction
+   * @param admin The admin allowed to update the swap, if any
+   * @param isPaused Whether the swap is paused
    */
   constructor(
     private connection: Connection,
@@ -133,6 +156,8 @@ export class TokenSwap {
     public hostFeeDenominator: Numberu64,
     public curveType: number,
     public payer: Account,
+    public admin: PublicKey | null = null,
+    public isPaused: boolean = false,
   ) {
     this.connection = connection;
     this.tokenSwap = tokenSwap;
@@ -155,6 +180,8 @@ export class TokenSwap {
     this.hostFeeDenominator = hostFeeDenominator;
     this.curveType = curveType;
     this.payer = payer;
+    this.admin = admin;
+    this.isPaused = isPaused;
   }
 
   /**
@@ -190,6 +217,7 @@ export class TokenSwap {
     hostFeeDenominator: number,
     curveType: number,
     curveParameters: Numberu64 = new Numberu64(0),
+    admin: PublicKey | null = null,
   ): TransactionInstruction {
     const keys = [
       {pubkey: tokenSwapAccount.publicKey, isSigner: false, isWritable: true},
@@ -201,6 +229,9 @@ export class TokenSwap {
       {pubkey: tokenAccountPool, isSigner: false, isWritable: true},
       {pubkey: tokenProgramId, isSigner: false, isWritable: false},
     ];
+    if (admin !== null) {
+      keys.push({pubkey: admin, isSigner: false, isWritable: false});
+    }
     const commandDataLayout = BufferLayout.struct([
       BufferLayout.u8('instruction'),
       BufferLayout.nu64('tradeFeeNumerator'),
@@ -255,7 +286,10 @@ export class TokenSwap {
     payer: Account,
   ): Promise<TokenSwap> {
     const data = await loadAccount(connection, address, programId);
-    const tokenSwapData = TokenSwapLayout.decode(data);
+    const tokenSwapData =
+      data[0] === 1
+        ? TokenSwapLayoutV1.decode(data)
+        : TokenSwapLayout.decode(data);
     if (!tokenSwapData.isInitialized) {
       throw new Error(`Invalid token swap state`);
     }
@@ -298,6 +332,13 @@ export class TokenSwap {
       tokenSwapData.hostFeeDenominator,
     );
     const curveType = tokenSwapData.curveType;
+    // swaps without an admin store the default public key
+    const admin =
+      tokenSwapData.admin === undefined ||
+      tokenSwapData.admin.every(byte => byte === 0)
+        ? null
+        : new PublicKey(tokenSwapData.admin);
+    const isPaused = !!tokenSwapData.isPaused;
 
     return new TokenSwap(
       connection,
@@ -321,6 +362,8 @@ export class TokenSwap {
       hostFeeDenominator,
       curveType,
       payer,
+      admin,
+      isPaused,
     );
   }
 
@@ -339,6 +382,7 @@ export class TokenSwap {
    * @param swapProgramId The program ID of the token-swap program
    * @param feeNumerator Numerator of the fee ratio
    * @param feeDenominator Denominator of the fee ratio
+   * @param admin The admin allowed to update the swap, if any
    * @return Token object for the newly minted token, Public key of the account holding the total supply of new tokens
    */
   static async createTokenSwap(
@@ -365,6 +409,7 @@ export class TokenSwap {
     hostFeeDenominator: number,
     curveType: number,
     curveParameters?: Numberu64,
+    admin: PublicKey | null = null,
   ): Promise<TokenSwap> {
     let transaction;
     const tokenSwap = new TokenSwap(
@@ -389,6 +434,7 @@ export class TokenSwap {
       new Numberu64(hostFeeDenominator),
       curveType,
       payer,
+      admin,
     );
 
     // Allocate memory for the account
@@ -426,6 +472,7 @@ export class TokenSwap {
       hostFeeDenominator,
       curveType,
       curveParameters,
+      admin,
     );
 
     transaction.add(instruction);
@@ -521,7 +568,8 @@ export class TokenSwap {
     );
 
     const keys = [
-      {pubkey: tokenSwap, isSigner: false, isWritable: false},
+      // swaps record their price move for the pool's dynamic fee
+      {pubkey: tokenSwap, isSigner: false, isWritable: true},
       {pubkey: authority, isSigner: false, isWritable: false},
       {pubkey: userTransferAuthority, isSigner: true, isWritable: false},
       {pubkey: userSource, isSigner: false, isWritable: true},
@@ -3,7 +3,7 @@
 use crate::{
     curve::{
         base::{CurveType, SwapCurve},
-        fees::Fees,
+        fees::{DynamicFee, Fees},
     },
     error::SwapError,
 };
@@ -57,6 +57,18 @@ impl<'a> SwapConstraints<'a> {
             Err(SwapError::InvalidFee.into())
         }
     }
+
+    /// Checks that the provided dynamic fee never goes below the minimum
+    /// trade fee
+    pub fn validate_dynamic_fee(&self, dynamic_fee: &DynamicFee) -> Result<(), ProgramError> {
+        if !dynamic_fee.is_enabled()
+            || dynamic_fee.min_trade_fee_numerator >= self.fees.trade_fee_numerator
+        {
+            Ok(())
+        } else {
+            Err(SwapError::InvalidFee.into())
+        }
+    }
 }
 
 #[cfg(feature = "production")]
@@ -192,5 +204,19 @@ mod tests {
             Err(SwapError::UnsupportedCurveType.into()),
             constraints.validate_curve(&swap_curve),
         );
+
+        let mut dynamic_fee = DynamicFee {
+            min_trade_fee_numerator: trade_fee_numerator,
+            max_trade_fee_numerator: trade_fee_numerator + 1,
+            ..DynamicFee::default()
+        };
+        assert_eq!(constraints.validate_dynamic_fee(&dynamic_fee), Ok(()));
+        dynamic_fee.min_trade_fee_numerator = trade_fee_numerator - 1;
+        assert_eq!(
+            Err(SwapError::InvalidFee.into()),
+            constraints.validate_dynamic_fee(&dynamic_fee),
+        );
+        dynamic_fee.max_trade_fee_numerator = 0;
+        assert_eq!(constraints.validate_dynamic_fee(&dynamic_fee), Ok(()));
     }
 }
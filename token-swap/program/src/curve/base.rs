@@ -118,11 +118,7 @@ impl SwapCurve {
         if source_amount == 0 {
             return Some(0);
         }
-        // Get the trading fee incurred if *half* the source amount is swapped
-        // for the other side. Reference at:
-        // https://github.com/balancer-labs/balancer-core/blob/f4ed5d65362a8d6cec21662fb6eae233b0babc1f/contracts/BMath.sol#L117
-        let half_source_amount = std::cmp::max(1, source_amount.checked_div(2)?);
-        let trade_fee = fees.trading_fee(half_source_amount)?;
+        let trade_fee = fees.single_token_trading_fee(source_amount)?;
         let source_amount = source_amount.checked_sub(trade_fee)?;
         self.calculator.deposit_single_token_type(
             source_amount,
@@ -146,11 +142,7 @@ impl SwapCurve {
         if source_amount == 0 {
             return Some(0);
         }
-        // Get the trading fee incurred if *half* the source amount is swapped
-        // for the other side. Reference at:
-        // https://github.com/balancer-labs/balancer-core/blob/f4ed5d65362a8d6cec21662fb6eae233b0babc1f/contracts/BMath.sol#L117
-        let half_source_amount = std::cmp::max(1, source_amount.checked_div(2)?);
-        let trade_fee = fees.trading_fee(half_source_amount)?;
+        let trade_fee = fees.single_token_trading_fee(source_amount)?;
         let source_amount = source_amount.checked_sub(trade_fee)?;
         self.calculator.withdraw_single_token_type_exact_out(
             source_amount,
@@ -8,6 +8,9 @@ use solana_program::{
 };
 use std::convert::TryFrom;
 
+/// Basis points in a whole, used for volatility estimates
+pub const BASIS_POINTS: u128 = 10_000;
+
 /// Encapsulates all fee information and calculations for swap operations
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Fees {
@@ -99,6 +102,15 @@ impl Fees {
         )
     }
 
+    /// Calculate the trading fee in trading tokens of a single-sided deposit
+    /// or withdrawal, charged as if *half* the amount is swapped for the
+    /// other side. Reference at:
+    /// https://github.com/balancer-labs/balancer-core/blob/f4ed5d65362a8d6cec21662fb6eae233b0babc1f/contracts/BMath.sol#L117
+    pub fn single_token_trading_fee(&self, trading_tokens: u128) -> Option<u128> {
+        let half_trading_tokens = std::cmp::max(1, trading_tokens.checked_div(2)?);
+        self.trading_fee(half_trading_tokens)
+    }
+
     /// Calculate the host fee based on the owner fee, only used in production
     /// situations where a program is hosted by multiple frontends
     pub fn host_fee(&self, owner_fee: u128) -> Option<u128> {
@@ -125,6 +137,133 @@ impl Fees {
     }
 }
 
+/// Volatility-based trade fee.  When enabled, the trade fee numerator scales
+/// linearly from `min_trade_fee_numerator` to `max_trade_fee_numerator` as the
+/// volatility estimate grows to `max_volatility`, using the
+/// `trade_fee_denominator` from [Fees](struct.Fees.html).
+///
+/// The volatility estimate is the sum of recent price moves in basis points,
+/// halved every `volatility_half_life` seconds.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DynamicFee {
+    /// Trade fee numerator charged when the pool is calm
+    pub min_trade_fee_numerator: u64,
+    /// Trade fee numerator charged when volatility reaches `max_volatility`,
+    /// 0 if dynamic fees are disabled
+    pub max_trade_fee_numerator: u64,
+    /// Volatility, in basis points, at which the maximum fee is charged
+    pub max_volatility: u64,
+    /// Number of seconds for the volatility estimate to decay by half
+    pub volatility_half_life: u64,
+    /// Current volatility estimate, in basis points
+    pub volatility: u64,
+    /// Unix timestamp of the last volatility update
+    pub last_update_timestamp: i64,
+}
+
+impl DynamicFee {
+    /// Are dynamic fees used by the swap
+    pub fn is_enabled(&self) -> bool {
+        self.max_trade_fee_numerator > 0
+    }
+
+    /// Volatility estimate at the given time, after decay.  Whole half-lives
+    /// halve the estimate, and the remainder is interpolated linearly.
+    pub fn current_volatility(&self, unix_timestamp: i64) -> u64 {
+        if self.volatility_half_life == 0 {
+            return self.volatility;
+        }
+        let elapsed =
+            u64::try_from(unix_timestamp.saturating_sub(self.last_update_timestamp)).unwrap_or(0);
+        let half_lives = elapsed / self.volatility_half_life;
+        if half_lives >= u64::BITS as u64 {
+            return 0;
+        }
+        let volatility = self.volatility >> half_lives;
+        let remainder = elapsed % self.volatility_half_life;
+        let decay = u128::from(volatility) * u128::from(remainder)
+            / (2 * u128::from(self.volatility_half_life));
+        volatility.saturating_sub(u64::try_from(decay).unwrap_or(u64::MAX))
+    }
+
+    /// Trade fee numerator for the given volatility estimate
+    pub fn trade_fee_numerator(&self, volatility: u64) -> Option<u64> {
+        if self.max_volatility == 0 {
+            return Some(self.max_trade_fee_numerator);
+        }
+        let volatility = std::cmp::min(volatility, self.max_volatility);
+        let range = self
+            .max_trade_fee_numerator
+            .checked_sub(self.min_trade_fee_numerator)?;
+        let increase = u128::from(range)
+            .checked_mul(u128::from(volatility))?
+            .checked_div(u128::from(self.max_volatility))?;
+        self.min_trade_fee_numerator
+            .checked_add(u64::try_from(increase).ok()?)
+    }
+
+    /// Fees to use at the given time, with the trade fee set to the current
+    /// dynamic rate
+    pub fn current_fees(&self, fees: &Fees, unix_timestamp: i64) -> Option<Fees> {
+        if !self.is_enabled() {
+            return Some(fees.clone());
+        }
+        let trade_fee_numerator =
+            self.trade_fee_numerator(self.current_volatility(unix_timestamp))?;
+        Some(Fees {
+            trade_fee_numerator,
+            ..fees.clone()
+        })
+    }
+
+    /// Adds a price move, in basis points, to the decayed volatility estimate
+    pub fn record_price_move(&mut self, price_move: u64, unix_timestamp: i64) {
+        self.volatility = self
+            .current_volatility(unix_timestamp)
+            .saturating_add(price_move);
+        self.last_update_timestamp = unix_timestamp;
+    }
+
+    /// Validate that the dynamic fee is reasonable for the given fees
+    pub fn validate(&self, fees: &Fees) -> Result<(), SwapError> {
+        if !self.is_enabled() {
+            return Ok(());
+        }
+        if self.min_trade_fee_numerator > self.max_trade_fee_numerator
+            || self.max_volatility == 0
+            || self.volatility_half_life == 0
+        {
+            return Err(SwapError::InvalidFee);
+        }
+        validate_fraction(self.max_trade_fee_numerator, fees.trade_fee_denominator)
+    }
+}
+
+/// Relative change of the price of token A in token B between two sets of
+/// pool balances, in basis points
+pub fn price_move(
+    swap_token_a_amount: u128,
+    swap_token_b_amount: u128,
+    new_swap_token_a_amount: u128,
+    new_swap_token_b_amount: u128,
+) -> Option<u64> {
+    let before = swap_token_a_amount.checked_mul(new_swap_token_b_amount)?;
+    let after = new_swap_token_a_amount.checked_mul(swap_token_b_amount)?;
+    if before == 0 {
+        return Some(0);
+    }
+    let difference = if after > before {
+        after - before
+    } else {
+        before - after
+    };
+    let price_move = match difference.checked_mul(BASIS_POINTS) {
+        Some(scaled) => scaled / before,
+        None => difference / std::cmp::max(1, before / BASIS_POINTS),
+    };
+    Some(u64::try_from(price_move).unwrap_or(u64::MAX))
+}
+
 /// IsInitialized is required to use `Pack::pack` and `Pack::unpack`
 impl IsInitialized for Fees {
     fn is_initialized(&self) -> bool {
@@ -183,6 +322,55 @@ impl Pack for Fees {
     }
 }
 
+impl IsInitialized for DynamicFee {
+    fn is_initialized(&self) -> bool {
+        true
+    }
+}
+
+impl Sealed for DynamicFee {}
+impl Pack for DynamicFee {
+    const LEN: usize = 48;
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 48];
+        let (
+            min_trade_fee_numerator,
+            max_trade_fee_numerator,
+            max_volatility,
+            volatility_half_life,
+            volatility,
+            last_update_timestamp,
+        ) = mut_array_refs![output, 8, 8, 8, 8, 8, 8];
+        *min_trade_fee_numerator = self.min_trade_fee_numerator.to_le_bytes();
+        *max_trade_fee_numerator = self.max_trade_fee_numerator.to_le_bytes();
+        *max_volatility = self.max_volatility.to_le_bytes();
+        *volatility_half_life = self.volatility_half_life.to_le_bytes();
+        *volatility = self.volatility.to_le_bytes();
+        *last_update_timestamp = self.last_update_timestamp.to_le_bytes();
+    }
+
+    fn unpack_from_slice(input: &[u8]) -> Result<DynamicFee, ProgramError> {
+        let input = array_ref![input, 0, 48];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            min_trade_fee_numerator,
+            max_trade_fee_numerator,
+            max_volatility,
+            volatility_half_life,
+            volatility,
+            last_update_timestamp,
+        ) = array_refs![input, 8, 8, 8, 8, 8, 8];
+        Ok(Self {
+            min_trade_fee_numerator: u64::from_le_bytes(*min_trade_fee_numerator),
+            max_trade_fee_numerator: u64::from_le_bytes(*max_trade_fee_numerator),
+            max_volatility: u64::from_le_bytes(*max_volatility),
+            volatility_half_life: u64::from_le_bytes(*volatility_half_life),
+            volatility: u64::from_le_bytes(*volatility),
+            last_update_timestamp: i64::from_le_bytes(*last_update_timestamp),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,4 +413,86 @@ mod tests {
         let unpacked = Fees::unpack_from_slice(&packed).unwrap();
         assert_eq!(fees, unpacked);
     }
+
+    #[test]
+    fn pack_dynamic_fee() {
+        let dynamic_fee = DynamicFee {
+            min_trade_fee_numerator: 1,
+            max_trade_fee_numerator: 30,
+            max_volatility: 500,
+            volatility_half_life: 600,
+            volatility: 123,
+            last_update_timestamp: -4,
+        };
+        let mut packed = [0u8; DynamicFee::LEN];
+        Pack::pack_into_slice(&dynamic_fee, &mut packed[..]);
+        let unpacked = DynamicFee::unpack_from_slice(&packed).unwrap();
+        assert_eq!(dynamic_fee, unpacked);
+    }
+
+    #[test]
+    fn dynamic_fee_scales_with_volatility() {
+        let fees = Fees {
+            trade_fee_numerator: 25,
+            trade_fee_denominator: 10_000,
+            ..Fees::default()
+        };
+        let mut dynamic_fee = DynamicFee {
+            min_trade_fee_numerator: 10,
+            max_trade_fee_numerator: 110,
+            max_volatility: 1_000,
+            volatility_half_life: 100,
+            volatility: 0,
+            last_update_timestamp: 0,
+        };
+        dynamic_fee.validate(&fees).unwrap();
+
+        let current = dynamic_fee.current_fees(&fees, 0).unwrap();
+        assert_eq!(current.trade_fee_numerator, 10);
+        assert_eq!(current.trade_fee_denominator, fees.trade_fee_denominator);
+
+        dynamic_fee.record_price_move(500, 0);
+        let current = dynamic_fee.current_fees(&fees, 0).unwrap();
+        assert_eq!(current.trade_fee_numerator, 60);
+
+        // capped at the maximum
+        dynamic_fee.record_price_move(5_000, 0);
+        assert_eq!(dynamic_fee.volatility, 5_500);
+        let current = dynamic_fee.current_fees(&fees, 0).unwrap();
+        assert_eq!(current.trade_fee_numerator, 110);
+
+        // decays by half every half-life, linearly in between
+        assert_eq!(dynamic_fee.current_volatility(100), 2_750);
+        assert_eq!(dynamic_fee.current_volatility(150), 2_750 - 687);
+        assert_eq!(dynamic_fee.current_volatility(200), 1_375);
+        assert_eq!(dynamic_fee.current_volatility(100 * 64), 0);
+        let current = dynamic_fee.current_fees(&fees, 500).unwrap();
+        assert_eq!(current.trade_fee_numerator, 10 + 100 * 171 / 1_000);
+
+        // disabled dynamic fees leave the fees untouched
+        let disabled = DynamicFee::default();
+        assert_eq!(disabled.current_fees(&fees, 1_000).unwrap(), fees);
+        disabled.validate(&fees).unwrap();
+
+        let invalid = DynamicFee {
+            max_trade_fee_numerator: 10_000,
+            ..dynamic_fee.clone()
+        };
+        assert_eq!(invalid.validate(&fees), Err(SwapError::InvalidFee));
+        let invalid = DynamicFee {
+            min_trade_fee_numerator: 111,
+            ..dynamic_fee
+        };
+        assert_eq!(invalid.validate(&fees), Err(SwapError::InvalidFee));
+    }
+
+    #[test]
+    fn price_moves() {
+        assert_eq!(price_move(100, 100, 100, 100), Some(0));
+        assert_eq!(price_move(100, 100, 110, 100), Some(1_000));
+        assert_eq!(price_move(100, 100, 100, 110), Some(909));
+        assert_eq!(price_move(0, 100, 100, 100), Some(0));
+        let large = u64::MAX as u128;
+        assert_eq!(price_move(large, large, large, large / 2), Some(10_000));
+    }
 }
@@ -0,0 +1,585 @@
+//! The curve.fi invariant calculator for pools of two or more tokens.
+use {
+    crate::{
+        curve::{
+            base::SwapResult,
+            calculator::{RoundDirection, SwapWithoutFeesResult},
+            fees::Fees,
+        },
+        error::SwapError,
+    },
+    spl_math::{checked_ceil_div::CheckedCeilDiv, precise_number::PreciseNumber, uint::U256},
+    std::convert::TryFrom,
+};
+
+/// Minimum number of tokens in a multi-token stable pool
+pub const MIN_TOKENS: usize = 2;
+/// Maximum number of tokens in a multi-token stable pool
+pub const MAX_TOKENS: usize = 8;
+
+const ITERATIONS: u8 = 32;
+
+/// StableSwap invariant over any number of tokens, with all token balances
+/// given in pool order.
+///
+/// As with `StableCurve`, `amp` is actually `A * n**(n-1)` from the
+/// whitepaper, following the convention of the original contracts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MultiStableCurve {
+    /// Amplifier constant
+    pub amp: u64,
+}
+
+impl MultiStableCurve {
+    /// Calculates `A * n**n` for deriving D, see `stable::compute_a`
+    fn compute_leverage(&self, n_coins: usize) -> Option<U256> {
+        U256::from(self.amp).checked_mul(n_coins.into())
+    }
+
+    /// Compute stable swap invariant (D)
+    /// Equation:
+    /// A * sum(x_i) * n**n + D = A * D * n**n + D**(n+1) / (n**n * prod(x_i))
+    pub fn compute_d(&self, amounts: &[u128]) -> Option<u128> {
+        let n_coins = U256::from(amounts.len());
+        let leverage = self.compute_leverage(amounts.len())?;
+        let mut sum_x = U256::zero(); // sum(x_i), a.k.a S
+        for amount in amounts {
+            sum_x = sum_x.checked_add((*amount).into())?;
+        }
+        if sum_x.is_zero() {
+            return Some(0);
+        }
+        let amounts_times_coins = amounts
+            .iter()
+            .map(|amount| {
+                U256::from(*amount)
+                    .checked_mul(n_coins)?
+                    .checked_add(U256::one())
+            })
+            .collect::<Option<Vec<U256>>>()?;
+
+        let mut d = sum_x;
+        // Newton's method to approximate D
+        for _ in 0..ITERATIONS {
+            let mut d_product = d;
+            for amount_times_coins in amounts_times_coins.iter() {
+                d_product = d_product.checked_mul(d)?.checked_div(*amount_times_coins)?;
+            }
+            let d_previous = d;
+            // d = (leverage * sum_x + d_p * n_coins) * d / ((leverage - 1) * d + (n_coins + 1) * d_p);
+            let numerator = leverage
+                .checked_mul(sum_x)?
+                .checked_add(d_product.checked_mul(n_coins)?)?
+                .checked_mul(d)?;
+            let denominator = leverage
+                .checked_sub(U256::one())?
+                .checked_mul(d)?
+                .checked_add(n_coins.checked_add(U256::one())?.checked_mul(d_product)?)?;
+            d = numerator.checked_div(denominator)?;
+            // Equality with the precision of 1
+            if d == d_previous {
+                break;
+            }
+        }
+        u128::try_from(d).ok()
+    }
+
+    /// Compute the new amount of the destination token, given a new amount
+    /// of the source token, such that D stays the same.
+    /// Solve for y:
+    /// y**2 + y * (sum' - (A*n**n - 1) * D / (A * n**n)) = D ** (n + 1) / (n ** (2 * n) * prod' * A)
+    /// y**2 + b*y = c
+    fn compute_new_destination_amount(
+        &self,
+        amounts: &[u128],
+        source_index: usize,
+        destination_index: usize,
+        new_source_amount: u128,
+    ) -> Option<u128> {
+        let n_coins = U256::from(amounts.len());
+        let leverage = self.compute_leverage(amounts.len())?;
+        let d_val = U256::from(self.compute_d(amounts)?);
+
+        // sum' and prod' are over all tokens except the destination
+        let mut c = d_val;
+        let mut sum_x = U256::zero();
+        for (index, amount) in amounts.iter().enumerate() {
+            if index == destination_index {
+                continue;
+            }
+            let amount = U256::from(if index == source_index {
+                new_source_amount
+            } else {
+                *amount
+            });
+            sum_x = sum_x.checked_add(amount)?;
+            c = c
+                .checked_mul(d_val)?
+                .checked_div(amount.checked_mul(n_coins)?)?;
+        }
+        // c =  D ** (n + 1) / (n ** (2 * n) * prod' * A)
+        c = c
+            .checked_mul(d_val)?
+            .checked_div(leverage.checked_mul(n_coins)?)?;
+        // b = sum' - (A*n**n - 1) * D / (A * n**n)
+        let b = sum_x.checked_add(d_val.checked_div(leverage)?)?;
+
+        // Solve for y by approximating: y**2 + b*y = c
+        let mut y = d_val;
+        for _ in 0..ITERATIONS {
+            let (y_new, _) = (y.checked_mul(y)?.checked_add(c)?).checked_ceil_div(
+                y.checked_mul(U256::from(2))?
+                    .checked_add(b)?
+                    .checked_sub(d_val)?,
+            )?;
+            if y_new == y {
+                break;
+            } else {
+                y = y_new;
+            }
+        }
+        u128::try_from(y).ok()
+    }
+
+    /// Calculate how much of the destination token will be provided given an
+    /// amount of source token, without any fees
+    pub fn swap_without_fees(
+        &self,
+        source_amount: u128,
+        amounts: &[u128],
+        source_index: usize,
+        destination_index: usize,
+    ) -> Option<SwapWithoutFeesResult> {
+        if source_index == destination_index
+            || source_index >= amounts.len()
+            || destination_index >= amounts.len()
+        {
+            return None;
+        }
+        if source_amount == 0 {
+            return Some(SwapWithoutFeesResult {
+                source_amount_swapped: 0,
+                destination_amount_swapped: 0,
+            });
+        }
+        let new_source_amount = amounts[source_index].checked_add(source_amount)?;
+        let new_destination_amount = self.compute_new_destination_amount(
+            amounts,
+            source_index,
+            destination_index,
+            new_source_amount,
+        )?;
+        let amount_swapped = amounts[destination_index].checked_sub(new_destination_amount)?;
+
+        Some(SwapWithoutFeesResult {
+            source_amount_swapped: source_amount,
+            destination_amount_swapped: amount_swapped,
+        })
+    }
+
+    /// Subtract fees and calculate how much destination token will be provided
+    /// given an amount of source token, see `SwapCurve::swap`
+    pub fn swap(
+        &self,
+        source_amount: u128,
+        amounts: &[u128],
+        source_index: usize,
+        destination_index: usize,
+        fees: &Fees,
+    ) -> Option<SwapResult> {
+        // debit the fee to calculate the amount swapped
+        let trade_fee = fees.trading_fee(source_amount)?;
+        let owner_fee = fees.owner_trading_fee(source_amount)?;
+
+        let total_fees = trade_fee.checked_add(owner_fee)?;
+        let source_amount_less_fees = source_amount.checked_sub(total_fees)?;
+
+        let SwapWithoutFeesResult {
+            source_amount_swapped,
+            destination_amount_swapped,
+        } = self.swap_without_fees(
+            source_amount_less_fees,
+            amounts,
+            source_index,
+            destination_index,
+        )?;
+
+        let source_amount_swapped = source_amount_swapped.checked_add(total_fees)?;
+        Some(SwapResult {
+            new_swap_source_amount: amounts[source_index].checked_add(source_amount_swapped)?,
+            new_swap_destination_amount: amounts[destination_index]
+                .checked_sub(destination_amount_swapped)?,
+            source_amount_swapped,
+            destination_amount_swapped,
+            trade_fee,
+            owner_fee,
+        })
+    }
+
+    /// Get the trading fee incurred by depositing or withdrawing
+    /// `source_amount` of only one of the `n_coins` tokens, charged on the
+    /// share of it that would be swapped for the other tokens to keep the
+    /// pool balanced: `fee * n / (4 * (n - 1))`.  For two tokens, this is the
+    /// fee on half of the source amount.
+    fn single_token_type_trading_fee(
+        source_amount: u128,
+        n_coins: usize,
+        fees: &Fees,
+    ) -> Option<u128> {
+        let n_coins = u128::try_from(n_coins).ok()?;
+        let swapped_amount = source_amount
+            .checked_mul(n_coins)?
+            .checked_div(n_coins.checked_sub(1)?.checked_mul(4)?)?;
+        fees.trading_fee(std::cmp::max(1, swapped_amount))
+    }
+
+    /// Get the amounts of all of the pool's tokens, in pool order, that are
+    /// worth the given amount of pool tokens, see
+    /// `CurveCalculator::pool_tokens_to_trading_tokens`
+    pub fn pool_tokens_to_trading_tokens(
+        &self,
+        pool_tokens: u128,
+        pool_token_supply: u128,
+        amounts: &[u128],
+        round_direction: RoundDirection,
+    ) -> Option<Vec<u128>> {
+        amounts
+            .iter()
+            .map(|amount| {
+                let value = pool_tokens.checked_mul(*amount)?;
+                let token_amount = value.checked_div(pool_token_supply)?;
+                match round_direction {
+                    RoundDirection::Floor => Some(token_amount),
+                    RoundDirection::Ceiling => {
+                        // Only round up a non-zero amount, so that tiny
+                        // amounts of pool tokens are rejected later in
+                        // processing, see `constant_product`
+                        if value.checked_rem(pool_token_supply)? > 0 && token_amount > 0 {
+                            token_amount.checked_add(1)
+                        } else {
+                            Some(token_amount)
+                        }
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Get the amount of pool tokens for the deposited amount of the token
+    /// at `index`, see `SwapCurve::deposit_single_token_type`
+    pub fn deposit_single_token_type(
+        &self,
+        source_amount: u128,
+        amounts: &[u128],
+        pool_supply: u128,
+        index: usize,
+        fees: &Fees,
+    ) -> Option<u128> {
+        if source_amount == 0 {
+            return Some(0);
+        }
+        let trade_fee = Self::single_token_type_trading_fee(source_amount, amounts.len(), fees)?;
+        let source_amount = source_amount.checked_sub(trade_fee)?;
+
+        let d0 = PreciseNumber::new(self.compute_d(amounts)?)?;
+        let mut new_amounts = amounts.to_vec();
+        let new_amount = new_amounts.get_mut(index)?;
+        *new_amount = new_amount.checked_add(source_amount)?;
+        let d1 = PreciseNumber::new(self.compute_d(&new_amounts)?)?;
+
+        let diff = d1.checked_sub(&d0)?;
+        let final_amount =
+            (diff.checked_mul(&PreciseNumber::new(pool_supply)?))?.checked_div(&d0)?;
+        final_amount.floor()?.to_imprecise()
+    }
+
+    /// Get the amount of pool tokens for the withdrawn amount of the token
+    /// at `index`, see `SwapCurve::withdraw_single_token_type_exact_out`
+    pub fn withdraw_single_token_type_exact_out(
+        &self,
+        source_amount: u128,
+        amounts: &[u128],
+        pool_supply: u128,
+        index: usize,
+        fees: &Fees,
+    ) -> Option<u128> {
+        if source_amount == 0 {
+            return Some(0);
+        }
+        let trade_fee = Self::single_token_type_trading_fee(source_amount, amounts.len(), fees)?;
+        let source_amount = source_amount.checked_sub(trade_fee)?;
+
+        let d0 = PreciseNumber::new(self.compute_d(amounts)?)?;
+        let mut new_amounts = amounts.to_vec();
+        let new_amount = new_amounts.get_mut(index)?;
+        *new_amount = new_amount.checked_sub(source_amount)?;
+        let d1 = PreciseNumber::new(self.compute_d(&new_amounts)?)?;
+
+        let diff = d0.checked_sub(&d1)?;
+        let final_amount =
+            (diff.checked_mul(&PreciseNumber::new(pool_supply)?))?.checked_div(&d0)?;
+        final_amount.ceiling()?.to_imprecise()
+    }
+
+    /// Validate that the given number of tokens and amounts can be used to
+    /// create a pool
+    pub fn validate_supply(&self, amounts: &[u128]) -> Result<(), SwapError> {
+        if amounts.len() < MIN_TOKENS || amounts.len() > MAX_TOKENS {
+            return Err(SwapError::IncorrectTokenCount);
+        }
+        if amounts.contains(&0) {
+            return Err(SwapError::EmptySupply);
+        }
+        Ok(())
+    }
+
+    /// Validate the curve parameters
+    pub fn validate(&self) -> Result<(), SwapError> {
+        if self.amp == 0 {
+            Err(SwapError::InvalidCurve)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{
+        calculator::{CurveCalculator, TradeDirection},
+        stable::StableCurve,
+    };
+    use proptest::prelude::*;
+    use sim::StableSwapModel;
+
+    #[test]
+    fn swap_zero() {
+        let curve = MultiStableCurve { amp: 100 };
+        let result = curve
+            .swap_without_fees(0, &[100, 1_000_000_000_000_000, 1_000], 0, 1)
+            .unwrap();
+        assert_eq!(result.source_amount_swapped, 0);
+        assert_eq!(result.destination_amount_swapped, 0);
+    }
+
+    #[test]
+    fn swap_invalid_indices() {
+        let curve = MultiStableCurve { amp: 100 };
+        let amounts = [1_000_000, 1_000_000, 1_000_000];
+        assert_eq!(curve.swap_without_fees(10, &amounts, 1, 1), None);
+        assert_eq!(curve.swap_without_fees(10, &amounts, 0, 3), None);
+        assert_eq!(curve.swap_without_fees(10, &amounts, 3, 0), None);
+    }
+
+    #[test]
+    fn validate_supply() {
+        let curve = MultiStableCurve { amp: 100 };
+        assert_eq!(
+            curve.validate_supply(&[1]),
+            Err(SwapError::IncorrectTokenCount)
+        );
+        assert_eq!(
+            curve.validate_supply(&[1; MAX_TOKENS + 1]),
+            Err(SwapError::IncorrectTokenCount)
+        );
+        assert_eq!(
+            curve.validate_supply(&[1, 0, 1]),
+            Err(SwapError::EmptySupply)
+        );
+        assert_eq!(curve.validate_supply(&[1; MIN_TOKENS]), Ok(()));
+        assert_eq!(curve.validate_supply(&[1; MAX_TOKENS]), Ok(()));
+    }
+
+    #[test]
+    fn single_token_type_trading_fee() {
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 100,
+            ..Fees::default()
+        };
+        // half of the amount is swapped in a pool of two tokens
+        assert_eq!(
+            MultiStableCurve::single_token_type_trading_fee(1_000_000, 2, &fees),
+            Some(5_000)
+        );
+        // 3 / 8 of the amount in a pool of three tokens
+        assert_eq!(
+            MultiStableCurve::single_token_type_trading_fee(1_000_000, 3, &fees),
+            Some(3_750)
+        );
+        // 8 / 28 of the amount in a pool of eight tokens
+        assert_eq!(
+            MultiStableCurve::single_token_type_trading_fee(2_800_000, MAX_TOKENS, &fees),
+            Some(8_000)
+        );
+    }
+
+    #[test]
+    fn pool_tokens_to_trading_tokens() {
+        let curve = MultiStableCurve { amp: 100 };
+        let amounts = [1_000, 2_000, 3_001];
+        assert_eq!(
+            curve.pool_tokens_to_trading_tokens(10, 100, &amounts, RoundDirection::Floor),
+            Some(vec![100, 200, 300])
+        );
+        assert_eq!(
+            curve.pool_tokens_to_trading_tokens(10, 100, &amounts, RoundDirection::Ceiling),
+            Some(vec![100, 200, 301])
+        );
+        // tiny amounts are not rounded up to a token
+        assert_eq!(
+            curve.pool_tokens_to_trading_tokens(1, 10_000, &amounts, RoundDirection::Ceiling),
+            Some(vec![0, 0, 0])
+        );
+        assert_eq!(
+            curve.pool_tokens_to_trading_tokens(10, 0, &amounts, RoundDirection::Floor),
+            None
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn compute_d(
+            amounts in prop::collection::vec(100..1_000_000_000_000_000_000u128, MIN_TOKENS..=MAX_TOKENS),
+            amp in 1..150u64
+        ) {
+            let curve = MultiStableCurve { amp };
+            let model: StableSwapModel = StableSwapModel::new(
+                curve.amp.into(),
+                amounts.clone(),
+                amounts.len() as u8,
+            );
+
+            let d = curve.compute_d(&amounts).unwrap();
+            let sim_d = model.sim_d();
+
+            let diff = (sim_d as i128 - d as i128).abs();
+            let tolerance = std::cmp::max(1, sim_d as i128 / 1_000_000_000);
+            assert!(
+                diff <= tolerance,
+                "d={}, sim_d={}, amp={}, amounts={:?}, diff={}",
+                d,
+                sim_d,
+                amp,
+                amounts,
+                diff
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn swap_no_fee(
+            amounts in prop::collection::vec(100..1_000_000_000_000_000_000u128, MIN_TOKENS..=MAX_TOKENS),
+            source_amount in 100..100_000_000_000u128,
+            source_index in 0..MAX_TOKENS,
+            destination_index in 0..MAX_TOKENS,
+            amp in 1..150u64
+        ) {
+            let source_index = source_index % amounts.len();
+            let destination_index = destination_index % amounts.len();
+            prop_assume!(source_index != destination_index);
+            prop_assume!(source_amount < amounts[source_index]);
+
+            let curve = MultiStableCurve { amp };
+            let model: StableSwapModel = StableSwapModel::new(
+                curve.amp.into(),
+                amounts.clone(),
+                amounts.len() as u8,
+            );
+
+            let result = curve
+                .swap_without_fees(source_amount, &amounts, source_index, destination_index)
+                .unwrap();
+            let sim_result =
+                model.sim_exchange(source_index as u128, destination_index as u128, source_amount);
+
+            let diff =
+                (sim_result as i128 - result.destination_amount_swapped as i128).abs();
+
+            // tolerate a difference of 2 because of the ceiling during calculation
+            let tolerance = std::cmp::max(2, sim_result as i128 / 1_000_000_000);
+
+            assert!(
+                diff <= tolerance,
+                "result={}, sim_result={}, amp={}, source_amount={}, amounts={:?}, diff={}",
+                result.destination_amount_swapped,
+                sim_result,
+                amp,
+                source_amount,
+                amounts,
+                diff
+            );
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn two_tokens_match_stable_curve(
+            swap_source_amount in 100..1_000_000_000_000_000_000u128,
+            swap_destination_amount in 100..1_000_000_000_000_000_000u128,
+            source_amount in 100..100_000_000_000u128,
+            amp in 1..150u64
+        ) {
+            prop_assume!(source_amount < swap_source_amount);
+            let multi_curve = MultiStableCurve { amp };
+            let curve = StableCurve { amp };
+
+            let multi_result = multi_curve
+                .swap_without_fees(
+                    source_amount,
+                    &[swap_source_amount, swap_destination_amount],
+                    0,
+                    1,
+                )
+                .unwrap();
+            let result = curve
+                .swap_without_fees(
+                    source_amount,
+                    swap_source_amount,
+                    swap_destination_amount,
+                    TradeDirection::AtoB,
+                )
+                .unwrap();
+
+            let diff = (result.destination_amount_swapped as i128
+                - multi_result.destination_amount_swapped as i128)
+                .abs();
+            let tolerance =
+                std::cmp::max(2, result.destination_amount_swapped as i128 / 1_000_000_000);
+            assert!(diff <= tolerance);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn deposit_then_withdraw_does_not_profit(
+            amounts in prop::collection::vec(1_000_000..1_000_000_000_000_000u128, MIN_TOKENS..=MAX_TOKENS),
+            source_amount in 1..1_000_000_000u128,
+            index in 0..MAX_TOKENS,
+            pool_supply in 1_000_000..1_000_000_000_000u128,
+            amp in 1..150u64
+        ) {
+            let index = index % amounts.len();
+            let curve = MultiStableCurve { amp };
+            let fees = Fees::default();
+
+            let pool_tokens_minted = curve
+                .deposit_single_token_type(source_amount, &amounts, pool_supply, index, &fees)
+                .unwrap();
+            let mut new_amounts = amounts;
+            new_amounts[index] += source_amount;
+            let pool_tokens_burned = curve
+                .withdraw_single_token_type_exact_out(
+                    source_amount,
+                    &new_amounts,
+                    pool_supply + pool_tokens_minted,
+                    index,
+                    &fees,
+                )
+                .unwrap();
+            assert!(pool_tokens_burned >= pool_tokens_minted);
+        }
+    }
+}
@@ -102,6 +102,24 @@ pub enum SwapError {
     /// The operation cannot be performed on the given curve
     #[error("The operation cannot be performed on the given curve")]
     UnsupportedCurveOperation,
+    /// The swap admin is missing, incorrect, or did not sign
+    #[error("The swap admin is missing, incorrect, or did not sign")]
+    InvalidAdmin,
+    /// The swap is paused
+    #[error("The swap is paused")]
+    SwapPaused,
+
+    // 30.
+    /// The number of tokens in a multi-token pool is out of range, or does
+    /// not match the accounts provided
+    #[error("Incorrect number of tokens for a multi-token pool")]
+    IncorrectTokenCount,
+    /// The flash swap receiver program cannot be the swap or token program
+    #[error("Invalid flash swap receiver program")]
+    InvalidFlashSwapReceiver,
+    /// The pool was not repaid enough after a flash swap
+    #[error("Not enough tokens repaid after flash swap")]
+    InsufficientFlashSwapRepayment,
 }
 impl From<SwapError> for ProgramError {
     fn from(e: SwapError) -> Self {
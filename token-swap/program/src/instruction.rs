@@ -2,7 +2,11 @@
 
 #![allow(clippy::too_many_arguments)]
 
-use crate::curve::{base::SwapCurve, fees::Fees};
+use crate::curve::{
+    base::SwapCurve,
+    fees::Fees,
+    multi_stable::{MAX_TOKENS, MIN_TOKENS},
+};
 use crate::error::SwapError;
 use solana_program::{
     instruction::{AccountMeta, Instruction},
@@ -90,6 +94,157 @@ pub struct WithdrawSingleTokenTypeExactAmountOut {
     pub maximum_pool_token_amount: u64,
 }
 
+/// RouteSwap instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteSwap {
+    /// SOURCE amount to transfer into the first pool of the route
+    pub amount_in: u64,
+    /// Minimum amount of the final DESTINATION token to output, prevents
+    /// excessive slippage across the whole route
+    pub minimum_amount_out: u64,
+}
+
+/// SetFees instruction data
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct SetFees {
+    /// new swap fees
+    pub fees: Fees,
+}
+
+/// SetDynamicFee instruction data
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetDynamicFee {
+    /// Trade fee numerator charged when the pool is calm
+    pub min_trade_fee_numerator: u64,
+    /// Trade fee numerator charged at `max_volatility`, 0 to disable
+    /// dynamic fees
+    pub max_trade_fee_numerator: u64,
+    /// Volatility, in basis points, at which the maximum fee is charged
+    pub max_volatility: u64,
+    /// Number of seconds for the volatility estimate to decay by half
+    pub volatility_half_life: u64,
+}
+
+/// InitializeMultiStable instruction data
+#[repr(C)]
+#[derive(Debug, PartialEq)]
+pub struct InitializeMultiStable {
+    /// all swap fees
+    pub fees: Fees,
+    /// Amplifier constant of the StableSwap invariant
+    pub amp: u64,
+}
+
+/// MultiStableExchange instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiStableExchange {
+    /// Index of the SOURCE token in the pool
+    pub source_index: u8,
+    /// Index of the DESTINATION token in the pool
+    pub destination_index: u8,
+    /// SOURCE amount to transfer, output to DESTINATION is based on the exchange rate
+    pub amount_in: u64,
+    /// Minimum amount of DESTINATION token to output, prevents excessive slippage
+    pub minimum_amount_out: u64,
+}
+
+/// MultiStableDepositSingle instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiStableDepositSingle {
+    /// Index of the deposited token in the pool
+    pub token_index: u8,
+    /// Token amount to deposit
+    pub source_token_amount: u64,
+    /// Pool token amount to receive in exchange. The amount is set by
+    /// the current exchange rate and size of the pool
+    pub minimum_pool_token_amount: u64,
+}
+
+/// MultiStableWithdrawSingle instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiStableWithdrawSingle {
+    /// Index of the withdrawn token in the pool
+    pub token_index: u8,
+    /// Amount of the token to receive
+    pub destination_token_amount: u64,
+    /// Maximum amount of pool tokens to burn, prevents excessive slippage
+    pub maximum_pool_token_amount: u64,
+}
+
+/// MultiStableDepositAll instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiStableDepositAll {
+    /// Pool token amount to transfer. The token amounts are set by the
+    /// current exchange rate and size of the pool
+    pub pool_token_amount: u64,
+    /// Maximum amount of each token to deposit, in pool order, prevents
+    /// excessive slippage
+    pub maximum_token_amounts: Vec<u64>,
+}
+
+/// MultiStableWithdrawAll instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiStableWithdrawAll {
+    /// Amount of pool tokens to burn. User receives an output of each token
+    /// based on the percentage of the pool tokens that are returned.
+    pub pool_token_amount: u64,
+    /// Minimum amount of each token to receive, in pool order, prevents
+    /// excessive slippage
+    pub minimum_token_amounts: Vec<u64>,
+}
+
+/// FlashSwap instruction data
+#[cfg_attr(feature = "fuzz", derive(Arbitrary))]
+#[repr(C)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlashSwap {
+    /// Amount of DESTINATION token sent to the user before the receiver
+    /// program is invoked
+    pub amount_out: u64,
+}
+
+/// Tag of the instruction invoked on the receiver program of a `FlashSwap`
+pub const RECEIVE_FLASH_SWAP_INSTRUCTION_TAG: u8 = 0;
+
+/// Number of accounts required for each pool in a `RouteSwap`
+pub const ROUTE_SWAP_HOP_ACCOUNTS_LEN: usize = 7;
+
+/// Accounts for one pool of a `RouteSwap`, in the order the swaps are made
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouteSwapHop {
+    /// Token-swap
+    pub swap_pubkey: Pubkey,
+    /// Swap authority
+    pub authority_pubkey: Pubkey,
+    /// Base Account to swap INTO
+    pub swap_source_pubkey: Pubkey,
+    /// Base Account to swap FROM
+    pub swap_destination_pubkey: Pubkey,
+    /// User account receiving the output of this pool
+    pub destination_pubkey: Pubkey,
+    /// Pool token mint, to generate trading fees
+    pub pool_mint_pubkey: Pubkey,
+    /// Fee account, to receive trading fees
+    pub pool_fee_pubkey: Pubkey,
+    /// Whether the pool uses dynamic fees, which requires the Token-swap to
+    /// be writable
+    pub dynamic_fee: bool,
+}
+
 /// Instructions supported by the token swap program.
 #[repr(C)]
 #[derive(Debug, PartialEq)]
@@ -106,11 +261,15 @@ pub enum SwapInstruction {
     ///   6. `[writable]` Pool Token Account to deposit the initial pool token
     ///   supply.  Must be empty, not owned by swap authority.
     ///   7. `[]` Token program id
+    ///   8. `[optional]` Admin allowed to update the swap after initialization
+    ///
+    ///   A swap account sized for the original `SwapV1` layout is initialized
+    ///   as `SwapV1`, and cannot be given an admin.
     Initialize(Initialize),
 
     ///   Swap the tokens in the pool.
     ///
-    ///   0. `[]` Token-swap
+    ///   0. `[]` Token-swap, `[writable]` if the swap uses dynamic fees
     ///   1. `[]` swap authority
     ///   2. `[]` user transfer authority
     ///   3. `[writable]` token_(A|B) SOURCE Account, amount is transferable by user transfer authority,
@@ -185,6 +344,190 @@ pub enum SwapInstruction {
     ///   8. `[writable]` Fee account, to receive withdrawal fees
     ///   9. `[]` Token program id
     WithdrawSingleTokenTypeExactAmountOut(WithdrawSingleTokenTypeExactAmountOut),
+
+    ///   Swap tokens through an ordered list of pools.  The output of each
+    ///   pool becomes the input of the next one, and only the output of the
+    ///   final pool is checked against the minimum amount.
+    ///
+    ///   Intermediate outputs are held in user token accounts, so the user
+    ///   transfer authority must be able to transfer out of every
+    ///   intermediate DESTINATION account as well as the SOURCE account.
+    ///
+    ///   0. `[]` user transfer authority
+    ///   1. `[]` Token program id
+    ///   2. `[writable]` token SOURCE Account, amount is transferable by user transfer authority,
+    ///
+    ///   Then, for each pool in the route:
+    ///
+    ///   3. `[]` Token-swap, `[writable]` if the swap uses dynamic fees
+    ///   4. `[]` swap authority
+    ///   5. `[writable]` Base Account to swap INTO.  Must be the previous DESTINATION token.
+    ///   6. `[writable]` Base Account to swap FROM.
+    ///   7. `[writable]` DESTINATION Account assigned to USER as the owner.
+    ///   8. `[writable]` Pool token mint, to generate trading fees
+    ///   9. `[writable]` Fee account, to receive trading fees
+    RouteSwap(RouteSwap),
+
+    ///   Update the fees of the swap.  When the program is built with
+    ///   the `production` feature, the new fees must match the program
+    ///   owner's constraints.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Swap admin
+    SetFees(SetFees),
+
+    ///   Update the pool token account receiving trading and withdrawal fees.
+    ///   When the program is built with the `production` feature, the new
+    ///   account must be owned by the program owner.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Swap admin
+    ///   2. `[]` New pool token account to receive fees, not owned by swap authority
+    SetPoolFeeAccount,
+
+    ///   Pause the swap, disallowing swaps, deposits, and single token type
+    ///   withdrawals.  Withdrawing all token types is still allowed.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Swap admin
+    Pause,
+
+    ///   Unpause the swap.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Swap admin
+    Unpause,
+
+    ///   Update the swap admin.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Swap admin
+    ///   2. `[]` New swap admin
+    SetAdmin,
+
+    ///   Enable, update, or disable the volatility-based trade fee.  While
+    ///   enabled, the trade fee numerator scales between the minimum and
+    ///   maximum as the volatility estimate grows, and the configured trade fee
+    ///   numerator is ignored.  When the program is built with the
+    ///   `production` feature, the minimum must respect the program owner's
+    ///   trade fee.
+    ///
+    ///   0. `[writable]` Token-swap
+    ///   1. `[signer]` Swap admin
+    SetDynamicFee(SetDynamicFee),
+
+    ///   Initializes a new stable swap pool holding between 2 and 8 tokens.
+    ///   Tokens are referred to by the order of their accounts here.
+    ///
+    ///   0. `[writable, signer]` New multi-token swap to create.
+    ///   1. `[]` swap authority derived from `create_program_address(&[multi-token swap account])`
+    ///   2. `[writable]` Pool Token Mint. Must be empty, owned by swap authority.
+    ///   3. `[]` Pool Token Account to deposit trading and withdraw fees.
+    ///   Must be empty, not owned by swap authority
+    ///   4. `[writable]` Pool Token Account to deposit the initial pool token
+    ///   supply.  Must be empty, not owned by swap authority.
+    ///   5. `[]` Token program id
+    ///   6..6+N. `[]` token Accounts, in pool order. Must be non zero, owned by swap authority.
+    InitializeMultiStable(InitializeMultiStable),
+
+    ///   Swap between two tokens of a multi-token pool, given by their index.
+    ///
+    ///   0. `[]` multi-token swap
+    ///   1. `[]` swap authority
+    ///   2. `[]` user transfer authority
+    ///   3. `[writable]` SOURCE Account, amount is transferable by user transfer authority,
+    ///   4. `[writable]` DESTINATION Account assigned to USER as the owner.
+    ///   5. `[writable]` Pool token mint, to generate trading fees
+    ///   6. `[writable]` Fee account, to receive trading fees
+    ///   7. `[]` Token program id
+    ///   8..8+N. `[writable]` token Accounts of the swap, in pool order.
+    MultiStableExchange(MultiStableExchange),
+
+    ///   Deposit one token of a multi-token pool, given by its index.  The
+    ///   output is a "pool" token representing ownership into the pool.
+    ///
+    ///   0. `[]` multi-token swap
+    ///   1. `[]` swap authority
+    ///   2. `[]` user transfer authority
+    ///   3. `[writable]` SOURCE Account, amount is transferable by user transfer authority,
+    ///   4. `[writable]` Pool MINT account, swap authority is the owner.
+    ///   5. `[writable]` Pool Account to deposit the generated tokens, user is the owner.
+    ///   6. `[]` Token program id
+    ///   7..7+N. `[writable]` token Accounts of the swap, in pool order.
+    MultiStableDepositSingle(MultiStableDepositSingle),
+
+    ///   Withdraw one token of a multi-token pool, given by its index, given
+    ///   the exact amount out expected.
+    ///
+    ///   0. `[]` multi-token swap
+    ///   1. `[]` swap authority
+    ///   2. `[]` user transfer authority
+    ///   3. `[writable]` Pool mint account, swap authority is the owner
+    ///   4. `[writable]` SOURCE Pool account, amount is transferable by user transfer authority.
+    ///   5. `[writable]` User Account to credit
+    ///   6. `[writable]` Fee account, to receive withdrawal fees
+    ///   7. `[]` Token program id
+    ///   8..8+N. `[writable]` token Accounts of the swap, in pool order.
+    MultiStableWithdrawSingle(MultiStableWithdrawSingle),
+
+    ///   Borrow tokens from the pool, and repay them within the same
+    ///   instruction.  `amount_out` of the DESTINATION token is sent to the
+    ///   user, then the receiver program is invoked.  Once it returns, the
+    ///   pool must have received enough SOURCE token to pay for the
+    ///   DESTINATION token still missing, as if it were bought with `Swap`,
+    ///   including fees.  Any DESTINATION token returned to the pool is
+    ///   credited after the trading fees.
+    ///
+    ///   0. `[writable]` Token-swap, only written if the swap uses dynamic fees
+    ///   1. `[]` swap authority
+    ///   2. `[writable]` token_(A|B) Base Account to repay INTO.  Must be the SOURCE token.
+    ///   3. `[writable]` token_(A|B) Base Account to borrow FROM.  Must be the DESTINATION token.
+    ///   4. `[writable]` token_(A|B) DESTINATION Account assigned to USER as the owner.
+    ///   5. `[writable]` Pool token mint, to generate trading fees
+    ///   6. `[writable]` Fee account, to receive trading fees
+    ///   7. `[]` Token program id
+    ///   8. `[]` Flash swap receiver program id
+    ///   .. `[any]` Additional accounts expected by the receiver program's
+    ///   `ReceiveFlashSwap` instruction.
+    ///
+    ///   The receiver program must contain an instruction with tag
+    ///   `RECEIVE_FLASH_SWAP_INSTRUCTION_TAG`, taking `amount_out` as a u64,
+    ///   with the following accounts:
+    ///
+    ///   0. `[writable]` DESTINATION Account, holding the borrowed tokens
+    ///   1. `[writable]` token_(A|B) Base Account to repay INTO.
+    ///   2. `[]` Token program id
+    ///   .. `[any]` Additional accounts provided to the `FlashSwap` instruction above.
+    FlashSwap(FlashSwap),
+
+    ///   Deposit all of the tokens of a multi-token pool, in the same
+    ///   proportions as the pool.  The output is a "pool" token representing
+    ///   ownership in the pool.
+    ///
+    ///   0. `[]` multi-token swap
+    ///   1. `[]` swap authority
+    ///   2. `[]` user transfer authority
+    ///   3. `[writable]` Pool MINT account, swap authority is the owner.
+    ///   4. `[writable]` Pool Account to deposit the generated tokens, user is the owner.
+    ///   5. `[]` Token program id
+    ///   6..6+N. `[writable]` token Accounts of the swap, in pool order.
+    ///   6+N..6+2N. `[writable]` SOURCE Accounts, in pool order, amounts are
+    ///   transferable by user transfer authority.
+    MultiStableDepositAll(MultiStableDepositAll),
+
+    ///   Withdraw all of the tokens of a multi-token pool, in the same
+    ///   proportions as the pool, by burning pool tokens.
+    ///
+    ///   0. `[]` multi-token swap
+    ///   1. `[]` swap authority
+    ///   2. `[]` user transfer authority
+    ///   3. `[writable]` Pool mint account, swap authority is the owner
+    ///   4. `[writable]` SOURCE Pool account, amount is transferable by user transfer authority.
+    ///   5. `[writable]` Fee account, to receive withdrawal fees
+    ///   6. `[]` Token program id
+    ///   7..7+N. `[writable]` token Accounts of the swap, in pool order.
+    ///   7+N..7+2N. `[writable]` User Accounts to credit, in pool order.
+    MultiStableWithdrawAll(MultiStableWithdrawAll),
 }
 
 impl SwapInstruction {
@@ -246,10 +589,110 @@ impl SwapInstruction {
                     maximum_pool_token_amount,
                 })
             }
+            6 => {
+                let (amount_in, rest) = Self::unpack_u64(rest)?;
+                let (minimum_amount_out, _rest) = Self::unpack_u64(rest)?;
+                Self::RouteSwap(RouteSwap {
+                    amount_in,
+                    minimum_amount_out,
+                })
+            }
+            7 => {
+                if rest.len() >= Fees::LEN {
+                    let (fees, _rest) = rest.split_at(Fees::LEN);
+                    let fees = Fees::unpack_unchecked(fees)?;
+                    Self::SetFees(SetFees { fees })
+                } else {
+                    return Err(SwapError::InvalidInstruction.into());
+                }
+            }
+            8 => Self::SetPoolFeeAccount,
+            9 => Self::Pause,
+            10 => Self::Unpause,
+            11 => Self::SetAdmin,
+            12 => {
+                let (min_trade_fee_numerator, rest) = Self::unpack_u64(rest)?;
+                let (max_trade_fee_numerator, rest) = Self::unpack_u64(rest)?;
+                let (max_volatility, rest) = Self::unpack_u64(rest)?;
+                let (volatility_half_life, _rest) = Self::unpack_u64(rest)?;
+                Self::SetDynamicFee(SetDynamicFee {
+                    min_trade_fee_numerator,
+                    max_trade_fee_numerator,
+                    max_volatility,
+                    volatility_half_life,
+                })
+            }
+            13 => {
+                if rest.len() >= Fees::LEN {
+                    let (fees, rest) = rest.split_at(Fees::LEN);
+                    let fees = Fees::unpack_unchecked(fees)?;
+                    let (amp, _rest) = Self::unpack_u64(rest)?;
+                    Self::InitializeMultiStable(InitializeMultiStable { fees, amp })
+                } else {
+                    return Err(SwapError::InvalidInstruction.into());
+                }
+            }
+            14 => {
+                let (source_index, rest) = Self::unpack_u8(rest)?;
+                let (destination_index, rest) = Self::unpack_u8(rest)?;
+                let (amount_in, rest) = Self::unpack_u64(rest)?;
+                let (minimum_amount_out, _rest) = Self::unpack_u64(rest)?;
+                Self::MultiStableExchange(MultiStableExchange {
+                    source_index,
+                    destination_index,
+                    amount_in,
+                    minimum_amount_out,
+                })
+            }
+            15 => {
+                let (token_index, rest) = Self::unpack_u8(rest)?;
+                let (source_token_amount, rest) = Self::unpack_u64(rest)?;
+                let (minimum_pool_token_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::MultiStableDepositSingle(MultiStableDepositSingle {
+                    token_index,
+                    source_token_amount,
+                    minimum_pool_token_amount,
+                })
+            }
+            16 => {
+                let (token_index, rest) = Self::unpack_u8(rest)?;
+                let (destination_token_amount, rest) = Self::unpack_u64(rest)?;
+                let (maximum_pool_token_amount, _rest) = Self::unpack_u64(rest)?;
+                Self::MultiStableWithdrawSingle(MultiStableWithdrawSingle {
+                    token_index,
+                    destination_token_amount,
+                    maximum_pool_token_amount,
+                })
+            }
+            17 => {
+                let (amount_out, _rest) = Self::unpack_u64(rest)?;
+                Self::FlashSwap(FlashSwap { amount_out })
+            }
+            18 => {
+                let (pool_token_amount, rest) = Self::unpack_u64(rest)?;
+                let (maximum_token_amounts, _rest) = Self::unpack_u64_vec(rest)?;
+                Self::MultiStableDepositAll(MultiStableDepositAll {
+                    pool_token_amount,
+                    maximum_token_amounts,
+                })
+            }
+            19 => {
+                let (pool_token_amount, rest) = Self::unpack_u64(rest)?;
+                let (minimum_token_amounts, _rest) = Self::unpack_u64_vec(rest)?;
+                Self::MultiStableWithdrawAll(MultiStableWithdrawAll {
+                    pool_token_amount,
+                    minimum_token_amounts,
+                })
+            }
             _ => return Err(SwapError::InvalidInstruction.into()),
         })
     }
 
+    fn unpack_u8(input: &[u8]) -> Result<(u8, &[u8]), ProgramError> {
+        let (&value, rest) = input.split_first().ok_or(SwapError::InvalidInstruction)?;
+        Ok((value, rest))
+    }
+
     fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
         if input.len() >= 8 {
             let (amount, rest) = input.split_at(8);
@@ -264,6 +707,25 @@ impl SwapInstruction {
         }
     }
 
+    /// Unpacks a list of u64 prefixed by its length as a u8
+    fn unpack_u64_vec(input: &[u8]) -> Result<(Vec<u64>, &[u8]), ProgramError> {
+        let (len, mut rest) = Self::unpack_u8(input)?;
+        let mut values = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            let (value, next) = Self::unpack_u64(rest)?;
+            values.push(value);
+            rest = next;
+        }
+        Ok((values, rest))
+    }
+
+    fn pack_u64_vec(values: &[u64], buf: &mut Vec<u8>) {
+        buf.push(values.len() as u8);
+        for value in values {
+            buf.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
     /// Packs a [SwapInstruction](enum.SwapInstruction.html) into a byte buffer.
     pub fn pack(&self) -> Vec<u8> {
         let mut buf = Vec::with_capacity(size_of::<Self>());
@@ -323,6 +785,95 @@ impl SwapInstruction {
                 buf.extend_from_slice(&destination_token_amount.to_le_bytes());
                 buf.extend_from_slice(&maximum_pool_token_amount.to_le_bytes());
             }
+            Self::RouteSwap(RouteSwap {
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                buf.push(6);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
+            }
+            Self::SetFees(SetFees { fees }) => {
+                buf.push(7);
+                let mut fees_slice = [0u8; Fees::LEN];
+                Pack::pack_into_slice(fees, &mut fees_slice[..]);
+                buf.extend_from_slice(&fees_slice);
+            }
+            Self::SetPoolFeeAccount => buf.push(8),
+            Self::Pause => buf.push(9),
+            Self::Unpause => buf.push(10),
+            Self::SetAdmin => buf.push(11),
+            Self::SetDynamicFee(SetDynamicFee {
+                min_trade_fee_numerator,
+                max_trade_fee_numerator,
+                max_volatility,
+                volatility_half_life,
+            }) => {
+                buf.push(12);
+                buf.extend_from_slice(&min_trade_fee_numerator.to_le_bytes());
+                buf.extend_from_slice(&max_trade_fee_numerator.to_le_bytes());
+                buf.extend_from_slice(&max_volatility.to_le_bytes());
+                buf.extend_from_slice(&volatility_half_life.to_le_bytes());
+            }
+            Self::InitializeMultiStable(InitializeMultiStable { fees, amp }) => {
+                buf.push(13);
+                let mut fees_slice = [0u8; Fees::LEN];
+                Pack::pack_into_slice(fees, &mut fees_slice[..]);
+                buf.extend_from_slice(&fees_slice);
+                buf.extend_from_slice(&amp.to_le_bytes());
+            }
+            Self::MultiStableExchange(MultiStableExchange {
+                source_index,
+                destination_index,
+                amount_in,
+                minimum_amount_out,
+            }) => {
+                buf.push(14);
+                buf.push(*source_index);
+                buf.push(*destination_index);
+                buf.extend_from_slice(&amount_in.to_le_bytes());
+                buf.extend_from_slice(&minimum_amount_out.to_le_bytes());
+            }
+            Self::MultiStableDepositSingle(MultiStableDepositSingle {
+                token_index,
+                source_token_amount,
+                minimum_pool_token_amount,
+            }) => {
+                buf.push(15);
+                buf.push(*token_index);
+                buf.extend_from_slice(&source_token_amount.to_le_bytes());
+                buf.extend_from_slice(&minimum_pool_token_amount.to_le_bytes());
+            }
+            Self::MultiStableWithdrawSingle(MultiStableWithdrawSingle {
+                token_index,
+                destination_token_amount,
+                maximum_pool_token_amount,
+            }) => {
+                buf.push(16);
+                buf.push(*token_index);
+                buf.extend_from_slice(&destination_token_amount.to_le_bytes());
+                buf.extend_from_slice(&maximum_pool_token_amount.to_le_bytes());
+            }
+            Self::FlashSwap(FlashSwap { amount_out }) => {
+                buf.push(17);
+                buf.extend_from_slice(&amount_out.to_le_bytes());
+            }
+            Self::MultiStableDepositAll(MultiStableDepositAll {
+                pool_token_amount,
+                maximum_token_amounts,
+            }) => {
+                buf.push(18);
+                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
+                Self::pack_u64_vec(maximum_token_amounts, &mut buf);
+            }
+            Self::MultiStableWithdrawAll(MultiStableWithdrawAll {
+                pool_token_amount,
+                minimum_token_amounts,
+            }) => {
+                buf.push(19);
+                buf.extend_from_slice(&pool_token_amount.to_le_bytes());
+                Self::pack_u64_vec(minimum_token_amounts, &mut buf);
+            }
         }
         buf
     }
@@ -363,6 +914,41 @@ pub fn initialize(
     })
 }
 
+/// Creates an 'initialize' instruction for a swap with an admin allowed to
+/// update it after initialization.
+pub fn initialize_with_admin(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    token_a_pubkey: &Pubkey,
+    token_b_pubkey: &Pubkey,
+    pool_pubkey: &Pubkey,
+    fee_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    fees: Fees,
+    swap_curve: SwapCurve,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = initialize(
+        program_id,
+        token_program_id,
+        swap_pubkey,
+        authority_pubkey,
+        token_a_pubkey,
+        token_b_pubkey,
+        pool_pubkey,
+        fee_pubkey,
+        destination_pubkey,
+        fees,
+        swap_curve,
+    )?;
+    instruction
+        .accounts
+        .push(AccountMeta::new_readonly(*admin_pubkey, false));
+    Ok(instruction)
+}
+
 /// Creates a 'deposit_all_token_types' instruction.
 pub fn deposit_all_token_types(
     program_id: &Pubkey,
@@ -552,23 +1138,511 @@ pub fn swap(
     })
 }
 
-/// Unpacks a reference from a bytes buffer.
-/// TODO actually pack / unpack instead of relying on normal memory layout.
-pub fn unpack<T>(input: &[u8]) -> Result<&T, ProgramError> {
-    if input.len() < size_of::<u8>() + size_of::<T>() {
-        return Err(ProgramError::InvalidAccountData);
-    }
-    #[allow(clippy::cast_ptr_alignment)]
-    let val: &T = unsafe { &*(&input[1] as *const u8 as *const T) };
-    Ok(val)
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::curve::{base::CurveType, stable::StableCurve};
-    use std::sync::Arc;
-
+/// Creates a 'swap' instruction for a swap using dynamic fees, which updates
+/// its volatility estimate on every trade.
+pub fn swap_with_dynamic_fee(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    swap_source_pubkey: &Pubkey,
+    swap_destination_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    pool_fee_pubkey: &Pubkey,
+    host_fee_pubkey: Option<&Pubkey>,
+    instruction: Swap,
+) -> Result<Instruction, ProgramError> {
+    let mut instruction = swap(
+        program_id,
+        token_program_id,
+        swap_pubkey,
+        authority_pubkey,
+        user_transfer_authority_pubkey,
+        source_pubkey,
+        swap_source_pubkey,
+        swap_destination_pubkey,
+        destination_pubkey,
+        pool_mint_pubkey,
+        pool_fee_pubkey,
+        host_fee_pubkey,
+        instruction,
+    )?;
+    instruction.accounts[0].is_writable = true;
+    Ok(instruction)
+}
+
+/// Creates a 'route_swap' instruction.
+pub fn route_swap(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    hops: &[RouteSwapHop],
+    instruction: RouteSwap,
+) -> Result<Instruction, ProgramError> {
+    if hops.is_empty() {
+        return Err(SwapError::InvalidInput.into());
+    }
+    let data = SwapInstruction::RouteSwap(instruction).pack();
+
+    let mut accounts = Vec::with_capacity(3 + hops.len() * ROUTE_SWAP_HOP_ACCOUNTS_LEN);
+    accounts.push(AccountMeta::new_readonly(
+        *user_transfer_authority_pubkey,
+        true,
+    ));
+    accounts.push(AccountMeta::new_readonly(*token_program_id, false));
+    accounts.push(AccountMeta::new(*source_pubkey, false));
+    for hop in hops {
+        accounts.push(AccountMeta {
+            pubkey: hop.swap_pubkey,
+            is_signer: false,
+            is_writable: hop.dynamic_fee,
+        });
+        accounts.push(AccountMeta::new_readonly(hop.authority_pubkey, false));
+        accounts.push(AccountMeta::new(hop.swap_source_pubkey, false));
+        accounts.push(AccountMeta::new(hop.swap_destination_pubkey, false));
+        accounts.push(AccountMeta::new(hop.destination_pubkey, false));
+        accounts.push(AccountMeta::new(hop.pool_mint_pubkey, false));
+        accounts.push(AccountMeta::new(hop.pool_fee_pubkey, false));
+    }
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_fees' instruction.
+pub fn set_fees(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    fees: Fees,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::SetFees(SetFees { fees }).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_pool_fee_account' instruction.
+pub fn set_pool_fee_account(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    fee_account_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::SetPoolFeeAccount.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*fee_account_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'pause' instruction.
+pub fn pause(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::Pause.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'unpause' instruction.
+pub fn unpause(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::Unpause.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_admin' instruction.
+pub fn set_admin(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    new_admin_pubkey: &Pubkey,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::SetAdmin.pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+        AccountMeta::new_readonly(*new_admin_pubkey, false),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'set_dynamic_fee' instruction.
+pub fn set_dynamic_fee(
+    program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    admin_pubkey: &Pubkey,
+    instruction: SetDynamicFee,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::SetDynamicFee(instruction).pack();
+
+    let accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*admin_pubkey, true),
+    ];
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates an 'initialize_multi_stable' instruction.
+pub fn initialize_multi_stable(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    pool_pubkey: &Pubkey,
+    fee_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    token_pubkeys: &[Pubkey],
+    fees: Fees,
+    amp: u64,
+) -> Result<Instruction, ProgramError> {
+    check_multi_stable_token_count(token_pubkeys)?;
+    let data = SwapInstruction::InitializeMultiStable(InitializeMultiStable { fees, amp }).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*swap_pubkey, true),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new(*pool_pubkey, false),
+        AccountMeta::new_readonly(*fee_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    accounts.extend(
+        token_pubkeys
+            .iter()
+            .map(|token_pubkey| AccountMeta::new_readonly(*token_pubkey, false)),
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'multi_stable_exchange' instruction.
+pub fn multi_stable_exchange(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    pool_fee_pubkey: &Pubkey,
+    token_pubkeys: &[Pubkey],
+    instruction: MultiStableExchange,
+) -> Result<Instruction, ProgramError> {
+    check_multi_stable_token_count(token_pubkeys)?;
+    let data = SwapInstruction::MultiStableExchange(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*pool_fee_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    accounts.extend(
+        token_pubkeys
+            .iter()
+            .map(|token_pubkey| AccountMeta::new(*token_pubkey, false)),
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'multi_stable_deposit_single' instruction.
+pub fn multi_stable_deposit_single(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    token_pubkeys: &[Pubkey],
+    instruction: MultiStableDepositSingle,
+) -> Result<Instruction, ProgramError> {
+    check_multi_stable_token_count(token_pubkeys)?;
+    let data = SwapInstruction::MultiStableDepositSingle(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    accounts.extend(
+        token_pubkeys
+            .iter()
+            .map(|token_pubkey| AccountMeta::new(*token_pubkey, false)),
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'multi_stable_withdraw_single' instruction.
+pub fn multi_stable_withdraw_single(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    fee_account_pubkey: &Pubkey,
+    token_pubkeys: &[Pubkey],
+    instruction: MultiStableWithdrawSingle,
+) -> Result<Instruction, ProgramError> {
+    check_multi_stable_token_count(token_pubkeys)?;
+    let data = SwapInstruction::MultiStableWithdrawSingle(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new(*fee_account_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    accounts.extend(
+        token_pubkeys
+            .iter()
+            .map(|token_pubkey| AccountMeta::new(*token_pubkey, false)),
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'multi_stable_deposit_all' instruction.
+pub fn multi_stable_deposit_all(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    token_pubkeys: &[Pubkey],
+    source_pubkeys: &[Pubkey],
+    instruction: MultiStableDepositAll,
+) -> Result<Instruction, ProgramError> {
+    check_multi_stable_token_count(token_pubkeys)?;
+    if source_pubkeys.len() != token_pubkeys.len() {
+        return Err(SwapError::IncorrectTokenCount.into());
+    }
+    let data = SwapInstruction::MultiStableDepositAll(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    accounts.extend(
+        token_pubkeys
+            .iter()
+            .chain(source_pubkeys.iter())
+            .map(|pubkey| AccountMeta::new(*pubkey, false)),
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'multi_stable_withdraw_all' instruction.
+pub fn multi_stable_withdraw_all(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    user_transfer_authority_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    source_pubkey: &Pubkey,
+    fee_account_pubkey: &Pubkey,
+    token_pubkeys: &[Pubkey],
+    destination_pubkeys: &[Pubkey],
+    instruction: MultiStableWithdrawAll,
+) -> Result<Instruction, ProgramError> {
+    check_multi_stable_token_count(token_pubkeys)?;
+    if destination_pubkeys.len() != token_pubkeys.len() {
+        return Err(SwapError::IncorrectTokenCount.into());
+    }
+    let data = SwapInstruction::MultiStableWithdrawAll(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new_readonly(*user_transfer_authority_pubkey, true),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*source_pubkey, false),
+        AccountMeta::new(*fee_account_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+    ];
+    accounts.extend(
+        token_pubkeys
+            .iter()
+            .chain(destination_pubkeys.iter())
+            .map(|pubkey| AccountMeta::new(*pubkey, false)),
+    );
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+/// Creates a 'flash_swap' instruction.
+pub fn flash_swap(
+    program_id: &Pubkey,
+    token_program_id: &Pubkey,
+    swap_pubkey: &Pubkey,
+    authority_pubkey: &Pubkey,
+    swap_source_pubkey: &Pubkey,
+    swap_destination_pubkey: &Pubkey,
+    destination_pubkey: &Pubkey,
+    pool_mint_pubkey: &Pubkey,
+    pool_fee_pubkey: &Pubkey,
+    receiver_program_id: &Pubkey,
+    receiver_accounts: Vec<AccountMeta>,
+    instruction: FlashSwap,
+) -> Result<Instruction, ProgramError> {
+    let data = SwapInstruction::FlashSwap(instruction).pack();
+
+    let mut accounts = vec![
+        AccountMeta::new(*swap_pubkey, false),
+        AccountMeta::new_readonly(*authority_pubkey, false),
+        AccountMeta::new(*swap_source_pubkey, false),
+        AccountMeta::new(*swap_destination_pubkey, false),
+        AccountMeta::new(*destination_pubkey, false),
+        AccountMeta::new(*pool_mint_pubkey, false),
+        AccountMeta::new(*pool_fee_pubkey, false),
+        AccountMeta::new_readonly(*token_program_id, false),
+        AccountMeta::new_readonly(*receiver_program_id, false),
+    ];
+    accounts.extend(receiver_accounts);
+
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts,
+        data,
+    })
+}
+
+fn check_multi_stable_token_count(token_pubkeys: &[Pubkey]) -> Result<(), ProgramError> {
+    if token_pubkeys.len() < MIN_TOKENS || token_pubkeys.len() > MAX_TOKENS {
+        Err(SwapError::IncorrectTokenCount.into())
+    } else {
+        Ok(())
+    }
+}
+
+/// Unpacks a reference from a bytes buffer.
+/// TODO actually pack / unpack instead of relying on normal memory layout.
+pub fn unpack<T>(input: &[u8]) -> Result<&T, ProgramError> {
+    if input.len() < size_of::<u8>() + size_of::<T>() {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    #[allow(clippy::cast_ptr_alignment)]
+    let val: &T = unsafe { &*(&input[1] as *const u8 as *const T) };
+    Ok(val)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curve::{base::CurveType, stable::StableCurve};
+    use std::sync::Arc;
+
     #[test]
     fn pack_intialize() {
         let trade_fee_numerator: u64 = 1;
@@ -709,4 +1783,233 @@ mod tests {
         let unpacked = SwapInstruction::unpack(&expect).unwrap();
         assert_eq!(unpacked, check);
     }
+
+    #[test]
+    fn pack_route_swap() {
+        let amount_in: u64 = 1_000;
+        let minimum_amount_out: u64 = 990;
+        let check = SwapInstruction::RouteSwap(RouteSwap {
+            amount_in,
+            minimum_amount_out,
+        });
+        let packed = check.pack();
+        let mut expect = vec![6];
+        expect.extend_from_slice(&amount_in.to_le_bytes());
+        expect.extend_from_slice(&minimum_amount_out.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_set_fees() {
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 4,
+            owner_trade_fee_numerator: 2,
+            owner_trade_fee_denominator: 5,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 3,
+            host_fee_numerator: 5,
+            host_fee_denominator: 20,
+        };
+        let check = SwapInstruction::SetFees(SetFees { fees: fees.clone() });
+        let packed = check.pack();
+        let mut expect = vec![7];
+        expect.extend_from_slice(&fees.trade_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&fees.trade_fee_denominator.to_le_bytes());
+        expect.extend_from_slice(&fees.owner_trade_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&fees.owner_trade_fee_denominator.to_le_bytes());
+        expect.extend_from_slice(&fees.owner_withdraw_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&fees.owner_withdraw_fee_denominator.to_le_bytes());
+        expect.extend_from_slice(&fees.host_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&fees.host_fee_denominator.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_admin_instructions() {
+        for (check, tag) in [
+            (SwapInstruction::SetPoolFeeAccount, 8),
+            (SwapInstruction::Pause, 9),
+            (SwapInstruction::Unpause, 10),
+            (SwapInstruction::SetAdmin, 11),
+        ] {
+            let packed = check.pack();
+            assert_eq!(packed, vec![tag]);
+            let unpacked = SwapInstruction::unpack(&packed).unwrap();
+            assert_eq!(unpacked, check);
+        }
+    }
+
+    #[test]
+    fn pack_set_dynamic_fee() {
+        let min_trade_fee_numerator: u64 = 5;
+        let max_trade_fee_numerator: u64 = 100;
+        let max_volatility: u64 = 2_000;
+        let volatility_half_life: u64 = 300;
+        let check = SwapInstruction::SetDynamicFee(SetDynamicFee {
+            min_trade_fee_numerator,
+            max_trade_fee_numerator,
+            max_volatility,
+            volatility_half_life,
+        });
+        let packed = check.pack();
+        let mut expect = vec![12];
+        expect.extend_from_slice(&min_trade_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&max_trade_fee_numerator.to_le_bytes());
+        expect.extend_from_slice(&max_volatility.to_le_bytes());
+        expect.extend_from_slice(&volatility_half_life.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_initialize_multi_stable() {
+        let fees = Fees {
+            trade_fee_numerator: 1,
+            trade_fee_denominator: 4,
+            owner_trade_fee_numerator: 2,
+            owner_trade_fee_denominator: 5,
+            owner_withdraw_fee_numerator: 1,
+            owner_withdraw_fee_denominator: 3,
+            host_fee_numerator: 5,
+            host_fee_denominator: 20,
+        };
+        let amp: u64 = 100;
+        let check = SwapInstruction::InitializeMultiStable(InitializeMultiStable {
+            fees: fees.clone(),
+            amp,
+        });
+        let packed = check.pack();
+        let mut expect = vec![13];
+        let mut fees_slice = [0u8; Fees::LEN];
+        fees.pack_into_slice(&mut fees_slice);
+        expect.extend_from_slice(&fees_slice);
+        expect.extend_from_slice(&amp.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_multi_stable_exchange() {
+        let source_index: u8 = 2;
+        let destination_index: u8 = 0;
+        let amount_in: u64 = 2;
+        let minimum_amount_out: u64 = 10;
+        let check = SwapInstruction::MultiStableExchange(MultiStableExchange {
+            source_index,
+            destination_index,
+            amount_in,
+            minimum_amount_out,
+        });
+        let packed = check.pack();
+        let mut expect = vec![14, source_index, destination_index];
+        expect.extend_from_slice(&amount_in.to_le_bytes());
+        expect.extend_from_slice(&minimum_amount_out.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_multi_stable_deposit_single() {
+        let token_index: u8 = 3;
+        let source_token_amount: u64 = 10;
+        let minimum_pool_token_amount: u64 = 5;
+        let check = SwapInstruction::MultiStableDepositSingle(MultiStableDepositSingle {
+            token_index,
+            source_token_amount,
+            minimum_pool_token_amount,
+        });
+        let packed = check.pack();
+        let mut expect = vec![15, token_index];
+        expect.extend_from_slice(&source_token_amount.to_le_bytes());
+        expect.extend_from_slice(&minimum_pool_token_amount.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_multi_stable_withdraw_single() {
+        let token_index: u8 = 1;
+        let destination_token_amount: u64 = 102;
+        let maximum_pool_token_amount: u64 = 1_000;
+        let check = SwapInstruction::MultiStableWithdrawSingle(MultiStableWithdrawSingle {
+            token_index,
+            destination_token_amount,
+            maximum_pool_token_amount,
+        });
+        let packed = check.pack();
+        let mut expect = vec![16, token_index];
+        expect.extend_from_slice(&destination_token_amount.to_le_bytes());
+        expect.extend_from_slice(&maximum_pool_token_amount.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_multi_stable_deposit_all() {
+        let pool_token_amount: u64 = 5;
+        let maximum_token_amounts: Vec<u64> = vec![10, 20, 30];
+        let check = SwapInstruction::MultiStableDepositAll(MultiStableDepositAll {
+            pool_token_amount,
+            maximum_token_amounts: maximum_token_amounts.clone(),
+        });
+        let packed = check.pack();
+        let mut expect = vec![18];
+        expect.extend_from_slice(&pool_token_amount.to_le_bytes());
+        expect.push(3);
+        for amount in maximum_token_amounts {
+            expect.extend_from_slice(&amount.to_le_bytes());
+        }
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+
+        // missing token amount
+        expect.truncate(expect.len() - 8);
+        assert_eq!(
+            SwapInstruction::unpack(&expect),
+            Err(SwapError::InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn pack_multi_stable_withdraw_all() {
+        let pool_token_amount: u64 = 1_212_438_012_089;
+        let minimum_token_amounts: Vec<u64> = vec![102_198_761_982_612, 2, 3, 4];
+        let check = SwapInstruction::MultiStableWithdrawAll(MultiStableWithdrawAll {
+            pool_token_amount,
+            minimum_token_amounts: minimum_token_amounts.clone(),
+        });
+        let packed = check.pack();
+        let mut expect = vec![19];
+        expect.extend_from_slice(&pool_token_amount.to_le_bytes());
+        expect.push(4);
+        for amount in minimum_token_amounts {
+            expect.extend_from_slice(&amount.to_le_bytes());
+        }
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
+
+    #[test]
+    fn pack_flash_swap() {
+        let amount_out: u64 = 1_000_000;
+        let check = SwapInstruction::FlashSwap(FlashSwap { amount_out });
+        let packed = check.pack();
+        let mut expect = vec![17];
+        expect.extend_from_slice(&amount_out.to_le_bytes());
+        assert_eq!(packed, expect);
+        let unpacked = SwapInstruction::unpack(&expect).unwrap();
+        assert_eq!(unpacked, check);
+    }
 }
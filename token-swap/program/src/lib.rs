@@ -6,10 +6,11 @@ pub mod constraints;
 pub mod curve;
 pub mod error;
 pub mod instruction;
+#[cfg(feature = "processor")]
 pub mod processor;
 pub mod state;
 
-#[cfg(not(feature = "no-entrypoint"))]
+#[cfg(all(feature = "processor", not(feature = "no-entrypoint")))]
 mod entrypoint;
 
 // Export current sdk types for downstream users building with a different sdk version
@@ -1,6 +1,10 @@
 //! State transition types
 
-use crate::curve::{base::SwapCurve, fees::Fees};
+use crate::curve::{
+    base::SwapCurve,
+    fees::{DynamicFee, Fees},
+    multi_stable::{MultiStableCurve, MAX_TOKENS},
+};
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 use enum_dispatch::enum_dispatch;
 use solana_program::{
@@ -38,13 +42,22 @@ pub trait SwapState {
     fn fees(&self) -> &Fees;
     /// Curve associated with swap
     fn swap_curve(&self) -> &SwapCurve;
+
+    /// Admin allowed to update the swap after initialization, if any
+    fn admin(&self) -> Option<&Pubkey>;
+    /// Is the swap paused, disallowing swaps and deposits
+    fn is_paused(&self) -> bool;
+    /// Volatility-based trade fee, if enabled
+    fn dynamic_fee(&self) -> Option<&DynamicFee>;
 }
 
 /// All versions of SwapState
 #[enum_dispatch(SwapState)]
 pub enum SwapVersion {
-    /// Latest version, used for all new swaps
+    /// Original version, without an admin
     SwapV1,
+    /// Latest version, used for all new swaps
+    SwapV2,
 }
 
 /// SwapVersion does not implement program_pack::Pack because there are size
@@ -52,7 +65,11 @@ pub enum SwapVersion {
 /// special implementations are provided here
 impl SwapVersion {
     /// Size of the latest version of the SwapState
-    pub const LATEST_LEN: usize = 1 + SwapV1::LEN; // add one for the version enum
+    pub const LATEST_LEN: usize = 1 + SwapV2::LEN; // add one for the version enum
+
+    /// Size of the original version of the SwapState, still accepted when
+    /// initializing a swap without an admin
+    pub const V1_LEN: usize = 1 + SwapV1::LEN;
 
     /// Pack a swap into a byte array, based on its version
     pub fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
@@ -61,6 +78,10 @@ impl SwapVersion {
                 dst[0] = 1;
                 SwapV1::pack(swap_info, &mut dst[1..])
             }
+            Self::SwapV2(swap_info) => {
+                dst[0] = 2;
+                SwapV2::pack(swap_info, &mut dst[1..])
+            }
         }
     }
 
@@ -72,6 +93,7 @@ impl SwapVersion {
             .ok_or(ProgramError::InvalidAccountData)?;
         match version {
             1 => Ok(Arc::new(SwapV1::unpack(rest)?)),
+            2 => Ok(Arc::new(SwapV2::unpack(rest)?)),
             _ => Err(ProgramError::UninitializedAccount),
         }
     }
@@ -171,6 +193,18 @@ impl SwapState for SwapV1 {
     fn swap_curve(&self) -> &SwapCurve {
         &self.swap_curve
     }
+
+    fn admin(&self) -> Option<&Pubkey> {
+        None
+    }
+
+    fn is_paused(&self) -> bool {
+        false
+    }
+
+    fn dynamic_fee(&self) -> Option<&DynamicFee> {
+        None
+    }
 }
 
 impl Sealed for SwapV1 {}
@@ -248,6 +282,369 @@ impl Pack for SwapV1 {
     }
 }
 
+/// Program states, with an admin able to update the swap after
+/// initialization.
+#[repr(C)]
+#[derive(Debug, Default, PartialEq)]
+pub struct SwapV2 {
+    /// Initialized state.
+    pub is_initialized: bool,
+    /// Bump seed used in program address.
+    /// The program address is created deterministically with the bump seed,
+    /// swap program id, and swap account pubkey.  This program address has
+    /// authority over the swap's token A account, token B account, and pool
+    /// token mint.
+    pub bump_seed: u8,
+
+    /// Program ID of the tokens being exchanged.
+    pub token_program_id: Pubkey,
+
+    /// Token A
+    pub token_a: Pubkey,
+    /// Token B
+    pub token_b: Pubkey,
+
+    /// Pool tokens are issued when A or B tokens are deposited.
+    /// Pool tokens can be withdrawn back to the original A or B token.
+    pub pool_mint: Pubkey,
+
+    /// Mint information for token A
+    pub token_a_mint: Pubkey,
+    /// Mint information for token B
+    pub token_b_mint: Pubkey,
+
+    /// Pool token account to receive trading and / or withdrawal fees
+    pub pool_fee_account: Pubkey,
+
+    /// All fee information
+    pub fees: Fees,
+
+    /// Swap curve parameters, to be unpacked and used by the SwapCurve, which
+    /// calculates swaps, deposits, and withdrawals
+    pub swap_curve: SwapCurve,
+
+    /// Admin allowed to update fees, rotate the pool fee account, and pause
+    /// the swap.  Set to the default pubkey if the swap has no admin.
+    pub admin: Pubkey,
+
+    /// Paused state.  A paused swap only allows withdrawing all token types.
+    pub is_paused: bool,
+
+    /// Volatility-based trade fee configuration and state
+    pub dynamic_fee: DynamicFee,
+}
+
+impl SwapState for SwapV2 {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+
+    fn bump_seed(&self) -> u8 {
+        self.bump_seed
+    }
+
+    fn token_program_id(&self) -> &Pubkey {
+        &self.token_program_id
+    }
+
+    fn token_a_account(&self) -> &Pubkey {
+        &self.token_a
+    }
+
+    fn token_b_account(&self) -> &Pubkey {
+        &self.token_b
+    }
+
+    fn pool_mint(&self) -> &Pubkey {
+        &self.pool_mint
+    }
+
+    fn token_a_mint(&self) -> &Pubkey {
+        &self.token_a_mint
+    }
+
+    fn token_b_mint(&self) -> &Pubkey {
+        &self.token_b_mint
+    }
+
+    fn pool_fee_account(&self) -> &Pubkey {
+        &self.pool_fee_account
+    }
+
+    fn fees(&self) -> &Fees {
+        &self.fees
+    }
+
+    fn swap_curve(&self) -> &SwapCurve {
+        &self.swap_curve
+    }
+
+    fn admin(&self) -> Option<&Pubkey> {
+        if self.admin == Pubkey::default() {
+            None
+        } else {
+            Some(&self.admin)
+        }
+    }
+
+    fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    fn dynamic_fee(&self) -> Option<&DynamicFee> {
+        if self.dynamic_fee.is_enabled() {
+            Some(&self.dynamic_fee)
+        } else {
+            None
+        }
+    }
+}
+
+impl Sealed for SwapV2 {}
+impl IsInitialized for SwapV2 {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for SwapV2 {
+    const LEN: usize = 404;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 404];
+        let (
+            is_initialized,
+            bump_seed,
+            token_program_id,
+            token_a,
+            token_b,
+            pool_mint,
+            token_a_mint,
+            token_b_mint,
+            pool_fee_account,
+            fees,
+            swap_curve,
+            admin,
+            is_paused,
+            dynamic_fee,
+        ) = mut_array_refs![output, 1, 1, 32, 32, 32, 32, 32, 32, 32, 64, 33, 32, 1, 48];
+        is_initialized[0] = self.is_initialized as u8;
+        bump_seed[0] = self.bump_seed;
+        token_program_id.copy_from_slice(self.token_program_id.as_ref());
+        token_a.copy_from_slice(self.token_a.as_ref());
+        token_b.copy_from_slice(self.token_b.as_ref());
+        pool_mint.copy_from_slice(self.pool_mint.as_ref());
+        token_a_mint.copy_from_slice(self.token_a_mint.as_ref());
+        token_b_mint.copy_from_slice(self.token_b_mint.as_ref());
+        pool_fee_account.copy_from_slice(self.pool_fee_account.as_ref());
+        self.fees.pack_into_slice(&mut fees[..]);
+        self.swap_curve.pack_into_slice(&mut swap_curve[..]);
+        admin.copy_from_slice(self.admin.as_ref());
+        is_paused[0] = self.is_paused as u8;
+        self.dynamic_fee.pack_into_slice(&mut dynamic_fee[..]);
+    }
+
+    /// Unpacks a byte buffer into a [SwapV2](struct.SwapV2.html).
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 404];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            is_initialized,
+            bump_seed,
+            token_program_id,
+            token_a,
+            token_b,
+            pool_mint,
+            token_a_mint,
+            token_b_mint,
+            pool_fee_account,
+            fees,
+            swap_curve,
+            admin,
+            is_paused,
+            dynamic_fee,
+        ) = array_refs![input, 1, 1, 32, 32, 32, 32, 32, 32, 32, 64, 33, 32, 1, 48];
+        Ok(Self {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            bump_seed: bump_seed[0],
+            token_program_id: Pubkey::new_from_array(*token_program_id),
+            token_a: Pubkey::new_from_array(*token_a),
+            token_b: Pubkey::new_from_array(*token_b),
+            pool_mint: Pubkey::new_from_array(*pool_mint),
+            token_a_mint: Pubkey::new_from_array(*token_a_mint),
+            token_b_mint: Pubkey::new_from_array(*token_b_mint),
+            pool_fee_account: Pubkey::new_from_array(*pool_fee_account),
+            fees: Fees::unpack_from_slice(fees)?,
+            swap_curve: SwapCurve::unpack_from_slice(swap_curve)?,
+            admin: Pubkey::new_from_array(*admin),
+            is_paused: match is_paused {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            dynamic_fee: DynamicFee::unpack_from_slice(dynamic_fee)?,
+        })
+    }
+}
+
+/// State of a stable swap pool holding two or more tokens, see
+/// `MultiStableCurve`.  Tokens are referred to by their index in the pool.
+#[repr(C)]
+#[derive(Debug, Default, PartialEq)]
+pub struct MultiStableSwap {
+    /// Initialized state.
+    pub is_initialized: bool,
+    /// Bump seed used in program address.
+    /// The program address is created deterministically with the bump seed,
+    /// swap program id, and swap account pubkey.  This program address has
+    /// authority over all of the swap's token accounts, and pool token mint.
+    pub bump_seed: u8,
+
+    /// Program ID of the tokens being exchanged.
+    pub token_program_id: Pubkey,
+
+    /// Pool tokens are issued when tokens are deposited.
+    /// Pool tokens can be withdrawn back to the original tokens.
+    pub pool_mint: Pubkey,
+    /// Pool token account to receive trading and / or withdrawal fees
+    pub pool_fee_account: Pubkey,
+
+    /// All fee information
+    pub fees: Fees,
+    /// StableSwap invariant for all of the pool's tokens
+    pub curve: MultiStableCurve,
+
+    /// Number of tokens in the pool, only the first `num_tokens` entries of
+    /// `token_accounts` and `token_mints` are used
+    pub num_tokens: u8,
+    /// Token accounts holding the pool's liquidity, in pool order
+    pub token_accounts: [Pubkey; MAX_TOKENS],
+    /// Mints of the pool's tokens, in pool order
+    pub token_mints: [Pubkey; MAX_TOKENS],
+}
+
+impl MultiStableSwap {
+    /// Tag stored in the first byte of the account, following the tags of
+    /// `SwapVersion`, so that a multi-token swap can never be read as a
+    /// two-token swap or the other way around
+    pub const VERSION: u8 = 3;
+
+    /// Addresses of the token accounts holding the pool's liquidity
+    pub fn token_accounts(&self) -> &[Pubkey] {
+        &self.token_accounts[..self.num_tokens as usize]
+    }
+
+    /// Addresses of the mints of the pool's tokens
+    pub fn token_mints(&self) -> &[Pubkey] {
+        &self.token_mints[..self.num_tokens as usize]
+    }
+}
+
+impl Sealed for MultiStableSwap {}
+impl IsInitialized for MultiStableSwap {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Pack for MultiStableSwap {
+    const LEN: usize = 684;
+
+    fn pack_into_slice(&self, output: &mut [u8]) {
+        let output = array_mut_ref![output, 0, 684];
+        let (
+            version,
+            is_initialized,
+            bump_seed,
+            token_program_id,
+            pool_mint,
+            pool_fee_account,
+            fees,
+            amp,
+            num_tokens,
+            token_accounts,
+            token_mints,
+        ) = mut_array_refs![output, 1, 1, 1, 32, 32, 32, 64, 8, 1, 256, 256];
+        version[0] = Self::VERSION;
+        is_initialized[0] = self.is_initialized as u8;
+        bump_seed[0] = self.bump_seed;
+        token_program_id.copy_from_slice(self.token_program_id.as_ref());
+        pool_mint.copy_from_slice(self.pool_mint.as_ref());
+        pool_fee_account.copy_from_slice(self.pool_fee_account.as_ref());
+        self.fees.pack_into_slice(&mut fees[..]);
+        *amp = self.curve.amp.to_le_bytes();
+        num_tokens[0] = self.num_tokens;
+        for (dst, src) in token_accounts
+            .chunks_mut(32)
+            .zip(self.token_accounts.iter())
+        {
+            dst.copy_from_slice(src.as_ref());
+        }
+        for (dst, src) in token_mints.chunks_mut(32).zip(self.token_mints.iter()) {
+            dst.copy_from_slice(src.as_ref());
+        }
+    }
+
+    /// Unpacks a byte buffer into a [MultiStableSwap](struct.MultiStableSwap.html).
+    fn unpack_from_slice(input: &[u8]) -> Result<Self, ProgramError> {
+        let input = array_ref![input, 0, 684];
+        #[allow(clippy::ptr_offset_with_cast)]
+        let (
+            version,
+            is_initialized,
+            bump_seed,
+            token_program_id,
+            pool_mint,
+            pool_fee_account,
+            fees,
+            amp,
+            num_tokens,
+            token_accounts_data,
+            token_mints_data,
+        ) = array_refs![input, 1, 1, 1, 32, 32, 32, 64, 8, 1, 256, 256];
+        // only an account that was never initialized may be missing the tag
+        if version[0] != Self::VERSION && (version[0] != 0 || is_initialized[0] != 0) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if num_tokens[0] as usize > MAX_TOKENS {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let mut token_accounts = [Pubkey::default(); MAX_TOKENS];
+        for (dst, src) in token_accounts
+            .iter_mut()
+            .zip(token_accounts_data.chunks(32))
+        {
+            *dst = Pubkey::new(src);
+        }
+        let mut token_mints = [Pubkey::default(); MAX_TOKENS];
+        for (dst, src) in token_mints.iter_mut().zip(token_mints_data.chunks(32)) {
+            *dst = Pubkey::new(src);
+        }
+        Ok(Self {
+            is_initialized: match is_initialized {
+                [0] => false,
+                [1] => true,
+                _ => return Err(ProgramError::InvalidAccountData),
+            },
+            bump_seed: bump_seed[0],
+            token_program_id: Pubkey::new_from_array(*token_program_id),
+            pool_mint: Pubkey::new_from_array(*pool_mint),
+            pool_fee_account: Pubkey::new_from_array(*pool_fee_account),
+            fees: Fees::unpack_from_slice(fees)?,
+            curve: MultiStableCurve {
+                amp: u64::from_le_bytes(*amp),
+            },
+            num_tokens: num_tokens[0],
+            token_accounts,
+            token_mints,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -274,6 +671,15 @@ mod tests {
     const TEST_TOKEN_A_MINT: Pubkey = Pubkey::new_from_array([5u8; 32]);
     const TEST_TOKEN_B_MINT: Pubkey = Pubkey::new_from_array([6u8; 32]);
     const TEST_POOL_FEE_ACCOUNT: Pubkey = Pubkey::new_from_array([7u8; 32]);
+    const TEST_ADMIN: Pubkey = Pubkey::new_from_array([8u8; 32]);
+    const TEST_DYNAMIC_FEE: DynamicFee = DynamicFee {
+        min_trade_fee_numerator: 1,
+        max_trade_fee_numerator: 3,
+        max_volatility: 500,
+        volatility_half_life: 60,
+        volatility: 20,
+        last_update_timestamp: 1_000,
+    };
 
     const TEST_CURVE_TYPE: u8 = 2;
     const TEST_AMP: u64 = 1;
@@ -287,7 +693,7 @@ mod tests {
             curve_type,
             calculator,
         };
-        let swap_info = SwapVersion::SwapV1(SwapV1 {
+        let swap_info = SwapVersion::SwapV2(SwapV2 {
             is_initialized: true,
             bump_seed: TEST_BUMP_SEED,
             token_program_id: TEST_TOKEN_PROGRAM_ID,
@@ -299,6 +705,9 @@ mod tests {
             pool_fee_account: TEST_POOL_FEE_ACCOUNT,
             fees: TEST_FEES,
             swap_curve: swap_curve.clone(),
+            admin: TEST_ADMIN,
+            is_paused: true,
+            dynamic_fee: TEST_DYNAMIC_FEE,
         });
 
         let mut packed = [0u8; SwapVersion::LATEST_LEN];
@@ -316,6 +725,9 @@ mod tests {
         assert_eq!(*unpacked.pool_fee_account(), TEST_POOL_FEE_ACCOUNT);
         assert_eq!(*unpacked.fees(), TEST_FEES);
         assert_eq!(*unpacked.swap_curve(), swap_curve);
+        assert_eq!(unpacked.admin(), Some(&TEST_ADMIN));
+        assert!(unpacked.is_paused());
+        assert_eq!(unpacked.dynamic_fee(), Some(&TEST_DYNAMIC_FEE));
     }
 
     #[test]
@@ -374,4 +786,136 @@ mod tests {
         let err = SwapV1::unpack(&packed).unwrap_err();
         assert_eq!(err, ProgramError::UninitializedAccount);
     }
+
+    #[test]
+    fn swap_v2_pack() {
+        let curve_type = TEST_CURVE_TYPE.try_into().unwrap();
+        let calculator = Arc::new(TEST_CURVE);
+        let swap_curve = SwapCurve {
+            curve_type,
+            calculator,
+        };
+        let swap_info = SwapV2 {
+            is_initialized: true,
+            bump_seed: TEST_BUMP_SEED,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            token_a: TEST_TOKEN_A,
+            token_b: TEST_TOKEN_B,
+            pool_mint: TEST_POOL_MINT,
+            token_a_mint: TEST_TOKEN_A_MINT,
+            token_b_mint: TEST_TOKEN_B_MINT,
+            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+            fees: TEST_FEES,
+            swap_curve,
+            admin: TEST_ADMIN,
+            is_paused: false,
+            dynamic_fee: TEST_DYNAMIC_FEE,
+        };
+
+        let mut packed = [0u8; SwapV2::LEN];
+        SwapV2::pack_into_slice(&swap_info, &mut packed);
+        let unpacked = SwapV2::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+
+        let mut packed = [0u8; SwapV1::LEN];
+        SwapV1::pack_into_slice(
+            &SwapV1 {
+                is_initialized: true,
+                ..Default::default()
+            },
+            &mut packed,
+        );
+        let mut expect = packed.to_vec();
+        expect.extend_from_slice(&TEST_ADMIN.to_bytes());
+        expect.push(0);
+        expect.extend_from_slice(&[0u8; DynamicFee::LEN]);
+        let unpacked = SwapV2::unpack(&expect).unwrap();
+        assert_eq!(unpacked.admin(), Some(&TEST_ADMIN));
+        assert!(!unpacked.is_paused());
+        assert_eq!(unpacked.dynamic_fee(), None);
+
+        let swap_info = SwapV2 {
+            is_initialized: true,
+            ..Default::default()
+        };
+        assert_eq!(swap_info.admin(), None);
+
+        let packed = [0u8; SwapV2::LEN];
+        let err = SwapV2::unpack(&packed).unwrap_err();
+        assert_eq!(err, ProgramError::UninitializedAccount);
+    }
+
+    #[test]
+    fn multi_stable_swap_pack() {
+        let mut token_accounts = [Pubkey::default(); MAX_TOKENS];
+        let mut token_mints = [Pubkey::default(); MAX_TOKENS];
+        for (i, (token_account, token_mint)) in token_accounts
+            .iter_mut()
+            .zip(token_mints.iter_mut())
+            .take(3)
+            .enumerate()
+        {
+            *token_account = Pubkey::new_from_array([10 + i as u8; 32]);
+            *token_mint = Pubkey::new_from_array([20 + i as u8; 32]);
+        }
+        let swap_info = MultiStableSwap {
+            is_initialized: true,
+            bump_seed: TEST_BUMP_SEED,
+            token_program_id: TEST_TOKEN_PROGRAM_ID,
+            pool_mint: TEST_POOL_MINT,
+            pool_fee_account: TEST_POOL_FEE_ACCOUNT,
+            fees: TEST_FEES,
+            curve: MultiStableCurve { amp: TEST_AMP },
+            num_tokens: 3,
+            token_accounts,
+            token_mints,
+        };
+
+        let mut packed = [0u8; MultiStableSwap::LEN];
+        MultiStableSwap::pack_into_slice(&swap_info, &mut packed);
+        let unpacked = MultiStableSwap::unpack(&packed).unwrap();
+        assert_eq!(swap_info, unpacked);
+        assert_eq!(unpacked.token_accounts(), &token_accounts[..3]);
+        assert_eq!(unpacked.token_mints(), &token_mints[..3]);
+
+        let mut expect = vec![MultiStableSwap::VERSION, 1u8, TEST_BUMP_SEED];
+        expect.extend_from_slice(&TEST_TOKEN_PROGRAM_ID.to_bytes());
+        expect.extend_from_slice(&TEST_POOL_MINT.to_bytes());
+        expect.extend_from_slice(&TEST_POOL_FEE_ACCOUNT.to_bytes());
+        let mut fees = [0u8; Fees::LEN];
+        TEST_FEES.pack_into_slice(&mut fees);
+        expect.extend_from_slice(&fees);
+        expect.extend_from_slice(&TEST_AMP.to_le_bytes());
+        expect.push(3);
+        for token_account in token_accounts.iter() {
+            expect.extend_from_slice(&token_account.to_bytes());
+        }
+        for token_mint in token_mints.iter() {
+            expect.extend_from_slice(&token_mint.to_bytes());
+        }
+        assert_eq!(&packed[..], &expect[..]);
+
+        // not a multi-token swap
+        let mut wrong_version = packed;
+        wrong_version[0] = 2;
+        let err = MultiStableSwap::unpack(&wrong_version).unwrap_err();
+        assert_eq!(err, ProgramError::InvalidAccountData);
+        let mut missing_version = packed;
+        missing_version[0] = 0;
+        let err = MultiStableSwap::unpack(&missing_version).unwrap_err();
+        assert_eq!(err, ProgramError::InvalidAccountData);
+
+        // a multi-token swap is never read as a two-token swap
+        let err = SwapVersion::unpack(&packed).err().unwrap();
+        assert_eq!(err, ProgramError::UninitializedAccount);
+
+        // too many tokens
+        packed[171] = MAX_TOKENS as u8 + 1;
+        let err = MultiStableSwap::unpack(&packed).unwrap_err();
+        assert_eq!(err, ProgramError::InvalidAccountData);
+
+        let packed = [0u8; MultiStableSwap::LEN];
+        let err = MultiStableSwap::unpack(&packed).unwrap_err();
+        assert_eq!(err, ProgramError::UninitializedAccount);
+    }
 }
@@ -0,0 +1,44 @@
+//! Error types
+
+use core::fmt;
+
+/// Errors that may be returned when quoting a swap, mirroring the errors the
+/// token-swap program returns for the same instruction
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum QuoteError {
+    /// The swap state could not be unpacked, or uses an unsupported version
+    InvalidAccountData,
+    /// The swap curve type is not known
+    InvalidCurve,
+    /// The swap is paused by its admin
+    SwapPaused,
+    /// The curve does not support the operation
+    UnsupportedCurveOperation,
+    /// The operation would give or take zero tokens
+    ZeroTradingTokens,
+    /// General calculation failure due to overflow or underflow
+    CalculationFailure,
+    /// Fee calculation failed due to overflow, underflow, or unexpected 0
+    FeeCalculationFailure,
+    /// The result does not fit in a token amount
+    ConversionFailure,
+}
+
+impl fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::InvalidAccountData => "Invalid or unsupported swap account data",
+            Self::InvalidCurve => "The provided curve parameters are invalid",
+            Self::SwapPaused => "The swap is paused",
+            Self::UnsupportedCurveOperation => {
+                "The operation cannot be performed on the given curve"
+            }
+            Self::ZeroTradingTokens => "Given pool token amount results in zero trading tokens",
+            Self::CalculationFailure => "General calculation failure due to overflow or underflow",
+            Self::FeeCalculationFailure => {
+                "Fee calculation failed due to overflow, underflow, or unexpected 0"
+            }
+            Self::ConversionFailure => "Conversion to u64 failed with an overflow or underflow",
+        })
+    }
+}
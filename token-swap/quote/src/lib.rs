@@ -0,0 +1,839 @@
+//! Off-chain quotes for token swaps
+//!
+//! Quotes run the token-swap program's own `SwapCurve` and `Fees`, including
+//! their rounding, so that clients can predict the outcome of a swap,
+//! single-sided deposit, or single-sided withdrawal from the swap state and its
+//! token balances.  The program is used without its processor, so the crate
+//! does not depend on the token program or the program entrypoint.
+
+#![deny(missing_docs)]
+#![forbid(unsafe_code)]
+
+pub mod error;
+
+pub use {
+    error::QuoteError,
+    spl_token_swap::curve::{
+        base::SwapCurve,
+        calculator::TradeDirection,
+        fees::{DynamicFee, Fees},
+    },
+};
+
+use {
+    solana_program::program_pack::Pack,
+    spl_math::uint::U256,
+    spl_token_swap::{
+        solana_program,
+        state::{SwapV1, SwapV2},
+    },
+    std::convert::TryFrom,
+};
+
+/// Basis points in a whole, used for price impact
+pub const BASIS_POINTS: u64 = 10_000;
+
+/// Swap parameters needed to quote its instructions
+#[derive(Debug, PartialEq)]
+pub struct SwapPool {
+    /// Curve used by the swap
+    pub curve: SwapCurve,
+    /// Fees charged by the swap
+    pub fees: Fees,
+    /// Volatility-based trade fee, if enabled
+    pub dynamic_fee: Option<DynamicFee>,
+    /// Whether the admin paused the swap
+    pub is_paused: bool,
+}
+
+impl SwapPool {
+    /// Unpack the pool parameters from the data of a token-swap account,
+    /// including its version byte.  Only two-token swaps are supported.
+    pub fn unpack(input: &[u8]) -> Result<Self, QuoteError> {
+        let (version, rest) = input.split_first().ok_or(QuoteError::InvalidAccountData)?;
+        match version {
+            1 => {
+                let swap = SwapV1::unpack(rest).map_err(|_| QuoteError::InvalidAccountData)?;
+                Ok(Self {
+                    curve: swap.swap_curve,
+                    fees: swap.fees,
+                    dynamic_fee: None,
+                    is_paused: false,
+                })
+            }
+            2 => {
+                let swap = SwapV2::unpack(rest).map_err(|_| QuoteError::InvalidAccountData)?;
+                Ok(Self {
+                    curve: swap.swap_curve,
+                    fees: swap.fees,
+                    dynamic_fee: Some(swap.dynamic_fee).filter(DynamicFee::is_enabled),
+                    is_paused: swap.is_paused,
+                })
+            }
+            _ => Err(QuoteError::InvalidAccountData),
+        }
+    }
+
+    /// Fees charged by the swap at the given time, including the current
+    /// dynamic trade fee
+    pub fn current_fees(&self, unix_timestamp: i64) -> Result<Fees, QuoteError> {
+        match &self.dynamic_fee {
+            Some(dynamic_fee) => dynamic_fee
+                .current_fees(&self.fees, unix_timestamp)
+                .ok_or(QuoteError::FeeCalculationFailure),
+            None => Ok(self.fees.clone()),
+        }
+    }
+
+    fn check_not_paused(&self) -> Result<(), QuoteError> {
+        if self.is_paused {
+            Err(QuoteError::SwapPaused)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Token balances of a swap, as held by its token accounts and pool mint
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PoolBalances {
+    /// Amount of tokens in the swap's token A account
+    pub token_a_amount: u64,
+    /// Amount of tokens in the swap's token B account
+    pub token_b_amount: u64,
+    /// Supply of the pool token mint
+    pub pool_token_supply: u64,
+}
+
+impl PoolBalances {
+    /// Swap token amounts, ordered as (source, destination)
+    fn source_and_destination(&self, trade_direction: TradeDirection) -> (u128, u128) {
+        let a = u128::from(self.token_a_amount);
+        let b = u128::from(self.token_b_amount);
+        match trade_direction {
+            TradeDirection::AtoB => (a, b),
+            TradeDirection::BtoA => (b, a),
+        }
+    }
+}
+
+/// Outcome of a `Swap` instruction
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SwapQuote {
+    /// Amount of source tokens taken from the user, including fees
+    pub amount_in: u64,
+    /// Amount of destination tokens sent to the user
+    pub amount_out: u64,
+    /// Amount of source tokens kept by the pool as the trade fee
+    pub trade_fee: u64,
+    /// Amount of source tokens taken as the owner fee
+    pub owner_fee: u64,
+    /// Pool tokens minted to the pool fee account for the owner fee
+    pub owner_fee_pool_tokens: u64,
+    /// Pool tokens minted to the host fee account, if one is provided
+    pub host_fee_pool_tokens: u64,
+    /// Token A balance of the swap after the trade
+    pub new_token_a_amount: u64,
+    /// Token B balance of the swap after the trade
+    pub new_token_b_amount: u64,
+    /// Loss against the spot price of the pool, excluding fees, in basis
+    /// points
+    pub price_impact_bps: u64,
+}
+
+/// Outcome of a `DepositSingleTokenTypeExactAmountIn` instruction
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DepositSingleQuote {
+    /// Amount of pool tokens minted to the user
+    pub pool_token_amount: u64,
+    /// Amount of source tokens kept by the pool as the trade fee
+    pub trade_fee: u64,
+    /// Loss against the spot price of the pool, excluding fees, in basis
+    /// points
+    pub price_impact_bps: u64,
+}
+
+/// Outcome of a `WithdrawSingleTokenTypeExactAmountOut` instruction
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WithdrawSingleQuote {
+    /// Total amount of pool tokens taken from the user, including the owner
+    /// withdraw fee
+    pub pool_token_amount: u64,
+    /// Amount of pool tokens burned
+    pub burn_pool_token_amount: u64,
+    /// Amount of pool tokens sent to the pool fee account
+    pub owner_fee: u64,
+    /// Amount of destination tokens kept by the pool as the trade fee
+    pub trade_fee: u64,
+    /// Loss against the spot price of the pool, excluding fees, in basis
+    /// points
+    pub price_impact_bps: u64,
+}
+
+/// Quote a `Swap` of `amount_in` source tokens.  Set `with_host_fee` if a
+/// host fee account is passed to the instruction.
+pub fn quote_swap(
+    pool: &SwapPool,
+    balances: &PoolBalances,
+    amount_in: u64,
+    trade_direction: TradeDirection,
+    with_host_fee: bool,
+    unix_timestamp: i64,
+) -> Result<SwapQuote, QuoteError> {
+    pool.check_not_paused()?;
+    let fees = pool.current_fees(unix_timestamp)?;
+    let (swap_source_amount, swap_destination_amount) =
+        balances.source_and_destination(trade_direction);
+    let result = pool
+        .curve
+        .swap(
+            u128::from(amount_in),
+            swap_source_amount,
+            swap_destination_amount,
+            trade_direction,
+            &fees,
+        )
+        .ok_or(QuoteError::ZeroTradingTokens)?;
+
+    let (new_token_a_amount, new_token_b_amount) = match trade_direction {
+        TradeDirection::AtoB => (
+            result.new_swap_source_amount,
+            result.new_swap_destination_amount,
+        ),
+        TradeDirection::BtoA => (
+            result.new_swap_destination_amount,
+            result.new_swap_source_amount,
+        ),
+    };
+    let mut owner_fee_pool_tokens = pool
+        .curve
+        .withdraw_single_token_type_exact_out(
+            result.owner_fee,
+            new_token_a_amount,
+            new_token_b_amount,
+            u128::from(balances.pool_token_supply),
+            trade_direction,
+            &fees,
+        )
+        .ok_or(QuoteError::FeeCalculationFailure)?;
+    let mut host_fee_pool_tokens = 0;
+    if with_host_fee && owner_fee_pool_tokens > 0 {
+        host_fee_pool_tokens = fees
+            .host_fee(owner_fee_pool_tokens)
+            .ok_or(QuoteError::FeeCalculationFailure)?;
+        owner_fee_pool_tokens = owner_fee_pool_tokens
+            .checked_sub(host_fee_pool_tokens)
+            .ok_or(QuoteError::FeeCalculationFailure)?;
+    }
+
+    let total_fees = result
+        .trade_fee
+        .checked_add(result.owner_fee)
+        .ok_or(QuoteError::CalculationFailure)?;
+    let spot = pool.curve.calculator.swap_without_fees(
+        probe_amount(swap_source_amount),
+        swap_source_amount,
+        swap_destination_amount,
+        trade_direction,
+    );
+    let price_impact_bps = match spot {
+        Some(spot) => price_impact_bps(
+            spot.destination_amount_swapped,
+            spot.source_amount_swapped,
+            result.destination_amount_swapped,
+            result.source_amount_swapped.saturating_sub(total_fees),
+        ),
+        None => 0,
+    };
+
+    Ok(SwapQuote {
+        amount_in: to_u64(result.source_amount_swapped)?,
+        amount_out: to_u64(result.destination_amount_swapped)?,
+        trade_fee: to_u64(result.trade_fee)?,
+        owner_fee: to_u64(result.owner_fee)?,
+        owner_fee_pool_tokens: to_u64(owner_fee_pool_tokens)?,
+        host_fee_pool_tokens: to_u64(host_fee_pool_tokens)?,
+        new_token_a_amount: to_u64(new_token_a_amount)?,
+        new_token_b_amount: to_u64(new_token_b_amount)?,
+        price_impact_bps,
+    })
+}
+
+/// Quote a `DepositSingleTokenTypeExactAmountIn` of `source_token_amount`
+/// tokens.  `TradeDirection::AtoB` deposits token A, and
+/// `TradeDirection::BtoA` deposits token B.
+pub fn quote_deposit_single_token_type(
+    pool: &SwapPool,
+    balances: &PoolBalances,
+    source_token_amount: u64,
+    trade_direction: TradeDirection,
+    unix_timestamp: i64,
+) -> Result<DepositSingleQuote, QuoteError> {
+    pool.check_not_paused()?;
+    if !pool.curve.calculator.allows_deposits() {
+        return Err(QuoteError::UnsupportedCurveOperation);
+    }
+    let pool_token_supply = u128::from(balances.pool_token_supply);
+    if pool_token_supply == 0 {
+        return Ok(DepositSingleQuote {
+            pool_token_amount: to_u64(pool.curve.calculator.new_pool_supply())?,
+            ..DepositSingleQuote::default()
+        });
+    }
+
+    let fees = pool.current_fees(unix_timestamp)?;
+    let source_token_amount = u128::from(source_token_amount);
+    let token_a_amount = u128::from(balances.token_a_amount);
+    let token_b_amount = u128::from(balances.token_b_amount);
+    let pool_token_amount = pool
+        .curve
+        .deposit_single_token_type(
+            source_token_amount,
+            token_a_amount,
+            token_b_amount,
+            pool_token_supply,
+            trade_direction,
+            &fees,
+        )
+        .ok_or(QuoteError::ZeroTradingTokens)?;
+    if pool_token_amount == 0 {
+        return Err(QuoteError::ZeroTradingTokens);
+    }
+    let trade_fee = fees
+        .single_token_trading_fee(source_token_amount)
+        .ok_or(QuoteError::FeeCalculationFailure)?;
+
+    let (swap_source_amount, _) = balances.source_and_destination(trade_direction);
+    let spot_probe = probe_amount(swap_source_amount);
+    let price_impact_bps = match pool.curve.calculator.deposit_single_token_type(
+        spot_probe,
+        token_a_amount,
+        token_b_amount,
+        pool_token_supply,
+        trade_direction,
+    ) {
+        Some(spot) => price_impact_bps(
+            spot,
+            spot_probe,
+            pool_token_amount,
+            source_token_amount.saturating_sub(trade_fee),
+        ),
+        None => 0,
+    };
+
+    Ok(DepositSingleQuote {
+        pool_token_amount: to_u64(pool_token_amount)?,
+        trade_fee: to_u64(trade_fee)?,
+        price_impact_bps,
+    })
+}
+
+/// Quote a `WithdrawSingleTokenTypeExactAmountOut` of `destination_token_amount`
+/// tokens.  `TradeDirection::AtoB` withdraws token A, and
+/// `TradeDirection::BtoA` withdraws token B.  Set `from_pool_fee_account` if
+/// the pool tokens come from the pool fee account, which does not pay the
+/// owner withdraw fee.
+pub fn quote_withdraw_single_token_type_exact_out(
+    pool: &SwapPool,
+    balances: &PoolBalances,
+    destination_token_amount: u64,
+    trade_direction: TradeDirection,
+    from_pool_fee_account: bool,
+    unix_timestamp: i64,
+) -> Result<WithdrawSingleQuote, QuoteError> {
+    pool.check_not_paused()?;
+    let fees = pool.current_fees(unix_timestamp)?;
+    let destination_token_amount = u128::from(destination_token_amount);
+    let token_a_amount = u128::from(balances.token_a_amount);
+    let token_b_amount = u128::from(balances.token_b_amount);
+    let pool_token_supply = u128::from(balances.pool_token_supply);
+    let burn_pool_token_amount = pool
+        .curve
+        .withdraw_single_token_type_exact_out(
+            destination_token_amount,
+            token_a_amount,
+            token_b_amount,
+            pool_token_supply,
+            trade_direction,
+            &fees,
+        )
+        .ok_or(QuoteError::ZeroTradingTokens)?;
+    let owner_fee = if from_pool_fee_account {
+        0
+    } else {
+        fees.owner_withdraw_fee(burn_pool_token_amount)
+            .ok_or(QuoteError::FeeCalculationFailure)?
+    };
+    let pool_token_amount = burn_pool_token_amount
+        .checked_add(owner_fee)
+        .ok_or(QuoteError::CalculationFailure)?;
+    if pool_token_amount == 0 {
+        return Err(QuoteError::ZeroTradingTokens);
+    }
+    let trade_fee = fees
+        .single_token_trading_fee(destination_token_amount)
+        .ok_or(QuoteError::FeeCalculationFailure)?;
+
+    let (swap_destination_amount, _) = balances.source_and_destination(trade_direction);
+    let spot_probe = probe_amount(swap_destination_amount);
+    let price_impact_bps = match pool.curve.calculator.withdraw_single_token_type_exact_out(
+        spot_probe,
+        token_a_amount,
+        token_b_amount,
+        pool_token_supply,
+        trade_direction,
+    ) {
+        Some(spot) => price_impact_bps(
+            spot_probe,
+            spot,
+            destination_token_amount.saturating_sub(trade_fee),
+            burn_pool_token_amount,
+        ),
+        None => 0,
+    };
+
+    Ok(WithdrawSingleQuote {
+        pool_token_amount: to_u64(pool_token_amount)?,
+        burn_pool_token_amount: to_u64(burn_pool_token_amount)?,
+        owner_fee: to_u64(owner_fee)?,
+        trade_fee: to_u64(trade_fee)?,
+        price_impact_bps,
+    })
+}
+
+/// Small amount used to approximate the spot price of the pool
+fn probe_amount(swap_amount: u128) -> u128 {
+    core::cmp::max(1, swap_amount / 1_000_000)
+}
+
+/// Relative loss of the execution rate `amount_out / amount_in` against the
+/// spot rate `spot_out / spot_in`, in basis points, between 0 and 10_000
+fn price_impact_bps(spot_out: u128, spot_in: u128, amount_out: u128, amount_in: u128) -> u64 {
+    let spot = U256::from(spot_out) * U256::from(amount_in);
+    if spot.is_zero() {
+        return 0;
+    }
+    // both products are below 2^256, and the quotient is below 2^64 when
+    // the execution rate is no better than the spot rate
+    let execution = U256::from(amount_out) * U256::from(spot_in) * U256::from(BASIS_POINTS);
+    let execution_bps = execution / spot;
+    if execution_bps >= U256::from(BASIS_POINTS) {
+        0
+    } else {
+        BASIS_POINTS - execution_bps.as_u64()
+    }
+}
+
+fn to_u64(val: u128) -> Result<u64, QuoteError> {
+    u64::try_from(val).map_err(|_| QuoteError::ConversionFailure)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_program::pubkey::Pubkey;
+    use spl_token_swap::{
+        curve::{
+            base::CurveType, calculator::INITIAL_SWAP_POOL_AMOUNT,
+            constant_price::ConstantPriceCurve, constant_product::ConstantProductCurve,
+            offset::OffsetCurve, stable::StableCurve,
+        },
+        state::SwapVersion,
+    };
+    use std::sync::Arc;
+
+    const BALANCES: PoolBalances = PoolBalances {
+        token_a_amount: 1_000_000_000,
+        token_b_amount: 4_000_000_000,
+        pool_token_supply: 2_000_000_000,
+    };
+
+    const FEES: Fees = Fees {
+        trade_fee_numerator: 25,
+        trade_fee_denominator: 10_000,
+        owner_trade_fee_numerator: 5,
+        owner_trade_fee_denominator: 10_000,
+        owner_withdraw_fee_numerator: 1,
+        owner_withdraw_fee_denominator: 100,
+        host_fee_numerator: 20,
+        host_fee_denominator: 100,
+    };
+
+    fn constant_product_curve() -> SwapCurve {
+        SwapCurve {
+            curve_type: CurveType::ConstantProduct,
+            calculator: Arc::new(ConstantProductCurve {}),
+        }
+    }
+
+    fn test_pool() -> SwapPool {
+        SwapPool {
+            curve: constant_product_curve(),
+            fees: FEES,
+            dynamic_fee: None,
+            is_paused: false,
+        }
+    }
+
+    #[test]
+    fn unpack_swap_accounts() {
+        let swap_v1 = SwapV1 {
+            is_initialized: true,
+            bump_seed: 255,
+            token_program_id: Pubkey::new_unique(),
+            pool_fee_account: Pubkey::new_unique(),
+            fees: FEES,
+            swap_curve: SwapCurve {
+                curve_type: CurveType::Stable,
+                calculator: Arc::new(StableCurve { amp: 85 }),
+            },
+            ..SwapV1::default()
+        };
+        let mut packed = [0u8; SwapVersion::V1_LEN];
+        SwapVersion::pack(SwapVersion::SwapV1(swap_v1), &mut packed).unwrap();
+        assert_eq!(
+            SwapPool::unpack(&packed),
+            Ok(SwapPool {
+                curve: SwapCurve {
+                    curve_type: CurveType::Stable,
+                    calculator: Arc::new(StableCurve { amp: 85 }),
+                },
+                ..test_pool()
+            })
+        );
+        assert_eq!(
+            SwapPool::unpack(&packed[..SwapVersion::V1_LEN - 1]),
+            Err(QuoteError::InvalidAccountData)
+        );
+
+        let dynamic_fee = DynamicFee {
+            min_trade_fee_numerator: 25,
+            max_trade_fee_numerator: 125,
+            max_volatility: 100,
+            volatility_half_life: 60,
+            volatility: 50,
+            last_update_timestamp: 1_000,
+        };
+        let swap_v2 = SwapV2 {
+            is_initialized: true,
+            fees: FEES,
+            swap_curve: constant_product_curve(),
+            admin: Pubkey::new_unique(),
+            is_paused: true,
+            dynamic_fee,
+            ..SwapV2::default()
+        };
+        let mut packed = [0u8; SwapVersion::LATEST_LEN];
+        SwapVersion::pack(SwapVersion::SwapV2(swap_v2), &mut packed).unwrap();
+        let pool = SwapPool::unpack(&packed).unwrap();
+        assert!(pool.is_paused);
+        assert_eq!(pool.fees, FEES);
+        assert_eq!(
+            pool.dynamic_fee,
+            Some(DynamicFee {
+                min_trade_fee_numerator: 25,
+                max_trade_fee_numerator: 125,
+                max_volatility: 100,
+                volatility_half_life: 60,
+                volatility: 50,
+                last_update_timestamp: 1_000,
+            })
+        );
+
+        let uninitialized = [2u8; SwapVersion::LATEST_LEN];
+        assert_eq!(
+            SwapPool::unpack(&uninitialized),
+            Err(QuoteError::InvalidAccountData)
+        );
+        let mut multi_token = [0u8; SwapVersion::LATEST_LEN];
+        multi_token[0] = 3;
+        multi_token[1] = 1;
+        assert_eq!(
+            SwapPool::unpack(&multi_token),
+            Err(QuoteError::InvalidAccountData)
+        );
+    }
+
+    #[test]
+    fn swap_quote_matches_program() {
+        let pool = test_pool();
+        let amount_in = 10_000_000;
+        let quote =
+            quote_swap(&pool, &BALANCES, amount_in, TradeDirection::AtoB, false, 0).unwrap();
+        let result = constant_product_curve()
+            .swap(
+                u128::from(amount_in),
+                u128::from(BALANCES.token_a_amount),
+                u128::from(BALANCES.token_b_amount),
+                TradeDirection::AtoB,
+                &FEES,
+            )
+            .unwrap();
+        assert_eq!(u128::from(quote.amount_in), result.source_amount_swapped);
+        assert_eq!(
+            u128::from(quote.amount_out),
+            result.destination_amount_swapped
+        );
+        assert_eq!(u128::from(quote.trade_fee), result.trade_fee);
+        assert_eq!(u128::from(quote.owner_fee), result.owner_fee);
+        assert_eq!(
+            u128::from(quote.new_token_a_amount),
+            result.new_swap_source_amount
+        );
+        assert_eq!(
+            u128::from(quote.new_token_b_amount),
+            result.new_swap_destination_amount
+        );
+        let owner_fee_pool_tokens = constant_product_curve()
+            .withdraw_single_token_type_exact_out(
+                result.owner_fee,
+                result.new_swap_source_amount,
+                result.new_swap_destination_amount,
+                u128::from(BALANCES.pool_token_supply),
+                TradeDirection::AtoB,
+                &FEES,
+            )
+            .unwrap();
+        assert_eq!(
+            u128::from(quote.owner_fee_pool_tokens),
+            owner_fee_pool_tokens
+        );
+        assert_eq!(quote.host_fee_pool_tokens, 0);
+        // 1% of the pool moves the price by about 1%
+        assert!(quote.price_impact_bps >= 90 && quote.price_impact_bps <= 110);
+
+        let host_quote =
+            quote_swap(&pool, &BALANCES, amount_in, TradeDirection::AtoB, true, 0).unwrap();
+        assert_eq!(
+            host_quote.host_fee_pool_tokens,
+            quote.owner_fee_pool_tokens * 20 / 100
+        );
+        assert_eq!(
+            host_quote.owner_fee_pool_tokens + host_quote.host_fee_pool_tokens,
+            quote.owner_fee_pool_tokens
+        );
+        assert_eq!(host_quote.amount_out, quote.amount_out);
+    }
+
+    #[test]
+    fn swap_quote_price_impact() {
+        let pool = test_pool();
+        let small = quote_swap(&pool, &BALANCES, 1_000, TradeDirection::BtoA, false, 0).unwrap();
+        let large = quote_swap(
+            &pool,
+            &BALANCES,
+            1_000_000_000,
+            TradeDirection::BtoA,
+            false,
+            0,
+        )
+        .unwrap();
+        assert!(small.price_impact_bps < 10);
+        assert!(large.price_impact_bps > 1_000);
+
+        let pool = SwapPool {
+            curve: SwapCurve {
+                curve_type: CurveType::ConstantPrice,
+                calculator: Arc::new(ConstantPriceCurve { token_b_price: 4 }),
+            },
+            ..test_pool()
+        };
+        let quote = quote_swap(
+            &pool,
+            &BALANCES,
+            400_000_000,
+            TradeDirection::AtoB,
+            false,
+            0,
+        )
+        .unwrap();
+        assert_eq!(quote.price_impact_bps, 0);
+    }
+
+    #[test]
+    fn price_impact_limits() {
+        assert_eq!(price_impact_bps(2, 1, 2, 1), 0);
+        assert_eq!(price_impact_bps(2, 1, 1, 1), 5_000);
+        assert_eq!(price_impact_bps(2, 1, 3, 1), 0);
+        assert_eq!(price_impact_bps(2, 1, 0, 1), BASIS_POINTS);
+        assert_eq!(price_impact_bps(0, 1, 1, 1), 0);
+        assert_eq!(price_impact_bps(2, 1, 1, 0), 0);
+        assert_eq!(
+            price_impact_bps(u128::MAX, u128::MAX, u64::MAX.into(), u64::MAX.into()),
+            0
+        );
+    }
+
+    #[test]
+    fn swap_quote_dynamic_fee() {
+        let dynamic_pool = SwapPool {
+            dynamic_fee: Some(DynamicFee {
+                min_trade_fee_numerator: 25,
+                max_trade_fee_numerator: 125,
+                max_volatility: 100,
+                volatility_half_life: 60,
+                volatility: 100,
+                last_update_timestamp: 1_000,
+            }),
+            ..test_pool()
+        };
+        let amount_in = 10_000_000;
+        let calm = quote_swap(
+            &test_pool(),
+            &BALANCES,
+            amount_in,
+            TradeDirection::AtoB,
+            false,
+            1_000,
+        )
+        .unwrap();
+        let volatile = quote_swap(
+            &dynamic_pool,
+            &BALANCES,
+            amount_in,
+            TradeDirection::AtoB,
+            false,
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(volatile.trade_fee, calm.trade_fee * 5);
+        assert!(volatile.amount_out < calm.amount_out);
+
+        // long after the last move, the volatility has decayed away
+        let later = quote_swap(
+            &dynamic_pool,
+            &BALANCES,
+            amount_in,
+            TradeDirection::AtoB,
+            false,
+            1_000 + 60 * 64,
+        )
+        .unwrap();
+        assert_eq!(later, calm);
+    }
+
+    #[test]
+    fn paused_swap_quote() {
+        let pool = SwapPool {
+            is_paused: true,
+            ..test_pool()
+        };
+        assert_eq!(
+            quote_swap(&pool, &BALANCES, 1_000, TradeDirection::AtoB, false, 0),
+            Err(QuoteError::SwapPaused)
+        );
+        assert_eq!(
+            quote_deposit_single_token_type(&pool, &BALANCES, 1_000, TradeDirection::AtoB, 0),
+            Err(QuoteError::SwapPaused)
+        );
+        assert_eq!(
+            quote_withdraw_single_token_type_exact_out(
+                &pool,
+                &BALANCES,
+                1_000,
+                TradeDirection::AtoB,
+                false,
+                0
+            ),
+            Err(QuoteError::SwapPaused)
+        );
+    }
+
+    #[test]
+    fn deposit_single_quote() {
+        let pool = test_pool();
+        let amount = 10_000_000;
+        let quote =
+            quote_deposit_single_token_type(&pool, &BALANCES, amount, TradeDirection::AtoB, 0)
+                .unwrap();
+        let expected = constant_product_curve()
+            .deposit_single_token_type(
+                u128::from(amount),
+                u128::from(BALANCES.token_a_amount),
+                u128::from(BALANCES.token_b_amount),
+                u128::from(BALANCES.pool_token_supply),
+                TradeDirection::AtoB,
+                &FEES,
+            )
+            .unwrap();
+        assert_eq!(u128::from(quote.pool_token_amount), expected);
+        assert_eq!(quote.trade_fee, amount / 2 * 25 / 10_000);
+        assert!(quote.price_impact_bps > 0 && quote.price_impact_bps < 100);
+
+        let empty = PoolBalances {
+            pool_token_supply: 0,
+            ..BALANCES
+        };
+        let quote = quote_deposit_single_token_type(&pool, &empty, amount, TradeDirection::BtoA, 0)
+            .unwrap();
+        assert_eq!(
+            u128::from(quote.pool_token_amount),
+            INITIAL_SWAP_POOL_AMOUNT
+        );
+
+        assert_eq!(
+            quote_deposit_single_token_type(&pool, &BALANCES, 0, TradeDirection::AtoB, 0),
+            Err(QuoteError::ZeroTradingTokens)
+        );
+        let offset_pool = SwapPool {
+            curve: SwapCurve {
+                curve_type: CurveType::Offset,
+                calculator: Arc::new(OffsetCurve {
+                    token_b_offset: 1_000,
+                }),
+            },
+            ..test_pool()
+        };
+        assert_eq!(
+            quote_deposit_single_token_type(&offset_pool, &BALANCES, 1, TradeDirection::AtoB, 0),
+            Err(QuoteError::UnsupportedCurveOperation)
+        );
+    }
+
+    #[test]
+    fn withdraw_single_quote() {
+        let pool = test_pool();
+        let amount = 10_000_000;
+        let quote = quote_withdraw_single_token_type_exact_out(
+            &pool,
+            &BALANCES,
+            amount,
+            TradeDirection::BtoA,
+            false,
+            0,
+        )
+        .unwrap();
+        let expected = constant_product_curve()
+            .withdraw_single_token_type_exact_out(
+                u128::from(amount),
+                u128::from(BALANCES.token_a_amount),
+                u128::from(BALANCES.token_b_amount),
+                u128::from(BALANCES.pool_token_supply),
+                TradeDirection::BtoA,
+                &FEES,
+            )
+            .unwrap();
+        assert_eq!(u128::from(quote.burn_pool_token_amount), expected);
+        assert_eq!(quote.owner_fee, quote.burn_pool_token_amount / 100);
+        assert_eq!(
+            quote.pool_token_amount,
+            quote.burn_pool_token_amount + quote.owner_fee
+        );
+        assert!(quote.price_impact_bps > 0 && quote.price_impact_bps < 100);
+
+        let fee_account_quote = quote_withdraw_single_token_type_exact_out(
+            &pool,
+            &BALANCES,
+            amount,
+            TradeDirection::BtoA,
+            true,
+            0,
+        )
+        .unwrap();
+        assert_eq!(fee_account_quote.owner_fee, 0);
+        assert_eq!(
+            fee_account_quote.pool_token_amount,
+            quote.burn_pool_token_amount
+        );
+    }
+}